@@ -0,0 +1,47 @@
+use binary_decision_diagrams::formats::compressed;
+use binary_decision_diagrams::v4::core::Bdd;
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::convert::TryFrom;
+
+/// Compares the compressed binary format with the raw text format on the `itgr` inputs:
+/// prints the compression ratio of every file and measures the load time of both formats.
+pub fn criterion_benchmark(c: &mut Criterion) {
+    let mut benchmarks = Vec::new();
+    for file in std::fs::read_dir("./bench_inputs/itgr").unwrap() {
+        let path = file.unwrap().path();
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+        if file_name.ends_with(".bdd") {
+            benchmarks.push(file_name.to_string());
+        }
+    }
+    benchmarks.sort();
+
+    let mut group = c.benchmark_group("formats");
+    group.sample_size(10);
+    for benchmark in &benchmarks {
+        let text = std::fs::read_to_string(format!("./bench_inputs/itgr/{}", benchmark)).unwrap();
+        let bdd = Bdd::try_from(text.as_str()).unwrap().sort_preorder();
+        let text = bdd.to_string();
+        let bytes = compressed::encode(&bdd);
+        println!(
+            "{}: {} nodes, text {} B, compressed {} B ({:.2} B/node, ratio {:.2})",
+            benchmark,
+            bdd.get_size(),
+            text.len(),
+            bytes.len(),
+            (bytes.len() as f64) / (bdd.get_size() as f64),
+            (text.len() as f64) / (bytes.len() as f64),
+        );
+
+        group.bench_function(format!("{}/text", benchmark), |b| {
+            b.iter(|| Bdd::try_from(text.as_str()).unwrap().get_size())
+        });
+        group.bench_function(format!("{}/compressed", benchmark), |b| {
+            b.iter(|| compressed::decode(&bytes).unwrap().get_size())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -2,6 +2,13 @@
 
 //use binary_decision_diagrams::v2::bench_fun::deps::{Bdd, NodeId};
 use binary_decision_diagrams::v3::core::bdd::Bdd;
+use binary_decision_diagrams::v2::ops::Or;
+use binary_decision_diagrams::perf_testing::bdd::Bdd as PerfBdd;
+use binary_decision_diagrams::perf_testing::apply::apply_with;
+use binary_decision_diagrams::perf_testing::best_first_apply::{
+    best_first_apply_with, ByLeftNode, ByLevel,
+};
+use binary_decision_diagrams::perf_testing::bdd_dfs::{Indexed, Pointer};
 use criterion::{criterion_group, criterion_main, Criterion};
 use std::convert::TryFrom;
 //use binary_decision_diagrams::_bdd_u32::_impl_task_bench::{gen_tasks, TaskCache, UnrolledStack};
@@ -50,7 +57,7 @@ pub fn criterion_benchmark(c: &mut Criterion<Perf>) {
         let left = left.sort_preorder();
         let right_path = format!("./bench_inputs/reach/{}.or.right.bdd", benchmark);
         let mut right =
-            Bdd::try_from(std::fs::read_to_string(right_path).unwrap().as_str()).unwrap();
+            Bdd::try_from(std::fs::read_to_string(&right_path).unwrap().as_str()).unwrap();
         println!("Right ready: {}", right.node_count());
         let right = right.sort_preorder();
 
@@ -96,7 +103,7 @@ pub fn criterion_benchmark(c: &mut Criterion<Perf>) {
                 //unsafe { Cudd_bddOr(cudd, dd_left, dd_right) }
                 //left.or(&right)
                 //left.or(&right)
-                binary_decision_diagrams::v3::core::ooo::apply(&left, &right).node_count()
+                binary_decision_diagrams::v3::core::ooo::apply::<Or>(&left, &right).node_count()
                 //apply(&left, &right).node_count()
                 //optimized_coupled_dfs(&left, &right)
                 //explore(&left)
@@ -105,6 +112,25 @@ pub fn criterion_benchmark(c: &mut Criterion<Perf>) {
         });
 
         //unsafe { Cudd_Quit(cudd); }
+
+        // Compare the stack strategies on the same (sorted) inputs.
+        let left = PerfBdd::try_from(std::fs::read_to_string(&left_path).unwrap().as_str()).unwrap();
+        let left = left.sort_preorder();
+        let right = PerfBdd::try_from(std::fs::read_to_string(&right_path).unwrap().as_str()).unwrap();
+        let right = right.sort_preorder();
+        group.bench_function(format!("{}/indexed_stack", benchmark), |b| {
+            b.iter(|| apply_with::<Indexed>(&left, &right))
+        });
+        group.bench_function(format!("{}/pointer_stack", benchmark), |b| {
+            b.iter(|| apply_with::<Pointer>(&left, &right))
+        });
+        // Compare the DFS order with the best-first task orders.
+        group.bench_function(format!("{}/best_first_level", benchmark), |b| {
+            b.iter(|| best_first_apply_with::<ByLevel>(&left, &right))
+        });
+        group.bench_function(format!("{}/best_first_left_node", benchmark), |b| {
+            b.iter(|| best_first_apply_with::<ByLeftNode>(&left, &right))
+        });
     }
     group.finish();
 }
@@ -0,0 +1,58 @@
+use binary_decision_diagrams::testing::{ensure_corpus, read_corpus_file};
+use binary_decision_diagrams::v2::{Bdd, NodeId, SplitBdd};
+use binary_decision_diagrams::v4::core::Bdd as Bdd4;
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::convert::TryFrom;
+
+/// The lookup table of the `or` operation.
+fn or(l: NodeId, r: NodeId) -> NodeId {
+    if l.is_one() || r.is_one() {
+        NodeId::ONE
+    } else if l.is_zero() && r.is_zero() {
+        NodeId::ZERO
+    } else {
+        NodeId::UNDEFINED
+    }
+}
+
+/// Compares the apply algorithm on operands stored as a `Bdd` (16-byte nodes) with
+/// operands stored as a `SplitBdd` (8-byte link pairs and a separate variable array)
+/// on the `reach` inputs.
+pub fn criterion_benchmark(c: &mut Criterion) {
+    let mut benchmarks = Vec::new();
+    let corpus = ensure_corpus().unwrap();
+    for file in std::fs::read_dir(corpus.join("reach")).unwrap() {
+        let path = file.unwrap().path();
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+        if let Some(name) = file_name.strip_suffix(".or.left.bdd") {
+            benchmarks.push(name.to_string());
+        }
+    }
+    benchmarks.sort();
+
+    let read = |path: String| {
+        let text = read_corpus_file(&path).unwrap();
+        let bdd = Bdd4::try_from(text.as_str()).unwrap();
+        Bdd::try_from(&bdd).unwrap()
+    };
+
+    let mut group = c.benchmark_group("storage");
+    group.sample_size(10);
+    for benchmark in &benchmarks {
+        let left = read(format!("reach/{}.or.left.bdd", benchmark));
+        let right = read(format!("reach/{}.or.right.bdd", benchmark));
+        let split_left = SplitBdd::try_from(&left).unwrap();
+        let split_right = SplitBdd::try_from(&right).unwrap();
+
+        group.bench_function(format!("{}/nodes", benchmark), |b| {
+            b.iter(|| Bdd::mixed_binary_operation(&left, &right, or).node_count())
+        });
+        group.bench_function(format!("{}/split", benchmark), |b| {
+            b.iter(|| Bdd::mixed_binary_operation(&split_left, &split_right, or).node_count())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
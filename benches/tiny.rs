@@ -0,0 +1,40 @@
+use binary_decision_diagrams::v2::{ApplyKernel, ApplyOptions, Bdd, VariableId};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+/// The number of operations in one iteration of the benchmark.
+const OPERATIONS: usize = 1_000_000;
+
+/// Compares the latency of the `U16` kernel with the `U32` kernel on `10^6` conjunctions
+/// of tiny `Bdds` (three-literal clauses), which is typical for constraint builders.
+pub fn criterion_benchmark(c: &mut Criterion) {
+    let x: Vec<Bdd> = (0..16u16)
+        .map(|v| Bdd::new_variable(VariableId::from(v)))
+        .collect();
+    let clauses: Vec<Bdd> = (0..16)
+        .map(|i| x[i].or(&x[(i + 3) % 16].and_not(&x[(i + 7) % 16])))
+        .collect();
+
+    let mut group = c.benchmark_group("tiny");
+    group.sample_size(10);
+    for kernel in [ApplyKernel::U16, ApplyKernel::U32] {
+        let options = ApplyOptions {
+            kernel: Some(kernel),
+            ..ApplyOptions::default()
+        };
+        group.bench_function(format!("{:?}", kernel), |b| {
+            b.iter(|| {
+                let mut nodes = 0;
+                for i in 0..OPERATIONS {
+                    let left = &clauses[i % 16];
+                    let right = &clauses[(i / 16) % 16];
+                    nodes += left.and_with(right, &options).node_count();
+                }
+                nodes
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
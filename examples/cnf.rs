@@ -0,0 +1,87 @@
+//! Compile a formula in conjunctive normal form (given in the DIMACS format) into a `Bdd`.
+//!
+//! Run with `cargo run --example cnf`.
+
+use binary_decision_diagrams::v2::{Bdd, VariableId};
+
+/// Exactly one of `x1`, `x2` and `x3` holds, which (together with the other clauses) forces `x4`.
+const DIMACS: &str = "
+c A small satisfiable instance.
+p cnf 4 8
+-1 -2 0
+-1 -3 0
+-2 -3 0
+1 2 4 0
+1 3 4 0
+2 3 4 0
+1 2 3 0
+-4 1 2 3 0
+";
+
+/// Parse the clauses of a DIMACS file. Variables are numbered from one, negative numbers
+/// are negated literals.
+fn parse_dimacs(data: &str) -> Result<(u16, Vec<Vec<i32>>), String> {
+    let mut variables = 0;
+    let mut clauses = Vec::new();
+    let mut clause = Vec::new();
+    for line in data.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('c') {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix("p cnf") {
+            let count = header.split_whitespace().next().unwrap_or("");
+            variables = count
+                .parse::<u16>()
+                .map_err(|_| format!("Invalid variable count `{}`.", count))?;
+            continue;
+        }
+        for literal in line.split_whitespace() {
+            let literal = literal
+                .parse::<i32>()
+                .map_err(|_| format!("Invalid literal `{}`.", literal))?;
+            if literal == 0 {
+                clauses.push(std::mem::take(&mut clause));
+            } else if literal.unsigned_abs() > u32::from(variables) {
+                return Err(format!("Literal {} is out of range.", literal));
+            } else {
+                clause.push(literal);
+            }
+        }
+    }
+    Ok((variables, clauses))
+}
+
+fn main() -> Result<(), String> {
+    let (variables, clauses) = parse_dimacs(DIMACS)?;
+    let ff = Bdd::new_false();
+    let tt = ff.imp(&ff);
+    let literal = |literal: i32| {
+        let variable = Bdd::new_variable(VariableId::from((literal.unsigned_abs() - 1) as u16));
+        if literal > 0 {
+            variable
+        } else {
+            variable.imp(&ff)
+        }
+    };
+
+    // Every clause is a disjunction of literals, the formula is their conjunction.
+    let mut formula = tt.clone();
+    for clause in &clauses {
+        let clause_bdd = clause
+            .iter()
+            .fold(ff.clone(), |result, l| result.or(&literal(*l)));
+        formula = formula.and(&clause_bdd);
+    }
+
+    println!(
+        "{} variables, {} clauses: {} nodes, satisfiable: {}",
+        variables,
+        clauses.len(),
+        formula.node_count(),
+        !formula.semantic_eq(&ff)
+    );
+    // The instance forces `x4` and exactly one of `x1..x3`.
+    let x4 = literal(4);
+    assert!(formula.imp(&x4).semantic_eq(&tt));
+    Ok(())
+}
@@ -0,0 +1,78 @@
+//! Count the satisfying assignments of a `Bdd` (e.g. the number of solutions of a puzzle).
+//!
+//! The library has no built-in counting yet, so the example shows how to implement it
+//! on top of the public node representation (`Bdd::node_slice`).
+//!
+//! Run with `cargo run --example counting`.
+
+use binary_decision_diagrams::v2::{Bdd, VariableId};
+
+/// The number of satisfying assignments of `bdd` over `variable_count` variables.
+///
+/// Children always precede their parents in the node list, so a single pass from the
+/// terminals up to the root (the last node) is enough. The count of a node only covers
+/// the variables at or below its own variable, skipped variables double the count.
+fn count_solutions(bdd: &Bdd, variable_count: u16) -> u128 {
+    let nodes = bdd.node_slice();
+    let level = |index: usize| {
+        if index < 2 {
+            u32::from(variable_count)
+        } else {
+            u32::from(u16::from(nodes[index].variable()))
+        }
+    };
+    let mut counts = vec![0u128; nodes.len()];
+    if nodes.len() > 1 {
+        counts[1] = 1;
+    }
+    for (index, node) in nodes.iter().enumerate().skip(2) {
+        let (low, high) = (node.low_link().as_index(), node.high_link().as_index());
+        let low_count = counts[low] << (level(low) - level(index) - 1);
+        let high_count = counts[high] << (level(high) - level(index) - 1);
+        counts[index] = low_count + high_count;
+    }
+    let root = nodes.len() - 1;
+    counts[root] << level(root)
+}
+
+fn main() {
+    // Non-attacking rooks on a 4x4 board: exactly one rook in every row and every column.
+    // Variable `4 * row + column` is true if the square is occupied.
+    let n = 4;
+    let ff = Bdd::new_false();
+    let square =
+        |row: usize, column: usize| Bdd::new_variable(VariableId::from((n * row + column) as u16));
+    let exactly_one = |squares: Vec<Bdd>| {
+        let mut result = ff.clone();
+        for (i, chosen) in squares.iter().enumerate() {
+            let mut term = chosen.clone();
+            for (j, other) in squares.iter().enumerate() {
+                if i != j {
+                    term = term.and_not(other);
+                }
+            }
+            result = result.or(&term);
+        }
+        result
+    };
+
+    let mut board = ff.imp(&ff);
+    for i in 0..n {
+        board = board.and(&exactly_one((0..n).map(|j| square(i, j)).collect()));
+        board = board.and(&exactly_one((0..n).map(|j| square(j, i)).collect()));
+    }
+    let solutions = count_solutions(&board, (n * n) as u16);
+    println!(
+        "{} placements of {} rooks ({} nodes)",
+        solutions,
+        n,
+        board.node_count()
+    );
+    // Every permutation of the columns is a solution.
+    assert_eq!(24, solutions);
+
+    // Counting also works for constants and for variables which do not appear in the `Bdd`.
+    assert_eq!(0, count_solutions(&ff, 3));
+    assert_eq!(8, count_solutions(&ff.imp(&ff), 3));
+    assert_eq!(4, count_solutions(&square(0, 1), 3));
+}
@@ -0,0 +1,54 @@
+//! Export a `Bdd` into the DOT format of Graphviz.
+//!
+//! Run with `cargo run --example dot | dot -Tsvg > bdd.svg`.
+
+use binary_decision_diagrams::v2::{Bdd, VariableId};
+use std::fmt::Write;
+
+/// A DOT graph of the given `bdd`. Low links are dashed, high links are solid.
+///
+/// Only the nodes reachable from the root are included (results of some operations can
+/// contain unreachable nodes).
+fn to_dot(bdd: &Bdd) -> String {
+    let nodes = bdd.node_slice();
+    let mut reachable = vec![false; nodes.len()];
+    let mut stack = vec![nodes.len() - 1];
+    while let Some(index) = stack.pop() {
+        if !reachable[index] {
+            reachable[index] = true;
+            if index >= 2 {
+                stack.push(nodes[index].low_link().as_index());
+                stack.push(nodes[index].high_link().as_index());
+            }
+        }
+    }
+
+    let mut dot = String::from("digraph bdd {\n");
+    for (index, node) in nodes.iter().enumerate().filter(|(i, _)| reachable[*i]) {
+        if index < 2 {
+            writeln!(dot, "  n{} [shape=box, label=\"{}\"];", index, index).unwrap();
+            continue;
+        }
+        let variable = u16::from(node.variable());
+        writeln!(dot, "  n{} [label=\"x{}\"];", index, variable).unwrap();
+        let (low, high) = (node.low_link().as_index(), node.high_link().as_index());
+        writeln!(dot, "  n{} -> n{} [style=dashed];", index, low).unwrap();
+        writeln!(dot, "  n{} -> n{};", index, high).unwrap();
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+fn main() {
+    let x: Vec<Bdd> = (0..4u16)
+        .map(|v| Bdd::new_variable(VariableId::from(v)))
+        .collect();
+    let bdd = x[0].and(&x[1]).or(&x[2].xor(&x[3]));
+    let dot = to_dot(&bdd);
+    print!("{}", dot);
+
+    // Both terminals and all decision nodes are in the graph.
+    let decision_nodes = dot.matches("label=\"x").count();
+    assert_eq!(bdd.node_count() - 2, decision_nodes);
+    assert_eq!(2 * decision_nodes, dot.matches("->").count());
+}
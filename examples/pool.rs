@@ -0,0 +1,46 @@
+//! Store many related `Bdds` in a `BddPool`, share them with another thread, and compact
+//! the pool (requires the `std` feature).
+//!
+//! Run with `cargo run --example pool`.
+
+use binary_decision_diagrams::v2::{Bdd, BddPool, BddStorage, VariableId};
+
+fn main() {
+    let x: Vec<Bdd> = (0..8u16)
+        .map(|v| Bdd::new_variable(VariableId::from(v)))
+        .collect();
+
+    // Prefixes of a chain of conjunctions share most of their nodes.
+    let mut pool = BddPool::new();
+    let mut conjunction = x[7].clone();
+    let mut handles = Vec::new();
+    for i in (0..7).rev() {
+        conjunction = conjunction.and(&x[i]);
+        handles.push(pool.intern(&conjunction));
+    }
+    let standalone: usize = handles.iter().map(|h| pool.export(*h).node_count()).sum();
+    println!(
+        "{} Bdds: {} nodes in the pool, {} nodes when stored separately",
+        handles.len(),
+        pool.node_count(),
+        standalone
+    );
+    assert!(pool.node_count() < standalone);
+
+    // A frozen snapshot can be analysed in another thread while the pool keeps changing.
+    let frozen = pool.freeze(handles[0]);
+    let analysis = std::thread::spawn(move || frozen.to_bdd().node_count());
+    let garbage = pool.intern(&x[0].xor(&x[1]));
+
+    // Handles that are stored elsewhere remember the epoch in which they were created.
+    let epoch = pool.epoch();
+    pool.sort_preorder(&mut handles);
+    assert!(!pool.is_current_epoch(epoch));
+    println!(
+        "After compaction: {} nodes (the garbage {:?} was removed)",
+        pool.node_count(),
+        garbage
+    );
+    assert_eq!(4, analysis.join().unwrap());
+    assert!(pool.export(handles[6]).semantic_eq(&conjunction));
+}
@@ -0,0 +1,83 @@
+//! Symbolic reachability in a small transition system: a three-bit counter which can either
+//! increment, or reset to zero once it reaches six.
+//!
+//! Run with `cargo run --example reachability`.
+
+use binary_decision_diagrams::v2::{ApplyContext, Bdd, Delta, PooledHandle, VariableId};
+
+/// The number of bits of the counter.
+const BITS: usize = 3;
+
+fn main() -> Result<(), String> {
+    let mut context = ApplyContext::new();
+    // Current state bits use even variables, successor state bits use odd variables,
+    // so that renaming between them preserves the variable order.
+    let source: Vec<VariableId> = (0..BITS).map(|i| VariableId::from(2 * i as u16)).collect();
+    let target: Vec<VariableId> = (0..BITS)
+        .map(|i| VariableId::from(2 * i as u16 + 1))
+        .collect();
+    let s: Vec<PooledHandle> = source
+        .iter()
+        .map(|v| context.import(&Bdd::new_variable(*v)))
+        .collect();
+    let t: Vec<PooledHandle> = target
+        .iter()
+        .map(|v| context.import(&Bdd::new_variable(*v)))
+        .collect();
+    let ff = context.import(&Bdd::new_false());
+    let tt = context.imp(ff, ff);
+
+    // Increment: every successor bit is the current bit xor the carry from lower bits.
+    let mut increment = tt;
+    let mut carry = tt;
+    for i in 0..BITS {
+        let bit = context.xor(s[i], carry);
+        let next = context.iff(t[i], bit);
+        increment = context.and(increment, next);
+        carry = context.and(carry, s[i]);
+    }
+    // Reset: from `110` to `000`.
+    let six = state(&mut context, &s, 6);
+    let zero_next = state(&mut context, &t, 0);
+    let reset = context.and(six, zero_next);
+    // The counter cannot increment past six.
+    let not_six = context.imp(six, ff);
+    let increment = context.and(increment, not_six);
+    let relation = context.or(increment, reset);
+
+    // Explore the states reachable from zero, one breadth-first layer at a time.
+    let initial = state(&mut context, &s, 0);
+    let mut delta = Delta::new(initial);
+    let mut iterations = 0;
+    while !delta.is_fixed_point() {
+        delta = context.delta_image(delta, relation, &source, &target)?;
+        iterations += 1;
+    }
+
+    // States `0..=6` are reachable, `7` is not.
+    let seven = state(&mut context, &s, 7);
+    let unreachable = context.and(delta.reached(), seven);
+    assert_eq!(ff, unreachable);
+    let reached = context.export(delta.reached());
+    println!(
+        "Fixed point after {} iterations, reachable states: {} nodes",
+        iterations,
+        reached.node_count()
+    );
+    Ok(())
+}
+
+/// The `Bdd` of a single state: a conjunction of literals over the given `bits`.
+fn state(context: &mut ApplyContext, bits: &[PooledHandle], value: usize) -> PooledHandle {
+    let ff = context.import(&Bdd::new_false());
+    let mut result = context.imp(ff, ff);
+    for (i, bit) in bits.iter().enumerate() {
+        let literal = if (value >> i) & 1 == 1 {
+            *bit
+        } else {
+            context.imp(*bit, ff)
+        };
+        result = context.and(result, literal);
+    }
+    result
+}
@@ -0,0 +1,40 @@
+//! **(internal)** Architecture-specific intrinsics, with portable fallbacks.
+//!
+//! All code that would otherwise use `core::arch` directly goes through this module, such that
+//! the library builds on every 64-bit target (and `wasm32`). On targets without a supported
+//! intrinsic (or when intrinsics are disabled by the `wasm` feature), the hints are no-ops.
+//!
+//! Prefetch instructions ignore invalid memory, hence the functions are safe for any pointer.
+//! The pointer should still be computed using `wrapping_add` (not `get_unchecked`), because
+//! creating an out-of-bounds reference is undefined behaviour even if it is never read.
+
+/// Hint the CPU that the memory at the given `pointer` will be needed soon (the data is
+/// loaded into all cache levels).
+#[inline]
+pub(crate) fn prefetch<T>(pointer: *const T) {
+    #[cfg(all(target_arch = "x86_64", not(feature = "wasm")))]
+    unsafe {
+        core::arch::x86_64::_mm_prefetch::<{ core::arch::x86_64::_MM_HINT_T0 }>(
+            pointer as *const i8,
+        );
+    }
+    #[cfg(not(all(target_arch = "x86_64", not(feature = "wasm"))))]
+    let _ = pointer;
+}
+
+/// Same as `prefetch`, but for data which is needed later and should not displace the data
+/// in the fastest cache levels (the data is loaded into the outer cache levels only).
+///
+/// Currently only used by the benchmark code.
+#[cfg(feature = "bench")]
+#[inline]
+pub(crate) fn prefetch_far<T>(pointer: *const T) {
+    #[cfg(all(target_arch = "x86_64", not(feature = "wasm")))]
+    unsafe {
+        core::arch::x86_64::_mm_prefetch::<{ core::arch::x86_64::_MM_HINT_T2 }>(
+            pointer as *const i8,
+        );
+    }
+    #[cfg(not(all(target_arch = "x86_64", not(feature = "wasm"))))]
+    let _ = pointer;
+}
@@ -0,0 +1,330 @@
+//! The recommended public API of the library.
+//!
+//! The crate contains several generations of BDD implementations (`v2`, `v3`, `v4`, `v5`
+//! and `machine`), which are kept mostly for benchmarking and experiments, and are hidden
+//! from the documentation. `bdd::Bdd` hides them behind one stable type: every operation
+//! is dispatched to the most suitable engine, which is currently the `v2` apply with its
+//! 16/32/48-bit kernels, selected based on the size of the operands, or the out-of-order
+//! apply of `v3` for very large operands. A different engine can be requested using
+//! `Backend`.
+
+use crate::v2;
+use crate::v2::_impl_::bdd::binary_operations::dispatcher::{self, Strategy};
+use crate::v2::ops::{And, AndNot, BooleanOp, Iff, Imp, NotAnd, Or, Xor};
+use crate::v2::ApplyOptions;
+use crate::v3;
+use crate::v4;
+use core::convert::TryFrom;
+
+pub use crate::v2::{ApplyKernel, BoolOp, VariableId};
+
+/// Selects the engine which evaluates a binary operation (see `Bdd::apply_with`).
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum Backend {
+    /// Select the engine based on the operands (see `Backend::select`).
+    #[default]
+    Automatic,
+    /// Always use the given `v2` kernel. If the operands are too large for the kernel,
+    /// the smallest larger kernel which fits is used instead.
+    Kernel(ApplyKernel),
+    /// Use the stack-based `v4::apply` algorithm. The operands and the result are converted
+    /// to and from the `v4` representation, so this is mainly useful for comparisons.
+    Stack,
+    /// Use the out-of-order `v3::core::ooo::apply` algorithm. Same as with `Stack`,
+    /// the operands and the result are converted to and from the `v3` representation.
+    OutOfOrder,
+}
+
+impl Backend {
+    /// The smallest number of nodes of the larger operand for which `Backend::Automatic`
+    /// uses the out-of-order apply.
+    ///
+    /// The out-of-order apply overlaps the memory accesses of independent tasks, which only
+    /// pays off once most accesses miss the caches. It also converts the operands and the
+    /// result, so for operands of up to a million nodes, it is about 2.5 times slower than
+    /// the `U48` kernel. The threshold is therefore an order of magnitude above that.
+    pub const OUT_OF_ORDER_NODES: usize = 1 << 24;
+
+    /// The engine which `Backend::Automatic` uses for an operation on `left` and `right`.
+    ///
+    /// The `v2` kernel is selected based on the operand sizes (see `ApplyKernel`).
+    /// Additionally, big operands which are not sorted in DFS preorder are sorted before
+    /// the kernel starts, since the kernels rely on the locality of sorted operands. The rules
+    /// are described in `v2::_impl_::bdd::binary_operations::dispatcher`.
+    ///
+    /// If the larger operand has at least `Backend::OUT_OF_ORDER_NODES` nodes, the out-of-order
+    /// apply is selected instead.
+    pub fn select(left: &Bdd, right: &Bdd) -> Backend {
+        if is_out_of_order_size(left.node_count(), right.node_count()) {
+            return Backend::OutOfOrder;
+        }
+        // Sorting does not change the sizes, so it does not affect the kernel.
+        let options = ApplyOptions {
+            precondition: Some(false),
+            ..ApplyOptions::default()
+        };
+        let (left, right) = (&left.inner, &right.inner);
+        let strategy = match dispatcher::select(left, right, &options) {
+            Strategy::Swap => dispatcher::select(right, left, &options),
+            strategy => strategy,
+        };
+        match strategy {
+            Strategy::Apply(kernel) => Backend::Kernel(kernel),
+            // The larger operand is on the left and sorting is disabled.
+            Strategy::Swap | Strategy::Precondition => unreachable!(),
+        }
+    }
+}
+
+/// **(internal)** True if `Backend::Automatic` uses the out-of-order apply for operands
+/// with the given node counts (see `Backend::OUT_OF_ORDER_NODES`).
+fn is_out_of_order_size(left_nodes: usize, right_nodes: usize) -> bool {
+    left_nodes.max(right_nodes) >= Backend::OUT_OF_ORDER_NODES
+}
+
+/// A binary decision diagram of a Boolean function.
+///
+/// The variables are ordered by their `VariableId`. The diagram is not guaranteed to be
+/// minimal (see `Bdd::reduce`), so use `Bdd::semantic_eq` to compare two functions.
+#[derive(Clone)]
+pub struct Bdd {
+    inner: v2::Bdd,
+}
+
+impl Bdd {
+    /// The constant `false` function.
+    pub fn new_false() -> Bdd {
+        Bdd::from(v2::Bdd::new_false())
+    }
+
+    /// The constant `true` function.
+    pub fn new_true() -> Bdd {
+        Bdd::from(v2::Bdd::new_true())
+    }
+
+    /// The function which is true if and only if the `variable` is true.
+    ///
+    /// Panics if the variable is `VariableId::UNDEFINED`.
+    pub fn new_variable(variable: VariableId) -> Bdd {
+        Bdd::from(v2::Bdd::new_variable(variable))
+    }
+
+    /// The function which is true if and only if the `variable` has the given `value`.
+    ///
+    /// Panics if the variable is `VariableId::UNDEFINED`.
+    pub fn new_literal(variable: VariableId, value: bool) -> Bdd {
+        let bdd = v2::Bdd::new_variable(variable);
+        Bdd::from(if value { bdd } else { bdd.not() })
+    }
+
+    /// The number of variables of this function (one more than the largest used variable).
+    pub fn variable_count(&self) -> u16 {
+        self.inner.variable_count()
+    }
+
+    /// The number of nodes of this `Bdd`, including the terminal nodes.
+    pub fn node_count(&self) -> usize {
+        self.inner.node_count()
+    }
+
+    /// The negation of this function.
+    pub fn not(&self) -> Bdd {
+        Bdd::from(self.inner.not())
+    }
+
+    /// The conjunction `self & other`.
+    pub fn and(&self, other: &Bdd) -> Bdd {
+        self.apply(BoolOp::And, other)
+    }
+
+    /// The disjunction `self | other`.
+    pub fn or(&self, other: &Bdd) -> Bdd {
+        self.apply(BoolOp::Or, other)
+    }
+
+    /// The exclusive disjunction `self ^ other`.
+    pub fn xor(&self, other: &Bdd) -> Bdd {
+        self.apply(BoolOp::Xor, other)
+    }
+
+    /// The implication `self => other`.
+    pub fn imp(&self, other: &Bdd) -> Bdd {
+        self.apply(BoolOp::Imp, other)
+    }
+
+    /// The equivalence `self <=> other`.
+    pub fn iff(&self, other: &Bdd) -> Bdd {
+        self.apply(BoolOp::Iff, other)
+    }
+
+    /// The difference `self & !other`.
+    pub fn and_not(&self, other: &Bdd) -> Bdd {
+        self.apply(BoolOp::AndNot, other)
+    }
+
+    /// A conjunction of all the `operands` (`true` if there are none).
+    ///
+    /// The two smallest partial results are always combined first, which is typically much
+    /// faster than a left fold over the operands (see `v2::Bdd::and_all`).
+    pub fn and_all<I: IntoIterator<Item = Bdd>>(operands: I) -> Bdd {
+        Bdd::from(v2::Bdd::and_all(operands.into_iter().map(|it| it.inner)))
+    }
+
+    /// A disjunction of all the `operands` (`false` if there are none), evaluated
+    /// the same way as `Bdd::and_all`.
+    pub fn or_all<I: IntoIterator<Item = Bdd>>(operands: I) -> Bdd {
+        Bdd::from(v2::Bdd::or_all(operands.into_iter().map(|it| it.inner)))
+    }
+
+    /// Apply the logical operation `op`, using the automatically selected engine.
+    pub fn apply(&self, op: BoolOp, other: &Bdd) -> Bdd {
+        self.apply_with(op, other, Backend::Automatic)
+    }
+
+    /// Apply the logical operation `op`, using the engine selected by `backend`.
+    pub fn apply_with(&self, op: BoolOp, other: &Bdd, backend: Backend) -> Bdd {
+        match op {
+            BoolOp::And => self.apply_backend::<And>(other, backend),
+            BoolOp::Or => self.apply_backend::<Or>(other, backend),
+            BoolOp::Xor => self.apply_backend::<Xor>(other, backend),
+            BoolOp::Imp => self.apply_backend::<Imp>(other, backend),
+            BoolOp::Iff => self.apply_backend::<Iff>(other, backend),
+            BoolOp::AndNot => self.apply_backend::<AndNot>(other, backend),
+            BoolOp::NotAnd => self.apply_backend::<NotAnd>(other, backend),
+        }
+    }
+
+    /// A minimal `Bdd` of the same function.
+    pub fn reduce(&self) -> Bdd {
+        Bdd::from(self.inner.reduce())
+    }
+
+    /// True if the two `Bdds` represent the same function (their structure can differ).
+    pub fn semantic_eq(&self, other: &Bdd) -> bool {
+        self.inner.semantic_eq(&other.inner)
+    }
+
+    /// The underlying `v2::Bdd`, for operations which are not (yet) part of this API.
+    pub fn as_v2(&self) -> &v2::Bdd {
+        &self.inner
+    }
+
+    /// **(internal)** Evaluate `OP` using the engine selected by `backend`.
+    fn apply_backend<OP: BooleanOp>(&self, other: &Bdd, backend: Backend) -> Bdd {
+        // A valid `v2::Bdd` is always a valid `v4::core::Bdd`, and the result only uses
+        // the variables of the operands, which fit into `v2::VariableId`.
+        let convert = |bdd: &v2::Bdd| v4::core::Bdd::try_from(bdd).unwrap();
+        let backend = match backend {
+            // Only the out-of-order rule of `Backend::select` is checked here, the kernel
+            // is selected by the `v2` apply itself.
+            Backend::Automatic if is_out_of_order_size(self.node_count(), other.node_count()) => {
+                Backend::OutOfOrder
+            }
+            backend => backend,
+        };
+        let result = match backend {
+            Backend::Automatic => self.inner.apply::<OP>(&other.inner),
+            Backend::Kernel(kernel) => {
+                let options = ApplyOptions {
+                    kernel: Some(kernel),
+                    ..ApplyOptions::default()
+                };
+                self.inner.apply_with::<OP>(&other.inner, &options)
+            }
+            Backend::Stack => {
+                let result = v4::apply::apply::<OP>(&convert(&self.inner), &convert(&other.inner));
+                v2::Bdd::try_from(&result).unwrap()
+            }
+            Backend::OutOfOrder => {
+                let left = v3::core::bdd::Bdd::from(&convert(&self.inner));
+                let right = v3::core::bdd::Bdd::from(&convert(&other.inner));
+                let result = v3::core::ooo::apply::<OP>(&left, &right);
+                v2::Bdd::try_from(&v4::core::Bdd::try_from(&result).unwrap()).unwrap()
+            }
+        };
+        let mut result = Bdd::from(result);
+        let variable_count = self.variable_count().max(other.variable_count());
+        result.inner.update_variable_count(variable_count);
+        result
+    }
+}
+
+impl From<v2::Bdd> for Bdd {
+    fn from(value: v2::Bdd) -> Self {
+        Bdd { inner: value }
+    }
+}
+
+impl From<Bdd> for v2::Bdd {
+    fn from(value: Bdd) -> Self {
+        value.inner
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bdd::{is_out_of_order_size, ApplyKernel, Backend, Bdd, BoolOp, VariableId};
+    use alloc::vec::Vec;
+
+    #[test]
+    pub fn backend_test() {
+        let x: Vec<Bdd> = (0..5u16)
+            .map(|v| Bdd::new_variable(VariableId::from(v)))
+            .collect();
+        let left = x[0].and(&x[1]).or(&x[2].xor(&x[3]));
+        let not_x2 = Bdd::new_literal(VariableId::from(2), false);
+        let right = x[4].imp(&x[0]).iff(&not_x2);
+        let backends = [
+            Backend::Automatic,
+            Backend::Kernel(ApplyKernel::U16),
+            Backend::Kernel(ApplyKernel::U32),
+            Backend::Kernel(ApplyKernel::U48),
+            Backend::Stack,
+            Backend::OutOfOrder,
+        ];
+        for op in BoolOp::ALL.iter().copied() {
+            let expected = left.apply(op, &right);
+            for backend in backends.iter().copied() {
+                let result = left.apply_with(op, &right, backend);
+                assert!(expected.semantic_eq(&result), "{:?} {:?}", op, backend);
+                assert_eq!(5, result.variable_count());
+            }
+        }
+        assert!(left.and_not(&left).semantic_eq(&Bdd::new_false()));
+        assert!(left.or(&left.not()).semantic_eq(&Bdd::new_true()));
+        assert_eq!(1, x[0].and(&x[0].not()).reduce().node_count());
+        let any = x.iter().skip(1).fold(x[0].clone(), |acc, it| acc.or(it));
+        assert!(any.semantic_eq(&Bdd::or_all(x.iter().cloned())));
+        let all = x.iter().skip(1).fold(x[0].clone(), |acc, it| acc.and(it));
+        assert!(all.semantic_eq(&Bdd::and_all(x.iter().cloned())));
+    }
+
+    #[test]
+    pub fn select_backend_test() {
+        let x: Vec<Bdd> = (0..20u16)
+            .map(|v| Bdd::new_variable(VariableId::from(v)))
+            .collect();
+        let small = x[0].and(&x[1]);
+        let u16 = Backend::Kernel(ApplyKernel::U16);
+        assert_eq!(u16, Backend::select(&small, &x[2]));
+        assert_eq!(u16, Backend::select(&x[2], &small));
+
+        // A function with an exponential number of nodes in this variable order.
+        let large = (0..10).fold(Bdd::new_false(), |acc, i| acc.or(&x[i].and(&x[i + 10])));
+        assert!(large.node_count() > 1000);
+        let u32 = Backend::Kernel(ApplyKernel::U32);
+        assert_eq!(u32, Backend::select(&large, &small));
+        assert_eq!(u32, Backend::select(&small, &large));
+        // Too many variables for the `U16` kernel.
+        let wide = x[0].and(&Bdd::new_variable(VariableId::from(100)));
+        assert_eq!(u32, Backend::select(&wide, &small));
+
+        // Only very large operands use the out-of-order apply.
+        let threshold = Backend::OUT_OF_ORDER_NODES;
+        assert!(!is_out_of_order_size(large.node_count(), small.node_count()));
+        assert!(!is_out_of_order_size(threshold - 1, 2));
+        assert!(is_out_of_order_size(threshold, 2));
+        assert!(is_out_of_order_size(2, threshold));
+    }
+}
+
@@ -0,0 +1,67 @@
+//! Apply a binary operation to two `Bdd` files in the text format of `Bdd::try_from`
+//! (e.g. the files in `bench_inputs`) and save the result in the same format.
+//!
+//! Usage: `apply <operation> <left.bdd> <right.bdd> [result.bdd]`
+//!
+//! The operation is one of `and`, `or`, `xor`, `imp`, `iff`, `and_not` or `not_and`. Without
+//! the output path, the result is written to the standard output. If the output path ends
+//! with `.dot`, the result is written as a Graphviz digraph instead. The statistics of
+//! the operation are always written to the standard error output.
+//!
+//! Usage: `apply --script <script.txt>`
+//!
+//! In batch mode, a whole workflow of operations is described by a script (see `script.rs`).
+
+mod script;
+
+use binary_decision_diagrams::v2::BoolOp;
+use script::{read_bdd, write_bdd};
+use std::convert::TryFrom;
+use std::path::Path;
+use std::time::Instant;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if let Err(message) = run(&args[1..]) {
+        eprintln!("Error: {}", message);
+        eprintln!("Usage: apply <operation> <left.bdd> <right.bdd> [result.bdd]");
+        eprintln!("       apply --script <script.txt>");
+        std::process::exit(1);
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    if args.len() == 2 && args[0] == "--script" {
+        let path = Path::new(&args[1]);
+        let script = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read `{}`: {}", path.display(), e))?;
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        return script::run_script(&script, base, &mut std::io::stdout());
+    }
+    if args.len() != 3 && args.len() != 4 {
+        return Err(format!("Expected 3 or 4 arguments, found {}.", args.len()));
+    }
+    let op = BoolOp::try_from(args[0].as_str())?;
+    let left = read_bdd(Path::new(&args[1]))?;
+    let right = read_bdd(Path::new(&args[2]))?;
+
+    let start = Instant::now();
+    let result = left.apply_op(op, &right);
+    let elapsed = start.elapsed();
+    eprintln!(
+        "{}: {} x {} nodes -> {} nodes in {}ms.",
+        op.name(),
+        left.node_count(),
+        right.node_count(),
+        result.node_count(),
+        elapsed.as_millis()
+    );
+
+    match args.get(3) {
+        Some(path) => write_bdd(Path::new(path), &result),
+        None => {
+            println!("{}", result);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,420 @@
+//! The batch mode of `apply`: a small scripting language for multi-operation workflows.
+//!
+//! A script is a sequence of statements terminated by `;`:
+//!  - `let <name> = <expression>;` evaluates the expression and binds the result to `name`.
+//!  - `save <name> "<path>";` writes the `Bdd` in the text format of `Bdd::try_from`,
+//!    or as a Graphviz digraph if the path ends with `.dot` (see `write_bdd`).
+//!  - `stats <name>;` prints the node and variable count of the `Bdd`.
+//!
+//! An expression is a `<name>`, a `load "<path>"`, a named operation `<op>(<left>, <right>)`
+//! (e.g. `and_not(a, b)`), a parenthesized expression, or two expressions combined using
+//! the infix operators `&` (and), `^` (xor), `|` (or), `=>` (imp) and `<=>` (iff), listed
+//! from the highest to the lowest precedence. All operators are left-associative. Relative
+//! paths are resolved against the directory of the script and `#` starts a line comment.
+//!
+//! The script is first parsed into a list of `Statement` objects (the plan), which is then
+//! executed in a single `Forest`, so all results share one node storage and operation cache.
+
+use binary_decision_diagrams::v2::{ApplyContext, Bdd, BoolOp, Forest, PooledHandle};
+use binary_decision_diagrams::v4;
+use std::convert::TryFrom;
+use std::io::Write;
+use std::path::Path;
+
+/// One statement of a script, together with its line number.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Statement {
+    Let(usize, String, Expression),
+    Save(usize, String, String),
+    Stats(usize, String),
+}
+
+/// An expression which evaluates to a `Bdd`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression {
+    Name(String),
+    Load(String),
+    Apply(BoolOp, Box<Expression>, Box<Expression>),
+}
+
+/// A token of the script.
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Identifier(String),
+    Text(String),
+    Symbol(&'static str),
+}
+
+/// The infix operators, grouped from the lowest to the highest precedence.
+const PRECEDENCE: [&[(&str, BoolOp)]; 5] = [
+    &[("<=>", BoolOp::Iff)],
+    &[("=>", BoolOp::Imp)],
+    &[("|", BoolOp::Or)],
+    &[("^", BoolOp::Xor)],
+    &[("&", BoolOp::And)],
+];
+
+/// All symbols of the language. Longer symbols go first, such that `<=>` is not split.
+const SYMBOLS: [&str; 10] = ["<=>", "=>", "&", "|", "^", "(", ")", ",", "=", ";"];
+
+/// Parse and execute the given `script`. Relative paths are resolved against `base`
+/// and the output of `stats` is written into `output`.
+pub fn run_script(script: &str, base: &Path, output: &mut dyn Write) -> Result<(), String> {
+    let plan = parse(script)?;
+    let mut forest = Forest::new();
+    for statement in &plan {
+        execute(&mut forest, statement, base, output)?;
+    }
+    Ok(())
+}
+
+/// Parse the given `script` into a list of statements.
+pub fn parse(script: &str) -> Result<Vec<Statement>, String> {
+    let tokens = tokenize(script)?;
+    let mut parser = Parser {
+        tokens: &tokens,
+        position: 0,
+    };
+    let mut statements = Vec::new();
+    while parser.position < tokens.len() {
+        statements.push(parser.statement()?);
+    }
+    Ok(statements)
+}
+
+/// **(internal)** Execute one `statement` with the variables stored in the `forest`.
+fn execute(
+    forest: &mut Forest,
+    statement: &Statement,
+    base: &Path,
+    output: &mut dyn Write,
+) -> Result<(), String> {
+    let line = match statement {
+        Statement::Let(line, _, _) | Statement::Save(line, _, _) | Statement::Stats(line, _) => {
+            *line
+        }
+    };
+    let in_line = |message: String| format!("Line {}: {}", line, message);
+    match statement {
+        Statement::Let(_, name, expression) => {
+            let handle = evaluate(forest, expression, base).map_err(in_line)?;
+            forest.insert_handle(name, handle).map_err(in_line)
+        }
+        Statement::Save(_, name, path) => {
+            let bdd = export(forest, name).map_err(in_line)?;
+            write_bdd(&base.join(path), &bdd).map_err(in_line)
+        }
+        Statement::Stats(_, name) => {
+            let bdd = export(forest, name).map_err(in_line)?;
+            writeln!(
+                output,
+                "{}: {} nodes, {} variables",
+                name,
+                bdd.node_count(),
+                bdd.variable_count()
+            )
+            .map_err(|e| in_line(format!("Cannot write output: {}", e)))
+        }
+    }
+}
+
+/// **(internal)** Evaluate the `expression` in the context of the `forest`.
+fn evaluate(
+    forest: &mut Forest,
+    expression: &Expression,
+    base: &Path,
+) -> Result<PooledHandle, String> {
+    match expression {
+        Expression::Name(name) => forest
+            .get(name)
+            .ok_or_else(|| format!("Unknown name `{}`.", name)),
+        Expression::Load(path) => {
+            let bdd = read_bdd(&base.join(path))?;
+            Ok(forest.context_mut().import(&bdd))
+        }
+        Expression::Apply(op, left, right) => {
+            let left = evaluate(forest, left, base)?;
+            let right = evaluate(forest, right, base)?;
+            let context = forest.context_mut();
+            Ok(apply(context, *op, left, right))
+        }
+    }
+}
+
+/// **(internal)** Apply the operation `op` to two handles of the `context`.
+fn apply(
+    context: &mut ApplyContext,
+    op: BoolOp,
+    left: PooledHandle,
+    right: PooledHandle,
+) -> PooledHandle {
+    match op {
+        BoolOp::And => context.and(left, right),
+        BoolOp::Or => context.or(left, right),
+        BoolOp::Xor => context.xor(left, right),
+        BoolOp::Imp => context.imp(left, right),
+        BoolOp::Iff => context.iff(left, right),
+        BoolOp::AndNot => context.and_not(left, right),
+        BoolOp::NotAnd => context.not_and(left, right),
+    }
+}
+
+/// **(internal)** A standalone copy of the variable `name`.
+fn export(forest: &Forest, name: &str) -> Result<Bdd, String> {
+    forest
+        .export(name)
+        .ok_or_else(|| format!("Unknown name `{}`.", name))
+}
+
+/// Read a `Bdd` from a file in the text format of `Bdd::try_from`.
+pub fn read_bdd(path: &Path) -> Result<Bdd, String> {
+    let data = std::fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read `{}`: {}", path.display(), e))?;
+    Bdd::try_from(data.trim()).map_err(|e| format!("Invalid `Bdd` in `{}`: {}", path.display(), e))
+}
+
+/// Write a `Bdd` into a file in the text format of `Bdd::try_from`, or in the Graphviz DOT
+/// format (see `v4::core::Bdd::to_dot`) if the file has the `.dot` extension.
+pub fn write_bdd(path: &Path, bdd: &Bdd) -> Result<(), String> {
+    let data = if path.extension().map_or(false, |it| it == "dot") {
+        v4::core::Bdd::try_from(bdd)?.to_dot(None)
+    } else {
+        bdd.to_string()
+    };
+    std::fs::write(path, data).map_err(|e| format!("Cannot write `{}`: {}", path.display(), e))
+}
+
+/// **(internal)** Split the `script` into tokens annotated with line numbers.
+fn tokenize(script: &str) -> Result<Vec<(usize, Token)>, String> {
+    let mut tokens = Vec::new();
+    for (index, line) in script.lines().enumerate() {
+        let line_number = index + 1;
+        let mut rest = line.trim_start();
+        while !rest.is_empty() && !rest.starts_with('#') {
+            if let Some(symbol) = SYMBOLS.iter().find(|s| rest.starts_with(*s)) {
+                tokens.push((line_number, Token::Symbol(symbol)));
+                rest = &rest[symbol.len()..];
+            } else if let Some(text) = rest.strip_prefix('"') {
+                let end = text
+                    .find('"')
+                    .ok_or_else(|| format!("Line {}: Unterminated string.", line_number))?;
+                tokens.push((line_number, Token::Text(text[..end].to_string())));
+                rest = &text[end + 1..];
+            } else {
+                let end = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                if end == 0 {
+                    let unexpected = rest.chars().next().unwrap_or_default();
+                    return Err(format!(
+                        "Line {}: Unexpected character `{}`.",
+                        line_number, unexpected
+                    ));
+                }
+                tokens.push((line_number, Token::Identifier(rest[..end].to_string())));
+                rest = &rest[end..];
+            }
+            rest = rest.trim_start();
+        }
+    }
+    Ok(tokens)
+}
+
+/// **(internal)** A recursive descent parser over a list of tokens.
+struct Parser<'a> {
+    tokens: &'a [(usize, Token)],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn statement(&mut self) -> Result<Statement, String> {
+        let line = self.line();
+        let keyword = self.identifier()?;
+        let statement = match keyword.as_str() {
+            "let" => {
+                let name = self.identifier()?;
+                self.expect("=")?;
+                Statement::Let(line, name, self.expression(0)?)
+            }
+            "save" => {
+                let name = self.identifier()?;
+                Statement::Save(line, name, self.text()?)
+            }
+            "stats" => Statement::Stats(line, self.identifier()?),
+            _ => return Err(self.error(&format!("Unknown statement `{}`.", keyword))),
+        };
+        self.expect(";")?;
+        Ok(statement)
+    }
+
+    /// Parse an expression whose operators have at least the given precedence `level`.
+    fn expression(&mut self, level: usize) -> Result<Expression, String> {
+        if level == PRECEDENCE.len() {
+            return self.atom();
+        }
+        let mut result = self.expression(level + 1)?;
+        while let Some((_, op)) = PRECEDENCE[level]
+            .iter()
+            .find(|(symbol, _)| self.peek() == Some(&Token::Symbol(symbol)))
+        {
+            self.position += 1;
+            let right = self.expression(level + 1)?;
+            result = Expression::Apply(*op, Box::new(result), Box::new(right));
+        }
+        Ok(result)
+    }
+
+    fn atom(&mut self) -> Result<Expression, String> {
+        if self.peek() == Some(&Token::Symbol("(")) {
+            self.position += 1;
+            let result = self.expression(0)?;
+            self.expect(")")?;
+            return Ok(result);
+        }
+        let name = self.identifier()?;
+        if name == "load" {
+            return Ok(Expression::Load(self.text()?));
+        }
+        if self.peek() != Some(&Token::Symbol("(")) {
+            return Ok(Expression::Name(name));
+        }
+        let op = BoolOp::try_from(name.as_str()).map_err(|e| self.error(&e))?;
+        self.expect("(")?;
+        let left = self.expression(0)?;
+        self.expect(",")?;
+        let right = self.expression(0)?;
+        self.expect(")")?;
+        Ok(Expression::Apply(op, Box::new(left), Box::new(right)))
+    }
+
+    fn identifier(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some(Token::Identifier(name)) => {
+                let name = name.clone();
+                self.position += 1;
+                Ok(name)
+            }
+            _ => Err(self.error("Expected a name.")),
+        }
+    }
+
+    fn text(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some(Token::Text(text)) => {
+                let text = text.clone();
+                self.position += 1;
+                Ok(text)
+            }
+            _ => Err(self.error("Expected a quoted path.")),
+        }
+    }
+
+    fn expect(&mut self, symbol: &'static str) -> Result<(), String> {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expected `{}`.", symbol)))
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, token)| token)
+    }
+
+    /// The line of the current token (or of the last token at the end of the input).
+    fn line(&self) -> usize {
+        let last = self.tokens.len().saturating_sub(1);
+        self.tokens
+            .get(self.position.min(last))
+            .map(|(line, _)| *line)
+            .unwrap_or(1)
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("Line {}: {}", self.line(), message)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse, run_script, Expression, Statement};
+    use binary_decision_diagrams::v2::{Bdd, BoolOp, VariableId};
+
+    #[test]
+    pub fn script_parser_test() {
+        let script =
+            "# A comment.\nlet a = load \"x.bdd\";\nlet b = a | c & d => and_not(a, (b ^ c));";
+        let name = |n: &str| Box::new(Expression::Name(n.to_string()));
+        let and_not = Expression::Apply(
+            BoolOp::AndNot,
+            name("a"),
+            Box::new(Expression::Apply(BoolOp::Xor, name("b"), name("c"))),
+        );
+        let or = Expression::Apply(
+            BoolOp::Or,
+            name("a"),
+            Box::new(Expression::Apply(BoolOp::And, name("c"), name("d"))),
+        );
+        let expected = vec![
+            Statement::Let(2, "a".to_string(), Expression::Load("x.bdd".to_string())),
+            Statement::Let(
+                3,
+                "b".to_string(),
+                Expression::Apply(BoolOp::Imp, Box::new(or), Box::new(and_not)),
+            ),
+        ];
+        assert_eq!(expected, parse(script).unwrap());
+
+        for (script, line) in [
+            ("let a = ;", 1),
+            ("let a = b\nstats a;", 2),
+            ("foo a;", 1),
+            ("let a = nand(a, b);", 1),
+            ("save a x.bdd;", 1),
+            ("let a = load \"x.bdd;", 1),
+            ("\n\nlet a = $;", 3),
+        ] {
+            let error = parse(script).unwrap_err();
+            assert!(error.starts_with(&format!("Line {}:", line)), "{}", error);
+        }
+    }
+
+    #[test]
+    pub fn script_execution_test() {
+        let directory = std::env::temp_dir().join("apply_script_execution_test");
+        std::fs::create_dir_all(&directory).unwrap();
+        let x = Bdd::new_variable(VariableId::from(0u16));
+        let y = Bdd::new_variable(VariableId::from(1u16));
+        std::fs::write(directory.join("x.bdd"), x.to_string()).unwrap();
+        std::fs::write(directory.join("y.bdd"), y.to_string()).unwrap();
+
+        let script = r#"
+            let a = load "x.bdd";
+            let b = a ^ load "y.bdd";   # x xor y
+            let c = and_not(b, a) | (a & b);
+            save c "c.bdd";
+            stats c;
+            stats a;
+        "#;
+        let mut output = Vec::new();
+        run_script(script, &directory, &mut output).unwrap();
+        assert_eq!(
+            "c: 5 nodes, 2 variables\na: 3 nodes, 2 variables\n",
+            String::from_utf8(output).unwrap()
+        );
+        // (x ^ y) & !x | x & (x ^ y) = x ^ y
+        let c = super::read_bdd(&directory.join("c.bdd")).unwrap();
+        assert!(c.semantic_eq(&x.xor(&y)));
+        let script = r#"let a = load "x.bdd"; save a "a.dot";"#;
+        run_script(script, &directory, &mut Vec::new()).unwrap();
+        let dot = std::fs::read_to_string(directory.join("a.dot")).unwrap();
+        assert!(dot.starts_with("digraph bdd {") && dot.contains("label=\"x_0\""));
+
+        let error = run_script("stats a;", &directory, &mut Vec::new()).unwrap_err();
+        assert!(error.starts_with("Line 1:"));
+        let error = run_script("let a = load \"missing.bdd\";", &directory, &mut Vec::new());
+        assert!(error.is_err());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+//! A differential fuzzer which compares random sequences of `v2` operations with CUDD.
+//!
+//! Every result is converted into CUDD (see `v2::cudd`) and compared with the result that CUDD
+//! computed for the same operation. The CUDD result is also converted back into a `Bdd`. Since CUDD is canonical, the two results are semantically equal
+//! iff the two CUDD nodes are identical. Additionally, the node count of every result must
+//! match the size of the canonical (reduced) diagram without complement edges, so that
+//! redundant or duplicate nodes in the optimized kernels are detected as well.
+//!
+//! Usage: `cudd_fuzz [iterations] [seed]` (defaults to 1000 iterations and seed 1).
+
+use binary_decision_diagrams::v2::{ApplyKernel, ApplyOptions, Bdd, BoolOp, VariableId};
+use cudd_sys::cudd::{
+    Cudd_E, Cudd_Init, Cudd_IsComplement, Cudd_Not, Cudd_Quit, Cudd_ReadLogicZero, Cudd_ReadOne,
+    Cudd_Ref, Cudd_Regular, Cudd_T, Cudd_bddAnd, Cudd_bddIthVar, Cudd_bddOr, Cudd_bddXnor,
+    Cudd_bddXor,
+};
+use cudd_sys::{DdManager, DdNode};
+use std::collections::HashSet;
+use std::os::raw::c_int;
+
+/// The largest number of variables of one fuzzing iteration.
+const MAX_VARIABLES: u16 = 12;
+/// The number of operations in one fuzzing iteration.
+const OPERATIONS: usize = 64;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let iterations: u64 = args.get(1).map(|it| it.parse().unwrap()).unwrap_or(1000);
+    let seed: u64 = args.get(2).map(|it| it.parse().unwrap()).unwrap_or(1);
+
+    let mut failures = 0;
+    for iteration in 0..iterations {
+        let mut rng = XorShift::new(seed.wrapping_add(iteration));
+        if let Err(message) = fuzz(&mut rng) {
+            failures += 1;
+            println!("Iteration {} (seed {}): {}", iteration, seed, message);
+        }
+    }
+    println!("{} iterations, {} failures.", iterations, failures);
+    if failures > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Run one sequence of random operations and compare every result with CUDD.
+fn fuzz(rng: &mut XorShift) -> Result<(), String> {
+    let variables = 1 + rng.next_below(usize::from(MAX_VARIABLES)) as u16;
+    let cudd: *mut DdManager = unsafe { Cudd_Init(0, 0, 1 << 12, 1 << 16, 0) };
+
+    // Start with positive literals and evaluate random operations on random operands.
+    let mut operands: Vec<(Bdd, *mut DdNode)> = (0..variables)
+        .map(|v| {
+            let bdd = Bdd::new_variable(VariableId::from(v));
+            let node = unsafe { Cudd_bddIthVar(cudd, c_int::from(v)) };
+            (bdd, node)
+        })
+        .collect();
+    let mut result = Ok(());
+    for step in 0..OPERATIONS {
+        let op = BoolOp::ALL[rng.next_below(BoolOp::ALL.len())];
+        let left = rng.next_below(operands.len());
+        let right = rng.next_below(operands.len());
+        // Also force each kernel, since they have separate implementations of every operation.
+        let kernel = [
+            None,
+            Some(ApplyKernel::U16),
+            Some(ApplyKernel::U32),
+            Some(ApplyKernel::U48),
+        ][rng.next_below(4)];
+        let options = ApplyOptions {
+            kernel,
+            ..ApplyOptions::default()
+        };
+
+        let bdd = operands[left]
+            .0
+            .apply_op_with(op, &operands[right].0, &options);
+        let expected = cudd_apply(cudd, op, operands[left].1, operands[right].1);
+        let actual = unsafe { bdd.move_to_cudd(cudd) };
+        let description = format!(
+            "step {}: {:?}({}, {}) with {:?} over {} variables",
+            step, op, left, right, kernel, variables
+        );
+        if actual != expected {
+            result = Err(format!("{}: the result is not equivalent.", description));
+            break;
+        }
+        let expected_count = canonical_node_count(cudd, expected);
+        if bdd.node_count() != expected_count {
+            result = Err(format!(
+                "{}: {} nodes, but the canonical result has {} nodes.",
+                description,
+                bdd.node_count(),
+                expected_count
+            ));
+            break;
+        }
+        let converted = unsafe { Bdd::from_cudd(cudd, expected) };
+        if !converted.map(|it| it.semantic_eq(&bdd)).unwrap_or(false) {
+            result = Err(format!("{}: conversion from CUDD failed.", description));
+            break;
+        }
+        operands.push((bdd, expected));
+    }
+
+    unsafe { Cudd_Quit(cudd) };
+    result
+}
+
+/// Compute the operation `op` in CUDD. The result is referenced and never released,
+/// the whole manager is destroyed at the end of the iteration.
+fn cudd_apply(
+    cudd: *mut DdManager,
+    op: BoolOp,
+    left: *mut DdNode,
+    right: *mut DdNode,
+) -> *mut DdNode {
+    let result = unsafe {
+        match op {
+            BoolOp::And => Cudd_bddAnd(cudd, left, right),
+            BoolOp::Or => Cudd_bddOr(cudd, left, right),
+            BoolOp::Xor => Cudd_bddXor(cudd, left, right),
+            BoolOp::Imp => Cudd_bddOr(cudd, Cudd_Not(left), right),
+            BoolOp::Iff => Cudd_bddXnor(cudd, left, right),
+            BoolOp::AndNot => Cudd_bddAnd(cudd, left, Cudd_Not(right)),
+            BoolOp::NotAnd => Cudd_bddAnd(cudd, Cudd_Not(left), right),
+        }
+    };
+    unsafe { Cudd_Ref(result) };
+    result
+}
+
+/// The number of nodes of the reduced diagram of `node` *without* complement edges
+/// (i.e. the node count of a canonical `Bdd`, including the terminals that it needs).
+///
+/// A node of CUDD reached through a complemented edge represents the negated function,
+/// so every (node, complement) pair corresponds to one node of the `Bdd`.
+fn canonical_node_count(cudd: *mut DdManager, node: *mut DdNode) -> usize {
+    let zero = unsafe { Cudd_ReadLogicZero(cudd) };
+    let one = unsafe { Cudd_ReadOne(cudd) };
+    if node == zero {
+        return 1;
+    }
+    let mut visited = HashSet::new();
+    let mut stack = vec![node];
+    while let Some(top) = stack.pop() {
+        if top == zero || top == one || !visited.insert(top) {
+            continue;
+        }
+        let regular = unsafe { Cudd_Regular(top) };
+        let (mut high, mut low) = unsafe { (Cudd_T(regular), Cudd_E(regular)) };
+        if unsafe { Cudd_IsComplement(top) } != 0 {
+            high = unsafe { Cudd_Not(high) };
+            low = unsafe { Cudd_Not(low) };
+        }
+        stack.push(high);
+        stack.push(low);
+    }
+    // Both terminals are present in every `Bdd` except for `false`.
+    visited.len() + 2
+}
+
+/// A tiny `xorshift64*` pseudo-random generator, such that every iteration can be
+/// reproduced from its seed.
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> XorShift {
+        XorShift(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn next_below(&mut self, bound: usize) -> usize {
+        (self.next() % (bound as u64)) as usize
+    }
+}
@@ -1,7 +1,8 @@
 #![allow(unused_imports)]
 
 use binary_decision_diagrams::v4::core::Bdd;
-use binary_decision_diagrams::v4::apply::apply;
+use binary_decision_diagrams::v4::apply_with_stats;
+use binary_decision_diagrams::v4::ops::Or;
 use std::convert::TryFrom;
 use perfcnt::linux::{PerfCounterBuilderLinux, HardwareEventType};
 use criterion::measurement::Measurement;
@@ -126,7 +127,8 @@ fn main() {
 }
 
 fn benchmark_code(left: &Bdd, right: &Bdd) -> (usize, usize) {
-    let (created, counted) = apply(left, right);
+    let (_, stats) = apply_with_stats::<Or>(left, right);
+    let (created, counted) = (stats.nodes, stats.tasks);
     println!("Counted {} nodes, created {} nodes.", counted, created);
     (created, counted)
 }
\ No newline at end of file
@@ -1,5 +1,6 @@
 //use binary_decision_diagrams::v2::Bdd;
 use binary_decision_diagrams::v3::core::bdd::Bdd;
+use binary_decision_diagrams::v2::ops::Or;
 use std::convert::TryFrom;
 use std::time::SystemTime;
 
@@ -38,7 +39,7 @@ fn main() {
             let start = SystemTime::now();
             for _ in 0..1 {
                 //k += left.and(&right).node_count();
-                k += binary_decision_diagrams::v3::core::ooo::apply(&left, &right).node_count();
+                k += binary_decision_diagrams::v3::core::ooo::apply::<Or>(&left, &right).node_count();
                 //k += and_not_u48_function(&left, &right).node_count();
                 //k += gen_tasks(&left, &right, &mut task_cache, &mut node_cache);
             }
@@ -0,0 +1,303 @@
+//! Boolean expressions which can be converted into a `v2::Bdd`.
+//!
+//! An expression is a variable name (letters, digits and `_`), a constant `true` or `false`,
+//! a negation `!<expression>`, a named operation `<op>(<left>, <right>)` (e.g. `and_not(a, b)`,
+//! see `BoolOp::name`), a parenthesized expression, or two expressions combined using the infix
+//! operators `&` (and), `^` (xor), `|` (or), `=>` (imp) and `<=>` (iff), listed from the highest
+//! to the lowest precedence. Negation binds stronger than all infix operators, and all infix
+//! operators are left-associative.
+//!
+//! The `Display` implementation writes the expression such that it can be parsed again
+//! by `BooleanExpression::try_from`.
+
+use crate::v2::{Bdd, BoolOp, VariableId};
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt::{Display, Formatter};
+
+/// An abstract syntax tree of a Boolean expression (see module documentation).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BooleanExpression {
+    Const(bool),
+    Variable(String),
+    Not(Box<BooleanExpression>),
+    Binary(BoolOp, Box<BooleanExpression>, Box<BooleanExpression>),
+}
+
+/// **(internal)** A token of an expression.
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Identifier(String),
+    Symbol(&'static str),
+}
+
+/// **(internal)** The infix operators, from the lowest to the highest precedence.
+const PRECEDENCE: [(&str, BoolOp); 5] = [
+    ("<=>", BoolOp::Iff),
+    ("=>", BoolOp::Imp),
+    ("|", BoolOp::Or),
+    ("^", BoolOp::Xor),
+    ("&", BoolOp::And),
+];
+
+/// **(internal)** All symbols of the language. Longer symbols go first, such that `<=>`
+/// is not split.
+const SYMBOLS: [&str; 9] = ["<=>", "=>", "&", "|", "^", "!", "(", ")", ","];
+
+impl BooleanExpression {
+    /// The names of all variables used in this expression, sorted and without duplicates.
+    pub fn variables(&self) -> Vec<String> {
+        let mut result = Vec::new();
+        let mut stack = vec![self];
+        while let Some(expression) = stack.pop() {
+            match expression {
+                BooleanExpression::Const(_) => (),
+                BooleanExpression::Variable(name) => result.push(name.clone()),
+                BooleanExpression::Not(inner) => stack.push(inner),
+                BooleanExpression::Binary(_, left, right) => {
+                    stack.push(left);
+                    stack.push(right);
+                }
+            }
+        }
+        result.sort();
+        result.dedup();
+        result
+    }
+}
+
+impl TryFrom<&str> for BooleanExpression {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let tokens = tokenize(value)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+        };
+        let result = parser.expression(0)?;
+        if parser.position < tokens.len() {
+            return Err(parser.error("Expected an operator"));
+        }
+        Ok(result)
+    }
+}
+
+impl Display for BooleanExpression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            BooleanExpression::Const(value) => write!(f, "{}", value),
+            BooleanExpression::Variable(name) => write!(f, "{}", name),
+            BooleanExpression::Not(inner) => write!(f, "!{}", inner),
+            BooleanExpression::Binary(op, left, right) => {
+                match PRECEDENCE.iter().find(|(_, it)| it == op) {
+                    Some((symbol, _)) => write!(f, "({} {} {})", left, symbol, right),
+                    None => write!(f, "{}({}, {})", op.name(), left, right),
+                }
+            }
+        }
+    }
+}
+
+impl Bdd {
+    /// Build the `Bdd` of the given `expression`, where the variable `variable_order[i]`
+    /// is represented by `VariableId::from(i)`.
+    ///
+    /// The result has exactly `variable_order.len()` variables. Fails if the expression uses
+    /// a variable that is not in the `variable_order`, or if there are too many variables.
+    pub fn from_expression(
+        expression: &BooleanExpression,
+        variable_order: &[&str],
+    ) -> Result<Bdd, String> {
+        let variable_count = u16::try_from(variable_order.len())
+            .map_err(|_| format!("Too many variables: {}.", variable_order.len()))?;
+        let mut result = build(expression, variable_order)?;
+        result.update_variable_count(variable_count);
+        Ok(result)
+    }
+}
+
+/// **(internal)** Recursively build the `Bdd` of the `expression` using the `apply`
+/// functions of the sub-expressions.
+fn build(expression: &BooleanExpression, variable_order: &[&str]) -> Result<Bdd, String> {
+    Ok(match expression {
+        BooleanExpression::Const(false) => Bdd::new_false(),
+        BooleanExpression::Const(true) => Bdd::new_true(),
+        BooleanExpression::Variable(name) => {
+            let index = variable_order
+                .iter()
+                .position(|it| it == name)
+                .ok_or_else(|| format!("Unknown variable `{}`.", name))?;
+            Bdd::new_variable(VariableId::from(index as u16))
+        }
+        BooleanExpression::Not(inner) => build(inner, variable_order)?.not(),
+        BooleanExpression::Binary(op, left, right) => {
+            let left = build(left, variable_order)?;
+            let right = build(right, variable_order)?;
+            left.apply_op(*op, &right)
+        }
+    })
+}
+
+/// **(internal)** Split the `expression` into tokens.
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = expression.trim_start();
+    while !rest.is_empty() {
+        if let Some(symbol) = SYMBOLS.iter().find(|s| rest.starts_with(*s)) {
+            tokens.push(Token::Symbol(symbol));
+            rest = &rest[symbol.len()..];
+        } else {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            if end == 0 {
+                let unexpected = rest.chars().next().unwrap_or_default();
+                return Err(format!("Unexpected character `{}`.", unexpected));
+            }
+            tokens.push(Token::Identifier(rest[..end].to_string()));
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+/// **(internal)** A recursive descent parser over a list of tokens.
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl Parser<'_> {
+    /// Parse an expression whose operators have at least the given precedence `level`.
+    fn expression(&mut self, level: usize) -> Result<BooleanExpression, String> {
+        if level == PRECEDENCE.len() {
+            return self.unary();
+        }
+        let (symbol, op) = PRECEDENCE[level];
+        let mut result = self.expression(level + 1)?;
+        while self.peek() == Some(&Token::Symbol(symbol)) {
+            self.position += 1;
+            let right = self.expression(level + 1)?;
+            result = BooleanExpression::Binary(op, Box::new(result), Box::new(right));
+        }
+        Ok(result)
+    }
+
+    fn unary(&mut self) -> Result<BooleanExpression, String> {
+        if self.peek() == Some(&Token::Symbol("!")) {
+            self.position += 1;
+            return Ok(BooleanExpression::Not(Box::new(self.unary()?)));
+        }
+        if self.peek() == Some(&Token::Symbol("(")) {
+            self.position += 1;
+            let result = self.expression(0)?;
+            self.expect(")")?;
+            return Ok(result);
+        }
+        let name = match self.peek() {
+            Some(Token::Identifier(name)) => name.clone(),
+            _ => return Err(self.error("Expected a variable or a constant")),
+        };
+        self.position += 1;
+        if self.peek() == Some(&Token::Symbol("(")) {
+            let op = BoolOp::try_from(name.as_str())?;
+            self.expect("(")?;
+            let left = self.expression(0)?;
+            self.expect(",")?;
+            let right = self.expression(0)?;
+            self.expect(")")?;
+            return Ok(BooleanExpression::Binary(
+                op,
+                Box::new(left),
+                Box::new(right),
+            ));
+        }
+        Ok(match name.as_str() {
+            "true" => BooleanExpression::Const(true),
+            "false" => BooleanExpression::Const(false),
+            _ => BooleanExpression::Variable(name),
+        })
+    }
+
+    fn expect(&mut self, symbol: &'static str) -> Result<(), String> {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expected `{}`", symbol)))
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("{} at token {}.", message, self.position + 1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::expressions::BooleanExpression;
+    use crate::v2::{Bdd, BoolOp, VariableId};
+    use core::convert::TryFrom;
+
+    #[test]
+    pub fn expression_parser_test() {
+        let expression = BooleanExpression::try_from("!a & b | c <=> and_not(a, !true)").unwrap();
+        let var = |name: &str| Box::new(BooleanExpression::Variable(name.to_string()));
+        let not_a = Box::new(BooleanExpression::Not(var("a")));
+        let left = BooleanExpression::Binary(
+            BoolOp::Or,
+            Box::new(BooleanExpression::Binary(BoolOp::And, not_a, var("b"))),
+            var("c"),
+        );
+        let not_true = BooleanExpression::Not(Box::new(BooleanExpression::Const(true)));
+        let right = BooleanExpression::Binary(BoolOp::AndNot, var("a"), Box::new(not_true));
+        let expected = BooleanExpression::Binary(BoolOp::Iff, Box::new(left), Box::new(right));
+        assert_eq!(expected, expression);
+        assert_eq!(vec!["a", "b", "c"], expression.variables());
+
+        // The written expression is parsed back into the same tree.
+        let written = expression.to_string();
+        assert_eq!("(((!a & b) | c) <=> and_not(a, !true))", written);
+        assert_eq!(
+            expression,
+            BooleanExpression::try_from(written.as_str()).unwrap()
+        );
+
+        for invalid in ["", "a &", "(a | b", "a b", "a $ b", "foo(a, b)", "!"] {
+            assert!(BooleanExpression::try_from(invalid).is_err());
+        }
+    }
+
+    #[test]
+    pub fn from_expression_test() {
+        let v: Vec<Bdd> = (0..3u16)
+            .map(|v| Bdd::new_variable(VariableId::from(v)))
+            .collect();
+        let expression = BooleanExpression::try_from("(x => y) & !(z ^ x)").unwrap();
+        let bdd = Bdd::from_expression(&expression, &["x", "y", "z"]).unwrap();
+        let expected = v[0].imp(&v[1]).and(&v[2].xor(&v[0]).not());
+        assert!(bdd.semantic_eq(&expected));
+        assert_eq!(3, bdd.variable_count());
+
+        // The variable order decides the variable ids, unused variables still count.
+        let bdd = Bdd::from_expression(&expression, &["z", "x", "y", "w"]).unwrap();
+        let expected = v[1].imp(&v[2]).and(&v[0].xor(&v[1]).not());
+        assert!(bdd.semantic_eq(&expected));
+        assert_eq!(4, bdd.variable_count());
+
+        let tautology = BooleanExpression::try_from("x | !x").unwrap();
+        let tautology = Bdd::from_expression(&tautology, &["x"]).unwrap();
+        assert!(tautology.semantic_eq(&Bdd::new_true()));
+        assert!(Bdd::from_expression(&expression, &["x", "y"]).is_err());
+    }
+}
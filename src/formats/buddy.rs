@@ -0,0 +1,199 @@
+//! The text format of the `bdd_save` and `bdd_load` functions of BuDDy.
+//!
+//! The file starts with the number of decision nodes and the number of variables, followed
+//! by the level of every variable (the variable ordering). Then, every decision node is
+//! written on a separate line as `id variable low high`, where `low` and `high` are ids of
+//! previously written nodes, or `0`/`1` for the terminals. The root is always the last node.
+//! A constant function `c` is written simply as `0 0 c`.
+//!
+//! A `v4::core::Bdd` always uses the identity variable ordering, hence files with any other
+//! ordering are rejected.
+
+use crate::v4::core::{Bdd, Node, NodeIndex, Variable};
+use crate::{FromIndex, IntoIndex};
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+/// Write the given `Bdd` over `variable_count` variables in the BuDDy text format.
+///
+/// Fails if the `Bdd` uses a variable which is not smaller than `variable_count`.
+pub fn write(bdd: &Bdd, variable_count: u16) -> Result<String, String> {
+    if bdd.is_constant() {
+        return Ok(format!("0 0 {}\n", if bdd.is_one() { 1 } else { 0 }));
+    }
+    let nodes = bdd.as_node_slice();
+    for node in nodes.iter().filter(|it| !it.is_terminal()) {
+        let variable = u32::from(node.get_variable());
+        if variable >= u32::from(variable_count) {
+            return Err(format!(
+                "Variable {} is not one of the {} variables.",
+                variable, variable_count
+            ));
+        }
+    }
+
+    // BuDDy expects children before parents, so the nodes are written in DFS postorder.
+    let mut postorder = Vec::with_capacity(nodes.len());
+    let mut visited = vec![false; nodes.len()];
+    visited[0] = true;
+    visited[1] = true;
+    let mut stack = vec![(bdd.get_root_index(), false)];
+    while let Some((index, expanded)) = stack.pop() {
+        if expanded {
+            postorder.push(index);
+        } else if !visited[index.into_index()] {
+            visited[index.into_index()] = true;
+            let node = bdd.get_node(index);
+            stack.push((index, true));
+            stack.push((node.get_high_link(), false));
+            stack.push((node.get_low_link(), false));
+        }
+    }
+
+    let mut output = String::with_capacity(32 * (postorder.len() + 1));
+    // Writing into a `String` never fails.
+    let _ = writeln!(output, "{} {}", postorder.len(), variable_count);
+    for level in 0..variable_count {
+        let _ = write!(output, "{} ", level);
+    }
+    output.push('\n');
+    for index in postorder {
+        let (variable, low, high) = bdd.get_node(index).unpack();
+        let _ = writeln!(
+            output,
+            "{} {} {} {}",
+            u64::from(index),
+            u32::from(variable),
+            u64::from(low),
+            u64::from(high)
+        );
+    }
+    Ok(output)
+}
+
+/// Read a `Bdd` from the BuDDy text format. Returns the `Bdd` together with its number
+/// of variables (which is zero for constants).
+pub fn read(data: &str) -> Result<(Bdd, u16), String> {
+    let mut numbers = data.split_whitespace().map(|item| {
+        item.parse::<u64>()
+            .map_err(|_| format!("Invalid numeral `{}`.", item))
+    });
+    let mut next = move || {
+        numbers
+            .next()
+            .unwrap_or_else(|| Err(String::from("Unexpected end of input.")))
+    };
+
+    let node_count = next()?;
+    let variable_count = next()?;
+    if node_count == 0 && variable_count == 0 {
+        return match next()? {
+            0 => Ok((Bdd::new_zero(), 0)),
+            1 => Ok((Bdd::new_one(), 0)),
+            value => Err(format!("Invalid constant `{}`.", value)),
+        };
+    }
+    if variable_count > u64::from(u16::MAX) {
+        return Err(format!("Too many variables: {}.", variable_count));
+    }
+    for variable in 0..variable_count {
+        let level = next()?;
+        if level != variable {
+            return Err(format!(
+                "Variable {} is at level {}, only the identity ordering is supported.",
+                variable, level
+            ));
+        }
+    }
+
+    // Every node needs at least 8 bytes of input, which limits the reserved capacity.
+    let capacity = node_count.min(data.len() as u64 / 8) as usize;
+    let mut nodes = Vec::with_capacity(capacity + 2);
+    nodes.push(Node::ZERO);
+    nodes.push(Node::ONE);
+    let mut ids: BTreeMap<u64, NodeIndex> = BTreeMap::new();
+    let resolve = |ids: &BTreeMap<u64, NodeIndex>, id: u64| match id {
+        0 => Ok(NodeIndex::ZERO),
+        1 => Ok(NodeIndex::ONE),
+        id => ids
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| format!("Node {} is used before it is defined.", id)),
+    };
+    for _ in 0..node_count {
+        let id = next()?;
+        let variable = next()?;
+        let low = resolve(&ids, next()?)?;
+        let high = resolve(&ids, next()?)?;
+        if variable >= variable_count {
+            return Err(format!(
+                "Variable {} is not one of the {} variables.",
+                variable, variable_count
+            ));
+        }
+        if id < 2 || ids.contains_key(&id) {
+            return Err(format!("Duplicate or invalid node id {}.", id));
+        }
+        ids.insert(id, NodeIndex::from_index(nodes.len()));
+        let variable = Variable::from(variable as u32);
+        nodes.push(Node::pack(variable, low, high));
+    }
+    if next().is_ok() {
+        return Err(String::from("Unexpected data after the last node."));
+    }
+    if let Some(error) = Bdd::check_consistency_errors(&nodes) {
+        return Err(error);
+    }
+    let mut bdd = unsafe { Bdd::from_raw_parts(u32::MAX, nodes) };
+    bdd.recompute_height();
+    Ok((bdd, variable_count as u16))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read, write};
+    use crate::v4::core::Bdd;
+    use core::convert::TryFrom;
+
+    #[test]
+    pub fn buddy_round_trip_test() {
+        // (x0 & x2) | x1, as saved by BuDDy (node ids are arbitrary).
+        let data = "3 3\n0 1 2 \n17 2 0 1\n18 1 17 1\n19 0 18 1\n";
+        let (bdd, variable_count) = read(data).unwrap();
+        assert_eq!(3, variable_count);
+        let expected = Bdd::try_from("0,0,0|0,1,1|2,0,1|1,2,1|0,3,1|").unwrap();
+        assert_eq!(expected.as_node_slice(), bdd.as_node_slice());
+
+        let written = write(&bdd, 3).unwrap();
+        assert_eq!("3 3\n0 1 2 \n2 2 0 1\n3 1 2 1\n4 0 3 1\n", written);
+        let (loaded, _) = read(&written).unwrap();
+        assert_eq!(bdd.as_node_slice(), loaded.as_node_slice());
+        assert!(write(&bdd, 2).is_err());
+
+        // Constants.
+        assert_eq!("0 0 0\n", write(&Bdd::new_zero(), 4).unwrap());
+        assert_eq!("0 0 1\n", write(&Bdd::new_one(), 4).unwrap());
+        let (one, variable_count) = read("0 0 1\n").unwrap();
+        assert!(one.is_one());
+        assert_eq!(0, variable_count);
+
+        // Malformed inputs.
+        for data in [
+            "",
+            "0 0 2",
+            "1 2\n1 0 \n2 0 0 1\n",
+            "1 2\n0 1 \n2 0 3 1\n",
+            "1 2\n0 1 \n2 2 0 1\n",
+            "2 2\n0 1 \n2 1 0 1\n2 0 2 1\n",
+            "2 2\n0 1 \n2 0 0 1\n3 1 2 1\n",
+            "1 2\n0 1 \n2 0 0 1\n5",
+            "1 2\n0 1 \n2 0 0",
+        ] {
+            assert!(read(data).is_err());
+        }
+    }
+}
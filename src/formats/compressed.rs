@@ -0,0 +1,229 @@
+//! A compact binary format for `v4::core::Bdd` objects.
+//!
+//! Generic compression tools do not work very well on BDD snapshots, because the links are
+//! (seemingly random) 64-bit integers. However, when the `Bdd` is sorted in DFS preorder
+//! (see `Bdd::sort_preorder`), most links point just a few nodes ahead of the current node,
+//! and most variables are very close to the variable of the previous node. This format
+//! exploits this by storing every value as a difference (delta) with respect to
+//! the current node, encoded as a variable-length integer (varint). The format still works
+//! for unsorted graphs, it just does not compress them as well.
+//!
+//! The layout is as follows (all numbers are LEB128 varints):
+//!  - The magic bytes `BDDC` followed by a single version byte.
+//!  - The number of nodes in the `Bdd` (including terminals).
+//!  - For every non-terminal node `i` (in the order of the `Bdd`): The zig-zag encoded
+//!    difference between its variable and the variable of node `i - 1` (for the first node,
+//!    the difference is computed against `0`), followed by the low and the high link. A link
+//!    to a terminal node is saved as its index (`0` or `1`), any other link `l` is saved
+//!    as `2 + zigzag(l - i)`.
+
+use crate::v4::core::{Bdd, Node, NodeIndex, Variable};
+use crate::{FromIndex, IntoIndex};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+const MAGIC: &[u8; 4] = b"BDDC";
+const VERSION: u8 = 1;
+
+/// Encode the given `Bdd` into the compressed binary format.
+///
+/// For best results, the `Bdd` should be sorted in DFS preorder.
+pub fn encode(bdd: &Bdd) -> Vec<u8> {
+    let nodes = bdd.as_node_slice();
+    // Most preorder nodes fit into 3-4 bytes.
+    let mut output = Vec::with_capacity(8 + 4 * nodes.len());
+    output.extend_from_slice(MAGIC);
+    output.push(VERSION);
+    write_varint(&mut output, u64::from_index(nodes.len()));
+
+    let mut last_variable = 0u32;
+    for (i, node) in nodes.iter().enumerate().skip(terminal_count(nodes)) {
+        let (variable, low, high) = node.unpack();
+        let variable = u32::from(variable);
+        write_varint(&mut output, zigzag(i64::from(variable) - i64::from(last_variable)));
+        write_varint(&mut output, encode_link(i, low));
+        write_varint(&mut output, encode_link(i, high));
+        last_variable = variable;
+    }
+    output
+}
+
+/// Decode a `Bdd` from the compressed binary format.
+///
+/// The result is fully validated, so an invalid input produces an error instead of
+/// an inconsistent `Bdd`.
+pub fn decode(data: &[u8]) -> Result<Bdd, String> {
+    let mut position = 0;
+    let node_count = read_header(data, &mut position)?;
+    if node_count == 0 {
+        return Err("A BDD must contain at least one node.".to_string());
+    }
+    // Every node takes at least 3 bytes, which is a cheap sanity check
+    // before we allocate memory based on untrusted input.
+    if node_count > 2 && (node_count - 2) > (data.len() as u64) {
+        return Err(format!("Node count {} exceeds input size.", node_count));
+    }
+    let node_count = node_count.into_index();
+
+    let mut nodes = Vec::with_capacity(node_count);
+    nodes.push(Node::ZERO);
+    if node_count > 1 {
+        nodes.push(Node::ONE);
+    }
+    let mut last_variable = 0i64;
+    for i in nodes.len()..node_count {
+        let variable = last_variable + unzigzag(read_varint(data, &mut position)?);
+        if variable < 0 || variable >= i64::from(u32::MAX) {
+            return Err(format!("Invalid variable {} in node {}.", variable, i));
+        }
+        let low = decode_link(i, read_varint(data, &mut position)?)?;
+        let high = decode_link(i, read_varint(data, &mut position)?)?;
+        nodes.push(Node::pack(Variable::from(variable as u32), low, high));
+        last_variable = variable;
+    }
+    if position != data.len() {
+        return Err(format!("Unexpected {} bytes after the last node.", data.len() - position));
+    }
+
+    if let Some(error) = Bdd::check_consistency_errors(&nodes) {
+        Err(error)
+    } else {
+        Ok(Bdd::from_nodes(nodes))
+    }
+}
+
+/// True if the `data` start with the magic bytes of the compressed format.
+pub fn is_compressed(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// The node count stored in the header of the compressed `data`, read without
+/// decoding (or allocating) the nodes.
+pub fn header_node_count(data: &[u8]) -> Result<u64, String> {
+    read_header(data, &mut 0)
+}
+
+/// **(internal)** Check the magic bytes and the version, and read the node count.
+fn read_header(data: &[u8], position: &mut usize) -> Result<u64, String> {
+    if data.len() < 5 || !is_compressed(data) {
+        return Err("Invalid header: not a compressed BDD.".to_string());
+    }
+    if data[4] != VERSION {
+        return Err(format!("Unsupported format version {}.", data[4]));
+    }
+    *position = 5;
+    read_varint(data, position)
+}
+
+/// **(internal)** The number of terminal nodes at the beginning of the node slice.
+fn terminal_count(nodes: &[Node]) -> usize {
+    if nodes.len() > 1 {
+        2
+    } else {
+        1
+    }
+}
+
+fn encode_link(node: usize, link: NodeIndex) -> u64 {
+    let link = u64::from(link);
+    if link < 2 {
+        link
+    } else {
+        2 + zigzag((link as i64) - (node as i64))
+    }
+}
+
+fn decode_link(node: usize, value: u64) -> Result<NodeIndex, String> {
+    if value < 2 {
+        Ok(NodeIndex::from(value))
+    } else {
+        let link = (node as i64).checked_add(unzigzag(value - 2));
+        match link {
+            Some(link) if link >= 0 => Ok(NodeIndex::from(link as u64)),
+            _ => Err(format!("Invalid link in node {}.", node)),
+        }
+    }
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+fn write_varint(output: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        output.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    output.push(value as u8);
+}
+
+fn read_varint(data: &[u8], position: &mut usize) -> Result<u64, String> {
+    let mut result = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = match data.get(*position) {
+            Some(byte) => *byte,
+            None => return Err("Unexpected end of input.".to_string()),
+        };
+        *position += 1;
+        if shift == 63 && byte > 1 {
+            return Err("Varint overflow.".to_string());
+        }
+        result |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(result);
+        }
+        shift += 7;
+        if shift > 63 {
+            return Err("Varint overflow.".to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode, encode, unzigzag, zigzag};
+    use crate::v4::core::Bdd;
+    use core::convert::TryFrom;
+
+    #[test]
+    pub fn compressed_round_trip_test() {
+        for value in [0i64, 1, -1, 63, -64, i64::MAX, i64::MIN] {
+            assert_eq!(value, unzigzag(zigzag(value)));
+        }
+
+        let bdds = [
+            Bdd::new_zero(),
+            Bdd::new_one(),
+            Bdd::try_from("0,0,0|0,1,1|3,0,1|1,2,1|").unwrap(),
+            Bdd::try_from("0,0,0|0,1,1|2,1,0|4,0,1|1,2,3|").unwrap(),
+        ];
+        for bdd in bdds.iter() {
+            let bytes = encode(bdd);
+            let decoded = decode(&bytes).unwrap();
+            assert_eq!(bdd.as_node_slice(), decoded.as_node_slice());
+            assert_eq!(bdd.get_height(), decoded.get_height());
+        }
+    }
+
+    #[test]
+    pub fn compressed_invalid_input_test() {
+        let bdd = Bdd::try_from("0,0,0|0,1,1|3,0,1|1,2,1|").unwrap();
+        let bytes = encode(&bdd);
+        assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(decode(&bytes[1..]).is_err());
+        let mut extra = bytes.clone();
+        extra.push(0);
+        assert!(decode(&extra).is_err());
+        // Make the high link of the root point to the root itself.
+        let mut cycle = bytes;
+        let last = cycle.len() - 1;
+        cycle[last] = 2;
+        assert!(decode(&cycle).is_err());
+    }
+}
@@ -0,0 +1,247 @@
+//! The string and byte formats of the `biodivine-lib-bdd` crate.
+//!
+//! Both formats store the nodes of a `Bdd` in the order in which they appear in the node
+//! array, i.e. terminals first and the root last, which is exactly the layout of
+//! `v4::core::Bdd`. The only difference is that the terminal nodes of `biodivine-lib-bdd`
+//! carry the number of variables of the `Bdd` as their variable. This number is not part of
+//! a `v4::core::Bdd`, so it has to be supplied when exporting and it is returned when
+//! importing.
+//!
+//! The layout is as follows:
+//!  - String: Every node is written as `variable,low,high|`, and the whole string starts
+//!    with an extra `|` (e.g. `|3,0,0|3,1,1|1,0,1|`).
+//!  - Bytes: Every node is written as 10 bytes: The variable as `u16`, followed by the low
+//!    and the high link as `u32` (all little endian).
+
+use crate::v4::core::{Bdd, Node, NodeIndex, Variable};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt::Write;
+
+/// The number of bytes of one node in the byte format.
+const NODE_BYTES: usize = 10;
+
+/// Write the given `Bdd` over `variable_count` variables as a `biodivine-lib-bdd` string.
+///
+/// Fails if the `Bdd` uses a variable which is not smaller than `variable_count`.
+pub fn write_string(bdd: &Bdd, variable_count: u16) -> Result<String, String> {
+    let nodes = export_nodes(bdd, variable_count)?;
+    let mut output = String::with_capacity(1 + 16 * nodes.len());
+    output.push('|');
+    for (variable, low, high) in nodes {
+        // Writing into a `String` never fails.
+        let _ = write!(output, "{},{},{}|", variable, low, high);
+    }
+    Ok(output)
+}
+
+/// Read a `Bdd` from a `biodivine-lib-bdd` string. Returns the `Bdd` together with
+/// its number of variables.
+pub fn read_string(data: &str) -> Result<(Bdd, u16), String> {
+    let mut nodes = Vec::new();
+    for item in data.trim().split('|').filter(|s| !s.is_empty()) {
+        let mut values = item.split(',');
+        let (variable, low, high) = match (values.next(), values.next(), values.next()) {
+            (Some(variable), Some(low), Some(high)) if values.next().is_none() => {
+                (variable, low, high)
+            }
+            _ => return Err(format!("Unexpected node representation `{}`.", item)),
+        };
+        let variable = variable
+            .parse::<u16>()
+            .map_err(|_| format!("Invalid variable numeral `{}`.", variable))?;
+        let low = low
+            .parse::<u64>()
+            .map_err(|_| format!("Invalid pointer numeral `{}`.", low))?;
+        let high = high
+            .parse::<u64>()
+            .map_err(|_| format!("Invalid pointer numeral `{}`.", high))?;
+        nodes.push((variable, low, high));
+    }
+    import_nodes(nodes)
+}
+
+/// Write the given `Bdd` over `variable_count` variables in the `biodivine-lib-bdd`
+/// byte format.
+///
+/// Fails if the `Bdd` uses a variable which is not smaller than `variable_count`,
+/// or if it has too many nodes to be addressed by 32-bit links.
+pub fn write_bytes(bdd: &Bdd, variable_count: u16) -> Result<Vec<u8>, String> {
+    let nodes = export_nodes(bdd, variable_count)?;
+    if nodes.len() as u64 > u64::from(u32::MAX) + 1 {
+        return Err(format!(
+            "A BDD with {} nodes cannot be written in the byte format.",
+            nodes.len()
+        ));
+    }
+    let mut output = Vec::with_capacity(NODE_BYTES * nodes.len());
+    for (variable, low, high) in nodes {
+        output.extend_from_slice(&variable.to_le_bytes());
+        output.extend_from_slice(&(low as u32).to_le_bytes());
+        output.extend_from_slice(&(high as u32).to_le_bytes());
+    }
+    Ok(output)
+}
+
+/// Read a `Bdd` from the `biodivine-lib-bdd` byte format. Returns the `Bdd` together with
+/// its number of variables.
+pub fn read_bytes(data: &[u8]) -> Result<(Bdd, u16), String> {
+    let chunks = data.chunks_exact(NODE_BYTES);
+    if !chunks.remainder().is_empty() {
+        return Err(format!(
+            "Data length {} is not a multiple of {}.",
+            data.len(),
+            NODE_BYTES
+        ));
+    }
+    let nodes = chunks
+        .map(|chunk| {
+            let variable = u16::from_le_bytes([chunk[0], chunk[1]]);
+            let low = u32::from_le_bytes([chunk[2], chunk[3], chunk[4], chunk[5]]);
+            let high = u32::from_le_bytes([chunk[6], chunk[7], chunk[8], chunk[9]]);
+            (variable, u64::from(low), u64::from(high))
+        })
+        .collect();
+    import_nodes(nodes)
+}
+
+/// **(internal)** Translate the nodes of `bdd` into `(variable, low, high)` triples
+/// in which the terminals are labelled with `variable_count`.
+fn export_nodes(bdd: &Bdd, variable_count: u16) -> Result<Vec<(u16, u64, u64)>, String> {
+    let mut nodes = Vec::with_capacity(bdd.as_node_slice().len());
+    for node in bdd.as_node_slice() {
+        let (variable, low, high) = node.unpack();
+        let variable = if node.is_terminal() {
+            variable_count
+        } else {
+            match u16::try_from(u32::from(variable)) {
+                Ok(variable) if variable < variable_count => variable,
+                _ => {
+                    return Err(format!(
+                        "Variable {} is not one of the {} variables.",
+                        u32::from(variable),
+                        variable_count
+                    ))
+                }
+            }
+        };
+        nodes.push((variable, u64::from(low), u64::from(high)));
+    }
+    Ok(nodes)
+}
+
+/// **(internal)** Build a validated `Bdd` from `(variable, low, high)` triples in which
+/// the terminals are labelled with the number of variables.
+fn import_nodes(triples: Vec<(u16, u64, u64)>) -> Result<(Bdd, u16), String> {
+    let variable_count = match triples.first() {
+        Some((variable, _, _)) => *variable,
+        None => return Err(String::from("A BDD must contain at least one node.")),
+    };
+    let mut nodes = Vec::with_capacity(triples.len());
+    for (index, (variable, low, high)) in triples.into_iter().enumerate() {
+        if index < 2 {
+            let expected = index as u64;
+            if variable != variable_count || low != expected || high != expected {
+                return Err(format!(
+                    "Invalid terminal node `{},{},{}` ({} variables).",
+                    variable, low, high, variable_count
+                ));
+            }
+            nodes.push(if index == 0 { Node::ZERO } else { Node::ONE });
+        } else if variable >= variable_count {
+            return Err(format!(
+                "Variable {} is not one of the {} variables.",
+                variable, variable_count
+            ));
+        } else {
+            let variable = Variable::from(u32::from(variable));
+            nodes.push(Node::pack(
+                variable,
+                NodeIndex::from(low),
+                NodeIndex::from(high),
+            ));
+        }
+    }
+    if let Some(error) = Bdd::check_consistency_errors(&nodes) {
+        return Err(error);
+    }
+    let mut bdd = unsafe { Bdd::from_raw_parts(u32::MAX, nodes) };
+    bdd.recompute_height();
+    Ok((bdd, variable_count))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_bytes, read_string, write_bytes, write_string};
+    use crate::v4::core::Bdd;
+    use core::convert::TryFrom;
+
+    #[test]
+    pub fn lib_bdd_round_trip_test() {
+        let string = "|3,0,0|3,1,1|1,0,1|0,2,1|";
+        let (bdd, variable_count) = read_string(string).unwrap();
+        assert_eq!(3, variable_count);
+        let expected = Bdd::try_from("0,0,0|0,1,1|1,0,1|0,2,1|").unwrap();
+        assert_eq!(expected.as_node_slice(), bdd.as_node_slice());
+        assert_eq!(string, write_string(&bdd, 3).unwrap());
+
+        let bytes = write_bytes(&bdd, 3).unwrap();
+        assert_eq!(40, bytes.len());
+        let (loaded, variable_count) = read_bytes(&bytes).unwrap();
+        assert_eq!(3, variable_count);
+        assert_eq!(bdd.as_node_slice(), loaded.as_node_slice());
+
+        // Constants.
+        for constant in [Bdd::new_zero(), Bdd::new_one()] {
+            let string = write_string(&constant, 0).unwrap();
+            let (loaded, _) = read_string(&string).unwrap();
+            assert_eq!(constant.as_node_slice(), loaded.as_node_slice());
+        }
+        assert_eq!("|0,0,0|", write_string(&Bdd::new_zero(), 0).unwrap());
+
+        // Variables outside of the variable count cannot be exported.
+        assert!(write_string(&bdd, 1).is_err());
+        assert!(write_bytes(&bdd, 1).is_err());
+
+        // Malformed inputs.
+        for data in [
+            "",
+            "|3,0,0|3,1,1|1,0|",
+            "|3,0,0|3,1,1|x,0,1|",
+            "|3,0,0|2,1,1|1,0,1|",
+            "|3,0,0|3,0,0|1,0,1|",
+            "|3,0,0|3,1,1|4,0,1|",
+            "|3,0,0|3,1,1|1,0,3|",
+            "|3,0,0|3,1,1|0,0,1|1,2,1|",
+        ] {
+            assert!(read_string(data).is_err());
+        }
+        assert!(read_bytes(&bytes[1..]).is_err());
+        assert!(read_bytes(&[]).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "biodivine-lib-bdd")]
+    pub fn lib_bdd_compatibility_test() {
+        use biodivine_lib_bdd::{Bdd as LibBdd, BddVariableSet};
+
+        let variables = BddVariableSet::new_anonymous(5);
+        let v = variables.variables();
+        let lib_bdd = variables
+            .mk_var(v[0])
+            .and(&variables.mk_var(v[3]))
+            .or(&variables.mk_var(v[1]).xor(&variables.mk_var(v[4])));
+
+        let (bdd, variable_count) = read_string(&lib_bdd.to_string()).unwrap();
+        assert_eq!(5, variable_count);
+        assert_eq!(lib_bdd.to_string(), write_string(&bdd, 5).unwrap());
+        let (from_bytes, _) = read_bytes(&lib_bdd.to_bytes()).unwrap();
+        assert_eq!(bdd.as_node_slice(), from_bytes.as_node_slice());
+
+        let bytes = write_bytes(&bdd, 5).unwrap();
+        let restored = LibBdd::from_bytes(&mut &bytes[..]);
+        assert_eq!(lib_bdd, restored);
+    }
+}
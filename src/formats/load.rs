@@ -0,0 +1,226 @@
+//! Both supported formats (the text format of `Bdd::try_from` and the `compressed` binary
+//! format) can describe a `Bdd` which is much larger than the input itself, or which has
+//! an inconsistent link structure. The functions in this module check the size of
+//! the input and the declared number of nodes against `LoadLimits` *before* anything is
+//! allocated, and fully validate the result, so they are safe to use with user-supplied
+//! files.
+
+use crate::formats::compressed;
+use crate::v4::core::{Bdd, Node};
+use alloc::format;
+use alloc::string::String;
+use core::convert::TryFrom;
+use core::fmt::{Display, Formatter};
+use core::mem::size_of;
+
+/// Resource limits of `load_bytes` and `load_file`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct LoadLimits {
+    /// The largest accepted number of nodes (including terminals).
+    pub max_nodes: u64,
+    /// The largest amount of memory (in bytes) used by the input data together with
+    /// the nodes of the loaded `Bdd`.
+    pub max_memory: u64,
+}
+
+/// An error reported by `load_bytes` and `load_file`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum LoadError {
+    /// The file cannot be read.
+    Io(String),
+    /// Loading needs the given number of bytes, which exceeds `LoadLimits::max_memory`.
+    MemoryLimit(u64),
+    /// The input declares the given number of nodes, which exceeds `LoadLimits::max_nodes`.
+    NodeLimit(u64),
+    /// The input is not a valid `Bdd` in any of the supported formats.
+    Invalid(String),
+}
+
+impl LoadLimits {
+    /// Limits with the given number of nodes and bytes of memory.
+    pub const fn new(max_nodes: u64, max_memory: u64) -> LoadLimits {
+        LoadLimits {
+            max_nodes,
+            max_memory,
+        }
+    }
+
+    /// **(internal)** Check that an input of `input_size` bytes which declares
+    /// `node_count` nodes fits into these limits.
+    fn check(&self, input_size: u64, node_count: u64) -> Result<(), LoadError> {
+        if node_count > self.max_nodes {
+            return Err(LoadError::NodeLimit(node_count));
+        }
+        let required = node_count
+            .saturating_mul(size_of::<Node>() as u64)
+            .saturating_add(input_size);
+        if required > self.max_memory {
+            return Err(LoadError::MemoryLimit(required));
+        }
+        Ok(())
+    }
+}
+
+/// By default, at most `2^24` nodes and `1GiB` of memory are allowed.
+impl Default for LoadLimits {
+    fn default() -> Self {
+        LoadLimits::new(1 << 24, 1 << 30)
+    }
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            LoadError::Io(message) => write!(f, "Cannot read input: {}", message),
+            LoadError::MemoryLimit(bytes) => {
+                write!(
+                    f,
+                    "Loading requires {} bytes, which exceeds the limit.",
+                    bytes
+                )
+            }
+            LoadError::NodeLimit(count) => {
+                write!(f, "Node count {} exceeds the limit.", count)
+            }
+            LoadError::Invalid(message) => write!(f, "Invalid input: {}", message),
+        }
+    }
+}
+
+impl From<LoadError> for String {
+    fn from(value: LoadError) -> Self {
+        format!("{}", value)
+    }
+}
+
+/// Load a `Bdd` from `data` in the compressed binary format, or in the text format
+/// (the format is detected automatically).
+pub fn load_bytes(data: &[u8], limits: &LoadLimits) -> Result<Bdd, LoadError> {
+    let input_size = data.len() as u64;
+    if input_size > limits.max_memory {
+        return Err(LoadError::MemoryLimit(input_size));
+    }
+    if compressed::is_compressed(data) {
+        let node_count = compressed::header_node_count(data).map_err(LoadError::Invalid)?;
+        limits.check(input_size, node_count)?;
+        compressed::decode(data).map_err(LoadError::Invalid)
+    } else {
+        let text = core::str::from_utf8(data)
+            .map_err(|_| LoadError::Invalid(String::from("Not a compressed or text BDD.")))?;
+        let text = text.trim();
+        // Every node of the text format is one non-empty item separated by `|`.
+        let node_count = text.split('|').filter(|s| !s.is_empty()).count() as u64;
+        if node_count == 0 {
+            return Err(LoadError::Invalid(String::from(
+                "A BDD must contain at least one node.",
+            )));
+        }
+        limits.check(input_size, node_count)?;
+        Bdd::try_from(text).map_err(LoadError::Invalid)
+    }
+}
+
+/// Load a `Bdd` from the file at the given `path` (see `load_bytes`).
+///
+/// Files which are larger than `LoadLimits::max_memory` are rejected before they are read.
+#[cfg(feature = "std")]
+pub fn load_file<P: AsRef<std::path::Path>>(
+    path: P,
+    limits: &LoadLimits,
+) -> Result<Bdd, LoadError> {
+    use std::io::Read;
+
+    let io_error = |e: std::io::Error| LoadError::Io(format!("{}", e));
+    let file = std::fs::File::open(path).map_err(io_error)?;
+    let file_size = file.metadata().map_err(io_error)?.len();
+    if file_size > limits.max_memory {
+        return Err(LoadError::MemoryLimit(file_size));
+    }
+    // The file can grow while it is being read, so the limit is enforced again.
+    let mut data = std::vec::Vec::with_capacity(file_size as usize);
+    file.take(limits.max_memory.saturating_add(1))
+        .read_to_end(&mut data)
+        .map_err(io_error)?;
+    load_bytes(&data, limits)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{load_bytes, LoadError, LoadLimits};
+    use crate::formats::compressed;
+    use crate::v4::core::Bdd;
+    use core::convert::TryFrom;
+
+    #[test]
+    pub fn load_limits_test() {
+        let text = "0,0,0|0,1,1|3,0,1|1,2,1|";
+        let bdd = Bdd::try_from(text).unwrap();
+        let binary = compressed::encode(&bdd);
+        let limits = LoadLimits::default();
+        for data in [text.as_bytes(), binary.as_slice()] {
+            let loaded = load_bytes(data, &limits).unwrap();
+            assert_eq!(bdd.as_node_slice(), loaded.as_node_slice());
+            assert!(matches!(
+                load_bytes(data, &LoadLimits::new(3, 1 << 20)),
+                Err(LoadError::NodeLimit(4))
+            ));
+            assert!(matches!(
+                load_bytes(data, &LoadLimits::new(4, 64)),
+                Err(LoadError::MemoryLimit(_))
+            ));
+        }
+
+        // A crafted header with 2^48 nodes is rejected before anything is allocated.
+        let mut crafted = b"BDDC\x01".to_vec();
+        let mut count = 1u64 << 48;
+        while count >= 0x80 {
+            crafted.push((count as u8) | 0x80);
+            count >>= 7;
+        }
+        crafted.push(count as u8);
+        assert!(matches!(
+            load_bytes(&crafted, &limits),
+            Err(LoadError::NodeLimit(count)) if count == 1 << 48
+        ));
+        // Without limits, the header is still checked against the input size.
+        let unlimited = LoadLimits::new(u64::MAX, u64::MAX);
+        assert!(matches!(
+            load_bytes(&crafted, &unlimited),
+            Err(LoadError::Invalid(_))
+        ));
+
+        // Malformed inputs are reported as invalid.
+        for data in [
+            &b""[..],
+            b"\xff\xfe",
+            b"0,0,0|0,1,1|1,0,4|",
+            b"0,0,0|0,1,1|1,2,1|",
+        ] {
+            assert!(matches!(
+                load_bytes(data, &limits),
+                Err(LoadError::Invalid(_))
+            ));
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    pub fn load_file_test() {
+        use super::load_file;
+
+        // Files are read with the same limits as byte slices.
+        let text = "0,0,0|0,1,1|3,0,1|1,2,1|";
+        let bdd = Bdd::try_from(text).unwrap();
+        let limits = LoadLimits::default();
+        let path = std::env::temp_dir().join("load_limits_test.bdd");
+        std::fs::write(&path, text).unwrap();
+        let loaded = load_file(&path, &limits).unwrap();
+        assert_eq!(bdd.as_node_slice(), loaded.as_node_slice());
+        assert!(matches!(
+            load_file(&path, &LoadLimits::new(4, 8)),
+            Err(LoadError::MemoryLimit(_))
+        ));
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(load_file(&path, &limits), Err(LoadError::Io(_))));
+    }
+}
@@ -0,0 +1,15 @@
+/// A compact binary format based on delta-encoded varints (see module documentation).
+pub mod compressed;
+
+/// The string and byte formats of the `biodivine-lib-bdd` crate.
+pub mod lib_bdd;
+
+/// The `bdd_save` text format of BuDDy.
+pub mod buddy;
+
+/// Loading of `Bdd` files from untrusted sources with enforced resource limits.
+mod load;
+
+#[cfg(feature = "std")]
+pub use load::load_file;
+pub use load::{load_bytes, LoadError, LoadLimits};
@@ -1,5 +1,18 @@
 // Force the unsafe code in unsafe functions to be properly annotated as such.
 #![warn(unsafe_op_in_unsafe_fn)]
+// Without the `std` feature, the library only depends on `core` and `alloc`.
+//
+// Supported feature combinations (each should build without warnings):
+//  - default (`std` + `bench`): everything, Linux only.
+//  - `--no-default-features --features std`: the portable library.
+//  - `--no-default-features`: `no_std + alloc`. Excludes `BddPool`, `v2::reorder` and
+//    operation timing (see `logging`).
+//  - `--no-default-features --features wasm[-js]`: the `wasm32` build.
+//  - `log` and `huge-pages` can be added to any of the above, `cudd-interop`,
+//    `sylvan-interop` and `mmap` to any build with `std`.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
 
 #[macro_use]
 extern crate static_assertions;
@@ -10,8 +23,14 @@ extern crate static_assertions;
 //
 // For the conversion, please use exclusively the `IntoIndex` trait defined below,
 // such that in the future, we can track where the conversions happen.
+#[cfg(not(feature = "wasm"))]
 assert_eq_size!(usize, u64);
 
+// The `wasm` build is the only exception, since `wasm32` is a 32-bit target. This is still
+// fine, because a valid index can never exceed the (32-bit) addressable memory anyway.
+#[cfg(feature = "wasm")]
+const_assert!(core::mem::size_of::<usize>() >= 4);
+
 trait IntoIndex {
     fn into_index(self) -> usize;
 }
@@ -34,6 +53,7 @@ impl IntoIndex for u32 {
 
 impl IntoIndex for u64 {
     fn into_index(self) -> usize {
+        debug_assert!(self <= usize::MAX as u64);
         self as usize
     }
 }
@@ -44,10 +64,58 @@ impl FromIndex for u64 {
     }
 }
 
+// Must be declared before the other modules, so that the logging macros are visible in them.
+#[macro_use]
+mod logging;
+
+/// **(internal)** The shared 48/16-bit layout of packed node ids and variables.
+mod packing;
+
+/// **(internal)** Architecture-specific intrinsics, with portable fallbacks.
+mod arch;
+
+/// The recommended public API: a `Bdd` which dispatches to the best internal engine.
+pub mod bdd;
+
+/// Reachability and fixed-point algorithms of symbolic model checking (see `bdd::Bdd`).
+pub mod symbolic;
+
+// The generation modules stay public for the benchmarks and binaries, but they are hidden
+// from the documentation, so that `bdd` is the only documented `Bdd` API.
+
+/// **(internal)** The current main engine (used by `bdd::Bdd`). Prefer the `bdd` module.
+#[doc(hidden)]
 pub mod v2;
+/// **(internal)** An experimental generation of the library. Prefer the `bdd` module.
+#[doc(hidden)]
 pub mod v3;
+/// **(internal)** The stack-based apply and its data structures. Prefer the `bdd` module.
+#[doc(hidden)]
 pub mod v4;
+/// **(internal)** Complement-edge `Bdds`. Prefer the `bdd` module.
+#[doc(hidden)]
+pub mod v5;
 
+/// **(internal)** A `Bdd` with a machine-friendly node layout. Prefer the `bdd` module.
+#[doc(hidden)]
 pub mod machine;
 
-pub mod perf_testing;
\ No newline at end of file
+pub mod zdd;
+
+/// Boolean expressions which can be converted into a `v2::Bdd`.
+pub mod expressions;
+
+/// Serialization formats for `v4::core::Bdd` objects.
+pub mod formats;
+
+#[cfg(feature = "bench")]
+pub mod perf_testing;
+
+/// Access to the benchmark corpus, which is generated when it is not available
+/// (requires the `std` feature).
+#[cfg(any(feature = "std", test))]
+pub mod testing;
+
+/// A JS-friendly API for using the library in the browser (requires the `wasm-js` feature).
+#[cfg(feature = "wasm-js")]
+pub mod wasm;
\ No newline at end of file
@@ -0,0 +1,131 @@
+//! **(internal)** Diagnostic output of the library.
+//!
+//! With the `log` feature, all diagnostics are forwarded to the `log` facade, so the embedding
+//! application decides where (and whether) they end up. Without the feature, the macros below
+//! compile to nothing, but their arguments are still type-checked.
+
+#[cfg(feature = "log")]
+#[allow(unused_macros)]
+macro_rules! trace {
+    ($($arg:tt)*) => { log::trace!(target: "binary_decision_diagrams", $($arg)*) };
+}
+
+#[cfg(not(feature = "log"))]
+#[allow(unused_macros)]
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        if false {
+            let _ = format_args!($($arg)*);
+        }
+    };
+}
+
+#[cfg(feature = "log")]
+#[allow(unused_macros)]
+macro_rules! debug {
+    ($($arg:tt)*) => { log::debug!(target: "binary_decision_diagrams", $($arg)*) };
+}
+
+#[cfg(not(feature = "log"))]
+#[allow(unused_macros)]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if false {
+            let _ = format_args!($($arg)*);
+        }
+    };
+}
+
+/// **(internal)** A marker of a single `Bdd` operation. It is created when the operation
+/// starts and reports the operation kind, operand sizes, result size and duration (as a single
+/// `debug` record) once the operation finishes.
+///
+/// Without the `log` feature (or when `debug` output is disabled), this is essentially free.
+/// The duration is only measured with the `std` feature, and not in the `wasm` build, because
+/// `wasm32` has no system clock.
+///
+/// The span also reports the operation to the `v2::Profiler` recording in the current thread.
+pub(crate) struct OperationSpan {
+    #[cfg(feature = "log")]
+    inner: Option<SpanData>,
+    /// The operation and its start time, if a `v2::Profiler` is recording.
+    #[cfg(all(feature = "std", not(feature = "wasm")))]
+    profile: Option<(&'static str, std::time::Instant)>,
+}
+
+#[cfg(feature = "log")]
+struct SpanData {
+    operation: &'static str,
+    left_size: usize,
+    right_size: usize,
+    #[cfg(all(feature = "std", not(feature = "wasm")))]
+    start: std::time::Instant,
+}
+
+impl OperationSpan {
+    /// Start a new operation with the given name and operand sizes (in nodes).
+    #[inline]
+    pub(crate) fn start(operation: &'static str, left_size: usize, right_size: usize) -> Self {
+        #[cfg(all(feature = "std", not(feature = "wasm")))]
+        let profile = if crate::v2::_impl_::profiler::is_recording() {
+            Some((operation, std::time::Instant::now()))
+        } else {
+            None
+        };
+        #[cfg(feature = "log")]
+        {
+            let inner = if log::log_enabled!(target: "binary_decision_diagrams", log::Level::Debug) {
+                Some(SpanData {
+                    operation,
+                    left_size,
+                    right_size,
+                    #[cfg(all(feature = "std", not(feature = "wasm")))]
+                    start: std::time::Instant::now(),
+                })
+            } else {
+                None
+            };
+            OperationSpan {
+                inner,
+                #[cfg(all(feature = "std", not(feature = "wasm")))]
+                profile,
+            }
+        }
+        #[cfg(not(feature = "log"))]
+        {
+            let _ = (operation, left_size, right_size);
+            OperationSpan {
+                #[cfg(all(feature = "std", not(feature = "wasm")))]
+                profile,
+            }
+        }
+    }
+
+    /// Finish the operation, given the size of its result (in nodes).
+    #[inline]
+    pub(crate) fn finish(self, result_size: usize) {
+        #[cfg(all(feature = "std", not(feature = "wasm")))]
+        if let Some((operation, start)) = self.profile {
+            crate::v2::_impl_::profiler::report(operation, start.elapsed(), result_size);
+        }
+        #[cfg(feature = "log")]
+        if let Some(data) = self.inner {
+            #[cfg(all(feature = "std", not(feature = "wasm")))]
+            debug!(
+                "{}: {} x {} -> {} nodes in {:?}.",
+                data.operation,
+                data.left_size,
+                data.right_size,
+                result_size,
+                data.start.elapsed()
+            );
+            #[cfg(not(all(feature = "std", not(feature = "wasm"))))]
+            debug!(
+                "{}: {} x {} -> {} nodes.",
+                data.operation, data.left_size, data.right_size, result_size
+            );
+        }
+        #[cfg(not(feature = "log"))]
+        let _ = result_size;
+    }
+}
@@ -0,0 +1,112 @@
+use super::Bdd;
+use crate::machine::{BddNode, NodeId, VariableId};
+use crate::v4::core::{Bdd as Bdd4, Node, NodeIndex, Variable};
+use core::cmp::max;
+use core::convert::TryFrom;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Convert a `machine::Bdd` into a `v4::core::Bdd`. The variables are widened to 32 bits.
+///
+/// The conversion fails if the `machine::Bdd` does not satisfy the invariants required by
+/// `v4::core::Bdd::check_consistency_errors` (these cannot be fully enforced when the `Bdd`
+/// is constructed using `Bdd::push_node`).
+impl TryFrom<&Bdd> for Bdd4 {
+    type Error = String;
+
+    fn try_from(bdd: &Bdd) -> Result<Self, Self::Error> {
+        let nodes: Vec<Node> = bdd
+            .nodes
+            .iter()
+            .map(|node| {
+                if *node == BddNode::ZERO {
+                    Node::ZERO
+                } else if *node == BddNode::ONE {
+                    Node::ONE
+                } else {
+                    let (variable, low, high) = node.unpack();
+                    Node::pack(
+                        Variable::from(u32::from(u16::from(variable))),
+                        NodeIndex::from(low.into_u64()),
+                        NodeIndex::from(high.into_u64()),
+                    )
+                }
+            })
+            .collect();
+        if let Some(error) = Bdd4::check_consistency_errors(&nodes) {
+            Err(error)
+        } else {
+            Ok(Bdd4::from_nodes(nodes))
+        }
+    }
+}
+
+/// Convert a `v4::core::Bdd` into a `machine::Bdd`.
+///
+/// The conversion fails if the `Bdd` uses variables that do not fit into `machine::VariableId`,
+/// or if it has more than `2^48 - 1` nodes.
+impl TryFrom<&Bdd4> for Bdd {
+    type Error = String;
+
+    fn try_from(bdd: &Bdd4) -> Result<Self, Self::Error> {
+        if bdd.get_size() >= NodeId::UNDEFINED.into_u64() {
+            return Err(format!("Bdd with {} nodes does not fit into 48 bits.", bdd.get_size()));
+        }
+        let mut variable_count = 0u16;
+        let mut nodes = Vec::with_capacity(bdd.as_node_slice().len());
+        for node in bdd.as_node_slice() {
+            if node.is_terminal() {
+                nodes.push(if node.get_low_link().is_zero() { BddNode::ZERO } else { BddNode::ONE });
+                continue;
+            }
+            let (variable, low, high) = node.unpack();
+            let variable = u32::from(variable);
+            if variable >= u32::from(u16::from(VariableId::UNDEFINED)) {
+                return Err(format!("Variable {} does not fit into 16 bits.", variable));
+            }
+            let variable = variable as u16;
+            variable_count = max(variable_count, variable + 1);
+            // Node count is checked above, so the links fit into 48 bits.
+            let (low, high) = (NodeId::from_u48(u64::from(low)), NodeId::from_u48(u64::from(high)));
+            let node = BddNode::try_pack(VariableId::from(variable), low, high)
+                .ok_or_else(|| format!("Invalid decision node {:?}.", node))?;
+            nodes.push(node);
+        }
+        Ok(Bdd { variable_count, nodes })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::machine::{Bdd, BddNode, NodeId, VariableId};
+    use crate::v4::core::Bdd as Bdd4;
+    use core::convert::TryFrom;
+
+    #[test]
+    fn machine_v4_round_trip() {
+        let mut bdd = Bdd::new_true();
+        bdd.ensure_variable_count(4);
+        let x3 = BddNode::try_pack(VariableId::from(3), NodeId::ZERO, NodeId::ONE).unwrap();
+        let x3 = unsafe { bdd.push_node(x3) };
+        let x1 = BddNode::try_pack(VariableId::from(1), x3, NodeId::ONE).unwrap();
+        unsafe { bdd.push_node(x1) };
+
+        let converted = Bdd4::try_from(&bdd).unwrap();
+        assert_eq!(bdd.node_count() as u64, converted.get_size());
+        let back = Bdd::try_from(&converted).unwrap();
+        assert!(bdd.eq_bytes(&back));
+    }
+
+    #[test]
+    fn machine_v4_invalid_order() {
+        let mut bdd = Bdd::new_true();
+        bdd.ensure_variable_count(4);
+        let x1 = BddNode::try_pack(VariableId::from(1), NodeId::ZERO, NodeId::ONE).unwrap();
+        let x1 = unsafe { bdd.push_node(x1) };
+        // This node violates variable ordering, but `push_node` cannot detect it.
+        let x3 = BddNode::try_pack(VariableId::from(3), x1, NodeId::ONE).unwrap();
+        unsafe { bdd.push_node(x3) };
+        assert!(Bdd4::try_from(&bdd).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use super::Bdd;
+use crate::machine::{NodeId, VariableId};
+use alloc::vec;
+use alloc::vec::Vec;
+
+impl Bdd {
+    /// Evaluate the function of this `Bdd` in the given `valuation`, where `valuation[i]` is
+    /// the value of `VariableId::from(i)`.
+    ///
+    /// *Panics:* `valuation` must contain a value for every variable of the `Bdd`.
+    pub fn eval(&self, valuation: &[bool]) -> bool {
+        assert!(
+            valuation.len() >= usize::from(self.variable_count()),
+            "Valuation has {} values, but the Bdd has {} variables.",
+            valuation.len(),
+            self.variable_count()
+        );
+        let mut node = self.root_id();
+        while !node.is_terminal() {
+            let (variable, low, high) = self[node].unpack();
+            node = if valuation[usize::from(u16::from(variable))] {
+                high
+            } else {
+                low
+            };
+        }
+        node.is_one()
+    }
+
+    /// Evaluate the function of this `Bdd` in a `partial` valuation, or return `None` if
+    /// the result depends on the variables which are not assigned.
+    ///
+    /// If a variable appears multiple times in `partial`, the last value is used. Variables
+    /// which do not appear in the `Bdd` are ignored.
+    pub fn eval_partial(&self, partial: &[(VariableId, bool)]) -> Option<bool> {
+        let mut valuation = vec![None; usize::from(self.variable_count())];
+        for (variable, value) in partial {
+            if let Some(slot) = valuation.get_mut(usize::from(u16::from(*variable))) {
+                *slot = Some(*value);
+            }
+        }
+
+        // Explore all nodes which are reachable using the assigned values. The result is
+        // decided if only one terminal is reachable.
+        let mut visited = vec![false; self.node_count()];
+        let mut stack: Vec<NodeId> = vec![self.root_id()];
+        let mut result = None;
+        while let Some(node) = stack.pop() {
+            let index = u64::from(node) as usize;
+            if visited[index] {
+                continue;
+            }
+            visited[index] = true;
+            if node.is_terminal() {
+                if result.is_some() {
+                    // Both terminals are reachable.
+                    return None;
+                }
+                result = Some(node.is_one());
+                continue;
+            }
+            let (variable, low, high) = self[node].unpack();
+            match valuation[usize::from(u16::from(variable))] {
+                Some(true) => stack.push(high),
+                Some(false) => stack.push(low),
+                None => {
+                    stack.push(high);
+                    stack.push(low);
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::machine::{Bdd, BddNode, NodeId, VariableId};
+
+    #[test]
+    fn bdd_eval() {
+        // (x1 & x3) | !x1
+        let mut bdd = Bdd::new_true();
+        bdd.ensure_variable_count(4);
+        let x3 = BddNode::try_pack(VariableId::from(3), NodeId::ZERO, NodeId::ONE).unwrap();
+        let x3 = bdd.try_push_node(x3).unwrap();
+        let x1 = BddNode::try_pack(VariableId::from(1), NodeId::ONE, x3).unwrap();
+        bdd.try_push_node(x1).unwrap();
+
+        for valuation in 0..16u32 {
+            let valuation: Vec<bool> = (0..4).map(|i| valuation & (1 << i) != 0).collect();
+            let expected = (valuation[1] && valuation[3]) || !valuation[1];
+            assert_eq!(expected, bdd.eval(&valuation));
+            let partial: Vec<(VariableId, bool)> = valuation
+                .iter()
+                .enumerate()
+                .map(|(i, value)| (VariableId::from(i as u16), *value))
+                .collect();
+            assert_eq!(Some(expected), bdd.eval_partial(&partial));
+        }
+
+        let (x1, x3) = (VariableId::from(1), VariableId::from(3));
+        assert_eq!(None, bdd.eval_partial(&[]));
+        assert_eq!(Some(true), bdd.eval_partial(&[(x1, false)]));
+        assert_eq!(None, bdd.eval_partial(&[(x1, true)]));
+        assert_eq!(None, bdd.eval_partial(&[(x3, false)]));
+        assert_eq!(Some(true), bdd.eval_partial(&[(x3, true)]));
+        let overwritten = [(x3, true), (x1, true), (x3, false)];
+        assert_eq!(Some(false), bdd.eval_partial(&overwritten));
+        assert_eq!(Some(true), Bdd::new_true().eval_partial(&[]));
+        assert!(!Bdd::new_false().eval(&[]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn bdd_eval_short_valuation() {
+        let mut bdd = Bdd::new_true();
+        bdd.ensure_variable_count(4);
+        bdd.eval(&[true, false]);
+    }
+}
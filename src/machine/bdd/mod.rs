@@ -1,8 +1,18 @@
 use super::BddNode;
 use crate::machine::NodeId;
-use std::cmp::max;
-use std::convert::TryFrom;
-use std::ops::Index;
+use core::cmp::max;
+use core::convert::TryFrom;
+use core::ops::Index;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::vec;
+
+/// Conversions between `machine::Bdd` and the other `Bdd` representations.
+mod conversions;
+
+/// Evaluation of `machine::Bdd` objects in (partial) valuations.
+mod evaluation;
 
 /// A directed acyclic graph representing a Boolean function.
 ///
@@ -79,7 +89,8 @@ impl Bdd {
     ///  - The links on the inserted node are valid in this `Bdd`.
     ///  - The node does not break variable ordering (i.e. it only links to *greater* variables).
     ///
-    /// The last condition cannot be easily checked at runtime, thus this function cannot be safe.
+    /// The last condition cannot be checked without reading the linked nodes, thus this
+    /// function cannot be safe. Use `Bdd::try_push_node` for a checked alternative.
     pub unsafe fn push_node(&mut self, node: BddNode) -> NodeId {
         debug_assert!(self.variable_count > u16::from(node.variable()));
         debug_assert!(node.low_link().into_u64() < self.nodes.len() as u64);
@@ -88,6 +99,43 @@ impl Bdd {
         self.root_id()
     }
 
+    /// Append the given `node` to the `Bdd` as a new root node, or return an error if
+    /// the node violates any of the conditions of `Bdd::push_node`.
+    ///
+    /// The checks are performed in release builds as well, and they take constant time:
+    /// If every node in the `Bdd` respects the variable ordering, then the decision variable
+    /// of a node is also the smallest variable in the whole sub-graph of the node (terminals
+    /// use the undefined variable, which is greater than any valid variable). Hence, it is
+    /// sufficient to compare the variable of the new node with the variables of its
+    /// two successors. As long as a `Bdd` is built only using this method, the ordering
+    /// of the whole graph is therefore guaranteed without any full-graph validation.
+    pub fn try_push_node(&mut self, node: BddNode) -> Result<NodeId, String> {
+        let variable = node.variable();
+        if variable.is_undefined() || self.variable_count <= u16::from(variable) {
+            return Err(format!(
+                "Variable {:?} is not valid in a Bdd with {} variables.",
+                variable, self.variable_count
+            ));
+        }
+        let (low, high) = node.links();
+        for link in [low, high] {
+            let child = self.get_node(link);
+            match child {
+                None => return Err(format!("Link {:?} is not valid in this Bdd.", link)),
+                Some(child) if child.variable() <= variable => {
+                    return Err(format!(
+                        "Node with variable {:?} links to a node with variable {:?}.",
+                        variable,
+                        child.variable()
+                    ));
+                }
+                Some(_) => (),
+            }
+        }
+        // All conditions of `push_node` are satisfied.
+        Ok(unsafe { self.push_node(node) })
+    }
+
     /// Checks for "syntactic" equality between two `Bdd` objects.
     ///
     /// This is more strict than logical equivalence because two `Bdd` objects can represent the
@@ -126,6 +174,30 @@ mod tests {
         assert_eq!(bdd[inserted], node);
     }
 
+    #[test]
+    fn bdd_try_push_node() {
+        let mut bdd = Bdd::new_true();
+        bdd.ensure_variable_count(10);
+        let x7 = BddNode::try_pack(VariableId::from(7), NodeId::ZERO, NodeId::ONE).unwrap();
+        let x7 = bdd.try_push_node(x7).unwrap();
+        let x3 = BddNode::try_pack(VariableId::from(3), x7, NodeId::ONE).unwrap();
+        let x3 = bdd.try_push_node(x3).unwrap();
+        assert_eq!(x3, bdd.root_id());
+
+        // Variable ordering violations.
+        let x7_above_x3 = BddNode::try_pack(VariableId::from(7), NodeId::ZERO, x3).unwrap();
+        assert!(bdd.try_push_node(x7_above_x3).is_err());
+        let x3_above_x3 = BddNode::try_pack(VariableId::from(3), x3, NodeId::ZERO).unwrap();
+        assert!(bdd.try_push_node(x3_above_x3).is_err());
+        // Invalid variable and links.
+        let x12 = BddNode::try_pack(VariableId::from(12), NodeId::ZERO, NodeId::ONE).unwrap();
+        assert!(bdd.try_push_node(x12).is_err());
+        let dangling =
+            BddNode::try_pack(VariableId::from(1), NodeId::from_u48(32), NodeId::ONE).unwrap();
+        assert!(bdd.try_push_node(dangling).is_err());
+        assert_eq!(4, bdd.node_count());
+    }
+
     #[test]
     #[should_panic]
     #[cfg(debug_assertions)]
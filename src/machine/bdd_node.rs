@@ -1,4 +1,5 @@
 use crate::machine::{NodeId, VariableId};
+use crate::packing::ID_BITS;
 
 /// A compact representation of a BDD node, packed into 16 bytes.
 ///
@@ -26,7 +27,7 @@ impl BddNode {
     pub const unsafe fn pack_unchecked(variable: VariableId, low: NodeId, high: NodeId) -> BddNode {
         BddNode(
             low.into_u64(),
-            high.into_u64() | (variable.into_u64() << 48),
+            high.into_u64() | (variable.into_u64() << ID_BITS),
         )
     }
 
@@ -49,7 +50,7 @@ impl BddNode {
     pub fn unpack(self) -> (VariableId, NodeId, NodeId) {
         unsafe {
             // Operations are safe due to the way values are packed in the u64 integers.
-            let var = VariableId::from_u64(self.1 >> 48);
+            let var = VariableId::from_u64(self.1 >> ID_BITS);
             let low = NodeId::from_u64(self.0);
             let high = NodeId::from_u48(self.1);
             (var, low, high)
@@ -61,7 +62,7 @@ impl BddNode {
     /// WARNING: The result can be undefined if called on a terminal node!
     #[inline]
     pub fn variable(&self) -> VariableId {
-        unsafe { VariableId::from_u64(self.1 >> 48) }
+        unsafe { VariableId::from_u64(self.1 >> ID_BITS) }
     }
 
     /// Read the low link.
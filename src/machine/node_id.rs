@@ -1,4 +1,5 @@
-use std::convert::TryFrom;
+use crate::packing;
+use core::convert::TryFrom;
 
 /// A unique identifier of a node in a BDD.
 ///
@@ -22,12 +23,12 @@ impl NodeId {
     pub const ONE: NodeId = NodeId(1);
 
     /// Undefined id.
-    pub const UNDEFINED: NodeId = NodeId((1 << 48) - 1);
+    pub const UNDEFINED: NodeId = NodeId(packing::ID_MASK);
 
     /// **(internal)** A mask of bits that are used in a valid `NodeId`.
     ///
     /// Used to quickly extract a `NodeId` from an integer with additional packed data.
-    const BIT_MASK: u64 = (1 << 48) - 1;
+    const BIT_MASK: u64 = packing::ID_MASK;
 
     #[inline]
     pub fn is_zero(&self) -> bool {
@@ -61,7 +62,7 @@ impl NodeId {
     ///
     /// The `u64` must be a valid `NodeId`. We do not truncate or wrap the data in any way.
     pub(crate) unsafe fn from_u64(value: u64) -> NodeId {
-        debug_assert!(value < (1 << 48));
+        debug_assert!(value <= Self::BIT_MASK);
         NodeId(value)
     }
 
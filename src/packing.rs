@@ -0,0 +1,100 @@
+//! **(internal)** Packing of a 16-bit variable and a 48-bit node id into a single `u64`.
+//!
+//! All packed node representations (`v2::BddNode`, `machine::BddNode` and the benchmark
+//! variants in `v2::bench_fun`) store one of their links together with the decision variable
+//! in the same 64-bit word. The layout of that word is defined here, so that all of them
+//! agree on it: the id occupies the 48 least significant bits and the variable occupies
+//! the 16 most significant bits.
+
+/// The number of bits used by the node id.
+pub(crate) const ID_BITS: u32 = 48;
+
+/// A mask with bits set where the node id is stored.
+pub(crate) const ID_MASK: u64 = (1 << ID_BITS) - 1;
+
+/// A mask with bits set where the variable is stored.
+pub(crate) const VARIABLE_MASK: u64 = !ID_MASK;
+
+/// Pack the `variable` and the `id` into a single word. The id must fit into 48 bits
+/// (higher bits are discarded).
+#[inline]
+pub(crate) const fn pack(variable: u16, id: u64) -> u64 {
+    ((variable as u64) << ID_BITS) | (id & ID_MASK)
+}
+
+/// Extract the variable from a packed word.
+#[inline]
+pub(crate) const fn variable(word: u64) -> u16 {
+    (word >> ID_BITS) as u16
+}
+
+/// Extract the node id from a packed word.
+#[inline]
+pub(crate) const fn id(word: u64) -> u64 {
+    word & ID_MASK
+}
+
+#[cfg(test)]
+mod test {
+    use super::{id, pack, variable, ID_MASK, VARIABLE_MASK};
+
+    #[test]
+    pub fn packing_round_trip_test() {
+        let variables = [0, 1, 2, 255, 256, 0x7fff, 0x8000, u16::MAX - 1, u16::MAX];
+        let ids = [
+            0,
+            1,
+            2,
+            u64::from(u32::MAX),
+            1 << 32,
+            (1 << 47) - 1,
+            1 << 47,
+            ID_MASK - 1,
+            ID_MASK,
+        ];
+        for v in variables.iter() {
+            for i in ids.iter() {
+                let word = pack(*v, *i);
+                assert_eq!(*v, variable(word));
+                assert_eq!(*i, id(word));
+                assert_eq!(u64::from(*v), (word & VARIABLE_MASK) >> 48);
+                assert_eq!(*i, word & ID_MASK);
+            }
+        }
+        // The masks split the word exactly.
+        assert_eq!(0, ID_MASK & VARIABLE_MASK);
+        assert_eq!(u64::MAX, ID_MASK | VARIABLE_MASK);
+        assert_eq!(48, ID_MASK.count_ones());
+        // The largest variable with the largest id is an all-ones word.
+        assert_eq!(u64::MAX, pack(u16::MAX, ID_MASK));
+        // Ids which do not fit into 48 bits never corrupt the variable.
+        assert_eq!(7, variable(pack(7, u64::MAX)));
+        assert_eq!(ID_MASK, id(pack(7, u64::MAX)));
+    }
+
+    #[test]
+    pub fn packed_node_layout_test() {
+        // Every packed node representation agrees with the shared layout.
+        use crate::machine;
+        use crate::v2;
+        let (var, low, high) = (u16::MAX - 1, ID_MASK - 1, ID_MASK - 2);
+
+        let v2_var = v2::VariableId::from(var);
+        let node = v2::BddNode::pack(v2_var, v2::NodeId::ONE, v2::NodeId::ZERO);
+        assert!((v2_var, v2::NodeId::ONE, v2::NodeId::ZERO) == node.unpack());
+        assert!(v2_var == node.variable());
+        assert!(v2::VariableId::UNDEFINED == v2::BddNode::ONE.variable());
+        assert!(v2::BddNode::ONE.low_link().is_one());
+        assert!(v2::BddNode::ZERO.low_link().is_zero());
+
+        let node = unsafe {
+            machine::BddNode::pack_unchecked(
+                machine::VariableId::from(var),
+                machine::NodeId::from_u48(low),
+                machine::NodeId::from_u48(high),
+            )
+        };
+        let (v, l, h) = node.unpack();
+        assert_eq!((var, low, high), (u16::from(v), u64::from(l), u64::from(h)));
+    }
+}
@@ -166,7 +166,7 @@ pub mod bdd {
         pub fn prefetch(&self, id: NodeId) {
             unsafe {
                 let pointer: *const PackedBddNode = self.nodes.get_unchecked(id.into_usize());
-                std::arch::x86_64::_mm_prefetch::<3>(pointer as *const i8);
+                crate::arch::prefetch(pointer);
             }
         }
 
@@ -349,7 +349,7 @@ pub mod bdd {
 
         #[test]
         pub fn basic_sorting_test() {
-            let bdd = std::fs::read_to_string("bench_inputs/itgr/large-large-large.109.and_not.left.bdd").unwrap();
+            let bdd = crate::testing::read_corpus_file("itgr/large-large-large.109.and_not.left.bdd").unwrap();
             let bdd = Bdd::try_from(bdd.as_str()).unwrap();
 
             // Note that initially, the BDD is in post-order, but sorted from high to low. Our
@@ -372,6 +372,49 @@ pub mod bdd_dfs {
     use super::bdd::Bdd;
     use super::node_id::NodeId;
 
+    /// A common interface of the (unchecked) stacks used by the DFS-based algorithms.
+    ///
+    /// None of the operations are checked: the stack must not grow beyond its
+    /// `capacity` and must not be read or popped when empty.
+    pub trait Stack<T: Sized + Copy> {
+        fn new(capacity: usize) -> Self;
+        fn is_empty(&self) -> bool;
+        fn len(&self) -> usize;
+        fn peek(&mut self) -> &mut T;
+        /// Access an item below the top of the stack. Offset `1` is the top item.
+        fn peek_at(&mut self, offset: usize) -> &mut T;
+        fn push(&mut self, item: T);
+        fn pop(&mut self) -> T;
+    }
+
+    /// A compile-time selection of the `Stack` implementation used by an algorithm.
+    ///
+    /// The strategy is a type (as opposed to a concrete stack) because one algorithm
+    /// can need stacks of several different item types.
+    pub trait StackStrategy {
+        type Stack<T: Sized + Copy>: Stack<T>;
+    }
+
+    /// Uses `UnsafeStack`, i.e. the top of the stack is an index into the item vector.
+    pub struct Indexed;
+
+    /// Uses `PointerUnsafeStack`, i.e. the top of the stack is a raw pointer.
+    pub struct Pointer;
+
+    /// The strategy used by the algorithms that do not specify one explicitly.
+    ///
+    /// Neither stack is strictly faster (`coupled_dfs` and `ooo_apply` favour `Pointer`,
+    /// `dfs_node_count` and `apply` favour `Indexed`), so both are kept for the benchmarks.
+    pub type DefaultStrategy = Indexed;
+
+    impl StackStrategy for Indexed {
+        type Stack<T: Sized + Copy> = UnsafeStack<T>;
+    }
+
+    impl StackStrategy for Pointer {
+        type Stack<T: Sized + Copy> = PointerUnsafeStack<T>;
+    }
+
     pub struct UnsafeStack<T: Sized + Copy> {
         index_after_last: usize,
         items: Vec<T>
@@ -383,9 +426,9 @@ pub mod bdd_dfs {
         _items: Vec<T>,
     }
 
-    impl <T: Sized + Copy> PointerUnsafeStack<T> {
+    impl <T: Sized + Copy> Stack<T> for PointerUnsafeStack<T> {
 
-        pub fn new(capacity: usize) -> PointerUnsafeStack<T> {
+        fn new(capacity: usize) -> PointerUnsafeStack<T> {
             // This design sacrifices the first element as a "stopper", but requires less
             // pointer arithmetic co implement correctly.
             let mut data = Vec::with_capacity(capacity + 1);
@@ -400,46 +443,48 @@ pub mod bdd_dfs {
             }
         }
 
-        pub fn is_empty(&self) -> bool {
+        fn is_empty(&self) -> bool {
             self.pointer_zero == self.pointer_last
         }
 
-        pub fn len(&self) -> usize {
+        fn len(&self) -> usize {
             unsafe {
                 self.pointer_last.offset_from(self.pointer_zero).abs() as usize
             }
         }
 
-        pub fn peek(&mut self) -> &mut T {
+        fn peek(&mut self) -> &mut T {
             unsafe {
                 &mut *self.pointer_last
             }
         }
 
-        pub fn peek_at(&mut self, offset: usize) -> &mut T {
+        fn peek_at(&mut self, offset: usize) -> &mut T {
             unsafe {
-                &mut *self.pointer_last.sub(offset)
+                &mut *self.pointer_last.sub(offset - 1)
             }
         }
 
-        pub fn push(&mut self, item: T) {
+        fn push(&mut self, item: T) {
             unsafe {
                 self.pointer_last = self.pointer_last.add(1);
                 *self.pointer_last = item;
             }
         }
 
-        pub fn pop(&mut self) {
+        fn pop(&mut self) -> T {
             unsafe {
+                let item = *self.pointer_last;
                 self.pointer_last = self.pointer_last.sub(1);
+                item
             }
         }
 
     }
 
-    impl <T: Sized + Copy> UnsafeStack<T> {
+    impl <T: Sized + Copy> Stack<T> for UnsafeStack<T> {
 
-        pub fn new(capacity: usize) -> UnsafeStack<T> {
+        fn new(capacity: usize) -> UnsafeStack<T> {
             let mut items = Vec::with_capacity(capacity);
             unsafe { items.set_len(capacity); }
             UnsafeStack {
@@ -447,29 +492,29 @@ pub mod bdd_dfs {
             }
         }
 
-        pub fn len(&self) -> usize {
+        fn len(&self) -> usize {
             self.index_after_last
         }
 
-        pub fn is_empty(&self) -> bool {
+        fn is_empty(&self) -> bool {
             self.index_after_last == 0
         }
 
-        pub fn peek(&mut self ) -> &mut T {
+        fn peek(&mut self ) -> &mut T {
             unsafe { self.items.get_unchecked_mut(self.index_after_last - 1) }
         }
 
-        pub fn peek_at(&mut self, offset: usize) -> &mut T {
+        fn peek_at(&mut self, offset: usize) -> &mut T {
             unsafe { self.items.get_unchecked_mut(self.index_after_last - offset) }
         }
 
-        pub fn push(&mut self, item: T) {
+        fn push(&mut self, item: T) {
             let slot = unsafe { self.items.get_unchecked_mut(self.index_after_last) };
             *slot = item;
             self.index_after_last += 1;
         }
 
-        pub fn pop(&mut self) -> T {
+        fn pop(&mut self) -> T {
             self.index_after_last -= 1;
             unsafe { *self.items.get_unchecked(self.index_after_last) }
         }
@@ -478,8 +523,14 @@ pub mod bdd_dfs {
 
     /// A simple function for testing performance of BDD traversal.
     pub fn dfs_node_count(bdd: &Bdd) -> usize {
+        dfs_node_count_with::<DefaultStrategy>(bdd)
+    }
+
+    /// Same as `dfs_node_count`, but with an explicit `StackStrategy`.
+    pub fn dfs_node_count_with<S: StackStrategy>(bdd: &Bdd) -> usize {
         let mut count = 0;
-        let mut stack = UnsafeStack::<NodeId>::new(bdd.get_height() + 1);
+        // Every expanded node pushes both of its children, so each level can hold two items.
+        let mut stack = S::Stack::<NodeId>::new(2 * (bdd.get_height() + 1));
         let mut expanded = vec![false; bdd.node_count()];
 
         stack.push(bdd.get_root_id());
@@ -500,6 +551,40 @@ pub mod bdd_dfs {
 
         count
     }
+
+    #[cfg(test)]
+    mod test {
+        use super::{dfs_node_count_with, Indexed, Pointer, PointerUnsafeStack, Stack, UnsafeStack};
+        use crate::perf_testing::bdd::Bdd;
+        use std::convert::TryFrom;
+
+        fn check_stack<S: Stack<u32>>() {
+            let mut stack = S::new(4);
+            assert!(stack.is_empty());
+            stack.push(1);
+            stack.push(2);
+            stack.push(3);
+            assert_eq!(3, stack.len());
+            assert_eq!(3, *stack.peek());
+            assert_eq!(3, *stack.peek_at(1));
+            assert_eq!(1, *stack.peek_at(3));
+            *stack.peek_at(2) = 5;
+            assert_eq!(3, stack.pop());
+            assert_eq!(5, stack.pop());
+            assert_eq!(1, stack.pop());
+            assert!(stack.is_empty());
+        }
+
+        #[test]
+        pub fn stack_strategies_are_equivalent() {
+            check_stack::<UnsafeStack<u32>>();
+            check_stack::<PointerUnsafeStack<u32>>();
+
+            let bdd = Bdd::try_from("0,0,0|0,1,1|1,0,1|0,2,1|").unwrap();
+            assert_eq!(4, dfs_node_count_with::<Indexed>(&bdd));
+            assert_eq!(4, dfs_node_count_with::<Pointer>(&bdd));
+        }
+    }
 }
 
 pub mod coupled_dfs {
@@ -507,7 +592,7 @@ pub mod coupled_dfs {
     use super::node_id::NodeId;
     use std::num::NonZeroU64;
     use std::ops::Rem;
-    use super::bdd_dfs::UnsafeStack;
+    use super::bdd_dfs::{DefaultStrategy, Stack, StackStrategy};
 
     struct Cache {
         capacity: NonZeroU64,
@@ -548,7 +633,7 @@ pub mod coupled_dfs {
                 // Usually not that important, but seems to be actually helping for large BDDs.
                 let pointer: *const (NodeId, NodeId) =
                     self.items.get_unchecked((block_start as usize) + 128);
-                std::arch::x86_64::_mm_prefetch::<1>(pointer as *const i8);
+                crate::arch::prefetch_far(pointer);
             }
             (block_start + block_index).rem(self.capacity) as usize
         }
@@ -556,8 +641,13 @@ pub mod coupled_dfs {
     }
 
     pub fn coupled_dfs(left_bdd: &Bdd, right_bdd: &Bdd) -> usize {
+        coupled_dfs_with::<DefaultStrategy>(left_bdd, right_bdd)
+    }
+
+    /// Same as `coupled_dfs`, but with an explicit `StackStrategy`.
+    pub fn coupled_dfs_with<S: StackStrategy>(left_bdd: &Bdd, right_bdd: &Bdd) -> usize {
         let height_sum = left_bdd.get_height() + right_bdd.get_height();
-        let mut stack: UnsafeStack<(NodeId, NodeId)> = UnsafeStack::new(height_sum);
+        let mut stack = S::Stack::<(NodeId, NodeId)>::new(height_sum);
         let mut visited = Cache::new(left_bdd.node_count());
         let mut count = 0;
 
@@ -819,7 +909,7 @@ pub mod task_cache {
                 // Usually not that important, but seems to be actually helping for large BDDs.
                 let pointer: *const ((NodeId, NodeId), NodeId) =
                     self.items.get_unchecked((block_start as usize) + 128);
-                std::arch::x86_64::_mm_prefetch::<1>(pointer as *const i8);
+                crate::arch::prefetch_far(pointer);
             }
             (block_start + block_index) as usize
         }
@@ -831,7 +921,7 @@ pub mod apply {
     use super::bdd::Bdd;
     use super::node_id::NodeId;
     use super::packed_bdd_node::PackedBddNode;
-    use super::bdd_dfs::UnsafeStack;
+    use super::bdd_dfs::{DefaultStrategy, Stack, StackStrategy};
     use super::variable_id::VariableId;
     use super::node_cache::NodeCache;
     use super::task_cache::TaskCache;
@@ -860,12 +950,17 @@ pub mod apply {
     }
 
     pub fn apply(left_bdd: &Bdd, right_bdd: &Bdd) -> (usize, usize) {
+        apply_with::<DefaultStrategy>(left_bdd, right_bdd)
+    }
+
+    /// Same as `apply`, but with an explicit `StackStrategy`.
+    pub fn apply_with<S: StackStrategy>(left_bdd: &Bdd, right_bdd: &Bdd) -> (usize, usize) {
         let height_limit = left_bdd.get_height() + right_bdd.get_height();
         let mut task_cache = TaskCache::new(left_bdd.node_count());
         let mut node_cache = NodeCache::new(2 * left_bdd.node_count(), 2 * left_bdd.node_count());
         let mut task_count = 0;
 
-        let mut stack = UnsafeStack::new(height_limit);
+        let mut stack = S::Stack::<ApplyTask>::new(height_limit);
         stack.push(ApplyTask::new(0, (left_bdd.get_root_id(), right_bdd.get_root_id())));
 
         while !stack.is_empty() {
@@ -949,13 +1044,235 @@ pub mod apply {
 
 }
 
+/// An experimental `apply` (the same `or` operation as `apply::apply`) which expands
+/// the pending tasks in the order given by a `TaskPriority` (using a bucket queue),
+/// instead of the strict DFS order.
+///
+/// Since a task can only be resolved once both of its successors are resolved, all
+/// expanded tasks are kept in memory and the result nodes are created afterwards,
+/// level by level from the last variable. This gives up the "swap trick" of the DFS stack
+/// (a task is finished while its successors are still in the cache), and the experiment
+/// measures whether a better temporal locality of the task cache outweighs this.
+pub mod best_first_apply {
+    use super::bdd::Bdd;
+    use super::node_cache::NodeCache;
+    use super::node_id::NodeId;
+    use super::packed_bdd_node::PackedBddNode;
+    use super::task_cache::TaskCache;
+    use super::variable_id::VariableId;
+    use std::cmp::{min, Reverse};
+
+    /// A compile-time selection of the order in which `best_first_apply_with` expands tasks.
+    /// Tasks with a smaller priority are expanded first.
+    pub trait TaskPriority {
+        fn priority(task: (NodeId, NodeId), variable: VariableId) -> usize;
+    }
+
+    /// Expands the tasks level by level, i.e. breadth-first with respect to the decision
+    /// variables.
+    pub struct ByLevel;
+
+    /// Expands the tasks in the order of their left node (in blocks of `2^LEFT_BLOCK_SHIFT`
+    /// nodes). The task cache places tasks with the same left node close to each other, so
+    /// consecutive tasks should mostly hit the same part of the cache.
+    pub struct ByLeftNode;
+
+    const LEFT_BLOCK_SHIFT: u32 = 6;
+
+    impl TaskPriority for ByLevel {
+        fn priority(_task: (NodeId, NodeId), variable: VariableId) -> usize {
+            variable.into()
+        }
+    }
+
+    impl TaskPriority for ByLeftNode {
+        fn priority(task: (NodeId, NodeId), _variable: VariableId) -> usize {
+            (u64::from(task.0) >> LEFT_BLOCK_SHIFT) as usize
+        }
+    }
+
+    /// An expanded task. Successors are indices into the task list, where `0` and `1`
+    /// are the terminal results.
+    #[derive(Copy, Clone)]
+    struct Task {
+        task: (NodeId, NodeId),
+        variable: VariableId,
+        successors: [usize; 2],
+    }
+
+    /// A bucket queue of task indices. Priorities do not have to be monotone.
+    struct BucketQueue {
+        buckets: Vec<Vec<usize>>,
+        current: usize,
+        len: usize,
+    }
+
+    impl BucketQueue {
+        fn new() -> BucketQueue {
+            BucketQueue { buckets: Vec::new(), current: 0, len: 0 }
+        }
+
+        fn push(&mut self, priority: usize, task: usize) {
+            if priority >= self.buckets.len() {
+                self.buckets.resize_with(priority + 1, Vec::new);
+            }
+            self.buckets[priority].push(task);
+            self.current = min(self.current, priority);
+            self.len += 1;
+        }
+
+        fn pop(&mut self) -> Option<usize> {
+            if self.len == 0 {
+                return None;
+            }
+            while self.buckets[self.current].is_empty() {
+                self.current += 1;
+            }
+            self.len -= 1;
+            self.buckets[self.current].pop()
+        }
+    }
+
+    /// The state of the expansion phase of `best_first_apply_with`.
+    struct Expansion<'a> {
+        left_bdd: &'a Bdd,
+        right_bdd: &'a Bdd,
+        task_cache: TaskCache,
+        queue: BucketQueue,
+        tasks: Vec<Task>,
+    }
+
+    impl Expansion<'_> {
+        /// Find the `task` in the task cache, or create it and schedule its expansion.
+        /// Returns the index of the task.
+        fn ensure<P: TaskPriority>(&mut self, task: (NodeId, NodeId)) -> usize {
+            let (left, right) = task;
+            if left.is_one() || right.is_one() {
+                return 1;
+            } else if left.is_zero() && right.is_zero() {
+                return 0;
+            }
+            let (cached, slot) = self.task_cache.read(task);
+            if !cached.is_undefined() {
+                return cached.into_usize();
+            }
+            let index = self.tasks.len();
+            self.tasks.push(Task { task, variable: VariableId::UNDEFINED, successors: [0, 0] });
+            self.task_cache.write_at(slot, task, NodeId::from(index));
+            let left_node = unsafe { self.left_bdd.get_node_unchecked(left) };
+            let right_node = unsafe { self.right_bdd.get_node_unchecked(right) };
+            let variable = min(left_node.get_variable(), right_node.get_variable());
+            self.queue.push(P::priority(task, variable), index);
+            index
+        }
+    }
+
+    pub fn best_first_apply(left_bdd: &Bdd, right_bdd: &Bdd) -> (usize, usize) {
+        best_first_apply_with::<ByLevel>(left_bdd, right_bdd)
+    }
+
+    /// Same as `best_first_apply`, but with an explicit `TaskPriority`.
+    pub fn best_first_apply_with<P: TaskPriority>(left_bdd: &Bdd, right_bdd: &Bdd) -> (usize, usize) {
+        let mut node_cache = NodeCache::new(2 * left_bdd.node_count(), 2 * left_bdd.node_count());
+        let terminal = Task {
+            task: (NodeId::UNDEFINED, NodeId::UNDEFINED),
+            variable: VariableId::UNDEFINED,
+            successors: [0, 0],
+        };
+        let mut expansion = Expansion {
+            left_bdd,
+            right_bdd,
+            task_cache: TaskCache::new(left_bdd.node_count()),
+            queue: BucketQueue::new(),
+            tasks: vec![terminal, terminal],
+        };
+
+        let root = expansion.ensure::<P>((left_bdd.get_root_id(), right_bdd.get_root_id()));
+        if root < 2 {
+            return (node_cache.len(), 0);
+        }
+
+        // Expand the tasks in priority order.
+        let mut task_count = 0;
+        while let Some(index) = expansion.queue.pop() {
+            task_count += 1;
+            let (left, right) = expansion.tasks[index].task;
+            let left_node = unsafe { left_bdd.get_node_unchecked(left) };
+            let right_node = unsafe { right_bdd.get_node_unchecked(right) };
+            let (l_var, l_low, l_high) = left_node.unpack();
+            let (r_var, r_low, r_high) = right_node.unpack();
+            let (variable, low, high) = if l_var == r_var {
+                (l_var, (l_low, r_low), (l_high, r_high))
+            } else if l_var < r_var {
+                (l_var, (l_low, right), (l_high, right))
+            } else {
+                (r_var, (left, r_low), (left, r_high))
+            };
+            let low = expansion.ensure::<P>(low);
+            let high = expansion.ensure::<P>(high);
+            expansion.tasks[index].variable = variable;
+            expansion.tasks[index].successors = [low, high];
+        }
+
+        // Successors always have a larger variable, so the tasks can be resolved
+        // from the last variable to the first one.
+        let tasks = expansion.tasks;
+        let mut order: Vec<usize> = (2..tasks.len()).collect();
+        order.sort_unstable_by_key(|index| Reverse(tasks[*index].variable));
+        let mut results = vec![NodeId::UNDEFINED; tasks.len()];
+        results[0] = NodeId::ZERO;
+        results[1] = NodeId::ONE;
+        for index in order {
+            let task = &tasks[index];
+            let (low, high) = (results[task.successors[0]], results[task.successors[1]]);
+            results[index] = if low == high {
+                low
+            } else {
+                let node = PackedBddNode::pack(task.variable, low, high);
+                let mut cached = node_cache.ensure(&node);
+                while let Err(slot) = cached {
+                    cached = node_cache.ensure_at(&node, slot);
+                }
+                cached.unwrap()
+            };
+        }
+
+        (node_cache.len(), task_count)
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::{best_first_apply_with, ByLeftNode, ByLevel};
+        use crate::perf_testing::apply::apply_with;
+        use crate::perf_testing::bdd::Bdd;
+        use crate::perf_testing::bdd_dfs::Indexed;
+        use std::convert::TryFrom;
+
+        #[test]
+        pub fn best_first_apply_test() {
+            let read = |path: &str| {
+                let bdd = crate::testing::read_corpus_file(path).unwrap();
+                Bdd::try_from(bdd.as_str()).unwrap().sort_preorder()
+            };
+            let left = read("itgr/large-large-large.109.and_not.left.bdd");
+            let right = read("itgr/large-large-large.109.and_not.right.bdd");
+
+            // Both algorithms create the same set of result nodes.
+            let (expected, _) = apply_with::<Indexed>(&left, &right);
+            assert!(expected > 2);
+            assert_eq!(expected, best_first_apply_with::<ByLevel>(&left, &right).0);
+            assert_eq!(expected, best_first_apply_with::<ByLeftNode>(&left, &right).0);
+        }
+    }
+}
+
 pub mod ooo_apply {
     use super::packed_bdd_node::PackedBddNode;
     use super::node_cache::{NodeCache, NodeCacheSlot};
     use super::bdd::Bdd;
     use super::node_id::NodeId;
     use super::variable_id::VariableId;
-    use super::bdd_dfs::UnsafeStack;
+    use super::bdd_dfs::{DefaultStrategy, Stack, StackStrategy};
     use std::ops::Rem;
 
     const ROB_MASK: u64 = 1 << 63;
@@ -1077,7 +1394,7 @@ pub mod ooo_apply {
                 // Usually not that important, but seems to be actually helping for large BDDs.
                 let pointer: *const ((NodeId, NodeId), NodeIdOrRobSlot) =
                     self.items.get_unchecked((block_start as usize) + 128);
-                std::arch::x86_64::_mm_prefetch::<1>(pointer as *const i8);
+                crate::arch::prefetch_far(pointer);
             }
             (block_start + block_index) as usize
         }
@@ -1285,9 +1602,14 @@ pub mod ooo_apply {
     }
 
     pub fn ooo_apply(left_bdd: &Bdd, right_bdd: &Bdd) -> (usize, usize) {
+        ooo_apply_with::<DefaultStrategy>(left_bdd, right_bdd)
+    }
+
+    /// Same as `ooo_apply`, but with an explicit `StackStrategy`.
+    pub fn ooo_apply_with<S: StackStrategy>(left_bdd: &Bdd, right_bdd: &Bdd) -> (usize, usize) {
         let height_limit = left_bdd.get_height() + right_bdd.get_height();
         let mut task_cache = TaskCache::new(left_bdd.node_count());
-        let mut stack = UnsafeStack::new(height_limit);
+        let mut stack = S::Stack::new(height_limit);
         let mut rob = ReorderBuffer::new(height_limit);
         let mut queue = ExecutionRetireQueue::<64>::new();
         let mut node_cache = NodeCache::new(2 * left_bdd.node_count(), 2 * left_bdd.node_count());
@@ -1450,7 +1772,8 @@ pub mod ooo_apply {
 
         }
 
-        println!("{} {} {} {} {} {}",
+        debug!(
+            "OOO pipeline: iter {}, decode {}, issue {}, resolve {}, execute {}, retire {}.",
             iter,
             decode,
             issue,
@@ -1459,7 +1782,7 @@ pub mod ooo_apply {
             retire,
         );
 
-        println!("Resolvable: {} ({:.2}%)", resolvable, 100.0 * (resolvable as f64) / {decode as f64});
+        debug!("Resolvable: {} ({:.2}%).", resolvable, 100.0 * (resolvable as f64) / {decode as f64});
 
         (node_cache.len(), task_count)
     }
@@ -1598,7 +1921,7 @@ pub mod ooo_apply_2 {
                 // Usually not that important, but seems to be actually helping for large BDDs.
                 let pointer: *const ((NodeId, NodeId), MagicNumber) =
                     self.items.get_unchecked((block_start as usize) + 128);
-                std::arch::x86_64::_mm_prefetch::<1>(pointer as *const i8);
+                crate::arch::prefetch_far(pointer);
             }
             (block_start + block_index) as usize
         }
@@ -0,0 +1,193 @@
+//! Fixed-point algorithms of symbolic model checking, built on `bdd::Bdd`.
+//!
+//! A set of states is a `Bdd` over the "current" `state_vars`. A transition relation is
+//! a `Bdd` over the `state_vars` and their "next" copies `next_vars`, such that
+//! `next_vars[i]` is the next value of `state_vars[i]`. The images are computed using
+//! the relational product (`v2::Bdd::relational_join`), which quantifies and renames
+//! the variables in a single pass, so it requires that renaming between the state and
+//! the next variables preserves their order (e.g. interleaved `s0, n0, s1, n1, ...`, or
+//! all state variables before all next variables).
+
+use crate::bdd::{Bdd, VariableId};
+use crate::v2;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// The successors of the given `states`: `exists state_vars: states & transition`,
+/// with `next_vars` renamed to `state_vars`.
+///
+/// Returns an error if the variable lists have different lengths, or if the renaming does
+/// not preserve the order of the variables.
+pub fn post(
+    states: &Bdd,
+    transition: &Bdd,
+    state_vars: &[VariableId],
+    next_vars: &[VariableId],
+) -> Result<Bdd, String> {
+    let rename = pairs(next_vars, state_vars)?;
+    let result = states
+        .as_v2()
+        .relational_join(transition.as_v2(), state_vars, &rename)?;
+    Ok(Bdd::from(result))
+}
+
+/// The predecessors of the given `states`: `exists next_vars: transition & states'`,
+/// where `states'` are the `states` with `state_vars` renamed to `next_vars`.
+///
+/// Returns an error under the same conditions as `post`.
+pub fn pre(
+    states: &Bdd,
+    transition: &Bdd,
+    state_vars: &[VariableId],
+    next_vars: &[VariableId],
+) -> Result<Bdd, String> {
+    let rename = pairs(state_vars, next_vars)?;
+    // A join with `true` over no variables only renames the `states`.
+    let next_states = states
+        .as_v2()
+        .relational_join(&v2::Bdd::new_true(), &[], &rename)?;
+    Ok(Bdd::from(
+        next_states.and_exists(transition.as_v2(), next_vars),
+    ))
+}
+
+/// All states reachable from the `initial` states using the `transition` relation
+/// (including the `initial` states).
+///
+/// Every iteration only computes the successors of the newly discovered states.
+pub fn reach_forward(
+    initial: &Bdd,
+    transition: &Bdd,
+    state_vars: &[VariableId],
+    next_vars: &[VariableId],
+) -> Result<Bdd, String> {
+    saturate(initial, |frontier| {
+        post(frontier, transition, state_vars, next_vars)
+    })
+}
+
+/// All states from which the `target` states are reachable using the `transition` relation
+/// (including the `target` states).
+pub fn reach_backward(
+    target: &Bdd,
+    transition: &Bdd,
+    state_vars: &[VariableId],
+    next_vars: &[VariableId],
+) -> Result<Bdd, String> {
+    saturate(target, |frontier| {
+        pre(frontier, transition, state_vars, next_vars)
+    })
+}
+
+/// The fixed-point of `step` which is reached by repeatedly applying `step`
+/// to the `initial` set, until the set no longer changes.
+///
+/// The result is only a fixed-point of `step` if the iteration terminates, which is
+/// guaranteed for monotone functions starting at `false` (see `least_fixed_point`)
+/// or `true` (see `greatest_fixed_point`).
+pub fn fixed_point<F: FnMut(&Bdd) -> Bdd>(initial: Bdd, mut step: F) -> Bdd {
+    let mut current = initial;
+    loop {
+        let next = step(&current);
+        if next.semantic_eq(&current) {
+            return next;
+        }
+        current = next;
+    }
+}
+
+/// The least fixed-point of a monotone `step` function (e.g. the reachable states are
+/// the least fixed-point of `X -> initial | post(X)`).
+pub fn least_fixed_point<F: FnMut(&Bdd) -> Bdd>(step: F) -> Bdd {
+    fixed_point(Bdd::new_false(), step)
+}
+
+/// The greatest fixed-point of a monotone `step` function (e.g. the states with an infinite
+/// path inside a set `G` are the greatest fixed-point of `X -> G & pre(X)`).
+pub fn greatest_fixed_point<F: FnMut(&Bdd) -> Bdd>(step: F) -> Bdd {
+    fixed_point(Bdd::new_true(), step)
+}
+
+/// **(internal)** Extend the `initial` set with the results of `image` until no new states
+/// are found. The `image` is only applied to the states discovered in the last iteration.
+fn saturate<F>(initial: &Bdd, mut image: F) -> Result<Bdd, String>
+where
+    F: FnMut(&Bdd) -> Result<Bdd, String>,
+{
+    let mut reached = initial.clone();
+    let mut frontier = initial.clone();
+    while !frontier.semantic_eq(&Bdd::new_false()) {
+        frontier = image(&frontier)?.and_not(&reached);
+        reached = reached.or(&frontier);
+    }
+    Ok(reached)
+}
+
+/// **(internal)** Pair the variables `from[i]` with `to[i]`.
+fn pairs(from: &[VariableId], to: &[VariableId]) -> Result<Vec<(VariableId, VariableId)>, String> {
+    if from.len() != to.len() {
+        return Err(format!(
+            "Expected the same number of state and next variables, got {} and {}.",
+            from.len(),
+            to.len()
+        ));
+    }
+    Ok(from.iter().cloned().zip(to.iter().cloned()).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bdd::{Bdd, VariableId};
+    use crate::symbolic::{
+        greatest_fixed_point, least_fixed_point, post, pre, reach_backward, reach_forward,
+    };
+    use alloc::vec::Vec;
+
+    #[test]
+    pub fn reachability_test() {
+        // A 3-bit counter which adds 2 (modulo 8). State bit `i` is the variable `2i`,
+        // its next value is the variable `2i + 1`.
+        let v: Vec<Bdd> = (0..6u16)
+            .map(|v| Bdd::new_variable(VariableId::from(v)))
+            .collect();
+        let state_vars: Vec<VariableId> = (0..3u16).map(|i| VariableId::from(2 * i)).collect();
+        let next_vars: Vec<VariableId> = (0..3u16).map(|i| VariableId::from(2 * i + 1)).collect();
+        let (s, n) = (|i: usize| &v[2 * i], |i: usize| &v[2 * i + 1]);
+        let transition = n(0)
+            .iff(s(0))
+            .and(&n(1).xor(s(1)))
+            .and(&n(2).iff(&s(2).xor(s(1))));
+        let zero = s(0).or(s(1)).or(s(2)).not();
+        let even = s(0).not();
+
+        // 0 -> 2, and 7 -> 1.
+        let two = post(&zero, &transition, &state_vars, &next_vars).unwrap();
+        assert!(two.semantic_eq(&s(0).not().and(s(1)).and(&s(2).not())));
+        let seven = s(0).and(s(1)).and(s(2));
+        let one = s(0).and(&s(1).or(s(2)).not());
+        assert!(pre(&one, &transition, &state_vars, &next_vars)
+            .unwrap()
+            .semantic_eq(&seven));
+
+        let reached = reach_forward(&zero, &transition, &state_vars, &next_vars).unwrap();
+        assert!(reached.semantic_eq(&even));
+        let lfp =
+            least_fixed_point(|x| zero.or(&post(x, &transition, &state_vars, &next_vars).unwrap()));
+        assert!(lfp.semantic_eq(&reached));
+        let source = reach_backward(&one, &transition, &state_vars, &next_vars).unwrap();
+        assert!(source.semantic_eq(&s(0)));
+
+        // The even states form a cycle, but the states below 4 (`!s2`) always reach 4.
+        let gfp = |g: &Bdd| {
+            greatest_fixed_point(|x| g.and(&pre(x, &transition, &state_vars, &next_vars).unwrap()))
+        };
+        assert!(gfp(&even).semantic_eq(&even));
+        assert!(gfp(&s(2).not()).semantic_eq(&Bdd::new_false()));
+
+        // Invalid variables are reported.
+        assert!(post(&zero, &transition, &state_vars, &next_vars[..2]).is_err());
+        let reversed: Vec<VariableId> = next_vars.iter().rev().cloned().collect();
+        assert!(reach_forward(&zero, &transition, &state_vars, &reversed).is_err());
+    }
+}
@@ -0,0 +1,332 @@
+//! Access to the benchmark corpus (`bench_inputs`) for tests and benchmarks.
+//!
+//! The original corpus is not part of the published crate. `ensure_corpus` uses it when it
+//! is available and otherwise generates a small synthetic corpus with the same layout:
+//!
+//!  1. If the `BDD_BENCH_INPUTS` environment variable is set, it must point to an existing
+//!     corpus directory, which is used as is.
+//!  2. If `bench_inputs` exists in the crate root, it is used.
+//!  3. Otherwise, a synthetic corpus is generated (deterministically) in the system temporary
+//!     directory. It contains the benchmarks used by the unit tests, with the same names and
+//!     in the same text format, but the `Bdds` are much smaller than the original ones.
+//!
+//! Apart from the corpus, the module provides generators of functions whose `Bdds` are known
+//! to be hard (`hidden_weighted_bit`, `multiplier_bit`, `queens`). Unlike the random corpus,
+//! their structure is very regular, which stresses caches and memory access patterns
+//! differently.
+
+use crate::v2::{Bdd, VariableId};
+use crate::v4::core::Bdd as Bdd4;
+use std::convert::TryFrom;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// The environment variable which can be used to override the location of the corpus.
+pub const CORPUS_VARIABLE: &str = "BDD_BENCH_INPUTS";
+
+/// Benchmarks in the synthetic corpus: `(category, benchmark, operation, seed)`.
+const SYNTHETIC_BENCHMARKS: [(&str, &str, &str, u64); 2] = [
+    ("itgr", "large-large-large.109", "and_not", 109),
+    ("reach", "synthetic.1", "or", 1),
+];
+
+/// Return the path to the benchmark corpus. The synthetic corpus is generated at most once
+/// per process (see the module documentation).
+pub fn ensure_corpus() -> Result<PathBuf, String> {
+    static CORPUS: OnceLock<Result<PathBuf, String>> = OnceLock::new();
+    CORPUS.get_or_init(find_or_generate_corpus).clone()
+}
+
+/// Read the file at the given `path` relative to the root of the corpus, e.g.
+/// `itgr/large-large-large.109.and_not.left.bdd`.
+pub fn read_corpus_file(path: &str) -> Result<String, String> {
+    let file = ensure_corpus()?.join(path);
+    std::fs::read_to_string(&file)
+        .map_err(|e| format!("Cannot read `{}`: {}", file.display(), e))
+}
+
+/// **(internal)** Resolve the corpus location, generating the synthetic corpus if necessary.
+fn find_or_generate_corpus() -> Result<PathBuf, String> {
+    if let Some(path) = std::env::var_os(CORPUS_VARIABLE) {
+        let path = PathBuf::from(path);
+        return if path.is_dir() {
+            Ok(path)
+        } else {
+            Err(format!("`{}` is not a directory.", path.display()))
+        };
+    }
+    let shipped = Path::new(env!("CARGO_MANIFEST_DIR")).join("bench_inputs");
+    if shipped.is_dir() {
+        return Ok(shipped);
+    }
+
+    let name = format!("binary-decision-diagrams-{}-corpus", env!("CARGO_PKG_VERSION"));
+    let target = std::env::temp_dir().join(name);
+    if target.is_dir() {
+        return Ok(target);
+    }
+    // Generate the corpus into a private directory and move it into place at once, so that
+    // other processes never observe a partially written corpus.
+    let staging = target.with_extension(format!("{}.tmp", std::process::id()));
+    write_synthetic_corpus(&staging)
+        .map_err(|e| format!("Cannot generate corpus in `{}`: {}", staging.display(), e))?;
+    if std::fs::rename(&staging, &target).is_err() {
+        // Another process was faster. Its corpus is identical.
+        let _ = std::fs::remove_dir_all(&staging);
+    }
+    if target.is_dir() {
+        Ok(target)
+    } else {
+        Err(format!("Cannot create corpus in `{}`.", target.display()))
+    }
+}
+
+/// **(internal)** Write all `SYNTHETIC_BENCHMARKS` into the given directory.
+fn write_synthetic_corpus(root: &Path) -> std::io::Result<()> {
+    for (category, benchmark, operation, seed) in SYNTHETIC_BENCHMARKS.iter() {
+        let directory = root.join(category);
+        std::fs::create_dir_all(&directory)?;
+        let operands = [("left", *seed), ("right", seed.wrapping_add(1 << 32))];
+        for (side, seed) in operands.iter() {
+            let file = format!("{}.{}.{}.bdd", benchmark, operation, side);
+            std::fs::write(directory.join(file), synthetic_bdd(*seed))?;
+        }
+    }
+    Ok(())
+}
+
+/// **(internal)** Generate a random (but deterministic) `Bdd` in the corpus text format.
+///
+/// The function is a disjunction of random conjunctions of three literals. Same as in the
+/// original corpus, the nodes are written in postorder with high links visited first.
+fn synthetic_bdd(seed: u64) -> String {
+    const VARIABLES: u16 = 24;
+    const TERMS: usize = 40;
+    // A xorshift generator, so that the corpus does not depend on the `rand` crate.
+    let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+    let mut next = move |bound: u64| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state % bound
+    };
+
+    let ff = Bdd::new_false();
+    let mut result = ff.clone();
+    for _ in 0..TERMS {
+        let mut term = ff.imp(&ff);
+        for _ in 0..3 {
+            let variable = VariableId::from(next(u64::from(VARIABLES)) as u16);
+            let literal = Bdd::new_variable(variable);
+            let literal = if next(2) == 0 { literal } else { literal.imp(&ff) };
+            term = term.and(&literal);
+        }
+        result = result.or(&term);
+    }
+    write_postorder(&Bdd4::try_from(&result).unwrap(), VARIABLES)
+}
+
+/// **(internal)** Write the `bdd` in the text format of the corpus, with nodes in postorder
+/// (high links first) and terminal nodes labelled by the variable count.
+fn write_postorder(bdd: &Bdd4, variable_count: u16) -> String {
+    let nodes = bdd.as_node_slice();
+    let mut output = format!("{},0,0|{},1,1|", variable_count, variable_count);
+    if nodes.len() <= 2 {
+        return output;
+    }
+    // New ids of the already written nodes (0 means not written yet).
+    let mut new_id = vec![0u64; nodes.len()];
+    new_id[1] = 1;
+    let mut next_id = 2;
+    let mut stack = vec![nodes.len() - 1];
+    while let Some(&top) = stack.last() {
+        let (variable, low, high) = nodes[top].unpack();
+        let (low, high) = (u64::from(low) as usize, u64::from(high) as usize);
+        let is_done = |index: usize| index < 2 || new_id[index] != 0;
+        if new_id[top] != 0 {
+            stack.pop();
+        } else if !is_done(high) {
+            stack.push(high);
+        } else if !is_done(low) {
+            stack.push(low);
+        } else {
+            new_id[top] = next_id;
+            next_id += 1;
+            let variable = u32::from(variable);
+            write!(output, "{},{},{}|", variable, new_id[low], new_id[high]).unwrap();
+            stack.pop();
+        }
+    }
+    output
+}
+
+/// The hidden weighted bit function of `n` variables: the value of the variable `x_{w-1}`,
+/// where `w` is the number of true variables (and `false` if `w = 0`).
+///
+/// The `Bdd` has exponential size for every variable ordering.
+pub fn hidden_weighted_bit(n: u16) -> Bdd {
+    let x = literals(n);
+    let ff = Bdd::new_false();
+    // `exact[k]` is true iff exactly `k` of the variables processed so far are true.
+    let mut exact = vec![Bdd::true_with_capacity(2)];
+    for x_i in &x {
+        let mut next = Vec::with_capacity(exact.len() + 1);
+        for k in 0..=exact.len() {
+            let stay = exact.get(k).map(|it| it.and_not(x_i)).unwrap_or_else(|| ff.clone());
+            let step = if k > 0 { exact[k - 1].and(x_i) } else { ff.clone() };
+            next.push(stay.or(&step));
+        }
+        exact = next;
+    }
+    let mut result = ff;
+    for k in 1..exact.len() {
+        result = result.or(&exact[k].and(&x[k - 1]));
+    }
+    result.update_variable_count(n);
+    result
+}
+
+/// The output `bit` of the product of two `n`-bit numbers `a` and `b`. The bits are
+/// interleaved: bit `i` (least significant first) of `a` is the variable `2 * i`, and bit `i`
+/// of `b` is the variable `2 * i + 1`.
+///
+/// The middle bits (`bit` close to `n`) have exponential size for every variable ordering.
+/// Panics if `bit >= 2 * n`.
+pub fn multiplier_bit(n: u16, bit: u16) -> Bdd {
+    assert!(bit < 2 * n, "Bit {} does not exist in a {}-bit product.", bit, 2 * n);
+    let x = literals(2 * n);
+    let ff = Bdd::new_false();
+    // The product is computed as a sum of shifted partial products using a ripple-carry
+    // adder. Only the bits up to `bit` are needed.
+    let mut product = vec![ff.clone(); usize::from(bit) + 1];
+    for i in 0..usize::from(n) {
+        let mut carry = ff.clone();
+        for (k, sum) in product.iter_mut().enumerate().skip(i) {
+            let addend = if k - i < usize::from(n) {
+                x[2 * i].and(&x[2 * (k - i) + 1])
+            } else {
+                ff.clone()
+            };
+            let half = sum.xor(&addend);
+            let next_carry = sum.and(&addend).or(&carry.and(&half));
+            *sum = half.xor(&carry);
+            carry = next_carry;
+        }
+    }
+    let mut result = product.pop().unwrap();
+    result.update_variable_count(2 * n);
+    result
+}
+
+/// The `n`-queens problem: the variable `n * row + column` is true if there is a queen
+/// on the square. The function is true for placements of `n` queens where no two queens
+/// attack each other.
+///
+/// The intermediate results are much larger than the result, but the construction is still
+/// fast for `n <= 8`.
+pub fn queens(n: u16) -> Bdd {
+    let x = literals(n * n);
+    let n = usize::from(n);
+    let square = |row: usize, column: usize| &x[n * row + column];
+    let mut result = Bdd::true_with_capacity(2);
+    for row in 0..n {
+        for column in 0..n {
+            // A queen on this square excludes queens on all attacked squares.
+            let mut free = Bdd::true_with_capacity(2);
+            for other_row in 0..n {
+                for other_column in 0..n {
+                    let (dr, dc) = (
+                        (row as isize - other_row as isize).abs(),
+                        (column as isize - other_column as isize).abs(),
+                    );
+                    let is_attacked = (dr == 0 || dc == 0 || dr == dc) && dr + dc > 0;
+                    if is_attacked {
+                        free = free.and_not(square(other_row, other_column));
+                    }
+                }
+            }
+            result = result.and(&square(row, column).imp(&free));
+        }
+        // Every row contains a queen.
+        let mut any = Bdd::new_false();
+        for column in 0..n {
+            any = any.or(square(row, column));
+        }
+        result = result.and(&any);
+    }
+    result.update_variable_count((n * n) as u16);
+    result
+}
+
+/// **(internal)** The positive literals of the variables `0..count`.
+fn literals(count: u16) -> Vec<Bdd> {
+    (0..count).map(|v| Bdd::new_variable(VariableId::from(v))).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{hidden_weighted_bit, multiplier_bit, queens, read_corpus_file, synthetic_bdd};
+    use crate::v4::core::Bdd;
+    use std::convert::TryFrom;
+
+    /// Evaluate the `bdd` in the valuation given by the bits of `valuation`.
+    fn eval(bdd: &crate::v2::Bdd, valuation: u64) -> bool {
+        let nodes = bdd.node_slice();
+        let mut index = nodes.len() - 1;
+        while index > 1 {
+            let node = nodes[index];
+            let variable = u16::from(node.variable());
+            index = if valuation & (1 << variable) == 0 {
+                node.low_link().as_index()
+            } else {
+                node.high_link().as_index()
+            };
+        }
+        index == 1
+    }
+
+    #[test]
+    pub fn hard_function_generators_test() {
+        let hwb = hidden_weighted_bit(8);
+        assert_eq!(8, hwb.variable_count());
+        for x in 0..256u64 {
+            let weight = x.count_ones();
+            let expected = weight > 0 && x & (1 << (weight - 1)) != 0;
+            assert_eq!(expected, eval(&hwb, x));
+        }
+
+        let n = 4;
+        let bits: Vec<_> = (0..2 * n).map(|bit| multiplier_bit(n, bit)).collect();
+        for a in 0..16u64 {
+            for b in 0..16u64 {
+                // Interleave the bits of `a` and `b`.
+                let valuation = (0..4).fold(0, |acc, i| {
+                    acc | ((a >> i) & 1) << (2 * i) | ((b >> i) & 1) << (2 * i + 1)
+                });
+                for (bit, bdd) in bits.iter().enumerate() {
+                    assert_eq!((a * b) >> bit & 1 == 1, eval(bdd, valuation));
+                }
+            }
+        }
+
+        let queens = queens(4);
+        let solutions: Vec<u64> = (0..1 << 16).filter(|x| eval(&queens, *x)).collect();
+        // The two solutions are mirror images: .Q.. / ...Q / Q... / ..Q. and its mirror.
+        assert_eq!(vec![0b0010_1000_0001_0100, 0b0100_0001_1000_0010], solutions);
+    }
+
+    #[test]
+    pub fn synthetic_corpus_test() {
+        // The generator is deterministic and produces valid `Bdds`.
+        assert_eq!(synthetic_bdd(7), synthetic_bdd(7));
+        let bdd = Bdd::try_from(synthetic_bdd(7).as_str()).unwrap();
+        assert!(bdd.get_size() > 100);
+
+        let left = read_corpus_file("itgr/large-large-large.109.and_not.left.bdd").unwrap();
+        let right = read_corpus_file("itgr/large-large-large.109.and_not.right.bdd").unwrap();
+        assert!(Bdd::try_from(left.as_str()).is_ok());
+        assert!(Bdd::try_from(right.as_str()).is_ok());
+        assert!(read_corpus_file("itgr/missing.bdd").is_err());
+    }
+}
@@ -0,0 +1,66 @@
+use crate::v2::BddAllocConfig;
+use alloc::vec::Vec;
+
+/// **(internal)** Same as `vec![value; len]`, but the memory of the vector is advised
+/// using `advise_huge_pages` before it is initialized.
+pub(crate) fn filled_vec<T: Clone>(value: T, len: usize, config: &BddAllocConfig) -> Vec<T> {
+    let mut result = Vec::with_capacity(len);
+    advise_huge_pages(&mut result, config);
+    result.resize(len, value);
+    result
+}
+
+/// **(internal)** Ask the operating system to back the allocated capacity of the `buffer`
+/// with huge pages, if this is enabled by the `config`. Only the huge pages which are fully
+/// inside the buffer are affected, so small buffers are ignored.
+///
+/// The advice only applies to the memory which was not written yet, so it should be given
+/// right after the buffer is allocated.
+#[cfg(all(feature = "huge-pages", target_os = "linux"))]
+pub(crate) fn advise_huge_pages<T>(buffer: &mut Vec<T>, config: &BddAllocConfig) {
+    use crate::v2::HUGE_PAGE_SIZE;
+    if !config.huge_pages {
+        return;
+    }
+    let start = buffer.as_ptr() as usize;
+    let end = start + buffer.capacity() * core::mem::size_of::<T>();
+    let aligned_start = start.div_ceil(HUGE_PAGE_SIZE) * HUGE_PAGE_SIZE;
+    let aligned_end = end / HUGE_PAGE_SIZE * HUGE_PAGE_SIZE;
+    if aligned_end > aligned_start {
+        let length = aligned_end - aligned_start;
+        // The range is inside of the allocation of the buffer, and the advice does not change
+        // its contents. The advice is only a hint, so errors can be ignored.
+        unsafe {
+            libc::madvise(
+                aligned_start as *mut libc::c_void,
+                length,
+                libc::MADV_HUGEPAGE,
+            );
+        }
+    }
+}
+
+/// **(internal)** Huge pages are not supported on this platform (or without the `huge-pages`
+/// feature), so this does nothing.
+#[cfg(not(all(feature = "huge-pages", target_os = "linux")))]
+pub(crate) fn advise_huge_pages<T>(buffer: &mut Vec<T>, config: &BddAllocConfig) {
+    let _ = (buffer, config);
+}
+
+#[cfg(test)]
+mod test {
+    use super::filled_vec;
+    use crate::v2::{BddAllocConfig, HUGE_PAGE_SIZE};
+
+    #[test]
+    pub fn filled_vec_test() {
+        let config = BddAllocConfig { huge_pages: true };
+        // Large enough to contain at least one aligned huge page.
+        let len = 2 * HUGE_PAGE_SIZE / 8;
+        let buffer = filled_vec(7u64, len, &config);
+        assert_eq!(len, buffer.len());
+        assert!(buffer.iter().all(|it| *it == 7));
+        let small = filled_vec(1u8, 3, &BddAllocConfig::default());
+        assert_eq!(vec![1u8, 1, 1], small);
+    }
+}
@@ -0,0 +1,158 @@
+use crate::v2::ops::{And, BooleanOp, Or};
+use crate::v2::{ApplyOptions, Bdd, BddNode, NodeId};
+use alloc::collections::BinaryHeap;
+use alloc::vec::Vec;
+use core::cmp::{max, Ordering, Reverse};
+
+impl Bdd {
+    /// A conjunction of all the `Bdds` produced by the `operands` iterator (`true` if there
+    /// are none).
+    ///
+    /// The two smallest (partial) results are always combined first, so the large
+    /// intermediate results are only created at the end (unlike in a left fold, where every
+    /// operation includes the growing accumulator). As soon as a partial result is `false`,
+    /// the remaining operations are skipped. The node storage of the consumed partial
+    /// results is reused for the next results.
+    pub fn and_all<I: IntoIterator<Item = Bdd>>(operands: I) -> Bdd {
+        smallest_first::<And, I>(operands, NodeId::ONE)
+    }
+
+    /// A disjunction of all the `Bdds` produced by the `operands` iterator (`false` if there
+    /// are none). The operands are combined in the same order as in `Bdd::and_all`.
+    pub fn or_all<I: IntoIterator<Item = Bdd>>(operands: I) -> Bdd {
+        smallest_first::<Or, I>(operands, NodeId::ZERO)
+    }
+}
+
+/// **(internal)** An entry of the `smallest_first` heap. Entries are ordered by the node
+/// count of the `Bdd`, and then by their insertion `order`, so that the result does not
+/// depend on the implementation of `BinaryHeap`.
+struct Entry {
+    node_count: usize,
+    order: usize,
+    bdd: Bdd,
+}
+
+impl Entry {
+    fn key(&self) -> (usize, usize) {
+        (self.node_count, self.order)
+    }
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+/// **(internal)** Combine the `operands` using `OP`, always applying it to the two smallest
+/// available `Bdds`. The `neutral` constant is the result for an empty list of operands,
+/// the opposite constant is absorbing (the result of `OP` is then always that constant).
+fn smallest_first<OP: BooleanOp, I: IntoIterator<Item = Bdd>>(operands: I, neutral: NodeId) -> Bdd {
+    let absorbing = if neutral.is_one() {
+        NodeId::ZERO
+    } else {
+        NodeId::ONE
+    };
+    let mut variables = 0;
+    let mut order = 0;
+    let mut heap = BinaryHeap::new();
+    for bdd in operands {
+        variables = max(variables, bdd.variable_count());
+        heap.push(Reverse(Entry {
+            node_count: bdd.node_count(),
+            order,
+            bdd,
+        }));
+        order += 1;
+    }
+    let options = ApplyOptions::default();
+    // A node vector of the consumed operands, used to store the next result.
+    let mut spare: Vec<BddNode> = Vec::new();
+    let mut result = loop {
+        let first = match heap.pop() {
+            Some(Reverse(entry)) => entry.bdd,
+            None => break Bdd::_new_constant(neutral, 0),
+        };
+        if first.root_node() == absorbing {
+            break first;
+        }
+        let second = match heap.pop() {
+            Some(Reverse(entry)) => entry.bdd,
+            None => break first,
+        };
+        let bdd = first.apply_in_buffer::<OP>(&second, &options, core::mem::take(&mut spare));
+        // The operands are no longer needed, the larger node vector is kept.
+        for mut nodes in [first.nodes, second.nodes] {
+            if nodes.capacity() > spare.capacity() {
+                nodes.clear();
+                spare = nodes;
+            }
+        }
+        heap.push(Reverse(Entry {
+            node_count: bdd.node_count(),
+            order,
+            bdd,
+        }));
+        order += 1;
+    };
+    result.update_variable_count(variables);
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::_impl_::test_util::variables;
+    use crate::v2::Bdd;
+    use alloc::vec::Vec;
+
+    #[test]
+    pub fn smallest_first_test() {
+        let x = variables(10);
+        let clauses: Vec<Bdd> = (0..9)
+            .map(|i| x[i].or(&x[i + 1].xor(&x[(i + 5) % 10])))
+            .collect();
+
+        let expected = clauses
+            .iter()
+            .skip(1)
+            .fold(clauses[0].clone(), |a, b| a.and(b));
+        let result = Bdd::and_all(clauses.iter().cloned());
+        assert!(result.semantic_eq(&expected));
+        assert_eq!(expected.variable_count(), result.variable_count());
+        let expected = clauses
+            .iter()
+            .skip(1)
+            .fold(clauses[0].clone(), |a, b| a.or(b));
+        let result = Bdd::or_all(clauses.iter().cloned());
+        assert!(result.semantic_eq(&expected));
+
+        // The absorbing constant stops the reduction, but keeps the variables.
+        let contradiction = [x[0].clone(), x[0].not(), x[9].clone()];
+        let result = Bdd::and_all(contradiction.iter().cloned());
+        assert_eq!(1, result.node_count());
+        assert_eq!(10, result.variable_count());
+        let tautology = [x[3].clone(), x[3].not()];
+        assert_eq!(2, Bdd::or_all(tautology.iter().cloned()).node_count());
+
+        // Degenerate inputs.
+        assert_eq!(2, Bdd::and_all(Vec::new()).node_count());
+        assert_eq!(1, Bdd::or_all(Vec::new()).node_count());
+        let single = Bdd::and_all([clauses[0].clone()]);
+        assert!(single.nodes == clauses[0].nodes);
+    }
+}
@@ -0,0 +1,179 @@
+//! **(internal)** Estimates the number of result nodes of a binary operation, which is used
+//! as the initial capacity of the node cache.
+//!
+//! A fixed guess (e.g. the size of the left operand) wastes a lot of memory when the result
+//! is small and causes many node cache collisions when the result is large. Instead,
+//! we run a short "coupled DFS" over the operands which only collects distinct tasks.
+//! If the sample covers the whole operation, the number of tasks is an upper bound on the
+//! result size. Otherwise, we measure how many tasks were created per visited left node
+//! and extrapolate this rate to the whole left operand.
+//!
+//! The estimate can still be wrong, in which case the node cache grows as needed.
+//!
+//! For small operands, the sample would take a significant part of the whole operation,
+//! so the size of the left operand is used instead (see `SAMPLE_THRESHOLD`).
+
+use crate::v2::_impl_::storage::StorageAccess;
+use crate::v2::{BddStorage, NodeId};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::{max, min};
+
+/// **(internal)** The largest number of distinct tasks explored by the sample.
+pub(super) const SAMPLE_TASKS: usize = 1 << 12;
+
+/// **(internal)** Operations where the left operand has at most this many nodes are not
+/// sampled: their node cache is small regardless of the estimate.
+pub(super) const SAMPLE_THRESHOLD: usize = SAMPLE_TASKS;
+
+/// **(internal)** The smallest capacity returned by the estimator.
+const MIN_CAPACITY: usize = 16;
+
+/// **(internal)** Estimate the number of nodes (including terminals) of the result of an
+/// operation on `left_bdd` and `right_bdd` with the given `lookup` table.
+pub(super) fn estimate_result_capacity<L, R, TABLE>(
+    left_bdd: &L,
+    right_bdd: &R,
+    lookup: &TABLE,
+) -> usize
+where
+    L: BddStorage + ?Sized,
+    R: BddStorage + ?Sized,
+    TABLE: Fn(NodeId, NodeId) -> NodeId,
+{
+    if left_bdd.node_count() <= SAMPLE_THRESHOLD {
+        return max(MIN_CAPACITY, left_bdd.node_count());
+    }
+    let mut tasks = SampleSet::new();
+    let mut left_nodes = SampleSet::new();
+    let mut stack = vec![(left_bdd.root_node(), right_bdd.root_node())];
+    while let Some((left, right)) = stack.pop() {
+        if tasks.len >= SAMPLE_TASKS {
+            break;
+        }
+        if !lookup(left, right).is_undefined() || !tasks.insert((left.0, right.0)) {
+            continue;
+        }
+        left_nodes.insert((left.0, 0));
+        // Both ids come from the operands (or their successors), hence they are valid.
+        let (l_var, l_low, l_high) = unsafe { left_bdd.get_node_unchecked(left) }.unpack();
+        let (r_var, r_low, r_high) = unsafe { right_bdd.get_node_unchecked(right) }.unpack();
+        let variable = min(l_var, r_var);
+        let (l_low, l_high) = if l_var == variable {
+            (l_low, l_high)
+        } else {
+            (left, left)
+        };
+        let (r_low, r_high) = if r_var == variable {
+            (r_low, r_high)
+        } else {
+            (right, right)
+        };
+        stack.push((l_high, r_high));
+        stack.push((l_low, r_low));
+    }
+
+    // Every task creates at most one result node.
+    let sampled = tasks.len + 2;
+    let estimate = if stack.is_empty() {
+        sampled
+    } else {
+        // Tasks per visited left node, extrapolated to the whole left operand.
+        let rate = tasks.len.div_ceil(max(1, left_nodes.len));
+        let pairs = left_bdd.node_count().saturating_mul(right_bdd.node_count());
+        let extrapolated = rate.saturating_mul(left_bdd.node_count());
+        max(sampled, min(extrapolated, pairs))
+    };
+    max(MIN_CAPACITY, estimate)
+}
+
+/// **(internal)** A fixed-size open-addressing set of at most `SAMPLE_TASKS` keys, used
+/// by the sample instead of a general set to avoid an allocation for every key.
+struct SampleSet {
+    len: usize,
+    // Empty slots are `SampleSet::EMPTY`, which is never a valid key.
+    slots: Vec<(u64, u64)>,
+}
+
+impl SampleSet {
+    const EMPTY: (u64, u64) = (u64::MAX, u64::MAX);
+    const SLOTS: usize = 2 * SAMPLE_TASKS;
+    const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+    fn new() -> SampleSet {
+        SampleSet {
+            len: 0,
+            slots: vec![Self::EMPTY; Self::SLOTS],
+        }
+    }
+
+    /// Insert the `key` and return `true` if it was not present. The set must not be full.
+    fn insert(&mut self, key: (u64, u64)) -> bool {
+        debug_assert!(self.len < SAMPLE_TASKS);
+        let hash = key.0.wrapping_mul(Self::SEED) ^ key.1.wrapping_mul(Self::SEED).rotate_left(32);
+        // The number of slots is a power of two.
+        let mut index = (hash >> 32) as usize % Self::SLOTS;
+        loop {
+            let slot = &mut self.slots[index];
+            if *slot == key {
+                return false;
+            }
+            if *slot == Self::EMPTY {
+                *slot = key;
+                self.len += 1;
+                return true;
+            }
+            index = (index + 1) % Self::SLOTS;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::_impl_::test_util::variables;
+    use super::{estimate_result_capacity, SAMPLE_TASKS, SAMPLE_THRESHOLD};
+    use crate::v2::{Bdd, NodeId};
+
+    /// **(internal)** The lookup table of `and` (see `Bdd::and`).
+    fn and_table(l: NodeId, r: NodeId) -> NodeId {
+        if l.is_zero() || r.is_zero() {
+            NodeId::ZERO
+        } else if l.is_one() && r.is_one() {
+            NodeId::ONE
+        } else {
+            NodeId::UNDEFINED
+        }
+    }
+
+    #[test]
+    pub fn capacity_estimate_test() {
+        let x = variables(34);
+
+        // A small operation is not sampled, the estimate is the size of the left operand.
+        let left = x[0].xor(&x[1]).xor(&x[2]);
+        let right = x[1].or(&x[3]);
+        let result = left.and(&right);
+        let estimate = estimate_result_capacity(&left, &right, &and_table);
+        assert!(estimate >= result.node_count());
+        assert!(estimate <= 16);
+
+        // Disjoint variables: every left node meets every right node, so the result is much
+        // larger than the left operand, and a sample cannot cover it.
+        let mut left = Bdd::new_false();
+        let mut right = Bdd::new_false();
+        for i in 0..10 {
+            left = left.or(&x[2 * i].iff(&x[2 * i + 1]).xor(&x[24 + i]));
+            right = right.or(&x[24 + i].and(&x[12 + i]));
+        }
+        assert!(left.node_count() > SAMPLE_THRESHOLD);
+        let result = left.and(&right);
+        assert!(result.node_count() > SAMPLE_TASKS);
+        let estimate = estimate_result_capacity(&left, &right, &and_table);
+        assert!(estimate > left.node_count());
+        assert!(estimate <= left.node_count() * right.node_count());
+
+        // Trivial operations.
+        let ff = Bdd::new_false();
+        assert_eq!(16, estimate_result_capacity(&ff, &x[0], &and_table));
+    }
+}
@@ -0,0 +1,210 @@
+//! Selects the strategy of every binary operation on `Bdds`.
+//!
+//! All binary operations consult `select` before they start. The decision is based on
+//! the operand sizes, their sortedness flags, an estimate of the amount of work
+//! (`estimate_task_count`) and the `ApplyOptions`. The rules are evaluated in order
+//! and the first rule which applies is used:
+//!
+//! | # | Condition                                                          | Strategy       |
+//! |---|--------------------------------------------------------------------|----------------|
+//! | 1 | The right operand is larger than the left operand.                 | `Swap`         |
+//! | 2 | `options.precondition` is `Some(true)` and an operand is unsorted. | `Precondition` |
+//! | 3 | `options.precondition` is `None`, an operand is unsorted, both the | `Precondition` |
+//! |   | left operand and the estimated task count exceed                   |                |
+//! |   | `PRECONDITION_THRESHOLD`, and the sorted copies of the unsorted    |                |
+//! |   | operands fit into `options.memory_budget`.                         |                |
+//! | 4 | `options.kernel` is set and the operands fit into it.              | `Apply(kernel)`|
+//! | 5 | The operands fit into the `U16` kernel.                            | `Apply(U16)`   |
+//! | 6 | The operands fit into the `U32` kernel.                            | `Apply(U32)`   |
+//! | 7 | Otherwise.                                                         | `Apply(U48)`   |
+//!
+//! After `Swap` or `Precondition`, the operation is restarted with the new operands, so the
+//! final strategy is always `Apply`. Swapping ensures that the larger operand is always
+//! on the left, which is what the kernels are optimized for (mirrored operations such as
+//! `imp`/`inv_imp` are used for asymmetric operators).
+//!
+//! Rule 5 targets hot loops which repeatedly combine tiny `Bdds` (e.g. constraint builders).
+//! For these, the allocation of the caches of the general kernels dominates the actual work.
+
+use crate::v2::small::MAX_VARIABLES;
+use crate::v2::{ApplyKernel, ApplyOptions, Bdd, BddNode};
+use core::cmp::max;
+use core::mem::size_of;
+
+use super::u32::{MAX_LEFT_SIZE, MAX_RIGHT_SIZE};
+
+/// Operations where both the larger operand and the estimated number of tasks exceed this
+/// number are considered "big" and their operands are sorted in DFS preorder before
+/// the operation starts (unless they are already known to be sorted).
+///
+/// The task cache relies on the operands being (more or less) sorted to preserve locality,
+/// but this only really matters once the cache no longer fits into L3, which happens
+/// at around 100k nodes. Below that, the sorting is usually more expensive than
+/// the speedup it provides.
+pub const PRECONDITION_THRESHOLD: u64 = 1 << 17;
+
+/// Operations where both operands have at most this many nodes (and at most
+/// `small::MAX_VARIABLES` variables) use the `U16` kernel.
+///
+/// The whole state of the kernel for this capacity takes less than 8kB, so it stays
+/// in the L1 cache. Operands of this size rarely produce results which do not fit.
+pub const SMALL_KERNEL_CAPACITY: usize = 256;
+
+/// The strategy selected for a binary operation.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Strategy {
+    /// Restart the operation with swapped (mirrored) operands.
+    Swap,
+    /// Restart the operation with operands sorted in DFS preorder.
+    Precondition,
+    /// Run the given kernel.
+    Apply(ApplyKernel),
+}
+
+/// An upper bound on the number of tasks (pairs of operand nodes) that an operation on
+/// `left` and `right` can visit.
+///
+/// Every task is a pair of nodes, so there are at most `left * right` tasks. At the same time,
+/// a task is reached by following the same (partial) valuation in both operands, so there are
+/// at most `2^k` tasks with decision variable `k`, and `2^(variables + 1)` tasks in total.
+pub fn estimate_task_count(left: &Bdd, right: &Bdd) -> u64 {
+    let pairs = (left.node_count() as u64).saturating_mul(right.node_count() as u64);
+    let variables = u32::from(max(left.variable_count(), right.variable_count()));
+    let functions = 1u64.checked_shl(variables + 1).unwrap_or(u64::MAX);
+    pairs.min(functions)
+}
+
+/// Select the strategy for a binary operation on `left` and `right` (see the module
+/// documentation for the decision table).
+pub fn select(left: &Bdd, right: &Bdd, options: &ApplyOptions) -> Strategy {
+    let left_nodes = left.node_count() as u64;
+    let right_nodes = right.node_count() as u64;
+    if right_nodes > left_nodes {
+        return Strategy::Swap;
+    }
+
+    let is_sorted = left.is_sorted() && right.is_sorted();
+    if !is_sorted {
+        let is_big = left_nodes > PRECONDITION_THRESHOLD
+            && estimate_task_count(left, right) > PRECONDITION_THRESHOLD;
+        let precondition = match options.precondition {
+            Some(forced) => forced,
+            None => is_big && copy_size(left, right) <= options.memory_budget.unwrap_or(usize::MAX),
+        };
+        if precondition {
+            return Strategy::Precondition;
+        }
+    }
+
+    let variables = max(left.variable_count(), right.variable_count());
+    let fits_u16 = fits_u16(left_nodes, right_nodes, variables);
+    let fits_u32 = fits_u32(left_nodes, right_nodes);
+    match options.kernel {
+        Some(ApplyKernel::U16) if fits_u16 => Strategy::Apply(ApplyKernel::U16),
+        Some(ApplyKernel::U16) | Some(ApplyKernel::U32) if fits_u32 => {
+            Strategy::Apply(ApplyKernel::U32)
+        }
+        Some(_) => Strategy::Apply(ApplyKernel::U48),
+        None if fits_u16 => Strategy::Apply(ApplyKernel::U16),
+        None if fits_u32 => Strategy::Apply(ApplyKernel::U32),
+        None => Strategy::Apply(ApplyKernel::U48),
+    }
+}
+
+/// True if operands with the given node counts and number of `variables` can be processed
+/// by the `U16` kernel.
+pub fn fits_u16(left_nodes: u64, right_nodes: u64, variables: u16) -> bool {
+    let capacity = SMALL_KERNEL_CAPACITY as u64;
+    left_nodes <= capacity && right_nodes <= capacity && usize::from(variables) <= MAX_VARIABLES
+}
+
+/// True if operands with the given node counts can be processed by the `U32` kernel.
+pub fn fits_u32(left_nodes: u64, right_nodes: u64) -> bool {
+    left_nodes < MAX_LEFT_SIZE && right_nodes < MAX_RIGHT_SIZE
+}
+
+/// **(internal)** The number of bytes needed for sorted copies of the unsorted operands.
+fn copy_size(left: &Bdd, right: &Bdd) -> usize {
+    [left, right]
+        .iter()
+        .filter(|bdd| !bdd.is_sorted())
+        .map(|bdd| bdd.node_count() * size_of::<BddNode>())
+        .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{estimate_task_count, fits_u16, fits_u32, select, Strategy};
+    use crate::v2::{ApplyKernel, ApplyOptions, Bdd, VariableId};
+
+    #[test]
+    pub fn dispatcher_decision_table_test() {
+        let small = Bdd::new_variable(VariableId::from(0));
+        let mut large = small.and(&Bdd::new_variable(VariableId::from(1)));
+        large.sort_preorder();
+        let unsorted = Bdd::new_variable(VariableId::from(2)).or(&large);
+        assert!(!unsorted.is_sorted());
+        let defaults = ApplyOptions::default();
+
+        assert_eq!(Strategy::Swap, select(&small, &large, &defaults));
+        assert_eq!(Strategy::Apply(ApplyKernel::U16), select(&large, &small, &defaults));
+        // Small operations are never sorted, unless forced.
+        assert_eq!(Strategy::Apply(ApplyKernel::U16), select(&unsorted, &small, &defaults));
+        let forced = ApplyOptions {
+            precondition: Some(true),
+            ..defaults
+        };
+        assert_eq!(Strategy::Precondition, select(&unsorted, &small, &forced));
+        assert_eq!(Strategy::Apply(ApplyKernel::U16), select(&large, &small, &forced));
+        let u48 = ApplyOptions {
+            kernel: Some(ApplyKernel::U48),
+            ..defaults
+        };
+        assert_eq!(Strategy::Apply(ApplyKernel::U48), select(&large, &small, &u48));
+        let u32 = ApplyOptions {
+            kernel: Some(ApplyKernel::U32),
+            ..defaults
+        };
+        assert_eq!(Strategy::Apply(ApplyKernel::U32), select(&large, &small, &u32));
+        // Too many variables for the `U16` kernel.
+        let wide = Bdd::new_variable(VariableId::from(64));
+        assert_eq!(Strategy::Apply(ApplyKernel::U32), select(&wide, &small, &defaults));
+        let u16 = ApplyOptions {
+            kernel: Some(ApplyKernel::U16),
+            ..defaults
+        };
+        assert_eq!(Strategy::Apply(ApplyKernel::U32), select(&wide, &small, &u16));
+
+        // Two variables: 4 * 3 node pairs, but at most 2^3 tasks.
+        assert_eq!(8, estimate_task_count(&large, &small));
+
+        // The forced kernel is actually used and gives the same result.
+        let expected = large.xor(&unsorted);
+        for options in [defaults, forced, u48, u32, u16] {
+            let result = large.xor_with(&unsorted, &options);
+            assert_eq!(1, result.xor(&expected).node_count());
+        }
+    }
+
+    #[test]
+    pub fn u32_limits_test() {
+        // The documented limits of `ApplyKernel::U32`: less than `2^32 - 1` nodes
+        // on the left and less than `2^31 - 1` nodes on the right.
+        let left_limit = (1u64 << 32) - 1;
+        let right_limit = (1u64 << 31) - 1;
+        assert!(fits_u32(left_limit - 1, right_limit - 1));
+        assert!(!fits_u32(left_limit, right_limit - 1));
+        assert!(!fits_u32(left_limit - 1, right_limit));
+        assert!(fits_u32(left_limit - 1, 1));
+        assert!(fits_u32(1, right_limit - 1));
+        assert!(!fits_u32(Bdd::MAX_NODE_COUNT, 1));
+    }
+
+    #[test]
+    pub fn u16_limits_test() {
+        assert!(fits_u16(256, 256, 64));
+        assert!(!fits_u16(257, 1, 64));
+        assert!(!fits_u16(1, 257, 64));
+        assert!(!fits_u16(2, 2, 65));
+    }
+}
@@ -1,4 +1,17 @@
-use crate::v2::{Bdd, NodeId};
+use crate::logging::OperationSpan;
+use crate::v2::_impl_::bdd::binary_operations::dispatcher::Strategy;
+use crate::v2::ops::{And, AndNot, BooleanOp, Iff, Imp, NotAnd, Or, Xor};
+use crate::v2::{
+    ApplyDiagnostics, ApplyKernel, ApplyOptions, Bdd, BddNode, BddStorage, BoolOp, NodeId,
+    ReplacementPolicy, TaskCacheConfig,
+};
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::Cell;
+use core::cmp::max;
+use core::convert::TryFrom;
 
 /// **(internal)** A general `apply` algorithm suitable for any `Bdd`.
 mod u48;
@@ -10,95 +23,264 @@ mod u48;
 /// 10-15% improvement, so why not do it.
 mod u32;
 
-impl Bdd {
-    /// A logical conjunction of two `Bdd` objects.
-    pub fn and(&self, other: &Bdd) -> Bdd {
-        let self_nodes = self.node_count() as u64;
-        let other_nodes = other.node_count() as u64;
-        if other_nodes > self_nodes {
-            other.and(self)
+/// **(internal)** An `apply` algorithm for tiny `Bdds` which reuses the stack-allocated
+/// `SmallBdd` kernel. Apart from the exported result, it never touches the heap, and its
+/// whole state (a few kilobytes) stays in the L1 cache.
+mod small;
+
+/// **(internal)** Estimation of the initial node cache capacity.
+mod capacity;
+
+/// Conjunction and disjunction of many `Bdds` (see `Bdd::and_all`).
+mod batch;
+
+/// Selection of the apply kernel and operand preconditioning.
+pub mod dispatcher;
+
+/// **(internal)** Extra stack entries on top of the theoretical maximum.
+const STACK_SAFETY_MARGIN: usize = 4;
+
+/// **(internal)** The number of entries of a "coupled DFS" stack for a search which expands
+/// at most `height` tasks on any path: two entries per expanded task, the root task, and
+/// the fake bottom entry.
+fn stack_capacity(height: usize) -> usize {
+    height
+        .checked_mul(2)
+        .and_then(|it| it.checked_add(2 + STACK_SAFETY_MARGIN))
+        .expect("Stack capacity overflow.")
+}
+
+/// **(internal)** Evaluates `$apply` with `$l` and `$r` bound to the operands ordered such
+/// that the larger one is on the left, and `$t` bound to the lookup table for this order
+/// (the `$mirrored` table if the operands are swapped).
+///
+/// The `U48` kernels expect the larger operand on the left, since the task cache
+/// is indexed by the left node (see `TaskCache::new`).
+macro_rules! larger_first {
+    (
+        ($left:expr, $right:expr, $table:expr, $mirrored:expr),
+        |$l:ident, $r:ident, $t:ident| $apply:expr
+    ) => {
+        if $right.node_count() > $left.node_count() {
+            let ($l, $r, $t) = ($right, $left, $mirrored);
+            $apply
         } else {
-            if self_nodes < u32::MAX_LEFT_SIZE && other_nodes < u32::MAX_RIGHT_SIZE {
-                self._u32_and(other)
+            let ($l, $r, $t) = ($left, $right, $table);
+            $apply
+        }
+    };
+}
+
+impl Bdd {
+    /// **(internal)** Copies of the operands which are sorted in DFS preorder. Operands which
+    /// are already sorted are borrowed instead.
+    fn precondition<'a>(&'a self, other: &'a Bdd) -> (Cow<'a, Bdd>, Cow<'a, Bdd>) {
+        fn sorted(bdd: &Bdd) -> Cow<'_, Bdd> {
+            if bdd.is_sorted() {
+                Cow::Borrowed(bdd)
             } else {
-                self._u48_and(other)
+                let mut copy = bdd.clone();
+                copy.sort_preorder();
+                Cow::Owned(copy)
             }
         }
+        (sorted(self), sorted(other))
     }
 
-    /// A logical disjunction of two `Bdd` objects.
-    pub fn or(&self, other: &Bdd) -> Bdd {
-        let self_nodes = self.node_count() as u64;
-        let other_nodes = other.node_count() as u64;
-        if other_nodes > self_nodes {
-            other.or(self)
+    /// **(internal)** Run one of the given kernels (selected by `kernel`), reporting
+    /// the operation to the log. If the result does not fit into the `u16` kernel,
+    /// the operation is repeated using the `u32` kernel.
+    fn run_kernel<K16, K32, K48>(
+        &self,
+        other: &Bdd,
+        name: &'static str,
+        kernel: ApplyKernel,
+        u16: K16,
+        u32: K32,
+        u48: K48,
+    ) -> Bdd
+    where
+        K16: FnOnce(&Bdd, &Bdd) -> Option<Bdd>,
+        K32: FnOnce(&Bdd, &Bdd) -> Bdd,
+        K48: FnOnce(&Bdd, &Bdd) -> Bdd,
+    {
+        let span = OperationSpan::start(name, self.node_count(), other.node_count());
+        let result = match kernel {
+            ApplyKernel::U16 => u16(self, other).unwrap_or_else(|| u32(self, other)),
+            ApplyKernel::U32 => u32(self, other),
+            ApplyKernel::U48 => u48(self, other),
+        };
+        span.finish(result.node_count());
+        result.debug_check_integrity(name);
+        result
+    }
+
+    /// **(internal)** Create a copy of an operand `Bdd` which is used as a result
+    /// of an operation, instead of exporting an equivalent `Bdd` from the node cache.
+    ///
+    /// The apply algorithms detect that the result is equivalent to one of the operands
+    /// by checking that every terminal result of a task `(left, right)` is exactly `left`
+    /// (or `right`, respectively). If this holds, then by induction, the result of every
+    /// task is equivalent to its `left` (`right`) node: Tasks which follow the decision
+    /// variable of `left` simply recreate the `left` node, and tasks which follow only
+    /// the variable of `right` produce the same result for both successors, hence they
+    /// are reduced into the (equivalent) result of `left`.
+    pub(super) fn _operand_copy(&self, variables: u16) -> Bdd {
+        let mut result = self.clone();
+        result.update_variable_count(variables);
+        result
+    }
+
+    /// **(internal)** Create a constant `Bdd` with the given terminal `value`. This is used
+    /// when the result of an operation is known before the apply algorithm starts.
+    pub(super) fn _new_constant(value: NodeId, variables: u16) -> Bdd {
+        let mut result = if value.is_zero() {
+            Bdd::new_false()
         } else {
-            if self_nodes < u32::MAX_LEFT_SIZE && other_nodes < u32::MAX_RIGHT_SIZE {
-                self._u32_or(other)
-            } else {
-                self._u48_or(other)
+            Bdd::true_with_capacity(2)
+        };
+        result.update_variable_count(variables);
+        result
+    }
+}
+
+impl Bdd {
+    /// A binary logical operation given by the operator `OP` (see `ops::BooleanOp`).
+    ///
+    /// Unlike `Bdd::binary_operation`, the operation uses a copy of the apply algorithm
+    /// specialized to `OP`, so custom operators are as fast as the pre-defined ones.
+    pub fn apply<OP: BooleanOp>(&self, other: &Bdd) -> Bdd {
+        self.apply_with::<OP>(other, &ApplyOptions::default())
+    }
+
+    /// Same as `Bdd::apply`, but with explicit `ApplyOptions`.
+    pub fn apply_with<OP: BooleanOp>(&self, other: &Bdd, options: &ApplyOptions) -> Bdd {
+        self.apply_in_buffer::<OP>(other, options, Vec::new())
+    }
+
+    /// Same as `Bdd::apply`, but the result replaces the `target` `Bdd`, reusing the memory
+    /// of its nodes. If the `target` has enough capacity for the result, no memory is
+    /// allocated for the result nodes.
+    ///
+    /// An operation cannot write its result into the memory of its own operand, but iterative
+    /// computations (e.g. a fixed-point) can alternate between two `Bdds` instead:
+    /// `reached.apply_into::<Or>(&step, &mut next)`, followed by `swap(&mut reached, &mut next)`.
+    /// The memory is only reused when the result is built by the `U32` or `U48` kernel.
+    pub fn apply_into<OP: BooleanOp>(&self, other: &Bdd, target: &mut Bdd) {
+        self.apply_into_with::<OP>(other, target, &ApplyOptions::default())
+    }
+
+    /// Same as `Bdd::apply_into`, but with explicit `ApplyOptions`.
+    pub fn apply_into_with<OP: BooleanOp>(
+        &self,
+        other: &Bdd,
+        target: &mut Bdd,
+        options: &ApplyOptions,
+    ) {
+        let buffer = core::mem::take(&mut target.nodes);
+        *target = self.apply_in_buffer::<OP>(other, options, buffer);
+    }
+
+    /// Same as `Bdd::apply_into`, but the operation is given by `BoolOp`.
+    pub fn apply_op_into(&self, op: BoolOp, other: &Bdd, target: &mut Bdd) {
+        match op {
+            BoolOp::And => self.apply_into::<And>(other, target),
+            BoolOp::Or => self.apply_into::<Or>(other, target),
+            BoolOp::Xor => self.apply_into::<Xor>(other, target),
+            BoolOp::Imp => self.apply_into::<Imp>(other, target),
+            BoolOp::Iff => self.apply_into::<Iff>(other, target),
+            BoolOp::AndNot => self.apply_into::<AndNot>(other, target),
+            BoolOp::NotAnd => self.apply_into::<NotAnd>(other, target),
+        }
+    }
+
+    /// **(internal)** Implementation of `Bdd::apply_with`. If the result is created by
+    /// the `U32` or `U48` kernel, its nodes are stored in the `buffer` (see `NodeCache::new`).
+    fn apply_in_buffer<OP: BooleanOp>(
+        &self,
+        other: &Bdd,
+        options: &ApplyOptions,
+        buffer: Vec<BddNode>,
+    ) -> Bdd {
+        if self.is_identical_to(other) {
+            // The result of `x op x` only depends on the diagonal of the truth table.
+            let variables = max(self.variable_count, other.variable_count);
+            match (OP::TABLE[0][0], OP::TABLE[1][1]) {
+                (false, true) => return self._operand_copy(variables),
+                (false, false) => return Bdd::_new_constant(NodeId::ZERO, variables),
+                (true, true) => return Bdd::_new_constant(NodeId::ONE, variables),
+                (true, false) => (),
+            }
+        }
+        match dispatcher::select(self, other, options) {
+            Strategy::Swap => other.apply_in_buffer::<OP::Mirror>(self, options, buffer),
+            Strategy::Precondition => {
+                let (left, right) = self.precondition(other);
+                left.apply_in_buffer::<OP>(&right, options, buffer)
+            }
+            Strategy::Apply(kernel) => {
+                // Only one of the kernels (other than `U16`) takes the buffer.
+                let buffer = Cell::new(buffer);
+                self.run_kernel(
+                    other,
+                    OP::NAME,
+                    kernel,
+                    Bdd::_u16_apply_op::<OP>,
+                    |l, r| l._u32_apply_op::<OP>(r, &options.task_cache, buffer.take()),
+                    |l, r| l._u48_apply_op::<OP>(r, &options.task_cache, buffer.take()),
+                )
             }
         }
     }
 
+    /// A logical conjunction of two `Bdd` objects.
+    pub fn and(&self, other: &Bdd) -> Bdd {
+        self.and_with(other, &ApplyOptions::default())
+    }
+
+    /// Same as `Bdd::and`, but with explicit `ApplyOptions`.
+    pub fn and_with(&self, other: &Bdd, options: &ApplyOptions) -> Bdd {
+        self.apply_with::<And>(other, options)
+    }
+
+    /// A logical disjunction of two `Bdd` objects.
+    pub fn or(&self, other: &Bdd) -> Bdd {
+        self.or_with(other, &ApplyOptions::default())
+    }
+
+    /// Same as `Bdd::or`, but with explicit `ApplyOptions`.
+    pub fn or_with(&self, other: &Bdd, options: &ApplyOptions) -> Bdd {
+        self.apply_with::<Or>(other, options)
+    }
+
     /// A logical implication of two `Bdd` objects.
     pub fn imp(&self, other: &Bdd) -> Bdd {
-        let self_nodes = self.node_count() as u64;
-        let other_nodes = other.node_count() as u64;
-        if other_nodes > self_nodes {
-            other.inv_imp(self)
-        } else {
-            if self_nodes < u32::MAX_LEFT_SIZE && other_nodes < u32::MAX_RIGHT_SIZE {
-                self._u32_imp(other)
-            } else {
-                self._u48_imp(other)
-            }
-        }
+        self.imp_with(other, &ApplyOptions::default())
     }
 
-    /// **(internal)** A mirrored implication operation.
-    fn inv_imp(&self, other: &Bdd) -> Bdd {
-        let self_nodes = self.node_count() as u64;
-        let other_nodes = other.node_count() as u64;
-        if other_nodes > self_nodes {
-            other.imp(self)
-        } else {
-            if self_nodes < u32::MAX_LEFT_SIZE && other_nodes < u32::MAX_RIGHT_SIZE {
-                self._u32_inv_imp(other)
-            } else {
-                self._u48_inv_imp(other)
-            }
-        }
+    /// Same as `Bdd::imp`, but with explicit `ApplyOptions`.
+    pub fn imp_with(&self, other: &Bdd, options: &ApplyOptions) -> Bdd {
+        self.apply_with::<Imp>(other, options)
     }
 
     /// A logical equivalence of two `Bdd` objects.
     pub fn iff(&self, other: &Bdd) -> Bdd {
-        let self_nodes = self.node_count() as u64;
-        let other_nodes = other.node_count() as u64;
-        if other_nodes > self_nodes {
-            other.iff(self)
-        } else {
-            if self_nodes < u32::MAX_LEFT_SIZE && other_nodes < u32::MAX_RIGHT_SIZE {
-                self._u32_iff(other)
-            } else {
-                self._u48_iff(other)
-            }
-        }
+        self.iff_with(other, &ApplyOptions::default())
+    }
+
+    /// Same as `Bdd::iff`, but with explicit `ApplyOptions`.
+    pub fn iff_with(&self, other: &Bdd, options: &ApplyOptions) -> Bdd {
+        self.apply_with::<Iff>(other, options)
     }
 
     /// A logical exclusive disjunction of two `Bdd` objects.
     pub fn xor(&self, other: &Bdd) -> Bdd {
-        let self_nodes = self.node_count() as u64;
-        let other_nodes = other.node_count() as u64;
-        if other_nodes > self_nodes {
-            other.xor(self)
-        } else {
-            if self_nodes < u32::MAX_LEFT_SIZE && other_nodes < u32::MAX_RIGHT_SIZE {
-                self._u32_xor(other)
-            } else {
-                self._u48_xor(other)
-            }
-        }
+        self.xor_with(other, &ApplyOptions::default())
+    }
+
+    /// Same as `Bdd::xor`, but with explicit `ApplyOptions`.
+    pub fn xor_with(&self, other: &Bdd, options: &ApplyOptions) -> Bdd {
+        self.apply_with::<Xor>(other, options)
     }
 
     /// A logical conjunction with a negated send argument of two `Bdd` objects.
@@ -106,31 +288,31 @@ impl Bdd {
     /// This method is used for set difference when using `Bdd` as a set representation.
     /// That is why it warrants a separate method.
     pub fn and_not(&self, other: &Bdd) -> Bdd {
-        let self_nodes = self.node_count() as u64;
-        let other_nodes = other.node_count() as u64;
-        if other_nodes > self_nodes {
-            other.not_and(self)
-        } else {
-            if self_nodes < u32::MAX_LEFT_SIZE && other_nodes < u32::MAX_RIGHT_SIZE {
-                self._u32_and_not(other)
-            } else {
-                self._u48_and_not(other)
-            }
-        }
+        self.and_not_with(other, &ApplyOptions::default())
     }
 
-    /// **(internal)** A mirrored `and_not` operation.
-    fn not_and(&self, other: &Bdd) -> Bdd {
-        let self_nodes = self.node_count() as u64;
-        let other_nodes = other.node_count() as u64;
-        if other_nodes > self_nodes {
-            other.and_not(self)
-        } else {
-            if self_nodes < u32::MAX_LEFT_SIZE && other_nodes < u32::MAX_RIGHT_SIZE {
-                self._u32_not_and(other)
-            } else {
-                self._u48_not_and(other)
-            }
+    /// Same as `Bdd::and_not`, but with explicit `ApplyOptions`.
+    pub fn and_not_with(&self, other: &Bdd, options: &ApplyOptions) -> Bdd {
+        self.apply_with::<AndNot>(other, options)
+    }
+
+    /// Apply the logical operation selected by `op`. This is equivalent to calling
+    /// the corresponding method (e.g. `Bdd::and` for `BoolOp::And`), so the operation
+    /// uses the same specialized kernels.
+    pub fn apply_op(&self, op: BoolOp, other: &Bdd) -> Bdd {
+        self.apply_op_with(op, other, &ApplyOptions::default())
+    }
+
+    /// Same as `Bdd::apply_op`, but with explicit `ApplyOptions`.
+    pub fn apply_op_with(&self, op: BoolOp, other: &Bdd, options: &ApplyOptions) -> Bdd {
+        match op {
+            BoolOp::And => self.apply_with::<And>(other, options),
+            BoolOp::Or => self.apply_with::<Or>(other, options),
+            BoolOp::Xor => self.apply_with::<Xor>(other, options),
+            BoolOp::Imp => self.apply_with::<Imp>(other, options),
+            BoolOp::Iff => self.apply_with::<Iff>(other, options),
+            BoolOp::AndNot => self.apply_with::<AndNot>(other, options),
+            BoolOp::NotAnd => self.apply_with::<NotAnd>(other, options),
         }
     }
 
@@ -145,16 +327,982 @@ impl Bdd {
     where
         TABLE: Fn(NodeId, NodeId) -> NodeId,
     {
-        let self_nodes = self.node_count() as u64;
-        let other_nodes = other.node_count() as u64;
-        if other_nodes > self_nodes {
-            other.binary_operation(self, |l, r| table(r, l))
+        self.binary_operation_with(other, table, &ApplyOptions::default())
+    }
+
+    /// Same as `Bdd::binary_operation`, but with explicit `ApplyOptions`.
+    pub fn binary_operation_with<TABLE>(
+        &self,
+        other: &Bdd,
+        table: TABLE,
+        options: &ApplyOptions,
+    ) -> Bdd
+    where
+        TABLE: Fn(NodeId, NodeId) -> NodeId,
+    {
+        self.binary_operation_impl(other, &table, false, options)
+    }
+
+    /// **(internal)** Implementation of `Bdd::binary_operation_with`. If `mirrored` is set,
+    /// the arguments of the `table` are swapped (i.e. the operands were swapped).
+    ///
+    /// The swapped table cannot be simply wrapped in a new closure, because each recursive
+    /// call would then create a new instance of this function.
+    fn binary_operation_impl<TABLE>(
+        &self,
+        other: &Bdd,
+        table: &TABLE,
+        mirrored: bool,
+        options: &ApplyOptions,
+    ) -> Bdd
+    where
+        TABLE: Fn(NodeId, NodeId) -> NodeId,
+    {
+        match dispatcher::select(self, other, options) {
+            Strategy::Swap => other.binary_operation_impl(self, table, !mirrored, options),
+            Strategy::Precondition => {
+                let (left, right) = self.precondition(other);
+                left.binary_operation_impl(&right, table, mirrored, options)
+            }
+            Strategy::Apply(kernel) if mirrored => {
+                let table = |l, r| table(r, l);
+                self.run_kernel(
+                    other,
+                    "binary_operation",
+                    kernel,
+                    |l, r| l._u16_apply(r, table),
+                    |l, r| u32::_u32_apply(l, r, table, &options.task_cache, Vec::new()),
+                    |l, r| u48::_u48_apply(l, r, table, &options.task_cache),
+                )
+            }
+            Strategy::Apply(kernel) => self.run_kernel(
+                other,
+                "binary_operation",
+                kernel,
+                |l, r| l._u16_apply(r, table),
+                |l, r| u32::_u32_apply(l, r, table, &options.task_cache, Vec::new()),
+                |l, r| u48::_u48_apply(l, r, table, &options.task_cache),
+            ),
+        }
+    }
+
+    /// A general binary operation on two operands which can use different storage types,
+    /// e.g. a `BddView` of a huge memory-mapped `Bdd` and a small in-memory `Bdd`.
+    /// The `TABLE` is the same as in `Bdd::binary_operation`.
+    ///
+    /// The operands are used as they are (they are not sorted or copied), and the operation
+    /// always uses the `U48` kernel.
+    pub fn mixed_binary_operation<L, R, TABLE>(left: &L, right: &R, table: TABLE) -> Bdd
+    where
+        L: BddStorage + ?Sized,
+        R: BddStorage + ?Sized,
+        TABLE: Fn(NodeId, NodeId) -> NodeId,
+    {
+        let (left_size, right_size) = (left.node_count(), right.node_count());
+        let span = OperationSpan::start("mixed_binary_operation", left_size, right_size);
+        let config = TaskCacheConfig::default();
+        let result = larger_first!((left, right, &table, |l, r| table(r, l)), |l, r, table| {
+            u48::_u48_apply(l, r, table, &config)
+        });
+        span.finish(result.node_count());
+        result.debug_check_integrity("mixed_binary_operation");
+        result
+    }
+
+    /// Same as `Bdd::binary_operation`, but the result is not materialized. Instead, every
+    /// created node is passed to the `sink` together with its id, and the id of the result
+    /// root is returned (`NodeId::ZERO` or `NodeId::ONE` if the result is constant).
+    ///
+    /// The nodes are reported bottom-up: children are always reported before their parents
+    /// and ids are assigned in increasing order starting with `2`. Only the (fixed-size)
+    /// unique table is kept in memory, so this is suitable for operations where only
+    /// aggregate information about the result is needed (its size, the number of nodes
+    /// per variable, etc.). Same as `Bdd::binary_operation`, the reported graph is not
+    /// guaranteed to be minimal.
+    pub fn binary_operation_streaming<TABLE, SINK>(
+        &self,
+        other: &Bdd,
+        table: TABLE,
+        mut sink: SINK,
+    ) -> NodeId
+    where
+        TABLE: Fn(NodeId, NodeId) -> NodeId,
+        SINK: FnMut(NodeId, BddNode),
+    {
+        let span = OperationSpan::start("streaming", self.node_count(), other.node_count());
+        let mut created = 0;
+        let counting_sink = |id: NodeId, node: BddNode| {
+            created += 1;
+            sink(id, node);
+        };
+        let config = TaskCacheConfig::default();
+        let root = larger_first!((self, other, &table, |l, r| table(r, l)), |l, r, table| {
+            u48::_u48_apply_streaming(l, r, table, None, &config, counting_sink)
+        });
+        // Same as `Bdd::node_count`, the reported size includes the terminal nodes.
+        span.finish(created + 2);
+        root
+    }
+
+    /// Same as `Bdd::mixed_binary_operation`, but the result is written into a new file
+    /// at the given `path`, which is then mapped into memory (requires the `mmap` feature).
+    ///
+    /// Together with `MmapBddStorage` operands, this allows operations on `Bdds` which do
+    /// not fit into RAM: the operands and the result are only accessed through the page
+    /// cache of the operating system, and the only data structures kept in memory are
+    /// the task cache and the unique table, which share the given `memory_budget` (in bytes).
+    /// Both are fixed-size and leaky, so a smaller budget means more recomputation,
+    /// and the result may contain duplicate nodes (see `Bdd::binary_operation_streaming`).
+    /// Use `Bdd::reduce` on the result if the canonical form is needed (and it fits into RAM).
+    #[cfg(feature = "mmap")]
+    pub fn external_binary_operation<L, R, TABLE, P>(
+        left: &L,
+        right: &R,
+        table: TABLE,
+        memory_budget: usize,
+        path: P,
+    ) -> Result<crate::v2::MmapBddStorage, String>
+    where
+        L: BddStorage + ?Sized,
+        R: BddStorage + ?Sized,
+        TABLE: Fn(NodeId, NodeId) -> NodeId,
+        P: AsRef<std::path::Path>,
+    {
+        use crate::v2::_impl_::mmap::NodeWriter;
+        use core::mem::size_of;
+
+        let (left_size, right_size) = (left.node_count(), right.node_count());
+        let span = OperationSpan::start("external_binary_operation", left_size, right_size);
+        // Half of the budget for each table. The unique table has two slots per node.
+        let task_entry = 2 * size_of::<NodeId>() + size_of::<NodeId>();
+        let node_entry = 2 * (size_of::<BddNode>() + size_of::<NodeId>());
+        let config = TaskCacheConfig {
+            capacity: Some(max(memory_budget / 2 / task_entry, 2)),
+            ..Default::default()
+        };
+        let node_capacity = Some(max(memory_budget / 2 / node_entry, 1));
+
+        let mut writer = NodeWriter::create(path.as_ref())?;
+        writer.write(BddNode::ZERO);
+        writer.write(BddNode::ONE);
+        let mut created = 0;
+        let sink = |_: NodeId, node: BddNode| {
+            created += 1;
+            writer.write(node);
+        };
+        let root = larger_first!((left, right, &table, |l, r| table(r, l)), |l, r, table| {
+            u48::_u48_apply_streaming(l, r, table, node_capacity, &config, sink)
+        });
+        writer.finish()?;
+        if root.as_index() < 2 {
+            // Constant results are stored without the (unreachable) nodes created on the way.
+            let mut writer = NodeWriter::create(path.as_ref())?;
+            writer.write(BddNode::ZERO);
+            if root.is_one() {
+                writer.write(BddNode::ONE);
+            }
+            writer.finish()?;
+        } else {
+            // The root has the smallest variable of all result nodes, so it cannot be equal
+            // to any other node and it is always created last.
+            debug_assert_eq!(root.as_index(), created + 1);
+        }
+        span.finish(created + 2);
+        crate::v2::MmapBddStorage::open(path)
+    }
+}
+
+impl Bdd {
+    /// Same as `Bdd::binary_operation`, but `yield_fn` is called after every `period` expanded
+    /// tasks (rounded up to a power of two). This allows embedding long operations into
+    /// cooperative schedulers (e.g. a GUI thread), where `yield_fn` handles pending events.
+    ///
+    /// Same as `Bdd::binary_operation_with_timeout`, the operands are used as they are and
+    /// the operation always uses the `U48` kernel.
+    pub fn binary_operation_with_yield<TABLE, YIELD>(
+        &self,
+        other: &Bdd,
+        table: TABLE,
+        period: u64,
+        mut yield_fn: YIELD,
+    ) -> Bdd
+    where
+        TABLE: Fn(NodeId, NodeId) -> NodeId,
+        YIELD: FnMut(),
+    {
+        let span = OperationSpan::start("yield", self.node_count(), other.node_count());
+        let interrupt = |_: &ApplyDiagnostics| {
+            yield_fn();
+            false
+        };
+        let config = TaskCacheConfig::default();
+        let result = larger_first!((self, other, &table, |l, r| table(r, l)), |l, r, table| {
+            u48::_u48_apply_interruptible(l, r, table, &config, Vec::new(), period, interrupt)
+        });
+        let result = match result {
+            Ok((result, _)) => result,
+            Err(_) => unreachable!("The operation cannot be interrupted."),
+        };
+        span.finish(result.node_count());
+        result
+    }
+
+    /// Same as `Bdd::apply`, but also returns the `ApplyDiagnostics` of the operation, i.e.
+    /// the task and node cache statistics and the stack high-water mark.
+    ///
+    /// Same as `Bdd::binary_operation_with_timeout`, the operands are used as they are and
+    /// the operation always uses the `U48` kernel. The `elapsed` time is only measured when
+    /// a system clock is available (i.e. not in `no_std` and `wasm` builds).
+    pub fn apply_with_stats<OP: BooleanOp>(&self, other: &Bdd) -> (Bdd, ApplyDiagnostics) {
+        const PERIOD: u64 = u48::INTERRUPT_PERIOD;
+        let span = OperationSpan::start(OP::NAME, self.node_count(), other.node_count());
+        #[cfg(all(feature = "std", not(feature = "wasm")))]
+        let start = std::time::Instant::now();
+        let never = |_: &ApplyDiagnostics| false;
+        let config = TaskCacheConfig::default();
+        let mirrored = <OP::Mirror as BooleanOp>::lookup;
+        let result = larger_first!((self, other, OP::lookup, mirrored), |l, r, table| {
+            u48::_u48_apply_interruptible(l, r, table, &config, Vec::new(), PERIOD, never)
+        });
+        let (result, diagnostics) = match result {
+            Ok(result) => result,
+            Err(_) => unreachable!("The operation cannot be interrupted."),
+        };
+        #[cfg(all(feature = "std", not(feature = "wasm")))]
+        let diagnostics = ApplyDiagnostics {
+            elapsed: start.elapsed(),
+            ..diagnostics
+        };
+        span.finish(result.node_count());
+        result.debug_check_integrity(OP::NAME);
+        (result, diagnostics)
+    }
+
+    /// Same as `Bdd::apply`, but the operation is aborted (returning `None`) once the result
+    /// has more than `node_limit` nodes (including the two terminal nodes).
+    ///
+    /// The node count is checked after every expanded task, so the operation never creates
+    /// more than a few nodes over the limit, and since the search is deterministic, the same
+    /// operands always give the same outcome. The operands are used as they are and
+    /// the operation always uses the `U48` kernel.
+    pub fn try_apply<OP: BooleanOp>(&self, other: &Bdd, node_limit: usize) -> Option<Bdd> {
+        let span = OperationSpan::start(OP::NAME, self.node_count(), other.node_count());
+        let limit = node_limit as u64;
+        let exceeded = |progress: &ApplyDiagnostics| progress.nodes_created + 2 > limit;
+        let config = TaskCacheConfig::default();
+        let mirrored = <OP::Mirror as BooleanOp>::lookup;
+        let result = larger_first!((self, other, OP::lookup, mirrored), |l, r, table| {
+            u48::_u48_apply_interruptible(l, r, table, &config, Vec::new(), 1, exceeded)
+        });
+        match result {
+            // The last nodes can be created after the last expanded task.
+            Ok((result, _)) if result.node_count() <= node_limit => {
+                span.finish(result.node_count());
+                Some(result)
+            }
+            _ => {
+                debug!("{}: result exceeds {} nodes.", OP::NAME, node_limit);
+                None
+            }
+        }
+    }
+
+    /// Same as `Bdd::and`, but returns `None` if the result has more than `node_limit` nodes
+    /// (see `Bdd::try_apply`).
+    pub fn try_and(&self, other: &Bdd, node_limit: usize) -> Option<Bdd> {
+        self.try_apply::<And>(other, node_limit)
+    }
+
+    /// Same as `Bdd::or`, but returns `None` if the result has more than `node_limit` nodes
+    /// (see `Bdd::try_apply`).
+    pub fn try_or(&self, other: &Bdd, node_limit: usize) -> Option<Bdd> {
+        self.try_apply::<Or>(other, node_limit)
+    }
+
+    /// Same as `Bdd::imp`, but returns `None` if the result has more than `node_limit` nodes
+    /// (see `Bdd::try_apply`).
+    pub fn try_imp(&self, other: &Bdd, node_limit: usize) -> Option<Bdd> {
+        self.try_apply::<Imp>(other, node_limit)
+    }
+
+    /// Same as `Bdd::iff`, but returns `None` if the result has more than `node_limit` nodes
+    /// (see `Bdd::try_apply`).
+    pub fn try_iff(&self, other: &Bdd, node_limit: usize) -> Option<Bdd> {
+        self.try_apply::<Iff>(other, node_limit)
+    }
+
+    /// Same as `Bdd::xor`, but returns `None` if the result has more than `node_limit` nodes
+    /// (see `Bdd::try_apply`).
+    pub fn try_xor(&self, other: &Bdd, node_limit: usize) -> Option<Bdd> {
+        self.try_apply::<Xor>(other, node_limit)
+    }
+
+    /// Same as `Bdd::and_not`, but returns `None` if the result has more than `node_limit`
+    /// nodes (see `Bdd::try_apply`).
+    pub fn try_and_not(&self, other: &Bdd, node_limit: usize) -> Option<Bdd> {
+        self.try_apply::<AndNot>(other, node_limit)
+    }
+}
+
+/// Timeouts need a system clock, which is not available in `no_std` and `wasm` builds.
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+impl Bdd {
+    /// Same as `Bdd::binary_operation`, but the operation is aborted once it runs longer than
+    /// the given `timeout`. In such case, the error describes the progress of the operation
+    /// (see `ApplyDiagnostics`).
+    ///
+    /// The operands are used as they are (they are not sorted or copied), and the operation
+    /// always uses the `U48` kernel. The time is checked periodically, so the operation
+    /// can run slightly longer than `timeout`.
+    pub fn binary_operation_with_timeout<TABLE>(
+        &self,
+        other: &Bdd,
+        table: TABLE,
+        timeout: core::time::Duration,
+    ) -> Result<Bdd, ApplyDiagnostics>
+    where
+        TABLE: Fn(NodeId, NodeId) -> NodeId,
+    {
+        const PERIOD: u64 = u48::INTERRUPT_PERIOD;
+        let span = OperationSpan::start("timeout", self.node_count(), other.node_count());
+        let start = std::time::Instant::now();
+        let interrupt = |_: &ApplyDiagnostics| start.elapsed() > timeout;
+        let config = TaskCacheConfig::default();
+        let result = larger_first!((self, other, &table, |l, r| table(r, l)), |l, r, table| {
+            u48::_u48_apply_interruptible(l, r, table, &config, Vec::new(), PERIOD, interrupt)
+        });
+        match result {
+            Ok((result, _)) => {
+                span.finish(result.node_count());
+                Ok(result)
+            }
+            Err(mut diagnostics) => {
+                diagnostics.elapsed = start.elapsed();
+                debug!("Operation aborted after {:?}: {:?}", timeout, diagnostics);
+                Err(diagnostics)
+            }
+        }
+    }
+}
+
+impl ApplyDiagnostics {
+    /// The fraction of task cache lookups which were successful.
+    pub fn task_cache_hit_rate(&self) -> f64 {
+        let lookups = self.task_cache_hits + self.tasks_expanded;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.task_cache_hits as f64 / lookups as f64
+        }
+    }
+
+    /// The fraction of node cache lookups which found an existing node.
+    pub fn node_cache_hit_rate(&self) -> f64 {
+        if self.node_lookups == 0 {
+            0.0
+        } else {
+            (self.node_lookups - self.nodes_created) as f64 / self.node_lookups as f64
+        }
+    }
+}
+
+impl TaskCacheConfig {
+    /// **(internal)** The number of cache entries, if the default capacity of the cache is
+    /// `default_capacity`. A two-way cache always has an even number of entries, such that
+    /// every bucket is complete.
+    pub(crate) fn entries(&self, default_capacity: usize) -> usize {
+        let capacity = max(self.capacity.unwrap_or(default_capacity), 2);
+        if self.is_two_way() {
+            capacity + capacity % 2
+        } else {
+            capacity
+        }
+    }
+
+    /// **(internal)** True if the cache uses `ReplacementPolicy::TwoWay`.
+    pub(crate) fn is_two_way(&self) -> bool {
+        self.replacement == ReplacementPolicy::TwoWay
+    }
+}
+
+impl BoolOp {
+    /// All the supported operations.
+    pub const ALL: [BoolOp; 7] = [
+        BoolOp::And,
+        BoolOp::Or,
+        BoolOp::Xor,
+        BoolOp::Imp,
+        BoolOp::Iff,
+        BoolOp::AndNot,
+        BoolOp::NotAnd,
+    ];
+
+    /// The name of the operation, which is also the name of the corresponding `Bdd` method
+    /// (e.g. `and_not` for `BoolOp::AndNot`).
+    pub fn name(self) -> &'static str {
+        match self {
+            BoolOp::And => "and",
+            BoolOp::Or => "or",
+            BoolOp::Xor => "xor",
+            BoolOp::Imp => "imp",
+            BoolOp::Iff => "iff",
+            BoolOp::AndNot => "and_not",
+            BoolOp::NotAnd => "not_and",
+        }
+    }
+
+    /// Evaluate the operation on two Boolean values.
+    pub fn eval(self, left: bool, right: bool) -> bool {
+        match self {
+            BoolOp::And => left && right,
+            BoolOp::Or => left || right,
+            BoolOp::Xor => left != right,
+            BoolOp::Imp => !left || right,
+            BoolOp::Iff => left == right,
+            BoolOp::AndNot => left && !right,
+            BoolOp::NotAnd => !left && right,
+        }
+    }
+
+    /// The terminal lookup table of the operation in the format of `Bdd::binary_operation`.
+    /// It is only defined for terminal nodes, i.e. the result is `NodeId::UNDEFINED`
+    /// whenever one of the arguments is not a terminal.
+    pub fn table(self, left: NodeId, right: NodeId) -> NodeId {
+        if left.as_index() > 1 || right.as_index() > 1 {
+            NodeId::UNDEFINED
+        } else if self.eval(left.is_one(), right.is_one()) {
+            NodeId::ONE
         } else {
-            if self_nodes < u32::MAX_LEFT_SIZE && other_nodes < u32::MAX_RIGHT_SIZE {
-                u32::_u32_apply(self, other, table)
+            NodeId::ZERO
+        }
+    }
+}
+
+/// Find the operation with the given name (see `BoolOp::name`).
+impl TryFrom<&str> for BoolOp {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        BoolOp::ALL
+            .iter()
+            .find(|op| op.name() == value)
+            .cloned()
+            .ok_or_else(|| format!("Unknown operation `{}`.", value))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::_impl_::test_util::{and_table, variables};
+    use crate::v2::ops::{And, Or, Xor};
+    use crate::v2::{Bdd, NodeId, TaskCacheConfig, VariableId};
+
+    #[test]
+    pub fn operand_copy_test() {
+        let x0 = Bdd::new_variable(VariableId::from(0));
+        let x1 = Bdd::new_variable(VariableId::from(1));
+        let mut a = x0.or(&x1);
+        a.sort_preorder();
+        let b = x0.and(&x1);
+        assert!(a.is_sorted());
+        assert!(!b.is_sorted());
+
+        // `b` implies `a`, so `a | b = a` and `a & b = b`. The result is then a copy
+        // of the operand, which we can recognize using the sortedness flag.
+        let config = TaskCacheConfig::default();
+        let or = [
+            a.or(&b),
+            a._u48_apply_op::<Or>(&b, &config, Vec::new()),
+            a._u32_apply_op::<Or>(&b, &config, Vec::new()),
+        ];
+        for result in or {
+            assert!(result.is_sorted());
+            assert!(result.nodes == a.nodes);
+        }
+        let and = [
+            a.and(&b),
+            a._u48_apply_op::<And>(&b, &config, Vec::new()),
+            a._u32_apply_op::<And>(&b, &config, Vec::new()),
+        ];
+        for result in and {
+            assert!(!result.is_sorted());
+            assert!(result.nodes == b.nodes);
+        }
+
+        // Results which are not a copy of an operand are still computed normally.
+        let c = a.xor(&b);
+        assert!(c.nodes != a.nodes && c.nodes != b.nodes);
+        assert!(c.and(&b).node_count() == 1);
+    }
+
+    #[test]
+    pub fn mirrored_and_constant_operations_test() {
+        let a = Bdd::new_variable(VariableId::from(0));
+        // `b` is larger, so asymmetric operations on `(a, b)` use the mirrored variants.
+        let b = Bdd::new_variable(VariableId::from(1)).and(&Bdd::new_variable(VariableId::from(2)));
+        let is_false = |bdd: Bdd| bdd.node_count() == 1;
+
+        // a => b is true when a is true and b is true, but false when a is true and b false.
+        assert!(is_false(a.imp(&b).and(&a).xor(&a.and(&b))));
+        // a & !b never intersects b, and together with a & b gives a.
+        assert!(is_false(a.and_not(&b).and(&b)));
+        assert!(is_false(a.and_not(&b).or(&a.and(&b)).xor(&a)));
+        assert!(is_false(b.and_not(&a).and(&a)));
+
+        // Operations with constant operands are resolved immediately.
+        let ff = Bdd::new_false();
+        let tt = ff.iff(&ff);
+        assert_eq!(2, tt.node_count());
+        assert!(is_false(a.and(&ff)));
+        assert_eq!(2, a.or(&tt).node_count());
+        assert!(is_false(ff.and_not(&a)));
+        assert!(is_false(a.xor(&a.or(&ff))));
+    }
+
+    #[test]
+    pub fn streaming_operation_test() {
+        let x = variables(4);
+        let left = x[0].and(&x[1]).or(&x[2]);
+        let right = x[1].xor(&x[3]);
+        let xor = |l: NodeId, r: NodeId| {
+            if l.as_index() < 2 && r.as_index() < 2 {
+                if l == r {
+                    NodeId::ZERO
+                } else {
+                    NodeId::ONE
+                }
             } else {
-                u48::_u48_apply(self, other, table)
+                NodeId::UNDEFINED
             }
+        };
+        let expected = left.binary_operation(&right, xor);
+
+        // Count the nodes of every variable, checking that children are reported first.
+        let mut per_variable = [0usize; 4];
+        let mut reported = 0;
+        let root = left.binary_operation_streaming(&right, xor, |id, node| {
+            assert_eq!(reported + 2, id.as_index());
+            assert!(node.low_link().as_index() < id.as_index());
+            assert!(node.high_link().as_index() < id.as_index());
+            per_variable[usize::from(u16::from(node.variable()))] += 1;
+            reported += 1;
+        });
+        assert_eq!(expected.node_count(), reported + 2);
+        assert_eq!(reported + 1, root.as_index());
+        let mut expected_per_variable = [0usize; 4];
+        for node in &expected.nodes[2..] {
+            expected_per_variable[usize::from(u16::from(node.variable()))] += 1;
+        }
+        assert_eq!(expected_per_variable, per_variable);
+
+        // Constant results are not streamed.
+        let root = left.binary_operation_streaming(&left, xor, |_, _| panic!());
+        assert!(root.is_zero());
+    }
+
+    #[test]
+    #[cfg(feature = "mmap")]
+    pub fn external_binary_operation_test() {
+        use crate::v2::{BddStorage, MmapBddStorage};
+        let x = variables(8);
+        let left = (0..4).fold(Bdd::new_false(), |acc, i| acc.or(&x[i].and(&x[i + 4])));
+        let right = (0..4).fold(Bdd::new_false(), |acc, i| acc.xor(&x[2 * i]));
+        let expected = left.and(&right);
+
+        let operand_path = std::env::temp_dir().join("external_operation_operand.bin");
+        let result_path = std::env::temp_dir().join("external_operation_result.bin");
+        let operand = MmapBddStorage::create(&left, &operand_path).unwrap();
+        // Even with no memory for the caches, the result is correct (but not minimal).
+        for budget in [0, 1 << 20] {
+            let result =
+                Bdd::external_binary_operation(&operand, &right, and_table, budget, &result_path)
+                    .unwrap();
+            assert_eq!(2, result.to_bdd().iff(&expected).node_count());
+            assert!(result.node_count() >= expected.node_count());
+            let result =
+                Bdd::external_binary_operation(&right, &operand, and_table, budget, &result_path)
+                    .unwrap();
+            assert_eq!(2, result.to_bdd().iff(&expected).node_count());
+        }
+
+        // Constant results only contain the terminals.
+        let not_left = left.not();
+        let result =
+            Bdd::external_binary_operation(&operand, &not_left, and_table, 0, &result_path)
+                .unwrap();
+        assert_eq!(1, result.node_count());
+        drop((operand, result));
+        std::fs::remove_file(&operand_path).unwrap();
+        std::fs::remove_file(&result_path).unwrap();
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", not(feature = "wasm")))]
+    pub fn operation_timeout_test() {
+        use core::time::Duration;
+        // `x_i & x_(i + n)` pairs in the worst variable order, so the operands are large.
+        let n = 12;
+        let x = variables(2 * n);
+        let mut left = Bdd::new_false();
+        let mut right = Bdd::new_false();
+        for i in 0..usize::from(n) {
+            left = left.or(&x[i].and(&x[i + usize::from(n)]));
+            right = right.or(&x[i].xor(&x[i + usize::from(n)]));
+        }
+
+        let hour = Duration::from_secs(3600);
+        let result = left.binary_operation_with_timeout(&right, and_table, hour);
+        assert_eq!(2, result.unwrap().iff(&left.and(&right)).node_count());
+
+        let timeout = left.binary_operation_with_timeout(&right, and_table, Duration::ZERO);
+        let diagnostics = match timeout {
+            Ok(_) => panic!("The operation should time out."),
+            Err(diagnostics) => diagnostics,
+        };
+        assert!(diagnostics.tasks_expanded > 0);
+        assert!(diagnostics.nodes_created <= diagnostics.node_lookups);
+        assert!(diagnostics.deepest_level < 2 * n);
+        assert!((0.0..=1.0).contains(&diagnostics.task_cache_hit_rate()));
+        assert!((0.0..=1.0).contains(&diagnostics.node_cache_hit_rate()));
+    }
+
+    #[test]
+    pub fn operation_yield_test() {
+        use crate::v2::BoolOp;
+        let n = 10;
+        let x = variables(2 * n);
+        let mut left = Bdd::new_false();
+        let mut right = Bdd::new_false();
+        for i in 0..usize::from(n) {
+            left = left.or(&x[i].and(&x[i + usize::from(n)]));
+            right = right.or(&x[i].xor(&x[i + usize::from(n)]));
+        }
+        let expected = left.and(&right);
+        let and = |l, r| BoolOp::And.table(l, r);
+
+        // The hook is called repeatedly, and more often with a shorter period.
+        let mut yields = 0;
+        let result = left.binary_operation_with_yield(&right, and, 64, || yields += 1);
+        assert_eq!(2, result.iff(&expected).node_count());
+        let mut rare_yields = 0;
+        let result = right.binary_operation_with_yield(&left, and, 1000, || rare_yields += 1);
+        assert_eq!(2, result.iff(&expected).node_count());
+        assert!(rare_yields > 0 && yields > 8 * rare_yields);
+        let mut no_yields = 0;
+        left.binary_operation_with_yield(&right, and, 1 << 40, || no_yields += 1);
+        assert_eq!(0, no_yields);
+    }
+
+    #[test]
+    pub fn degenerate_operand_test() {
+        use crate::v2::_impl_::storage::StorageAccess;
+        use crate::v2::BddNode;
+
+        let config = TaskCacheConfig::default();
+        // A conjunction of `x_0, ..., x_{n-1}` (with the last literal optionally negated),
+        // i.e. a path through all the variables, built node by node.
+        let chain = |n: u16, negate_last: bool| {
+            let mut bdd = Bdd::true_with_capacity(usize::from(n) + 2);
+            bdd.update_variable_count(n);
+            let mut last = NodeId::ONE;
+            for v in (0..n).rev() {
+                let node = if negate_last && v == n - 1 {
+                    BddNode::pack(VariableId::from(v), last, NodeId::ZERO)
+                } else {
+                    BddNode::pack(VariableId::from(v), NodeId::ZERO, last)
+                };
+                last = bdd.push_node(node);
+            }
+            bdd
+        };
+        let x = variables(8);
+        let positive = x.iter().skip(1).fold(x[0].clone(), |a, b| a.and(b));
+        let negative = x[..7]
+            .iter()
+            .skip(1)
+            .fold(x[0].clone(), |a, b| a.and(b))
+            .and_not(&x[7]);
+
+        let (left, right) = (chain(8, false), chain(8, true));
+        assert_eq!(8, left.height_bound());
+        let and = [
+            left._u48_apply_op::<And>(&right, &config, Vec::new()),
+            left._u32_apply_op::<And>(&right, &config, Vec::new()),
+            left.and(&right),
+        ];
+        for result in and {
+            assert_eq!(1, result.node_count());
+        }
+        let expected = positive.xor(&negative);
+        let xor = [
+            left._u48_apply_op::<Xor>(&right, &config, Vec::new()),
+            left._u32_apply_op::<Xor>(&right, &config, Vec::new()),
+            left.xor(&right),
+        ];
+        for result in xor {
+            assert_eq!(2, result.iff(&expected).node_count());
+        }
+
+        // A `false` Bdd with a stray (redundant) root node.
+        let mut stray_false = Bdd::true_with_capacity(3);
+        stray_false.update_variable_count(4);
+        stray_false.push_node(BddNode::pack(VariableId::from(3), NodeId::ZERO, NodeId::ZERO));
+        let and = [
+            x[3]._u48_apply_op::<And>(&stray_false, &config, Vec::new()),
+            x[3]._u32_apply_op::<And>(&stray_false, &config, Vec::new()),
+        ];
+        for result in and {
+            assert_eq!(1, result.node_count());
+        }
+        let or = [
+            x[3]._u48_apply_op::<Or>(&stray_false, &config, Vec::new()),
+            x[3]._u32_apply_op::<Or>(&stray_false, &config, Vec::new()),
+        ];
+        for result in or {
+            assert_eq!(2, result.iff(&x[3]).node_count());
+        }
+
+        // Single-variable operands.
+        let not_x0 = x[0].xor(&x[0].or(&x[1]).or(&x[1].imp(&x[1])));
+        assert_eq!(1, x[0]._u48_apply_op::<And>(&not_x0, &config, Vec::new()).node_count());
+        assert_eq!(2, x[0]._u32_apply_op::<Or>(&not_x0, &config, Vec::new()).node_count());
+        assert_eq!(2, x[0]._u48_apply_op::<Xor>(&not_x0, &config, Vec::new()).node_count());
+    }
+
+    #[test]
+    pub fn apply_op_test() {
+        use crate::v2::BoolOp;
+        use core::convert::TryFrom;
+
+        let x = variables(4);
+        let a = x[0].and(&x[2]).or(&x[1]);
+        let b = x[1].xor(&x[3]).or(&x[0].and_not(&x[2]));
+        let named = [
+            a.and(&b),
+            a.or(&b),
+            a.xor(&b),
+            a.imp(&b),
+            a.iff(&b),
+            a.and_not(&b),
+            b.and_not(&a),
+        ];
+        for (op, expected) in BoolOp::ALL.iter().zip(named.iter()) {
+            let result = a.apply_op(*op, &b);
+            assert!(result.semantic_eq(expected));
+            assert!(result.semantic_eq(&a.binary_operation(&b, |l, r| op.table(l, r))));
+            // Identical operands produce a constant (or a copy of the operand).
+            let expected = if op.eval(true, true) == op.eval(false, false) {
+                Bdd::_new_constant(op.table(NodeId::ONE, NodeId::ONE), 4)
+            } else {
+                a.clone()
+            };
+            assert!(a.apply_op(*op, &a).semantic_eq(&expected));
+            assert_eq!(Ok(*op), BoolOp::try_from(op.name()));
+        }
+        assert!(BoolOp::try_from("nand").is_err());
+    }
+
+    #[test]
+    pub fn custom_operator_test() {
+        use crate::v2::ops::{BooleanOp, Mirrored};
+        use crate::v2::{ApplyKernel, ApplyOptions};
+
+        /// Negated conjunction, which is not one of the pre-defined operators.
+        struct Nand;
+        impl BooleanOp for Nand {
+            const TABLE: [[bool; 2]; 2] = [[true, true], [true, false]];
+            type Mirror = Nand;
+        }
+
+        /// Reverse implication, mirrored using `Mirrored`.
+        struct Converse;
+        impl BooleanOp for Converse {
+            const TABLE: [[bool; 2]; 2] = [[true, false], [true, true]];
+            type Mirror = Mirrored<Converse>;
+        }
+
+        let x = variables(4);
+        let a = x[0].and(&x[2]).or(&x[1]);
+        let b = x[1].xor(&x[3]).or(&x[0].and_not(&x[2]));
+        for kernel in [ApplyKernel::U16, ApplyKernel::U32, ApplyKernel::U48] {
+            let options = ApplyOptions {
+                kernel: Some(kernel),
+                ..ApplyOptions::default()
+            };
+            let nand = a.and(&b).not();
+            assert!(a.apply_with::<Nand>(&b, &options).semantic_eq(&nand));
+            // The smaller operand is on the left, so the operands are swapped.
+            assert!(b.node_count() > x[3].node_count());
+            let converse = x[3].apply_with::<Converse>(&b, &options);
+            assert!(converse.semantic_eq(&b.imp(&x[3])));
+            assert!(b.apply_with::<Converse>(&x[3], &options).semantic_eq(&x[3].imp(&b)));
+        }
+        // `x nand x` is the negation of `x`.
+        assert!(a.apply::<Nand>(&a).semantic_eq(&a.not()));
+    }
+
+    #[test]
+    pub fn task_cache_config_test() {
+        use crate::v2::{ApplyKernel, ApplyOptions, ReplacementPolicy, TaskHash};
+
+        let x = variables(10);
+        let mut a = Bdd::new_false();
+        let mut b = Bdd::new_true();
+        for i in 0..5 {
+            a = a.or(&x[i].and(&x[i + 5]));
+            b = b.and(&x[2 * i].xor(&x[2 * i + 1]).or(&x[(3 * i) % 10]));
+        }
+        let expected = a.xor(&b);
+        for kernel in [ApplyKernel::U32, ApplyKernel::U48] {
+            for replacement in [ReplacementPolicy::Overwrite, ReplacementPolicy::TwoWay] {
+                for hash in [TaskHash::Rolling, TaskHash::Uniform] {
+                    // A tiny cache still gives the correct result, only slower.
+                    for capacity in [None, Some(1), Some(7), Some(1 << 16)] {
+                        let options = ApplyOptions {
+                            kernel: Some(kernel),
+                            task_cache: TaskCacheConfig {
+                                capacity,
+                                replacement,
+                                hash,
+                                ..TaskCacheConfig::default()
+                            },
+                            ..ApplyOptions::default()
+                        };
+                        let result = a.apply_with::<Xor>(&b, &options);
+                        assert!(result.semantic_eq(&expected));
+                        assert_eq!(expected.node_count(), result.node_count());
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn apply_into_test() {
+        use crate::v2::{ApplyKernel, ApplyOptions, BoolOp};
+
+        let x = variables(12);
+        // A simple fixed-point: all valuations reachable from `x0` by "shifting" the ones.
+        let step = (0..11).fold(Bdd::new_false(), |acc, i| acc.or(&x[i].and(&x[i + 1])));
+        for kernel in [ApplyKernel::U16, ApplyKernel::U32, ApplyKernel::U48] {
+            let options = ApplyOptions {
+                kernel: Some(kernel),
+                ..ApplyOptions::default()
+            };
+            let mut reached = x[0].clone();
+            let mut next = Bdd::new_false();
+            loop {
+                reached.apply_into_with::<Or>(&step, &mut next, &options);
+                if next.semantic_eq(&reached) {
+                    break;
+                }
+                core::mem::swap(&mut reached, &mut next);
+            }
+            assert!(reached.semantic_eq(&x[0].or(&step)));
+        }
+
+        // A large enough target is reused by the big kernels.
+        let mut target = step.xor(&x[5]);
+        for kernel in [ApplyKernel::U32, ApplyKernel::U48] {
+            let options = ApplyOptions {
+                kernel: Some(kernel),
+                ..ApplyOptions::default()
+            };
+            let pointer = target.nodes.as_ptr();
+            x[0].apply_into_with::<Xor>(&x[1], &mut target, &options);
+            assert!(target.semantic_eq(&x[0].xor(&x[1])));
+            assert_eq!(pointer, target.nodes.as_ptr());
         }
+        for op in BoolOp::ALL.iter().copied() {
+            step.apply_op_into(op, &x[3], &mut target);
+            assert!(target.semantic_eq(&step.apply_op(op, &x[3])));
+        }
+    }
+
+    #[test]
+    pub fn huge_pages_test() {
+        use crate::v2::{ApplyKernel, ApplyOptions, BddAllocConfig};
+
+        let x = variables(16);
+        let a = (0..8).fold(Bdd::new_false(), |acc, i| acc.or(&x[i].and(&x[i + 8])));
+        let b = (0..8).fold(Bdd::new_true(), |acc, i| acc.and(&x[2 * i].iff(&x[15 - i])));
+        let expected = a.xor(&b);
+        for kernel in [ApplyKernel::U32, ApplyKernel::U48] {
+            // The task cache is large enough to be backed by huge pages.
+            let options = ApplyOptions {
+                kernel: Some(kernel),
+                task_cache: TaskCacheConfig {
+                    capacity: Some(1 << 18),
+                    alloc: BddAllocConfig { huge_pages: true },
+                    ..TaskCacheConfig::default()
+                },
+                ..ApplyOptions::default()
+            };
+            let result = a.apply_with::<Xor>(&b, &options);
+            assert!(result.semantic_eq(&expected));
+            assert_eq!(expected.node_count(), result.node_count());
+        }
+    }
+
+    #[test]
+    pub fn apply_with_stats_test() {
+        use crate::v2::ops::Imp;
+
+        let x = variables(6);
+        let a = x[0].xor(&x[3]).and(&x[1].xor(&x[4])).or(&x[2].and(&x[5]));
+        let b = x[0].and(&x[1]).or(&x[2].iff(&x[4]));
+        for (l, r) in [(&a, &b), (&b, &a)] {
+            let (result, stats) = l.apply_with_stats::<Imp>(r);
+            assert!(result.semantic_eq(&l.imp(r)));
+            assert!(stats.tasks_expanded > 0);
+            assert!(stats.nodes_created < stats.node_lookups);
+            assert!(stats.nodes_created as usize + 2 >= result.node_count());
+            // Every path of the search expands at most one task per variable.
+            assert!(stats.stack_high_water > 0);
+            assert!(stats.stack_high_water <= 2 * x.len() + 1);
+        }
+
+        // Constant results are resolved without expanding any tasks.
+        let (result, stats) = a.apply_with_stats::<And>(&Bdd::new_false());
+        assert!(result.semantic_eq(&Bdd::new_false()));
+        assert_eq!(0, stats.tasks_expanded);
+        assert_eq!(0, stats.stack_high_water);
+    }
+
+    #[test]
+    pub fn node_limit_test() {
+        // A conjunction of n independent "x_i <=> y_i" clauses, with all x variables before
+        // all y variables, needs 3 * 2^n - 1 nodes (including terminals).
+        let n = 6u16;
+        let x: Vec<Bdd> = (0..2 * n)
+            .map(|v| Bdd::new_variable(VariableId::from(v)))
+            .collect();
+        let mut left = Bdd::new_true();
+        for i in 0..(n as usize / 2) {
+            left = left.and(&x[i].iff(&x[i + n as usize]));
+        }
+        let mut right = Bdd::new_true();
+        for i in (n as usize / 2)..(n as usize) {
+            right = right.and(&x[i].iff(&x[i + n as usize]));
+        }
+        let expected = left.and(&right);
+        let size = expected.node_count();
+        assert_eq!(3 * (1 << n) - 1, size);
+
+        // The limit is inclusive and the outcome is the same for both operand orders.
+        for (l, r) in [(&left, &right), (&right, &left)] {
+            assert!(l.try_and(r, size).unwrap().semantic_eq(&expected));
+            assert!(l.try_and(r, size - 1).is_none());
+            assert!(l.try_and(r, 10).is_none());
+        }
+        let xor = left.xor(&right);
+        assert!(left.try_xor(&right, xor.node_count()).unwrap().semantic_eq(&xor));
+        assert!(left.try_xor(&right, xor.node_count() - 1).is_none());
+
+        // Constant results always fit.
+        assert!(left.try_and_not(&left, 2).unwrap().semantic_eq(&Bdd::new_false()));
+        assert!(left.try_or(&Bdd::new_true(), 2).is_some());
+        assert!(left.try_imp(&left, 2).is_some());
+        assert!(left.try_iff(&left, 2).is_some());
     }
 }
@@ -0,0 +1,168 @@
+use crate::v2::_impl_::bdd::binary_operations::dispatcher::SMALL_KERNEL_CAPACITY;
+use crate::v2::small::{SmallBdd, MAX_VARIABLES};
+use crate::v2::ops::BooleanOp;
+use crate::v2::{Bdd, NodeId};
+use core::cell::Cell;
+use core::cmp::max;
+
+/// **(internal)** The `SmallBdd` used by the `U16` kernel.
+type KernelBdd = SmallBdd<MAX_VARIABLES, SMALL_KERNEL_CAPACITY>;
+
+impl Bdd {
+    /// **(internal)** Run a binary operation given by the `lookup` table on stack-allocated
+    /// copies of the operands. Returns `None` if the operands or the result do not fit
+    /// into a `KernelBdd`.
+    ///
+    /// The copies keep the node indices of the operands, so results which are copies of
+    /// an operand are detected the same way as in the other kernels.
+    pub(super) fn _u16_apply<TABLE>(&self, other: &Bdd, lookup: TABLE) -> Option<Bdd>
+    where
+        TABLE: Fn(NodeId, NodeId) -> NodeId,
+    {
+        let variables = max(self.variable_count(), other.variable_count());
+        let root_result = lookup(self.root_node(), other.root_node());
+        if !root_result.is_undefined() {
+            return Some(Bdd::_new_constant(root_result, variables));
+        }
+
+        let left = KernelBdd::copy_of(self)?;
+        let right = KernelBdd::copy_of(other)?;
+        // Stays true while the result is a copy of the left/right operand
+        // (see `Bdd::_operand_copy`).
+        let is_left_copy = Cell::new(true);
+        let is_right_copy = Cell::new(true);
+        let result = left.binary_operation(&right, |l, r| {
+            let result = lookup(l, r);
+            if !result.is_undefined() {
+                is_left_copy.set(is_left_copy.get() && result == l);
+                is_right_copy.set(is_right_copy.get() && result == r);
+            }
+            result
+        })?;
+
+        Some(if result.is_false() {
+            Bdd::_new_constant(NodeId::ZERO, variables)
+        } else if is_left_copy.get() {
+            self._operand_copy(variables)
+        } else if is_right_copy.get() {
+            other._operand_copy(variables)
+        } else {
+            result.to_bdd(variables)
+        })
+    }
+
+    /// **(internal)** Same as `Bdd::_u48_apply_op`, but using the `U16` kernel.
+    pub(super) fn _u16_apply_op<OP: BooleanOp>(&self, other: &Bdd) -> Option<Bdd> {
+        self._u16_apply(other, OP::lookup)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::_impl_::test_util::variables;
+    use crate::v2::_impl_::bdd::binary_operations::dispatcher::SMALL_KERNEL_CAPACITY;
+    use crate::v2::small::SmallBdd;
+    use crate::v2::{ApplyKernel, ApplyOptions, Bdd, BoolOp};
+    use core::convert::TryFrom;
+
+    #[test]
+    pub fn u16_kernel_test() {
+        let x = variables(16);
+        let u16 = ApplyOptions {
+            kernel: Some(ApplyKernel::U16),
+            ..ApplyOptions::default()
+        };
+        let u32 = ApplyOptions {
+            kernel: Some(ApplyKernel::U32),
+            ..ApplyOptions::default()
+        };
+        let left = x[0].and(&x[3]).or(&x[5].xor(&x[1]));
+        let right = x[2].iff(&x[3]).and_not(&x[4]);
+        let expected = [
+            left.and_with(&right, &u32),
+            left.or_with(&right, &u32),
+            left.imp_with(&right, &u32),
+            right.imp_with(&left, &u32),
+            left.iff_with(&right, &u32),
+            left.xor_with(&right, &u32),
+            left.and_not_with(&right, &u32),
+            right.and_not_with(&left, &u32),
+        ];
+        let actual = [
+            left.and_with(&right, &u16),
+            left.or_with(&right, &u16),
+            left.imp_with(&right, &u16),
+            right.imp_with(&left, &u16),
+            left.iff_with(&right, &u16),
+            left.xor_with(&right, &u16),
+            left.and_not_with(&right, &u16),
+            right.and_not_with(&left, &u16),
+        ];
+        for (expected, actual) in expected.iter().zip(actual.iter()) {
+            assert!(expected.semantic_eq(actual));
+            assert_eq!(expected.node_count(), actual.node_count());
+            assert_eq!(expected.variable_count(), actual.variable_count());
+        }
+
+        // Results which do not fit into the kernel are recomputed by `U32`: each half
+        // of the equality of `x0..x7` and `x8..x15` fits, but the whole equality does not.
+        let equal = |range: core::ops::Range<usize>| {
+            range.fold(x[0].imp(&x[0]), |result, i| {
+                result.and(&x[i].iff(&x[i + 8]))
+            })
+        };
+        let (low, high) = (equal(0..4), equal(4..8));
+        assert!(low.node_count() <= SMALL_KERNEL_CAPACITY);
+        assert!(high.node_count() <= SMALL_KERNEL_CAPACITY);
+        let result = low.and_with(&high, &u16);
+        assert!(result.node_count() > SMALL_KERNEL_CAPACITY);
+        assert!(result.semantic_eq(&low.and_with(&high, &u32)));
+    }
+
+    #[test]
+    pub fn u16_kernel_random_test() {
+        // A simple xorshift generator, so that the test is deterministic.
+        let mut state = 0x51_7c_c1_b7_27_22_0a_95u64;
+        let mut rng = move |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % bound as u64) as usize
+        };
+        let u16 = ApplyOptions {
+            kernel: Some(ApplyKernel::U16),
+            ..ApplyOptions::default()
+        };
+        let u48 = ApplyOptions {
+            kernel: Some(ApplyKernel::U48),
+            ..ApplyOptions::default()
+        };
+        let x = variables(10);
+        // A random function built by the `U48` kernel from a few random steps.
+        let mut random_bdd = || {
+            let mut result = x[rng(x.len())].clone();
+            for _ in 0..rng(8) {
+                let op = BoolOp::ALL[rng(BoolOp::ALL.len())];
+                result = result.apply_op_with(op, &x[rng(x.len())], &u48);
+            }
+            result
+        };
+        for _ in 0..100 {
+            let (left, right) = (random_bdd(), random_bdd());
+            for op in BoolOp::ALL.iter().copied() {
+                let expected = left.apply_op_with(op, &right, &u48);
+                let actual = left.apply_op_with(op, &right, &u16);
+                assert!(expected.semantic_eq(&actual), "{:?}", op);
+                assert_eq!(expected.node_count(), actual.node_count());
+                assert_eq!(expected.variable_count(), actual.variable_count());
+
+                // The public `SmallBdd` API uses the same algorithm.
+                type Small = SmallBdd<10, SMALL_KERNEL_CAPACITY>;
+                let small_left = Small::try_from(&left).unwrap();
+                let small_right = Small::try_from(&right).unwrap();
+                let small = small_left.apply_op(op, &small_right).unwrap();
+                assert!(expected.semantic_eq(&Bdd::from(&small)));
+            }
+        }
+    }
+}
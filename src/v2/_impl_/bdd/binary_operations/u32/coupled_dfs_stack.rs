@@ -1,5 +1,8 @@
+use super::super::stack_capacity;
 use super::PointerPair;
 use crate::v2::NodeId;
+use alloc::vec::Vec;
+use alloc::vec;
 
 /// **(internal)** A 32-bit version of the `Stack` used in the general `u48` algorithm.
 /// Method documentation omitted when equivalent to the one on `u48` version.
@@ -17,11 +20,10 @@ pub(super) struct Stack {
 }
 
 impl Stack {
-    pub fn new(variable_count: u16) -> Stack {
-        let variable_count = usize::from(variable_count);
+    pub fn new(height: usize) -> Stack {
         let mut stack = Stack {
             index_after_last: 1,
-            items: vec![PointerPair(0); 2 * variable_count + 2],
+            items: vec![PointerPair(0); stack_capacity(height)],
         };
         stack.items[0] = PointerPair::from(PointerPair::RESULT_MASK);
         stack
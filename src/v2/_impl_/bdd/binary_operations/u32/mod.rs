@@ -1,9 +1,13 @@
 use crate::v2::_impl_::bdd::binary_operations::u32::coupled_dfs_stack::Stack;
 use crate::v2::_impl_::bdd::binary_operations::u32::partial_task_cache::TaskCache;
 use crate::v2::_impl_::bdd::binary_operations::u48::partial_node_cache::NodeCache;
-use crate::v2::{Bdd, BddNode, NodeId};
-use std::cmp::{max, min};
-use std::convert::TryFrom;
+use crate::v2::_impl_::bdd::binary_operations::capacity::estimate_result_capacity;
+use crate::v2::_impl_::storage::StorageAccess;
+use crate::v2::ops::BooleanOp;
+use crate::v2::{Bdd, BddNode, NodeId, TaskCacheConfig};
+use alloc::vec::Vec;
+use core::cmp::{max, min};
+use core::convert::TryFrom;
 
 /// Implementation details of the `PointerPair` struct.
 mod pointer_pair;
@@ -25,7 +29,22 @@ pub(super) const MAX_LEFT_SIZE: u64 = u32::MAX as u64;
 /// Right `Bdd` cannot have the highest bit set.
 pub(super) const MAX_RIGHT_SIZE: u64 = MAX_LEFT_SIZE ^ (1 << 31);
 
-pub(super) fn _u32_apply<TABLE>(left_bdd: &Bdd, right_bdd: &Bdd, lookup: TABLE) -> Bdd
+// The size limits must agree with the `PointerPair` layout: every left pointer fits into
+// the lower half, and no right pointer can set the result flag.
+const _: () = assert!(MAX_LEFT_SIZE <= PointerPair::LEFT_POINTER_MASK + 1);
+const _: () = assert!((MAX_RIGHT_SIZE << 32) & PointerPair::RESULT_MASK == 0);
+const _: () = assert!(MAX_RIGHT_SIZE <= MAX_LEFT_SIZE);
+// Every `Bdd` which fits into the `u32` kernel also fits into the 48-bit node ids.
+const _: () = assert!(MAX_LEFT_SIZE < Bdd::MAX_NODE_COUNT);
+
+/// **(internal)** Same as `_u48_apply`, but for operands which fit into `PointerPair`.
+pub(super) fn _u32_apply<TABLE>(
+    left_bdd: &Bdd,
+    right_bdd: &Bdd,
+    lookup: TABLE,
+    config: &TaskCacheConfig,
+    buffer: Vec<BddNode>,
+) -> Bdd
 where
     TABLE: Fn(NodeId, NodeId) -> NodeId,
 {
@@ -34,10 +53,20 @@ where
 
     let variables = max(left_bdd.variable_count(), right_bdd.variable_count());
 
+    let root_result = lookup(left_bdd.root_node(), right_bdd.root_node());
+    if !root_result.is_undefined() {
+        // The stack cannot handle a root task which is resolved immediately.
+        return Bdd::_new_constant(root_result, variables);
+    }
+
     let mut is_not_false = false;
-    let mut node_cache = NodeCache::new(left_bdd.node_count());
-    let mut task_cache = TaskCache::new(left_bdd.node_count(), right_bdd.node_count());
-    let mut stack = Stack::new(variables);
+    // Stays true while the result is a copy of the left/right operand (see `Bdd::_operand_copy`).
+    let mut is_left_copy = true;
+    let mut is_right_copy = true;
+    let capacity = estimate_result_capacity(left_bdd, right_bdd, &lookup);
+    let mut node_cache = NodeCache::new(capacity, buffer, &config.alloc);
+    let mut task_cache = TaskCache::new(left_bdd.node_count(), right_bdd.node_count(), config);
+    let mut stack = Stack::new(left_bdd.height_bound() + right_bdd.height_bound());
     unsafe {
         let root = PointerPair::pack(left_bdd.root_node(), right_bdd.root_node());
         stack.push_task_unchecked(root);
@@ -58,6 +87,8 @@ where
             is_not_false = is_not_false || lookup_result.is_one();
 
             if !lookup_result.is_undefined() {
+                is_left_copy = is_left_copy && lookup_result == left;
+                is_right_copy = is_right_copy && lookup_result == right;
                 finish_task = finish_task || unsafe { stack.save_result_unchecked(lookup_result) };
             } else {
                 let cached_node = task_cache.read(tasks);
@@ -126,204 +157,28 @@ where
         }
     }
 
-    if is_not_false {
+    if !is_not_false {
+        Bdd::new_false()
+    } else if is_left_copy {
+        left_bdd._operand_copy(variables)
+    } else if is_right_copy {
+        right_bdd._operand_copy(variables)
+    } else {
         let mut result = node_cache.export();
         result.update_variable_count(variables);
         result
-    } else {
-        Bdd::new_false()
     }
 }
 
-macro_rules! apply_u32 {
-    ($left:ident, $right:ident, $zero:expr, $one:expr) => {{
-        let left_bdd = $left;
-        let right_bdd = $right;
-        debug_assert!(left_bdd.node_count() < usize::try_from(MAX_LEFT_SIZE).unwrap());
-        debug_assert!(right_bdd.node_count() < usize::try_from(MAX_RIGHT_SIZE).unwrap());
-
-        let variables = max(left_bdd.variable_count(), right_bdd.variable_count());
-
-        let mut is_not_false = false;
-        let mut node_cache = NodeCache::new(left_bdd.node_count());
-        let mut task_cache = TaskCache::new(left_bdd.node_count(), right_bdd.node_count());
-        let mut stack = Stack::new(variables);
-        unsafe {
-            let root = PointerPair::pack(left_bdd.root_node(), right_bdd.root_node());
-            stack.push_task_unchecked(root);
-        }
-
-        // The calls to stack operations are safe due to the order in which we perform the Bdd search.
-        loop {
-            // If the top is a result, go straight to finishing a task. If not, first expand,
-            // but if the result of the expansion is a finished task, then also finish a task.
-            let mut finish_task = stack.has_result();
-
-            if !finish_task {
-                // Expand current top task.
-                let tasks = unsafe { stack.peek_as_task_unchecked() };
-                let (left, right) = tasks.unpack();
-
-                if $zero(left, right) {
-                    finish_task = finish_task || unsafe { stack.save_result_unchecked(NodeId::ZERO) };
-                } else if $one(left, right) {
-                    is_not_false = true;
-                    finish_task = finish_task || unsafe { stack.save_result_unchecked(NodeId::ONE) };
-                } else {
-                    let cached_node = task_cache.read(tasks);
-                    if !cached_node.is_undefined() {
-                        finish_task =
-                            finish_task || unsafe { stack.save_result_unchecked(cached_node) };
-                    } else {
-                        let left_node = unsafe { left_bdd.get_node_unchecked(left) };
-                        let right_node = unsafe { right_bdd.get_node_unchecked(right) };
-                        let (left_var, left_low, left_high) = left_node.unpack();
-                        let (right_var, right_low, right_high) = right_node.unpack();
-                        left_bdd.prefetch(left_low);
-                        right_bdd.prefetch(right_low);
-
-                        let decision_variable = min(left_var, right_var);
-
-                        let (left_low, left_high) = if decision_variable == left_var {
-                            (left_low, left_high)
-                        } else {
-                            (left, left)
-                        };
-
-                        let (right_low, right_high) = if decision_variable == right_var {
-                            (right_low, right_high)
-                        } else {
-                            (right, right)
-                        };
-
-                        let low_tasks = PointerPair::pack(left_low, right_low);
-                        let high_tasks = PointerPair::pack(left_high, right_high);
-
-                        task_cache.prefetch(high_tasks);
-
-                        // When completed, the order of tasks will be swapped (high on top).
-                        unsafe {
-                            stack.push_task_unchecked(high_tasks);
-                            stack.push_task_unchecked(low_tasks);
-                        }
-                    }
-                }
-            }
-
-            if finish_task {
-                // Finish current top task.
-                let (low, high) = unsafe { stack.pop_results_unchecked() };
-                let task = unsafe { stack.peek_as_task_unchecked() };
-
-                if high == low {
-                    task_cache.write(task, low);
-                    unsafe { stack.save_result_unchecked(low) };
-                } else {
-                    let (left, right) = task.unpack();
-                    let (left_var, right_var) =
-                        (left_bdd.get_variable(left), right_bdd.get_variable(right));
-                    let decision_variable = min(left_var, right_var);
-
-                    let node = BddNode::pack(decision_variable, low, high);
-                    let result_id = node_cache.ensure(node);
-                    task_cache.write(task, result_id);
-                    unsafe { stack.save_result_unchecked(result_id) };
-                }
-            }
-
-            if stack.has_last_entry() {
-                break; // The last entry is the result to the first task.
-            }
-        }
-
-        if is_not_false {
-            let mut result = node_cache.export();
-            result.update_variable_count(variables);
-            result
-        } else {
-            Bdd::new_false()
-        }
-    }}
-}
-
 impl Bdd {
-    pub(super) fn _u32_and(&self, other: &Bdd) -> Bdd {
-        debug_assert!(self.node_count() >= other.node_count());
-        apply_u32!(
-            self,
-            other,
-            |l: NodeId, r: NodeId| l.is_zero() || r.is_zero(),
-            |l: NodeId, r: NodeId| l.is_one() && r.is_one()
-        )
-    }
-
-    pub(super) fn _u32_or(&self, other: &Bdd) -> Bdd {
-        debug_assert!(self.node_count() >= other.node_count());
-        apply_u32!(
-            self,
-            other,
-            |l: NodeId, r: NodeId| l.is_zero() && r.is_zero(),
-            |l: NodeId, r: NodeId| l.is_one() || r.is_one()
-        )
-    }
-
-    pub(super) fn _u32_imp(&self, other: &Bdd) -> Bdd {
-        debug_assert!(self.node_count() >= other.node_count());
-        apply_u32!(
-            self,
-            other,
-            |l: NodeId, r: NodeId| l.is_one() && r.is_zero(),
-            |l: NodeId, r: NodeId| l.is_zero() || r.is_one()
-        )
-    }
-
-    pub(super) fn _u32_inv_imp(&self, other: &Bdd) -> Bdd {
-        debug_assert!(self.node_count() >= other.node_count());
-        apply_u32!(
-            self,
-            other,
-            |l: NodeId, r: NodeId| l.is_zero() || r.is_one(),
-            |l: NodeId, r: NodeId| l.is_one() && r.is_zero()
-        )
-    }
-
-    pub(super) fn _u32_iff(&self, other: &Bdd) -> Bdd {
-        debug_assert!(self.node_count() >= other.node_count());
-        apply_u32!(
-            self,
-            other,
-            |l: NodeId, r: NodeId| (l.is_one() && r.is_zero()) || (l.is_zero() && r.is_one()),
-            |l: NodeId, r: NodeId| (l.is_zero() && r.is_zero()) || (l.is_one() && r.is_one())
-        )
-    }
-
-    pub(super) fn _u32_xor(&self, other: &Bdd) -> Bdd {
-        debug_assert!(self.node_count() >= other.node_count());
-        apply_u32!(
-            self,
-            other,
-            |l: NodeId, r: NodeId| (l.is_one() && r.is_one()) || (l.is_zero() && r.is_zero()),
-            |l: NodeId, r: NodeId| (l.is_zero() && r.is_one()) || (l.is_one() && r.is_zero())
-        )
-    }
-
-    pub(super) fn _u32_and_not(&self, other: &Bdd) -> Bdd {
-        debug_assert!(self.node_count() >= other.node_count());
-        apply_u32!(
-            self,
-            other,
-            |l: NodeId, r: NodeId| l.is_zero() || r.is_one(),
-            |l: NodeId, r: NodeId| l.is_one() && r.is_zero()
-        )
-    }
-
-    pub(super) fn _u32_not_and(&self, other: &Bdd) -> Bdd {
+    /// **(internal)** Same as `Bdd::_u48_apply_op`, but using the `u32` kernel.
+    pub(super) fn _u32_apply_op<OP: BooleanOp>(
+        &self,
+        other: &Bdd,
+        config: &TaskCacheConfig,
+        buffer: Vec<BddNode>,
+    ) -> Bdd {
         debug_assert!(self.node_count() >= other.node_count());
-        apply_u32!(
-            self,
-            other,
-            |l: NodeId, r: NodeId| l.is_one() && r.is_zero(),
-            |l: NodeId, r: NodeId| l.is_zero() || r.is_one()
-        )
+        _u32_apply(self, other, OP::lookup, config, buffer)
     }
 }
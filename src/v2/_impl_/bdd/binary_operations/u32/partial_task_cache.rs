@@ -1,9 +1,11 @@
-use crate::v2::NodeId;
+use crate::v2::_impl_::alloc_config::filled_vec;
+use crate::v2::{NodeId, TaskCacheConfig, TaskHash};
 use crate::v2::_impl_::bdd::binary_operations::u32::PointerPair;
-use std::cmp::max;
-use std::convert::TryFrom;
-use std::num::NonZeroU64;
-use std::ops::{BitXor, Rem};
+use core::cmp::max;
+use core::convert::TryFrom;
+use core::num::NonZeroU64;
+use core::ops::{BitXor, Rem};
+use alloc::vec::Vec;
 
 /// **(internal)** Task cache based on the general `u48` version. See the original
 /// version for documentation comments.
@@ -12,6 +14,8 @@ use std::ops::{BitXor, Rem};
 /// a bit of space and also some hashing time.
 pub(super) struct TaskCache {
     capacity: NonZeroU64,
+    two_way: bool,
+    rolling: bool,
     keys: Vec<PointerPair>,
     values: Vec<NodeId>,
 }
@@ -20,13 +24,15 @@ impl TaskCache {
     const HASH_BLOCK: u64 = 1 << 14;
     const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
 
-    pub fn new(left_size: usize, right_size: usize) -> TaskCache {
+    pub fn new(left_size: usize, right_size: usize, config: &TaskCacheConfig) -> TaskCache {
         debug_assert!(left_size >= right_size);
-        let capacity = max(left_size, right_size);
+        let capacity = config.entries(max(left_size, right_size));
         TaskCache {
             capacity: NonZeroU64::new(u64::try_from(capacity).unwrap()).unwrap(),
-            keys: vec![PointerPair(0); capacity],
-            values: vec![NodeId::ZERO; capacity],
+            two_way: config.is_two_way(),
+            rolling: config.hash == TaskHash::Rolling,
+            keys: filled_vec(PointerPair(0), capacity, &config.alloc),
+            values: filled_vec(NodeId::ZERO, capacity, &config.alloc),
         }
     }
 
@@ -36,6 +42,8 @@ impl TaskCache {
         unsafe {
             if *self.keys.get_unchecked(index) == tasks {
                 *self.values.get_unchecked(index)
+            } else if self.two_way && *self.keys.get_unchecked(index + 1) == tasks {
+                *self.values.get_unchecked(index + 1)
             } else {
                 NodeId::UNDEFINED
             }
@@ -46,6 +54,10 @@ impl TaskCache {
     pub fn write(&mut self, tasks: PointerPair, result: NodeId) {
         let index = self.hashed_index(tasks);
         unsafe {
+            if self.two_way && *self.keys.get_unchecked(index) != tasks {
+                *self.keys.get_unchecked_mut(index + 1) = *self.keys.get_unchecked(index);
+                *self.values.get_unchecked_mut(index + 1) = *self.values.get_unchecked(index);
+            }
             let key = self.keys.get_unchecked_mut(index);
             let value = self.values.get_unchecked_mut(index);
             *key = tasks;
@@ -55,15 +67,9 @@ impl TaskCache {
 
     #[inline]
     pub fn prefetch(&self, tasks: PointerPair) {
-        if cfg!(target_arch = "x86_64") {
-            let index = self.hashed_index(tasks);
-            unsafe {
-                let key: *const PointerPair = self.keys.get_unchecked(index);
-                let value: *const NodeId = self.values.get_unchecked(index);
-                std::arch::x86_64::_mm_prefetch::<3>(key as *const i8);
-                std::arch::x86_64::_mm_prefetch::<3>(value as *const i8);
-            }
-        }
+        let index = self.hashed_index(tasks);
+        crate::arch::prefetch(self.keys.as_ptr().wrapping_add(index));
+        crate::arch::prefetch(self.values.as_ptr().wrapping_add(index));
     }
 
     #[inline]
@@ -78,8 +84,16 @@ impl TaskCache {
         let (left, right) = tasks.unpack();
         let left_hash = u64::from(left).wrapping_mul(Self::SEED);
         let right_hash = u64::from(right).wrapping_mul(Self::SEED);
-        let block_index = left_hash.bitxor(right_hash).rem(Self::HASH_BLOCK);
-        (left.0 + block_index).rem(self.capacity) as usize
-        //left_hash.bitxor(right_hash).rem(self.capacity) as usize
+        let index = if self.rolling {
+            let block_index = left_hash.bitxor(right_hash).rem(Self::HASH_BLOCK);
+            (left.0 + block_index).rem(self.capacity)
+        } else {
+            left_hash.bitxor(right_hash).rem(self.capacity)
+        };
+        if self.two_way {
+            (index & !1) as usize
+        } else {
+            index as usize
+        }
     }
 }
@@ -3,12 +3,12 @@ use crate::v2::NodeId;
 
 impl PointerPair {
     pub const RESULT_MASK: u64 = 1 << 63;
-    const LEFT_POINTER_MASK: u64 = 0xffff_ffff;
+    pub const LEFT_POINTER_MASK: u64 = 0xffff_ffff;
 
     #[inline]
     pub fn pack(left: NodeId, right: NodeId) -> PointerPair {
         debug_assert!(left.0 < super::MAX_LEFT_SIZE);
-        debug_assert!(left.0 < super::MAX_RIGHT_SIZE);
+        debug_assert!(right.0 < super::MAX_RIGHT_SIZE);
         // Left pointer goes into the "lower" bits.
         PointerPair((right.0 << 32) | left.0)
     }
@@ -0,0 +1,309 @@
+use super::coupled_dfs_stack::Stack;
+use super::partial_node_cache::NodeCache;
+use super::partial_task_cache::TaskCache;
+use crate::v2::_impl_::storage::StorageAccess;
+use crate::v2::{Bdd, BddNode, NodeId, TaskCacheConfig, VariableId};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::{max, min};
+
+/// **(internal)** Marks the keys of the nested `Or` tasks in the task cache, which is shared
+/// with the product tasks (ids of the product tasks always fit into 48 bits).
+const OR_TASK: u64 = 1 << 62;
+
+impl Bdd {
+    /// **(internal)** The relational product `exists variables: self & other` (see
+    /// `Bdd::and_exists`), computed by a single "coupled DFS" search.
+    ///
+    /// The product tasks are expanded in the same way as in `_u48_apply` with the `And`
+    /// table. Once both results of a task with a quantified decision variable are known,
+    /// they are merged by a nested `Or` search over the result nodes created so far, which
+    /// shares the node cache and the task cache with the product. If the low result of such
+    /// a task is `true`, its high task is skipped.
+    ///
+    /// The merged results leave unreachable nodes in the node cache, so the reachable nodes
+    /// are copied into the final `Bdd`.
+    pub(crate) fn _u48_and_exists(
+        &self,
+        other: &Bdd,
+        variables: &[VariableId],
+        config: &TaskCacheConfig,
+    ) -> Bdd {
+        if self.node_count() < other.node_count() {
+            // The task cache expects the larger operand on the left.
+            return other._u48_and_exists(self, variables, config);
+        }
+        let variable_count = max(self.variable_count, other.variable_count);
+        let root = and_lookup(self.root_node(), other.root_node());
+        if !root.is_undefined() {
+            return Bdd::_new_constant(root, variable_count);
+        }
+
+        let mut quantified = vec![false; usize::from(variable_count)];
+        for variable in variables {
+            if let Some(flag) = quantified.get_mut(usize::from(variable.0)) {
+                *flag = true;
+            }
+        }
+        let is_quantified = |left: NodeId, right: NodeId| {
+            let variable = min(self.get_variable(left), other.get_variable(right));
+            quantified[usize::from(variable.0)]
+        };
+
+        let mut node_cache = NodeCache::new(self.node_count(), Vec::new(), &config.alloc);
+        let mut task_cache = TaskCache::new(self.node_count(), other.node_count(), config);
+        let mut stack = Stack::new(self.height_bound() + other.height_bound());
+        // Every path in a result has at most `variable_count` decision nodes.
+        let mut or_stack = Stack::new(2 * usize::from(variable_count));
+        unsafe {
+            stack.push_task_unchecked(self.root_node(), other.root_node());
+        }
+
+        loop {
+            let mut finish_task = stack.has_result();
+
+            if !finish_task {
+                let (left, right) = unsafe { stack.peek_as_task_unchecked() };
+                let mut result = and_lookup(left, right);
+                if result.is_undefined() {
+                    result = task_cache.read(left, right);
+                }
+                if !result.is_undefined() {
+                    finish_task = unsafe { save_result(&mut stack, result, &is_quantified) };
+                } else {
+                    let left_node = unsafe { self.get_node_unchecked(left) };
+                    let right_node = unsafe { other.get_node_unchecked(right) };
+                    let (left_var, left_low, left_high) = left_node.unpack();
+                    let (right_var, right_low, right_high) = right_node.unpack();
+                    self.prefetch(left_low);
+                    other.prefetch(right_low);
+
+                    let decision_variable = min(left_var, right_var);
+                    let (left_low, left_high) = if decision_variable == left_var {
+                        (left_low, left_high)
+                    } else {
+                        (left, left)
+                    };
+                    let (right_low, right_high) = if decision_variable == right_var {
+                        (right_low, right_high)
+                    } else {
+                        (right, right)
+                    };
+
+                    task_cache.prefetch(left_high, right_high);
+                    unsafe {
+                        stack.push_task_unchecked(left_high, right_high);
+                        stack.push_task_unchecked(left_low, right_low);
+                    }
+                }
+            }
+
+            if finish_task {
+                let (low, high) = unsafe { stack.pop_results_unchecked() };
+                let (left, right) = unsafe { stack.peek_as_task_unchecked() };
+                let decision_variable = min(self.get_variable(left), other.get_variable(right));
+
+                let result = if quantified[usize::from(decision_variable.0)] {
+                    or_results(&mut node_cache, &mut task_cache, &mut or_stack, low, high)
+                } else if low == high {
+                    low
+                } else {
+                    node_cache.ensure(BddNode::pack(decision_variable, low, high))
+                };
+                task_cache.write(left, right, result);
+                unsafe { save_result(&mut stack, result, &is_quantified) };
+            }
+
+            if stack.has_last_entry() {
+                break;
+            }
+        }
+
+        let mut result = compact(node_cache.export(), stack.last_result());
+        result.update_variable_count(variable_count);
+        result.debug_check_integrity("and_exists");
+        result
+    }
+}
+
+/// **(internal)** The lookup table of the product tasks (same as `And`).
+#[inline]
+fn and_lookup(left: NodeId, right: NodeId) -> NodeId {
+    if left.is_zero() || right.is_zero() {
+        NodeId::ZERO
+    } else if left.is_one() && right.is_one() {
+        NodeId::ONE
+    } else {
+        NodeId::UNDEFINED
+    }
+}
+
+/// **(internal)** The lookup table of the nested `Or` tasks. Unlike in a normal apply,
+/// both operands are in the same `Bdd`, so identical operands are resolved as well.
+#[inline]
+fn or_lookup(left: NodeId, right: NodeId) -> NodeId {
+    if left.is_one() || right.is_one() {
+        NodeId::ONE
+    } else if left.is_zero() || left == right {
+        right
+    } else if right.is_zero() {
+        left
+    } else {
+        NodeId::UNDEFINED
+    }
+}
+
+/// **(internal)** The task cache key of a nested `Or` task. The operands are ordered,
+/// since `Or` is commutative.
+#[inline]
+fn or_key(left: NodeId, right: NodeId) -> (NodeId, NodeId) {
+    let (smaller, larger) = (min(left.0, right.0), max(left.0, right.0));
+    (NodeId(smaller | OR_TASK), NodeId(larger))
+}
+
+/// **(internal)** Save the `result` of the top task (see `Stack::save_result_unchecked`).
+///
+/// If the `result` belongs to the low task of a task with a quantified variable and it is
+/// `true`, the high task (which is now on top) is resolved immediately, since `true | x`
+/// is always `true`.
+///
+/// *Precondition:* Same as `Stack::save_result_unchecked`.
+#[inline]
+unsafe fn save_result<Q>(stack: &mut Stack, result: NodeId, is_quantified: &Q) -> bool
+where
+    Q: Fn(NodeId, NodeId) -> bool,
+{
+    if unsafe { stack.save_result_unchecked(result) } {
+        return true;
+    }
+    // The result was swapped below its (high) sibling, so their parent is the third entry.
+    if result.is_one() {
+        let (left, right) = unsafe { stack.peek_parent_unchecked() };
+        if is_quantified(left, right) {
+            return unsafe { stack.save_result_unchecked(NodeId::ONE) };
+        }
+    }
+    false
+}
+
+/// **(internal)** The disjunction of two nodes of the `node_cache`, computed by a nested
+/// "coupled DFS" search on the given `stack`. The new nodes are created in the same
+/// `node_cache`, and the results of the tasks are saved into the shared `task_cache`
+/// (see `or_key`).
+fn or_results(
+    node_cache: &mut NodeCache,
+    task_cache: &mut TaskCache,
+    stack: &mut Stack,
+    left: NodeId,
+    right: NodeId,
+) -> NodeId {
+    let mut root = or_lookup(left, right);
+    if root.is_undefined() {
+        let (key_left, key_right) = or_key(left, right);
+        root = task_cache.read(key_left, key_right);
+    }
+    if !root.is_undefined() {
+        // The stack cannot handle a root task which is resolved immediately.
+        return root;
+    }
+    stack.clear();
+    unsafe {
+        stack.push_task_unchecked(left, right);
+    }
+
+    loop {
+        let mut finish_task = stack.has_result();
+
+        if !finish_task {
+            let (left, right) = unsafe { stack.peek_as_task_unchecked() };
+            let mut result = or_lookup(left, right);
+            if result.is_undefined() {
+                let (key_left, key_right) = or_key(left, right);
+                result = task_cache.read(key_left, key_right);
+            }
+            if !result.is_undefined() {
+                finish_task = unsafe { stack.save_result_unchecked(result) };
+            } else {
+                let (left_var, left_low, left_high) = node_cache.get_node(left).unpack();
+                let (right_var, right_low, right_high) = node_cache.get_node(right).unpack();
+                let decision_variable = min(left_var, right_var);
+                let (left_low, left_high) = if decision_variable == left_var {
+                    (left_low, left_high)
+                } else {
+                    (left, left)
+                };
+                let (right_low, right_high) = if decision_variable == right_var {
+                    (right_low, right_high)
+                } else {
+                    (right, right)
+                };
+                unsafe {
+                    stack.push_task_unchecked(left_high, right_high);
+                    stack.push_task_unchecked(left_low, right_low);
+                }
+            }
+        }
+
+        if finish_task {
+            let (low, high) = unsafe { stack.pop_results_unchecked() };
+            let (left, right) = unsafe { stack.peek_as_task_unchecked() };
+            let left_var = node_cache.get_node(left).variable();
+            let right_var = node_cache.get_node(right).variable();
+            let result = if low == high {
+                low
+            } else {
+                node_cache.ensure(BddNode::pack(min(left_var, right_var), low, high))
+            };
+            let (key_left, key_right) = or_key(left, right);
+            task_cache.write(key_left, key_right, result);
+            unsafe { stack.save_result_unchecked(result) };
+        }
+
+        if stack.has_last_entry() {
+            break;
+        }
+    }
+
+    stack.last_result()
+}
+
+/// **(internal)** Only keep the nodes of `bdd` which are reachable from the given `root`,
+/// in their original order. The nodes of `bdd` must only point to nodes with smaller ids
+/// (which is true for the nodes created by a `NodeCache`).
+fn compact(mut bdd: Bdd, root: NodeId) -> Bdd {
+    if root.as_index() < 2 {
+        return Bdd::_new_constant(root, bdd.variable_count);
+    }
+    let root_index = root.as_index();
+    let mut reachable = vec![false; root_index + 1];
+    reachable[root_index] = true;
+    let mut reachable_count = 0;
+    for index in (2..=root_index).rev() {
+        if reachable[index] {
+            reachable_count += 1;
+            let (_, low, high) = bdd.nodes[index].unpack();
+            reachable[low.as_index()] = true;
+            reachable[high.as_index()] = true;
+        }
+    }
+    if reachable_count == root_index - 1 {
+        // Everything up to the root is reachable, only the nodes after the root are removed.
+        bdd.nodes.truncate(root_index + 1);
+        return bdd;
+    }
+
+    let mut translation = vec![NodeId::UNDEFINED; root_index + 1];
+    translation[0] = NodeId::ZERO;
+    translation[1] = NodeId::ONE;
+    let mut result = Bdd::true_with_capacity(reachable_count + 2);
+    result.update_variable_count(bdd.variable_count);
+    for index in 2..=root_index {
+        if reachable[index] {
+            let (variable, low, high) = bdd.nodes[index].unpack();
+            let low = translation[low.as_index()];
+            let high = translation[high.as_index()];
+            translation[index] = result.push_node(BddNode::pack(variable, low, high));
+        }
+    }
+    result
+}
@@ -1,4 +1,7 @@
+use super::super::stack_capacity;
 use crate::v2::NodeId;
+use alloc::vec::Vec;
+use alloc::vec;
 
 /// **(internal)** A stack that keeps track of tasks that still need to be completed or the results
 /// which have not been used yet. Each entry has either two valid left/right `NodeId` pointers,
@@ -8,21 +11,24 @@ use crate::v2::NodeId;
 /// it will automatically swap with an entry underneath, if that entry is not result as well.
 /// This mechanism ensures that if the top entry is a result, we know that the entry underneath
 /// is a result as well and we can finish the task that spawned them.
+///
+/// The whole protocol (including the fake bottom entry) is exercised step by step
+/// in `coupled_dfs_stack_test`.
 pub(super) struct Stack {
     index_after_last: usize,
     items: Vec<(NodeId, NodeId)>,
 }
 
 impl Stack {
-    /// **(internal)** Create a new stack with a sufficient capacity for a "coupled DFS" over
-    /// `Bdds` with depth bounded by `variable_count`.
-    pub fn new(variable_count: u16) -> Stack {
-        let variable_count = usize::from(variable_count);
+    /// **(internal)** Create a new stack with a sufficient capacity for a "coupled DFS" in
+    /// which at most `height` tasks are expanded on any path (i.e. the sum of the heights
+    /// of the operands, see `Bdd::height_bound`).
+    pub fn new(height: usize) -> Stack {
         let mut stack = Stack {
             index_after_last: 1,
             // In a standard "coupled DFS" algorithm, the stack can never be larger than
-            // 2 * the number of variables in the Bdd.
-            items: vec![(NodeId::ZERO, NodeId::ZERO); 2 * variable_count + 2],
+            // 2 * the number of expanded tasks + the root task + the fake entry.
+            items: vec![(NodeId::ZERO, NodeId::ZERO); stack_capacity(height)],
         };
         // A "fake" first entry ensures that even when the last task finishes, we can safely
         // check the predecessor. It does not count into the length of the stack.
@@ -31,6 +37,32 @@ impl Stack {
         stack
     }
 
+    /// **(internal)** Remove all entries (except for the fake bottom entry), such that
+    /// the stack can be used for another search.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.index_after_last = 1;
+    }
+
+    /// **(internal)** Get the entry two positions below the top, interpreting it as a task.
+    /// Right after `Stack::save_result_unchecked` swapped a high task on top, this is
+    /// the parent task of both the high task and the saved (low) result.
+    ///
+    /// *Precondition:* There are at least three entries on the stack and the third one
+    /// from the top is a task.
+    pub unsafe fn peek_parent_unchecked(&self) -> (NodeId, NodeId) {
+        debug_assert!(self.index_after_last > 3);
+        debug_assert!(!self.items[self.index_after_last - 3].0.is_undefined());
+
+        unsafe { *self.items.get_unchecked(self.index_after_last - 3) }
+    }
+
+    /// **(internal)** The number of entries on the stack (without the fake bottom entry).
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.index_after_last - 1
+    }
+
     /// **(internal)** Returns `true` if the stack has only one entry. This is actually the
     /// terminating condition for the "coupled DFS" search, because in a do-while loop,
     /// the last entry must be a result.
@@ -39,6 +71,15 @@ impl Stack {
         self.index_after_last == 2
     }
 
+    /// **(internal)** The result of the first task, once the search is finished
+    /// (see `Stack::has_last_entry`).
+    #[inline]
+    pub fn last_result(&self) -> NodeId {
+        debug_assert!(self.has_last_entry());
+        debug_assert!(self.items[1].0.is_undefined());
+        self.items[1].1
+    }
+
     /// **(internal)** Create a new task entry on the stack.
     ///
     /// *Precondition:* The capacity of the stack is sufficient (should be trivially satisfied
@@ -125,3 +166,51 @@ impl Stack {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::Stack;
+    use crate::v2::NodeId;
+
+    #[test]
+    pub fn coupled_dfs_stack_test() {
+        let mut stack = Stack::new(2);
+        unsafe {
+            // The root task is the only entry, the fake bottom entry is not counted.
+            stack.push_task_unchecked(NodeId(2), NodeId(3));
+            assert!(stack.has_last_entry());
+            assert!(!stack.has_result());
+            assert_eq!((NodeId(2), NodeId(3)), stack.peek_as_task_unchecked());
+
+            // Expand the root: the high task is pushed first, so the low task is on top.
+            stack.push_task_unchecked(NodeId(4), NodeId(5));
+            stack.push_task_unchecked(NodeId(6), NodeId(7));
+            assert!(!stack.has_last_entry());
+
+            // The low result swaps with the high task underneath, which becomes the top.
+            assert!(!stack.save_result_unchecked(NodeId(10)));
+            assert!(!stack.has_result());
+            assert_eq!((NodeId(4), NodeId(5)), stack.peek_as_task_unchecked());
+            assert_eq!((NodeId(2), NodeId(3)), stack.peek_parent_unchecked());
+
+            // The high result lands on top of the low result: both results are ready.
+            assert!(stack.save_result_unchecked(NodeId(11)));
+            assert!(stack.has_result());
+            assert_eq!((NodeId(10), NodeId(11)), stack.pop_results_unchecked());
+
+            // The root task is on top again and its result never swaps with
+            // the fake bottom entry, which terminates the search.
+            assert_eq!((NodeId(2), NodeId(3)), stack.peek_as_task_unchecked());
+            assert!(stack.save_result_unchecked(NodeId(12)));
+            assert!(stack.has_last_entry());
+            assert!(stack.has_result());
+            assert_eq!(NodeId(12), stack.last_result());
+
+            // The stack can be reused for another search.
+            stack.clear();
+            assert_eq!(0, stack.len());
+            stack.push_task_unchecked(NodeId(4), NodeId(5));
+            assert!(stack.has_last_entry());
+        }
+    }
+}
@@ -1,8 +1,12 @@
-use crate::v2::{Bdd, BddNode, NodeId};
+use crate::v2::_impl_::bdd::binary_operations::capacity::estimate_result_capacity;
+use crate::v2::_impl_::storage::StorageAccess;
+use crate::v2::ops::BooleanOp;
+use crate::v2::{ApplyDiagnostics, Bdd, BddNode, BddStorage, NodeId, TaskCacheConfig};
 use coupled_dfs_stack::Stack;
-use partial_node_cache::NodeCache;
+use partial_node_cache::{NodeCache, StreamingNodeCache};
 use partial_task_cache::TaskCache;
-use std::cmp::{max, min};
+use alloc::vec::Vec;
+use core::cmp::{max, min};
 
 /// **(internal)** A task/result stack used when performing the "coupled DFS" routine.
 mod coupled_dfs_stack;
@@ -13,6 +17,10 @@ mod partial_task_cache;
 /// **(internal)** A partial node cache serves as incomplete storage for uniqueness resolution.
 pub(super) mod partial_node_cache;
 
+/// **(internal)** The relational product on top of the "coupled DFS" search
+/// (see `Bdd::and_exists`).
+mod and_exists;
+
 /// **(internal)** A general apply algorithm for performing arbitrary binary operations
 /// on arbitrary `Bdd` objects.
 ///
@@ -20,17 +28,176 @@ pub(super) mod partial_node_cache;
 /// on literals - it returns `NodeId::UNDEFINED` if the result cannot be resolved
 /// into a terminal.
 ///
+/// The operands can use different storage types (see `BddStorage`). The task cache is
+/// configured by `config`.
+///
 /// Note that the left `Bdd` must always be the larger one.
-pub(super) fn _u48_apply<TABLE>(left_bdd: &Bdd, right_bdd: &Bdd, lookup: TABLE) -> Bdd
+pub(super) fn _u48_apply<L, R, TABLE>(
+    left_bdd: &L,
+    right_bdd: &R,
+    lookup: TABLE,
+    config: &TaskCacheConfig,
+) -> Bdd
+where
+    L: BddStorage + ?Sized,
+    R: BddStorage + ?Sized,
+    TABLE: Fn(NodeId, NodeId) -> NodeId,
+{
+    _u48_apply_in_buffer(left_bdd, right_bdd, lookup, config, Vec::new())
+}
+
+/// **(internal)** Same as `_u48_apply`, but the result nodes are stored in the given
+/// `buffer` (see `NodeCache::new`).
+pub(super) fn _u48_apply_in_buffer<L, R, TABLE>(
+    left_bdd: &L,
+    right_bdd: &R,
+    lookup: TABLE,
+    config: &TaskCacheConfig,
+    buffer: Vec<BddNode>,
+) -> Bdd
 where
+    L: BddStorage + ?Sized,
+    R: BddStorage + ?Sized,
     TABLE: Fn(NodeId, NodeId) -> NodeId,
+{
+    let never = |_: &ApplyDiagnostics| false;
+    let period = INTERRUPT_PERIOD;
+    match _u48_apply_interruptible(left_bdd, right_bdd, lookup, config, buffer, period, never) {
+        Ok((result, _)) => result,
+        Err(_) => unreachable!("The operation cannot be interrupted."),
+    }
+}
+
+/// **(internal)** Same as `_u48_apply`, but the `interrupt` function is called with
+/// the current progress of the operation after every `period` expanded tasks (rounded up
+/// to a power of two). If it returns `true`, the operation is aborted and the progress
+/// at that point is returned as an error. Otherwise, the result is returned together with
+/// the final progress of the operation.
+pub(super) fn _u48_apply_interruptible<L, R, TABLE, INTERRUPT>(
+    left_bdd: &L,
+    right_bdd: &R,
+    lookup: TABLE,
+    config: &TaskCacheConfig,
+    buffer: Vec<BddNode>,
+    period: u64,
+    interrupt: INTERRUPT,
+) -> Result<(Bdd, ApplyDiagnostics), ApplyDiagnostics>
+where
+    L: BddStorage + ?Sized,
+    R: BddStorage + ?Sized,
+    TABLE: Fn(NodeId, NodeId) -> NodeId,
+    INTERRUPT: FnMut(&ApplyDiagnostics) -> bool,
 {
     let variables = max(left_bdd.variable_count(), right_bdd.variable_count());
+    let capacity = estimate_result_capacity(left_bdd, right_bdd, &lookup);
+    let mut node_cache = NodeCache::new(capacity, buffer, &config.alloc);
+    let ensure = |node| node_cache.ensure(node);
+    let search = _u48_search(left_bdd, right_bdd, lookup, config, ensure, period, interrupt);
+
+    if search.root.is_undefined() {
+        Err(search.diagnostics)
+    } else if search.root.as_index() < 2 {
+        Ok((Bdd::_new_constant(search.root, variables), search.diagnostics))
+    } else {
+        // See `Bdd::_operand_copy`, but the operands are not necessarily `Bdd` objects.
+        let mut result = if search.is_left_copy {
+            left_bdd.to_bdd()
+        } else if search.is_right_copy {
+            right_bdd.to_bdd()
+        } else {
+            node_cache.export()
+        };
+        result.update_variable_count(variables);
+        Ok((result, search.diagnostics))
+    }
+}
+
+/// **(internal)** Same as `_u48_apply`, but the result nodes are not stored. Instead,
+/// every created node is passed to the `sink`. Returns the id of the result root.
+///
+/// The (fixed-size) unique table has space for `node_capacity` nodes, or for the estimated
+/// size of the result if `node_capacity` is not given.
+pub(super) fn _u48_apply_streaming<L, R, TABLE, SINK>(
+    left_bdd: &L,
+    right_bdd: &R,
+    lookup: TABLE,
+    node_capacity: Option<usize>,
+    config: &TaskCacheConfig,
+    sink: SINK,
+) -> NodeId
+where
+    L: BddStorage + ?Sized,
+    R: BddStorage + ?Sized,
+    TABLE: Fn(NodeId, NodeId) -> NodeId,
+    SINK: FnMut(NodeId, BddNode),
+{
+    let capacity =
+        node_capacity.unwrap_or_else(|| estimate_result_capacity(left_bdd, right_bdd, &lookup));
+    let mut node_cache = StreamingNodeCache::new(capacity, &config.alloc, sink);
+    let ensure = |node| node_cache.ensure(node);
+    let never = |_: &ApplyDiagnostics| false;
+    _u48_search(left_bdd, right_bdd, lookup, config, ensure, INTERRUPT_PERIOD, never).root
+}
+
+/// **(internal)** The outcome of `_u48_search`.
+struct Search {
+    /// The id of the result root (as returned by the node cache), or `NodeId::UNDEFINED`
+    /// if the search was interrupted.
+    root: NodeId,
+    /// The result is equivalent to the left operand (see `Bdd::_operand_copy`).
+    is_left_copy: bool,
+    /// The result is equivalent to the right operand.
+    is_right_copy: bool,
+    /// The progress of the search (the `elapsed` time is not measured here).
+    diagnostics: ApplyDiagnostics,
+}
+
+/// **(internal)** The default number of expanded tasks between two calls to the `interrupt`
+/// function of `_u48_search`.
+pub(super) const INTERRUPT_PERIOD: u64 = 1 << 12;
+
+/// **(internal)** The "coupled DFS" search of the general apply algorithm. New result nodes
+/// are created using `ensure`, which must return a unique id of the node (new nodes must
+/// have increasing ids). The search is aborted once `interrupt` returns `true` (it is
+/// called every `period` expanded tasks, rounded up to a power of two). The task cache
+/// is configured by `config`.
+fn _u48_search<L, R, TABLE, ENSURE, INTERRUPT>(
+    left_bdd: &L,
+    right_bdd: &R,
+    lookup: TABLE,
+    config: &TaskCacheConfig,
+    mut ensure: ENSURE,
+    period: u64,
+    mut interrupt: INTERRUPT,
+) -> Search
+where
+    L: BddStorage + ?Sized,
+    R: BddStorage + ?Sized,
+    TABLE: Fn(NodeId, NodeId) -> NodeId,
+    ENSURE: FnMut(BddNode) -> NodeId,
+    INTERRUPT: FnMut(&ApplyDiagnostics) -> bool,
+{
+    let mut diagnostics = ApplyDiagnostics::default();
+    let period_mask = period.next_power_of_two() - 1;
+
+    let root_result = lookup(left_bdd.root_node(), right_bdd.root_node());
+    if !root_result.is_undefined() {
+        // The stack cannot handle a root task which is resolved immediately.
+        return Search {
+            root: root_result,
+            is_left_copy: false,
+            is_right_copy: false,
+            diagnostics,
+        };
+    }
 
-    let mut is_not_false = false;
-    let mut node_cache = NodeCache::new(left_bdd.node_count());
-    let mut task_cache = TaskCache::new(left_bdd.node_count(), right_bdd.node_count());
-    let mut stack = Stack::new(variables);
+    // Stays true while the result is a copy of the left/right operand (see `Bdd::_operand_copy`).
+    let mut is_left_copy = true;
+    let mut is_right_copy = true;
+    let mut task_cache = TaskCache::new(left_bdd.node_count(), right_bdd.node_count(), config);
+    let mut stack = Stack::new(left_bdd.height_bound() + right_bdd.height_bound());
+    // The largest id returned by `ensure` so far (new nodes have increasing ids).
+    let mut last_created = NodeId::ONE;
     unsafe {
         stack.push_task_unchecked(left_bdd.root_node(), right_bdd.root_node());
     }
@@ -46,13 +213,15 @@ where
             let (left, right) = unsafe { stack.peek_as_task_unchecked() };
 
             let lookup_result = lookup(left, right);
-            is_not_false = is_not_false || lookup_result.is_one();
 
             if !lookup_result.is_undefined() {
+                is_left_copy = is_left_copy && lookup_result == left;
+                is_right_copy = is_right_copy && lookup_result == right;
                 finish_task = finish_task || unsafe { stack.save_result_unchecked(lookup_result) };
             } else {
                 let cached_node = task_cache.read(left, right);
                 if !cached_node.is_undefined() {
+                    diagnostics.task_cache_hits += 1;
                     finish_task =
                         finish_task || unsafe { stack.save_result_unchecked(cached_node) };
                 } else {
@@ -65,6 +234,18 @@ where
 
                     let decision_variable = min(left_var, right_var);
 
+                    diagnostics.tasks_expanded += 1;
+                    diagnostics.deepest_level = max(diagnostics.deepest_level, decision_variable.0);
+                    let is_check = diagnostics.tasks_expanded & period_mask == 0;
+                    if is_check && interrupt(&diagnostics) {
+                        return Search {
+                            root: NodeId::UNDEFINED,
+                            is_left_copy: false,
+                            is_right_copy: false,
+                            diagnostics,
+                        };
+                    }
+
                     let (left_low, left_high) = if decision_variable == left_var {
                         (left_low, left_high)
                     } else {
@@ -84,6 +265,7 @@ where
                         stack.push_task_unchecked(left_high, right_high);
                         stack.push_task_unchecked(left_low, right_low);
                     }
+                    diagnostics.stack_high_water = max(diagnostics.stack_high_water, stack.len());
                 }
             }
         }
@@ -102,7 +284,12 @@ where
                 let decision_variable = min(left_var, right_var);
 
                 let node = BddNode::pack(decision_variable, low, high);
-                let result_id = node_cache.ensure(node);
+                let result_id = ensure(node);
+                diagnostics.node_lookups += 1;
+                if result_id.0 > last_created.0 {
+                    diagnostics.nodes_created += 1;
+                    last_created = result_id;
+                }
                 task_cache.write(left, right, result_id);
                 unsafe { stack.save_result_unchecked(result_id) };
             }
@@ -113,201 +300,26 @@ where
         }
     }
 
-    if is_not_false {
-        let mut result = node_cache.export();
-        result.update_variable_count(variables);
-        result
-    } else {
-        Bdd::new_false()
+    Search {
+        root: stack.last_result(),
+        is_left_copy,
+        is_right_copy,
+        diagnostics,
     }
 }
 
-/// **(internal)** I apologise profoundly for this, but this seems to be a consistently faster
-/// approach than using a lookup table, so we use this macro to implement the pre-defined binary
-/// operations.
-///
-/// It is the general apply function but with the lookup table explicitly inlined using a macro.
-macro_rules! apply_u48 {
-    ($left:ident, $right:ident, $zero:expr, $one:expr) => {{
-        let left_bdd = $left;
-        let right_bdd = $right;
-        let variables = max(left_bdd.variable_count(), right_bdd.variable_count());
-
-        let mut is_not_false = false;
-        let mut node_cache = NodeCache::new(left_bdd.node_count());
-        let mut task_cache = TaskCache::new(left_bdd.node_count(), right_bdd.node_count());
-        let mut stack = Stack::new(variables);
-        unsafe { stack.push_task_unchecked(left_bdd.root_node(), right_bdd.root_node()); }
-
-        // The calls to stack operations are safe due to the order in which we perform the Bdd search.
-        loop {
-            // If the top is a result, go straight to finishing a task. If not, first expand,
-            // but if the result of the expansion is a finished task, then also finish a task.
-            let mut finish_task = stack.has_result();
-
-            if !finish_task {
-                // Expand current top task.
-                let (left, right) = unsafe { stack.peek_as_task_unchecked() };
-
-                if $zero(left, right) {
-                    finish_task = finish_task || unsafe { stack.save_result_unchecked(NodeId::ZERO) };
-                } else if $one(left, right) {
-                    is_not_false = true;
-                    finish_task = finish_task || unsafe { stack.save_result_unchecked(NodeId::ONE) };
-                } else {
-                    let cached_node = task_cache.read(left, right);
-                    if !cached_node.is_undefined() {
-                        finish_task = finish_task || unsafe { stack.save_result_unchecked(cached_node) };
-                    } else {
-                        let left_node = unsafe { left_bdd.get_node_unchecked(left) };
-                        let right_node = unsafe { right_bdd.get_node_unchecked(right) };
-                        let (left_var, left_low, left_high) = left_node.unpack();
-                        let (right_var, right_low, right_high) = right_node.unpack();
-                        left_bdd.prefetch(left_low);
-                        right_bdd.prefetch(right_low);
-
-                        let decision_variable = min(left_var, right_var);
-
-                        let (left_low, left_high) = if decision_variable == left_var {
-                            (left_low, left_high)
-                        } else {
-                            (left, left)
-                        };
-
-                        let (right_low, right_high) = if decision_variable == right_var {
-                            (right_low, right_high)
-                        } else {
-                            (right, right)
-                        };
-
-                        task_cache.prefetch(left_high, right_high);
-
-                        // When completed, the order of tasks will be swapped (high on top).
-                        unsafe {
-                            stack.push_task_unchecked(left_high, right_high);
-                            stack.push_task_unchecked(left_low, right_low);
-                        }
-                    }
-                }
-            }
-
-            if finish_task {
-                // Finish current top task.
-                let (low, high) = unsafe { stack.pop_results_unchecked() };
-                let (left, right) = unsafe { stack.peek_as_task_unchecked() };
-
-                if high == low {
-                    task_cache.write(left, right, low);
-                    unsafe { stack.save_result_unchecked(low) };
-                } else {
-                    let (left_var, right_var) =
-                        (left_bdd.get_variable(left), right_bdd.get_variable(right));
-                    let decision_variable = min(left_var, right_var);
-
-                    let node = BddNode::pack(decision_variable, low, high);
-                    let result_id = node_cache.ensure(node);
-                    task_cache.write(left, right, result_id);
-                    unsafe { stack.save_result_unchecked(result_id) };
-                }
-            }
-
-            if stack.has_last_entry() {
-                break; // The last entry is the result to the first task.
-            }
-        }
-
-        if is_not_false {
-            let mut result = node_cache.export();
-            result.update_variable_count(variables);
-            result
-        } else {
-            Bdd::new_false()
-        }
-    }}
-}
-
-/// **(internal)** Macro-generated general functions for mainstream logical operators.
-///
-/// We have a mirror operation for each asymmetric operation, because the main algorithm
-/// assumes that the left Bdd is always the larger one.
 impl Bdd {
-    pub(super) fn _u48_and(&self, other: &Bdd) -> Bdd {
-        debug_assert!(self.node_count() >= other.node_count());
-        apply_u48!(
-            self,
-            other,
-            |l: NodeId, r: NodeId| l.is_zero() || r.is_zero(),
-            |l: NodeId, r: NodeId| l.is_one() && r.is_one()
-        )
-    }
-
-    pub(super) fn _u48_or(&self, other: &Bdd) -> Bdd {
-        debug_assert!(self.node_count() >= other.node_count());
-        apply_u48!(
-            self,
-            other,
-            |l: NodeId, r: NodeId| l.is_zero() && r.is_zero(),
-            |l: NodeId, r: NodeId| l.is_one() || r.is_one()
-        )
-    }
-
-    pub(super) fn _u48_imp(&self, other: &Bdd) -> Bdd {
-        debug_assert!(self.node_count() >= other.node_count());
-        apply_u48!(
-            self,
-            other,
-            |l: NodeId, r: NodeId| l.is_one() && r.is_zero(),
-            |l: NodeId, r: NodeId| l.is_zero() || r.is_one()
-        )
-    }
-
-    pub(super) fn _u48_inv_imp(&self, other: &Bdd) -> Bdd {
-        debug_assert!(self.node_count() >= other.node_count());
-        apply_u48!(
-            self,
-            other,
-            |l: NodeId, r: NodeId| l.is_zero() || r.is_one(),
-            |l: NodeId, r: NodeId| l.is_one() && r.is_zero()
-        )
-    }
-
-    pub(super) fn _u48_iff(&self, other: &Bdd) -> Bdd {
-        debug_assert!(self.node_count() >= other.node_count());
-        apply_u48!(
-            self,
-            other,
-            |l: NodeId, r: NodeId| (l.is_one() && r.is_zero()) || (l.is_zero() && r.is_one()),
-            |l: NodeId, r: NodeId| (l.is_zero() && r.is_zero()) || (l.is_one() && r.is_one())
-        )
-    }
-
-    pub(super) fn _u48_xor(&self, other: &Bdd) -> Bdd {
-        debug_assert!(self.node_count() >= other.node_count());
-        apply_u48!(
-            self,
-            other,
-            |l: NodeId, r: NodeId| (l.is_one() && r.is_one()) || (l.is_zero() && r.is_zero()),
-            |l: NodeId, r: NodeId| (l.is_zero() && r.is_one()) || (l.is_one() && r.is_zero())
-        )
-    }
-
-    pub(super) fn _u48_and_not(&self, other: &Bdd) -> Bdd {
-        debug_assert!(self.node_count() >= other.node_count());
-        apply_u48!(
-            self,
-            other,
-            |l: NodeId, r: NodeId| l.is_zero() || r.is_one(),
-            |l: NodeId, r: NodeId| l.is_one() && r.is_zero()
-        )
-    }
-
-    pub(super) fn _u48_not_and(&self, other: &Bdd) -> Bdd {
+    /// **(internal)** The general apply algorithm specialized to the operator `OP`.
+    ///
+    /// The lookup table of `OP` is a plain function whose terminal checks are evaluated at
+    /// compile time, so it is fully inlined into this copy of the algorithm.
+    pub(super) fn _u48_apply_op<OP: BooleanOp>(
+        &self,
+        other: &Bdd,
+        config: &TaskCacheConfig,
+        buffer: Vec<BddNode>,
+    ) -> Bdd {
         debug_assert!(self.node_count() >= other.node_count());
-        apply_u48!(
-            self,
-            other,
-            |l: NodeId, r: NodeId| l.is_one() && r.is_zero(),
-            |l: NodeId, r: NodeId| l.is_zero() || r.is_one()
-        )
+        _u48_apply_in_buffer(self, other, OP::lookup, config, buffer)
     }
 }
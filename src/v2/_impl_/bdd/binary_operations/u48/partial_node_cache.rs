@@ -1,15 +1,20 @@
-use crate::v2::{Bdd, BddNode, NodeId};
-use std::convert::TryFrom;
-use std::num::NonZeroU64;
-use std::ops::{BitXor, Rem};
+use crate::v2::_impl_::alloc_config::{advise_huge_pages, filled_vec};
+use crate::v2::{Bdd, BddAllocConfig, BddNode, NodeId};
+use core::convert::TryFrom;
+use core::num::NonZeroU64;
+use core::ops::{BitXor, Rem};
+use alloc::vec::Vec;
 
-/// **(internal)** A partial hash map which handles uniqueness queries for the nodes of a `Bdd`.
+/// **(internal)** A hash map which handles uniqueness queries for the nodes of a `Bdd`.
 /// It owns the result `Bdd` into which all the nodes are stored (without leaking).
 ///
-/// It is a hash map which overwrites on collision, just as `TaskCache`, but it keeps the keys
-/// in the result `Bdd`, avoiding double allocation. We also assume that `NodeId::ZERO` is never
-/// saved into the cache (since it has a static position) and thus we can use it as an undefined
-/// value to speed up initial allocation.
+/// It is an open-addressing hash map with linear probing (same as the unique table of
+/// `ApplyContext`), but it keeps the keys in the result `Bdd`, avoiding double allocation.
+/// We also assume that `NodeId::ZERO` is never saved into the cache (since it has a static
+/// position) and thus we can use it as an undefined value to speed up initial allocation.
+///
+/// The initial capacity is only an estimate of the result size (see `capacity`). Once
+/// the table is half full, it doubles its size, so every node is stored exactly once.
 pub struct NodeCache {
     /*
        A little horror story for you: For some reason, if you try to keep `nodes`
@@ -23,19 +28,28 @@ pub struct NodeCache {
     nodes: Bdd,
     // Every value is either `NodeId::ZERO` or a valid pointer into `nodes`.
     values: Vec<NodeId>,
+    alloc: BddAllocConfig,
 }
 
 impl NodeCache {
     const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
 
-    /// **(internal)** Create a new node cache backed by a `Bdd`. The capacity of the `Bdd` will
-    /// extend if needed, but the capacity of the hash table is fixed.
-    pub fn new(capacity: usize) -> NodeCache {
+    /// **(internal)** Create a new node cache backed by a `Bdd` which is expected to have
+    /// `capacity` nodes. Both the `Bdd` and the hash table will extend if needed.
+    ///
+    /// The nodes of the `Bdd` are stored in the `buffer` (see `Bdd::true_in_buffer`), which
+    /// is typically empty, or the node vector of a `Bdd` which is no longer needed. The memory
+    /// of both is allocated according to `alloc`.
+    pub fn new(capacity: usize, buffer: Vec<BddNode>, alloc: &BddAllocConfig) -> NodeCache {
         debug_assert!(capacity > 0);
+        let slots = 2 * capacity;
+        let mut nodes = Bdd::true_in_buffer(buffer, capacity);
+        advise_huge_pages(&mut nodes.nodes, alloc);
         NodeCache {
-            nodes: Bdd::true_with_capacity(capacity),
-            values: vec![NodeId::ZERO; capacity],
-            capacity: NonZeroU64::new(u64::try_from(capacity).unwrap()).unwrap(),
+            nodes,
+            values: filled_vec(NodeId::ZERO, slots, alloc),
+            capacity: NonZeroU64::new(u64::try_from(slots).unwrap()).unwrap(),
+            alloc: *alloc,
         }
     }
 
@@ -43,18 +57,53 @@ impl NodeCache {
     /// the node is created. Returns a valid id of the existing or created node.
     #[inline]
     pub fn ensure(&mut self, node: BddNode) -> NodeId {
-        let index = self.hash(node);
-        let entry = unsafe { self.values.get_unchecked_mut(index) };
-        let candidate_id = *entry;
-        if !candidate_id.is_zero() && self.nodes.get_node(candidate_id) == node {
-            candidate_id
-        } else {
-            let new_id = self.nodes.push_node(node);
-            *entry = new_id;
-            new_id
+        let mut index = self.hash(node);
+        loop {
+            let candidate_id = unsafe { *self.values.get_unchecked(index) };
+            if candidate_id.is_zero() {
+                break;
+            }
+            if self.nodes.get_node(candidate_id) == node {
+                return candidate_id;
+            }
+            index += 1;
+            if index == self.values.len() {
+                index = 0;
+            }
+        }
+        let new_id = self.nodes.push_node(node);
+        unsafe {
+            *self.values.get_unchecked_mut(index) = new_id;
+        }
+        if 2 * new_id.0 > self.capacity.get() {
+            self.grow();
+        }
+        new_id
+    }
+
+    /// **(internal)** Double the size of the hash table and re-insert all nodes. This is
+    /// only needed when the initial capacity was underestimated.
+    #[cold]
+    fn grow(&mut self) {
+        let slots = 2 * self.values.len();
+        self.capacity = NonZeroU64::new(u64::try_from(slots).unwrap()).unwrap();
+        self.values = filled_vec(NodeId::ZERO, slots, &self.alloc);
+        for index in 2..self.nodes.node_count() {
+            let id = NodeId(index as u64);
+            let mut slot = self.hash(self.nodes.get_node(id));
+            while !self.values[slot].is_zero() {
+                slot = (slot + 1) % slots;
+            }
+            self.values[slot] = id;
         }
     }
 
+    /// **(internal)** Read a node which was returned by `NodeCache::ensure`.
+    #[inline]
+    pub fn get_node(&self, id: NodeId) -> BddNode {
+        self.nodes.get_node(id)
+    }
+
     /// Finalize this cache and return the final `Bdd` object.
     #[inline]
     pub fn export(self) -> Bdd {
@@ -67,8 +116,78 @@ impl NodeCache {
     /// check bounds when using it.
     #[inline]
     fn hash(&self, node: BddNode) -> usize {
-        let left = node.0.wrapping_mul(Self::SEED);
-        let right = node.1.wrapping_mul(Self::SEED);
-        left.bitxor(right).rem(self.capacity) as usize
+        node_hash(node, self.capacity)
+    }
+}
+
+/// **(internal)** A variant of `NodeCache` which does not keep the result `Bdd`. Instead,
+/// every created node is passed to the `sink` together with its (new) id.
+///
+/// The keys are stored directly in the hash table, so the memory footprint is fixed
+/// regardless of the size of the result. Same as `NodeCache`, the table uses linear probing,
+/// but it never grows: when `STREAMING_PROBES` consecutive slots are occupied, the first
+/// of them is overwritten (and the result may contain duplicate nodes).
+pub struct StreamingNodeCache<SINK: FnMut(NodeId, BddNode)> {
+    capacity: NonZeroU64,
+    // Entries with a `NodeId::ZERO` value are empty.
+    entries: Vec<(BddNode, NodeId)>,
+    next_id: u64,
+    sink: SINK,
+}
+
+/// **(internal)** The number of slots checked by `StreamingNodeCache::ensure`.
+const STREAMING_PROBES: usize = 8;
+
+impl<SINK: FnMut(NodeId, BddNode)> StreamingNodeCache<SINK> {
+    /// **(internal)** Create a new streaming cache for a result which is expected to have
+    /// `capacity` nodes. The ids of created nodes start at `2` (after the terminal nodes).
+    pub fn new(capacity: usize, alloc: &BddAllocConfig, sink: SINK) -> StreamingNodeCache<SINK> {
+        debug_assert!(capacity > 0);
+        let slots = 2 * capacity;
+        StreamingNodeCache {
+            capacity: NonZeroU64::new(u64::try_from(slots).unwrap()).unwrap(),
+            entries: filled_vec((BddNode::ZERO, NodeId::ZERO), slots, alloc),
+            next_id: 2,
+            sink,
+        }
+    }
+
+    /// **(internal)** Same as `NodeCache::ensure`, but a created node is only reported
+    /// to the `sink`.
+    #[inline]
+    pub fn ensure(&mut self, node: BddNode) -> NodeId {
+        let home = node_hash(node, self.capacity);
+        let mut index = home;
+        for _ in 0..STREAMING_PROBES {
+            let entry = unsafe { self.entries.get_unchecked(index) };
+            if entry.1.is_zero() {
+                break;
+            }
+            if entry.0 == node {
+                return entry.1;
+            }
+            index += 1;
+            if index == self.entries.len() {
+                index = 0;
+            }
+        }
+        if !self.entries[index].1.is_zero() {
+            // All probed slots are occupied.
+            index = home;
+        }
+        let new_id = NodeId(self.next_id);
+        self.next_id += 1;
+        self.entries[index] = (node, new_id);
+        (self.sink)(new_id, node);
+        new_id
     }
 }
+
+/// **(internal)** The hash function shared by the node caches. The result is always
+/// smaller than `capacity`.
+#[inline]
+fn node_hash(node: BddNode, capacity: NonZeroU64) -> usize {
+    let left = node.0.wrapping_mul(NodeCache::SEED);
+    let right = node.1.wrapping_mul(NodeCache::SEED);
+    left.bitxor(right).rem(capacity) as usize
+}
@@ -1,8 +1,10 @@
-use crate::v2::NodeId;
-use std::cmp::max;
-use std::convert::TryFrom;
-use std::num::NonZeroU64;
-use std::ops::{BitXor, Rem};
+use crate::v2::_impl_::alloc_config::filled_vec;
+use crate::v2::{NodeId, TaskCacheConfig, TaskHash};
+use core::cmp::max;
+use core::convert::TryFrom;
+use core::num::NonZeroU64;
+use core::ops::{BitXor, Rem};
+use alloc::vec::Vec;
 
 /// **(internal)** A partial hash map which saves the results of already processed tasks.
 ///
@@ -44,8 +46,14 @@ use std::ops::{BitXor, Rem};
 /// (Assuming the super block is bigger than the table. If not, it's just one table)
 /// This way, the window is moving predictably with respect to both pointers and the
 /// size of the block can be an (essentially) arbitrary constant.
+///
+/// The size, the hash function and the replacement policy can be changed using
+/// a `TaskCacheConfig`. With `ReplacementPolicy::TwoWay`, every hashed index is rounded
+/// down to an even bucket, and the odd slot holds the entry replaced most recently.
 pub(super) struct TaskCache {
     capacity: NonZeroU64,
+    two_way: bool,
+    rolling: bool,
     keys: Vec<(NodeId, NodeId)>,
     values: Vec<NodeId>,
 }
@@ -61,17 +69,20 @@ impl TaskCache {
     const HASH_BLOCK: u64 = 1 << 14;
     const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
 
-    /// **(internal)** Create a new `TaskCache` with the given fixed (non-zero!) capacity.
+    /// **(internal)** Create a new `TaskCache` with a fixed capacity given by the `config`
+    /// (by default, the size of the larger operand).
     ///
     /// Note that we expect the *left* size to be larger than the *right* size, due to
     /// the way our hashing algorithm works.
-    pub fn new(left_size: usize, right_size: usize) -> TaskCache {
+    pub fn new(left_size: usize, right_size: usize, config: &TaskCacheConfig) -> TaskCache {
         debug_assert!(left_size >= right_size);
-        let capacity = max(left_size, right_size);
+        let capacity = config.entries(max(left_size, right_size));
         TaskCache {
             capacity: NonZeroU64::new(u64::try_from(capacity).unwrap()).unwrap(),
-            keys: vec![(NodeId::ZERO, NodeId::ZERO); capacity],
-            values: vec![NodeId::ZERO; capacity],
+            two_way: config.is_two_way(),
+            rolling: config.hash == TaskHash::Rolling,
+            keys: filled_vec((NodeId::ZERO, NodeId::ZERO), capacity, &config.alloc),
+            values: filled_vec(NodeId::ZERO, capacity, &config.alloc),
         }
     }
 
@@ -83,6 +94,8 @@ impl TaskCache {
         unsafe {
             if *self.keys.get_unchecked(index) == (left, right) {
                 *self.values.get_unchecked(index)
+            } else if self.two_way && *self.keys.get_unchecked(index + 1) == (left, right) {
+                *self.values.get_unchecked(index + 1)
             } else {
                 NodeId::UNDEFINED
             }
@@ -94,6 +107,11 @@ impl TaskCache {
     pub fn write(&mut self, left: NodeId, right: NodeId, result: NodeId) {
         let index = self.hashed_index(left, right);
         unsafe {
+            if self.two_way && *self.keys.get_unchecked(index) != (left, right) {
+                // Keep the replaced entry in the second slot of the bucket.
+                *self.keys.get_unchecked_mut(index + 1) = *self.keys.get_unchecked(index);
+                *self.values.get_unchecked_mut(index + 1) = *self.values.get_unchecked(index);
+            }
             let key = self.keys.get_unchecked_mut(index);
             let value = self.values.get_unchecked_mut(index);
             *key = (left, right);
@@ -104,26 +122,29 @@ impl TaskCache {
     /// **(internal)** Prefetch the given entry if possible.
     #[inline]
     pub fn prefetch(&self, left: NodeId, right: NodeId) {
-        if cfg!(target_arch = "x86_64") {
-            let index = self.hashed_index(left, right);
-            unsafe {
-                let key: *const (NodeId, NodeId) = self.keys.get_unchecked(index);
-                let value: *const NodeId = self.values.get_unchecked(index);
-                std::arch::x86_64::_mm_prefetch::<3>(key as *const i8);
-                std::arch::x86_64::_mm_prefetch::<3>(value as *const i8);
-            }
-        }
+        let index = self.hashed_index(left, right);
+        crate::arch::prefetch(self.keys.as_ptr().wrapping_add(index));
+        crate::arch::prefetch(self.values.as_ptr().wrapping_add(index));
     }
 
     /// **(internal)** A hash function partially inspired by Knuth and FxHash.
     ///
-    /// Always returns a valid index into `self.keys` and `self.values`, hence no need to
-    /// check bounds when using it.
+    /// Always returns a valid index into `self.keys` and `self.values` (for two-way caches,
+    /// the index of the first slot of a bucket), hence no need to check bounds when using it.
     #[inline]
     fn hashed_index(&self, left: NodeId, right: NodeId) -> usize {
         let left_hash = u64::from(left).wrapping_mul(Self::SEED);
         let right_hash = u64::from(right).wrapping_mul(Self::SEED);
-        let block_index = left_hash.bitxor(right_hash).rem(Self::HASH_BLOCK);
-        (left.0 + block_index).rem(self.capacity) as usize
+        let index = if self.rolling {
+            let block_index = left_hash.bitxor(right_hash).rem(Self::HASH_BLOCK);
+            (left.0 + block_index).rem(self.capacity)
+        } else {
+            left_hash.bitxor(right_hash).rem(self.capacity)
+        };
+        if self.two_way {
+            (index & !1) as usize
+        } else {
+            index as usize
+        }
     }
 }
@@ -0,0 +1,94 @@
+use crate::v2::{Bdd, BddNode, NodeId};
+use alloc::collections::BTreeMap;
+use alloc::vec;
+
+impl Bdd {
+    /// The canonical (reduced and ordered) form of this `Bdd`.
+    ///
+    /// Isomorphic subgraphs are merged, nodes with identical low/high links are removed,
+    /// and the result is sorted in DFS preorder. Two `Bdds` with the same variable count
+    /// therefore represent the same function if and only if their reduced forms are
+    /// identical node by node (e.g. they have the same string representation).
+    pub fn reduce(&self) -> Bdd {
+        // Maps old node ids to their (already reduced) images in the result.
+        let mut image = vec![NodeId::UNDEFINED; self.nodes.len()];
+        image[0] = NodeId::ZERO;
+        if self.nodes.len() > 1 {
+            image[1] = NodeId::ONE;
+        }
+        let mut unique: BTreeMap<(u64, u64), NodeId> = BTreeMap::new();
+        let mut result = Bdd::true_with_capacity(self.nodes.len());
+
+        // Every node is reduced after both of its successors (DFS postorder).
+        let mut stack = vec![(self.root_node(), false)];
+        while let Some((top, expanded)) = stack.pop() {
+            if !image[top.as_index()].is_undefined() {
+                continue;
+            }
+            let (variable, low, high) = self.nodes[top.as_index()].unpack();
+            if !expanded {
+                stack.push((top, true));
+                stack.push((high, false));
+                stack.push((low, false));
+                continue;
+            }
+            let (low, high) = (image[low.as_index()], image[high.as_index()]);
+            image[top.as_index()] = if low == high {
+                low
+            } else {
+                let node = BddNode::pack(variable, low, high);
+                *unique
+                    .entry((node.0, node.1))
+                    .or_insert_with(|| result.push_node(node))
+            };
+        }
+
+        let mut result = match image[self.root_node().as_index()] {
+            NodeId::ZERO => Bdd::new_false(),
+            NodeId::ONE => Bdd::new_true(),
+            _ => result,
+        };
+        result.update_variable_count(self.variable_count);
+        result.sort_preorder();
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::_impl_::test_util::variables;
+    use crate::v2::{Bdd, BddNode, NodeId, VariableId};
+
+    #[test]
+    pub fn reduce_test() {
+        // (x0 & x1) | x2 with a redundant test of x1 and a duplicate node of x2.
+        let [x0, x1, x2] = [0u16, 1, 2].map(VariableId::from);
+        let mut bdd = Bdd::true_with_capacity(7);
+        let a = bdd.push_node(BddNode::pack(x2, NodeId::ZERO, NodeId::ONE));
+        let b = bdd.push_node(BddNode::pack(x2, NodeId::ZERO, NodeId::ONE));
+        let c = bdd.push_node(BddNode::pack(x1, b, b));
+        let d = bdd.push_node(BddNode::pack(x1, a, NodeId::ONE));
+        bdd.push_node(BddNode::pack(x0, c, d));
+        bdd.update_variable_count(3);
+        assert!(bdd.check_integrity().is_ok());
+        let reduced = bdd.reduce();
+        assert!(reduced.semantic_eq(&bdd));
+        assert_eq!(5, reduced.node_count());
+        assert_eq!(bdd.variable_count(), reduced.variable_count());
+        assert!(reduced.is_sorted());
+
+        // The canonical form does not depend on the way the function was computed.
+        let x = variables(3);
+        let expected = x[0].and(&x[1]).or(&x[2]);
+        let other = x[2].or(&x[1]).and(&x[2].or(&x[0]));
+        assert_eq!(expected.reduce().to_string(), reduced.to_string());
+        assert_eq!(other.reduce().to_string(), reduced.to_string());
+        assert_eq!(reduced.to_string(), reduced.reduce().to_string());
+
+        // Constants keep the variable count.
+        let tautology = x[1].or(&x[1].not());
+        assert_eq!(2, tautology.reduce().node_count());
+        assert_eq!(2, tautology.reduce().variable_count());
+        assert_eq!(1, Bdd::new_false().reduce().node_count());
+    }
+}
@@ -0,0 +1,143 @@
+use crate::v2::{Bdd, BddNode, NodeId, VariableId};
+use crate::v4::core::{Bdd as Bdd4, Node, NodeIndex, Variable};
+use core::cmp::max;
+use core::convert::TryFrom;
+use core::fmt::{Display, Formatter};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/*
+   Until the representations are unified, `v4::core::Bdd` serves as the "hub" for conversions:
+   it has the widest variables (`u32`) and node indices (`u64`), so every other representation
+   can be converted into it, and then from it into the desired target representation.
+*/
+
+/// Convert a `v2::Bdd` into a `v4::core::Bdd`. The variables are widened to 32 bits.
+///
+/// The conversion fails if the `v2::Bdd` does not satisfy the invariants required by
+/// `v4::core::Bdd::check_consistency_errors`.
+impl TryFrom<&Bdd> for Bdd4 {
+    type Error = String;
+
+    fn try_from(bdd: &Bdd) -> Result<Self, Self::Error> {
+        let mut nodes = Vec::with_capacity(bdd.node_count());
+        for (index, node) in bdd.nodes.iter().enumerate() {
+            // Terminal nodes in the text format can carry the variable count instead of
+            // an undefined variable, so we always replace them with the canonical versions.
+            if index == 0 {
+                nodes.push(Node::ZERO);
+            } else if index == 1 {
+                nodes.push(Node::ONE);
+            } else {
+                let (variable, low, high) = node.unpack();
+                nodes.push(Node::pack(
+                    Variable::from(u32::from(variable.0)),
+                    NodeIndex::from(low.0),
+                    NodeIndex::from(high.0),
+                ));
+            }
+        }
+        if let Some(error) = Bdd4::check_consistency_errors(&nodes) {
+            Err(error)
+        } else {
+            Ok(Bdd4::from_nodes(nodes))
+        }
+    }
+}
+
+/// Convert a `v4::core::Bdd` into a `v2::Bdd`.
+///
+/// The conversion fails if the `Bdd` uses variables that do not fit into `v2::VariableId`, or
+/// if it has more than `2^48 - 1` nodes.
+impl TryFrom<&Bdd4> for Bdd {
+    type Error = String;
+
+    fn try_from(bdd: &Bdd4) -> Result<Self, Self::Error> {
+        if bdd.get_size() >= (1 << 48) {
+            return Err(format!("Bdd with {} nodes does not fit into 48 bits.", bdd.get_size()));
+        }
+        let mut variable_count = 0u16;
+        let mut nodes = Vec::with_capacity(bdd.as_node_slice().len());
+        for node in bdd.as_node_slice() {
+            if node.is_terminal() {
+                nodes.push(if node.get_low_link().is_zero() { BddNode::ZERO } else { BddNode::ONE });
+                continue;
+            }
+            let (variable, low, high) = node.unpack();
+            let variable = u32::from(variable);
+            if variable >= u32::from(VariableId::UNDEFINED.0) {
+                return Err(format!("Variable {} does not fit into 16 bits.", variable));
+            }
+            let variable = variable as u16;
+            variable_count = max(variable_count, variable + 1);
+            nodes.push(BddNode::pack(
+                VariableId(variable),
+                NodeId(u64::from(low)),
+                NodeId(u64::from(high)),
+            ));
+        }
+        Ok(Bdd { variable_count, is_sorted: false, nodes, fingerprint: Default::default() })
+    }
+}
+
+/// Serialization into the text format accepted by `Bdd::try_from`. Same as in the `Bdd`
+/// files, the terminal nodes use the variable count as their variable.
+impl Display for Bdd {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        for (index, node) in self.nodes.iter().enumerate() {
+            let (variable, low, high) = node.unpack();
+            let variable = if index < 2 { self.variable_count } else { variable.0 };
+            write!(f, "{},{},{}|", variable, low.0, high.0)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::{Bdd, VariableId};
+    use crate::v4::core::Bdd as Bdd4;
+    use core::convert::TryFrom;
+
+    #[test]
+    pub fn v2_v4_round_trip() {
+        let a = Bdd::new_variable(VariableId::from(1));
+        let b = Bdd::new_variable(VariableId::from(3));
+        let c = Bdd::new_variable(VariableId::from(4));
+        let bdd = a.and(&b).or(&c);
+
+        let converted = Bdd4::try_from(&bdd).unwrap();
+        assert_eq!(bdd.node_count() as u64, converted.get_size());
+        let back = Bdd::try_from(&converted).unwrap();
+        assert!(bdd.nodes[2..] == back.nodes[2..]);
+        assert_eq!(5, back.variable_count());
+
+        assert!(Bdd4::try_from(&Bdd::new_false()).unwrap().is_zero());
+    }
+
+    #[test]
+    pub fn v4_v2_invalid_variable() {
+        use crate::v4::core::{Node, NodeIndex, Variable};
+        let bdd = Bdd4::from_nodes(vec![
+            Node::ZERO,
+            Node::ONE,
+            Node::pack(Variable::from(1 << 20), NodeIndex::ZERO, NodeIndex::ONE),
+        ]);
+        assert!(Bdd::try_from(&bdd).is_err());
+    }
+
+    #[test]
+    pub fn v2_text_round_trip() {
+        use alloc::string::ToString;
+        let a = Bdd::new_variable(VariableId::from(1));
+        let b = Bdd::new_variable(VariableId::from(3));
+        let bdd = a.xor(&b);
+        let text = bdd.to_string();
+        assert!(text.starts_with("4,0,0|4,1,1|"));
+        let back = Bdd::try_from(text.as_str()).unwrap();
+        assert!(bdd.nodes[2..] == back.nodes[2..]);
+        assert_eq!(4, back.variable_count());
+        assert_eq!("0,0,0|", Bdd::new_false().to_string());
+    }
+}
@@ -0,0 +1,246 @@
+use crate::v2::{Bdd, NodeId};
+use alloc::collections::BTreeSet;
+use core::cmp::{max, min};
+use alloc::vec::Vec;
+use alloc::vec;
+
+/// **(internal)** Two densities which differ by less than this (relative) amount are
+/// considered equal, because they may only differ due to rounding errors.
+const DENSITY_TOLERANCE: f64 = 1e-9;
+
+impl Bdd {
+    /// True if the two `Bdds` represent the same function.
+    ///
+    /// Structurally identical `Bdds` are recognized immediately, and `Bdds` with different
+    /// fingerprints are rejected without running any operation. Only when the fingerprints
+    /// match, the result is confirmed by an exact check (see `Bdd::coupled_eq`), which
+    /// does not build any result nodes.
+    pub fn semantic_eq(&self, other: &Bdd) -> bool {
+        if self.is_identical_to(other) {
+            true
+        } else if self.fingerprint() != other.fingerprint() {
+            false
+        } else {
+            self.coupled_eq(other)
+        }
+    }
+
+    /// True if every valuation which satisfies `self` also satisfies `other`, i.e. `self`
+    /// is a subset of `other`.
+    ///
+    /// This is equivalent to testing that `self.and_not(other)` is `false`, but runs as
+    /// a coupled DFS which stops at the first valuation that satisfies `self` and not `other`,
+    /// without building any result nodes.
+    pub fn implies_semantically(&self, other: &Bdd) -> bool {
+        self.is_identical_to(other)
+            || !self.coupled_witness(other, |left, right| left.is_one() && right.is_zero())
+    }
+
+    /// **(internal)** Exact equivalence check, equivalent to testing that `self.xor(other)`
+    /// is `false` (see `Bdd::coupled_witness`).
+    fn coupled_eq(&self, other: &Bdd) -> bool {
+        !self.coupled_witness(other, |left, right| left != right)
+    }
+
+    /// **(internal)** A coupled DFS over pairs of nodes which returns true if it reaches
+    /// a pair of terminals accepted by the `witness` predicate.
+    ///
+    /// Unlike a binary operation, the search does not need a node cache or a result `Bdd`,
+    /// and it stops at the first witness. Every pair is expanded at most once, so the search
+    /// is at most quadratic.
+    fn coupled_witness<F>(&self, other: &Bdd, witness: F) -> bool
+    where
+        F: Fn(NodeId, NodeId) -> bool,
+    {
+        let mut expanded: BTreeSet<(u64, u64)> = BTreeSet::new();
+        let mut stack = vec![(self.root_node(), other.root_node())];
+        while let Some((left, right)) = stack.pop() {
+            if left.as_index() < 2 && right.as_index() < 2 {
+                if witness(left, right) {
+                    return true;
+                }
+                continue;
+            }
+            if !expanded.insert((left.0, right.0)) {
+                continue;
+            }
+            // Terminals have an undefined variable, which is greater than any other variable.
+            let left_node = self.get_node(left);
+            let right_node = other.get_node(right);
+            let variable = min(left_node.variable(), right_node.variable());
+            let (left_low, left_high) = if left_node.variable() == variable {
+                (left_node.low_link(), left_node.high_link())
+            } else {
+                (left, left)
+            };
+            let (right_low, right_high) = if right_node.variable() == variable {
+                (right_node.low_link(), right_node.high_link())
+            } else {
+                (right, right)
+            };
+            stack.push((left_high, right_high));
+            stack.push((left_low, right_low));
+        }
+        false
+    }
+
+    /// A fast, probabilistic screening of the semantic equivalence of two `Bdds`.
+    ///
+    /// If the result is `false`, the `Bdds` are guaranteed to represent different functions.
+    /// If the result is `true`, they are likely equivalent, but you need an exact check
+    /// (e.g. `Bdd::semantic_eq`) to be sure.
+    ///
+    /// The check first compares the (cached) semantic fingerprints of both `Bdds`
+    /// (see `Bdd::fingerprint`) and the fraction of satisfying assignments. Neither depends
+    /// on the structure of the graph, so they are also valid for non-canonical `Bdds`.
+    /// Then, both `Bdds` are evaluated on `samples` random assignments, using `rng` as
+    /// the source of random bits. All steps are linear in the size of the `Bdds` (the sampling
+    /// is even proportional only to their height), hence much cheaper than the exact check.
+    pub fn probably_equal<R>(&self, other: &Bdd, samples: usize, mut rng: R) -> bool
+    where
+        R: FnMut() -> u64,
+    {
+        if self.is_identical_to(other) {
+            return true;
+        }
+        if self.fingerprint() != other.fingerprint() {
+            return false;
+        }
+        let (a, b) = (self.density(), other.density());
+        if (a - b).abs() > DENSITY_TOLERANCE * f64::max(a, b) {
+            return false;
+        }
+
+        let variables = usize::from(max(self.variable_count(), other.variable_count()));
+        let mut assignment = vec![0u64; variables.div_ceil(64)];
+        for _ in 0..samples {
+            for word in assignment.iter_mut() {
+                *word = rng();
+            }
+            if self.eval_bits(&assignment) != other.eval_bits(&assignment) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// **(internal)** Evaluate this `Bdd` in the given assignment, where the value of
+    /// variable `v` is the `v % 64`-th bit of `assignment[v / 64]`.
+    fn eval_bits(&self, assignment: &[u64]) -> bool {
+        let mut node = self.root_node();
+        while node.as_index() > 1 {
+            let (var, low, high) = self.get_node(node).unpack();
+            let var = usize::from(var.0);
+            let value = (assignment[var / 64] >> (var % 64)) & 1 == 1;
+            node = if value { high } else { low };
+        }
+        node.is_one()
+    }
+
+    /// **(internal)** The fraction of all assignments which satisfy this `Bdd`.
+    fn density(&self) -> f64 {
+        // `NaN` marks nodes with unknown density.
+        let mut density = vec![f64::NAN; self.node_count()];
+        density[0] = 0.0;
+        if self.node_count() > 1 {
+            density[1] = 1.0;
+        }
+        let mut stack: Vec<NodeId> = vec![self.root_node()];
+        while let Some(top) = stack.last().cloned() {
+            if !density[top.as_index()].is_nan() {
+                stack.pop();
+                continue;
+            }
+            let node = self.get_node(top);
+            let low = density[node.low_link().as_index()];
+            let high = density[node.high_link().as_index()];
+            if low.is_nan() || high.is_nan() {
+                if high.is_nan() {
+                    stack.push(node.high_link());
+                }
+                if low.is_nan() {
+                    stack.push(node.low_link());
+                }
+            } else {
+                density[top.as_index()] = (low + high) / 2.0;
+                stack.pop();
+            }
+        }
+        density[self.root_node().as_index()]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::{Bdd, BddNode, NodeId, VariableId};
+
+    #[test]
+    pub fn probably_equal_test() {
+        // A simple xorshift generator, so that the test is deterministic.
+        let mut state = 0x51_7c_c1_b7_27_22_0a_95u64;
+        let mut rng = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        let x0 = Bdd::new_variable(VariableId::from(0));
+        let x1 = Bdd::new_variable(VariableId::from(1));
+        let x2 = Bdd::new_variable(VariableId::from(2));
+
+        let a = x0.and(&x1).or(&x2);
+        let b = x2.or(&x1.and(&x0));
+        assert!(a.probably_equal(&b, 16, &mut rng));
+        assert!(a.probably_equal(&a, 16, &mut rng));
+
+        // Different density.
+        assert!(!a.probably_equal(&x0.or(&x1), 16, &mut rng));
+        // Same density, but different functions.
+        assert!(!x0.probably_equal(&x1, 16, &mut rng));
+        assert!(!x0.probably_equal(&Bdd::new_false(), 16, &mut rng));
+    }
+
+    #[test]
+    pub fn semantic_eq_test() {
+        let [x0, x1, x2] = [0u16, 1, 2].map(|v| Bdd::new_variable(VariableId::from(v)));
+        let a = x0.and(&x1).or(&x2);
+        let b = x2.or(&x1).and(&x2.or(&x0));
+        assert!(a.semantic_eq(&b));
+        assert!(a.coupled_eq(&b) && b.coupled_eq(&a));
+
+        // The exact check does not rely on fingerprints or on reduced inputs.
+        let mut redundant = Bdd::true_with_capacity(4);
+        let x = redundant.push_node(BddNode::pack(VariableId::from(1), NodeId::ZERO, NodeId::ONE));
+        redundant.push_node(BddNode::pack(VariableId::from(0), x, x));
+        assert!(redundant.coupled_eq(&x1) && x1.coupled_eq(&redundant));
+        assert!(!redundant.coupled_eq(&x0));
+        assert!(!a.coupled_eq(&x0.or(&x2)));
+        assert!(!x0.coupled_eq(&Bdd::new_false()));
+        assert!(x0.or(&x0.not()).coupled_eq(&Bdd::new_true()));
+        assert!(!Bdd::new_true().coupled_eq(&Bdd::new_false()));
+    }
+
+    #[test]
+    pub fn implies_semantically_test() {
+        let [x0, x1, x2] = [0u16, 1, 2].map(|v| Bdd::new_variable(VariableId::from(v)));
+        let small = x0.and(&x1);
+        let large = x2.or(&x1.and(&x0));
+        assert!(small.implies_semantically(&large));
+        assert!(!large.implies_semantically(&small));
+        assert!(large.implies_semantically(&large.clone()));
+        assert!(!x0.implies_semantically(&x1));
+
+        // The constants are the bottom and the top of the subset lattice.
+        let (ff, tt) = (Bdd::new_false(), Bdd::new_true());
+        assert!(ff.implies_semantically(&x0) && x0.implies_semantically(&tt));
+        assert!(!tt.implies_semantically(&x0) && !x0.implies_semantically(&ff));
+        assert!(ff.implies_semantically(&tt) && !tt.implies_semantically(&ff));
+
+        // Same as checking that `and_not` is empty.
+        for (a, b) in [(&small, &x2), (&x1, &small), (&x2, &large)] {
+            let expected = a.and_not(b).semantic_eq(&ff);
+            assert_eq!(expected, a.implies_semantically(b));
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use crate::v2::{Bdd, BitMatrix, BitVec};
+use alloc::vec;
+use alloc::vec::Vec;
+
+impl Bdd {
+    /// Evaluate this `Bdd` in every valuation of the given matrix (one valuation per column,
+    /// see `BitMatrix`). The `i`-th bit of the result is the value in the `i`-th valuation.
+    ///
+    /// The evaluation is bitsliced: the nodes are processed once for every 64 valuations, and
+    /// one word operation computes the value of a node in all 64 of them. This is much faster
+    /// than evaluating the valuations one by one, as long as there are many more valuations
+    /// than the height of the `Bdd`. Variables without a row in the matrix are `false`.
+    pub fn eval_batch(&self, valuations: &BitMatrix) -> BitVec {
+        let width = valuations.columns().div_ceil(64);
+        let root = self.root_node().as_index();
+        let mut result = vec![0u64; width];
+        if root < 2 {
+            let value = if root == 1 { u64::MAX } else { 0 };
+            return BitVec::from_words(valuations.columns(), vec![value; width]);
+        }
+        // The value of every node in the current block of 64 valuations. Successors always
+        // precede their parents, so the nodes can be evaluated in order.
+        let mut values: Vec<u64> = vec![0; self.node_count()];
+        values[1] = u64::MAX;
+        for (block, output) in result.iter_mut().enumerate() {
+            for index in 2..self.node_count() {
+                let (variable, low, high) = self.nodes[index].unpack();
+                let row = usize::from(variable.0);
+                let x = if row < valuations.rows() {
+                    valuations.row_words(row)[block]
+                } else {
+                    0
+                };
+                values[index] = (x & values[high.as_index()]) | (!x & values[low.as_index()]);
+            }
+            *output = values[root];
+        }
+        BitVec::from_words(valuations.columns(), result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::_impl_::test_util::variables;
+    use crate::v2::{Bdd, BitMatrix};
+
+    #[test]
+    pub fn eval_batch_test() {
+        let x = variables(4);
+        let f = x[0].and(&x[1]).or(&x[2].xor(&x[3]));
+        let expected = |v: usize| ((v & 1 != 0) && (v & 2 != 0)) || ((v & 4 != 0) != (v & 8 != 0));
+
+        // All 16 valuations, repeated such that the last block is incomplete.
+        let columns = 16 * 9;
+        let mut valuations = BitMatrix::new(4, columns);
+        for column in 0..columns {
+            for row in 0..4 {
+                valuations.set(row, column, (column % 16) >> row & 1 == 1);
+            }
+        }
+        let result = f.eval_batch(&valuations);
+        assert_eq!(columns, result.len());
+        for column in 0..columns {
+            assert_eq!(expected(column % 16), result.get(column));
+        }
+        assert_eq!(9 * 10, result.count_ones());
+
+        // Constants, missing rows, and unused bits of the last word.
+        let tt = x[0].imp(&x[0]);
+        assert_eq!(columns, tt.eval_batch(&valuations).count_ones());
+        assert_eq!(0, Bdd::new_false().eval_batch(&valuations).count_ones());
+        let small = BitMatrix::new(2, 70);
+        assert_eq!(70, x[3].imp(&x[1]).eval_batch(&small).count_ones());
+        assert_eq!(0, x[0].eval_batch(&BitMatrix::new(4, 0)).len());
+    }
+}
@@ -0,0 +1,157 @@
+use crate::v2::{Bdd, FingerprintCache, NodeId};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// **(internal)** Seeds of the terminal nodes.
+const ZERO_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+const ONE_SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+
+impl Bdd {
+    /// A semantic fingerprint of this `Bdd`: a 64-bit hash which only depends on the function
+    /// represented by the `Bdd`, not on the order or redundancy of its nodes.
+    ///
+    /// The hash is computed bottom-up: terminal nodes have fixed seeds and a decision node
+    /// combines its variable with the hashes of its successors. Nodes with equal successors
+    /// are skipped, so the fingerprint is also valid for `Bdds` that are not fully reduced.
+    /// Consequently, `Bdds` with different fingerprints always represent different functions
+    /// (the opposite holds only up to hash collisions, see `Bdd::semantic_eq`).
+    ///
+    /// The fingerprint is computed in linear time on first use and then cached in the `Bdd`.
+    pub fn fingerprint(&self) -> u64 {
+        if let Some(fingerprint) = self.fingerprint.get() {
+            return fingerprint;
+        }
+        let fingerprint = self.compute_fingerprint();
+        self.fingerprint.set(fingerprint);
+        fingerprint
+    }
+
+    /// **(internal)** True if `self` and `other` are the same object or have the same nodes.
+    ///
+    /// This is used to skip operations where both operands are the same `Bdd`. Node lists
+    /// are only compared when the (already cached) fingerprints do not rule out a match.
+    pub(crate) fn is_identical_to(&self, other: &Bdd) -> bool {
+        if core::ptr::eq(self, other) {
+            return true;
+        }
+        if let (Some(a), Some(b)) = (self.fingerprint.get(), other.fingerprint.get()) {
+            if a != b {
+                return false;
+            }
+        }
+        self.nodes == other.nodes
+    }
+
+    /// **(internal)** Compute the fingerprint of this `Bdd` (see `Bdd::fingerprint`).
+    fn compute_fingerprint(&self) -> u64 {
+        // Zero marks nodes with unknown hash (node hashes are never zero).
+        let mut hashes = vec![0u64; self.node_count()];
+        hashes[0] = ZERO_SEED;
+        if self.node_count() > 1 {
+            hashes[1] = ONE_SEED;
+        }
+        let mut stack: Vec<NodeId> = vec![self.root_node()];
+        while let Some(top) = stack.last().cloned() {
+            if hashes[top.as_index()] != 0 {
+                stack.pop();
+                continue;
+            }
+            let (variable, low, high) = self.get_node(top).unpack();
+            let low_hash = hashes[low.as_index()];
+            let high_hash = hashes[high.as_index()];
+            if low_hash == 0 || high_hash == 0 {
+                if high_hash == 0 {
+                    stack.push(high);
+                }
+                if low_hash == 0 {
+                    stack.push(low);
+                }
+            } else {
+                hashes[top.as_index()] = if low_hash == high_hash {
+                    low_hash
+                } else {
+                    node_hash(u16::from(variable), low_hash, high_hash)
+                };
+                stack.pop();
+            }
+        }
+        hashes[self.root_node().as_index()]
+    }
+}
+
+/// **(internal)** Combine the variable and successor hashes of a decision node. The result
+/// is never zero.
+fn node_hash(variable: u16, low: u64, high: u64) -> u64 {
+    // The successors are mixed asymmetrically, then the result is finalized like SplitMix64.
+    let mut hash = u64::from(variable).wrapping_add(1).wrapping_mul(ONE_SEED);
+    hash ^= low.rotate_left(23).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash ^= high.wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^= hash >> 31;
+    if hash == 0 {
+        1
+    } else {
+        hash
+    }
+}
+
+impl FingerprintCache {
+    /// The cached fingerprint, if it was computed already.
+    fn get(&self) -> Option<u64> {
+        match self.0.load(Ordering::Relaxed) {
+            0 => None,
+            fingerprint => Some(fingerprint),
+        }
+    }
+
+    fn set(&self, fingerprint: u64) {
+        self.0.store(fingerprint, Ordering::Relaxed);
+    }
+
+    /// Forget the cached value (the `Bdd` is being modified).
+    pub(crate) fn clear(&mut self) {
+        *self.0.get_mut() = 0;
+    }
+}
+
+impl Clone for FingerprintCache {
+    fn clone(&self) -> Self {
+        FingerprintCache(AtomicU64::new(self.0.load(Ordering::Relaxed)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::_impl_::test_util::variables;
+    use crate::v2::Bdd;
+
+    #[test]
+    pub fn fingerprint_test() {
+        let x = variables(4);
+        let a = x[0].and(&x[1]).or(&x[2].xor(&x[3]));
+        // The same function computed differently (and with sorted nodes).
+        let mut b = x[2].xor(&x[3]).or(&x[1].and(&x[0]));
+        b.sort_preorder();
+        assert_eq!(a.fingerprint(), b.fingerprint());
+        assert!(a.semantic_eq(&b));
+
+        let c = a.and_not(&x[3]);
+        assert_ne!(a.fingerprint(), c.fingerprint());
+        assert!(!a.semantic_eq(&c));
+
+        // Constants, complements and clones.
+        let ff = Bdd::new_false();
+        let tt = ff.imp(&ff);
+        assert_ne!(ff.fingerprint(), tt.fingerprint());
+        assert_ne!(x[0].fingerprint(), x[0].imp(&ff).fingerprint());
+        assert_eq!(a.fingerprint(), a.clone().fingerprint());
+        assert!(x[0].semantic_eq(&x[0].and(&x[0])));
+
+        // Operations on identical operands are resolved without the apply algorithm.
+        assert!(a.nodes == a.and(&a).nodes);
+        assert_eq!(1, a.xor(&a.clone()).node_count());
+        assert_eq!(2, a.iff(&a).node_count());
+    }
+}
@@ -0,0 +1,145 @@
+use crate::v2::{Bdd, BddNode, IntegrityError, NodeId, VariableId};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use core::fmt::{Display, Formatter};
+
+impl Bdd {
+    /// Verify the structure of this `Bdd`: The terminal slots contain the terminal nodes
+    /// (and no other slot does), every link points into the node vector, the variables
+    /// grow along every edge, and every decision node is reachable from the root.
+    ///
+    /// The check is linear in the size of the `Bdd`. In debug builds, it runs automatically
+    /// after every binary operation and every sort, so that a mistake in the renumbering
+    /// of the result fails loudly instead of producing a corrupted `Bdd`.
+    pub fn check_integrity(&self) -> Result<(), IntegrityError> {
+        if self.nodes.is_empty() {
+            return Err(IntegrityError::Empty);
+        }
+        let terminals = [BddNode::ZERO, BddNode::ONE];
+        for (index, node) in self.nodes.iter().enumerate() {
+            let id = NodeId(index as u64);
+            if index < 2 {
+                if *node != terminals[index] {
+                    return Err(IntegrityError::Terminal(id));
+                }
+                continue;
+            }
+            if node.variable() == VariableId::UNDEFINED {
+                return Err(IntegrityError::Terminal(id));
+            }
+            for link in [node.low_link(), node.high_link()] {
+                if link.as_index() >= self.nodes.len() {
+                    return Err(IntegrityError::InvalidLink(id));
+                }
+                if self.nodes[link.as_index()].variable() <= node.variable() {
+                    return Err(IntegrityError::VariableOrder(id));
+                }
+            }
+        }
+
+        // The variables grow along every edge, so the graph has no cycles.
+        let mut reachable = vec![false; self.nodes.len()];
+        let mut stack = vec![self.root_node()];
+        while let Some(top) = stack.pop() {
+            if top.as_index() < 2 || reachable[top.as_index()] {
+                continue;
+            }
+            reachable[top.as_index()] = true;
+            let node = self.nodes[top.as_index()];
+            stack.push(node.high_link());
+            stack.push(node.low_link());
+        }
+        match reachable.iter().skip(2).position(|it| !*it) {
+            Some(index) => Err(IntegrityError::Unreachable(NodeId((index + 2) as u64))),
+            None => Ok(()),
+        }
+    }
+
+    /// **(internal)** In debug builds, panic if the `result` of the operation `name`
+    /// is not a structurally valid `Bdd` (see `Bdd::check_integrity`).
+    #[inline]
+    pub(crate) fn debug_check_integrity(&self, name: &str) {
+        if cfg!(debug_assertions) {
+            if let Err(error) = self.check_integrity() {
+                panic!("Operation `{}` produced an invalid `Bdd`: {}", name, error);
+            }
+        }
+    }
+}
+
+impl Display for IntegrityError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            IntegrityError::Empty => write!(f, "The node vector is empty."),
+            IntegrityError::Terminal(id) => write!(f, "Unexpected terminal in slot {}.", id.0),
+            IntegrityError::InvalidLink(id) => write!(f, "Node {} has an invalid link.", id.0),
+            IntegrityError::VariableOrder(id) => {
+                write!(f, "Node {} violates the variable ordering.", id.0)
+            }
+            IntegrityError::Unreachable(id) => {
+                write!(f, "Node {} is not reachable from the root.", id.0)
+            }
+        }
+    }
+}
+
+impl From<IntegrityError> for String {
+    fn from(value: IntegrityError) -> Self {
+        format!("{}", value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::_impl_::test_util::variables;
+    use crate::v2::{Bdd, BddNode, FingerprintCache, IntegrityError, NodeId, VariableId};
+
+    fn corrupted(nodes: &[BddNode]) -> Bdd {
+        Bdd {
+            variable_count: 3,
+            is_sorted: false,
+            nodes: nodes.to_vec(),
+            fingerprint: FingerprintCache::default(),
+        }
+    }
+
+    #[test]
+    pub fn integrity_test() {
+        let x = variables(3);
+        let f = x[0].and(&x[1]).or(&x[2]);
+        assert!(f.check_integrity().is_ok());
+        assert!(Bdd::new_false().check_integrity().is_ok());
+
+        let v = |v: u16| VariableId::from(v);
+        let (zero, one) = (NodeId::ZERO, NodeId::ONE);
+        let node = |var: u16, low: u64, high: u64| BddNode::pack(v(var), NodeId(low), NodeId(high));
+        let cases = [
+            (vec![], IntegrityError::Empty),
+            (vec![BddNode::ONE], IntegrityError::Terminal(zero)),
+            (
+                vec![BddNode::ZERO, BddNode::ZERO],
+                IntegrityError::Terminal(one),
+            ),
+            (
+                vec![BddNode::ZERO, BddNode::ONE, BddNode::ONE],
+                IntegrityError::Terminal(NodeId(2)),
+            ),
+            (
+                vec![BddNode::ZERO, BddNode::ONE, node(1, 0, 3)],
+                IntegrityError::InvalidLink(NodeId(2)),
+            ),
+            (
+                vec![BddNode::ZERO, BddNode::ONE, node(1, 0, 1), node(1, 2, 1)],
+                IntegrityError::VariableOrder(NodeId(3)),
+            ),
+            (
+                vec![BddNode::ZERO, BddNode::ONE, node(1, 0, 1), node(0, 0, 1)],
+                IntegrityError::Unreachable(NodeId(2)),
+            ),
+        ];
+        for (nodes, error) in cases {
+            assert_eq!(Err(error), corrupted(&nodes).check_integrity());
+        }
+    }
+}
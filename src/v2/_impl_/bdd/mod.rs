@@ -1,2 +1,18 @@
 /// Implementation of binary operations on `Bdds`.
 pub mod binary_operations;
+/// Conversions between `v2::Bdd` and the other `Bdd` representations.
+mod conversions;
+/// Exact and probabilistic checks of semantic equivalence and implication.
+mod equivalence;
+/// Semantic fingerprints of `Bdds`.
+mod fingerprint;
+/// Multi-threaded preorder renumbering (see `Bdd::sort_preorder_parallel`).
+mod parallel_sort;
+/// Batched (bitsliced) evaluation of `Bdds` (see `Bdd::eval_batch`).
+mod eval;
+/// Negation of `Bdds` (see `Bdd::not`).
+mod negation;
+/// Structural validation of `Bdds` (see `Bdd::check_integrity`).
+mod integrity;
+/// Canonical reduction of `Bdds` (see `Bdd::reduce`).
+mod canonical;
@@ -0,0 +1,69 @@
+use crate::v2::{Bdd, BddNode, FingerprintCache, NodeId};
+use alloc::vec::Vec;
+
+impl Bdd {
+    /// A logical negation of this `Bdd`.
+    ///
+    /// The negation only swaps the terminal links of every node, so unlike `xor` with `true`,
+    /// it is a single linear pass over the node vector. The nodes keep their positions
+    /// (hence a sorted `Bdd` stays sorted), except that the constants `true` and `false`
+    /// differ in their node count.
+    pub fn not(&self) -> Bdd {
+        match self.node_count() {
+            1 => Bdd::_new_constant(NodeId::ONE, self.variable_count()),
+            2 => Bdd::_new_constant(NodeId::ZERO, self.variable_count()),
+            _ => {
+                let mut nodes = Vec::with_capacity(self.nodes.len());
+                nodes.push(BddNode::ZERO);
+                nodes.push(BddNode::ONE);
+                for node in &self.nodes[2..] {
+                    let (variable, low, high) = node.unpack();
+                    nodes.push(BddNode::pack(variable, negate(low), negate(high)));
+                }
+                Bdd {
+                    variable_count: self.variable_count,
+                    is_sorted: self.is_sorted,
+                    nodes,
+                    fingerprint: FingerprintCache::default(),
+                }
+            }
+        }
+    }
+}
+
+/// **(internal)** Swap the links to the two terminal nodes.
+#[inline]
+fn negate(link: NodeId) -> NodeId {
+    if link.is_zero() {
+        NodeId::ONE
+    } else if link.is_one() {
+        NodeId::ZERO
+    } else {
+        link
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::_impl_::test_util::variables;
+    use crate::v2::Bdd;
+
+    #[test]
+    pub fn negation_test() {
+        let x = variables(4);
+        let mut f = x[0].and(&x[1]).or(&x[2].xor(&x[3]));
+        let tt = Bdd::new_false().not();
+        assert_eq!(2, tt.node_count());
+        assert_eq!(1, tt.not().node_count());
+
+        let not_f = f.not();
+        assert_eq!(f.node_count(), not_f.node_count());
+        assert!(not_f.semantic_eq(&f.xor(&tt)));
+        assert!(f.and(&not_f).node_count() == 1);
+        assert_eq!(f.to_string(), not_f.not().to_string());
+
+        f.sort_preorder();
+        assert!(f.not().is_sorted());
+        assert_eq!(4, x[3].not().not().variable_count());
+    }
+}
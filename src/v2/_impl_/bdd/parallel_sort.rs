@@ -0,0 +1,127 @@
+use crate::v2::{Bdd, BddNode, NodeId};
+use alloc::vec::Vec;
+use core::cmp::{max, min};
+
+impl Bdd {
+    /// Same as `Bdd::sort_preorder`, but the nodes are relinked and copied to their new
+    /// positions by `threads` threads (with the `std` feature, except in the `wasm` build;
+    /// otherwise everything runs in the current thread).
+    ///
+    /// The result is the same (node by node) as with `Bdd::sort_preorder`, regardless
+    /// of the number of threads: the preorder numbering itself is a sequential DFS, only
+    /// the copying of the nodes, which is independent for every node, is split into ranges.
+    ///
+    /// *Panics:* the `Bdd` must not contain unreachable nodes.
+    pub fn sort_preorder_parallel(&mut self, threads: usize) {
+        let node_count = self.node_count();
+        if node_count < 2 {
+            self.is_sorted = true;
+            return;
+        }
+        let (new_id, unassigned) = self.preorder_ids();
+        assert_eq!(1, unassigned, "The Bdd contains unreachable nodes.");
+
+        let chunk_size = max(1, (node_count - 2).div_ceil(max(1, threads)));
+        let chunks: Vec<(usize, usize)> = (2..node_count)
+            .step_by(chunk_size)
+            .map(|start| (start, min(start + chunk_size, node_count)))
+            .collect();
+        let mut new_nodes: Vec<BddNode> = Vec::with_capacity(node_count);
+        let output = SharedNodes(new_nodes.as_mut_ptr());
+        let (nodes, new_id) = (&self.nodes, &new_id);
+        let mut unit = alloc::vec![(); chunks.len()];
+        run_chunks(&chunks, &mut unit, |(start, end), _| {
+            let output = &output;
+            for index in start..end {
+                let (variable, low, high) = nodes[index].unpack();
+                let low = NodeId(new_id[low.as_index()] as u64);
+                let high = NodeId(new_id[high.as_index()] as u64);
+                let node = BddNode::pack(variable, low, high);
+                // Every node is reachable, so the new ids are a permutation of
+                // `2..node_count` and every position is written exactly once.
+                unsafe {
+                    output.0.add(new_id[index]).write(node);
+                }
+            }
+        });
+        unsafe {
+            output.0.write(BddNode::ZERO);
+            output.0.add(1).write(BddNode::ONE);
+            // All `node_count` positions are initialized.
+            new_nodes.set_len(node_count);
+        }
+
+        self.nodes = new_nodes;
+        self.is_sorted = true;
+        self.debug_check_integrity("sort_preorder_parallel");
+    }
+}
+
+/// **(internal)** A pointer to the output nodes of `Bdd::sort_preorder_parallel` which can be
+/// shared by the copying threads (each thread writes to different positions).
+struct SharedNodes(*mut BddNode);
+
+unsafe impl Sync for SharedNodes {}
+
+/// **(internal)** Run `action` for every chunk with its associated `state`, in parallel
+/// if possible.
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+fn run_chunks<S, F>(chunks: &[(usize, usize)], states: &mut [S], action: F)
+where
+    S: Send,
+    F: Fn((usize, usize), &mut S) + Sync,
+{
+    if chunks.len() == 1 {
+        return action(chunks[0], &mut states[0]);
+    }
+    std::thread::scope(|scope| {
+        let action = &action;
+        for (chunk, state) in chunks.iter().zip(states.iter_mut()) {
+            scope.spawn(move || action(*chunk, state));
+        }
+    });
+}
+
+/// **(internal)** Run `action` for every chunk with its associated `state`, in parallel
+/// if possible.
+#[cfg(not(all(feature = "std", not(feature = "wasm"))))]
+fn run_chunks<S, F>(chunks: &[(usize, usize)], states: &mut [S], action: F)
+where
+    S: Send,
+    F: Fn((usize, usize), &mut S) + Sync,
+{
+    for (chunk, state) in chunks.iter().zip(states.iter_mut()) {
+        action(*chunk, state);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::_impl_::test_util::variables;
+    use crate::v2::Bdd;
+
+    #[test]
+    pub fn sort_preorder_parallel_test() {
+        let x = variables(8);
+        let mut unsorted = Bdd::new_false();
+        for i in 0..4 {
+            unsorted = unsorted.or(&x[i].and(&x[i + 4]));
+        }
+        let mut expected = unsorted.clone();
+        expected.sort_preorder();
+
+        // The layout is the same as the sequential one for any number of threads.
+        for threads in [0, 1, 2, 3, 7, 1000] {
+            let mut parallel = unsorted.clone();
+            parallel.sort_preorder_parallel(threads);
+            assert!(parallel.nodes == expected.nodes);
+            assert!(parallel.is_sorted());
+        }
+
+        for mut small in [Bdd::new_false(), Bdd::new_true(), x[3].clone()] {
+            let copy = small.clone();
+            small.sort_preorder_parallel(4);
+            assert!(small.nodes == copy.nodes);
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use crate::v2::{ApplyContext, Bdd, BddVec, NodeId, PooledHandle, VariableId};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// **(internal)** The `false` and `true` handles are the same in every context.
+const FALSE: PooledHandle = PooledHandle(NodeId::ZERO);
+const TRUE: PooledHandle = PooledHandle(NodeId::ONE);
+
+impl BddVec {
+    /// Create a vector from the given bits (least significant bit first).
+    pub fn new(bits: Vec<PooledHandle>) -> BddVec {
+        BddVec { bits }
+    }
+
+    /// A vector of the given `width` which represents the constant `value`
+    /// (higher bits of `value` are ignored).
+    ///
+    /// Constant vectors can be used with any `ApplyContext`.
+    pub fn constant(value: u64, width: usize) -> BddVec {
+        let bits = (0..width)
+            .map(|i| {
+                if i < 64 && (value >> i) & 1 == 1 {
+                    TRUE
+                } else {
+                    FALSE
+                }
+            })
+            .collect();
+        BddVec { bits }
+    }
+
+    /// The number of bits of this vector.
+    pub fn width(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// The bits of this vector (least significant bit first).
+    pub fn bits(&self) -> &[PooledHandle] {
+        &self.bits
+    }
+
+    /// Shift the vector by `amount` bits towards the most significant bit (i.e. multiply
+    /// it by `2^amount`). The width of the vector does not change.
+    pub fn shift_left(&self, amount: usize) -> BddVec {
+        let amount = amount.min(self.width());
+        let mut bits = Vec::with_capacity(self.width());
+        bits.resize(amount, FALSE);
+        bits.extend_from_slice(&self.bits[..(self.width() - amount)]);
+        BddVec { bits }
+    }
+
+    /// Shift the vector by `amount` bits towards the least significant bit (i.e. divide
+    /// it by `2^amount`). The width of the vector does not change.
+    pub fn shift_right(&self, amount: usize) -> BddVec {
+        let amount = amount.min(self.width());
+        let mut bits = self.bits[amount..].to_vec();
+        bits.resize(self.width(), FALSE);
+        BddVec { bits }
+    }
+}
+
+impl ApplyContext {
+    /// A vector whose bits are the given variables (least significant bit first).
+    pub fn vec_variables(&mut self, variables: &[VariableId]) -> BddVec {
+        let bits = variables
+            .iter()
+            .map(|v| self.import(&Bdd::new_variable(*v)))
+            .collect();
+        BddVec { bits }
+    }
+
+    /// The (wrapping) sum of two vectors of the same width.
+    pub fn vec_add(&mut self, left: &BddVec, right: &BddVec) -> Result<BddVec, String> {
+        check_width(left, right)?;
+        Ok(self.add_with_carry(left, right, FALSE, false))
+    }
+
+    /// The (wrapping) difference of two vectors of the same width.
+    pub fn vec_sub(&mut self, left: &BddVec, right: &BddVec) -> Result<BddVec, String> {
+        check_width(left, right)?;
+        // a - b = a + !b + 1
+        Ok(self.add_with_carry(left, right, TRUE, true))
+    }
+
+    /// A `Bdd` which is true exactly when the two vectors are equal.
+    pub fn vec_equal(&mut self, left: &BddVec, right: &BddVec) -> Result<PooledHandle, String> {
+        check_width(left, right)?;
+        let mut result = TRUE;
+        for (a, b) in left.bits.iter().zip(right.bits.iter()) {
+            let equal = self.iff(*a, *b);
+            result = self.and(result, equal);
+        }
+        Ok(result)
+    }
+
+    /// A `Bdd` which is true exactly when `left < right` (as unsigned numbers).
+    pub fn vec_less_than(&mut self, left: &BddVec, right: &BddVec) -> Result<PooledHandle, String> {
+        check_width(left, right)?;
+        // Starting from the least significant bit, `left < right` holds either because
+        // the current bit is smaller, or because it is equal and the lower bits are smaller.
+        let mut result = FALSE;
+        for (a, b) in left.bits.iter().zip(right.bits.iter()) {
+            let smaller = self.and_not(*b, *a);
+            let equal = self.iff(*a, *b);
+            let lower = self.and(equal, result);
+            result = self.or(smaller, lower);
+        }
+        Ok(result)
+    }
+
+    /// **(internal)** A ripple-carry adder. If `negate_right` is set, the bits of `right`
+    /// are negated.
+    fn add_with_carry(
+        &mut self,
+        left: &BddVec,
+        right: &BddVec,
+        mut carry: PooledHandle,
+        negate_right: bool,
+    ) -> BddVec {
+        let mut bits = Vec::with_capacity(left.width());
+        for (a, b) in left.bits.iter().zip(right.bits.iter()) {
+            let b = if negate_right { self.xor(*b, TRUE) } else { *b };
+            let half = self.xor(*a, b);
+            bits.push(self.xor(half, carry));
+            let generate = self.and(*a, b);
+            let propagate = self.and(half, carry);
+            carry = self.or(generate, propagate);
+        }
+        BddVec { bits }
+    }
+}
+
+/// **(internal)** Check that the two vectors have the same width.
+fn check_width(left: &BddVec, right: &BddVec) -> Result<(), String> {
+    if left.width() == right.width() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Vector widths differ ({} and {}).",
+            left.width(),
+            right.width()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::{ApplyContext, BddVec, VariableId};
+
+    #[test]
+    pub fn bdd_vec_test() {
+        let mut context = ApplyContext::new();
+        let c = |value| BddVec::constant(value, 4);
+
+        // Constant arithmetic wraps around.
+        assert_eq!(c(8), context.vec_add(&c(3), &c(5)).unwrap());
+        assert_eq!(c(1), context.vec_add(&c(9), &c(8)).unwrap());
+        assert_eq!(c(14), context.vec_sub(&c(3), &c(5)).unwrap());
+        assert_eq!(c(12), c(3).shift_left(2));
+        assert_eq!(c(2), c(11).shift_right(2));
+        assert_eq!(c(0), c(11).shift_right(7));
+
+        // Symbolic vectors over interleaved variables.
+        let x_vars: Vec<VariableId> = (0..4u16).map(|i| VariableId::from(2 * i)).collect();
+        let y_vars: Vec<VariableId> = (0..4u16).map(|i| VariableId::from(2 * i + 1)).collect();
+        let x = context.vec_variables(&x_vars);
+        let y = context.vec_variables(&y_vars);
+        let sum = context.vec_add(&x, &y).unwrap();
+        assert_eq!(x, context.vec_sub(&sum, &y).unwrap());
+        assert_eq!(sum, context.vec_add(&y, &x).unwrap());
+        let double = context.vec_add(&x, &x).unwrap();
+        assert_eq!(x.shift_left(1), double);
+
+        // Exactly one of `x < y`, `x = y` and `y < x` holds.
+        let less = context.vec_less_than(&x, &y).unwrap();
+        let greater = context.vec_less_than(&y, &x).unwrap();
+        let equal = context.vec_equal(&x, &y).unwrap();
+        let either = context.or(less, greater);
+        let one = c(1).bits()[0];
+        let zero = c(0).bits()[0];
+        assert_eq!(one, context.or(either, equal));
+        assert_eq!(zero, context.and(less, greater));
+        assert_eq!(zero, context.and(either, equal));
+        assert_eq!(zero, context.vec_less_than(&x, &x).unwrap());
+        let below_three = context.vec_less_than(&x, &c(3)).unwrap();
+        let x_high = context.or(x.bits()[2], x.bits()[3]);
+        let x_low = context.and(x.bits()[0], x.bits()[1]);
+        let expected = context.or(x_high, x_low);
+        assert_eq!(zero, context.and(below_three, expected));
+
+        assert!(context.vec_add(&x, &BddVec::constant(0, 3)).is_err());
+    }
+}
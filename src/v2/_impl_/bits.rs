@@ -0,0 +1,117 @@
+use crate::v2::{BitMatrix, BitVec};
+use alloc::vec;
+use alloc::vec::Vec;
+
+impl BitMatrix {
+    /// A matrix with the given number of `rows` and `columns` where all bits are zero.
+    pub fn new(rows: usize, columns: usize) -> BitMatrix {
+        BitMatrix {
+            rows,
+            columns,
+            words: vec![0; rows * words_for(columns)],
+        }
+    }
+
+    /// The number of rows (i.e. variables) of this matrix.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// The number of columns (i.e. valuations) of this matrix.
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Read the bit at the given `row` and `column`.
+    pub fn get(&self, row: usize, column: usize) -> bool {
+        assert!(column < self.columns, "Column {} out of bounds.", column);
+        (self.row_words(row)[column / 64] >> (column % 64)) & 1 == 1
+    }
+
+    /// Update the bit at the given `row` and `column`.
+    pub fn set(&mut self, row: usize, column: usize, value: bool) {
+        assert!(column < self.columns, "Column {} out of bounds.", column);
+        assert!(row < self.rows, "Row {} out of bounds.", row);
+        let word = &mut self.words[row * words_for(self.columns) + column / 64];
+        if value {
+            *word |= 1 << (column % 64);
+        } else {
+            *word &= !(1 << (column % 64));
+        }
+    }
+
+    /// The packed bits of the given `row`: column `c` is the `c % 64`-th bit of word `c / 64`.
+    /// The unused bits of the last word are always zero.
+    pub fn row_words(&self, row: usize) -> &[u64] {
+        assert!(row < self.rows, "Row {} out of bounds.", row);
+        let width = words_for(self.columns);
+        &self.words[(row * width)..((row + 1) * width)]
+    }
+}
+
+impl BitVec {
+    /// A vector of `len` zero bits.
+    pub fn new(len: usize) -> BitVec {
+        BitVec {
+            len,
+            words: vec![0; words_for(len)],
+        }
+    }
+
+    /// **(internal)** Create a vector from packed `words`. The unused bits of the last word
+    /// are cleared.
+    pub(crate) fn from_words(len: usize, mut words: Vec<u64>) -> BitVec {
+        debug_assert_eq!(words_for(len), words.len());
+        if !len.is_multiple_of(64) {
+            if let Some(last) = words.last_mut() {
+                *last &= (1 << (len % 64)) - 1;
+            }
+        }
+        BitVec { len, words }
+    }
+
+    /// The number of bits of this vector.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// True if the vector has no bits.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Read the bit at the given `index`.
+    pub fn get(&self, index: usize) -> bool {
+        assert!(index < self.len, "Index {} out of bounds.", index);
+        (self.words[index / 64] >> (index % 64)) & 1 == 1
+    }
+
+    /// Update the bit at the given `index`.
+    pub fn set(&mut self, index: usize, value: bool) {
+        assert!(index < self.len, "Index {} out of bounds.", index);
+        if value {
+            self.words[index / 64] |= 1 << (index % 64);
+        } else {
+            self.words[index / 64] &= !(1 << (index % 64));
+        }
+    }
+
+    /// The number of bits which are set.
+    pub fn count_ones(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// The packed bits of this vector: bit `i` is the `i % 64`-th bit of word `i / 64`.
+    /// The unused bits of the last word are always zero.
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+}
+
+/// **(internal)** The number of words needed for the given number of bits.
+fn words_for(bits: usize) -> usize {
+    bits.div_ceil(64)
+}
@@ -0,0 +1,732 @@
+use crate::v2::{
+    ApplyContext, Bdd, BddNode, BoolOp, CacheEntry, ContextSnapshot, CowChunks, NodeId,
+    PooledHandle, VariableId,
+};
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::{max, min};
+
+/// **(internal)** The initial size of the unique table and the operation cache.
+const INITIAL_CAPACITY: usize = 1 << 10;
+
+/// **(internal)** Operations supported by the `ApplyContext`. The discriminant is used
+/// in the operation cache.
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub(super) enum Operation {
+    And = 0,
+    Or = 1,
+    Imp = 2,
+    Iff = 3,
+    Xor = 4,
+    AndNot = 5,
+    /// Mirrored `Imp`, i.e. `right => left`.
+    InvImp = 6,
+    /// Mirrored `AndNot`, i.e. `!left & right`.
+    NotAnd = 7,
+}
+
+impl Operation {
+    /// **(internal)** The operation which computes the same function with swapped operands.
+    fn mirrored(self) -> Operation {
+        match self {
+            Operation::Imp => Operation::InvImp,
+            Operation::InvImp => Operation::Imp,
+            Operation::AndNot => Operation::NotAnd,
+            Operation::NotAnd => Operation::AndNot,
+            commutative => commutative,
+        }
+    }
+
+    /// **(internal)** The canonical operation cache key of the task `(left, right)`:
+    /// the operands are ordered by their ids, and if they are swapped, the operation is
+    /// mirrored. Hence `imp(a, b)` and `inv_imp(b, a)` (as well as `and(a, b)` and
+    /// `and(b, a)`) share one cache entry.
+    fn cache_key(self, left: NodeId, right: NodeId) -> (u8, NodeId, NodeId) {
+        if left.0 <= right.0 {
+            (self as u8, left, right)
+        } else {
+            (self.mirrored() as u8, right, left)
+        }
+    }
+
+    /// The terminal lookup table of this operation. Same as in `Bdd::binary_operation`,
+    /// `NodeId::UNDEFINED` means the result is not known yet.
+    pub(super) fn table(self, l: NodeId, r: NodeId) -> NodeId {
+        let is_terminal = l.as_index() < 2 && r.as_index() < 2;
+        let value = match self {
+            Operation::And if l.is_zero() || r.is_zero() => Some(false),
+            Operation::Or | Operation::Imp if r.is_one() => Some(true),
+            Operation::Or if l.is_one() => Some(true),
+            Operation::Imp if l.is_zero() => Some(true),
+            Operation::AndNot if l.is_zero() || r.is_one() => Some(false),
+            Operation::InvImp if l.is_one() || r.is_zero() => Some(true),
+            Operation::NotAnd if l.is_one() || r.is_zero() => Some(false),
+            Operation::And | Operation::AndNot | Operation::NotAnd if is_terminal => Some(true),
+            Operation::Or | Operation::Imp | Operation::InvImp if is_terminal => Some(false),
+            Operation::Iff if is_terminal => Some(l == r),
+            Operation::Xor if is_terminal => Some(l != r),
+            _ => None,
+        };
+        match value {
+            Some(true) => NodeId::ONE,
+            Some(false) => NodeId::ZERO,
+            None => NodeId::UNDEFINED,
+        }
+    }
+}
+
+impl From<BoolOp> for Operation {
+    fn from(value: BoolOp) -> Self {
+        match value {
+            BoolOp::And => Operation::And,
+            BoolOp::Or => Operation::Or,
+            BoolOp::Xor => Operation::Xor,
+            BoolOp::Imp => Operation::Imp,
+            BoolOp::Iff => Operation::Iff,
+            BoolOp::AndNot => Operation::AndNot,
+            BoolOp::NotAnd => Operation::NotAnd,
+        }
+    }
+}
+
+/// **(internal)** A single task of the `ApplyContext::apply` search.
+#[derive(Copy, Clone)]
+struct Frame {
+    left: NodeId,
+    right: NodeId,
+    variable: VariableId,
+    high_left: NodeId,
+    high_right: NodeId,
+    /// The result of the low branch, or `UNDEFINED` if it is not known yet.
+    low: NodeId,
+}
+
+impl Frame {
+    fn new(left: NodeId, right: NodeId) -> Frame {
+        Frame {
+            left,
+            right,
+            variable: VariableId::UNDEFINED,
+            high_left: NodeId::UNDEFINED,
+            high_right: NodeId::UNDEFINED,
+            low: NodeId::UNDEFINED,
+        }
+    }
+}
+
+impl CacheEntry {
+    const EMPTY: CacheEntry = CacheEntry {
+        operation: 0,
+        generation: 0,
+        left: NodeId::UNDEFINED,
+        right: NodeId::UNDEFINED,
+        result: NodeId::UNDEFINED,
+    };
+}
+
+impl ApplyContext {
+    /// Create a new context which only contains the two terminal nodes.
+    pub fn new() -> ApplyContext {
+        let mut nodes = CowChunks::new();
+        nodes.push(BddNode::ZERO);
+        nodes.push(BddNode::ONE);
+        ApplyContext {
+            variable_count: 0,
+            nodes,
+            unique_table: CowChunks::filled(INITIAL_CAPACITY, NodeId::UNDEFINED),
+            operation_cache: CowChunks::filled(INITIAL_CAPACITY, CacheEntry::EMPTY),
+            generation: 0,
+        }
+    }
+
+    /// The number of (unique) nodes stored in this context, including terminals.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// The largest variable count of all the `Bdds` imported into this context.
+    pub fn variable_count(&self) -> u16 {
+        self.variable_count
+    }
+
+    /// Save the current state of this context: its nodes, unique table and operation cache.
+    ///
+    /// The snapshot shares all its memory with the context. Only the parts of the context
+    /// which are modified afterwards are copied (in chunks of a few thousand items).
+    pub fn snapshot(&self) -> ContextSnapshot {
+        ContextSnapshot(self.clone())
+    }
+
+    /// Return this context to the state saved in the `snapshot`, including the warm caches.
+    ///
+    /// Handles created after the snapshot was taken are no longer valid once the context
+    /// is restored. A snapshot can be restored repeatedly.
+    pub fn restore(&mut self, snapshot: &ContextSnapshot) {
+        *self = snapshot.0.clone();
+    }
+
+    /// Forget all results in the operation cache. The nodes of the context (and all handles)
+    /// stay valid.
+    ///
+    /// The cache entries are not actually erased. Instead, the cache switches to a new
+    /// generation and entries of older generations are ignored (and eventually overwritten),
+    /// so the operation takes constant time regardless of the size of the cache.
+    pub fn clear_operation_cache(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+        if self.generation == 0 {
+            // After a wrap-around, some entries could become valid again.
+            let capacity = self.operation_cache.len();
+            self.operation_cache = CowChunks::filled(capacity, CacheEntry::EMPTY);
+        }
+    }
+
+    /// Copy a standalone `Bdd` into this context, sharing every node that already exists
+    /// in the context. Returns a handle to the root of the imported `Bdd`.
+    pub fn import(&mut self, bdd: &Bdd) -> PooledHandle {
+        self.variable_count = max(self.variable_count, bdd.variable_count());
+        if bdd.node_count() <= 2 {
+            return PooledHandle(bdd.root_node());
+        }
+        // Maps node ids of `bdd` to node ids in this context.
+        let mut translation = vec![NodeId::UNDEFINED; bdd.node_count()];
+        translation[0] = NodeId::ZERO;
+        translation[1] = NodeId::ONE;
+        let mut stack = vec![bdd.root_node()];
+        while let Some(top) = stack.last().cloned() {
+            if !translation[top.as_index()].is_undefined() {
+                stack.pop();
+                continue;
+            }
+            let (var, low, high) = bdd.get_node(top).unpack();
+            let new_low = translation[low.as_index()];
+            let new_high = translation[high.as_index()];
+            if new_low.is_undefined() || new_high.is_undefined() {
+                if new_high.is_undefined() {
+                    stack.push(high);
+                }
+                if new_low.is_undefined() {
+                    stack.push(low);
+                }
+            } else {
+                translation[top.as_index()] = self.ensure(BddNode::pack(var, new_low, new_high));
+                stack.pop();
+            }
+        }
+        PooledHandle(translation[bdd.root_node().as_index()])
+    }
+
+    /// Create a standalone copy of the `Bdd` referenced by the given `handle`.
+    ///
+    /// The result only contains the nodes reachable from the `handle` and it is sorted
+    /// bottom-up (i.e. in the same way as a result of `apply`).
+    pub fn export(&self, handle: PooledHandle) -> Bdd {
+        let root = handle.0;
+        let mut result = if root.is_zero() {
+            Bdd::new_false()
+        } else {
+            Bdd::true_with_capacity(2)
+        };
+        result.update_variable_count(self.variable_count);
+        if root.as_index() < 2 {
+            return result;
+        }
+
+        // Maps node ids of this context to node ids in the result.
+        let mut translation: BTreeMap<u64, NodeId> = BTreeMap::new();
+        translation.insert(0, NodeId::ZERO);
+        translation.insert(1, NodeId::ONE);
+        let mut stack = vec![root];
+        while let Some(top) = stack.last().cloned() {
+            if translation.contains_key(&top.0) {
+                stack.pop();
+                continue;
+            }
+            let (var, low, high) = self.nodes.get(top.as_index()).unpack();
+            match (translation.get(&low.0), translation.get(&high.0)) {
+                (Some(new_low), Some(new_high)) => {
+                    let new_id = result.push_node(BddNode::pack(var, *new_low, *new_high));
+                    translation.insert(top.0, new_id);
+                    stack.pop();
+                }
+                (new_low, new_high) => {
+                    if new_high.is_none() {
+                        stack.push(high);
+                    }
+                    if new_low.is_none() {
+                        stack.push(low);
+                    }
+                }
+            }
+        }
+        result.debug_check_integrity("export");
+        result
+    }
+
+    /// Replace every variable `from[i]` with the variable `to[i]` in the `Bdd` referenced
+    /// by the `handle`. Other variables are not changed.
+    ///
+    /// The renaming must preserve the order of the variables which appear in the `Bdd`
+    /// (e.g. renaming between interleaved "current" and "next" variables), in which case
+    /// it only needs to relabel the nodes. Otherwise, an error is returned.
+    pub fn rename(
+        &mut self,
+        handle: PooledHandle,
+        from: &[VariableId],
+        to: &[VariableId],
+    ) -> Result<PooledHandle, String> {
+        let support = self.support(handle);
+        let mapping = self.renaming(&support, from, to)?;
+
+        // Maps node ids of the original `Bdd` to node ids of the renamed `Bdd`.
+        let mut translation: BTreeMap<u64, NodeId> = BTreeMap::new();
+        translation.insert(0, NodeId::ZERO);
+        translation.insert(1, NodeId::ONE);
+        let root = handle.0;
+        let mut stack = vec![root];
+        while let Some(top) = stack.last().cloned() {
+            if translation.contains_key(&top.0) {
+                stack.pop();
+                continue;
+            }
+            let (var, low, high) = self.nodes.get(top.as_index()).unpack();
+            match (translation.get(&low.0), translation.get(&high.0)) {
+                (Some(new_low), Some(new_high)) => {
+                    let new_var = mapping[usize::from(var.0)];
+                    let new_id = self.ensure(BddNode::pack(new_var, *new_low, *new_high));
+                    translation.insert(top.0, new_id);
+                    stack.pop();
+                }
+                (new_low, new_high) => {
+                    if new_high.is_none() {
+                        stack.push(high);
+                    }
+                    if new_low.is_none() {
+                        stack.push(low);
+                    }
+                }
+            }
+        }
+        Ok(PooledHandle(translation[&root.0]))
+    }
+
+    /// **(internal)** The mapping of every variable of this context to its new variable,
+    /// given by renaming every variable `from[i]` to `to[i]`.
+    ///
+    /// The renaming must preserve the order of the variables in the (sorted) `support`,
+    /// otherwise an error is returned. The variable count of the context is extended
+    /// to include the renamed `support`.
+    pub(super) fn renaming(
+        &mut self,
+        support: &[VariableId],
+        from: &[VariableId],
+        to: &[VariableId],
+    ) -> Result<Vec<VariableId>, String> {
+        if from.len() != to.len() {
+            return Err(format!(
+                "Cannot rename {} variables to {} variables.",
+                from.len(),
+                to.len()
+            ));
+        }
+        let mut mapping: Vec<VariableId> = (0..self.variable_count).map(VariableId).collect();
+        for (old, new) in from.iter().zip(to) {
+            if let Some(slot) = mapping.get_mut(usize::from(old.0)) {
+                *slot = *new;
+            }
+        }
+        for pair in support.windows(2) {
+            let (first, second) = (
+                mapping[usize::from(pair[0].0)],
+                mapping[usize::from(pair[1].0)],
+            );
+            if first >= second {
+                return Err(format!(
+                    "Renaming does not preserve the order of variables {} and {}.",
+                    pair[0].0, pair[1].0
+                ));
+            }
+        }
+        for var in support {
+            self.variable_count = max(self.variable_count, mapping[usize::from(var.0)].0 + 1);
+        }
+        Ok(mapping)
+    }
+
+    /// A logical conjunction of two `Bdds` stored in this context.
+    pub fn and(&mut self, left: PooledHandle, right: PooledHandle) -> PooledHandle {
+        self.apply(Operation::And, left, right)
+    }
+
+    /// A logical disjunction of two `Bdds` stored in this context.
+    pub fn or(&mut self, left: PooledHandle, right: PooledHandle) -> PooledHandle {
+        self.apply(Operation::Or, left, right)
+    }
+
+    /// A logical implication of two `Bdds` stored in this context.
+    pub fn imp(&mut self, left: PooledHandle, right: PooledHandle) -> PooledHandle {
+        self.apply(Operation::Imp, left, right)
+    }
+
+    /// A logical equivalence of two `Bdds` stored in this context.
+    pub fn iff(&mut self, left: PooledHandle, right: PooledHandle) -> PooledHandle {
+        self.apply(Operation::Iff, left, right)
+    }
+
+    /// A logical exclusive disjunction of two `Bdds` stored in this context.
+    pub fn xor(&mut self, left: PooledHandle, right: PooledHandle) -> PooledHandle {
+        self.apply(Operation::Xor, left, right)
+    }
+
+    /// A logical conjunction with a negated second argument of two `Bdds` stored
+    /// in this context.
+    pub fn and_not(&mut self, left: PooledHandle, right: PooledHandle) -> PooledHandle {
+        self.apply(Operation::AndNot, left, right)
+    }
+
+    /// A logical implication with swapped arguments (`right => left`) of two `Bdds` stored
+    /// in this context. It shares operation cache entries with `ApplyContext::imp`.
+    pub fn inv_imp(&mut self, left: PooledHandle, right: PooledHandle) -> PooledHandle {
+        self.apply(Operation::InvImp, left, right)
+    }
+
+    /// A logical conjunction with a negated first argument of two `Bdds` stored
+    /// in this context. It shares operation cache entries with `ApplyContext::and_not`.
+    pub fn not_and(&mut self, left: PooledHandle, right: PooledHandle) -> PooledHandle {
+        self.apply(Operation::NotAnd, left, right)
+    }
+
+    /// Apply the logical operation selected by `op` to two `Bdds` stored in this context
+    /// (equivalent to calling the corresponding method, e.g. `ApplyContext::and`).
+    pub fn apply_op(
+        &mut self,
+        op: BoolOp,
+        left: PooledHandle,
+        right: PooledHandle,
+    ) -> PooledHandle {
+        self.apply(Operation::from(op), left, right)
+    }
+
+    /// **(internal)** The apply algorithm on the shared nodes. Results of all finished
+    /// tasks are saved into the operation cache, so they are available to later operations.
+    fn apply(
+        &mut self,
+        operation: Operation,
+        left: PooledHandle,
+        right: PooledHandle,
+    ) -> PooledHandle {
+        let mut stack = vec![Frame::new(left.0, right.0)];
+        loop {
+            let depth = stack.len();
+            let top = &mut stack[depth - 1];
+            let (left, right) = (top.left, top.right);
+            let mut value = operation.table(left, right);
+            if value.is_undefined() {
+                value = self.cache_get(operation, left, right);
+            }
+            if value.is_undefined() {
+                // Expand the task and continue with its low successor.
+                let (left_var, left_low, left_high) = self.nodes.get(left.as_index()).unpack();
+                let (right_var, right_low, right_high) = self.nodes.get(right.as_index()).unpack();
+                let variable = min(left_var, right_var);
+                let (left_low, left_high) = if left_var == variable {
+                    (left_low, left_high)
+                } else {
+                    (left, left)
+                };
+                let (right_low, right_high) = if right_var == variable {
+                    (right_low, right_high)
+                } else {
+                    (right, right)
+                };
+                top.variable = variable;
+                top.high_left = left_high;
+                top.high_right = right_high;
+                stack.push(Frame::new(left_low, right_low));
+                continue;
+            }
+
+            // The top task is finished. Propagate its result to its parents.
+            stack.pop();
+            let finished = loop {
+                let parent = match stack.last_mut() {
+                    None => break Some(value),
+                    Some(parent) => parent,
+                };
+                if parent.low.is_undefined() {
+                    parent.low = value;
+                    let high_task = Frame::new(parent.high_left, parent.high_right);
+                    stack.push(high_task);
+                    break None;
+                }
+                let parent = *parent;
+                value = if parent.low == value {
+                    value
+                } else {
+                    self.ensure(BddNode::pack(parent.variable, parent.low, value))
+                };
+                self.cache_put(operation, parent.left, parent.right, value);
+                stack.pop();
+            };
+            if let Some(value) = finished {
+                return PooledHandle(value);
+            }
+        }
+    }
+
+    /// **(internal)** Find the `node` in the unique table, or create it.
+    pub(super) fn ensure(&mut self, node: BddNode) -> NodeId {
+        let mask = self.unique_table.len() - 1;
+        let mut slot = node_hash(node) & mask;
+        loop {
+            let id = self.unique_table.get(slot);
+            if id.is_undefined() {
+                break;
+            }
+            if self.nodes.get(id.as_index()) == node {
+                return id;
+            }
+            slot = (slot + 1) & mask;
+        }
+        Bdd::check_next_node(self.nodes.len());
+        let id = NodeId(self.nodes.len() as u64);
+        self.nodes.push(node);
+        self.unique_table.set(slot, id);
+        if 2 * self.nodes.len() > self.unique_table.len() {
+            self.grow();
+        }
+        id
+    }
+
+    /// **(internal)** Double the size of the unique table and the operation cache. The
+    /// cache is lossy, so its contents are simply rehashed into the new table.
+    fn grow(&mut self) {
+        let capacity = 2 * self.unique_table.len();
+        let mask = capacity - 1;
+        let mut unique_table = CowChunks::filled(capacity, NodeId::UNDEFINED);
+        for index in 2..self.nodes.len() {
+            let mut slot = node_hash(self.nodes.get(index)) & mask;
+            while !unique_table.get(slot).is_undefined() {
+                slot = (slot + 1) & mask;
+            }
+            unique_table.set(slot, NodeId(index as u64));
+        }
+        self.unique_table = unique_table;
+
+        let old_cache = core::mem::replace(
+            &mut self.operation_cache,
+            CowChunks::filled(capacity, CacheEntry::EMPTY),
+        );
+        for index in 0..old_cache.len() {
+            let entry = old_cache.get(index);
+            if !entry.result.is_undefined() && entry.generation == self.generation {
+                let slot = task_hash(entry.operation, entry.left, entry.right) & mask;
+                self.operation_cache.set(slot, entry);
+            }
+        }
+    }
+
+    fn cache_get(&self, operation: Operation, left: NodeId, right: NodeId) -> NodeId {
+        let (operation, left, right) = operation.cache_key(left, right);
+        let slot = task_hash(operation, left, right) & (self.operation_cache.len() - 1);
+        let entry = self.operation_cache.get(slot);
+        let is_valid = entry.generation == self.generation && entry.operation == operation;
+        if is_valid && entry.left == left && entry.right == right {
+            entry.result
+        } else {
+            NodeId::UNDEFINED
+        }
+    }
+
+    fn cache_put(&mut self, operation: Operation, left: NodeId, right: NodeId, result: NodeId) {
+        let (operation, left, right) = operation.cache_key(left, right);
+        let slot = task_hash(operation, left, right) & (self.operation_cache.len() - 1);
+        let entry = CacheEntry {
+            operation,
+            generation: self.generation,
+            left,
+            right,
+            result,
+        };
+        self.operation_cache.set(slot, entry);
+    }
+}
+
+impl Bdd {
+    /// Compute `left op right` for every `left` operand and one shared `right` operand.
+    ///
+    /// All operands are imported into one `ApplyContext`, so the tasks of the `right`
+    /// operand are cached across all the `left` operands. This is much faster than
+    /// independent operations when many candidate functions are screened against
+    /// a single constraint, since the left operands usually share many sub-functions.
+    /// The variable count of every result is the larger of the counts of its operands.
+    pub fn apply_many(lefts: &[&Bdd], op: BoolOp, right: &Bdd) -> Vec<Bdd> {
+        let mut context = ApplyContext::new();
+        let right_handle = context.import(right);
+        lefts
+            .iter()
+            .map(|left| {
+                let left_handle = context.import(left);
+                let result = context.apply_op(op, left_handle, right_handle);
+                let mut result = context.export(result);
+                // The context counts the variables of all operands imported so far.
+                result.variable_count = max(left.variable_count, right.variable_count);
+                result
+            })
+            .collect()
+    }
+}
+
+impl Default for ApplyContext {
+    fn default() -> Self {
+        ApplyContext::new()
+    }
+}
+
+#[inline]
+pub(super) fn node_hash(node: BddNode) -> usize {
+    let hash =
+        node.0.wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ node.1.wrapping_mul(0xc2b2_ae3d_27d4_eb4f);
+    (hash ^ (hash >> 29)) as usize
+}
+
+#[inline]
+fn task_hash(operation: u8, left: NodeId, right: NodeId) -> usize {
+    let hash = left.0.wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ right.0.wrapping_mul(0xc2b2_ae3d_27d4_eb4f)
+        ^ u64::from(operation).wrapping_mul(0x1656_67b1_9e37_79f9);
+    (hash ^ (hash >> 29)) as usize
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::_impl_::test_util::variables;
+    use crate::v2::{ApplyContext, Bdd, BoolOp, VariableId};
+    use core::cmp::max;
+
+    #[test]
+    pub fn context_snapshot_test() {
+        let vars = variables(6);
+        let mut context = ApplyContext::new();
+        let handles: Vec<_> = vars.iter().map(|v| context.import(v)).collect();
+
+        let a = context.and(handles[0], handles[1]);
+        let a = context.or(a, handles[2]);
+        let b = context.xor(handles[3], handles[4]);
+        let b = context.imp(b, handles[5]);
+        let expected_a = vars[0].and(&vars[1]).or(&vars[2]);
+        assert_eq!(1, context.export(a).xor(&expected_a).node_count());
+
+        let snapshot = context.snapshot();
+        let node_count = context.node_count();
+        assert!(context.nodes.shared_chunks() > 0);
+
+        // "What if" operation, then roll back.
+        let c = context.iff(a, b);
+        let c = context.and_not(c, handles[0]);
+        let expected_c = expected_a
+            .iff(&vars[3].xor(&vars[4]).imp(&vars[5]))
+            .and_not(&vars[0]);
+        assert_eq!(1, context.export(c).xor(&expected_c).node_count());
+        assert!(context.node_count() > node_count);
+
+        context.restore(&snapshot);
+        assert_eq!(node_count, context.node_count());
+        assert_eq!(1, context.export(a).xor(&expected_a).node_count());
+        // The operation cache is warm, so repeating an operation creates no nodes.
+        let a_again = context.and(handles[0], handles[1]);
+        assert_eq!(a, context.or(a_again, handles[2]));
+        assert_eq!(node_count, context.node_count());
+        // Importing an existing `Bdd` does not create nodes either.
+        assert_eq!(a, context.import(&expected_a));
+        assert_eq!(node_count, context.node_count());
+    }
+
+    #[test]
+    pub fn operation_cache_generation_test() {
+        use super::Operation;
+        let mut context = ApplyContext::new();
+        let x0 = context.import(&Bdd::new_variable(VariableId::from(0)));
+        let x1 = context.import(&Bdd::new_variable(VariableId::from(1)));
+        let a = context.and(x0, x1);
+        assert_eq!(a.0, context.cache_get(Operation::And, x0.0, x1.0));
+
+        // Cleared entries are ignored, but the nodes (and handles) stay valid.
+        context.clear_operation_cache();
+        assert!(context.cache_get(Operation::And, x0.0, x1.0).is_undefined());
+        let node_count = context.node_count();
+        assert_eq!(a, context.and(x0, x1));
+        assert_eq!(node_count, context.node_count());
+        assert_eq!(a.0, context.cache_get(Operation::And, x0.0, x1.0));
+
+        // Entries from before a wrap-around of the generation counter are erased.
+        context.generation = u32::MAX;
+        let b = context.or(x0, x1);
+        context.clear_operation_cache();
+        assert_eq!(0, context.generation);
+        assert!(context.cache_get(Operation::Or, x0.0, x1.0).is_undefined());
+        assert!(context.cache_get(Operation::And, x0.0, x1.0).is_undefined());
+        assert_eq!(b, context.or(x0, x1));
+    }
+
+    #[test]
+    pub fn mirrored_operation_cache_test() {
+        use super::Operation;
+        let vars = variables(4);
+        let a = vars[0].and(&vars[2]).or(&vars[1]);
+        let b = vars[1].xor(&vars[3]);
+        let mut context = ApplyContext::new();
+        let (ha, hb) = (context.import(&a), context.import(&b));
+
+        // Mirrored operations compute the same function and share the cache entries.
+        let imp = context.imp(ha, hb);
+        assert_eq!(imp.0, context.cache_get(Operation::InvImp, hb.0, ha.0));
+        let node_count = context.node_count();
+        assert_eq!(imp, context.inv_imp(hb, ha));
+        let and_not = context.and_not(ha, hb);
+        assert_eq!(and_not.0, context.cache_get(Operation::NotAnd, hb.0, ha.0));
+        assert_eq!(and_not, context.not_and(hb, ha));
+        assert_eq!(1, context.export(imp).xor(&a.imp(&b)).node_count());
+        assert_eq!(1, context.export(and_not).xor(&a.and_not(&b)).node_count());
+
+        // Commutative operations share the entries of both operand orders.
+        let and = context.and(ha, hb);
+        assert_eq!(and.0, context.cache_get(Operation::And, hb.0, ha.0));
+        assert_eq!(and, context.and(hb, ha));
+
+        // The mirrored operations are correct on their own (with a cold cache).
+        context.clear_operation_cache();
+        let inv_imp = context.inv_imp(ha, hb);
+        let not_and = context.not_and(ha, hb);
+        assert_eq!(1, context.export(inv_imp).xor(&b.imp(&a)).node_count());
+        assert_eq!(1, context.export(not_and).xor(&b.and_not(&a)).node_count());
+        assert!(context.node_count() > node_count);
+    }
+
+    #[test]
+    pub fn apply_many_test() {
+        let constraint = crate::testing::hidden_weighted_bit(6);
+        let candidates = vec![
+            crate::testing::multiplier_bit(3, 2),
+            crate::testing::multiplier_bit(3, 3),
+            crate::testing::queens(4),
+            Bdd::new_variable(VariableId::from(9u16)),
+            Bdd::new_false(),
+        ];
+        let lefts: Vec<&Bdd> = candidates.iter().collect();
+        for op in BoolOp::ALL {
+            let results = Bdd::apply_many(&lefts, op, &constraint);
+            assert_eq!(candidates.len(), results.len());
+            for (left, result) in candidates.iter().zip(results.iter()) {
+                let expected = left.apply_op(op, &constraint);
+                assert!(result.semantic_eq(&expected));
+                assert_eq!(expected.node_count(), result.node_count());
+                let variables = max(left.variable_count(), constraint.variable_count());
+                assert_eq!(variables, result.variable_count());
+            }
+        }
+        assert!(Bdd::apply_many(&[], BoolOp::And, &constraint).is_empty());
+    }
+}
@@ -0,0 +1,88 @@
+use crate::v2::CowChunks;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// **(internal)** The number of items in one chunk. Small enough so that copying a chunk
+/// after a snapshot is cheap, large enough so that the list of chunks stays short.
+const CHUNK_SIZE: usize = 1 << 12;
+
+impl<T: Copy> CowChunks<T> {
+    /// Create an empty vector.
+    pub(super) fn new() -> CowChunks<T> {
+        CowChunks {
+            len: 0,
+            chunks: Vec::new(),
+        }
+    }
+
+    /// Create a vector of the given `len` where every item is `value`.
+    pub(super) fn filled(len: usize, value: T) -> CowChunks<T> {
+        let mut chunks = Vec::with_capacity(len.div_ceil(CHUNK_SIZE));
+        let mut remaining = len;
+        while remaining > 0 {
+            let chunk_len = remaining.min(CHUNK_SIZE);
+            chunks.push(Arc::new(vec![value; chunk_len]));
+            remaining -= chunk_len;
+        }
+        CowChunks { len, chunks }
+    }
+
+    #[inline]
+    pub(super) fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub(super) fn get(&self, index: usize) -> T {
+        debug_assert!(index < self.len);
+        self.chunks[index / CHUNK_SIZE][index % CHUNK_SIZE]
+    }
+
+    /// Overwrite the item at the given `index`. If the chunk is shared with a clone of this
+    /// vector, it is copied first.
+    #[inline]
+    pub(super) fn set(&mut self, index: usize, value: T) {
+        debug_assert!(index < self.len);
+        Arc::make_mut(&mut self.chunks[index / CHUNK_SIZE])[index % CHUNK_SIZE] = value;
+    }
+
+    /// Append an item at the end of the vector. If the last chunk is shared with a clone
+    /// of this vector, it is copied first.
+    pub(super) fn push(&mut self, value: T) {
+        if self.len.is_multiple_of(CHUNK_SIZE) {
+            self.chunks.push(Arc::new(Vec::with_capacity(CHUNK_SIZE)));
+        }
+        let last = self.chunks.len() - 1;
+        Arc::make_mut(&mut self.chunks[last]).push(value);
+        self.len += 1;
+    }
+
+    /// Index ranges of this vector whose items can differ from the items of `other`.
+    /// Chunks shared with `other` are skipped without looking at their items.
+    pub(super) fn unshared_ranges<'a>(
+        &'a self,
+        other: &'a CowChunks<T>,
+    ) -> impl Iterator<Item = Range<usize>> + 'a {
+        self.chunks
+            .iter()
+            .enumerate()
+            .filter(move |(i, chunk)| {
+                other
+                    .chunks
+                    .get(*i)
+                    .is_none_or(|other_chunk| !Arc::ptr_eq(chunk, other_chunk))
+            })
+            .map(move |(i, _)| (i * CHUNK_SIZE)..self.len.min((i + 1) * CHUNK_SIZE))
+    }
+
+    /// **(internal)** The number of chunks that are shared with some clone of this vector.
+    #[cfg(test)]
+    pub(super) fn shared_chunks(&self) -> usize {
+        self.chunks
+            .iter()
+            .filter(|chunk| Arc::strong_count(chunk) > 1)
+            .count()
+    }
+}
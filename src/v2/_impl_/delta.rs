@@ -0,0 +1,117 @@
+use crate::v2::{ApplyContext, Delta, NodeId, PooledHandle, VariableId};
+use alloc::string::String;
+
+impl Delta {
+    /// Start an iterative computation from the `initial` set of states. All initial states
+    /// are in the frontier.
+    pub fn new(initial: PooledHandle) -> Delta {
+        Delta {
+            reached: initial,
+            frontier: initial,
+        }
+    }
+
+    /// All the states reached so far.
+    pub fn reached(&self) -> PooledHandle {
+        self.reached
+    }
+
+    /// The states that were added to `reached` in the last iteration.
+    pub fn frontier(&self) -> PooledHandle {
+        self.frontier
+    }
+
+    /// True if the last iteration did not add any new states.
+    pub fn is_fixed_point(&self) -> bool {
+        self.frontier.0.is_zero()
+    }
+}
+
+impl ApplyContext {
+    /// Perform one iteration of an image computation: the states reachable from the frontier
+    /// of the `delta` using the `relation` which are not reached yet become the new frontier.
+    ///
+    /// The `relation` is defined over the `source` variables (the current state) and the
+    /// `target` variables (the successor state), and renaming between the two must preserve
+    /// the order of variables (see `ApplyContext::rename`). The states of the `delta` are
+    /// defined over the `source` variables.
+    ///
+    /// The successors are quantified and the already reached states are removed in
+    /// a single `and_exists` pass, so the part of the image that is already reached is
+    /// never built and no separate `and_not` is needed.
+    pub fn delta_image(
+        &mut self,
+        delta: Delta,
+        relation: PooledHandle,
+        source: &[VariableId],
+        target: &[VariableId],
+    ) -> Result<Delta, String> {
+        if delta.is_fixed_point() {
+            return Ok(delta);
+        }
+        let exclude = self.rename(delta.reached, source, target)?;
+        let image = self.and_exists_excluding(delta.frontier, relation, exclude, source, &[]);
+        if image.0.is_zero() {
+            return Ok(Delta {
+                reached: delta.reached,
+                frontier: PooledHandle(NodeId::ZERO),
+            });
+        }
+        let frontier = self.rename(image, target, source)?;
+        Ok(Delta {
+            reached: self.or(delta.reached, frontier),
+            frontier,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::{ApplyContext, Bdd, Delta, VariableId};
+
+    #[test]
+    pub fn delta_image_test() {
+        // A two-bit counter: even variables encode the current state (b0 = x0, b1 = x2),
+        // odd variables encode the successor state.
+        let mut context = ApplyContext::new();
+        let x: Vec<_> = (0..4u16)
+            .map(|v| context.import(&Bdd::new_variable(VariableId::from(v))))
+            .collect();
+        let source = [VariableId::from(0), VariableId::from(2)];
+        let target = [VariableId::from(1), VariableId::from(3)];
+        // b0' = !b0 and b1' = b1 ^ b0
+        let flip = context.xor(x[1], x[0]);
+        let carry = context.xor(x[2], x[0]);
+        let carry = context.iff(x[3], carry);
+        let relation = context.and(flip, carry);
+
+        // Start in 00 and visit 01, 10 and 11.
+        let b0_or_b1 = context.or(x[0], x[2]);
+        let empty = context.import(&Bdd::new_false());
+        let zero = context.imp(b0_or_b1, empty);
+        let mut delta = Delta::new(zero);
+        let expected = [
+            context.and_not(x[0], x[2]),
+            context.and_not(x[2], x[0]),
+            context.and(x[0], x[2]),
+        ];
+        for expected in expected.iter() {
+            delta = context
+                .delta_image(delta, relation, &source, &target)
+                .unwrap();
+            assert_eq!(*expected, delta.frontier());
+        }
+        delta = context
+            .delta_image(delta, relation, &source, &target)
+            .unwrap();
+        assert!(delta.is_fixed_point());
+        assert_eq!(1, delta.reached().0.as_index());
+
+        // Renaming that does not preserve the variable order is rejected.
+        let wrong_target = [VariableId::from(3), VariableId::from(1)];
+        let start = Delta::new(zero);
+        assert!(context
+            .delta_image(start, relation, &source, &wrong_target)
+            .is_err());
+    }
+}
@@ -0,0 +1,348 @@
+use crate::v2::{
+    ApplyContext, Bdd, BddNode, Forest, NodeId, PooledHandle, VariableId, VariableMetadata,
+};
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt::{Display, Formatter};
+
+impl Forest {
+    /// Create an empty forest.
+    pub fn new() -> Forest {
+        Forest {
+            context: ApplyContext::new(),
+            roots: BTreeMap::new(),
+            by_top_variable: BTreeMap::new(),
+            metadata: VariableMetadata::new(),
+        }
+    }
+
+    /// The number of roots in this forest.
+    pub fn len(&self) -> usize {
+        self.roots.len()
+    }
+
+    /// True if this forest has no roots.
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// The shared context which stores the nodes of all roots.
+    pub fn context(&self) -> &ApplyContext {
+        &self.context
+    }
+
+    /// The shared context which stores the nodes of all roots. It can be used to compute
+    /// new `Bdds` from the roots, which are then added using `Forest::insert_handle`.
+    pub fn context_mut(&mut self) -> &mut ApplyContext {
+        &mut self.context
+    }
+
+    /// Names, descriptions and groups of the variables used by this forest.
+    ///
+    /// The metadata is stored separately from the roots (it is not part of the forest
+    /// serialization), and clones of the forest share its strings.
+    pub fn metadata(&self) -> &VariableMetadata {
+        &self.metadata
+    }
+
+    /// Mutable access to the variable metadata of this forest.
+    pub fn metadata_mut(&mut self) -> &mut VariableMetadata {
+        &mut self.metadata
+    }
+
+    /// Copy the `bdd` into this forest under the given `name`, replacing the previous root
+    /// of the same name. Names cannot contain line breaks.
+    pub fn insert(&mut self, name: &str, bdd: &Bdd) -> Result<PooledHandle, String> {
+        check_name(name)?;
+        let handle = self.context.import(bdd);
+        self.set_root(name, handle);
+        Ok(handle)
+    }
+
+    /// Add a `handle` created by the context of this forest under the given `name`,
+    /// replacing the previous root of the same name. Names cannot contain line breaks.
+    pub fn insert_handle(&mut self, name: &str, handle: PooledHandle) -> Result<(), String> {
+        check_name(name)?;
+        if handle.0.as_index() >= self.context.node_count() {
+            return Err(format!("Invalid handle {}.", handle.0 .0));
+        }
+        self.set_root(name, handle);
+        Ok(())
+    }
+
+    /// The handle of the root with the given `name`.
+    pub fn get(&self, name: &str) -> Option<PooledHandle> {
+        self.roots.get(name).cloned()
+    }
+
+    /// Remove the root with the given `name` from this forest.
+    ///
+    /// The nodes of the root stay in the context (they can be shared with other roots).
+    pub fn remove(&mut self, name: &str) -> Option<PooledHandle> {
+        let handle = self.roots.remove(name)?;
+        let top = self.top_variable_of(handle);
+        if let Some(names) = self.by_top_variable.get_mut(&top) {
+            names.remove(name);
+            if names.is_empty() {
+                self.by_top_variable.remove(&top);
+            }
+        }
+        Some(handle)
+    }
+
+    /// The names of all roots (in lexicographic order).
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.roots.keys().map(|name| name.as_str())
+    }
+
+    /// Create a standalone copy of the root with the given `name`.
+    pub fn export(&self, name: &str) -> Option<Bdd> {
+        self.get(name).map(|handle| self.context.export(handle))
+    }
+
+    /// The top (root) variable of the root with the given `name`, or
+    /// `VariableId::UNDEFINED` if the root is a constant.
+    pub fn top_variable(&self, name: &str) -> Option<VariableId> {
+        self.get(name).map(|handle| self.top_variable_of(handle))
+    }
+
+    /// The names of all roots whose top variable is the given `variable` (in lexicographic
+    /// order). Use `VariableId::UNDEFINED` to obtain the constant roots.
+    pub fn roots_with_top_variable(&self, variable: VariableId) -> impl Iterator<Item = &str> {
+        self.by_top_variable
+            .get(&variable)
+            .into_iter()
+            .flat_map(|names| names.iter().map(|name| name.as_str()))
+    }
+
+    /// Replace every root `r` with `operation(r, operand)`, e.g.
+    /// `forest.apply_all(&mask, ApplyContext::and)`.
+    ///
+    /// The `operand` is imported only once and all operations share the operation cache
+    /// of the forest.
+    pub fn apply_all<F>(&mut self, operand: &Bdd, operation: F)
+    where
+        F: Fn(&mut ApplyContext, PooledHandle, PooledHandle) -> PooledHandle,
+    {
+        let operand = self.context.import(operand);
+        let names: Vec<String> = self.roots.keys().cloned().collect();
+        for name in names {
+            let result = operation(&mut self.context, self.roots[&name], operand);
+            self.set_root(&name, result);
+        }
+    }
+
+    /// **(internal)** Set the root `name` to `handle` and update the top variable index.
+    fn set_root(&mut self, name: &str, handle: PooledHandle) {
+        self.remove(name);
+        let top = self.top_variable_of(handle);
+        self.roots.insert(name.to_string(), handle);
+        self.by_top_variable
+            .entry(top)
+            .or_default()
+            .insert(name.to_string());
+    }
+
+    /// **(internal)** The variable of the node referenced by the `handle`.
+    fn top_variable_of(&self, handle: PooledHandle) -> VariableId {
+        self.context.nodes.get(handle.0.as_index()).variable()
+    }
+}
+
+impl Default for Forest {
+    fn default() -> Self {
+        Forest::new()
+    }
+}
+
+/// **(internal)** Names are stored one per line, so they cannot contain line breaks.
+fn check_name(name: &str) -> Result<(), String> {
+    if name.contains('\n') || name.contains('\r') {
+        Err(format!("Invalid root name `{}`.", name.escape_debug()))
+    } else {
+        Ok(())
+    }
+}
+
+/// Serialization of the whole forest: the first line contains the nodes reachable from
+/// the roots (each node only once, in the format of `Bdd::try_from`), followed by one
+/// `<node id> <name>` line for every root.
+impl Display for Forest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        // Maps node ids of the context to node ids in the output, which is sorted bottom-up.
+        let mut translation: BTreeMap<u64, u64> = BTreeMap::new();
+        translation.insert(0, 0);
+        translation.insert(1, 1);
+        let mut nodes: Vec<BddNode> = Vec::new();
+        for handle in self.roots.values() {
+            let mut stack = vec![handle.0];
+            while let Some(top) = stack.last().cloned() {
+                if translation.contains_key(&top.0) {
+                    stack.pop();
+                    continue;
+                }
+                let (var, low, high) = self.context.nodes.get(top.as_index()).unpack();
+                match (translation.get(&low.0), translation.get(&high.0)) {
+                    (Some(new_low), Some(new_high)) => {
+                        nodes.push(BddNode::pack(var, NodeId(*new_low), NodeId(*new_high)));
+                        translation.insert(top.0, (nodes.len() + 1) as u64);
+                        stack.pop();
+                    }
+                    (new_low, new_high) => {
+                        if new_high.is_none() {
+                            stack.push(high);
+                        }
+                        if new_low.is_none() {
+                            stack.push(low);
+                        }
+                    }
+                }
+            }
+        }
+        // Same as in `Bdd` files, terminals use the variable count as their variable.
+        let variable_count = self.context.variable_count;
+        write!(f, "{},0,0|{},1,1|", variable_count, variable_count)?;
+        for node in nodes {
+            let (var, low, high) = node.unpack();
+            write!(f, "{},{},{}|", u16::from(var), low.0, high.0)?;
+        }
+        for (name, handle) in &self.roots {
+            write!(f, "\n{} {}", translation[&handle.0 .0], name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Deserialization of the format produced by `Forest::fmt`.
+impl TryFrom<&str> for Forest {
+    type Error = String;
+
+    fn try_from(data: &str) -> Result<Self, Self::Error> {
+        let mut lines = data.lines();
+        let nodes = lines.next().unwrap_or("");
+        if nodes.split('|').all(|s| s.is_empty()) {
+            return Err(String::from("A forest must contain the terminal nodes."));
+        }
+        let nodes = Bdd::try_from(nodes)?.nodes;
+        let variable_count = u16::from(nodes[0].variable());
+        let is_terminal = |index: usize| {
+            let (_, low, high) = nodes[index].unpack();
+            low.0 == index as u64 && high.0 == index as u64
+        };
+        if nodes.len() < 2 || !is_terminal(0) || !is_terminal(1) {
+            return Err(String::from("The first two nodes must be the terminals."));
+        }
+        if u16::from(nodes[1].variable()) != variable_count {
+            return Err(String::from("Terminal nodes must have the same variable."));
+        }
+
+        let mut forest = Forest::new();
+        forest.context.variable_count = variable_count;
+        // Maps node ids of the input to node ids in the context.
+        let mut translation = vec![NodeId::ZERO, NodeId::ONE];
+        for (index, node) in nodes.iter().enumerate().skip(2) {
+            let (var, low, high) = node.unpack();
+            for link in [low, high] {
+                let is_valid = link.as_index() < index
+                    && u16::from(var) < u16::from(nodes[link.as_index()].variable());
+                if !is_valid {
+                    return Err(format!("Node {} has an invalid link {}.", index, link.0));
+                }
+            }
+            let (low, high) = (translation[low.as_index()], translation[high.as_index()]);
+            translation.push(if low == high {
+                low
+            } else {
+                forest.context.ensure(BddNode::pack(var, low, high))
+            });
+        }
+
+        for line in lines.filter(|line| !line.is_empty()) {
+            let (id, name) = line.split_once(' ').unwrap_or((line, ""));
+            let handle = match id.parse::<usize>() {
+                Ok(id) if id < translation.len() => PooledHandle(translation[id]),
+                _ => return Err(format!("Invalid root `{}`.", line)),
+            };
+            if forest.roots.contains_key(name) {
+                return Err(format!("Duplicate root name `{}`.", name));
+            }
+            forest.set_root(name, handle);
+        }
+        Ok(forest)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::_impl_::test_util::variables;
+    use crate::v2::{ApplyContext, Bdd, Forest, VariableId};
+    use core::convert::TryFrom;
+
+    #[test]
+    pub fn forest_test() {
+        let x = variables(4);
+        let sum = x[0].xor(&x[1]).xor(&x[2]);
+        let carry = x[0].and(&x[1]).or(&x[2].and(&x[0].xor(&x[1])));
+
+        let mut forest = Forest::new();
+        forest.insert("sum", &sum).unwrap();
+        forest.insert("carry", &carry).unwrap();
+        forest.insert("b", &x[1]).unwrap();
+        forest.insert("false", &Bdd::new_false()).unwrap();
+        assert!(forest.insert("a\nb", &sum).is_err());
+        assert_eq!(4, forest.len());
+        assert_eq!(
+            vec!["b", "carry", "false", "sum"],
+            forest.names().collect::<Vec<_>>()
+        );
+        assert!(forest.export("sum").unwrap().semantic_eq(&sum));
+        assert!(forest.export("none").is_none());
+
+        // Roots are indexed by their top variable.
+        let zero = VariableId::from(0u16);
+        let top: Vec<&str> = forest.roots_with_top_variable(zero).collect();
+        assert_eq!(vec!["carry", "sum"], top);
+        assert!(forest.top_variable("b").unwrap() == VariableId::from(1u16));
+        let constants: Vec<&str> = forest
+            .roots_with_top_variable(VariableId::UNDEFINED)
+            .collect();
+        assert_eq!(vec!["false"], constants);
+
+        // Bulk operations with one operand.
+        let not_x0 = x[0].imp(&Bdd::new_false());
+        forest.apply_all(&not_x0, ApplyContext::and);
+        assert!(forest
+            .export("sum")
+            .unwrap()
+            .semantic_eq(&sum.and_not(&x[0])));
+        assert!(forest
+            .export("b")
+            .unwrap()
+            .semantic_eq(&x[1].and_not(&x[0])));
+        let top: Vec<&str> = forest.roots_with_top_variable(zero).collect();
+        assert_eq!(vec!["b", "carry", "sum"], top);
+        assert_eq!(Some(forest.get("carry").unwrap()), forest.remove("carry"));
+        assert!(forest.top_variable("carry").is_none());
+        let context = forest.context_mut();
+        let (b, sum_handle) = (context.import(&x[1]), context.import(&sum));
+        let either = context.or(b, sum_handle);
+        forest.insert_handle("either", either).unwrap();
+
+        // Serialization round trip.
+        let data = forest.to_string();
+        let copy = Forest::try_from(data.as_str()).unwrap();
+        assert_eq!(data, copy.to_string());
+        for name in forest.names() {
+            let expected = forest.export(name).unwrap();
+            assert!(copy.export(name).unwrap().semantic_eq(&expected));
+        }
+        assert!(Forest::try_from("").is_err());
+        assert!(Forest::try_from("4,0,0|4,1,1|\n2 a").is_err());
+        assert!(Forest::try_from("4,0,0|4,1,1|1,0,1|1,2,1|\n3 a").is_err());
+        assert!(Forest::try_from("4,0,0|4,1,1|\n1 a\n0 a").is_err());
+    }
+}
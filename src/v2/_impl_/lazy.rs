@@ -0,0 +1,176 @@
+use crate::v2::{ApplyContext, Bdd, LazyBdd, LazyStep, PooledHandle};
+use alloc::vec;
+use alloc::vec::Vec;
+
+impl Bdd {
+    /// Start a lazily evaluated expression with this `Bdd` as the first operand.
+    ///
+    /// For example, `a.lazy().and(b).or(c).and_not(d).evaluate()` computes the same `Bdd`
+    /// as `a.and(b).or(c).and_not(d)`, but the intermediate results are not materialized
+    /// as standalone `Bdds` (see `LazyBdd`).
+    pub fn lazy(&self) -> LazyBdd<'_> {
+        LazyBdd::from(self)
+    }
+}
+
+impl<'a> From<&'a Bdd> for LazyBdd<'a> {
+    fn from(value: &'a Bdd) -> Self {
+        LazyBdd {
+            operands: vec![value],
+            steps: vec![LazyStep::Operand(0)],
+        }
+    }
+}
+
+impl<'a> LazyBdd<'a> {
+    /// A lazy logical conjunction. The `other` operand can be a `&Bdd` or a `LazyBdd`.
+    pub fn and<T: Into<LazyBdd<'a>>>(self, other: T) -> LazyBdd<'a> {
+        self.push(other.into(), LazyStep::And)
+    }
+
+    /// A lazy logical disjunction. The `other` operand can be a `&Bdd` or a `LazyBdd`.
+    pub fn or<T: Into<LazyBdd<'a>>>(self, other: T) -> LazyBdd<'a> {
+        self.push(other.into(), LazyStep::Or)
+    }
+
+    /// A lazy logical implication. The `other` operand can be a `&Bdd` or a `LazyBdd`.
+    pub fn imp<T: Into<LazyBdd<'a>>>(self, other: T) -> LazyBdd<'a> {
+        self.push(other.into(), LazyStep::Imp)
+    }
+
+    /// A lazy logical equivalence. The `other` operand can be a `&Bdd` or a `LazyBdd`.
+    pub fn iff<T: Into<LazyBdd<'a>>>(self, other: T) -> LazyBdd<'a> {
+        self.push(other.into(), LazyStep::Iff)
+    }
+
+    /// A lazy logical exclusive disjunction. The `other` operand can be a `&Bdd`
+    /// or a `LazyBdd`.
+    pub fn xor<T: Into<LazyBdd<'a>>>(self, other: T) -> LazyBdd<'a> {
+        self.push(other.into(), LazyStep::Xor)
+    }
+
+    /// A lazy logical conjunction with a negated second argument. The `other` operand can be
+    /// a `&Bdd` or a `LazyBdd`.
+    pub fn and_not<T: Into<LazyBdd<'a>>>(self, other: T) -> LazyBdd<'a> {
+        self.push(other.into(), LazyStep::AndNot)
+    }
+
+    /// The number of distinct operands of this expression. Operands which are the same
+    /// object, or which have the same nodes, are only counted once.
+    pub fn operand_count(&self) -> usize {
+        self.operands.len()
+    }
+
+    /// The number of binary operations in this expression.
+    pub fn operation_count(&self) -> usize {
+        self.steps.len() - self.operands_in_steps()
+    }
+
+    /// Evaluate the expression.
+    ///
+    /// All operations run in one `ApplyContext`, so the results of common sub-problems
+    /// (including repeated sub-expressions) are computed only once, and only the final
+    /// result is copied into a standalone `Bdd` (sorted bottom-up).
+    pub fn evaluate(&self) -> Bdd {
+        if self.steps.len() == 1 {
+            return self.operands[0].clone();
+        }
+        let mut context = ApplyContext::new();
+        // Operands are imported when they are first used.
+        let mut imported: Vec<Option<PooledHandle>> = vec![None; self.operands.len()];
+        let mut stack: Vec<PooledHandle> = Vec::new();
+        for step in &self.steps {
+            let result = if let LazyStep::Operand(index) = *step {
+                let operand = self.operands[index];
+                *imported[index].get_or_insert_with(|| context.import(operand))
+            } else {
+                // Every operation step is preceded by both of its arguments.
+                let right = stack.pop().unwrap();
+                let left = stack.pop().unwrap();
+                match step {
+                    LazyStep::And => context.and(left, right),
+                    LazyStep::Or => context.or(left, right),
+                    LazyStep::Imp => context.imp(left, right),
+                    LazyStep::Iff => context.iff(left, right),
+                    LazyStep::Xor => context.xor(left, right),
+                    LazyStep::AndNot => context.and_not(left, right),
+                    LazyStep::Operand(_) => unreachable!(),
+                }
+            };
+            stack.push(result);
+        }
+        debug_assert_eq!(1, stack.len());
+        context.export(stack[0])
+    }
+
+    /// **(internal)** Append the `other` expression and the operation `step` combining
+    /// the two expressions.
+    fn push(mut self, other: LazyBdd<'a>, step: LazyStep) -> LazyBdd<'a> {
+        for other_step in other.steps {
+            let other_step = match other_step {
+                LazyStep::Operand(index) => {
+                    LazyStep::Operand(self.operand_index(other.operands[index]))
+                }
+                operation => operation,
+            };
+            self.steps.push(other_step);
+        }
+        self.steps.push(step);
+        self
+    }
+
+    /// **(internal)** The index of the given `operand`, which is added to the operands
+    /// if it is not there yet.
+    fn operand_index(&mut self, operand: &'a Bdd) -> usize {
+        let existing = self
+            .operands
+            .iter()
+            .position(|it| it.is_identical_to(operand));
+        existing.unwrap_or_else(|| {
+            self.operands.push(operand);
+            self.operands.len() - 1
+        })
+    }
+
+    /// **(internal)** The number of operand steps in the expression.
+    fn operands_in_steps(&self) -> usize {
+        let is_operand = |step: &&LazyStep| matches!(step, LazyStep::Operand(_));
+        self.steps.iter().filter(is_operand).count()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::_impl_::test_util::variables;
+
+    #[test]
+    pub fn lazy_evaluation_test() {
+        let x = variables(6);
+        let a = x[0].xor(&x[3]);
+        let b = x[1].or(&x[4]);
+        let c = x[2].iff(&x[5]);
+        let d = x[0].and(&x[5]);
+
+        let eager = a.and(&b).or(&c).and_not(&d);
+        let lazy = a.lazy().and(&b).or(&c).and_not(&d);
+        assert_eq!(4, lazy.operand_count());
+        assert_eq!(3, lazy.operation_count());
+        let result = lazy.evaluate();
+        assert_eq!(2, result.iff(&eager).node_count());
+        // The context result is fully reduced, the eager result is not necessarily.
+        assert!(result.node_count() <= eager.node_count());
+
+        // Nested expressions and repeated operands (including clones).
+        let a_copy = a.clone();
+        let nested = a.lazy().xor(b.lazy().imp(&a_copy)).iff(c.lazy().or(&b));
+        assert_eq!(3, nested.operand_count());
+        assert_eq!(4, nested.operation_count());
+        let expected = a.xor(&b.imp(&a)).iff(&c.or(&b));
+        assert_eq!(2, nested.evaluate().iff(&expected).node_count());
+
+        // Trivial expressions.
+        assert!(a.lazy().evaluate().nodes == a.nodes);
+        assert_eq!(1, a.lazy().xor(&a_copy).evaluate().node_count());
+        assert_eq!(2, a.lazy().imp(&a).evaluate().node_count());
+    }
+}
@@ -0,0 +1,146 @@
+use crate::packing;
+use crate::v2::{Bdd, LimitError, VariableId};
+use alloc::format;
+use alloc::string::String;
+use core::fmt::{Display, Formatter};
+
+// `VariableId::UNDEFINED` is the variable of the terminal nodes, so it must be larger than
+// every decision variable, and a `Bdd` of the largest decision variable has exactly
+// `MAX_VARIABLE_COUNT` variables.
+const _: () = assert!(VariableId::UNDEFINED.0 == u16::MAX);
+const _: () = assert!(VariableId::MAX.0 as u32 + 1 == Bdd::MAX_VARIABLE_COUNT as u32);
+
+// The packed low link has 48 bits and `NodeId::UNDEFINED` is packed as the largest 48-bit
+// id, so the largest valid id is `MAX_NODE_COUNT - 1`.
+const _: () = assert!(Bdd::MAX_NODE_COUNT == packing::ID_MASK);
+const _: () = assert!(packing::id(u64::MAX) == Bdd::MAX_NODE_COUNT);
+
+impl VariableId {
+    /// The largest variable which can be used in a `Bdd`.
+    pub const MAX: VariableId = VariableId(u16::MAX - 1);
+}
+
+impl Bdd {
+    /// The largest number of variables of a `Bdd` (the variables `0..=VariableId::MAX`).
+    pub const MAX_VARIABLE_COUNT: u16 = u16::MAX;
+
+    /// The largest number of nodes of a `Bdd` (including terminals). Node ids are stored
+    /// in 48 bits and the largest 48-bit id is reserved for `NodeId::UNDEFINED`.
+    ///
+    /// An operation which would create more nodes panics.
+    pub const MAX_NODE_COUNT: u64 = (1 << 48) - 1;
+
+    /// **(internal)** Check that a `Bdd` (or a node storage) can have `node_count` nodes.
+    pub(crate) fn check_node_count(node_count: u64) -> Result<(), LimitError> {
+        if node_count > Bdd::MAX_NODE_COUNT {
+            Err(LimitError::TooManyNodes(node_count))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// **(internal)** Panic if a `Bdd` (or a node storage) with `node_count` nodes cannot
+    /// get another node. The check runs in every build, because a larger id would be
+    /// silently truncated when the node is packed.
+    #[inline]
+    pub(crate) fn check_next_node(node_count: usize) {
+        if node_count as u64 >= Bdd::MAX_NODE_COUNT {
+            too_many_nodes(node_count as u64 + 1);
+        }
+    }
+}
+
+/// **(internal)** The slow path of `Bdd::check_next_node`.
+#[cold]
+#[inline(never)]
+fn too_many_nodes(node_count: u64) -> ! {
+    panic!("{}", LimitError::TooManyNodes(node_count))
+}
+
+impl Display for LimitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            LimitError::ReservedVariable => write!(
+                f,
+                "Variable {} is reserved for terminal nodes.",
+                VariableId::UNDEFINED.0
+            ),
+            LimitError::TooManyNodes(count) => write!(
+                f,
+                "Node count {} exceeds the maximum of {} nodes.",
+                count,
+                Bdd::MAX_NODE_COUNT
+            ),
+        }
+    }
+}
+
+impl From<LimitError> for String {
+    fn from(value: LimitError) -> Self {
+        format!("{}", value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::{ApplyContext, Bdd, BddNode, BddView, LimitError, NodeId, VariableId};
+
+    #[test]
+    pub fn variable_limits_test() {
+        // The largest variable works in all operations.
+        let max = Bdd::new_variable(VariableId::MAX);
+        assert_eq!(Bdd::MAX_VARIABLE_COUNT, max.variable_count());
+        let first = Bdd::new_variable(VariableId::from(0u16));
+        let second_last = Bdd::new_variable(VariableId::from(u16::MAX - 2));
+        let both = first.and(&max);
+        assert_eq!(4, both.node_count());
+        assert_eq!(Bdd::MAX_VARIABLE_COUNT, both.variable_count());
+        let chain = both.xor(&second_last).or(&max.iff(&first));
+        let not_max = max.imp(&Bdd::new_false());
+        assert!(chain
+            .and(&not_max)
+            .semantic_eq(&first.imp(&second_last).and(&not_max)));
+        let mut context = ApplyContext::new();
+        let handle = context.import(&chain);
+        assert!(context.export(handle).semantic_eq(&chain));
+        assert_eq!(Bdd::MAX_VARIABLE_COUNT, context.variable_count());
+
+        // The terminal variable is rejected.
+        let reserved = Bdd::try_new_variable(VariableId::UNDEFINED);
+        assert_eq!(Some(LimitError::ReservedVariable), reserved.err());
+        let message = String::from(LimitError::ReservedVariable);
+        assert_eq!("Variable 65535 is reserved for terminal nodes.", message);
+        let reserved_node = BddNode::pack(VariableId::UNDEFINED, NodeId::ZERO, NodeId::ONE);
+        let nodes = [BddNode::ZERO, BddNode::ONE, reserved_node];
+        assert!(BddView::try_from_nodes(&nodes).is_err());
+    }
+
+    #[test]
+    pub fn node_limits_test() {
+        assert!(Bdd::check_node_count(0).is_ok());
+        assert!(Bdd::check_node_count(Bdd::MAX_NODE_COUNT).is_ok());
+        let over = Bdd::MAX_NODE_COUNT + 1;
+        assert_eq!(
+            Err(LimitError::TooManyNodes(over)),
+            Bdd::check_node_count(over)
+        );
+        assert!(Bdd::check_node_count(u64::MAX).is_err());
+        Bdd::check_next_node(0);
+        Bdd::check_next_node((Bdd::MAX_NODE_COUNT - 1) as usize);
+
+        // The largest valid id survives packing, `UNDEFINED` becomes the reserved id.
+        let largest = NodeId(Bdd::MAX_NODE_COUNT - 1);
+        let node = BddNode::pack(VariableId::MAX, largest, largest);
+        assert!(node.low_link() == largest && node.high_link() == largest);
+        assert!(node.variable() == VariableId::MAX);
+        let undefined = BddNode::pack(VariableId::MAX, NodeId::UNDEFINED, NodeId::ZERO);
+        assert_eq!(Bdd::MAX_NODE_COUNT, undefined.low_link().0);
+        assert!(undefined.variable() == VariableId::MAX);
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn node_limit_overflow_test() {
+        Bdd::check_next_node(Bdd::MAX_NODE_COUNT as usize);
+    }
+}
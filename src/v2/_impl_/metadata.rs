@@ -0,0 +1,390 @@
+use crate::v2::CowChunks;
+use crate::v2::{LimitError, SymbolId, SymbolTable, VariableId, VariableInfo, VariableMetadata};
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt::{Display, Formatter};
+
+impl SymbolTable {
+    /// Create an empty table.
+    pub fn new() -> SymbolTable {
+        SymbolTable::default()
+    }
+
+    /// The number of strings in this table.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// True if this table has no strings.
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// The identifier of the given `string`, adding it to the table if necessary.
+    pub fn intern(&mut self, string: &str) -> SymbolId {
+        if let Some(id) = self.get(string) {
+            return id;
+        }
+        let id = u32::try_from(self.strings.len()).expect("Too many symbols.");
+        let string: Arc<str> = Arc::from(string);
+        self.strings.push(string.clone());
+        self.ids.insert(string, SymbolId(id));
+        SymbolId(id)
+    }
+
+    /// The identifier of the given `string`, if it is in the table.
+    pub fn get(&self, string: &str) -> Option<SymbolId> {
+        self.ids.get(string).cloned()
+    }
+
+    /// The string with the given identifier, if it is in the table.
+    pub fn resolve(&self, id: SymbolId) -> Option<&str> {
+        self.strings.get(id.0 as usize).map(|string| &**string)
+    }
+}
+
+impl VariableMetadata {
+    /// Create metadata where no variable has a name, description or group.
+    pub fn new() -> VariableMetadata {
+        VariableMetadata {
+            symbols: SymbolTable::new(),
+            variables: CowChunks::new(),
+            by_name: BTreeMap::new(),
+        }
+    }
+
+    /// The table of all interned strings.
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    /// The variables which have a name, description or group (in increasing order).
+    pub fn variables(&self) -> impl Iterator<Item = VariableId> + '_ {
+        (0..self.variables.len())
+            .filter(move |i| self.variables.get(*i) != VariableInfo::default())
+            .map(|i| VariableId(i as u16))
+    }
+
+    /// The name of the given `variable`.
+    pub fn name(&self, variable: VariableId) -> Option<&str> {
+        self.resolve(self.info(variable).name)
+    }
+
+    /// The description of the given `variable`.
+    pub fn description(&self, variable: VariableId) -> Option<&str> {
+        self.resolve(self.info(variable).description)
+    }
+
+    /// The group of the given `variable`.
+    pub fn group(&self, variable: VariableId) -> Option<&str> {
+        self.resolve(self.info(variable).group)
+    }
+
+    /// The variable with the given `name`.
+    pub fn find(&self, name: &str) -> Option<VariableId> {
+        let id = self.symbols.get(name)?;
+        self.by_name.get(&id).cloned()
+    }
+
+    /// All variables in the given `group` (in increasing order).
+    pub fn group_members(&self, group: &str) -> Vec<VariableId> {
+        match self.symbols.get(group) {
+            None => Vec::new(),
+            Some(id) => self
+                .variables()
+                .filter(|v| self.info(*v).group == Some(id))
+                .collect(),
+        }
+    }
+
+    /// Set the name of the `variable`, replacing its previous name. Names must be unique
+    /// and (like all metadata strings) cannot contain line breaks.
+    pub fn set_name(&mut self, variable: VariableId, name: &str) -> Result<(), String> {
+        check_variable(variable)?;
+        check_string(name)?;
+        let id = self.symbols.intern(name);
+        match self.by_name.get(&id) {
+            Some(other) if *other == variable => return Ok(()),
+            Some(other) => {
+                return Err(format!(
+                    "Name `{}` is already used by variable {}.",
+                    name, other.0
+                ))
+            }
+            None => (),
+        }
+        if let Some(previous) = self.info(variable).name {
+            self.by_name.remove(&previous);
+        }
+        self.by_name.insert(id, variable);
+        self.update(variable, |info| info.name = Some(id));
+        Ok(())
+    }
+
+    /// Set the description of the `variable`, replacing its previous description.
+    pub fn set_description(
+        &mut self,
+        variable: VariableId,
+        description: &str,
+    ) -> Result<(), String> {
+        check_variable(variable)?;
+        check_string(description)?;
+        let id = self.symbols.intern(description);
+        self.update(variable, |info| info.description = Some(id));
+        Ok(())
+    }
+
+    /// Set the group of the `variable`, replacing its previous group.
+    pub fn set_group(&mut self, variable: VariableId, group: &str) -> Result<(), String> {
+        check_variable(variable)?;
+        check_string(group)?;
+        let id = self.symbols.intern(group);
+        self.update(variable, |info| info.group = Some(id));
+        Ok(())
+    }
+
+    /// Remove the name, description and group of the `variable`.
+    ///
+    /// The strings stay in the `SymbolTable`, since they can be used by other snapshots.
+    pub fn clear(&mut self, variable: VariableId) {
+        if let Some(name) = self.info(variable).name {
+            self.by_name.remove(&name);
+        }
+        if usize::from(variable.0) < self.variables.len() {
+            self.variables
+                .set(usize::from(variable.0), VariableInfo::default());
+        }
+    }
+
+    /// The variables whose name, description or group differ between `self` and `other`
+    /// (in increasing order).
+    ///
+    /// The snapshots are compared by their strings, so they do not need to share a history.
+    /// However, parts of the metadata which are shared with `other` (i.e. were not changed
+    /// since one was cloned from the other) are skipped without comparing any strings.
+    pub fn diff(&self, other: &VariableMetadata) -> Vec<VariableId> {
+        let mut indices: Vec<usize> = self
+            .variables
+            .unshared_ranges(&other.variables)
+            .chain(other.variables.unshared_ranges(&self.variables))
+            .flatten()
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+            .into_iter()
+            .map(|i| VariableId(i as u16))
+            .filter(|v| self.strings_of(*v) != other.strings_of(*v))
+            .collect()
+    }
+
+    /// **(internal)** The metadata of the `variable` (empty for variables without metadata).
+    fn info(&self, variable: VariableId) -> VariableInfo {
+        let index = usize::from(variable.0);
+        if index < self.variables.len() {
+            self.variables.get(index)
+        } else {
+            VariableInfo::default()
+        }
+    }
+
+    /// **(internal)** The name, description and group of the `variable`.
+    fn strings_of(&self, variable: VariableId) -> [Option<&str>; 3] {
+        let info = self.info(variable);
+        [
+            self.resolve(info.name),
+            self.resolve(info.description),
+            self.resolve(info.group),
+        ]
+    }
+
+    /// **(internal)** Modify the metadata of the `variable`, creating empty entries
+    /// up to the `variable` if necessary.
+    fn update<F: FnOnce(&mut VariableInfo)>(&mut self, variable: VariableId, action: F) {
+        let index = usize::from(variable.0);
+        while self.variables.len() <= index {
+            self.variables.push(VariableInfo::default());
+        }
+        let mut info = self.variables.get(index);
+        action(&mut info);
+        self.variables.set(index, info);
+    }
+
+    /// **(internal)** The string of an optional identifier.
+    fn resolve(&self, id: Option<SymbolId>) -> Option<&str> {
+        id.and_then(|id| self.symbols.resolve(id))
+    }
+}
+
+impl Default for VariableMetadata {
+    fn default() -> Self {
+        VariableMetadata::new()
+    }
+}
+
+/// **(internal)** Metadata strings are stored one per line, so they cannot contain
+/// line breaks.
+fn check_string(string: &str) -> Result<(), String> {
+    if string.contains('\n') || string.contains('\r') {
+        Err(format!(
+            "Invalid metadata string `{}`.",
+            string.escape_debug()
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn check_variable(variable: VariableId) -> Result<(), String> {
+    if variable == VariableId::UNDEFINED {
+        Err(LimitError::ReservedVariable.into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Serialization of the metadata: one `s <string>` line for every interned string (in the
+/// order of their identifiers), followed by one `v <variable> <name> <description> <group>`
+/// line for every variable with metadata. The last three items are string identifiers,
+/// or `-` if the item is missing. Every string is therefore written only once.
+impl Display for VariableMetadata {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        for string in &self.symbols.strings {
+            writeln!(f, "s {}", string)?;
+        }
+        let id = |id: Option<SymbolId>| match id {
+            Some(id) => format!("{}", id.0),
+            None => String::from("-"),
+        };
+        for variable in self.variables() {
+            let info = self.info(variable);
+            writeln!(
+                f,
+                "v {} {} {} {}",
+                variable.0,
+                id(info.name),
+                id(info.description),
+                id(info.group)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<&str> for VariableMetadata {
+    type Error = String;
+
+    fn try_from(data: &str) -> Result<Self, Self::Error> {
+        let mut result = VariableMetadata::new();
+        let mut entries = Vec::new();
+        for line in data.lines().filter(|line| !line.is_empty()) {
+            if let Some(string) = line.strip_prefix("s ") {
+                // Interning all strings first preserves their identifiers.
+                if result.symbols.get(string).is_some() {
+                    return Err(format!("Duplicate string `{}`.", string));
+                }
+                result.symbols.intern(string);
+            } else {
+                entries.push(line);
+            }
+        }
+        for line in entries {
+            let items: Vec<&str> = line.split(' ').collect();
+            if items.len() != 5 || items[0] != "v" {
+                return Err(format!("Unexpected metadata line `{}`.", line));
+            }
+            let variable = items[1]
+                .parse::<u16>()
+                .map_err(|_| format!("Invalid variable `{}`.", items[1]))?;
+            let variable = VariableId(variable);
+            if result.info(variable) != VariableInfo::default() {
+                return Err(format!("Duplicate metadata of variable {}.", variable.0));
+            }
+            let mut strings: [Option<String>; 3] = [None, None, None];
+            for (item, string) in items[2..].iter().zip(strings.iter_mut()) {
+                if *item != "-" {
+                    let id = item.parse::<u32>().ok().map(SymbolId);
+                    let resolved = id.and_then(|id| result.symbols.resolve(id));
+                    let resolved =
+                        resolved.ok_or_else(|| format!("Invalid string identifier `{}`.", item))?;
+                    *string = Some(String::from(resolved));
+                }
+            }
+            let [name, description, group] = strings;
+            if let Some(name) = name {
+                result.set_name(variable, &name)?;
+            }
+            if let Some(description) = description {
+                result.set_description(variable, &description)?;
+            }
+            if let Some(group) = group {
+                result.set_group(variable, &group)?;
+            }
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::{Forest, VariableId, VariableMetadata};
+    use core::convert::TryFrom;
+
+    #[test]
+    pub fn metadata_test() {
+        let v = VariableId::from;
+        let mut metadata = VariableMetadata::new();
+        metadata.set_name(v(0), "x").unwrap();
+        metadata.set_name(v(2), "y").unwrap();
+        metadata.set_group(v(0), "inputs").unwrap();
+        metadata.set_group(v(2), "inputs").unwrap();
+        metadata.set_description(v(2), "the second input").unwrap();
+        // Names are unique, strings are interned.
+        assert!(metadata.set_name(v(1), "x").is_err());
+        assert!(metadata.set_name(v(1), "a\nb").is_err());
+        assert!(metadata.set_name(VariableId::UNDEFINED, "z").is_err());
+        assert_eq!(4, metadata.symbols().len());
+        assert_eq!(Some("y"), metadata.name(v(2)));
+        assert_eq!(None, metadata.name(v(1)));
+        assert!(metadata.find("y") == Some(v(2)));
+        assert!(metadata.group_members("inputs") == vec![v(0), v(2)]);
+
+        // Clones share the strings, and the unchanged parts are skipped by `diff`.
+        let snapshot = metadata.clone();
+        assert!(core::ptr::eq(
+            metadata.name(v(0)).unwrap(),
+            snapshot.name(v(0)).unwrap()
+        ));
+        assert!(metadata.diff(&snapshot).is_empty());
+        metadata.set_name(v(0), "z").unwrap();
+        metadata.clear(v(2));
+        metadata.set_group(v(5000), "inputs").unwrap();
+        assert!(metadata.find("x").is_none() && metadata.find("y").is_none());
+        assert!(metadata.diff(&snapshot) == vec![v(0), v(2), v(5000)]);
+        assert!(snapshot.diff(&metadata) == vec![v(0), v(2), v(5000)]);
+        assert_eq!(Some("x"), snapshot.name(v(0)));
+
+        // Serialization preserves the strings and their identifiers.
+        let text = metadata.to_string();
+        assert_eq!(1, text.matches("s inputs\n").count());
+        let copy = VariableMetadata::try_from(text.as_str()).unwrap();
+        assert_eq!(text, copy.to_string());
+        assert!(copy.diff(&metadata).is_empty());
+        assert!(VariableMetadata::try_from("s x\nv 0 1 - -").is_err());
+        assert!(VariableMetadata::try_from("s x\nv 0 0 - -\nv 1 0 - -").is_err());
+        assert!(VariableMetadata::try_from("v 0 - -").is_err());
+
+        // Snapshots of a forest share its metadata.
+        let mut forest = Forest::new();
+        *forest.metadata_mut() = metadata;
+        let forest_snapshot = forest.clone();
+        assert!(forest
+            .metadata()
+            .diff(forest_snapshot.metadata())
+            .is_empty());
+    }
+}
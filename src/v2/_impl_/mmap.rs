@@ -0,0 +1,193 @@
+use crate::v2::_impl_::storage::check_nodes;
+use crate::v2::{Bdd, BddNode, BddStorage, MmapBddStorage, NodeId};
+use alloc::format;
+use alloc::string::String;
+use core::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// **(internal)** The size of one node in a `MmapBddStorage` file.
+const NODE_BYTES: usize = 16;
+
+impl MmapBddStorage {
+    /// Map the `Bdd` file at the given `path` into memory and check that it contains
+    /// a valid `Bdd` (see `BddView::try_from_nodes`). The check reads the whole file once.
+    ///
+    /// The nodes do not have to be sorted bottom-up, so the file can also use the DFS
+    /// preorder of `Bdd::sort_preorder`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<MmapBddStorage, String> {
+        let io_error = |e: std::io::Error| format!("{}", e);
+        let file = File::open(path).map_err(io_error)?;
+        // The file must not be modified while mapped, which is a documented requirement
+        // of `MmapBddStorage`.
+        let map = unsafe { memmap2::Mmap::map(&file) }.map_err(io_error)?;
+        if map.len() % NODE_BYTES != 0 {
+            return Err(format!(
+                "File size {} is not a multiple of the node size.",
+                map.len()
+            ));
+        }
+        let variable_count = check_nodes(map.len() / NODE_BYTES, |index| read_node(&map, index))?;
+        Ok(MmapBddStorage {
+            variable_count,
+            map,
+        })
+    }
+
+    /// Write the nodes of the `bdd` into a new file at the given `path`, and map it into
+    /// memory.
+    pub fn create<S, P>(bdd: &S, path: P) -> Result<MmapBddStorage, String>
+    where
+        S: BddStorage + ?Sized,
+        P: AsRef<Path>,
+    {
+        let mut writer = NodeWriter::create(path.as_ref())?;
+        for index in 0..bdd.node_count() {
+            // The indices are smaller than the node count.
+            let node = unsafe { bdd.get_node_unchecked(NodeId(index as u64)) };
+            writer.write(node);
+        }
+        writer.finish()?;
+        MmapBddStorage::open(path)
+    }
+}
+
+// The nodes are validated when the file is opened (and the file must not be modified
+// while it is mapped, see `MmapBddStorage::open`).
+unsafe impl BddStorage for MmapBddStorage {
+    fn variable_count(&self) -> u16 {
+        self.variable_count
+    }
+
+    #[inline]
+    fn node_count(&self) -> usize {
+        self.map.len() / NODE_BYTES
+    }
+
+    #[inline]
+    unsafe fn get_node_unchecked(&self, id: NodeId) -> BddNode {
+        debug_assert!(id.as_index() < self.node_count());
+        read_node(&self.map, unsafe { id.as_index_unchecked() })
+    }
+
+    #[inline]
+    fn prefetch(&self, id: NodeId) {
+        // Prefetch operations ignore memory errors and are therefore "externally safe".
+        let offset = (id.0 as usize).wrapping_mul(NODE_BYTES);
+        crate::arch::prefetch(self.map.as_ptr().wrapping_add(offset));
+    }
+
+    fn to_bdd(&self) -> Bdd {
+        let mut result = if self.node_count() == 1 {
+            Bdd::new_false()
+        } else {
+            Bdd::true_with_capacity(self.node_count())
+        };
+        result.update_variable_count(self.variable_count);
+        for index in 2..self.node_count() {
+            result.push_node(read_node(&self.map, index));
+        }
+        result
+    }
+}
+
+/// **(internal)** A buffered writer of a `MmapBddStorage` file.
+///
+/// The first I/O error is remembered and reported by `NodeWriter::finish`, so the writer
+/// can be used in a node sink which cannot fail.
+pub(crate) struct NodeWriter {
+    writer: BufWriter<File>,
+    error: Option<std::io::Error>,
+}
+
+impl NodeWriter {
+    pub(crate) fn create(path: &Path) -> Result<NodeWriter, String> {
+        let file = File::create(path).map_err(|e| format!("{}", e))?;
+        Ok(NodeWriter {
+            writer: BufWriter::new(file),
+            error: None,
+        })
+    }
+
+    /// Append the `node` to the file.
+    #[inline]
+    pub(crate) fn write(&mut self, node: BddNode) {
+        if self.error.is_none() {
+            let mut bytes = [0u8; NODE_BYTES];
+            bytes[..8].copy_from_slice(&node.0.to_le_bytes());
+            bytes[8..].copy_from_slice(&node.1.to_le_bytes());
+            if let Err(e) = self.writer.write_all(&bytes) {
+                self.error = Some(e);
+            }
+        }
+    }
+
+    /// Flush the written nodes, or return the first error.
+    pub(crate) fn finish(mut self) -> Result<(), String> {
+        match self.error.take() {
+            Some(e) => Err(format!("{}", e)),
+            None => self.writer.flush().map_err(|e| format!("{}", e)),
+        }
+    }
+}
+
+/// **(internal)** Decode the node at the given `index` of a `MmapBddStorage` file.
+#[inline]
+fn read_node(bytes: &[u8], index: usize) -> BddNode {
+    let offset = index * NODE_BYTES;
+    let word = |start: usize| {
+        let slice = &bytes[(offset + start)..(offset + start + 8)];
+        u64::from_le_bytes(<[u8; 8]>::try_from(slice).unwrap())
+    };
+    BddNode(word(0), word(8))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::_impl_::test_util::variables;
+    use crate::v2::{Bdd, BddStorage, MmapBddStorage};
+
+    #[test]
+    pub fn mmap_storage_test() {
+        let x = variables(4);
+        let bdd = x[0].and(&x[1]).or(&x[2].xor(&x[3]));
+        let path = std::env::temp_dir().join("mmap_storage_test.bin");
+        let storage = MmapBddStorage::create(&bdd, &path).unwrap();
+        assert_eq!(bdd.node_count(), storage.node_count());
+        assert_eq!(bdd.variable_count(), BddStorage::variable_count(&storage));
+        assert!(bdd.node_slice() == storage.to_bdd().node_slice());
+        let reopened = MmapBddStorage::open(&path).unwrap();
+        assert!(bdd.node_slice() == reopened.to_bdd().node_slice());
+        // The file must not be modified while it is mapped.
+        drop((storage, reopened));
+
+        let ff = MmapBddStorage::create(&Bdd::new_false(), &path).unwrap();
+        assert_eq!(1, ff.node_count());
+        drop(ff);
+
+        // Truncated and invalid files are rejected.
+        std::fs::write(&path, [0u8; 20]).unwrap();
+        assert!(MmapBddStorage::open(&path).is_err());
+        std::fs::write(&path, [0u8; 32]).unwrap();
+        assert!(MmapBddStorage::open(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+        assert!(MmapBddStorage::open(&path).is_err());
+    }
+
+    #[test]
+    pub fn mmap_sorted_round_trip_test() {
+        let x = variables(6);
+        let bdd = x[0].and(&x[3]).or(&x[1].and(&x[4])).or(&x[2].and(&x[5]));
+        let mut sorted = bdd.clone();
+        sorted.sort_preorder();
+        let path = std::env::temp_dir().join("mmap_sorted_round_trip_test.bin");
+        let storage = MmapBddStorage::create(&sorted, &path).unwrap();
+        assert!(sorted.node_slice() == storage.to_bdd().node_slice());
+        let reopened = MmapBddStorage::open(&path).unwrap();
+        assert!(sorted.node_slice() == reopened.to_bdd().node_slice());
+        assert_eq!(1, reopened.to_bdd().xor(&bdd).node_count());
+        drop((storage, reopened));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1 +1,44 @@
 pub mod bdd;
+/// Implementation of the shared `BddPool` storage.
+#[cfg(feature = "std")]
+mod pool;
+/// Implementation of the thread-safe `SyncBddPool`.
+#[cfg(feature = "std")]
+mod sync_pool;
+/// Implementation of the `ApplyContext` and its snapshots.
+mod context;
+/// Quantification and support of `Bdds` stored in an `ApplyContext`.
+mod quantification;
+/// Implementation of `Delta` image computation in an `ApplyContext`.
+mod delta;
+/// Implementation of `BddVec` arithmetic in an `ApplyContext`.
+mod bdd_vec;
+/// Implementation of `PartitionedBdd` and partitioned binary operations.
+mod partitioned;
+/// Implementation of lazily evaluated `LazyBdd` expressions.
+mod lazy;
+/// Implementation of the `Forest` of named `Bdds`.
+mod forest;
+/// Implementation of `SymbolTable` and `VariableMetadata`.
+mod metadata;
+/// Implementation of the `BddVariableSet` front-end.
+mod variable_set;
+/// Implementation of `BitMatrix` and `BitVec`.
+mod bits;
+/// Limits of the `Bdd` representation and `LimitError`.
+mod limits;
+/// Implementation of `BddStorage` and `BddView`.
+pub(crate) mod storage;
+/// Allocation of large buffers according to `BddAllocConfig`.
+pub(crate) mod alloc_config;
+/// Implementation of `MmapBddStorage`.
+#[cfg(feature = "mmap")]
+mod mmap;
+/// Implementation of the `Profiler` of binary operations.
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+pub(crate) mod profiler;
+/// Implementation of the copy-on-write storage used by `ApplyContext`.
+mod cow_chunks;
+/// **(internal)** Fixtures shared by the tests of the `v2` implementation.
+#[cfg(test)]
+pub(crate) mod test_util;
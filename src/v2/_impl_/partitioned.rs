@@ -0,0 +1,205 @@
+use crate::v2::{Bdd, BddNode, NodeId, PartitionedBdd, VariableId};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::{max, min};
+
+impl Bdd {
+    /// Same as `Bdd::binary_operation`, but the result is computed as a `PartitionedBdd`.
+    ///
+    /// While the worst-case size of the result (the product of operand sizes) exceeds
+    /// `max_nodes`, the operands are split by their top variable and the two cofactors are
+    /// processed independently. Each part is therefore computed in its own node table that
+    /// never grows beyond `max_nodes`. Use `1 << 48` to only split operations which could
+    /// overflow the 48-bit pointer space, or a smaller value to bound the memory of a single
+    /// part.
+    pub fn partitioned_operation<TABLE>(
+        &self,
+        other: &Bdd,
+        table: TABLE,
+        max_nodes: usize,
+    ) -> PartitionedBdd
+    where
+        TABLE: Fn(NodeId, NodeId) -> NodeId,
+    {
+        let mut result = PartitionedBdd {
+            variable_count: max(self.variable_count, other.variable_count),
+            parts: Vec::new(),
+        };
+        let mut cube = Vec::new();
+        partition(self, other, &table, max_nodes, &mut cube, &mut result.parts);
+        result
+    }
+}
+
+impl PartitionedBdd {
+    /// The `(cube, bdd)` pairs of this partitioned `Bdd`.
+    pub fn parts(&self) -> &[(Bdd, Bdd)] {
+        &self.parts
+    }
+
+    /// The number of stored parts.
+    pub fn part_count(&self) -> usize {
+        self.parts.len()
+    }
+
+    /// The total number of nodes in all parts (excluding the cubes).
+    pub fn node_count(&self) -> usize {
+        self.parts.iter().map(|(_, bdd)| bdd.node_count()).sum()
+    }
+
+    /// Recombine all parts into a single `Bdd`.
+    pub fn to_bdd(&self) -> Bdd {
+        let mut result = Bdd::new_false();
+        result.update_variable_count(self.variable_count);
+        for (cube, bdd) in &self.parts {
+            result = result.or(&cube.and(bdd));
+        }
+        result
+    }
+}
+
+/// **(internal)** Compute the parts of `left table right` restricted to the given `cube`
+/// (a list of literals with increasing variables) and append them to `parts`.
+fn partition<TABLE>(
+    left: &Bdd,
+    right: &Bdd,
+    table: &TABLE,
+    max_nodes: usize,
+    cube: &mut Vec<(VariableId, bool)>,
+    parts: &mut Vec<(Bdd, Bdd)>,
+) where
+    TABLE: Fn(NodeId, NodeId) -> NodeId,
+{
+    let left_root = left.root_node();
+    let right_root = right.root_node();
+    let is_terminal = |id: NodeId| id.is_zero() || id.is_one();
+    let worst_case = left.node_count().saturating_mul(right.node_count());
+    if worst_case <= max_nodes || is_terminal(left_root) || is_terminal(right_root) {
+        let result = left.binary_operation(right, table);
+        if result.node_count() > 1 {
+            parts.push((cube_bdd(cube), result));
+        }
+        return;
+    }
+
+    let variable = min(left.get_variable(left_root), right.get_variable(right_root));
+    for value in [false, true] {
+        cube.push((variable, value));
+        let left_cofactor = cofactor(left, variable, value);
+        let right_cofactor = cofactor(right, variable, value);
+        partition(
+            &left_cofactor,
+            &right_cofactor,
+            table,
+            max_nodes,
+            cube,
+            parts,
+        );
+        cube.pop();
+    }
+}
+
+/// **(internal)** Restrict `bdd` to `variable = value`, assuming that `variable` is not
+/// below the root variable of `bdd`.
+fn cofactor(bdd: &Bdd, variable: VariableId, value: bool) -> Bdd {
+    let root = bdd.root_node();
+    if bdd.get_variable(root) != variable {
+        return bdd.clone();
+    }
+    let node = bdd.get_node(root);
+    let child = if value {
+        node.high_link()
+    } else {
+        node.low_link()
+    };
+    extract(bdd, child)
+}
+
+/// **(internal)** Copy the sub-graph of `bdd` which is reachable from the given `root`.
+fn extract(bdd: &Bdd, root: NodeId) -> Bdd {
+    if root.is_zero() {
+        let mut result = Bdd::new_false();
+        result.update_variable_count(bdd.variable_count);
+        return result;
+    }
+    let mut result = Bdd::true_with_capacity(2);
+    result.update_variable_count(bdd.variable_count);
+    if root.is_one() {
+        return result;
+    }
+    // New ids of the already copied nodes (`NodeId::UNDEFINED` if not copied yet).
+    let mut new_id = vec![NodeId::UNDEFINED; bdd.node_count()];
+    new_id[0] = NodeId::ZERO;
+    new_id[1] = NodeId::ONE;
+    let mut stack = vec![root];
+    while let Some(&top) = stack.last() {
+        let (variable, low, high) = bdd.get_node(top).unpack();
+        if !new_id[top.as_index()].is_undefined() {
+            stack.pop();
+        } else if new_id[low.as_index()].is_undefined() {
+            stack.push(low);
+        } else if new_id[high.as_index()].is_undefined() {
+            stack.push(high);
+        } else {
+            let node = BddNode::pack(variable, new_id[low.as_index()], new_id[high.as_index()]);
+            new_id[top.as_index()] = result.push_node(node);
+            stack.pop();
+        }
+    }
+    result
+}
+
+/// **(internal)** Create a conjunction of the given literals (with increasing variables).
+fn cube_bdd(literals: &[(VariableId, bool)]) -> Bdd {
+    let mut result = Bdd::true_with_capacity(literals.len() + 2);
+    let mut last = NodeId::ONE;
+    for (variable, value) in literals.iter().rev() {
+        let node = if *value {
+            BddNode::pack(*variable, NodeId::ZERO, last)
+        } else {
+            BddNode::pack(*variable, last, NodeId::ZERO)
+        };
+        last = result.push_node(node);
+        result.update_variable_count(variable.0 + 1);
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::_impl_::test_util::{and_table, variables};
+    use crate::v2::Bdd;
+
+    #[test]
+    pub fn partitioned_operation_test() {
+        let x = variables(5);
+        let left = x[0].iff(&x[2]).or(&x[1].and(&x[4]));
+        let right = x[1].xor(&x[3]).or(&x[0].and_not(&x[4]));
+        let expected = left.and(&right);
+
+        // Small operations are not split.
+        let whole = left.partitioned_operation(&right, and_table, usize::MAX);
+        assert_eq!(1, whole.part_count());
+        assert_eq!(expected.node_count(), whole.node_count());
+
+        // Forcing the split creates disjoint parts that recombine into the same function.
+        let split = left.partitioned_operation(&right, and_table, 1);
+        assert!(split.part_count() > 1);
+        for (cube, bdd) in split.parts() {
+            assert!(bdd.node_count() > 1);
+            assert!(bdd.node_count() <= expected.node_count());
+            assert!(cube.node_count() > 2);
+        }
+        for (i, (a, _)) in split.parts().iter().enumerate() {
+            for (b, _) in &split.parts()[(i + 1)..] {
+                assert_eq!(1, a.and(b).node_count());
+            }
+        }
+        assert_eq!(2, split.to_bdd().iff(&expected).node_count());
+
+        // Unsatisfiable parts are dropped.
+        let contradiction = left.partitioned_operation(&left.imp(&Bdd::new_false()), and_table, 1);
+        assert_eq!(0, contradiction.part_count());
+        assert_eq!(1, contradiction.to_bdd().node_count());
+    }
+}
@@ -0,0 +1,803 @@
+use crate::v2::{
+    Bdd, BddNode, BddPool, BddStorage, FrozenBdd, LimitError, NodeId, PoolUsage, PoolWatermark,
+    PooledHandle, VariableId, Watermark,
+};
+use core::cmp::max;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+impl BddPool {
+    /// Create a new pool which only contains the two terminal nodes.
+    pub fn new() -> BddPool {
+        let mut unique_table = HashMap::new();
+        unique_table.insert(BddNode::ZERO, NodeId::ZERO);
+        unique_table.insert(BddNode::ONE, NodeId::ONE);
+        BddPool {
+            variable_count: 0,
+            nodes: Arc::new(vec![BddNode::ZERO, BddNode::ONE]),
+            unique_table,
+            far_links: 0,
+            auto_sort_threshold: None,
+            epoch: 0,
+            literal_count: 0,
+            peak_nodes: 2,
+            created_nodes: 2,
+            watermarks: Vec::new(),
+        }
+    }
+
+    /// Create a new pool with a reserved prefix of the node table which contains
+    /// the positive and negative literal of every variable in `0..variable_count`
+    /// (at ids `2 + 2 * v` and `3 + 2 * v`).
+    ///
+    /// The literals are then available in constant time, without a lookup in the unique
+    /// table (see `BddPool::literal`). The prefix is never removed or renumbered
+    /// by compaction, so literal handles are valid in every epoch.
+    pub fn with_literals(variable_count: u16) -> BddPool {
+        let mut pool = BddPool::new();
+        pool.variable_count = variable_count;
+        for v in 0..variable_count {
+            let var = VariableId(v);
+            pool.ensure(BddNode::pack(var, NodeId::ZERO, NodeId::ONE));
+            pool.ensure(BddNode::pack(var, NodeId::ONE, NodeId::ZERO));
+        }
+        pool.literal_count = variable_count;
+        pool.peak_nodes = pool.nodes.len();
+        pool
+    }
+
+    /// A handle to the positive (`x_v`) or negative (`!x_v`) literal of the given `variable`.
+    ///
+    /// For variables in the reserved prefix (see `BddPool::with_literals`), this is
+    /// a constant-time operation. Other literals are created in the pool as usual.
+    ///
+    /// Panics if the variable is `VariableId::UNDEFINED`.
+    pub fn literal(&mut self, variable: VariableId, positive: bool) -> PooledHandle {
+        if variable.0 < self.literal_count {
+            let id = 2 + 2 * u64::from(variable.0) + u64::from(!positive);
+            return PooledHandle(NodeId(id));
+        }
+        if variable == VariableId::UNDEFINED {
+            panic!("{}", LimitError::ReservedVariable);
+        }
+        self.variable_count = max(self.variable_count, variable.0 + 1);
+        let (low, high) = if positive {
+            (NodeId::ZERO, NodeId::ONE)
+        } else {
+            (NodeId::ONE, NodeId::ZERO)
+        };
+        let handle = PooledHandle(self.ensure(BddNode::pack(variable, low, high)));
+        self.check_watermarks();
+        handle
+    }
+
+    /// The number of (unique) nodes stored in this pool, including terminals.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// The largest variable count of all the `Bdds` interned into this pool.
+    pub fn variable_count(&self) -> u16 {
+        self.variable_count
+    }
+
+    /// The current epoch of the pool. It starts at zero and increases every time the pool
+    /// is compacted (i.e. the nodes are renumbered, see `BddPool::sort_preorder`).
+    ///
+    /// All handles (and node ids) obtained within one epoch stay valid until the epoch
+    /// changes. Code which stores handles outside of the pool should also store the epoch
+    /// and check it using `BddPool::is_current_epoch` before the handles are used.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// True if the given `epoch` is the current epoch of this pool, i.e. handles obtained
+    /// in that epoch are still valid.
+    pub fn is_current_epoch(&self, epoch: u64) -> bool {
+        self.epoch == epoch
+    }
+
+    /// A measure of how far the layout of the pool has drifted from DFS preorder: the fraction
+    /// of decision nodes whose low successor is a decision node, but not the directly
+    /// preceding one.
+    ///
+    /// In preorder, the low successor of a node usually follows right after it in memory,
+    /// so the value is low after `BddPool::sort_preorder`. Interning new `Bdds` appends their
+    /// nodes bottom-up, which increases the drift. The value is maintained incrementally,
+    /// so this is a constant-time operation.
+    pub fn layout_drift(&self) -> f64 {
+        let decision_nodes = self.nodes.len() - 2;
+        if decision_nodes == 0 {
+            0.0
+        } else {
+            self.far_links as f64 / decision_nodes as f64
+        }
+    }
+
+    /// The current node usage of the pool, including the high watermark of live nodes.
+    pub fn usage(&self) -> PoolUsage {
+        PoolUsage {
+            live_nodes: self.nodes.len(),
+            peak_nodes: self.peak_nodes,
+            created_nodes: self.created_nodes,
+        }
+    }
+
+    /// Call `callback` when the node usage of the pool reaches the given `watermark`.
+    ///
+    /// The watermarks are checked after every `BddPool::intern`, i.e. the callback gives
+    /// an early warning about the growth of the pool, but it cannot interrupt the operation.
+    /// A typical callback sets a flag which tells the owner of the pool to compact it
+    /// (see `BddPool::sort_preorder`), or to abort the computation.
+    pub fn add_watermark<F>(&mut self, watermark: Watermark, callback: F)
+    where
+        F: FnMut(&PoolUsage) + Send + 'static,
+    {
+        self.watermarks.push(PoolWatermark {
+            watermark,
+            armed: true,
+            callback: Box::new(callback),
+        });
+        self.check_watermarks();
+    }
+
+    /// Remove all callbacks registered using `BddPool::add_watermark`.
+    pub fn clear_watermarks(&mut self) {
+        self.watermarks.clear();
+    }
+
+    /// Enable (`Some`) or disable (`None`) automatic sorting in `BddPool::maintain` once
+    /// the `layout_drift` exceeds the given threshold. Disabled by default.
+    pub fn set_auto_sort_threshold(&mut self, threshold: Option<f64>) {
+        self.auto_sort_threshold = threshold;
+    }
+
+    /// An idle point of the pool: if automatic sorting is enabled and the layout drifted
+    /// too far from preorder, the pool is sorted (see `BddPool::sort_preorder`). Returns
+    /// `true` if the pool was sorted.
+    ///
+    /// Sorting changes the ids of all nodes, so the `handles` must contain every handle
+    /// that is still in use. They are updated in place; all other handles become invalid
+    /// (the `BddPool::epoch` changes).
+    pub fn maintain(&mut self, handles: &mut [PooledHandle]) -> bool {
+        match self.auto_sort_threshold {
+            Some(threshold) if self.layout_drift() > threshold => {
+                self.sort_preorder(handles);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Sort the nodes of the pool in DFS preorder (same as `Bdd::sort_preorder`), starting
+    /// with the roots given by `handles`. Only the nodes reachable from the `handles` are
+    /// kept (together with the reserved literals, see `BddPool::with_literals`), so this
+    /// also serves as garbage collection.
+    ///
+    /// The `handles` are updated in place; all other handles become invalid and the pool
+    /// starts a new `BddPool::epoch`.
+    pub fn sort_preorder(&mut self, handles: &mut [PooledHandle]) {
+        // Find the reachable nodes in preorder. The nodes first reached from each root form
+        // a block which gets decreasing ids, such that the root ends up last in its block.
+        // Nodes shared with an earlier root are in an earlier block, so every root has
+        // a higher id than all nodes reachable from it (see `FrozenBdd::node_slice`). This
+        // does not hold for the other nodes: a shared node which is reached (through another
+        // parent) before one of its parents gets a higher id than that parent.
+        let mut preorder = Vec::new();
+        let mut visited = self.reserved_flags();
+        let mut stack = Vec::new();
+        for handle in handles.iter() {
+            let block_start = preorder.len();
+            stack.push(handle.0);
+            while let Some(top) = stack.pop() {
+                if visited[top.as_index()] {
+                    continue;
+                }
+                visited[top.as_index()] = true;
+                preorder.push(top);
+                let node = self.nodes[top.as_index()];
+                stack.push(node.high_link());
+                stack.push(node.low_link());
+            }
+            preorder[block_start..].reverse();
+        }
+        self.renumber(&preorder, handles);
+    }
+
+    /// Remove all nodes which are not reachable from the roots given by `handles` (except
+    /// for the reserved literals, see `BddPool::with_literals`).
+    ///
+    /// Unlike `BddPool::sort_preorder`, the remaining nodes keep their relative order, so
+    /// the layout of the pool does not change. The `handles` are updated in place; all other
+    /// handles become invalid and the pool starts a new `BddPool::epoch`.
+    pub fn collect_garbage(&mut self, handles: &mut [PooledHandle]) {
+        let mut reachable = self.reserved_flags();
+        let mut stack: Vec<NodeId> = handles.iter().map(|handle| handle.0).collect();
+        while let Some(top) = stack.pop() {
+            if reachable[top.as_index()] {
+                continue;
+            }
+            reachable[top.as_index()] = true;
+            let node = self.nodes[top.as_index()];
+            stack.push(node.high_link());
+            stack.push(node.low_link());
+        }
+        let prefix = self.reserved_prefix();
+        let order: Vec<NodeId> = (prefix..self.nodes.len())
+            .filter(|index| reachable[*index])
+            .map(|index| NodeId(index as u64))
+            .collect();
+        self.renumber(&order, handles);
+    }
+
+    /// **(internal)** The number of nodes which always keep their ids: the terminals and
+    /// the reserved literals.
+    fn reserved_prefix(&self) -> usize {
+        2 + 2 * usize::from(self.literal_count)
+    }
+
+    /// **(internal)** One flag for every node of the pool, set only for the reserved prefix.
+    fn reserved_flags(&self) -> Vec<bool> {
+        let mut flags = vec![false; self.nodes.len()];
+        for flag in &mut flags[..self.reserved_prefix()] {
+            *flag = true;
+        }
+        flags
+    }
+
+    /// **(internal)** Keep only the reserved prefix and the nodes of the given `order`, which
+    /// get consecutive ids after the prefix. The `handles` are updated in place and
+    /// the pool starts a new `BddPool::epoch`.
+    fn renumber(&mut self, order: &[NodeId], handles: &mut [PooledHandle]) {
+        self.epoch += 1;
+        let prefix = self.reserved_prefix();
+        let mut new_id = vec![NodeId::UNDEFINED; self.nodes.len()];
+        for (index, id) in new_id[..prefix].iter_mut().enumerate() {
+            *id = NodeId(index as u64);
+        }
+        for (position, old_id) in order.iter().enumerate() {
+            new_id[old_id.as_index()] = NodeId((prefix + position) as u64);
+        }
+
+        // Copy the nodes and rebuild the unique table.
+        let mut nodes = Vec::with_capacity(prefix + order.len());
+        nodes.extend_from_slice(&self.nodes[..prefix]);
+        for old_id in order {
+            let (var, low, high) = self.nodes[old_id.as_index()].unpack();
+            nodes.push(BddNode::pack(var, new_id[low.as_index()], new_id[high.as_index()]));
+        }
+        self.unique_table.clear();
+        for (index, node) in nodes.iter().enumerate() {
+            self.unique_table.insert(*node, NodeId(index as u64));
+        }
+        self.far_links = nodes
+            .iter()
+            .enumerate()
+            .skip(2)
+            .filter(|(index, node)| is_far_link(node.low_link(), *index))
+            .count();
+        self.nodes = Arc::new(nodes);
+        for handle in handles.iter_mut() {
+            handle.0 = new_id[handle.0.as_index()];
+        }
+        self.check_watermarks();
+    }
+
+    /// Merge a standalone `Bdd` into this pool, sharing every node that is structurally
+    /// identical to an existing node. Returns a handle to the root of the interned `Bdd`.
+    ///
+    /// Interning the same `Bdd` (or a `Bdd` with the same structure) repeatedly always
+    /// returns the same handle and does not allocate new nodes.
+    pub fn intern(&mut self, bdd: &Bdd) -> PooledHandle {
+        self.variable_count = max(self.variable_count, bdd.variable_count());
+        if bdd.node_count() <= 2 {
+            // Constants map directly to the terminal nodes.
+            return PooledHandle(bdd.root_node());
+        }
+
+        // Maps node ids of `bdd` to node ids in this pool.
+        let mut translation = vec![NodeId::UNDEFINED; bdd.node_count()];
+        translation[0] = NodeId::ZERO;
+        translation[1] = NodeId::ONE;
+
+        if is_bottom_up(bdd) {
+            // Fast path: Nodes created by `apply` already have successors before
+            // their parents, so no search is needed.
+            self.ensure_all(bdd, 2..bdd.node_count(), &mut translation);
+        } else {
+            let order = postorder(bdd);
+            self.ensure_all(bdd, order.into_iter(), &mut translation);
+        }
+        self.check_watermarks();
+
+        PooledHandle(translation[bdd.root_node().as_index()])
+    }
+
+    /// Create a standalone copy of the `Bdd` referenced by the given `handle`.
+    ///
+    /// The result only contains the nodes reachable from the `handle` and it is sorted
+    /// bottom-up (i.e. in the same way as a result of `apply`).
+    pub fn export(&self, handle: PooledHandle) -> Bdd {
+        export(&self.nodes, self.variable_count, handle.0)
+    }
+
+    /// Create a read-only snapshot of the `Bdd` referenced by the given `handle`.
+    ///
+    /// The snapshot is created in constant time: it shares the nodes of the pool, which
+    /// are copied before the pool is modified again (i.e. the first `intern` or
+    /// `sort_preorder` after `freeze` copies the node storage once). The nodes of the
+    /// snapshot are therefore never removed or renumbered by the pool.
+    pub fn freeze(&self, handle: PooledHandle) -> FrozenBdd {
+        FrozenBdd {
+            variable_count: self.variable_count,
+            nodes: self.nodes.clone(),
+            root: handle.0,
+        }
+    }
+
+    /// **(internal)** Update the high watermark and call the callbacks of all
+    /// the watermarks which were reached. Watermarks of live nodes which are no
+    /// longer reached (after compaction) are armed again.
+    fn check_watermarks(&mut self) {
+        self.peak_nodes = max(self.peak_nodes, self.nodes.len());
+        let usage = self.usage();
+        for watermark in self.watermarks.iter_mut() {
+            let reached = match watermark.watermark {
+                Watermark::LiveNodes(threshold) => usage.live_nodes >= threshold,
+                Watermark::CreatedNodes(threshold) => usage.created_nodes >= threshold,
+            };
+            if reached && watermark.armed {
+                watermark.armed = false;
+                (watermark.callback)(&usage);
+            } else if !reached {
+                watermark.armed = true;
+            }
+        }
+    }
+
+    /// **(internal)** The bulk variant of `ensure` used by `intern`. The nodes of the `bdd`
+    /// are processed in the given `order` (successors must be processed before their
+    /// parents) and their pool ids are saved into `translation`.
+    fn ensure_all<I>(&mut self, bdd: &Bdd, order: I, translation: &mut [NodeId])
+    where
+        I: Iterator<Item = usize>,
+    {
+        // Reserve enough space for the worst case, such that the tables are not
+        // repeatedly resized during the merge.
+        Arc::make_mut(&mut self.nodes).reserve(bdd.node_count());
+        self.unique_table.reserve(bdd.node_count());
+        for index in order {
+            let (var, low, high) = bdd.nodes[index].unpack();
+            let low = translation[low.as_index()];
+            let high = translation[high.as_index()];
+            debug_assert!(!low.is_undefined() && !high.is_undefined());
+            translation[index] = self.ensure(BddNode::pack(var, low, high));
+        }
+    }
+
+    /// **(internal)** Ensure that the pool contains the given node (with links that point
+    /// into this pool). Returns the id of the existing or created node.
+    fn ensure(&mut self, node: BddNode) -> NodeId {
+        let nodes = Arc::make_mut(&mut self.nodes);
+        let far_links = &mut self.far_links;
+        let created_nodes = &mut self.created_nodes;
+        *self.unique_table.entry(node).or_insert_with(|| {
+            if is_far_link(node.low_link(), nodes.len()) {
+                *far_links += 1;
+            }
+            *created_nodes += 1;
+            nodes.push(node);
+            NodeId((nodes.len() - 1) as u64)
+        })
+    }
+}
+
+/// **(internal)** Copy the `Bdd` with the given `root` out of the shared `nodes` (see
+/// `BddPool::export`).
+pub(super) fn export(nodes: &[BddNode], variable_count: u16, root: NodeId) -> Bdd {
+    if root.is_zero() {
+        let mut result = Bdd::new_false();
+        result.update_variable_count(variable_count);
+        return result;
+    }
+
+    // Maps node ids of this pool to node ids in the result.
+    let mut translation: HashMap<NodeId, NodeId> = HashMap::new();
+    translation.insert(NodeId::ZERO, NodeId::ZERO);
+    translation.insert(NodeId::ONE, NodeId::ONE);
+    let mut result = Bdd::true_with_capacity(2);
+    result.update_variable_count(variable_count);
+
+    let mut stack = vec![root];
+    while let Some(top) = stack.last().cloned() {
+        if translation.contains_key(&top) {
+            stack.pop();
+            continue;
+        }
+        let (var, low, high) = nodes[top.as_index()].unpack();
+        match (translation.get(&low), translation.get(&high)) {
+            (Some(new_low), Some(new_high)) => {
+                let new_id = result.push_node(BddNode::pack(var, *new_low, *new_high));
+                translation.insert(top, new_id);
+                stack.pop();
+            }
+            (new_low, new_high) => {
+                if new_high.is_none() {
+                    stack.push(high);
+                }
+                if new_low.is_none() {
+                    stack.push(low);
+                }
+            }
+        }
+    }
+
+    result
+}
+
+impl FrozenBdd {
+    /// The root node of the snapshot.
+    pub fn root_node(&self) -> NodeId {
+        self.root
+    }
+
+    /// The nodes of the snapshot: the root is the last node and all nodes reachable from it
+    /// have smaller ids. The slice can also contain nodes which are not reachable from the root.
+    pub fn node_slice(&self) -> &[BddNode] {
+        &self.nodes[..=self.root.as_index()]
+    }
+}
+
+// The pool only contains valid nodes and all nodes reachable from the root have
+// smaller ids (see `FrozenBdd::node_slice`). The other nodes of the slice were interned
+// before the root, or belong to an earlier `BddPool::sort_preorder` block, so their links
+// also stay inside the slice. The snapshot nodes are never modified.
+unsafe impl BddStorage for FrozenBdd {
+    fn variable_count(&self) -> u16 {
+        self.variable_count
+    }
+
+    #[inline]
+    fn node_count(&self) -> usize {
+        self.root.as_index() + 1
+    }
+
+    #[inline]
+    unsafe fn get_node_unchecked(&self, id: NodeId) -> BddNode {
+        debug_assert!(id.as_index() <= self.root.as_index());
+        unsafe { *self.nodes.get_unchecked(id.as_index_unchecked()) }
+    }
+
+    #[inline]
+    fn prefetch(&self, id: NodeId) {
+        // Prefetch operations ignore memory errors and are therefore "externally safe".
+        crate::arch::prefetch(self.nodes.as_ptr().wrapping_add(id.0 as usize));
+    }
+
+    /// Copy the nodes reachable from the root into a standalone `Bdd` (see `BddPool::export`).
+    fn to_bdd(&self) -> Bdd {
+        export(&self.nodes, self.variable_count, self.root)
+    }
+}
+
+impl Default for BddPool {
+    fn default() -> Self {
+        BddPool::new()
+    }
+}
+
+/// **(internal)** True if the `link` of a node with the given `index` counts towards
+/// the `BddPool::layout_drift`. Links to terminal nodes are always cheap to follow.
+fn is_far_link(link: NodeId, index: usize) -> bool {
+    link.as_index() > 1 && link.as_index() + 1 != index
+}
+
+/// **(internal)** True if every link in the `bdd` points to a node with a smaller id.
+fn is_bottom_up(bdd: &Bdd) -> bool {
+    bdd.nodes.iter().enumerate().skip(2).all(|(index, node)| {
+        node.low_link().as_index() < index && node.high_link().as_index() < index
+    })
+}
+
+/// **(internal)** Indices of the non-terminal nodes of `bdd` which are reachable from
+/// the root, in DFS postorder.
+fn postorder(bdd: &Bdd) -> Vec<usize> {
+    let mut order = Vec::with_capacity(bdd.node_count());
+    let mut visited = vec![false; bdd.node_count()];
+    visited[0] = true;
+    visited[1] = true;
+    let mut stack: Vec<(NodeId, bool)> = vec![(bdd.root_node(), false)];
+    while let Some((top, expanded)) = stack.pop() {
+        if expanded {
+            order.push(top.as_index());
+        } else if !visited[top.as_index()] {
+            visited[top.as_index()] = true;
+            let node = bdd.nodes[top.as_index()];
+            stack.push((top, true));
+            stack.push((node.high_link(), false));
+            stack.push((node.low_link(), false));
+        }
+    }
+    order
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::_impl_::test_util::{and_table, variables};
+    use crate::v2::{Bdd, BddPool, VariableId};
+
+    #[test]
+    pub fn pool_intern_test() {
+        let x0 = Bdd::new_variable(VariableId::from(0));
+        let x1 = Bdd::new_variable(VariableId::from(1));
+        let and = x0.and(&x1);
+        let or = x0.or(&x1);
+
+        let mut pool = BddPool::new();
+        let and_handle = pool.intern(&and);
+        assert_eq!(4, pool.node_count());
+        // The `x1` node is shared, only the root is new.
+        let or_handle = pool.intern(&or);
+        assert_eq!(5, pool.node_count());
+        assert_ne!(and_handle, or_handle);
+
+        // Interning a structurally identical `Bdd` does not create new nodes,
+        // even if it is not sorted bottom-up.
+        let mut sorted_and = and.clone();
+        sorted_and.sort_preorder();
+        assert_eq!(and_handle, pool.intern(&sorted_and));
+        assert_eq!(5, pool.node_count());
+
+        assert_eq!(1, pool.export(and_handle).xor(&and).node_count());
+        assert_eq!(1, pool.export(or_handle).xor(&or).node_count());
+        assert_eq!(or.node_count(), pool.export(or_handle).node_count());
+        let false_handle = pool.intern(&Bdd::new_false());
+        assert_eq!(1, pool.export(false_handle).node_count());
+    }
+
+    #[test]
+    pub fn pool_preorder_maintenance_test() {
+        let x = variables(6);
+        let a = x[0].and(&x[3]).or(&x[1].and(&x[4])).or(&x[2].and(&x[5]));
+        let b = a.xor(&x[1]);
+
+        let mut pool = BddPool::new();
+        let mut handles = [pool.intern(&a), pool.intern(&b)];
+        let _garbage = pool.intern(&x[2].iff(&x[5]));
+        let drift = pool.layout_drift();
+        assert!(drift > 0.0);
+
+        // Automatic sorting is disabled by default.
+        assert!(!pool.maintain(&mut handles));
+        pool.set_auto_sort_threshold(Some(drift / 2.0));
+        let node_count = pool.node_count();
+        assert!(pool.maintain(&mut handles));
+        assert!(pool.layout_drift() < drift);
+        // Unreachable nodes are removed, the remaining `Bdds` are unchanged.
+        assert!(pool.node_count() < node_count);
+        assert_eq!(1, pool.export(handles[0]).xor(&a).node_count());
+        assert_eq!(1, pool.export(handles[1]).xor(&b).node_count());
+        // The sorted layout is fine until more `Bdds` are interned.
+        assert!(!pool.maintain(&mut handles));
+        let node_count = pool.node_count();
+        assert_eq!(handles[0], pool.intern(&a));
+        assert_eq!(node_count, pool.node_count());
+    }
+
+    #[test]
+    pub fn pool_garbage_collection_test() {
+        let x = variables(6);
+        let a = x[0].and(&x[3]).or(&x[1].and(&x[4]));
+        let b = a.xor(&x[2]).or(&x[5]);
+
+        let mut pool = BddPool::with_literals(2);
+        let x0 = pool.literal(VariableId::from(0), true);
+        let first = pool.intern(&a);
+        let _garbage = pool.intern(&x[2].iff(&x[5]));
+        let mut handles = [first, pool.intern(&b)];
+        let node_count = pool.node_count();
+        let epoch = pool.epoch();
+
+        pool.collect_garbage(&mut handles);
+        assert!(pool.node_count() < node_count);
+        assert!(!pool.is_current_epoch(epoch));
+        // The first `Bdd` precedes the garbage, so it keeps its ids.
+        assert_eq!(first, handles[0]);
+        assert!(handles[0].0.as_index() < handles[1].0.as_index());
+        assert_eq!(1, pool.export(handles[0]).xor(&a).node_count());
+        assert_eq!(1, pool.export(handles[1]).xor(&b).node_count());
+        // The reserved literals are kept and nothing else is garbage now.
+        assert_eq!(x0, pool.literal(VariableId::from(0), true));
+        let node_count = pool.node_count();
+        pool.collect_garbage(&mut handles);
+        assert_eq!(node_count, pool.node_count());
+        assert_eq!(handles[1], pool.intern(&b));
+        assert_eq!(node_count, pool.node_count());
+    }
+
+    #[test]
+    pub fn pool_epoch_test() {
+        let x = variables(4);
+        let a = x[0].and(&x[2]).or(&x[1].and(&x[3]));
+
+        let mut pool = BddPool::new();
+        let _garbage = pool.intern(&x[1].xor(&x[2]));
+        let stored = (pool.epoch(), pool.intern(&a));
+        assert_eq!(0, stored.0);
+
+        // Handles survive any number of other operations within the epoch.
+        for i in 0..4 {
+            pool.intern(&a.xor(&x[i]));
+            pool.freeze(stored.1);
+            pool.export(stored.1);
+        }
+        assert!(pool.is_current_epoch(stored.0));
+        assert_eq!(stored.1, pool.intern(&a));
+        assert!(!pool.maintain(&mut []));
+        assert!(pool.is_current_epoch(stored.0));
+
+        // Compaction starts a new epoch, the stored reference can detect this.
+        let mut handles = [stored.1];
+        pool.sort_preorder(&mut handles);
+        assert_eq!(1, pool.epoch());
+        assert!(!pool.is_current_epoch(stored.0));
+        assert_eq!(2, pool.export(handles[0]).iff(&a).node_count());
+        pool.set_auto_sort_threshold(Some(0.0));
+        pool.intern(&x[0].xor(&x[3]));
+        assert!(pool.maintain(&mut handles));
+        assert_eq!(2, pool.epoch());
+    }
+
+    #[test]
+    pub fn pool_freeze_test() {
+        use crate::v2::BddStorage;
+
+        let x = variables(4);
+        let a = x[0].and(&x[2]).or(&x[1].xor(&x[3]));
+        let b = x[0].iff(&x[3]);
+
+        let mut pool = BddPool::new();
+        let _garbage = pool.intern(&x[1].and(&x[2]));
+        let mut handles = [pool.intern(&a)];
+        let frozen = pool.freeze(handles[0]);
+        let nodes = frozen.node_slice().to_vec();
+
+        // An analysis thread reads the snapshot while the pool keeps changing.
+        let analysis = {
+            let frozen = frozen.clone();
+            std::thread::spawn(move || frozen.to_bdd())
+        };
+        pool.intern(&b);
+        pool.sort_preorder(&mut handles);
+        pool.intern(&a.and(&b));
+        assert!(analysis.join().unwrap().nodes == pool.export(handles[0]).nodes);
+
+        // The snapshot is not affected by the changes (including the removed garbage).
+        assert!(frozen.node_slice() == nodes.as_slice());
+        assert_eq!(frozen.node_count(), frozen.root_node().as_index() + 1);
+        assert_eq!(2, frozen.to_bdd().iff(&a).node_count());
+        let result = Bdd::mixed_binary_operation(&frozen, &b, and_table);
+        assert_eq!(2, result.iff(&a.and(&b)).node_count());
+
+        let false_handle = pool.intern(&Bdd::new_false());
+        assert_eq!(1, pool.freeze(false_handle).to_bdd().node_count());
+    }
+
+    #[test]
+    pub fn pool_multi_root_freeze_test() {
+        use crate::v2::BddStorage;
+
+        let x = variables(6);
+        let a = x[0].and(&x[3]).or(&x[1].and(&x[4])).or(&x[2].and(&x[5]));
+        let b = x[0].xor(&x[2].and(&x[5]));
+        let c = x[1].and(&x[4]).or(&x[2].and(&x[5]));
+        let bdds = [a, b, c];
+
+        let mut pool = BddPool::new();
+        let _garbage = pool.intern(&x[1].iff(&x[5]));
+        let mut handles: Vec<_> = bdds.iter().map(|bdd| pool.intern(bdd)).collect();
+        pool.sort_preorder(&mut handles);
+
+        // Every snapshot contains the whole `Bdd` of its root, including the nodes
+        // shared with the other roots.
+        for (handle, bdd) in handles.iter().zip(bdds.iter()) {
+            let frozen = pool.freeze(*handle);
+            let nodes = frozen.node_slice();
+            for node in &nodes[2..] {
+                assert!(node.low_link().as_index() < nodes.len());
+                assert!(node.high_link().as_index() < nodes.len());
+            }
+            assert_eq!(2, frozen.to_bdd().iff(bdd).node_count());
+            let result = Bdd::mixed_binary_operation(&frozen, &x[3], and_table);
+            assert_eq!(2, result.iff(&pool.export(*handle).and(&x[3])).node_count());
+        }
+    }
+
+    #[test]
+    pub fn pool_watermark_test() {
+        use crate::v2::{PoolUsage, Watermark};
+        use std::sync::{Arc, Mutex};
+
+        let x = variables(4);
+        let a = x[0].and(&x[2]).or(&x[1].and(&x[3]));
+        let n = a.node_count();
+
+        let mut pool = BddPool::new();
+        let events: Arc<Mutex<Vec<(u8, PoolUsage)>>> = Arc::new(Mutex::new(Vec::new()));
+        let live_events = events.clone();
+        pool.add_watermark(Watermark::LiveNodes(n + 1), move |usage| {
+            live_events.lock().unwrap().push((0, *usage))
+        });
+        let created_events = events.clone();
+        pool.add_watermark(Watermark::CreatedNodes(n as u64 + 3), move |usage| {
+            created_events.lock().unwrap().push((1, *usage))
+        });
+
+        let mut handles = [pool.intern(&a)];
+        assert_eq!(n, pool.usage().live_nodes);
+        assert!(events.lock().unwrap().is_empty());
+
+        // Each watermark is only reported once.
+        pool.intern(&x[0].xor(&x[3]));
+        let usage = pool.usage();
+        assert_eq!(n + 2, usage.live_nodes);
+        assert_eq!(n + 2, usage.peak_nodes);
+        assert_eq!(vec![(0, usage)], *events.lock().unwrap());
+        pool.intern(&x[1].xor(&x[2]));
+        assert_eq!(2, events.lock().unwrap().len());
+        assert_eq!(1, events.lock().unwrap()[1].0);
+
+        // Compaction removes the garbage and arms the live watermark again, but the peak
+        // and the number of created nodes are kept.
+        pool.sort_preorder(&mut handles);
+        let usage = pool.usage();
+        assert_eq!(n, usage.live_nodes);
+        assert_eq!(n + 4, usage.peak_nodes);
+        assert_eq!(n as u64 + 4, usage.created_nodes);
+        pool.intern(&x[1].xor(&x[2]));
+        assert_eq!(3, events.lock().unwrap().len());
+        assert_eq!(0, events.lock().unwrap()[2].0);
+        assert_eq!(n as u64 + 6, pool.usage().created_nodes);
+
+        pool.clear_watermarks();
+        pool.intern(&x[0].or(&x[2]));
+        assert_eq!(3, events.lock().unwrap().len());
+    }
+
+    #[test]
+    pub fn pool_literal_prefix_test() {
+        let x = variables(4);
+        let mut pool = BddPool::with_literals(3);
+        assert_eq!(8, pool.node_count());
+        assert_eq!(3, pool.variable_count());
+
+        // Reserved literals are found without creating (or looking up) nodes.
+        let x1 = pool.literal(VariableId::from(1), true);
+        let not_x2 = pool.literal(VariableId::from(2), false);
+        assert_eq!(4, x1.0.as_index());
+        assert_eq!(7, not_x2.0.as_index());
+        assert_eq!(x1, pool.intern(&x[1]));
+        assert_eq!(not_x2, pool.intern(&x[2].xor(&x[3].imp(&x[3]))));
+        assert_eq!(8, pool.node_count());
+        // Literals outside of the prefix are created as usual.
+        let x3 = pool.literal(VariableId::from(3), true);
+        assert_eq!(x3, pool.intern(&x[3]));
+        assert_eq!(9, pool.node_count());
+        assert_eq!(4, pool.variable_count());
+
+        // Compaction keeps the prefix, even if it is not reachable.
+        let clause = x[0].or(&x[1]).or(&x[3]);
+        let mut handles = [pool.intern(&clause), x3];
+        let _garbage = pool.intern(&x[0].and(&x[2]));
+        pool.sort_preorder(&mut handles);
+        assert_eq!(x1, pool.literal(VariableId::from(1), true));
+        assert_eq!(not_x2, pool.literal(VariableId::from(2), false));
+        assert_eq!(2, pool.export(x1).iff(&x[1]).node_count());
+        assert_eq!(2, pool.export(handles[0]).iff(&clause).node_count());
+        assert_eq!(2, pool.export(handles[1]).iff(&x[3]).node_count());
+        assert_eq!(handles[1], pool.intern(&x[3]));
+        let node_count = pool.node_count();
+        assert_eq!(handles[0], pool.intern(&clause));
+        assert_eq!(node_count, pool.node_count());
+    }
+}
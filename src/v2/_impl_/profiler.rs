@@ -0,0 +1,198 @@
+use crate::v2::{ProfileEntry, ProfileWeight, Profiler};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::string::String;
+use std::time::Duration;
+use std::vec::Vec;
+
+thread_local! {
+    /// The profile which is currently recorded in this thread (see `Profiler::record`).
+    static ACTIVE: RefCell<Option<ActiveProfile>> = const { RefCell::new(None) };
+}
+
+/// **(internal)** The state of a running `Profiler`.
+struct ActiveProfile {
+    labels: Vec<String>,
+    entries: BTreeMap<String, ProfileEntry>,
+}
+
+impl Profiler {
+    /// Create a profiler without any recorded operations.
+    pub fn new() -> Profiler {
+        Profiler::default()
+    }
+
+    /// Run the `action` and record every binary operation that it performs in the current
+    /// thread. The statistics are added to the operations recorded previously.
+    ///
+    /// If another profiler is already recording in this thread, it is paused until
+    /// the `action` finishes.
+    pub fn record<T, F: FnOnce() -> T>(&mut self, action: F) -> T {
+        let active = ActiveProfile {
+            labels: Vec::new(),
+            entries: std::mem::take(&mut self.entries),
+        };
+        let previous = ACTIVE.with(|it| it.borrow_mut().replace(active));
+        // Restores the previous state even if the `action` panics.
+        let _recording = Recording {
+            profiler: self,
+            previous,
+        };
+        action()
+    }
+
+    /// Run the `action`, attributing its operations to the given `label` (nested within
+    /// the labels of the enclosing calls). Without a recording profiler, the `action`
+    /// simply runs.
+    ///
+    /// The characters `;` and line breaks are not allowed in the collapsed stack format,
+    /// so they are replaced with `_`.
+    pub fn label<T, F: FnOnce() -> T>(label: &str, action: F) -> T {
+        let label = label.replace([';', '\n', '\r'], "_");
+        let is_recording = ACTIVE.with(|it| match it.borrow_mut().as_mut() {
+            Some(active) => {
+                active.labels.push(label);
+                true
+            }
+            None => false,
+        });
+        if !is_recording {
+            return action();
+        }
+        let _scope = LabelScope;
+        action()
+    }
+
+    /// The statistics of all call sites, keyed by their collapsed stacks
+    /// (`label;...;operation`).
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &ProfileEntry)> {
+        self.entries
+            .iter()
+            .map(|(stack, entry)| (stack.as_str(), entry))
+    }
+
+    /// Remove all recorded statistics.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Export the statistics in the collapsed stack format (one `label;...;operation value`
+    /// line per call site), which is accepted by flame graph tools (e.g. `inferno` or
+    /// `flamegraph.pl`). The value is selected by the `weight`.
+    pub fn collapsed_stacks(&self, weight: ProfileWeight) -> String {
+        let mut output = String::new();
+        for (stack, entry) in &self.entries {
+            let value = match weight {
+                ProfileWeight::Time => entry.duration.as_micros() as u64,
+                ProfileWeight::Nodes => entry.nodes,
+            };
+            // Writing into a `String` never fails.
+            let _ = writeln!(output, "{} {}", stack, value);
+        }
+        output
+    }
+}
+
+/// **(internal)** Puts the previous profile back into the thread-local state and moves
+/// the recorded statistics back into the profiler.
+struct Recording<'a> {
+    profiler: &'a mut Profiler,
+    previous: Option<ActiveProfile>,
+}
+
+impl Drop for Recording<'_> {
+    fn drop(&mut self) {
+        let active =
+            ACTIVE.with(|it| std::mem::replace(&mut *it.borrow_mut(), self.previous.take()));
+        if let Some(active) = active {
+            self.profiler.entries = active.entries;
+        }
+    }
+}
+
+/// **(internal)** Leaves the innermost label of the active profile.
+struct LabelScope;
+
+impl Drop for LabelScope {
+    fn drop(&mut self) {
+        ACTIVE.with(|it| {
+            if let Some(active) = it.borrow_mut().as_mut() {
+                active.labels.pop();
+            }
+        });
+    }
+}
+
+/// **(internal)** True if a `Profiler` is recording in this thread.
+pub(crate) fn is_recording() -> bool {
+    ACTIVE.with(|it| it.borrow().is_some())
+}
+
+/// **(internal)** Attribute an `operation` which took `duration` and produced a result
+/// of `nodes` nodes to the current labels of the recording profiler (if any).
+pub(crate) fn report(operation: &str, duration: Duration, nodes: usize) {
+    ACTIVE.with(|it| {
+        if let Some(active) = it.borrow_mut().as_mut() {
+            let mut stack = active.labels.join(";");
+            if !stack.is_empty() {
+                stack.push(';');
+            }
+            stack.push_str(operation);
+            let entry = active.entries.entry(stack).or_default();
+            entry.calls += 1;
+            entry.duration += duration;
+            entry.nodes += nodes as u64;
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::_impl_::test_util::variables;
+    use crate::v2::{ProfileWeight, Profiler};
+
+    #[test]
+    pub fn profiler_test() {
+        let x = variables(4);
+        // Operations outside of `record` are not recorded.
+        let _ = Profiler::label("ignored", || x[0].and(&x[1]));
+
+        let mut profiler = Profiler::new();
+        let result = profiler.record(|| {
+            let a = Profiler::label("init", || x[0].and(&x[1]));
+            Profiler::label("step", || {
+                let b = Profiler::label("guard;1", || x[2].xor(&x[3]));
+                a.or(&b).and(&x[0])
+            })
+        });
+        // Another session adds to the same statistics.
+        profiler.record(|| Profiler::label("init", || x[0].and(&x[1])));
+        assert!(result.semantic_eq(&x[0].and(&x[1].or(&x[2].xor(&x[3])))));
+
+        let stacks: Vec<(&str, u64, u64)> = profiler
+            .entries()
+            .map(|(stack, entry)| (stack, entry.calls, entry.nodes))
+            .collect();
+        let expected = vec![
+            ("init;and", 2, 8),
+            ("step;and", 1, 7),
+            ("step;guard_1;xor", 1, 5),
+            ("step;or", 1, 7),
+        ];
+        assert_eq!(expected, stacks);
+        assert_eq!(
+            "init;and 8\nstep;and 7\nstep;guard_1;xor 5\nstep;or 7\n",
+            profiler.collapsed_stacks(ProfileWeight::Nodes)
+        );
+        assert_eq!(
+            4,
+            profiler
+                .collapsed_stacks(ProfileWeight::Time)
+                .lines()
+                .count()
+        );
+        profiler.clear();
+        assert_eq!(0, profiler.entries().count());
+    }
+}
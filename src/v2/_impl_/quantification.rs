@@ -0,0 +1,378 @@
+use crate::v2::{ApplyContext, Bdd, BddNode, NodeId, PooledHandle, TaskCacheConfig, VariableId};
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::min;
+
+/// **(internal)** A single task of the `ApplyContext::and_exists` search.
+#[derive(Copy, Clone)]
+struct Frame {
+    left: NodeId,
+    right: NodeId,
+    exclude: NodeId,
+    variable: VariableId,
+    high_left: NodeId,
+    high_right: NodeId,
+    high_exclude: NodeId,
+    /// The result of the low branch, or `UNDEFINED` if it is not known yet.
+    low: NodeId,
+}
+
+impl Frame {
+    fn new(left: NodeId, right: NodeId, exclude: NodeId) -> Frame {
+        Frame {
+            left,
+            right,
+            exclude,
+            variable: VariableId::UNDEFINED,
+            high_left: NodeId::UNDEFINED,
+            high_right: NodeId::UNDEFINED,
+            high_exclude: NodeId::UNDEFINED,
+            low: NodeId::UNDEFINED,
+        }
+    }
+}
+
+impl ApplyContext {
+    /// The variables which appear in the `Bdd` referenced by the `handle`, in ascending order.
+    pub fn support(&self, handle: PooledHandle) -> Vec<VariableId> {
+        let mut is_used = vec![false; usize::from(self.variable_count)];
+        let mut visited = BTreeSet::new();
+        let mut stack = vec![handle.0];
+        while let Some(top) = stack.pop() {
+            if top.as_index() < 2 || !visited.insert(top.0) {
+                continue;
+            }
+            let (var, low, high) = self.nodes.get(top.as_index()).unpack();
+            is_used[usize::from(var.0)] = true;
+            stack.push(low);
+            stack.push(high);
+        }
+        is_used
+            .into_iter()
+            .enumerate()
+            .filter(|(_, used)| *used)
+            .map(|(var, _)| VariableId(var as u16))
+            .collect()
+    }
+
+    /// Existential quantification of the given `variables` in the `Bdd` referenced
+    /// by the `handle`.
+    pub fn exists(&mut self, handle: PooledHandle, variables: &[VariableId]) -> PooledHandle {
+        // Conjunction with `true` is an identity, and the right operand is never expanded.
+        self.and_exists(handle, PooledHandle(NodeId::ONE), variables)
+    }
+
+    /// The relational product of two `Bdds`: `exists variables: left & right`.
+    ///
+    /// The result is computed in a single pass, without building the (possibly much larger)
+    /// conjunction first. The disjunctions that implement the quantification share
+    /// the operation cache of this context.
+    pub fn and_exists(
+        &mut self,
+        left: PooledHandle,
+        right: PooledHandle,
+        variables: &[VariableId],
+    ) -> PooledHandle {
+        self.and_exists_excluding(left, right, PooledHandle(NodeId::ZERO), variables, &[])
+    }
+
+    /// The relational product of two `Bdds` (see `ApplyContext::and_exists`), where every
+    /// remaining variable `from[i]` is then replaced with the variable `to[i]` (see
+    /// `ApplyContext::rename`).
+    ///
+    /// The renaming is applied while the result nodes are created, so the product is never
+    /// built with the original variables. Same as with `ApplyContext::rename`, it must
+    /// preserve the order of the variables which remain in the result.
+    pub fn and_exists_rename(
+        &mut self,
+        left: PooledHandle,
+        right: PooledHandle,
+        variables: &[VariableId],
+        from: &[VariableId],
+        to: &[VariableId],
+    ) -> Result<PooledHandle, String> {
+        let mut remaining: BTreeSet<VariableId> = self.support(left).into_iter().collect();
+        remaining.extend(self.support(right));
+        for var in variables {
+            remaining.remove(var);
+        }
+        let remaining: Vec<VariableId> = remaining.into_iter().collect();
+        let mapping = self.renaming(&remaining, from, to)?;
+        let zero = PooledHandle(NodeId::ZERO);
+        Ok(self.and_exists_excluding(left, right, zero, variables, &mapping))
+    }
+
+    /// **(internal)** Compute `(exists variables: left & right) & !exclude` in a single pass.
+    ///
+    /// The `exclude` set must not depend on the quantified `variables`. The search stops
+    /// as soon as a task is fully excluded, so the parts of the image that are excluded
+    /// are never built.
+    ///
+    /// Result nodes of a variable `v` use the variable `mapping[v]` instead (variables
+    /// outside of the `mapping` are not changed). The `mapping` must preserve the order of
+    /// the variables which appear in the result, and it can only be used with an empty
+    /// `exclude` set.
+    pub(super) fn and_exists_excluding(
+        &mut self,
+        left: PooledHandle,
+        right: PooledHandle,
+        exclude: PooledHandle,
+        variables: &[VariableId],
+        mapping: &[VariableId],
+    ) -> PooledHandle {
+        debug_assert!(mapping.is_empty() || exclude.0.is_zero());
+        let relabel = |var: VariableId| mapping.get(usize::from(var.0)).cloned().unwrap_or(var);
+        let mut quantified_mask = vec![false; usize::from(self.variable_count)];
+        for var in variables {
+            if let Some(slot) = quantified_mask.get_mut(usize::from(var.0)) {
+                *slot = true;
+            }
+        }
+        let is_quantified = |var: VariableId| {
+            quantified_mask
+                .get(usize::from(var.0))
+                .cloned()
+                .unwrap_or(false)
+        };
+
+        // The results of this operation depend on `variables`, so they cannot be saved
+        // in the shared operation cache.
+        let mut task_cache: BTreeMap<(u64, u64, u64), NodeId> = BTreeMap::new();
+        let mut stack = vec![Frame::new(left.0, right.0, exclude.0)];
+        loop {
+            let depth = stack.len();
+            let top = &mut stack[depth - 1];
+            let (left, right, exclude) = (top.left, top.right, top.exclude);
+            let mut value = if left.is_zero() || right.is_zero() || exclude.is_one() {
+                NodeId::ZERO
+            } else if left.is_one() && right.is_one() && exclude.is_zero() {
+                NodeId::ONE
+            } else {
+                task_cache
+                    .get(&(left.0, right.0, exclude.0))
+                    .cloned()
+                    .unwrap_or(NodeId::UNDEFINED)
+            };
+            if value.is_undefined() {
+                // Expand the task and continue with its low successor.
+                let (left_var, left_low, left_high) = self.nodes.get(left.as_index()).unpack();
+                let (right_var, right_low, right_high) = self.nodes.get(right.as_index()).unpack();
+                let (exclude_var, exclude_low, exclude_high) =
+                    self.nodes.get(exclude.as_index()).unpack();
+                let variable = min(min(left_var, right_var), exclude_var);
+                let (left_low, left_high) = if left_var == variable {
+                    (left_low, left_high)
+                } else {
+                    (left, left)
+                };
+                let (right_low, right_high) = if right_var == variable {
+                    (right_low, right_high)
+                } else {
+                    (right, right)
+                };
+                let (exclude_low, exclude_high) = if exclude_var == variable {
+                    (exclude_low, exclude_high)
+                } else {
+                    (exclude, exclude)
+                };
+                top.variable = variable;
+                top.high_left = left_high;
+                top.high_right = right_high;
+                top.high_exclude = exclude_high;
+                stack.push(Frame::new(left_low, right_low, exclude_low));
+                continue;
+            }
+
+            // The top task is finished. Propagate its result to its parents.
+            stack.pop();
+            let finished = loop {
+                let parent = match stack.last_mut() {
+                    None => break Some(value),
+                    Some(parent) => parent,
+                };
+                let quantified = is_quantified(parent.variable);
+                if parent.low.is_undefined() && !(quantified && value.is_one()) {
+                    parent.low = value;
+                    let high_task =
+                        Frame::new(parent.high_left, parent.high_right, parent.high_exclude);
+                    stack.push(high_task);
+                    break None;
+                }
+                let parent = *parent;
+                value = if parent.low.is_undefined() {
+                    // A quantified variable with a `true` low branch: `true | x = true`.
+                    NodeId::ONE
+                } else if quantified {
+                    self.or(PooledHandle(parent.low), PooledHandle(value)).0
+                } else if parent.low == value {
+                    value
+                } else {
+                    self.ensure(BddNode::pack(relabel(parent.variable), parent.low, value))
+                };
+                task_cache.insert((parent.left.0, parent.right.0, parent.exclude.0), value);
+                stack.pop();
+            };
+            if let Some(value) = finished {
+                return PooledHandle(value);
+            }
+        }
+    }
+}
+
+impl Bdd {
+    /// The relational product of two `Bdds`: `exists variables: self & other`.
+    ///
+    /// The product is computed in a single "coupled DFS" pass of the `U48` apply kernel,
+    /// so the (possibly much larger) conjunction is never materialized.
+    pub fn and_exists(&self, other: &Bdd, variables: &[VariableId]) -> Bdd {
+        self._u48_and_exists(other, variables, &TaskCacheConfig::default())
+    }
+
+    /// The relational composition (join) of two relations: `exists shared: self & other`,
+    /// where every remaining variable `from` of the `rename` pairs is then replaced with
+    /// the variable `to`.
+    ///
+    /// For example, with relations `R1(x, y)` and `R2(y, z)`, joining over the `y` variables
+    /// and renaming `z` to `y` gives the composition `R1 ; R2` over the variables `(x, y)`.
+    /// The quantification and the renaming are computed in a single pass (see
+    /// `ApplyContext::and_exists_rename`), so the renaming must preserve the order of the
+    /// variables which remain in the result.
+    pub fn relational_join(
+        &self,
+        other: &Bdd,
+        shared: &[VariableId],
+        rename: &[(VariableId, VariableId)],
+    ) -> Result<Bdd, String> {
+        let (from, to): (Vec<VariableId>, Vec<VariableId>) = rename.iter().cloned().unzip();
+        let mut context = ApplyContext::new();
+        let left = context.import(self);
+        let right = context.import(other);
+        let result = context.and_exists_rename(left, right, shared, &from, &to)?;
+        Ok(context.export(result))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::_impl_::test_util::variables;
+    use crate::v2::{ApplyContext, Bdd, VariableId};
+
+    #[test]
+    pub fn context_quantification_test() {
+        let vars = variables(4);
+        let mut context = ApplyContext::new();
+        let x: Vec<_> = vars.iter().map(|v| context.import(v)).collect();
+
+        // f = (x0 & x1) | (x2 & !x3)
+        let a = context.and(x[0], x[1]);
+        let b = context.and_not(x[2], x[3]);
+        let f = context.or(a, b);
+        assert_eq!(
+            vec![0, 1, 2, 3],
+            context.support(f).into_iter().map(|v| v.0).collect::<Vec<_>>()
+        );
+
+        // exists x0: f = x1 | (x2 & !x3)
+        let expected = context.or(x[1], b);
+        assert_eq!(expected, context.exists(f, &[VariableId::from(0)]));
+        // exists x0, x2: f = x1 | !x3
+        let expected = context.imp(x[3], x[1]);
+        let quantified = context.exists(f, &[VariableId::from(0), VariableId::from(2)]);
+        assert_eq!(expected, quantified);
+        // exists x0, x1: f = true
+        let quantified = context.exists(f, &[VariableId::from(0), VariableId::from(1)]);
+        assert_eq!(1, quantified.0.as_index());
+
+        // exists x1: (x0 <=> x1) & (x1 <=> x2) = (x0 <=> x2)
+        let left = context.iff(x[0], x[1]);
+        let right = context.iff(x[1], x[2]);
+        let expected = context.iff(x[0], x[2]);
+        assert_eq!(expected, context.and_exists(left, right, &[VariableId::from(1)]));
+        let support: Vec<u16> = context.support(expected).into_iter().map(|v| v.0).collect();
+        assert_eq!(vec![0, 2], support);
+    }
+
+    #[test]
+    pub fn and_exists_test() {
+        let f = crate::testing::multiplier_bit(3, 2);
+        let g = crate::testing::hidden_weighted_bit(6);
+        let variables = [VariableId::from(1u16), VariableId::from(3u16)];
+
+        let mut context = ApplyContext::new();
+        let (left, right) = (context.import(&f), context.import(&g));
+        let conjunction = context.and(left, right);
+        let expected = context.exists(conjunction, &variables);
+        let expected = context.export(expected);
+
+        let product = f.and_exists(&g, &variables);
+        assert!(product.semantic_eq(&expected));
+        assert_eq!(product.node_count(), expected.node_count());
+        assert!(f.and_exists(&Bdd::new_false(), &variables).node_count() == 1);
+
+        // Every subset of the (even) variables, with the operands in both orders.
+        let h = crate::testing::multiplier_bit(3, 3).xor(&g);
+        for mask in 0..64u16 {
+            let variables: Vec<VariableId> = (0..6u16)
+                .filter(|i| mask & (1 << i) != 0)
+                .map(|i| VariableId::from(2 * i))
+                .collect();
+            let conjunction = context.import(&f.and(&h));
+            let expected = context.exists(conjunction, &variables);
+            let expected = context.export(expected);
+            for product in [f.and_exists(&h, &variables), h.and_exists(&f, &variables)] {
+                assert!(product.check_integrity().is_ok());
+                assert!(product.semantic_eq(&expected));
+                assert_eq!(product.node_count(), expected.node_count());
+            }
+        }
+        // Without variables, the product is just a conjunction.
+        assert!(f.and_exists(&g, &[]).semantic_eq(&f.and(&g)));
+    }
+
+    #[test]
+    pub fn relational_join_test() {
+        // Two-bit counters `x`, `y` and `z` with interleaved bits: `x = (0, 3)`,
+        // `y = (1, 4)` and `z = (2, 5)`.
+        let v = variables(6);
+        let bits = |offset: usize| (offset, offset + 3);
+        // The relation `target = source + 1 (mod 4)`.
+        let successor = |source: usize, target: usize| {
+            let (s0, s1) = bits(source);
+            let (t0, t1) = bits(target);
+            let low = v[t0].xor(&v[s0]);
+            let high = v[t1].iff(&v[s1].xor(&v[s0]));
+            low.and(&high)
+        };
+        let r1 = successor(0, 1);
+        let r2 = successor(1, 2);
+        let y = [VariableId::from(1u16), VariableId::from(4u16)];
+        let z_to_y = [
+            (VariableId::from(2u16), VariableId::from(1u16)),
+            (VariableId::from(5u16), VariableId::from(4u16)),
+        ];
+
+        // `y = x + 2 (mod 4)`.
+        let expected = v[1].iff(&v[0]).and(&v[4].xor(&v[3]));
+        let join = r1.relational_join(&r2, &y, &z_to_y).unwrap();
+        assert!(join.semantic_eq(&expected));
+        // Without renaming, the result is a relation over `(x, z)`.
+        let join = r1.relational_join(&r2, &y, &[]).unwrap();
+        assert!(join.semantic_eq(&v[2].iff(&v[0]).and(&v[5].xor(&v[3]))));
+
+        // The fused kernel gives the same nodes as the separate operations.
+        let mut context = ApplyContext::new();
+        let (left, right) = (context.import(&r1), context.import(&r2));
+        let fused = context
+            .and_exists_rename(left, right, &y, &[z_to_y[0].0, z_to_y[1].0], &y)
+            .unwrap();
+        let product = context.and_exists(left, right, &y);
+        let renamed = context.rename(product, &[z_to_y[0].0, z_to_y[1].0], &y);
+        assert_eq!(renamed, Ok(fused));
+
+        // Renaming `z` before `x` does not preserve the order of the result variables.
+        let z_first = [(VariableId::from(5u16), VariableId::from(0u16))];
+        assert!(r1.relational_join(&r2, &y, &z_first).is_err());
+    }
+}
@@ -0,0 +1,321 @@
+use crate::v2::{Bdd, BddNode, BddStorage, BddView, NodeId, SplitBdd, VariableId};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::{max, min};
+use core::convert::TryFrom;
+
+// A `Bdd` maintains all the invariants of `BddStorage`.
+unsafe impl BddStorage for Bdd {
+    fn variable_count(&self) -> u16 {
+        self.variable_count
+    }
+
+    #[inline]
+    fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    #[inline]
+    unsafe fn get_node_unchecked(&self, id: NodeId) -> BddNode {
+        unsafe { Bdd::get_node_unchecked(self, id) }
+    }
+
+    #[inline]
+    fn prefetch(&self, id: NodeId) {
+        Bdd::prefetch(self, id)
+    }
+
+    fn to_bdd(&self) -> Bdd {
+        self.clone()
+    }
+}
+
+impl Bdd {
+    /// All nodes of this `Bdd`.
+    pub fn node_slice(&self) -> &[BddNode] {
+        &self.nodes
+    }
+}
+
+// The nodes are validated when the view is created.
+unsafe impl BddStorage for BddView<'_> {
+    fn variable_count(&self) -> u16 {
+        self.variable_count
+    }
+
+    #[inline]
+    fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    #[inline]
+    unsafe fn get_node_unchecked(&self, id: NodeId) -> BddNode {
+        debug_assert!(id.as_index() < self.nodes.len());
+        unsafe { *self.nodes.get_unchecked(id.as_index_unchecked()) }
+    }
+
+    #[inline]
+    fn prefetch(&self, id: NodeId) {
+        // Prefetch operations ignore memory errors and are therefore "externally safe".
+        crate::arch::prefetch(self.nodes.as_ptr().wrapping_add(id.0 as usize));
+    }
+
+    fn to_bdd(&self) -> Bdd {
+        Bdd {
+            variable_count: self.variable_count,
+            is_sorted: false,
+            nodes: self.nodes.to_vec(),
+            fingerprint: Default::default(),
+        }
+    }
+}
+
+// The nodes are copied from a valid `Bdd`.
+unsafe impl BddStorage for SplitBdd {
+    fn variable_count(&self) -> u16 {
+        self.variable_count
+    }
+
+    #[inline]
+    fn node_count(&self) -> usize {
+        self.links.len()
+    }
+
+    #[inline]
+    unsafe fn get_node_unchecked(&self, id: NodeId) -> BddNode {
+        debug_assert!(id.as_index() < self.links.len());
+        let index = unsafe { id.as_index_unchecked() };
+        let (low, high) = unsafe { *self.links.get_unchecked(index) };
+        let variable = unsafe { *self.variables.get_unchecked(index) };
+        BddNode::pack(variable, NodeId(u64::from(low)), NodeId(u64::from(high)))
+    }
+
+    #[inline]
+    unsafe fn get_variable_unchecked(&self, id: NodeId) -> VariableId {
+        debug_assert!(id.as_index() < self.variables.len());
+        unsafe { *self.variables.get_unchecked(id.as_index_unchecked()) }
+    }
+
+    #[inline]
+    fn prefetch(&self, id: NodeId) {
+        // Only the links are prefetched, the variable is read later (if at all).
+        crate::arch::prefetch(self.links.as_ptr().wrapping_add(id.0 as usize));
+    }
+
+    fn to_bdd(&self) -> Bdd {
+        let mut result = if self.links.len() == 1 {
+            Bdd::new_false()
+        } else {
+            Bdd::true_with_capacity(self.links.len())
+        };
+        result.update_variable_count(self.variable_count);
+        for index in 2..self.links.len() {
+            let node = unsafe { self.get_node_unchecked(NodeId(index as u64)) };
+            result.push_node(node);
+        }
+        result
+    }
+}
+
+/// Convert a `Bdd` into the split layout. The conversion fails if the `Bdd` has
+/// `2^32` or more nodes.
+impl TryFrom<&Bdd> for SplitBdd {
+    type Error = String;
+
+    fn try_from(bdd: &Bdd) -> Result<Self, Self::Error> {
+        if u32::try_from(bdd.node_count()).is_err() {
+            return Err(format!(
+                "Bdd with {} nodes does not fit into 32 bits.",
+                bdd.node_count()
+            ));
+        }
+        let mut variables = Vec::with_capacity(bdd.node_count());
+        let mut links = Vec::with_capacity(bdd.node_count());
+        for node in &bdd.nodes {
+            let (variable, low, high) = node.unpack();
+            variables.push(variable);
+            // Links are smaller than the node count, so they fit into 32 bits as well.
+            links.push((low.0 as u32, high.0 as u32));
+        }
+        Ok(SplitBdd {
+            variable_count: bdd.variable_count,
+            variables,
+            links,
+        })
+    }
+}
+
+impl<'a> BddView<'a> {
+    /// Create a view of the given `nodes`, checking that they form a valid `Bdd`.
+    ///
+    /// The check is linear in the number of nodes, so after that, the view can be used
+    /// by the (unchecked) apply kernels.
+    pub fn try_from_nodes(nodes: &'a [BddNode]) -> Result<BddView<'a>, String> {
+        let variable_count = check_nodes(nodes.len(), |index| nodes[index])?;
+        Ok(BddView {
+            variable_count,
+            nodes,
+        })
+    }
+}
+
+/// **(internal)** Check that the `count` nodes provided by `node` form a valid `Bdd`
+/// (see `BddView::try_from_nodes`) and return the number of variables they use.
+pub(super) fn check_nodes<F>(count: usize, node: F) -> Result<u16, String>
+where
+    F: Fn(usize) -> BddNode,
+{
+    if count == 0 {
+        return Err(String::from("A Bdd must contain at least one node."));
+    }
+    if node(0) != BddNode::ZERO {
+        return Err(String::from("The first node must be the `false` terminal."));
+    }
+    if count > 1 && node(1) != BddNode::ONE {
+        return Err(String::from("The second node must be the `true` terminal."));
+    }
+    Bdd::check_node_count(count as u64)?;
+    let mut variable_count = 0;
+    for index in 2..count {
+        let (variable, low, high) = node(index).unpack();
+        if variable == VariableId::UNDEFINED {
+            return Err(format!("Node {} has an undefined variable.", index));
+        }
+        // Links can point to nodes with higher ids (e.g. after `Bdd::sort_preorder`). The
+        // variables strictly increase along every link, so the nodes cannot form a cycle.
+        for link in [low, high] {
+            let is_valid = link.as_index() < count && variable < node(link.as_index()).unpack().0;
+            if !is_valid {
+                return Err(format!("Node {} has an invalid link {}.", index, link.0));
+            }
+        }
+        if low == high {
+            return Err(format!("Node {} is redundant.", index));
+        }
+        variable_count = max(variable_count, variable.0 + 1);
+    }
+    Ok(variable_count)
+}
+
+/// **(internal)** Node access used by the apply kernels which work with any `BddStorage`.
+pub(crate) trait StorageAccess: BddStorage {
+    #[inline]
+    fn root_node(&self) -> NodeId {
+        NodeId((self.node_count() - 1) as u64)
+    }
+
+    #[inline]
+    fn get_variable(&self, id: NodeId) -> VariableId {
+        debug_assert!(id.as_index() < self.node_count());
+        unsafe { self.get_variable_unchecked(id) }
+    }
+
+    /// An upper bound on the number of decision nodes on any path from the root (zero
+    /// for constants).
+    ///
+    /// The variables increase along every path and they are all smaller than the variable
+    /// count (see the `# Safety` section of `BddStorage`), so a path from the root has at
+    /// most `variable_count - root_variable` decision nodes. The bound is not checked against
+    /// the nodes: the apply kernels size their stacks from it, which is why `BddStorage`
+    /// is an `unsafe trait`.
+    fn height_bound(&self) -> usize {
+        let decision_nodes = self.node_count().saturating_sub(2);
+        if decision_nodes == 0 {
+            return 0;
+        }
+        let root_variable = usize::from(self.get_variable(self.root_node()).0);
+        let variable_count = usize::from(self.variable_count());
+        debug_assert!(root_variable < variable_count);
+        min(decision_nodes, variable_count.saturating_sub(root_variable))
+    }
+}
+
+impl<S: BddStorage + ?Sized> StorageAccess for S {}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::_impl_::test_util::{and_table, variables};
+    use crate::v2::{Bdd, BddNode, BddStorage, BddView, NodeId, VariableId};
+
+    #[test]
+    pub fn bdd_view_test() {
+        let x = variables(4);
+        let large = x[0].and(&x[1]).or(&x[2].xor(&x[3]));
+        let small = x[1].imp(&x[3]);
+
+        // A view of a copy of the nodes, as if they were loaded from a file.
+        let nodes = large.node_slice().to_vec();
+        let view = BddView::try_from_nodes(&nodes).unwrap();
+        assert_eq!(large.variable_count(), BddStorage::variable_count(&view));
+        let expected = large.and(&small);
+        for result in [
+            Bdd::mixed_binary_operation(&view, &small, and_table),
+            Bdd::mixed_binary_operation(&small, &view, and_table),
+        ] {
+            assert_eq!(2, result.iff(&expected).node_count());
+        }
+        assert_eq!(2, view.to_bdd().iff(&large).node_count());
+
+        // A preorder-sorted `Bdd` has links to higher ids, but it is still valid.
+        let shared = x[0].and(&x[2]).or(&x[1].and(&x[3]));
+        let mut sorted = shared.clone();
+        sorted.sort_preorder();
+        let view = BddView::try_from_nodes(sorted.node_slice()).unwrap();
+        assert_eq!(2, view.to_bdd().iff(&shared).node_count());
+        let result = Bdd::mixed_binary_operation(&view, &small, and_table);
+        assert_eq!(2, result.iff(&shared.and(&small)).node_count());
+
+        // Invalid node lists are rejected.
+        assert!(BddView::try_from_nodes(&[]).is_err());
+        assert!(BddView::try_from_nodes(&[BddNode::ONE]).is_err());
+        let mut cycle = nodes.clone();
+        cycle.push(BddNode::pack(VariableId::from(0), NodeId::ONE, NodeId(7)));
+        assert!(BddView::try_from_nodes(&cycle).is_err());
+        let mut unordered = nodes.clone();
+        let root = NodeId((nodes.len() - 1) as u64);
+        unordered.push(BddNode::pack(VariableId::from(3), NodeId::ZERO, root));
+        assert!(BddView::try_from_nodes(&unordered).is_err());
+    }
+
+    #[test]
+    pub fn split_bdd_test() {
+        use crate::v2::SplitBdd;
+        use core::convert::TryFrom;
+
+        let x = variables(5);
+        let left = x[0].iff(&x[2]).or(&x[1].and(&x[4]));
+        let right = x[1].xor(&x[3]).or(&x[0].and_not(&x[4]));
+        let split_left = SplitBdd::try_from(&left).unwrap();
+        let split_right = SplitBdd::try_from(&right).unwrap();
+        assert_eq!(left.node_count(), split_left.node_count());
+        assert_eq!(left.variable_count(), BddStorage::variable_count(&split_left));
+        assert!(split_left.to_bdd().nodes == left.nodes);
+
+        let xor = |l: NodeId, r: NodeId| {
+            if l.as_index() < 2 && r.as_index() < 2 {
+                if l == r {
+                    NodeId::ZERO
+                } else {
+                    NodeId::ONE
+                }
+            } else {
+                NodeId::UNDEFINED
+            }
+        };
+        // The result does not depend on the storage layout.
+        let expected = Bdd::mixed_binary_operation(&left, &right, xor);
+        for result in [
+            Bdd::mixed_binary_operation(&split_left, &split_right, xor),
+            Bdd::mixed_binary_operation(&split_left, &right, xor),
+            Bdd::mixed_binary_operation(&left, &split_right, xor),
+        ] {
+            assert!(result.nodes == expected.nodes);
+        }
+        let mirrored = Bdd::mixed_binary_operation(&right, &split_left, xor);
+        assert_eq!(2, mirrored.iff(&expected).node_count());
+
+        let ff = SplitBdd::try_from(&Bdd::new_false()).unwrap();
+        assert_eq!(1, ff.to_bdd().node_count());
+    }
+}
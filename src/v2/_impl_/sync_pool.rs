@@ -0,0 +1,236 @@
+use super::context::{node_hash, Operation};
+use super::pool::export;
+use crate::v2::{Bdd, BddNode, BoolOp, NodeId, PooledHandle, SyncBddPool, VariableId};
+use core::cmp::min;
+use core::sync::atomic::{AtomicU16, Ordering};
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+
+/// **(internal)** The number of independently locked stripes of the unique table.
+const STRIPES: usize = 64;
+
+impl SyncBddPool {
+    /// Create a new pool which only contains the two terminal nodes.
+    pub fn new() -> SyncBddPool {
+        SyncBddPool {
+            variable_count: AtomicU16::new(0),
+            nodes: RwLock::new(vec![BddNode::ZERO, BddNode::ONE]),
+            unique_table: (0..STRIPES).map(|_| Mutex::new(HashMap::new())).collect(),
+        }
+    }
+
+    /// The number of (unique) nodes stored in this pool, including terminals.
+    pub fn node_count(&self) -> usize {
+        self.nodes.read().unwrap().len()
+    }
+
+    /// The largest variable count of all the `Bdds` imported into this pool.
+    pub fn variable_count(&self) -> u16 {
+        self.variable_count.load(Ordering::SeqCst)
+    }
+
+    /// Copy a standalone `Bdd` into this pool, sharing every node that already exists
+    /// in the pool. Returns a handle to the root of the imported `Bdd`.
+    pub fn import(&self, bdd: &Bdd) -> PooledHandle {
+        self.variable_count
+            .fetch_max(bdd.variable_count(), Ordering::SeqCst);
+        if bdd.node_count() <= 2 {
+            return PooledHandle(bdd.root_node());
+        }
+        // Maps node ids of `bdd` to node ids in this pool.
+        let mut translation = vec![NodeId::UNDEFINED; bdd.node_count()];
+        translation[0] = NodeId::ZERO;
+        translation[1] = NodeId::ONE;
+        let mut stack = vec![bdd.root_node()];
+        while let Some(top) = stack.last().cloned() {
+            if !translation[top.as_index()].is_undefined() {
+                stack.pop();
+                continue;
+            }
+            let (var, low, high) = bdd.get_node(top).unpack();
+            let new_low = translation[low.as_index()];
+            let new_high = translation[high.as_index()];
+            if new_low.is_undefined() || new_high.is_undefined() {
+                if new_high.is_undefined() {
+                    stack.push(high);
+                }
+                if new_low.is_undefined() {
+                    stack.push(low);
+                }
+            } else {
+                translation[top.as_index()] = self.ensure(BddNode::pack(var, new_low, new_high));
+                stack.pop();
+            }
+        }
+        PooledHandle(translation[bdd.root_node().as_index()])
+    }
+
+    /// Create a standalone copy of the `Bdd` referenced by the given `handle`
+    /// (see `BddPool::export`).
+    pub fn export(&self, handle: PooledHandle) -> Bdd {
+        let nodes = self.nodes.read().unwrap();
+        export(&nodes, self.variable_count(), handle.0)
+    }
+
+    /// A logical conjunction of two `Bdds` stored in this pool.
+    pub fn and(&self, left: PooledHandle, right: PooledHandle) -> PooledHandle {
+        self.apply(Operation::And, left, right)
+    }
+
+    /// A logical disjunction of two `Bdds` stored in this pool.
+    pub fn or(&self, left: PooledHandle, right: PooledHandle) -> PooledHandle {
+        self.apply(Operation::Or, left, right)
+    }
+
+    /// A logical exclusive disjunction of two `Bdds` stored in this pool.
+    pub fn xor(&self, left: PooledHandle, right: PooledHandle) -> PooledHandle {
+        self.apply(Operation::Xor, left, right)
+    }
+
+    /// A logical conjunction with a negated second argument of two `Bdds` stored
+    /// in this pool.
+    pub fn and_not(&self, left: PooledHandle, right: PooledHandle) -> PooledHandle {
+        self.apply(Operation::AndNot, left, right)
+    }
+
+    /// Apply the logical operation selected by `op` to two `Bdds` stored in this pool
+    /// (equivalent to calling the corresponding method, e.g. `SyncBddPool::and`).
+    pub fn apply_op(&self, op: BoolOp, left: PooledHandle, right: PooledHandle) -> PooledHandle {
+        self.apply(Operation::from(op), left, right)
+    }
+
+    /// **(internal)** The apply algorithm on the shared nodes. The task cache is local
+    /// to the operation, so operations running in different threads never wait for each
+    /// other, except when they access the same stripe of the unique table.
+    fn apply(&self, operation: Operation, left: PooledHandle, right: PooledHandle) -> PooledHandle {
+        let mut task_cache: HashMap<(NodeId, NodeId), NodeId> = HashMap::new();
+        let known = |cache: &HashMap<(NodeId, NodeId), NodeId>, task: (NodeId, NodeId)| {
+            let value = operation.table(task.0, task.1);
+            if value.is_undefined() {
+                cache.get(&task).cloned()
+            } else {
+                Some(value)
+            }
+        };
+
+        let root = (left.0, right.0);
+        // Every task is first visited as unexpanded (`false`), and then again once its
+        // sub-tasks are solved (`true`).
+        let mut stack = vec![(root, false)];
+        while let Some((task, expanded)) = stack.pop() {
+            if known(&task_cache, task).is_some() {
+                continue;
+            }
+            let (variable, low, high) = self.expand(task);
+            if !expanded {
+                stack.push((task, true));
+                stack.push((high, false));
+                stack.push((low, false));
+                continue;
+            }
+            // Both sub-tasks were solved before this task was visited again.
+            let low = known(&task_cache, low).unwrap();
+            let high = known(&task_cache, high).unwrap();
+            let result = if low == high {
+                low
+            } else {
+                self.ensure(BddNode::pack(variable, low, high))
+            };
+            task_cache.insert(task, result);
+        }
+        PooledHandle(known(&task_cache, root).unwrap())
+    }
+
+    /// **(internal)** The decision variable of a task, together with its low and high
+    /// sub-tasks.
+    fn expand(&self, task: (NodeId, NodeId)) -> (VariableId, (NodeId, NodeId), (NodeId, NodeId)) {
+        let (left, right) = task;
+        let nodes = self.nodes.read().unwrap();
+        let (left_var, left_low, left_high) = nodes[left.as_index()].unpack();
+        let (right_var, right_low, right_high) = nodes[right.as_index()].unpack();
+        let variable = min(left_var, right_var);
+        let (left_low, left_high) = if left_var == variable {
+            (left_low, left_high)
+        } else {
+            (left, left)
+        };
+        let (right_low, right_high) = if right_var == variable {
+            (right_low, right_high)
+        } else {
+            (right, right)
+        };
+        (variable, (left_low, right_low), (left_high, right_high))
+    }
+
+    /// **(internal)** Find the `node` in the unique table, or create it.
+    ///
+    /// The stripe of the node stays locked until the node is appended, so two threads can
+    /// never create the same node twice.
+    fn ensure(&self, node: BddNode) -> NodeId {
+        let mut stripe = self.unique_table[node_hash(node) % STRIPES].lock().unwrap();
+        if let Some(id) = stripe.get(&node) {
+            return *id;
+        }
+        let id = {
+            let mut nodes = self.nodes.write().unwrap();
+            Bdd::check_next_node(nodes.len());
+            nodes.push(node);
+            NodeId((nodes.len() - 1) as u64)
+        };
+        stripe.insert(node, id);
+        id
+    }
+}
+
+impl Default for SyncBddPool {
+    fn default() -> Self {
+        SyncBddPool::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::{Bdd, BoolOp, SyncBddPool, VariableId};
+
+    #[test]
+    pub fn sync_pool_test() {
+        let [x0, x1, x2, x3] = [0u16, 1, 2, 3].map(|v| Bdd::new_variable(VariableId::from(v)));
+        let pool = SyncBddPool::new();
+        let handles: Vec<_> = [&x0, &x1, &x2, &x3]
+            .iter()
+            .map(|x| pool.import(x))
+            .collect();
+        assert!(pool.import(&x1) == handles[1]);
+        assert_eq!(4, pool.variable_count());
+
+        // Several threads run independent operations on the shared pool.
+        let results: Vec<_> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..4)
+                .map(|i| {
+                    let (pool, handles) = (&pool, &handles);
+                    scope.spawn(move || {
+                        let (a, b) = (handles[i], handles[(i + 1) % 4]);
+                        let c = handles[(i + 2) % 4];
+                        let clause = pool.or(pool.and(a, b), pool.and_not(c, a));
+                        pool.apply_op(BoolOp::Xor, clause, handles[0])
+                    })
+                })
+                .collect();
+            workers.into_iter().map(|it| it.join().unwrap()).collect()
+        });
+
+        let x = [&x0, &x1, &x2, &x3];
+        for (i, result) in results.iter().enumerate() {
+            let (a, b, c) = (x[i], x[(i + 1) % 4], x[(i + 2) % 4]);
+            let expected = a.and(b).or(&c.and_not(a)).xor(&x0);
+            let exported = pool.export(*result);
+            assert!(exported.semantic_eq(&expected));
+            assert!(exported.check_integrity().is_ok());
+            // The nodes are shared, so importing the result again creates nothing new.
+            let count = pool.node_count();
+            assert!(pool.import(&expected) == *result);
+            assert_eq!(count, pool.node_count());
+        }
+        assert!(pool.xor(handles[2], handles[2]) == pool.import(&Bdd::new_false()));
+    }
+}
@@ -0,0 +1,16 @@
+use crate::v2::ops::{And, BooleanOp};
+use crate::v2::{Bdd, NodeId, VariableId};
+use alloc::vec::Vec;
+
+/// **(internal)** The `Bdds` of the variables `0..count`.
+pub fn variables(count: u16) -> Vec<Bdd> {
+    (0..count)
+        .map(|v| Bdd::new_variable(VariableId::from(v)))
+        .collect()
+}
+
+/// **(internal)** The lookup table of conjunction, for operations which take the `TABLE`
+/// as a closure (e.g. `Bdd::mixed_binary_operation`).
+pub fn and_table(left: NodeId, right: NodeId) -> NodeId {
+    And::lookup(left, right)
+}
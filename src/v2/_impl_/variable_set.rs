@@ -0,0 +1,197 @@
+use crate::expressions::BooleanExpression;
+use crate::v2::{Bdd, BddNode, BddVariableSet, NodeId, VariableId, VariableMetadata};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+impl BddVariableSet {
+    /// Create a set of variables with the given (unique) `names`.
+    pub fn new(names: &[&str]) -> Result<BddVariableSet, String> {
+        let variable_count = u16::try_from(names.len())
+            .map_err(|_| format!("Too many variables: {}.", names.len()))?;
+        let mut metadata = VariableMetadata::new();
+        for (index, name) in names.iter().enumerate() {
+            metadata.set_name(VariableId(index as u16), name)?;
+        }
+        Ok(BddVariableSet {
+            variable_count,
+            metadata,
+        })
+    }
+
+    /// Create a set of `variable_count` variables named `x_0`, `x_1`, etc.
+    pub fn new_anonymous(variable_count: u16) -> BddVariableSet {
+        let names: Vec<String> = (0..variable_count).map(|i| format!("x_{}", i)).collect();
+        let names: Vec<&str> = names.iter().map(|it| it.as_str()).collect();
+        // Generated names are unique and valid.
+        BddVariableSet::new(&names).unwrap()
+    }
+
+    /// The number of variables in this set.
+    pub fn variable_count(&self) -> u16 {
+        self.variable_count
+    }
+
+    /// All variables of this set (in increasing order).
+    pub fn variables(&self) -> Vec<VariableId> {
+        (0..self.variable_count).map(VariableId).collect()
+    }
+
+    /// The names of the variables of this set.
+    pub fn metadata(&self) -> &VariableMetadata {
+        &self.metadata
+    }
+
+    /// The variable with the given `name`, if it is in this set.
+    pub fn find_variable(&self, name: &str) -> Option<VariableId> {
+        self.metadata.find(name)
+    }
+
+    /// The name of the given `variable`, if it is in this set.
+    pub fn name_of(&self, variable: VariableId) -> Option<&str> {
+        self.metadata.name(variable)
+    }
+
+    /// A `Bdd` of the constant `false`.
+    pub fn mk_false(&self) -> Bdd {
+        self.finish(Bdd::new_false())
+    }
+
+    /// A `Bdd` of the constant `true`.
+    pub fn mk_true(&self) -> Bdd {
+        self.finish(Bdd::new_true())
+    }
+
+    /// A `Bdd` of the given `variable`.
+    ///
+    /// Panics if the `variable` is not in this set.
+    pub fn mk_var(&self, variable: VariableId) -> Bdd {
+        self.mk_literal(variable, true)
+    }
+
+    /// A `Bdd` of the variable with the given `name`, or an error if there is no such
+    /// variable in this set.
+    pub fn mk_var_by_name(&self, name: &str) -> Result<Bdd, String> {
+        self.find_variable(name)
+            .map(|variable| self.mk_var(variable))
+            .ok_or_else(|| format!("Unknown variable `{}`.", name))
+    }
+
+    /// A `Bdd` of the literal `variable = value`.
+    ///
+    /// Panics if the `variable` is not in this set.
+    pub fn mk_literal(&self, variable: VariableId, value: bool) -> Bdd {
+        self.mk_cube(&[(variable, value)])
+    }
+
+    /// A `Bdd` of the conjunction of the given literals. Duplicate literals are allowed,
+    /// and a cube with both values of some variable is the constant `false`.
+    ///
+    /// Panics if one of the variables is not in this set.
+    pub fn mk_cube(&self, literals: &[(VariableId, bool)]) -> Bdd {
+        for (variable, _) in literals {
+            self.check_variable(*variable);
+        }
+        let mut literals = literals.to_vec();
+        literals.sort();
+        literals.dedup();
+        if literals.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+            return self.mk_false();
+        }
+        // The cube is built bottom-up, starting with the largest variable.
+        let mut result = Bdd::true_with_capacity(literals.len() + 2);
+        let mut next = NodeId::ONE;
+        for (variable, value) in literals.into_iter().rev() {
+            let node = if value {
+                BddNode::pack(variable, NodeId::ZERO, next)
+            } else {
+                BddNode::pack(variable, next, NodeId::ZERO)
+            };
+            next = result.push_node(node);
+        }
+        self.finish(result)
+    }
+
+    /// Build the `Bdd` of the given `expression` (see `Bdd::from_expression`), where the
+    /// variables are identified by their names in this set.
+    pub fn eval_expression(&self, expression: &BooleanExpression) -> Result<Bdd, String> {
+        let names: Vec<&str> = self
+            .variables()
+            .into_iter()
+            .map(|variable| self.name_of(variable).unwrap_or_default())
+            .collect();
+        Bdd::from_expression(expression, &names)
+    }
+
+    /// Parse the given `expression` (see `BooleanExpression::try_from`) and build its `Bdd`.
+    pub fn eval_expression_string(&self, expression: &str) -> Result<Bdd, String> {
+        self.eval_expression(&BooleanExpression::try_from(expression)?)
+    }
+
+    /// **(internal)** Panic if the given `variable` is not in this set.
+    fn check_variable(&self, variable: VariableId) {
+        if variable.0 >= self.variable_count {
+            panic!(
+                "Variable {} is not one of the {} variables.",
+                variable.0, self.variable_count
+            );
+        }
+    }
+
+    /// **(internal)** Extend the variable count of a `Bdd` to the variables of this set.
+    fn finish(&self, mut bdd: Bdd) -> Bdd {
+        bdd.update_variable_count(self.variable_count);
+        bdd
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::{Bdd, BddVariableSet, VariableId};
+
+    #[test]
+    pub fn variable_set_test() {
+        let set = BddVariableSet::new(&["a", "b", "c"]).unwrap();
+        assert_eq!(3, set.variable_count());
+        let [a, b, c] = [0u16, 1, 2].map(VariableId::from);
+        assert!(vec![a, b, c] == set.variables());
+        assert!(Some(b) == set.find_variable("b"));
+        assert_eq!(Some("c"), set.name_of(c));
+        assert!(set.find_variable("d").is_none());
+        assert!(BddVariableSet::new(&["a", "b", "a"]).is_err());
+
+        // Every `Bdd` has all the variables of the set.
+        for bdd in [set.mk_false(), set.mk_true(), set.mk_var(a)] {
+            assert_eq!(3, bdd.variable_count());
+        }
+        let va = Bdd::new_variable(a);
+        let vc = Bdd::new_variable(c);
+        assert!(set.mk_var_by_name("a").unwrap().semantic_eq(&va));
+        assert!(set.mk_var_by_name("d").is_err());
+        assert!(set.mk_literal(c, false).semantic_eq(&vc.not()));
+
+        // a & !c, with a duplicate literal.
+        let cube = set.mk_cube(&[(c, false), (a, true), (c, false)]);
+        assert!(cube.semantic_eq(&va.and_not(&vc)));
+        assert_eq!(4, cube.node_count());
+        assert!(cube.check_integrity().is_ok());
+        assert!(set
+            .mk_cube(&[(a, true), (a, false)])
+            .semantic_eq(&set.mk_false()));
+        assert!(set.mk_cube(&[]).semantic_eq(&set.mk_true()));
+
+        let bdd = set.eval_expression_string("a & !c | b").unwrap();
+        assert!(bdd.semantic_eq(&va.and_not(&vc).or(&Bdd::new_variable(b))));
+        assert!(set.eval_expression_string("a & d").is_err());
+
+        let anonymous = BddVariableSet::new_anonymous(2);
+        assert!(Some(VariableId::from(1u16)) == anonymous.find_variable("x_1"));
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn variable_set_foreign_variable_test() {
+        BddVariableSet::new_anonymous(2).mk_var(VariableId::from(2u16));
+    }
+}
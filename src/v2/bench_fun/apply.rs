@@ -1,9 +1,10 @@
+use crate::packing;
 use crate::v2::bench_fun::deps::{BddNode, NodeId, VariableId};
 use fxhash::hash;
 use std::cmp::max;
 use std::convert::TryFrom;
 use std::num::NonZeroU64;
-use std::ops::{BitXor, Rem, Shl};
+use std::ops::{BitXor, Rem};
 //use bitintr::Pdep;
 
 pub struct NodeCache {
@@ -31,7 +32,7 @@ impl NodeCache {
     pub fn ensure(&mut self, node: BddNode) -> (NodeId, usize) {
         let hashed_position = self.hash(&node);
         unsafe {
-            let packed = ((node.1 .0 | (node.0 .0 as u64).shl(48u64)), node.2 .0);
+            let packed = (packing::pack(node.0 .0, node.1 .0), node.2 .0);
             let cell_index = self.hashes.get_unchecked_mut(hashed_position);
             if *cell_index == 0 {
                 // This hash was not seen before
@@ -78,7 +79,7 @@ impl NodeCache {
         let base = max(node.low_link().0, node.high_link().0);
         unsafe {
             let pointer: *const usize = self.hashes.get_unchecked((base as usize) + 128);
-            std::arch::x86_64::_mm_prefetch::<3>(pointer as *const i8);
+            crate::arch::prefetch(pointer);
         }
         (base + block_index).rem(self.capacity) as usize
         //low_hash.bitxor(high_hash).rem(self.capacity) as usize
@@ -169,7 +170,7 @@ impl TaskCache {
             // the pointer chasing in node cache, it only adds 5-10% in the main algorithm.
             let pointer: *const ((NodeId, NodeId), NodeId) =
                 self.keys.get_unchecked((block_start as usize) + 128);
-            std::arch::x86_64::_mm_prefetch::<3>(pointer as *const i8);
+            crate::arch::prefetch(pointer);
         }
         (block_start + block_index).rem(self.capacity) as usize
     }
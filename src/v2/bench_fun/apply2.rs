@@ -1,3 +1,4 @@
+use crate::packing;
 use crate::v2::bench_fun::deps::{BddNode, NodeId, PackedBddNode};
 use bitintr::Pdep;
 use likely_stable::unlikely;
@@ -29,7 +30,7 @@ pub struct Stack2 {
 }
 
 impl Stack2 {
-    const NEEDS_DECODE_MASK: u64 = (u16::MAX as u64) << 48;
+    const NEEDS_DECODE_MASK: u64 = packing::VARIABLE_MASK;
 
     pub fn new(variable_count: u16) -> Stack2 {
         let mut stack = Stack2 {
@@ -244,7 +245,7 @@ impl TaskCache2 {
     }
 
     pub fn grow(&mut self) {
-        println!("Task cache is growing!"); // TODO: Remove
+        trace!("Task cache is growing!");
         let right_len_log_2 = 63 - self.right_len.leading_zeros();
         let right_len_log_log_2 = 31 - right_len_log_2.leading_zeros();
         // log2(right_len) rounded up to the next larger power of two
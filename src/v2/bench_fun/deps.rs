@@ -1,8 +1,12 @@
+use crate::packing;
+#[cfg(feature = "cudd-interop")]
 use cudd_sys::cudd::{
     Cudd_ReadLogicZero, Cudd_ReadOne, Cudd_ReadZero, Cudd_Ref, Cudd_bddIte, Cudd_bddIthVar,
 };
+#[cfg(feature = "cudd-interop")]
 use cudd_sys::DdNode;
 use std::convert::TryFrom;
+#[cfg(feature = "cudd-interop")]
 use std::os::raw::c_int;
 
 /*
@@ -73,7 +77,7 @@ impl BddNode {
     }
 
     pub fn pack(self) -> PackedBddNode {
-        let packed_high = u64::from(self.2) | (u64::from(self.0 .0) << 48);
+        let packed_high = packing::pack(self.0 .0, u64::from(self.2));
         PackedBddNode(u64::from(self.1), packed_high)
     }
 }
@@ -130,10 +134,8 @@ impl Bdd {
     pub(crate) fn prefetch(&self, id: NodeId) {
         unsafe {
             // Prefetch operations ignore memory errors and are therefore "externally safe".
-            if cfg!(target_arch = "x86_64") {
-                let reference: *const BddNode = self.nodes.get_unchecked(id.0 as usize);
-                std::arch::x86_64::_mm_prefetch::<3>(reference as *const i8);
-            }
+            let reference: *const BddNode = self.nodes.get_unchecked(id.0 as usize);
+            crate::arch::prefetch(reference);
         }
     }*/
 
@@ -156,6 +158,7 @@ impl Bdd {
 }
 
 impl Bdd {
+    #[cfg(feature = "cudd-interop")]
     pub fn move_to_cudd(&self, manager: *mut cudd_sys::DdManager) -> *mut DdNode {
         let mut stack = Vec::with_capacity(2 * self.variable_count() as usize);
         stack.push(self.root_node());
@@ -16,8 +16,7 @@ pub mod coupled_dfs;
 pub mod deps;
 pub mod ooo;
 
-const VARIABLE_MASK: u64 = (u16::MAX as u64) << 48;
-const ID_MASK: u64 = !VARIABLE_MASK;
+use crate::packing;
 
 pub fn apply(left_bdd: &Bdd, right_bdd: &Bdd) -> Bdd {
     let variables = left_bdd.variable_count();
@@ -117,7 +116,7 @@ pub fn apply(left_bdd: &Bdd, right_bdd: &Bdd) -> Bdd {
         }
     }
 
-    println!("uOps: {}; cycles: {};", uops, cycles);
+    debug!("uOps: {}; cycles: {};", uops, cycles);
 
     /*for (i, node) in nodes.iter().enumerate() {
         if i + 10 < nodes.len() {
@@ -145,8 +144,8 @@ pub fn apply(left_bdd: &Bdd, right_bdd: &Bdd) -> Bdd {
     let node_count = node_cache.index_after_last;
 
     /*for (node_data, _) in nodes.iter().take(node_count) {
-        let (low, high) = (NodeId(node_data.0 & ID_MASK), NodeId(node_data.1));
-        let variable = ((node_data.0 & VARIABLE_MASK) >> 48) as u16;
+        let (low, high) = (NodeId(packing::id(node_data.0)), NodeId(node_data.1));
+        let variable = packing::variable(node_data.0);
         println!("{:?} {:?} {:?}", variable, low, high);
     }*/
 
@@ -170,7 +169,7 @@ pub fn apply(left_bdd: &Bdd, right_bdd: &Bdd) -> Bdd {
         // Unpack node
         let top = unsafe { *stack.get_unchecked(index_after_last) };
         let node_data = unsafe { nodes.get_unchecked_mut(top.as_index_unchecked()) };
-        let (low, high) = (NodeId(node_data.0 .0 & ID_MASK), NodeId(node_data.0 .1));
+        let (low, high) = (NodeId(packing::id(node_data.0 .0)), NodeId(node_data.0 .1));
 
         // Save index
         node_data.1 = new_index;
@@ -205,9 +204,9 @@ pub fn apply(left_bdd: &Bdd, right_bdd: &Bdd) -> Bdd {
 
     for i in 2..node_count {
         let original_node = unsafe { nodes.get_unchecked(i) };
-        let variable = ((original_node.0 .0 & VARIABLE_MASK) >> 48) as u16;
+        let variable = packing::variable(original_node.0 .0);
         let (low, high) = (
-            NodeId(original_node.0 .0 & ID_MASK),
+            NodeId(packing::id(original_node.0 .0)),
             NodeId(original_node.0 .1),
         );
 
@@ -37,7 +37,7 @@
 use std::cmp::min;
 use crate::v2::bench_fun::apply::{NodeCache, TaskCache};
 use crate::v2::bench_fun::deps::{Bdd, BddNode, NodeId, VariableId};
-use crate::v2::bench_fun::{ID_MASK, VARIABLE_MASK};
+use crate::packing;
 
 /// Reorder buffer is super simple. It basically just allocates slots for node ids that
 /// will be computed in the future. To track free cells, we use a linked list.
@@ -525,7 +525,7 @@ pub fn apply(left_bdd: &Bdd, right_bdd: &Bdd) -> Bdd {
         // Unpack node
         let top = unsafe { *stack.get_unchecked(index_after_last) };
         let node_data = unsafe { nodes.get_unchecked_mut(top.as_index_unchecked()) };
-        let (low, high) = (NodeId(node_data.0 .0 & ID_MASK), NodeId(node_data.0 .1));
+        let (low, high) = (NodeId(packing::id(node_data.0 .0)), NodeId(node_data.0 .1));
 
         // Save index
         node_data.1 = new_index;
@@ -560,9 +560,9 @@ pub fn apply(left_bdd: &Bdd, right_bdd: &Bdd) -> Bdd {
 
     for i in 2..node_count {
         let original_node = unsafe { nodes.get_unchecked(i) };
-        let variable = ((original_node.0 .0 & VARIABLE_MASK) >> 48) as u16;
+        let variable = packing::variable(original_node.0 .0);
         let (low, high) = (
-            NodeId(original_node.0 .0 & ID_MASK),
+            NodeId(packing::id(original_node.0 .0)),
             NodeId(original_node.0 .1),
         );
 
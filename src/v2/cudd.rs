@@ -0,0 +1,163 @@
+//! Conversion between `Bdd` objects and CUDD diagrams (requires the `cudd-interop` feature).
+//!
+//! The variable `VariableId::from(i)` of a `Bdd` is the CUDD variable with index `i`. CUDD uses
+//! complement edges, so a CUDD node which is reachable both through a regular and through
+//! a complemented edge is converted into two `Bdd` nodes (the function and its negation).
+//!
+//! All functions work with raw CUDD pointers, so they are `unsafe`: the `manager` must be
+//! a valid CUDD manager and every node must belong to this manager.
+
+use crate::v2::{Bdd, BddNode, NodeId, VariableId};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use cudd_sys::cudd::{
+    Cudd_E, Cudd_IsComplement, Cudd_IsConstant, Cudd_Not, Cudd_NodeReadIndex, Cudd_ReadLogicZero,
+    Cudd_ReadOne, Cudd_ReadSize, Cudd_RecursiveDeref, Cudd_Ref, Cudd_Regular, Cudd_T,
+    Cudd_bddIte, Cudd_bddIthVar,
+};
+use cudd_sys::{DdManager, DdNode};
+use std::collections::HashMap;
+use std::os::raw::c_int;
+
+impl Bdd {
+    /// Build the CUDD diagram of this `Bdd` in the given `manager`.
+    ///
+    /// The result is referenced, so it is the responsibility of the caller to release it
+    /// (using `Cudd_RecursiveDeref`) once it is no longer needed.
+    ///
+    /// # Safety
+    ///
+    /// The `manager` must be a valid CUDD manager.
+    pub unsafe fn move_to_cudd(&self, manager: *mut DdManager) -> *mut DdNode {
+        let zero = unsafe { Cudd_ReadLogicZero(manager) };
+        let one = unsafe { Cudd_ReadOne(manager) };
+        let root = self.root_node();
+        if root.as_index() < 2 {
+            let result = if root.is_zero() { zero } else { one };
+            unsafe { Cudd_Ref(result) };
+            return result;
+        }
+
+        // Nodes which are not translated yet have a null image.
+        let mut images: Vec<*mut DdNode> = vec![core::ptr::null_mut(); self.node_count()];
+        images[0] = zero;
+        images[1] = one;
+        let mut stack = vec![root];
+        while let Some(top) = stack.last() {
+            let node = self.get_node(*top);
+            let low = images[node.low_link().as_index()];
+            let high = images[node.high_link().as_index()];
+            if low.is_null() || high.is_null() {
+                if high.is_null() {
+                    stack.push(node.high_link());
+                }
+                if low.is_null() {
+                    stack.push(node.low_link());
+                }
+                continue;
+            }
+            let variable = c_int::from(u16::from(node.variable()));
+            let image = unsafe {
+                let image = Cudd_bddIte(manager, Cudd_bddIthVar(manager, variable), high, low);
+                // The intermediate results must survive the garbage collection of CUDD.
+                Cudd_Ref(image);
+                image
+            };
+            images[top.as_index()] = image;
+            stack.pop();
+        }
+
+        let result = images[root.as_index()];
+        unsafe {
+            Cudd_Ref(result);
+            for image in images.into_iter().skip(2).filter(|it| !it.is_null()) {
+                Cudd_RecursiveDeref(manager, image);
+            }
+        }
+        result
+    }
+
+    /// Build a `Bdd` of the given CUDD `node`. The result has the same number of variables
+    /// as the `manager`.
+    ///
+    /// Fails if the variables do not grow along some path of the diagram (i.e. CUDD uses
+    /// a variable order different from the order of variable indices), or if a variable
+    /// cannot be represented by a `VariableId`.
+    ///
+    /// # Safety
+    ///
+    /// The `manager` must be a valid CUDD manager and `node` must be a node of this manager.
+    pub unsafe fn from_cudd(manager: *mut DdManager, node: *mut DdNode) -> Result<Bdd, String> {
+        let variable_count = u16::try_from(unsafe { Cudd_ReadSize(manager) })
+            .map_err(|_| String::from("Too many CUDD variables."))?;
+        let zero = unsafe { Cudd_ReadLogicZero(manager) };
+        let one = unsafe { Cudd_ReadOne(manager) };
+        let mut result = if node == zero {
+            Bdd::new_false()
+        } else if node == one {
+            Bdd::new_true()
+        } else {
+            let mut result = Bdd::true_with_capacity(2);
+            let mut ids: HashMap<*mut DdNode, NodeId> = HashMap::new();
+            ids.insert(zero, NodeId::ZERO);
+            ids.insert(one, NodeId::ONE);
+            let mut stack = vec![node];
+            while let Some(top) = stack.last().copied() {
+                if ids.contains_key(&top) {
+                    stack.pop();
+                    continue;
+                }
+                let (index, low, high) = unsafe { cudd_node(top) };
+                match (ids.get(&low), ids.get(&high)) {
+                    (Some(low), Some(high)) => {
+                        let variable = u16::try_from(index)
+                            .ok()
+                            .filter(|it| *it <= VariableId::MAX.0)
+                            .ok_or_else(|| format!("Invalid CUDD variable {}.", index))?;
+                        let variable = VariableId::from(variable);
+                        for child in [low, high] {
+                            if result.get_node(*child).variable() <= variable {
+                                return Err(format!(
+                                    "CUDD variable {} is not ordered before its successors.",
+                                    index
+                                ));
+                            }
+                        }
+                        let id = result.push_node(BddNode::pack(variable, *low, *high));
+                        ids.insert(top, id);
+                        stack.pop();
+                    }
+                    (low_id, high_id) => {
+                        if high_id.is_none() {
+                            stack.push(high);
+                        }
+                        if low_id.is_none() {
+                            stack.push(low);
+                        }
+                    }
+                }
+            }
+            result
+        };
+        result.update_variable_count(variable_count);
+        Ok(result)
+    }
+}
+
+/// **(internal)** The variable index and the (low, high) successors of a non-terminal CUDD
+/// node, with the complement of the edge pointing to the `node` pushed to the successors.
+unsafe fn cudd_node(node: *mut DdNode) -> (u32, *mut DdNode, *mut DdNode) {
+    unsafe {
+        let regular = Cudd_Regular(node);
+        debug_assert!(Cudd_IsConstant(regular) == 0);
+        let (mut low, mut high) = (Cudd_E(regular), Cudd_T(regular));
+        if Cudd_IsComplement(node) != 0 {
+            low = Cudd_Not(low);
+            high = Cudd_Not(high);
+        }
+        (Cudd_NodeReadIndex(regular), low, high)
+    }
+}
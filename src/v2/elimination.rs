@@ -0,0 +1,94 @@
+//! Bucket elimination: existential quantification of a conjunction of `Bdds` without
+//! building the (usually very large) monolithic conjunction first.
+//!
+//! The variables are eliminated one by one in the given order. To eliminate a variable,
+//! all functions which depend on it (its "bucket") are conjoined and the variable is
+//! quantified in the same pass (`ApplyContext::and_exists`). The result no longer depends
+//! on the variable and is placed back among the remaining functions, i.e. into the bucket
+//! of the next variable which it depends on.
+//!
+//! All intermediate results are stored in a single `ApplyContext`, so the conjunctions,
+//! quantifications and support computations share one node storage and operation cache.
+
+use crate::v2::{ApplyContext, Bdd, NodeId, PooledHandle, VariableId};
+use alloc::vec::Vec;
+
+/// Compute `exists elimination_order: bdds[0] & ... & bdds[n-1]` using bucket elimination.
+///
+/// The order of `elimination_order` is the order in which the variables are eliminated.
+/// It has a significant impact on the size of the intermediate results. Variables that
+/// do not appear in any of the `bdds` are ignored. The conjunction of an empty list
+/// is `true`.
+pub fn conjoin_and_quantify(bdds: &[Bdd], elimination_order: &[VariableId]) -> Bdd {
+    let mut context = ApplyContext::new();
+    // Every pending function is stored together with its support.
+    let mut pending: Vec<(PooledHandle, Vec<VariableId>)> = bdds
+        .iter()
+        .map(|bdd| {
+            let handle = context.import(bdd);
+            (handle, context.support(handle))
+        })
+        .collect();
+
+    for variable in elimination_order {
+        let (bucket, rest): (Vec<_>, Vec<_>) = pending
+            .into_iter()
+            .partition(|(_, support)| support.binary_search(variable).is_ok());
+        pending = rest;
+        let mut bucket = bucket.into_iter().map(|(handle, _)| handle);
+        let mut result = match bucket.next() {
+            Some(first) => first,
+            None => continue,
+        };
+        let last = bucket.next_back();
+        for handle in bucket {
+            result = context.and(result, handle);
+        }
+        result = match last {
+            Some(last) => context.and_exists(result, last, &[*variable]),
+            None => context.exists(result, &[*variable]),
+        };
+        if result.0.is_zero() {
+            return context.export(result);
+        }
+        pending.push((result, context.support(result)));
+    }
+
+    let result = pending
+        .into_iter()
+        .fold(PooledHandle(NodeId::ONE), |result, (handle, _)| {
+            context.and(result, handle)
+        });
+    context.export(result)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::_impl_::test_util::variables;
+    use super::conjoin_and_quantify;
+    use crate::v2::{Bdd, VariableId};
+
+    #[test]
+    pub fn bucket_elimination_test() {
+        let x = variables(5);
+        // A chain of equivalences x0 <=> x1 <=> ... <=> x4 with x0 & !x4 is unsatisfiable.
+        let mut chain: Vec<Bdd> = (0..4).map(|i| x[i].iff(&x[i + 1])).collect();
+        let order: Vec<VariableId> = (1..4u16).map(VariableId::from).collect();
+        // exists x1..x3: chain = x0 <=> x4
+        let expected = x[0].iff(&x[4]);
+        let result = conjoin_and_quantify(&chain, &order);
+        assert_eq!(2, result.iff(&expected).node_count());
+
+        chain.push(x[0].and_not(&x[4]));
+        assert_eq!(1, conjoin_and_quantify(&chain, &order).node_count());
+
+        // Functions that are not affected by the elimination are kept.
+        let functions = vec![x[0].or(&x[1]), x[1].xor(&x[2]), x[3].clone()];
+        let result = conjoin_and_quantify(&functions, &[VariableId::from(1)]);
+        // exists x1: (x0 | x1) & (x1 ^ x2) = x0 | !x2
+        let expected = x[2].imp(&x[0]).and(&x[3]);
+        assert_eq!(2, result.iff(&expected).node_count());
+
+        assert_eq!(2, conjoin_and_quantify(&[], &order).node_count());
+    }
+}
@@ -1,7 +1,24 @@
-use std::cmp::max;
-use std::convert::TryFrom;
-use std::ops::{Shl, Shr};
-
+use crate::packing;
+use core::cmp::max;
+#[cfg(feature = "std")]
+use alloc::boxed::Box;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::sync::{Mutex, RwLock};
+use core::convert::TryFrom;
+use core::sync::atomic::AtomicU64;
+#[cfg(feature = "std")]
+use core::sync::atomic::AtomicU16;
+use core::time::Duration;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use alloc::vec;
+
+#[cfg(feature = "bench")]
 pub mod bench_fun;
 
 /*
@@ -37,6 +54,25 @@ pub mod bench_fun;
 
 pub mod _impl_;
 
+/// Static heuristics and offline search for good variable orders (see `reorder::force_order`
+/// and `reorder::anneal`, requires the `std` feature).
+#[cfg(feature = "std")]
+pub mod reorder;
+
+/// Existential quantification of conjunctions using bucket elimination
+/// (see `elimination::conjoin_and_quantify`).
+pub mod elimination;
+
+/// Fixed-capacity `Bdds` which never allocate on the heap (see `small::SmallBdd`).
+pub mod small;
+
+/// Binary logical operators which can be used with `Bdd::apply` (see `ops::BooleanOp`).
+pub mod ops;
+
+/// Conversion between `Bdd` objects and CUDD (requires the `cudd-interop` feature).
+#[cfg(feature = "cudd-interop")]
+pub mod cudd;
+
 /// **(internal)** A pointer into the `Bdd` graph. Its actual range is 6 bytes, so `0..(2^48 - 1)`.
 /// This allows indexing graphs which are ~4TB each. That should be enough for the foreseeable
 /// future and a bit more.
@@ -46,7 +82,7 @@ pub mod _impl_;
 /// very much depends on context, so be careful.
 ///
 /// We *may* check some of the conversions at runtime, but in general this is an *unsafe* land.
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub struct NodeId(u64);
 
 /// Index of a `Bdd` variable. It's range is `0..(2^16 - 1)`, but the last value is reserved
@@ -68,10 +104,17 @@ impl From<u16> for VariableId {
 /// of existing diagrams.
 ///
 /// A `Bdd` object is not guaranteed to be minimal or canonical. In general we try to create `Bdds`
-/// which are as small as possible, but we prefer speed to minimality.
+/// which are as small as possible, but we prefer speed to minimality. Use `Bdd::reduce` to obtain
+/// the canonical form when structural equality has to imply semantic equality.
+#[derive(Clone)]
 pub struct Bdd {
     variable_count: u16,
+    /// True if the `nodes` are known to be sorted in DFS preorder. The flag is conservative:
+    /// `false` only means that we don't know whether the nodes are sorted.
+    is_sorted: bool,
     nodes: Vec<BddNode>,
+    /// The semantic fingerprint of the `Bdd`, computed on demand (see `Bdd::fingerprint`).
+    fingerprint: FingerprintCache,
 }
 
 impl Bdd {
@@ -86,34 +129,663 @@ impl Bdd {
 
 }
 
+/// A read-only storage of `Bdd` nodes which can be used as an operand of
+/// `Bdd::mixed_binary_operation`.
+///
+/// The nodes follow the same conventions as the nodes of a `Bdd`. This allows the operands
+/// of one operation to be stored differently, e.g. a huge memory-mapped `Bdd` and a small
+/// in-memory `Bdd`.
+///
+/// The storage does not have to keep the nodes as `BddNode` objects: it only has to provide
+/// them on request (see `SplitBdd` for a storage with a different memory layout).
+///
+/// # Safety
+///
+/// The apply kernels read the nodes without any bounds checks, so every implementation
+/// must guarantee that:
+///
+///  - There is at least one node. The first node is `BddNode::ZERO` and the second node
+///    (if any) is `BddNode::ONE`. The root is the last node.
+///  - Every link of a decision node is smaller than `BddStorage::node_count`.
+///  - Every decision node has a variable smaller than `BddStorage::variable_count`, and this
+///    variable is smaller than the variables of both its successors (the terminals use
+///    `VariableId::UNDEFINED`).
+///  - The nodes do not change while the storage is borrowed.
+///
+/// Nodes from an untrusted source should be validated first (see `BddView::try_from_nodes`).
+pub unsafe trait BddStorage {
+    /// The number of variables of the stored `Bdd`.
+    fn variable_count(&self) -> u16;
+
+    /// The number of stored nodes (including the terminals).
+    fn node_count(&self) -> usize;
+
+    /// Read the node with the given `id`.
+    ///
+    /// # Safety
+    ///
+    /// The `id` must be smaller than `BddStorage::node_count`.
+    unsafe fn get_node_unchecked(&self, id: NodeId) -> BddNode;
+
+    /// Read only the decision variable of the node with the given `id`. Storages which keep
+    /// variables separately from links can override this to avoid reading the links.
+    ///
+    /// # Safety
+    ///
+    /// The `id` must be smaller than `BddStorage::node_count`.
+    #[inline]
+    unsafe fn get_variable_unchecked(&self, id: NodeId) -> VariableId {
+        unsafe { self.get_node_unchecked(id) }.variable()
+    }
+
+    /// Hint that the node with the given `id` will be needed soon. Does nothing by default.
+    #[inline]
+    fn prefetch(&self, id: NodeId) {
+        let _ = id;
+    }
+
+    /// Copy the stored `Bdd` into a standalone `Bdd` object.
+    fn to_bdd(&self) -> Bdd;
+}
+
+/// A `Bdd` borrowed from an external node storage (e.g. a memory-mapped file).
+///
+/// The nodes are validated once when the view is created (see `BddView::try_from_nodes`).
+#[derive(Copy, Clone)]
+pub struct BddView<'a> {
+    variable_count: u16,
+    nodes: &'a [BddNode],
+}
+
+/// A `Bdd` stored in a "hot/cold split" layout: the links of all nodes are stored in one
+/// array and the decision variables in another (parallel) array.
+///
+/// The apply kernels read the links of every node they visit, but they only need
+/// the variable to decide which operand to expand. Since the links are also compressed to
+/// 32 bits, one cache line holds twice as many link pairs as in a `Bdd`. The disadvantage
+/// is that nodes can only be read (see `BddStorage`) and the number of nodes is limited
+/// to `2^32 - 1`.
+#[derive(Clone)]
+pub struct SplitBdd {
+    variable_count: u16,
+    variables: Vec<VariableId>,
+    links: Vec<(u32, u32)>,
+}
+
+/// A read-only `Bdd` stored in a memory-mapped file (requires the `mmap` feature).
+///
+/// The file is a plain array of nodes in the same order as in a `Bdd` (terminals first, root
+/// last), every node stored as two little-endian `u64` values of the packed `BddNode`.
+/// The operating system only keeps the recently used pages in memory, so the `Bdd` can be much
+/// larger than the available RAM. It can be used as an operand of any operation which accepts
+/// a `BddStorage`, and `Bdd::external_binary_operation` writes its result into such a file.
+///
+/// The nodes are validated once when the file is opened (see `MmapBddStorage::open`).
+/// The file must not be modified while it is mapped.
+#[cfg(feature = "mmap")]
+pub struct MmapBddStorage {
+    variable_count: u16,
+    map: memmap2::Mmap,
+}
+
+/// An error reported when a value exceeds the limits of the `Bdd` representation
+/// (see `Bdd::MAX_VARIABLE_COUNT` and `Bdd::MAX_NODE_COUNT`).
+///
+/// The error converts into a `String`, so it can be propagated by the functions which
+/// report errors as messages.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum LimitError {
+    /// `VariableId::UNDEFINED` is reserved for terminal nodes and cannot be used
+    /// as a decision variable.
+    ReservedVariable,
+    /// The given number of nodes is larger than `Bdd::MAX_NODE_COUNT`.
+    TooManyNodes(u64),
+}
+
+/// A structural defect of a `Bdd`, reported by `Bdd::check_integrity`.
+///
+/// Every variant carries the id of the first node which has the defect.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum IntegrityError {
+    /// The node vector is empty.
+    Empty,
+    /// A terminal slot (`0` or `1`) does not contain the expected terminal node,
+    /// or a decision slot contains a terminal node.
+    Terminal(NodeId),
+    /// A link of the node points outside of the node vector.
+    InvalidLink(NodeId),
+    /// A successor of the node does not have a larger variable than the node.
+    VariableOrder(NodeId),
+    /// The node is not reachable from the root.
+    Unreachable(NodeId),
+}
+
+/// An implementation of the `apply` algorithm used by binary operations on `Bdds`.
+///
+/// The kernels differ in the size of the internal pointers, and therefore in the size
+/// of the operands which they can process.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ApplyKernel {
+    /// Uses 16-bit pointers and the fixed-size arrays of a `SmallBdd`, so it never allocates
+    /// memory until the result is exported. Requires both operands to have at most
+    /// `dispatcher::SMALL_KERNEL_CAPACITY` nodes and at most `small::MAX_VARIABLES`
+    /// variables. If the result does not fit, the operation is repeated with `U32`.
+    U16,
+    /// Uses 32-bit pointers into the operands. Requires the left operand to have less
+    /// than `2^32 - 1` and the right operand less than `2^31 - 1` nodes.
+    U32,
+    /// Uses 48-bit pointers, so it works for any `Bdd`.
+    U48,
+}
+
+/// A logical operation on two `Bdds`, used to select the operation at runtime
+/// (see `Bdd::apply_op`).
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum BoolOp {
+    /// Conjunction (`Bdd::and`).
+    And,
+    /// Disjunction (`Bdd::or`).
+    Or,
+    /// Exclusive disjunction (`Bdd::xor`).
+    Xor,
+    /// Implication (`Bdd::imp`).
+    Imp,
+    /// Equivalence (`Bdd::iff`).
+    Iff,
+    /// Conjunction with a negated right operand (`Bdd::and_not`).
+    AndNot,
+    /// Conjunction with a negated left operand, i.e. `!left & right`.
+    NotAnd,
+}
+
+/// Options of binary operations on `Bdds` (e.g. `Bdd::and_with`).
+///
+/// By default, everything is selected automatically (see `binary_operations::dispatcher`
+/// for the exact rules). The options can override these choices.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct ApplyOptions {
+    /// Use this kernel instead of the automatically selected one. If the operands are too
+    /// large for the kernel, the smallest larger kernel which fits is used instead.
+    pub kernel: Option<ApplyKernel>,
+    /// Always (`Some(true)`) or never (`Some(false)`) sort unsorted operands in DFS preorder
+    /// before the operation starts.
+    pub precondition: Option<bool>,
+    /// The largest amount of extra memory (in bytes) that can be used for sorted copies
+    /// of the operands. Unlimited by default.
+    pub memory_budget: Option<usize>,
+    /// The configuration of the task cache of the `U32` and `U48` kernels.
+    pub task_cache: TaskCacheConfig,
+}
+
+/// Configuration of the task cache used by the apply algorithms (see `ApplyOptions`
+/// and `v4::apply::apply_with_config`).
+///
+/// The task cache is "leaky": when two tasks compete for the same space, one of them
+/// is forgotten and may have to be recomputed later. By default, the cache has one entry
+/// for every node of the larger operand, which is usually enough. For very large operations,
+/// a larger (or two-way) cache trades memory for a better hit rate, while a smaller cache
+/// limits the memory used by the operation.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct TaskCacheConfig {
+    /// The number of cache entries, regardless of the size of the operands.
+    ///
+    /// In `v4`, this is the largest size to which the (growing) cache can grow.
+    pub capacity: Option<usize>,
+    /// What happens when a new entry does not fit into the cache.
+    pub replacement: ReplacementPolicy,
+    /// How the position of a task in the cache is computed.
+    pub hash: TaskHash,
+    /// How the large buffers of the operation (the task cache, the node cache and the result
+    /// nodes) are allocated. Currently only used by the `v2` kernels.
+    pub alloc: BddAllocConfig,
+}
+
+/// The replacement policy of a task cache (see `TaskCacheConfig`).
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum ReplacementPolicy {
+    /// Every task has exactly one slot, and a new entry overwrites the previous one (default).
+    #[default]
+    Overwrite,
+    /// The cache consists of buckets with two slots. A new entry is always stored into
+    /// the first slot, and the previous entry of the first slot is moved into the second slot.
+    /// Reads are slightly slower, but two conflicting tasks can be cached at the same time.
+    TwoWay,
+}
+
+/// The hash function of a task cache (see `TaskCacheConfig`).
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum TaskHash {
+    /// Tasks are hashed into a small window that moves with the pointer into the larger
+    /// operand (default). This gives good memory locality when the operands are sorted
+    /// in DFS preorder.
+    #[default]
+    Rolling,
+    /// Tasks are hashed uniformly over the whole cache. This has fewer collisions, but poor
+    /// memory locality. It can be better for operands which are not sorted.
+    Uniform,
+}
+
+/// Configuration of the memory allocated by binary operations (see `TaskCacheConfig::alloc`).
+///
+/// The caches of large operations are accessed randomly, so a large part of the runtime
+/// can be spent on TLB misses. Backing the caches with 2MB huge pages makes these misses
+/// much less frequent.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct BddAllocConfig {
+    /// Ask the operating system to back the buffers larger than `HUGE_PAGE_SIZE` with
+    /// transparent huge pages (using `madvise`). Requires Linux and the `huge-pages` feature,
+    /// otherwise the flag is ignored. The system must allow transparent huge pages
+    /// (`/sys/kernel/mm/transparent_hugepage/enabled` is `always` or `madvise`).
+    pub huge_pages: bool,
+}
+
+/// The size of the huge pages requested by `BddAllocConfig::huge_pages`.
+pub const HUGE_PAGE_SIZE: usize = 2 << 20;
+
+/// The progress of a binary operation, reported when the operation is aborted
+/// (see `Bdd::binary_operation_with_timeout`), or at the end of `Bdd::apply_with_stats`.
+///
+/// Compared with the size of the operands, the counters help to distinguish an operation
+/// that was almost done from one that is blowing up: e.g. a high node cache hit rate and
+/// a `deepest_level` close to the variable count mean the search is already revisiting
+/// known parts of the result.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct ApplyDiagnostics {
+    /// The time spent in the operation.
+    pub elapsed: Duration,
+    /// The number of tasks which were expanded (i.e. not resolved by the lookup table
+    /// or the task cache).
+    pub tasks_expanded: u64,
+    /// The number of tasks which were resolved by the task cache.
+    pub task_cache_hits: u64,
+    /// The number of node cache lookups (one for every non-redundant result node).
+    pub node_lookups: u64,
+    /// The number of nodes which were created (i.e. not found in the node cache).
+    pub nodes_created: u64,
+    /// The largest decision variable of an expanded task.
+    pub deepest_level: u16,
+    /// The largest number of entries on the task stack (at most twice the sum of the operand
+    /// heights, plus one).
+    pub stack_high_water: usize,
+}
+
 /// A `BddNode` packs together the decision variable and two pointers: low/high. It is slightly
 /// more memory efficient than just storing the values directly.
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
 pub struct BddNode(u64, u64);
 
-/// A collection of binary decision diagrams.
-pub struct BddPool {}
+// The kernels and the memory estimates (e.g. `ApplyOptions::memory_budget`) assume that
+// a node takes exactly two words.
+assert_eq_size!(BddNode, [u64; 2]);
+const_assert_eq!(core::mem::align_of::<BddNode>(), 8);
+
+/// A collection of binary decision diagrams (requires the `std` feature).
+///
+/// All diagrams in the pool share one node storage and one unique table, so structurally
+/// identical nodes are only stored once. Individual diagrams are referenced using
+/// a `PooledHandle`.
+///
+/// Node ids are stable: interning never changes the ids of existing nodes, so a handle stays
+/// valid across any number of operations. Only compaction (`BddPool::sort_preorder`, also
+/// called by `BddPool::maintain`) renumbers the nodes. Every compaction starts a new
+/// `BddPool::epoch`, so that references stored outside of the pool can detect that they
+/// are no longer valid.
+#[cfg(feature = "std")]
+pub struct BddPool {
+    variable_count: u16,
+    /// Shared with `FrozenBdd` snapshots, so it is copied on write while a snapshot exists.
+    nodes: Arc<Vec<BddNode>>,
+    unique_table: HashMap<BddNode, NodeId>,
+    /// The number of decision nodes with a "far" low link (see `BddPool::layout_drift`).
+    far_links: usize,
+    /// The `layout_drift` above which `BddPool::maintain` sorts the pool.
+    auto_sort_threshold: Option<f64>,
+    /// The number of compactions of the pool (see `BddPool::epoch`).
+    epoch: u64,
+    /// The number of variables whose literals are stored at fixed positions right after
+    /// the terminals (see `BddPool::with_literals`).
+    literal_count: u16,
+    /// The largest `node_count` the pool ever reached (see `BddPool::usage`).
+    peak_nodes: usize,
+    /// The number of nodes created by the pool so far (see `BddPool::usage`).
+    created_nodes: u64,
+    /// Callbacks registered using `BddPool::add_watermark`.
+    watermarks: Vec<PoolWatermark>,
+}
+
+/// Node usage statistics of a `BddPool` (see `BddPool::usage`).
+#[cfg(feature = "std")]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct PoolUsage {
+    /// The number of nodes currently stored in the pool, including terminals.
+    pub live_nodes: usize,
+    /// The high watermark of `live_nodes` (since the pool was created).
+    pub peak_nodes: usize,
+    /// The total number of nodes created by the pool (including nodes which were
+    /// already removed by compaction).
+    pub created_nodes: u64,
+}
+
+/// A threshold of a `BddPool` watermark callback (see `BddPool::add_watermark`).
+#[cfg(feature = "std")]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Watermark {
+    /// Reached when `PoolUsage::live_nodes` is at least the given value. The watermark
+    /// is reached again once the pool is compacted below the value and grows back.
+    LiveNodes(usize),
+    /// Reached (once) when `PoolUsage::created_nodes` is at least the given value.
+    CreatedNodes(u64),
+}
+
+/// **(internal)** A `Watermark` together with its callback.
+#[cfg(feature = "std")]
+struct PoolWatermark {
+    watermark: Watermark,
+    /// False once the callback was called, until the watermark can be reached again.
+    armed: bool,
+    callback: Box<dyn FnMut(&PoolUsage) + Send>,
+}
+
+/// A read-only snapshot of a `Bdd` stored in a `BddPool` (see `BddPool::freeze`).
+///
+/// The snapshot shares the node storage of the pool at the time it was created, so creating
+/// it is cheap and it is not affected by later changes of the pool: the pool copies its
+/// nodes before it modifies them again. A `FrozenBdd` can be sent to other threads and used
+/// as an operand of `Bdd::mixed_binary_operation` (it implements `BddStorage`).
+#[cfg(feature = "std")]
+#[derive(Clone)]
+pub struct FrozenBdd {
+    variable_count: u16,
+    nodes: Arc<Vec<BddNode>>,
+    root: NodeId,
+}
+
+/// A collection of binary decision diagrams which can be shared between threads (requires
+/// the `std` feature).
+///
+/// Same as in a `BddPool`, all diagrams share one node storage and one unique table. However,
+/// every operation only needs `&self`, so several threads can run independent operations
+/// on the same pool at the same time (e.g. using `std::thread::scope` or an `Arc`). The unique
+/// table is split into stripes, each protected by its own lock, and the node storage is only
+/// locked for writing while a new node is appended. Every operation uses its own task cache.
+///
+/// Nodes are never removed or renumbered, so a handle stays valid for the lifetime
+/// of the pool.
+#[cfg(feature = "std")]
+pub struct SyncBddPool {
+    variable_count: AtomicU16,
+    nodes: RwLock<Vec<BddNode>>,
+    unique_table: Vec<Mutex<HashMap<BddNode, NodeId>>>,
+}
+
+/// A profiler which attributes the duration and the result size of binary operations
+/// on `Bdds` to labels supplied by the user (requires the `std` feature, not available
+/// in the `wasm` build).
+///
+/// Operations are only recorded while the profiler runs (see `Profiler::record`), and each
+/// operation is attributed to the stack of labels entered using `Profiler::label` in the same
+/// thread, e.g. the components of a model. The statistics can be exported in the collapsed
+/// stack format of flame graph tools (see `Profiler::collapsed_stacks`).
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+#[derive(Clone, Default)]
+pub struct Profiler {
+    /// Statistics of every call site, keyed by its collapsed stack (`label;...;operation`).
+    entries: BTreeMap<String, ProfileEntry>,
+}
+
+/// The statistics of one call site of a `Profiler`.
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct ProfileEntry {
+    /// The number of recorded operations.
+    pub calls: u64,
+    /// The total duration of the recorded operations.
+    pub duration: Duration,
+    /// The total number of nodes of the results of the recorded operations.
+    pub nodes: u64,
+}
+
+/// The value used as the weight of a flame graph frame (see `Profiler::collapsed_stacks`).
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ProfileWeight {
+    /// The duration of the operations (in microseconds).
+    Time,
+    /// The number of nodes of the results.
+    Nodes,
+}
+
+/// A reference to the root node of a `Bdd` stored in a `BddPool` or an `ApplyContext`.
+///
+/// A handle is only meaningful for the pool (context) which created it.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct PooledHandle(NodeId);
+
+/// A long-lived context for `Bdd` operations.
+///
+/// Same as `BddPool`, the context stores multiple `Bdds` in a shared node storage with
+/// a unique table. Additionally, it keeps an operation cache which persists across
+/// operations, so repeated (or related) operations reuse previously computed results.
+///
+/// All the state of the context is stored in copy-on-write chunks. As a consequence,
+/// `ApplyContext::snapshot` is cheap (it only copies a list of chunk references) and the
+/// context only copies the chunks which are modified after the snapshot was taken.
+#[derive(Clone)]
+pub struct ApplyContext {
+    variable_count: u16,
+    nodes: CowChunks<BddNode>,
+    /// An open-addressing hash table with `NodeId::UNDEFINED` as the empty slot.
+    /// Its size is always a power of two.
+    unique_table: CowChunks<NodeId>,
+    /// A lossy (direct-mapped) cache of operation results.
+    operation_cache: CowChunks<CacheEntry>,
+    /// The generation of the valid `operation_cache` entries (see
+    /// `ApplyContext::clear_operation_cache`).
+    generation: u32,
+}
+
+/// A saved state of an `ApplyContext` (see `ApplyContext::snapshot`).
+#[derive(Clone)]
+pub struct ContextSnapshot(ApplyContext);
+
+/// The state of an iterative image computation in an `ApplyContext` (e.g. reachability).
+///
+/// Instead of the full set of states computed in each iteration, a `Delta` keeps the set of
+/// all `reached` states together with the `frontier`, i.e. the states that are new in the last
+/// iteration (`new \ old`). Only the frontier needs to be explored in the next iteration
+/// (see `ApplyContext::delta_image`).
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Delta {
+    reached: PooledHandle,
+    frontier: PooledHandle,
+}
+
+/// A `Bdd` split into independently computed parts: a list of `(cube, bdd)` pairs such that
+/// the represented function is the disjunction of all `cube & bdd`.
+///
+/// The cubes are conjunctions of literals and they are pairwise disjoint. Parts whose `bdd`
+/// is `false` are not stored. The parts are only recombined into a single `Bdd` on request
+/// (`PartitionedBdd::to_bdd`), so a result which would not fit into a single node table
+/// can still be processed part by part (see `Bdd::partitioned_operation`).
+#[derive(Clone)]
+pub struct PartitionedBdd {
+    variable_count: u16,
+    parts: Vec<(Bdd, Bdd)>,
+}
+
+/// A symbolic bit-vector: a vector of `Bdds` stored in an `ApplyContext`, where the `i`-th
+/// `Bdd` is the `i`-th bit of the vector (starting with the least significant bit).
+///
+/// Arithmetic on bit-vectors (`ApplyContext::vec_add`, `ApplyContext::vec_less_than`, ...)
+/// is implemented using the operations of the context, so all bits share its node storage
+/// and operation cache. Vectors are unsigned and arithmetic wraps around.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct BddVec {
+    bits: Vec<PooledHandle>,
+}
+
+/// A matrix of bits, used as a batch of valuations of `Bdd` variables (see `Bdd::eval_batch`):
+/// every column is one valuation and row `v` contains the values of variable `v`.
+///
+/// The matrix is stored row by row with 64 columns per word ("bitsliced"), so that one word
+/// operation processes 64 valuations at once.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct BitMatrix {
+    rows: usize,
+    columns: usize,
+    words: Vec<u64>,
+}
+
+/// A fixed-length vector of bits, packed 64 bits per word.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct BitVec {
+    len: usize,
+    words: Vec<u64>,
+}
+
+/// A lazily evaluated expression over `Bdds` (see `Bdd::lazy`).
+///
+/// Operations on a `LazyBdd` only record the expression. The whole expression is executed
+/// by `LazyBdd::evaluate` in a single `ApplyContext`: the intermediate results share one
+/// node storage and one operation cache, they are never converted into standalone `Bdds`,
+/// and operands which appear in the expression multiple times are only imported once.
+#[derive(Clone)]
+pub struct LazyBdd<'a> {
+    /// The distinct operands of the expression.
+    operands: Vec<&'a Bdd>,
+    /// The expression in postfix order.
+    steps: Vec<LazyStep>,
+}
+
+/// A collection of named `Bdds` (roots) stored in one shared `ApplyContext` (e.g. the outputs
+/// of a multi-output circuit).
+///
+/// Nodes shared by multiple roots are stored only once, and all operations on the roots use
+/// the same operation cache. The roots are also indexed by their top (root) variable, and
+/// the whole forest can be serialized into a single string (see `Forest::try_from`).
+#[derive(Clone)]
+pub struct Forest {
+    context: ApplyContext,
+    roots: BTreeMap<String, PooledHandle>,
+    /// Names of the roots, grouped by their top variable (`VariableId::UNDEFINED`
+    /// for constants).
+    by_top_variable: BTreeMap<VariableId, BTreeSet<String>>,
+    metadata: VariableMetadata,
+}
+
+/// Identifier of a string interned in a `SymbolTable`.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct SymbolId(u32);
+
+/// An append-only set of interned strings.
+///
+/// Every string is stored only once, and cloning the table only copies pointers to
+/// the strings. Since strings are never removed, a `SymbolId` of a table stays valid
+/// in all later versions of its clones.
+#[derive(Clone, Default)]
+pub struct SymbolTable {
+    strings: Vec<Arc<str>>,
+    ids: BTreeMap<Arc<str>, SymbolId>,
+}
+
+/// Names, descriptions and groups of variables, with all strings interned in one
+/// `SymbolTable`.
+///
+/// Clones share the interned strings and the (copy-on-write) per-variable entries, so
+/// snapshots of large variable sets are cheap, and so is `VariableMetadata::diff` between
+/// two snapshots. Variable names are unique.
+#[derive(Clone)]
+pub struct VariableMetadata {
+    symbols: SymbolTable,
+    variables: CowChunks<VariableInfo>,
+    by_name: BTreeMap<SymbolId, VariableId>,
+}
+
+/// A fixed set of named variables which creates `Bdds` over exactly these variables
+/// (similar to `BddVariableSet` of `biodivine-lib-bdd`).
+///
+/// The `i`-th variable of the set is `VariableId::from(i)` and its name is stored in
+/// `VariableMetadata`. Every `Bdd` created by the set has the same variable count as the set.
+#[derive(Clone)]
+pub struct BddVariableSet {
+    variable_count: u16,
+    metadata: VariableMetadata,
+}
+
+/// **(internal)** The metadata of a single variable in `VariableMetadata`.
+#[derive(Copy, Clone, Eq, PartialEq, Default)]
+struct VariableInfo {
+    name: Option<SymbolId>,
+    description: Option<SymbolId>,
+    group: Option<SymbolId>,
+}
+
+/// **(internal)** A vector split into fixed-size chunks which are shared (copy-on-write)
+/// between clones of the vector.
+#[derive(Clone)]
+struct CowChunks<T: Copy> {
+    len: usize,
+    chunks: Vec<Arc<Vec<T>>>,
+}
+
+/// **(internal)** A lazily computed fingerprint of a `Bdd`. Zero means that the fingerprint
+/// is not computed yet (a fingerprint is never zero).
+///
+/// The value is atomic, so that the fingerprint can be cached through a shared reference
+/// without making `Bdd` thread-unsafe.
+#[derive(Default)]
+struct FingerprintCache(AtomicU64);
+
+/// **(internal)** A single step of a `LazyBdd` expression. `Operand` pushes an operand
+/// on the evaluation stack, the other steps replace the two topmost values with their result.
+#[derive(Copy, Clone)]
+enum LazyStep {
+    Operand(usize),
+    And,
+    Or,
+    Imp,
+    Iff,
+    Xor,
+    AndNot,
+}
+
+/// **(internal)** A single entry of the `ApplyContext` operation cache. The entry is empty
+/// when the `result` is `NodeId::UNDEFINED`, and stale when its `generation` differs
+/// from the generation of the context.
+#[derive(Copy, Clone)]
+struct CacheEntry {
+    operation: u8,
+    generation: u32,
+    left: NodeId,
+    right: NodeId,
+    result: NodeId,
+}
+
+// Two cache entries fit exactly into one cache line.
+assert_eq_size!(CacheEntry, [u64; 4]);
 
 // TODO: Move this to separate modules:
 
 impl BddNode {
-    // A mask with bits set where variable bits are stored in a u64.
-    const VARIABLE_MASK: u64 = (u16::MAX as u64) << 48;
-    // A mask with bits set where id bits are stored in a u64.
-    const ID_MASK: u64 = !Self::VARIABLE_MASK;
-    pub const ZERO: BddNode = BddNode(Self::VARIABLE_MASK, 0);
-    pub const ONE: BddNode = BddNode(Self::VARIABLE_MASK | 1, 1);
+    // The low link is packed together with the variable (see `crate::packing`).
+    pub const ZERO: BddNode = BddNode(packing::VARIABLE_MASK, 0);
+    pub const ONE: BddNode = BddNode(packing::VARIABLE_MASK | 1, 1);
 
     #[inline]
     pub(crate) fn unpack(self) -> (VariableId, NodeId, NodeId) {
         let (x, y) = (self.0, self.1);
         (
-            VariableId(x.shr(48) as u16),
-            NodeId(x & Self::ID_MASK),
+            VariableId(packing::variable(x)),
+            NodeId(packing::id(x)),
             NodeId(y),
         )
     }
 
+    #[inline]
+    pub fn variable(&self) -> VariableId {
+        VariableId(packing::variable(self.0))
+    }
+
     #[inline]
     pub fn high_link(&self) -> NodeId {
         NodeId(self.1)
@@ -121,13 +793,12 @@ impl BddNode {
 
     #[inline]
     pub fn low_link(&self) -> NodeId {
-        NodeId(self.0 & Self::ID_MASK)
+        NodeId(packing::id(self.0))
     }
 
     #[inline]
     pub(crate) fn pack(variable: VariableId, low: NodeId, high: NodeId) -> BddNode {
-        let x = u64::from(variable.0).shl(48) | low.0;
-        BddNode(x, high.0)
+        BddNode(packing::pack(variable.0, low.0), high.0)
     }
 }
 
@@ -169,6 +840,12 @@ impl VariableId {
     pub const UNDEFINED: VariableId = VariableId(u16::MAX);
 }
 
+impl From<VariableId> for u16 {
+    fn from(value: VariableId) -> Self {
+        value.0
+    }
+}
+
 impl From<NodeId> for u64 {
     fn from(value: NodeId) -> Self {
         value.0
@@ -195,37 +872,85 @@ impl Bdd {
     pub fn new_false() -> Bdd {
         Bdd {
             variable_count: 0,
+            is_sorted: true,
             nodes: vec![BddNode::ZERO],
+            fingerprint: FingerprintCache::default(),
         }
     }
 
+    pub fn new_true() -> Bdd {
+        Bdd {
+            variable_count: 0,
+            is_sorted: true,
+            nodes: vec![BddNode::ZERO, BddNode::ONE],
+            fingerprint: FingerprintCache::default(),
+        }
+    }
+
+    /// **(internal)** Create a `true` Bdd that is intended to be extended with new nodes
+    /// (hence it is not considered sorted).
     pub(crate) fn true_with_capacity(capacity: usize) -> Bdd {
+        Bdd::true_in_buffer(Vec::new(), capacity)
+    }
+
+    /// **(internal)** Same as `Bdd::true_with_capacity`, but the nodes are stored in
+    /// the given `buffer` (its contents are discarded). The buffer is only reallocated
+    /// if it cannot hold `capacity` nodes.
+    pub(crate) fn true_in_buffer(mut buffer: Vec<BddNode>, capacity: usize) -> Bdd {
+        buffer.clear();
+        buffer.reserve(capacity);
         let mut bdd = Bdd {
             variable_count: 0,
-            nodes: Vec::with_capacity(capacity),
+            is_sorted: false,
+            nodes: buffer,
+            fingerprint: FingerprintCache::default(),
         };
         bdd.nodes.push(BddNode::ZERO);
         bdd.nodes.push(BddNode::ONE);
         bdd
     }
 
+    /// A `Bdd` of the given `variable`.
+    ///
+    /// Panics if the variable is `VariableId::UNDEFINED` (see `Bdd::try_new_variable`).
     pub fn new_variable(variable: VariableId) -> Bdd {
-        Bdd {
+        Bdd::try_new_variable(variable).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// A `Bdd` of the given `variable`, or an error if the variable is `VariableId::UNDEFINED`.
+    pub fn try_new_variable(variable: VariableId) -> Result<Bdd, LimitError> {
+        if variable == VariableId::UNDEFINED {
+            return Err(LimitError::ReservedVariable);
+        }
+        Ok(Bdd {
             variable_count: variable.0 + 1,
+            is_sorted: true,
             nodes: vec![
                 BddNode::ZERO,
                 BddNode::ONE,
                 BddNode::pack(variable, NodeId::ZERO, NodeId::ONE),
             ],
-        }
+            fingerprint: FingerprintCache::default(),
+        })
     }
 
     pub fn update_variable_count(&mut self, variables: u16) {
         self.variable_count = max(self.variable_count, variables);
     }
 
+    /// True if this `Bdd` is known to be sorted in DFS preorder (see `Bdd::sort_preorder`).
+    ///
+    /// The value is cached, so it is only `true` if the `Bdd` was explicitly sorted, or it
+    /// is trivially sorted (e.g. constants and variables).
+    #[inline]
+    pub fn is_sorted(&self) -> bool {
+        self.is_sorted
+    }
+
     #[inline]
     pub(crate) fn push_node(&mut self, node: BddNode) -> NodeId {
+        Bdd::check_next_node(self.nodes.len());
+        self.fingerprint.clear();
         self.nodes.push(node);
         self.root_node()
     }
@@ -242,23 +967,19 @@ impl Bdd {
 
     #[inline]
     pub(crate) fn prefetch(&self, id: NodeId) {
-        unsafe {
-            // Prefetch operations ignore memory errors and are therefore "externally safe".
-            if cfg!(target_arch = "x86_64") {
-                let reference: *const BddNode = self.nodes.get_unchecked(id.0 as usize);
-                std::arch::x86_64::_mm_prefetch::<3>(reference as *const i8);
-            }
-        }
+        // Prefetch operations ignore memory errors and are therefore "externally safe".
+        crate::arch::prefetch(self.nodes.as_ptr().wrapping_add(id.0 as usize));
     }
 
     pub(crate) fn get_variable(&self, id: NodeId) -> VariableId {
         debug_assert!((id.0 as usize) < self.nodes.len());
         let node = unsafe { self.nodes.get_unchecked(id.0 as usize) };
-        VariableId(node.0.shr(48) as u16)
+        VariableId(packing::variable(node.0))
     }
 
     pub fn sort_preorder_safe(&mut self) {
         if self.nodes.len() < 2 {
+            self.is_sorted = true;
             return;
         }
 
@@ -303,19 +1024,49 @@ impl Bdd {
         }
 
         self.nodes = new_nodes;
+        self.is_sorted = true;
     }
 
     pub fn sort_preorder(&mut self) {
         if self.nodes.len() < 2 {
+            self.is_sorted = true;
             return;
         }
         // Bdd sorted in pre-order is faster to iterate due to cache locality.
+        let (new_id, _) = self.preorder_ids();
+
+        let mut new_nodes = Bdd::true_with_capacity(self.node_count()).nodes;
+        // Allocate nodes without initialization
+        unsafe { new_nodes.set_len(self.node_count()) };
+        for old_index in 2..new_id.len() {
+            let (var, old_low, old_high) = unsafe { self.nodes.get_unchecked(old_index) }.unpack();
+
+            let new_index = unsafe { *new_id.get_unchecked(old_index) };
+            let new_low = unsafe { *new_id.get_unchecked(old_low.as_index_unchecked()) };
+            let new_high = unsafe { *new_id.get_unchecked(old_high.as_index_unchecked()) };
+            unsafe {
+                let cell = new_nodes.get_unchecked_mut(new_index);
+                *cell = BddNode::pack(var, NodeId(new_low as u64), NodeId(new_high as u64));
+            }
+        }
+
+        self.nodes = new_nodes;
+        self.is_sorted = true;
+        self.debug_check_integrity("sort_preorder");
+    }
+
+    /// **(internal)** The new id of every node when the nodes are sorted in DFS preorder
+    /// (see `Bdd::sort_preorder`), together with the largest id which was not assigned.
+    /// This is `1` (the terminal node), unless the `Bdd` contains unreachable nodes, which
+    /// keep the id `0`.
+    pub(crate) fn preorder_ids(&self) -> (Vec<usize>, usize) {
         let mut new_id = vec![0usize; self.nodes.len()];
         new_id[0] = 0;
         new_id[1] = 1;
 
         let mut stack_index_after_last: usize = 0;
-        let mut stack = vec![NodeId::ZERO; 3 * usize::from(self.variable_count())];
+        // The root is always pushed, even in a constant `Bdd` without variables.
+        let mut stack = vec![NodeId::ZERO; 3 * usize::from(self.variable_count()) + 1];
         unsafe {
             *stack.get_unchecked_mut(stack_index_after_last) = self.root_node();
             stack_index_after_last += 1;
@@ -343,23 +1094,7 @@ impl Bdd {
                 }
             }
         }
-
-        let mut new_nodes = Bdd::true_with_capacity(self.node_count()).nodes;
-        // Allocate nodes without initialization
-        unsafe { new_nodes.set_len(self.node_count()) };
-        for old_index in 2..new_id.len() {
-            let (var, old_low, old_high) = unsafe { self.nodes.get_unchecked(old_index) }.unpack();
-
-            let new_index = unsafe { *new_id.get_unchecked(old_index) };
-            let new_low = unsafe { *new_id.get_unchecked(old_low.as_index_unchecked()) };
-            let new_high = unsafe { *new_id.get_unchecked(old_high.as_index_unchecked()) };
-            unsafe {
-                let cell = new_nodes.get_unchecked_mut(new_index);
-                *cell = BddNode::pack(var, NodeId(new_low as u64), NodeId(new_high as u64));
-            }
-        }
-
-        self.nodes = new_nodes;
+        (new_id, new_index)
     }
 }
 
@@ -413,7 +1148,9 @@ impl TryFrom<&str> for Bdd {
         }
         Ok(Bdd {
             variable_count: nodes[0].unpack().0 .0,
+            is_sorted: false,
             nodes,
+            fingerprint: FingerprintCache::default(),
         })
     }
 }
@@ -0,0 +1,200 @@
+//! Binary logical operators which can be used with `Bdd::apply`.
+//!
+//! Every operator is a zero-sized type implementing `BooleanOp`, so `Bdd::apply::<Op>` is
+//! compiled into a separate copy of the apply algorithm where the lookup table of the operator
+//! is fully inlined. The pre-defined operators (`And`, `Or`, ...) are exactly the ones used by
+//! `Bdd::and`, `Bdd::or`, etc.
+//!
+//! A custom operator only needs to provide its truth table and its mirror operator (the same
+//! operator with swapped operands, which is used when the operands are swapped). A symmetric
+//! operator is its own mirror, and for other operators, the mirror can be `Mirrored<Self>`.
+
+use crate::v2::NodeId;
+use core::marker::PhantomData;
+
+/// A binary logical operator given by its truth table (see module documentation).
+///
+/// The terminal checks (`LEFT_ZERO`, `RIGHT_ONE`, ...) are derived from the `TABLE` at compile
+/// time, so `BooleanOp::lookup` of a concrete operator is reduced to a few comparisons.
+pub trait BooleanOp {
+    /// The name of the operation, used when reporting the operation to the log.
+    const NAME: &'static str = "binary_operation";
+
+    /// The truth table of the operator: `TABLE[left][right]` is the result for the given
+    /// `left` and `right` values.
+    const TABLE: [[bool; 2]; 2];
+
+    /// The same operator, but with swapped operands. Its `TABLE` must be the transposed
+    /// `TABLE` of this operator. Symmetric operators can use `Self`, the other operators
+    /// can use `Mirrored<Self>`.
+    type Mirror: BooleanOp<Mirror = Self>;
+
+    /// The result when the left operand is `false`, if it does not depend on the right operand.
+    const LEFT_ZERO: Option<bool> = constant(Self::TABLE[0][0], Self::TABLE[0][1]);
+    /// The result when the left operand is `true`, if it does not depend on the right operand.
+    const LEFT_ONE: Option<bool> = constant(Self::TABLE[1][0], Self::TABLE[1][1]);
+    /// The result when the right operand is `false`, if it does not depend on the left operand.
+    const RIGHT_ZERO: Option<bool> = constant(Self::TABLE[0][0], Self::TABLE[1][0]);
+    /// The result when the right operand is `true`, if it does not depend on the left operand.
+    const RIGHT_ONE: Option<bool> = constant(Self::TABLE[0][1], Self::TABLE[1][1]);
+
+    /// The result of the operation, where every operand is either a known constant or `None`
+    /// (a non-terminal node), or `None` if the result is not decided by the known constants.
+    #[inline(always)]
+    fn partial_eval(left: Option<bool>, right: Option<bool>) -> Option<bool> {
+        match (left, right) {
+            (Some(left), Some(right)) => Some(Self::TABLE[left as usize][right as usize]),
+            (Some(false), None) => Self::LEFT_ZERO,
+            (Some(true), None) => Self::LEFT_ONE,
+            (None, Some(false)) => Self::RIGHT_ZERO,
+            (None, Some(true)) => Self::RIGHT_ONE,
+            (None, None) => None,
+        }
+    }
+
+    /// The lookup table of the operator, as used by `Bdd::binary_operation`: returns a terminal
+    /// node if the result of the task `(left, right)` is already decided, and
+    /// `NodeId::UNDEFINED` otherwise.
+    #[inline(always)]
+    fn lookup(left: NodeId, right: NodeId) -> NodeId {
+        let value = |id: NodeId| if id.0 < 2 { Some(id.is_one()) } else { None };
+        match Self::partial_eval(value(left), value(right)) {
+            Some(false) => NodeId::ZERO,
+            Some(true) => NodeId::ONE,
+            None => NodeId::UNDEFINED,
+        }
+    }
+}
+
+/// **(internal)** The value of a row (or a column) of a truth table, if it is constant.
+const fn constant(first: bool, second: bool) -> Option<bool> {
+    if first == second {
+        Some(first)
+    } else {
+        None
+    }
+}
+
+/// **(internal)** The transposed truth table.
+const fn transpose(table: [[bool; 2]; 2]) -> [[bool; 2]; 2] {
+    [[table[0][0], table[1][0]], [table[0][1], table[1][1]]]
+}
+
+/// The operator `OP` with swapped operands.
+pub struct Mirrored<OP>(PhantomData<OP>);
+
+impl<OP: BooleanOp<Mirror = Mirrored<OP>>> BooleanOp for Mirrored<OP> {
+    const NAME: &'static str = OP::NAME;
+    const TABLE: [[bool; 2]; 2] = transpose(OP::TABLE);
+    type Mirror = OP;
+}
+
+/// Logical conjunction (see `Bdd::and`).
+pub struct And;
+
+/// Logical disjunction (see `Bdd::or`).
+pub struct Or;
+
+/// Logical implication (see `Bdd::imp`).
+pub struct Imp;
+
+/// Reverse implication, i.e. `Imp` with swapped operands.
+pub struct InvImp;
+
+/// Logical equivalence (see `Bdd::iff`).
+pub struct Iff;
+
+/// Exclusive disjunction (see `Bdd::xor`).
+pub struct Xor;
+
+/// Conjunction with a negated right operand (see `Bdd::and_not`).
+pub struct AndNot;
+
+/// Conjunction with a negated left operand, i.e. `AndNot` with swapped operands.
+pub struct NotAnd;
+
+impl BooleanOp for And {
+    const NAME: &'static str = "and";
+    const TABLE: [[bool; 2]; 2] = [[false, false], [false, true]];
+    type Mirror = And;
+}
+
+impl BooleanOp for Or {
+    const NAME: &'static str = "or";
+    const TABLE: [[bool; 2]; 2] = [[false, true], [true, true]];
+    type Mirror = Or;
+}
+
+impl BooleanOp for Imp {
+    const NAME: &'static str = "imp";
+    const TABLE: [[bool; 2]; 2] = [[true, true], [false, true]];
+    type Mirror = InvImp;
+}
+
+impl BooleanOp for InvImp {
+    const NAME: &'static str = "inv_imp";
+    const TABLE: [[bool; 2]; 2] = [[true, false], [true, true]];
+    type Mirror = Imp;
+}
+
+impl BooleanOp for Iff {
+    const NAME: &'static str = "iff";
+    const TABLE: [[bool; 2]; 2] = [[true, false], [false, true]];
+    type Mirror = Iff;
+}
+
+impl BooleanOp for Xor {
+    const NAME: &'static str = "xor";
+    const TABLE: [[bool; 2]; 2] = [[false, true], [true, false]];
+    type Mirror = Xor;
+}
+
+impl BooleanOp for AndNot {
+    const NAME: &'static str = "and_not";
+    const TABLE: [[bool; 2]; 2] = [[false, false], [true, false]];
+    type Mirror = NotAnd;
+}
+
+impl BooleanOp for NotAnd {
+    const NAME: &'static str = "not_and";
+    const TABLE: [[bool; 2]; 2] = [[false, true], [false, false]];
+    type Mirror = AndNot;
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::ops::{And, AndNot, BooleanOp, Iff, Imp, Or, Xor};
+    use crate::v2::{BoolOp, NodeId};
+
+    fn check<OP: BooleanOp>(op: BoolOp) {
+        let node = |value: bool| if value { NodeId::ONE } else { NodeId::ZERO };
+        for l in [false, true] {
+            for r in [false, true] {
+                assert_eq!(op.eval(l, r), OP::TABLE[l as usize][r as usize]);
+                assert_eq!(node(op.eval(l, r)), OP::lookup(node(l), node(r)));
+                let mirrored = OP::Mirror::TABLE[r as usize][l as usize];
+                assert_eq!(OP::TABLE[l as usize][r as usize], mirrored);
+            }
+        }
+    }
+
+    #[test]
+    pub fn boolean_op_test() {
+        check::<And>(BoolOp::And);
+        check::<Or>(BoolOp::Or);
+        check::<Xor>(BoolOp::Xor);
+        check::<Imp>(BoolOp::Imp);
+        check::<Iff>(BoolOp::Iff);
+        check::<AndNot>(BoolOp::AndNot);
+        check::<<AndNot as BooleanOp>::Mirror>(BoolOp::NotAnd);
+
+        // Decided by one operand, even if the other one is not a terminal.
+        let node = NodeId(5);
+        assert_eq!(NodeId::ZERO, And::lookup(node, NodeId::ZERO));
+        assert_eq!(NodeId::ONE, Imp::lookup(NodeId::ZERO, node));
+        assert_eq!(NodeId::ZERO, AndNot::lookup(node, NodeId::ONE));
+        assert!(And::lookup(node, NodeId::ONE).is_undefined());
+        assert!(Xor::lookup(NodeId::ONE, node).is_undefined());
+        assert!(Xor::lookup(node, node).is_undefined());
+    }
+}
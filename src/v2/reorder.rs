@@ -0,0 +1,397 @@
+use crate::expressions::BooleanExpression;
+use crate::v2::{Bdd, NodeId, VariableId};
+
+/// The number of independent annealing runs performed by `anneal`. The first run starts
+/// from the current order, the remaining ones start from a random permutation.
+const RESTARTS: usize = 4;
+
+/// The largest number of adjacent variables that are shuffled in a single annealing step.
+const WINDOW: usize = 3;
+
+/// The initial temperature of each run, relative to the size of the initial `Bdd`. That is,
+/// at the start of a run, a step which increases the size by `10%` is accepted with
+/// probability `1/e`.
+const INITIAL_TEMPERATURE: f64 = 0.1;
+
+/// The maximal number of refinement rounds performed by `force_order`. In practice, the
+/// heuristic usually converges much sooner.
+const FORCE_ROUNDS: usize = 64;
+
+/// Create a `Bdd` representing the same function as `bdd`, but with variable `v` replaced
+/// by `permutation[v]`. Consequently, the variables are ordered differently in the result.
+///
+/// The result is rebuilt using `apply` operations, so this is not particularly fast (it
+/// is designed for offline experiments, not for dynamic reordering).
+///
+/// *Panics:* `permutation` must be a permutation of `0..bdd.variable_count()`.
+pub fn permute(bdd: &Bdd, permutation: &[VariableId]) -> Bdd {
+    assert_eq!(permutation.len(), usize::from(bdd.variable_count()));
+    let mut seen = vec![false; permutation.len()];
+    for var in permutation {
+        assert!(!seen[usize::from(var.0)], "Not a permutation.");
+        seen[usize::from(var.0)] = true;
+    }
+
+    // The number of (not yet processed) parents of each node, so that we can drop
+    // the intermediate results once they are not needed anymore.
+    let mut parents = vec![0usize; bdd.node_count()];
+    for node in &bdd.nodes[2..] {
+        parents[node.low_link().as_index()] += 1;
+        parents[node.high_link().as_index()] += 1;
+    }
+
+    let mut results: Vec<Option<Bdd>> = vec![None; bdd.node_count()];
+    results[0] = Some(Bdd::new_false());
+    if bdd.node_count() > 1 {
+        results[1] = Some(Bdd::true_with_capacity(2));
+    }
+
+    let mut stack = vec![bdd.root_node()];
+    while let Some(top) = stack.last().cloned() {
+        if results[top.as_index()].is_some() {
+            stack.pop();
+            continue;
+        }
+        let (var, low, high) = bdd.nodes[top.as_index()].unpack();
+        match (&results[low.as_index()], &results[high.as_index()]) {
+            (Some(low_result), Some(high_result)) => {
+                let literal = Bdd::new_variable(permutation[usize::from(var.0)]);
+                let result = literal.and(high_result).or(&low_result.and_not(&literal));
+                results[top.as_index()] = Some(result);
+                release(&mut results, &mut parents, low);
+                release(&mut results, &mut parents, high);
+                stack.pop();
+            }
+            (low_result, high_result) => {
+                if high_result.is_none() {
+                    stack.push(high);
+                }
+                if low_result.is_none() {
+                    stack.push(low);
+                }
+            }
+        }
+    }
+
+    let mut result = results[bdd.root_node().as_index()].take().unwrap();
+    result.update_variable_count(bdd.variable_count());
+    result
+}
+
+/// **(internal)** Mark one parent of `node` as processed and drop its result if all parents
+/// are processed (terminal results are cheap, so they are always kept).
+fn release(results: &mut [Option<Bdd>], parents: &mut [usize], node: NodeId) {
+    let index = node.as_index();
+    parents[index] -= 1;
+    if parents[index] == 0 && index > 1 {
+        results[index] = None;
+    }
+}
+
+/// Search for a variable order which minimizes the size of `bdd` using simulated annealing.
+///
+/// In each step, a random window of (at most three) adjacent variables is shuffled, and the
+/// resulting `Bdd` is rebuilt to obtain its true node count. Larger results are accepted with
+/// a probability which decreases as the search "cools down". To avoid getting stuck in one
+/// local minimum, the search is restarted several times from a random order.
+///
+/// The `budget` is the total number of evaluated orders. The search is deterministic (it uses
+/// a fixed seed), so the same `bdd` and `budget` always produce the same result.
+///
+/// Returns the best permutation (in the format accepted by `permute`) together with
+/// the permuted `Bdd`. The result is never larger than the original `bdd`.
+pub fn anneal(bdd: &Bdd, budget: usize) -> (Vec<VariableId>, Bdd) {
+    let variables = usize::from(bdd.variable_count());
+    let mut best_permutation: Vec<VariableId> = (0..bdd.variable_count()).map(VariableId).collect();
+    let mut best = bdd.clone();
+    if variables < 2 {
+        return (best_permutation, best);
+    }
+
+    let mut random = XorShift::new(0x51_7c_c1_b7_27_22_0a_95);
+    let steps = budget / RESTARTS;
+    for restart in 0..RESTARTS {
+        let mut permutation = best_permutation.clone();
+        if restart > 0 {
+            random.shuffle(&mut permutation);
+        }
+        let mut size = permute(bdd, &permutation).node_count();
+        let initial_temperature = INITIAL_TEMPERATURE * (size as f64);
+        for step in 0..steps {
+            let temperature = initial_temperature * (1.0 - (step as f64) / (steps as f64));
+
+            let mut candidate = permutation.clone();
+            // The permutation maps variables to positions, but the window is defined
+            // on positions, so we have to find which variables are in the window.
+            let start = random.next_below(variables - 1);
+            let end = usize::min(start + WINDOW, variables);
+            let mut window: Vec<usize> = (0..variables)
+                .filter(|v| (start..end).contains(&usize::from(candidate[*v].0)))
+                .collect();
+            let mut positions: Vec<VariableId> = window.iter().map(|v| candidate[*v]).collect();
+            random.shuffle(&mut positions);
+            for (v, position) in window.drain(..).zip(positions) {
+                candidate[v] = position;
+            }
+
+            let result = permute(bdd, &candidate);
+            let candidate_size = result.node_count();
+            let accept = candidate_size <= size || {
+                let delta = (candidate_size - size) as f64;
+                temperature > 0.0 && random.next_f64() < (-delta / temperature).exp()
+            };
+            if candidate_size < best.node_count() {
+                best_permutation = candidate.clone();
+                best = result;
+            }
+            if accept {
+                permutation = candidate;
+                size = candidate_size;
+            }
+        }
+    }
+
+    (best_permutation, best)
+}
+
+/// Compute a static variable order using the FORCE heuristic.
+///
+/// Each of the `groups` is a set of variables which should be close to each other in the order
+/// (e.g. the support of one `Bdd` in a conjunction). In every round, each group is placed at
+/// the centre of gravity of its variables, each variable is moved to the average centre of
+/// its groups, and the variables are ranked by their new positions. The rounds stop once
+/// the total span of all groups no longer decreases. Variables which do not appear in any
+/// group keep their relative position.
+///
+/// Returns a permutation (in the format accepted by `permute`) starting from the identity.
+/// The total span of the groups in the result is never larger than in the identity order.
+///
+/// *Panics:* every variable in `groups` must be smaller than `variable_count`.
+pub fn force_order(groups: &[Vec<VariableId>], variable_count: u16) -> Vec<VariableId> {
+    let variables = usize::from(variable_count);
+    let mut best: Vec<VariableId> = (0..variable_count).map(VariableId).collect();
+    let mut best_span = span(groups, &best);
+    for _ in 0..FORCE_ROUNDS {
+        let mut force = vec![0.0f64; variables];
+        let mut degree = vec![0usize; variables];
+        for group in groups.iter().filter(|group| !group.is_empty()) {
+            let sum: usize = group.iter().map(|v| usize::from(best[usize::from(v.0)].0)).sum();
+            let centre = (sum as f64) / (group.len() as f64);
+            for v in group {
+                force[usize::from(v.0)] += centre;
+                degree[usize::from(v.0)] += 1;
+            }
+        }
+        for v in 0..variables {
+            force[v] = if degree[v] == 0 {
+                f64::from(best[v].0)
+            } else {
+                force[v] / (degree[v] as f64)
+            };
+        }
+
+        let mut ranking: Vec<usize> = (0..variables).collect();
+        ranking.sort_by(|a, b| force[*a].total_cmp(&force[*b]).then(a.cmp(b)));
+        let mut candidate = best.clone();
+        for (position, v) in ranking.into_iter().enumerate() {
+            candidate[v] = VariableId(position as u16);
+        }
+        let candidate_span = span(groups, &candidate);
+        if candidate_span >= best_span {
+            break;
+        }
+        best = candidate;
+        best_span = candidate_span;
+    }
+    best
+}
+
+/// Compute a static variable order (see `force_order`) which keeps the supports of the given
+/// `bdds` close together, e.g. before building their conjunction.
+///
+/// The permutation covers the largest variable count of the `bdds`.
+pub fn order_for_bdds(bdds: &[&Bdd]) -> Vec<VariableId> {
+    let variable_count = bdds.iter().map(|bdd| bdd.variable_count()).max().unwrap_or(0);
+    let supports: Vec<Vec<VariableId>> = bdds
+        .iter()
+        .map(|bdd| {
+            let mut support: Vec<VariableId> =
+                bdd.nodes.iter().skip(2).map(|node| node.variable()).collect();
+            support.sort();
+            support.dedup();
+            support
+        })
+        .collect();
+    force_order(&supports, variable_count)
+}
+
+/// Compute a static variable order from the structure of an `expression`, where variable
+/// `VariableId::from(i)` is `variable_order[i]` (as in `Bdd::from_expression`).
+///
+/// The variables are ordered by their first appearance in a depth-first traversal of
+/// the expression, in which the operand with more variables is always visited first. Variables
+/// which only interact within a small sub-expression thus end up next to each other. Unused
+/// variables are placed last, in their original order.
+///
+/// Returns a permutation in the format accepted by `permute`, or an error if the expression
+/// uses a variable which is not in the `variable_order`.
+pub fn order_for_expression(
+    expression: &BooleanExpression,
+    variable_order: &[&str],
+) -> Result<Vec<VariableId>, String> {
+    let mut permutation: Vec<Option<VariableId>> = vec![None; variable_order.len()];
+    let mut next_position = 0u16;
+    let mut stack = vec![expression];
+    while let Some(top) = stack.pop() {
+        match top {
+            BooleanExpression::Const(_) => (),
+            BooleanExpression::Variable(name) => {
+                let index = variable_order
+                    .iter()
+                    .position(|it| it == name)
+                    .ok_or_else(|| format!("Unknown variable `{}`.", name))?;
+                if permutation[index].is_none() {
+                    permutation[index] = Some(VariableId(next_position));
+                    next_position += 1;
+                }
+            }
+            BooleanExpression::Not(inner) => stack.push(inner),
+            BooleanExpression::Binary(_, left, right) => {
+                // The operand on top of the stack is visited first.
+                if right.variables().len() > left.variables().len() {
+                    stack.push(left);
+                    stack.push(right);
+                } else {
+                    stack.push(right);
+                    stack.push(left);
+                }
+            }
+        }
+    }
+    Ok(permutation
+        .into_iter()
+        .map(|position| {
+            position.unwrap_or_else(|| {
+                next_position += 1;
+                VariableId(next_position - 1)
+            })
+        })
+        .collect())
+}
+
+/// **(internal)** The sum of distances between the first and the last variable of each group
+/// in the order given by `permutation`.
+fn span(groups: &[Vec<VariableId>], permutation: &[VariableId]) -> usize {
+    groups
+        .iter()
+        .filter(|group| !group.is_empty())
+        .map(|group| {
+            let positions = group.iter().map(|v| permutation[usize::from(v.0)].0);
+            let (min, max) = positions.fold((u16::MAX, 0), |(a, b), p| (a.min(p), b.max(p)));
+            usize::from(max - min)
+        })
+        .sum()
+}
+
+/// **(internal)** A tiny `xorshift64*` pseudo-random generator, such that the search
+/// is deterministic and does not need any extra dependencies.
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> XorShift {
+        XorShift(seed | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn next_below(&mut self, bound: usize) -> usize {
+        (self.next() % (bound as u64)) as usize
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        ((self.next() >> 11) as f64) / ((1u64 << 53) as f64)
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.next_below(i + 1);
+            items.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{anneal, force_order, order_for_bdds, order_for_expression, permute};
+    use crate::expressions::BooleanExpression;
+    use crate::v2::{Bdd, VariableId};
+    use core::convert::TryFrom;
+
+    fn var(id: u16) -> Bdd {
+        Bdd::new_variable(VariableId::from(id))
+    }
+
+    #[test]
+    pub fn permute_and_anneal_test() {
+        // `(x0 & x2) | (x1 & x3)` is larger with the interleaved order.
+        let bdd = var(0).and(&var(2)).or(&var(1).and(&var(3)));
+        let identity: Vec<VariableId> = (0..4).map(VariableId::from).collect();
+        assert_eq!(bdd.node_count(), permute(&bdd, &identity).node_count());
+
+        let swap: Vec<VariableId> = [0, 2, 1, 3].iter().map(|v| VariableId::from(*v)).collect();
+        let permuted = permute(&bdd, &swap);
+        assert!(permuted.node_count() < bdd.node_count());
+        // Swapping two variables is its own inverse.
+        let back = permute(&permuted, &swap);
+        assert_eq!(1, back.xor(&bdd).node_count());
+
+        let (permutation, best) = anneal(&bdd, 40);
+        assert_eq!(permuted.node_count(), best.node_count());
+        let inverse = {
+            let mut inverse = identity.clone();
+            for (v, position) in permutation.iter().enumerate() {
+                inverse[usize::from(position.0)] = VariableId::from(v as u16);
+            }
+            inverse
+        };
+        assert_eq!(1, permute(&best, &inverse).xor(&bdd).node_count());
+    }
+
+    #[test]
+    pub fn static_order_test() {
+        let v = |ids: &[u16]| -> Vec<VariableId> { ids.iter().map(|v| VariableId(*v)).collect() };
+        let raw = |order: Vec<VariableId>| -> Vec<u16> { order.iter().map(|v| v.0).collect() };
+        let bdd = var(0).and(&var(2)).or(&var(1).and(&var(3)));
+
+        let groups = vec![v(&[0, 2]), v(&[1, 3])];
+        let order = force_order(&groups, 4);
+        assert_eq!(vec![0, 2, 1, 3], raw(order.clone()));
+        let supports = [&var(0).and(&var(2)), &var(1).and(&var(3))];
+        assert_eq!(vec![0, 2, 1, 3], raw(order_for_bdds(&supports)));
+        assert!(permute(&bdd, &order).node_count() < bdd.node_count());
+
+        // An order which is already good is not changed, and variables without groups stay.
+        let groups = vec![v(&[0, 1]), v(&[2, 3])];
+        assert_eq!(vec![0, 1, 2, 3, 4], raw(force_order(&groups, 5)));
+        assert!(order_for_bdds(&[]).is_empty());
+        assert!(order_for_bdds(&[&Bdd::new_false()]).is_empty());
+
+        let expression = BooleanExpression::try_from("(a & c) | (b & d)").unwrap();
+        let names = ["a", "b", "c", "d", "e"];
+        let order = order_for_expression(&expression, &names).unwrap();
+        assert_eq!(vec![0, 2, 1, 3, 4], raw(order.clone()));
+        let built = Bdd::from_expression(&expression, &names).unwrap();
+        assert!(permute(&built, &order).node_count() < built.node_count());
+
+        // The operand with more variables goes first.
+        let expression = BooleanExpression::try_from("a | (b ^ c)").unwrap();
+        let order = order_for_expression(&expression, &["a", "b", "c"]).unwrap();
+        assert_eq!(vec![2, 0, 1], raw(order));
+        assert!(order_for_expression(&expression, &["a", "b"]).is_err());
+    }
+}
@@ -0,0 +1,573 @@
+//! `SmallBdd` is a fixed-capacity `Bdd` for problems where the number of variables
+//! (at most 64) and the maximal number of nodes are known at compile time.
+//!
+//! A `SmallBdd` and all the state of its `apply` algorithm (the task stack, the unique table
+//! and the task cache) are stored in fixed-size arrays, so the operations never allocate
+//! memory on the heap. This makes them suitable for small controllers and other embedded
+//! environments. The downside is that an operation can fail when its result does not fit
+//! into the capacity of the `SmallBdd`.
+//!
+//! Conversions between `SmallBdd` and the general `Bdd` are provided using `From`/`TryFrom`.
+
+use crate::v2::ops::{And, AndNot, BooleanOp, Iff, Imp, NotAnd, Or, Xor};
+use crate::v2::{Bdd, BddNode, BoolOp, NodeId, VariableId};
+use core::cmp::min;
+use core::convert::TryFrom;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+
+/// The largest number of variables supported by a `SmallBdd`.
+pub const MAX_VARIABLES: usize = 64;
+
+/// The default node capacity of a `SmallBdd`.
+pub const DEFAULT_CAPACITY: usize = 256;
+
+/// A `Bdd` over `V` variables with at most `N` nodes (including terminals) that is stored
+/// entirely in a fixed-size array.
+///
+/// `V` must be at most `MAX_VARIABLES` and `N` must be in `3..=u16::MAX` (both conditions are
+/// checked at compile time). Same as for a general `Bdd`, the nodes are sorted such that
+/// the root node is always the last one.
+#[derive(Clone)]
+pub struct SmallBdd<const V: usize, const N: usize = DEFAULT_CAPACITY> {
+    node_count: u16,
+    nodes: [SmallNode; N],
+}
+
+/// **(internal)** A node of a `SmallBdd`. Terminal nodes use `V` as their variable.
+#[derive(Copy, Clone, Eq, PartialEq)]
+struct SmallNode {
+    variable: u8,
+    low: u16,
+    high: u16,
+}
+
+/// **(internal)** A single task of the `SmallBdd::binary_operation` search.
+#[derive(Copy, Clone)]
+struct Frame {
+    left: u16,
+    right: u16,
+    variable: u8,
+    /// Successors of `(left, right)` in the high branch. Only valid once the task is expanded.
+    high_left: u16,
+    high_right: u16,
+    /// The result of the low branch, or `UNDEFINED` if it is not known yet.
+    low: u16,
+}
+
+/// **(internal)** An invalid node index (the capacity is at most `u16::MAX`, so this is never
+/// a valid index).
+const UNDEFINED: u16 = u16::MAX;
+
+impl Frame {
+    const EMPTY: Frame = Frame::new(UNDEFINED, UNDEFINED);
+
+    const fn new(left: u16, right: u16) -> Frame {
+        Frame {
+            left,
+            right,
+            variable: 0,
+            high_left: UNDEFINED,
+            high_right: UNDEFINED,
+            low: UNDEFINED,
+        }
+    }
+}
+
+impl<const V: usize, const N: usize> SmallBdd<V, N> {
+    /// **(internal)** Referencing this constant fails the build if `V` or `N` are invalid.
+    const VALID_PARAMETERS: () = assert!(
+        V <= MAX_VARIABLES && N >= 3 && N <= u16::MAX as usize,
+        "SmallBdd requires V <= 64 and 3 <= N <= 65535."
+    );
+
+    /// **(internal)** The variable of the terminal nodes.
+    const TERMINAL: u8 = V as u8;
+
+    /// Create a `SmallBdd` representing the `false` function.
+    pub fn new_false() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let _ = Self::VALID_PARAMETERS;
+        // The unused nodes are zeroed, which is much cheaper than repeating the terminal.
+        let mut nodes = [SmallNode::ZERO; N];
+        nodes[0] = SmallNode {
+            variable: Self::TERMINAL,
+            low: 0,
+            high: 0,
+        };
+        SmallBdd {
+            node_count: 1,
+            nodes,
+        }
+    }
+
+    /// Create a `SmallBdd` representing the `true` function.
+    pub fn new_true() -> Self {
+        let mut bdd = Self::new_false();
+        bdd.nodes[1] = SmallNode {
+            variable: Self::TERMINAL,
+            low: 1,
+            high: 1,
+        };
+        bdd.node_count = 2;
+        bdd
+    }
+
+    /// Create a `SmallBdd` representing the function `x_{variable}`.
+    ///
+    /// *Panics:* `variable` must be smaller than `V`.
+    pub fn new_variable(variable: VariableId) -> Self {
+        assert!(usize::from(variable.0) < V, "Variable out of range.");
+        let mut bdd = Self::new_true();
+        bdd.nodes[2] = SmallNode {
+            variable: variable.0 as u8,
+            low: 0,
+            high: 1,
+        };
+        bdd.node_count = 3;
+        bdd
+    }
+
+    /// The number of nodes in this `SmallBdd`, including terminals.
+    pub fn node_count(&self) -> usize {
+        usize::from(self.node_count)
+    }
+
+    /// True if this `SmallBdd` represents the `false` function.
+    pub fn is_false(&self) -> bool {
+        self.node_count == 1
+    }
+
+    /// True if this `SmallBdd` represents the `true` function.
+    pub fn is_true(&self) -> bool {
+        self.node_count == 2
+    }
+
+    /// A logical conjunction of two `SmallBdd` objects.
+    ///
+    /// Returns `None` if the result does not fit into `N` nodes.
+    pub fn and(&self, other: &Self) -> Option<Self> {
+        self.binary_operation(other, |l, r| {
+            if l.is_zero() || r.is_zero() {
+                NodeId::ZERO
+            } else if l.is_one() && r.is_one() {
+                NodeId::ONE
+            } else {
+                NodeId::UNDEFINED
+            }
+        })
+    }
+
+    /// A logical disjunction of two `SmallBdd` objects.
+    ///
+    /// Returns `None` if the result does not fit into `N` nodes.
+    pub fn or(&self, other: &Self) -> Option<Self> {
+        self.binary_operation(other, |l, r| {
+            if l.is_one() || r.is_one() {
+                NodeId::ONE
+            } else if l.is_zero() && r.is_zero() {
+                NodeId::ZERO
+            } else {
+                NodeId::UNDEFINED
+            }
+        })
+    }
+
+    /// A logical implication of two `SmallBdd` objects.
+    ///
+    /// Returns `None` if the result does not fit into `N` nodes.
+    pub fn imp(&self, other: &Self) -> Option<Self> {
+        self.binary_operation(other, |l, r| {
+            if l.is_zero() || r.is_one() {
+                NodeId::ONE
+            } else if l.is_one() && r.is_zero() {
+                NodeId::ZERO
+            } else {
+                NodeId::UNDEFINED
+            }
+        })
+    }
+
+    /// A logical equivalence of two `SmallBdd` objects.
+    ///
+    /// Returns `None` if the result does not fit into `N` nodes.
+    pub fn iff(&self, other: &Self) -> Option<Self> {
+        self.binary_operation(other, |l, r| {
+            if l.as_index() > 1 || r.as_index() > 1 {
+                NodeId::UNDEFINED
+            } else if l == r {
+                NodeId::ONE
+            } else {
+                NodeId::ZERO
+            }
+        })
+    }
+
+    /// A logical exclusive disjunction of two `SmallBdd` objects.
+    ///
+    /// Returns `None` if the result does not fit into `N` nodes.
+    pub fn xor(&self, other: &Self) -> Option<Self> {
+        self.binary_operation(other, |l, r| {
+            if l.as_index() > 1 || r.as_index() > 1 {
+                NodeId::UNDEFINED
+            } else if l == r {
+                NodeId::ZERO
+            } else {
+                NodeId::ONE
+            }
+        })
+    }
+
+    /// A logical conjunction with a negated second argument of two `SmallBdd` objects.
+    ///
+    /// Returns `None` if the result does not fit into `N` nodes.
+    pub fn and_not(&self, other: &Self) -> Option<Self> {
+        self.binary_operation(other, |l, r| {
+            if l.is_zero() || r.is_one() {
+                NodeId::ZERO
+            } else if l.is_one() && r.is_zero() {
+                NodeId::ONE
+            } else {
+                NodeId::UNDEFINED
+            }
+        })
+    }
+
+    /// Apply the logical operator `OP` (see `v2::ops`), including custom operators.
+    ///
+    /// Returns `None` if the result does not fit into `N` nodes.
+    pub fn apply<OP: BooleanOp>(&self, other: &Self) -> Option<Self> {
+        self.binary_operation(other, OP::lookup)
+    }
+
+    /// Apply the logical operation selected by `op` (see `Bdd::apply_op`).
+    ///
+    /// Returns `None` if the result does not fit into `N` nodes.
+    pub fn apply_op(&self, op: BoolOp, other: &Self) -> Option<Self> {
+        match op {
+            BoolOp::And => self.apply::<And>(other),
+            BoolOp::Or => self.apply::<Or>(other),
+            BoolOp::Xor => self.apply::<Xor>(other),
+            BoolOp::Imp => self.apply::<Imp>(other),
+            BoolOp::Iff => self.apply::<Iff>(other),
+            BoolOp::AndNot => self.apply::<AndNot>(other),
+            BoolOp::NotAnd => self.apply::<NotAnd>(other),
+        }
+    }
+
+    /// A general binary operation on two `SmallBdd` objects. The `table` has the same meaning
+    /// as in `Bdd::binary_operation`.
+    ///
+    /// Returns `None` if the result does not fit into `N` nodes. No memory is allocated
+    /// on the heap: The search stack has a fixed depth (every task in the stack has
+    /// a larger decision variable than its parent), the unique table has `N` slots and
+    /// the task cache is a lossy, direct-mapped table with `N` slots.
+    pub fn binary_operation<TABLE>(&self, other: &Self, table: TABLE) -> Option<Self>
+    where
+        TABLE: Fn(NodeId, NodeId) -> NodeId,
+    {
+        let mut result = Self::new_true();
+        // Maps node hashes to indices in `result`. Zero is an empty slot, since terminal
+        // nodes are never saved in the table.
+        let mut unique_table = [0u16; N];
+        // Saves `(left, right, result)`. Collisions simply overwrite older tasks.
+        let mut task_cache = [(UNDEFINED, UNDEFINED, UNDEFINED); N];
+        let mut stack = [Frame::EMPTY; MAX_VARIABLES + 1];
+        stack[0] = Frame::new(self.root(), other.root());
+        let mut depth = 1;
+
+        let value = 'search: loop {
+            let top = &mut stack[depth - 1];
+            let (left, right) = (top.left, top.right);
+            let terminal = table(NodeId(u64::from(left)), NodeId(u64::from(right)));
+            let cache_slot = task_hash(left, right) % N;
+            let mut value = if !terminal.is_undefined() {
+                terminal.0 as u16
+            } else if task_cache[cache_slot].0 == left && task_cache[cache_slot].1 == right {
+                task_cache[cache_slot].2
+            } else {
+                // Expand the task and continue with its low successor.
+                let left_node = self.nodes[usize::from(left)];
+                let right_node = other.nodes[usize::from(right)];
+                let variable = min(left_node.variable, right_node.variable);
+                let (left_low, left_high) = left_node.successors(variable, left);
+                let (right_low, right_high) = right_node.successors(variable, right);
+                top.variable = variable;
+                top.high_left = left_high;
+                top.high_right = right_high;
+                stack[depth] = Frame::new(left_low, right_low);
+                depth += 1;
+                continue 'search;
+            };
+
+            // The top task is finished. Propagate its result to its parents.
+            loop {
+                depth -= 1;
+                if depth == 0 {
+                    break 'search value;
+                }
+                let parent = &mut stack[depth - 1];
+                if parent.low == UNDEFINED {
+                    parent.low = value;
+                    stack[depth] = Frame::new(parent.high_left, parent.high_right);
+                    depth += 1;
+                    continue 'search;
+                }
+                let node = SmallNode {
+                    variable: parent.variable,
+                    low: parent.low,
+                    high: value,
+                };
+                value = result.ensure(node, &mut unique_table)?;
+                let cache_slot = task_hash(parent.left, parent.right) % N;
+                task_cache[cache_slot] = (parent.left, parent.right, value);
+            }
+        };
+
+        // Every node that was created is reachable from the result, hence the result is
+        // either a terminal, or the last node.
+        if value == 0 {
+            Some(Self::new_false())
+        } else {
+            debug_assert_eq!(value, result.node_count - 1);
+            Some(result)
+        }
+    }
+
+    /// **(internal)** Index of the root node.
+    fn root(&self) -> u16 {
+        self.node_count - 1
+    }
+
+    /// **(internal)** Find the `node` in the `unique_table`, or append it to this `SmallBdd`.
+    /// Returns `None` if the node is new and there is no space left.
+    fn ensure(&mut self, node: SmallNode, unique_table: &mut [u16; N]) -> Option<u16> {
+        if node.low == node.high {
+            return Some(node.low);
+        }
+        let mut slot = node_hash(node) % N;
+        loop {
+            let index = unique_table[slot];
+            if index == 0 {
+                break;
+            }
+            if self.nodes[usize::from(index)] == node {
+                return Some(index);
+            }
+            slot = (slot + 1) % N;
+        }
+        // The table has `N` slots, but at most `N - 2` nodes, so an empty slot always exists.
+        if usize::from(self.node_count) == N {
+            return None;
+        }
+        let index = self.node_count;
+        self.nodes[usize::from(index)] = node;
+        self.node_count += 1;
+        unique_table[slot] = index;
+        Some(index)
+    }
+}
+
+impl SmallNode {
+    /// **(internal)** A placeholder for the unused nodes of a `SmallBdd`.
+    const ZERO: SmallNode = SmallNode {
+        variable: 0,
+        low: 0,
+        high: 0,
+    };
+
+    /// **(internal)** The low and high successor of the node with the given `index`
+    /// with respect to `variable`. If the node does not depend on `variable`, both
+    /// successors are the node itself.
+    #[inline]
+    fn successors(&self, variable: u8, index: u16) -> (u16, u16) {
+        if self.variable == variable {
+            (self.low, self.high)
+        } else {
+            (index, index)
+        }
+    }
+}
+
+#[inline]
+fn task_hash(left: u16, right: u16) -> usize {
+    let hash = u32::from(left).wrapping_mul(0x9e37_79b9) ^ u32::from(right).wrapping_mul(0x85eb_ca6b);
+    (hash >> 8) as usize
+}
+
+#[inline]
+fn node_hash(node: SmallNode) -> usize {
+    let hash = u32::from(node.variable).wrapping_mul(0xc2b2_ae35)
+        ^ u32::from(node.low).wrapping_mul(0x9e37_79b9)
+        ^ u32::from(node.high).wrapping_mul(0x85eb_ca6b);
+    (hash >> 8) as usize
+}
+
+impl<const V: usize, const N: usize> SmallBdd<V, N> {
+    /// **(internal)** Copy the nodes of `bdd` into a `SmallBdd`, keeping their order
+    /// (including any unreachable nodes). Unlike `TryFrom`, this never allocates.
+    ///
+    /// Returns `None` if `bdd` has more than `N` nodes or uses a variable which is not
+    /// smaller than `V`.
+    pub(crate) fn copy_of(bdd: &Bdd) -> Option<Self> {
+        let nodes = bdd.node_slice();
+        if nodes.len() < 2 {
+            return Some(Self::new_false());
+        }
+        if nodes.len() > N {
+            return None;
+        }
+        let mut result = Self::new_true();
+        for (index, node) in nodes.iter().enumerate().skip(2) {
+            let (variable, low, high) = node.unpack();
+            if usize::from(variable.0) >= V {
+                return None;
+            }
+            result.nodes[index] = SmallNode {
+                variable: variable.0 as u8,
+                low: low.0 as u16,
+                high: high.0 as u16,
+            };
+        }
+        result.node_count = nodes.len() as u16;
+        Some(result)
+    }
+
+    /// **(internal)** Convert this `SmallBdd` into a `Bdd` with the given `variable_count`.
+    pub(crate) fn to_bdd(&self, variable_count: u16) -> Bdd {
+        let mut result = if self.is_false() {
+            Bdd::new_false()
+        } else {
+            // The layout of both representations is the same.
+            let mut result = Bdd::true_with_capacity(self.node_count());
+            for node in &self.nodes[2..self.node_count()] {
+                result.push_node(BddNode::pack(
+                    VariableId(u16::from(node.variable)),
+                    NodeId(u64::from(node.low)),
+                    NodeId(u64::from(node.high)),
+                ));
+            }
+            result
+        };
+        result.update_variable_count(variable_count);
+        result
+    }
+}
+
+impl<const V: usize, const N: usize> From<&SmallBdd<V, N>> for Bdd {
+    fn from(bdd: &SmallBdd<V, N>) -> Self {
+        bdd.to_bdd(V as u16)
+    }
+}
+
+impl<const V: usize, const N: usize> TryFrom<&Bdd> for SmallBdd<V, N> {
+    type Error = String;
+
+    /// Fails if the `Bdd` uses a variable which is not smaller than `V`, or if the nodes
+    /// reachable from its root do not fit into `N`.
+    fn try_from(bdd: &Bdd) -> Result<Self, Self::Error> {
+        let root = bdd.root_node();
+        if root.is_zero() {
+            return Ok(SmallBdd::new_false());
+        }
+        let mut result = SmallBdd::new_true();
+        // Maps node ids of `bdd` to indices in `result`, using DFS postorder.
+        let mut translation = vec![UNDEFINED; bdd.node_count()];
+        translation[0] = 0;
+        translation[1] = 1;
+        let mut stack = vec![root];
+        while let Some(top) = stack.last().cloned() {
+            if translation[top.as_index()] != UNDEFINED {
+                stack.pop();
+                continue;
+            }
+            let (variable, low, high) = bdd.get_node(top).unpack();
+            let new_low = translation[low.as_index()];
+            let new_high = translation[high.as_index()];
+            if new_low == UNDEFINED || new_high == UNDEFINED {
+                if new_high == UNDEFINED {
+                    stack.push(high);
+                }
+                if new_low == UNDEFINED {
+                    stack.push(low);
+                }
+                continue;
+            }
+            if usize::from(variable.0) >= V {
+                return Err(format!("Variable {} exceeds the limit of {}.", variable.0, V));
+            }
+            if result.node_count() == N {
+                return Err(format!("The Bdd does not fit into {} nodes.", N));
+            }
+            result.nodes[result.node_count()] = SmallNode {
+                variable: variable.0 as u8,
+                low: new_low,
+                high: new_high,
+            };
+            translation[top.as_index()] = result.node_count;
+            result.node_count += 1;
+            stack.pop();
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v2::_impl_::test_util::variables;
+    use super::SmallBdd;
+    use crate::v2::{Bdd, VariableId};
+    use core::convert::TryFrom;
+
+    #[test]
+    pub fn small_bdd_test() {
+        type Small = SmallBdd<8, 64>;
+        let vars: Vec<Small> = (0..8u16)
+            .map(|v| Small::new_variable(VariableId::from(v)))
+            .collect();
+        let big_vars = variables(8);
+
+        // (x0 <=> x7) & (x1 | !x6) ^ (x2 => x5)
+        let small = vars[0]
+            .iff(&vars[7])
+            .unwrap()
+            .and(&vars[1].or(&vars[6].xor(&Small::new_true()).unwrap()).unwrap())
+            .unwrap()
+            .xor(&vars[2].imp(&vars[5]).unwrap())
+            .unwrap();
+        let big = big_vars[0]
+            .iff(&big_vars[7])
+            .and(&big_vars[1].or(&big_vars[6].xor(&Bdd::true_with_capacity(2))))
+            .xor(&big_vars[2].imp(&big_vars[5]));
+
+        let converted = Bdd::from(&small);
+        assert_eq!(8, converted.variable_count());
+        assert_eq!(1, converted.xor(&big).node_count());
+        let round_trip = Small::try_from(&big).unwrap();
+        assert_eq!(1, Bdd::from(&round_trip).xor(&big).node_count());
+
+        assert!(small.and_not(&small).unwrap().is_false());
+        assert!(small.iff(&small).unwrap().is_true());
+        assert!(Small::try_from(&Bdd::new_variable(VariableId::from(8))).is_err());
+    }
+
+    #[test]
+    pub fn small_bdd_capacity_test() {
+        // The parity of 8 variables needs 2 * 7 + 1 decision nodes.
+        type Small = SmallBdd<8, 12>;
+        let mut parity = Small::new_false();
+        let mut result = None;
+        for v in 0..8u16 {
+            match parity.xor(&Small::new_variable(VariableId::from(v))) {
+                Some(bdd) => parity = bdd,
+                None => {
+                    result = Some(v);
+                    break;
+                }
+            }
+        }
+        assert!(result.is_some());
+        assert!(Small::try_from(&Bdd::from(&parity)).is_ok());
+    }
+}
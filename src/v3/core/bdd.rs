@@ -1,7 +1,11 @@
 use crate::v3::core::node_id::NodeId;
 use crate::v3::core::packed_bdd_node::PackedBddNode;
-use std::convert::TryFrom;
+use core::convert::TryFrom;
 use crate::v3::core::variable_id::VariableId;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::vec;
 
 #[derive(Clone)]
 pub struct Bdd {
@@ -213,14 +217,65 @@ impl TryFrom<&str> for Bdd {
     }
 }
 
+/// Convert a `v3::core::bdd::Bdd` into a `v4::core::Bdd`. Both representations use 32-bit
+/// variables and 64-bit node ids, so only the invariants of the graph need to be validated.
+impl TryFrom<&Bdd> for crate::v4::core::Bdd {
+    type Error = String;
+
+    fn try_from(bdd: &Bdd) -> Result<Self, Self::Error> {
+        use crate::v4::core::{Node, NodeIndex, Variable};
+        let nodes: Vec<Node> = bdd.nodes.iter().map(|node| {
+            let (variable, low, high) = node.unpack();
+            Node::pack(
+                Variable::from(u32::from(variable)),
+                NodeIndex::from(u64::from(low)),
+                NodeIndex::from(u64::from(high)),
+            )
+        }).collect();
+        if let Some(error) = crate::v4::core::Bdd::check_consistency_errors(&nodes) {
+            Err(error)
+        } else {
+            Ok(crate::v4::core::Bdd::from_nodes(nodes))
+        }
+    }
+}
+
+/// Convert a `v4::core::Bdd` into a `v3::core::bdd::Bdd`. This conversion always succeeds,
+/// since the `v4` invariants are stricter than the `v3` invariants.
+impl From<&crate::v4::core::Bdd> for Bdd {
+    fn from(bdd: &crate::v4::core::Bdd) -> Self {
+        let nodes = bdd.as_node_slice().iter().map(|node| {
+            // Terminals use a 64-bit undefined variable, so they cannot be simply re-packed.
+            if node.is_terminal() {
+                return if node.get_low_link().is_zero() {
+                    PackedBddNode::ZERO
+                } else {
+                    PackedBddNode::ONE
+                };
+            }
+            let (variable, low, high) = node.unpack();
+            PackedBddNode::pack(
+                VariableId::from(u32::from(variable)),
+                NodeId::from(u64::from(low)),
+                NodeId::from(u64::from(high)),
+            )
+        }).collect();
+        Bdd {
+            height: bdd.get_height() as usize,
+            nodes,
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::v3::core::bdd::Bdd;
-    use std::convert::TryFrom;
+    use core::convert::TryFrom;
 
     #[test]
+    #[cfg(not(feature = "wasm"))] // Reads test data from the file system.
     pub fn basic_sorting_test() {
-        let bdd = std::fs::read_to_string("bench_inputs/itgr/large-large-large.109.and_not.left.bdd").unwrap();
+        let bdd = crate::testing::read_corpus_file("itgr/large-large-large.109.and_not.left.bdd").unwrap();
         let bdd = Bdd::try_from(bdd.as_str()).unwrap();
 
         // Note that initially, the BDD is in post-order, but sorted from high to low. Our
@@ -236,4 +291,17 @@ mod test {
         assert_eq!(postorder.nodes, preorder.sort_postorder().nodes);
     }
 
-}
\ No newline at end of file
+    #[test]
+    pub fn v3_v4_round_trip() {
+        let bdd = Bdd::try_from("5,0,0|5,1,1|3,0,1|1,2,1|").unwrap();
+        let converted = crate::v4::core::Bdd::try_from(&bdd).unwrap();
+        assert_eq!(bdd.node_count() as u64, converted.get_size());
+        let back = Bdd::from(&converted);
+        assert_eq!(bdd.nodes, back.nodes);
+
+        // Variable ordering violation is detected.
+        let invalid = Bdd::try_from("5,0,0|5,1,1|3,0,1|1,2,3|").unwrap();
+        assert!(crate::v4::core::Bdd::try_from(&invalid).is_err());
+    }
+
+}
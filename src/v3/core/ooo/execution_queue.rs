@@ -5,6 +5,7 @@ use crate::v3::core::ooo::task_cache::TaskCacheSlot;
 use crate::v3::core::ooo::task_stack::StackedTask;
 use crate::v3::core::packed_bdd_node::PackedBddNode;
 use crate::v3::core::variable_id::VariableId;
+use alloc::vec::Vec;
 
 const ROB_SLOT: u64 = 1 << 63;
 
@@ -16,6 +17,21 @@ pub struct PendingTask {
     result: (u64, u64),
     task_cache_slot: TaskCacheSlot,
     node_cache_slot: NodeCacheSlot,
+    /// The predicted result node and the node cache slot found by its speculative probe
+    /// (see `NodeCache::probe`), if the task speculated.
+    speculation: Option<(PackedBddNode, NodeCacheSlot)>,
+}
+
+assert_eq_size!(PendingTask, [u64; 12]);
+
+/// The outcome of `PendingTask::validate_speculation`.
+pub enum SpeculationOutcome {
+    /// The task did not speculate.
+    None,
+    /// The prediction was correct. Contains the slot found by the speculative probe.
+    Correct(NodeCacheSlot),
+    /// The prediction was wrong.
+    Mispredicted,
 }
 
 impl PendingTask {
@@ -85,6 +101,34 @@ impl PendingTask {
         self.variable
     }
 
+    /// Returns `true` if this task already speculated (see `PendingTask::speculate`).
+    pub fn has_speculated(&self) -> bool {
+        self.speculation.is_some()
+    }
+
+    /// Record a speculation: the result node of this task is predicted to be `node`,
+    /// and `slot` is the node cache slot found by the speculative probe.
+    pub fn speculate(&mut self, node: PackedBddNode, slot: NodeCacheSlot) {
+        self.speculation = Some((node, slot));
+    }
+
+    /// Validate the speculation of this task once both results are known. The speculative
+    /// state is squashed regardless of the outcome.
+    ///
+    /// # Safety
+    ///
+    /// Both results must be known.
+    pub unsafe fn validate_speculation(&mut self) -> SpeculationOutcome {
+        debug_assert!(self.has_low_result() && self.has_high_result());
+        match self.speculation.take() {
+            None => SpeculationOutcome::None,
+            Some((node, slot)) if node == unsafe { self.result_node() } => {
+                SpeculationOutcome::Correct(slot)
+            }
+            Some(_) => SpeculationOutcome::Mispredicted,
+        }
+    }
+
     pub fn mark_as_retired(&mut self) {
         self.rob_slot = RobSlot::UNDEFINED;
     }
@@ -148,6 +192,7 @@ impl<const LEN: usize> ExecutionRetireQueue<LEN> {
             result: task.get_raw_results(),
             task_cache_slot: task.get_task_slot(),
             node_cache_slot: NodeCacheSlot::UNDEFINED,
+            speculation: None,
         };
         self.execution_tail = (self.execution_tail + 1) % LEN
     }
@@ -191,3 +236,93 @@ impl<const LEN: usize> ExecutionRetireQueue<LEN> {
     }
 
 }
+
+#[cfg(test)]
+mod test {
+    use crate::v3::core::node_id::NodeId;
+    use crate::v3::core::ooo::execution_queue::{ExecutionRetireQueue, SpeculationOutcome};
+    use crate::v3::core::ooo::node_cache::NodeCacheSlot;
+    use crate::v3::core::ooo::reorder_buffer::RobSlot;
+    use crate::v3::core::ooo::task_cache::TaskCacheSlot;
+    use crate::v3::core::ooo::task_stack::TaskStack;
+    use crate::v3::core::packed_bdd_node::PackedBddNode;
+    use crate::v3::core::variable_id::VariableId;
+
+    #[test]
+    pub fn execution_retire_queue_test() {
+        unsafe {
+            // A task with a known low result and a high result that is still in ROB slot 3.
+            let mut stack = TaskStack::new(2, 2);
+            stack.push_new(0, (NodeId::from(2u64), NodeId::from(3u64)));
+            stack.push_new(1, (NodeId::from(4u64), NodeId::from(5u64)));
+            stack.push_new(2, (NodeId::from(6u64), NodeId::from(7u64)));
+            stack.pop_with_node_id(NodeId::ZERO);
+            stack.pop_with_slot_id(RobSlot::from(3u32));
+            let task = stack.get_top_mut();
+            task.set_decision_variable(VariableId::from(5u32));
+            task.set_task_slot(TaskCacheSlot::from(7u64));
+
+            // A queue of length `LEN` holds at most `LEN - 1` tasks.
+            let mut queue = ExecutionRetireQueue::<4>::new();
+            assert!(queue.is_empty());
+            for rob in 0..3u32 {
+                assert!(!queue.is_full());
+                queue.enqueue_for_execution(RobSlot::from(rob), task);
+            }
+            assert!(queue.is_full());
+            assert!(queue.can_execute());
+            assert!(!queue.can_retire());
+
+            // Execute stage: the first task waits for its high result and speculates.
+            let predicted = PackedBddNode::pack(5u32.into(), NodeId::ZERO, 9u64.into());
+            let pending = queue.execute_task_reference();
+            assert_eq!(RobSlot::from(0u32), pending.get_rob());
+            assert_eq!((NodeId::from(2u64), NodeId::from(3u64)), pending.operands());
+            assert!(pending.has_low_result());
+            assert!(!pending.has_high_result());
+            assert_eq!(NodeId::ZERO, pending.get_low_result());
+            assert_eq!(RobSlot::from(3u32), pending.get_high_rob());
+            pending.speculate(predicted.clone(), NodeCacheSlot::UNDEFINED);
+            assert!(pending.has_speculated());
+            pending.set_high_result(NodeId::from(9u64));
+            assert!(matches!(
+                pending.validate_speculation(),
+                SpeculationOutcome::Correct(slot) if slot.is_undefined()
+            ));
+            assert!(!pending.has_speculated());
+            queue.move_to_retire();
+            assert!(queue.can_retire());
+
+            // The second task mispredicts, the third one does not speculate at all.
+            let pending = queue.execute_task_reference();
+            pending.speculate(predicted, NodeCacheSlot::UNDEFINED);
+            pending.set_high_result(NodeId::from(8u64));
+            assert!(matches!(pending.validate_speculation(), SpeculationOutcome::Mispredicted));
+            queue.move_to_retire();
+            let pending = queue.execute_task_reference();
+            pending.set_high_result(NodeId::from(8u64));
+            assert!(matches!(pending.validate_speculation(), SpeculationOutcome::None));
+            queue.move_to_retire();
+            assert!(!queue.can_execute());
+
+            // Retire stage: tasks are retired in order and only retiring frees up space.
+            assert!(queue.is_full());
+            let retired = queue.retire_task_reference();
+            assert_eq!(RobSlot::from(0u32), retired.get_rob());
+            retired.mark_as_retired();
+            assert!(retired.is_retired());
+            queue.retire();
+            assert!(!queue.is_full());
+
+            // The queue wraps around.
+            queue.enqueue_for_execution(RobSlot::from(3u32), task);
+            assert!(queue.is_full());
+            queue.move_to_retire();
+            for rob in 1..4u32 {
+                assert_eq!(RobSlot::from(rob), queue.retire_task_reference().get_rob());
+                queue.retire();
+            }
+            assert!(queue.is_empty());
+        }
+    }
+}
@@ -1,11 +1,14 @@
-use std::cmp::min;
+use core::cmp::min;
 use crate::v3::core::bdd::Bdd;
 use crate::v3::core::node_id::NodeId;
-use crate::v3::core::ooo::execution_queue::ExecutionRetireQueue;
-use crate::v3::core::ooo::node_cache::NodeCache;
+use crate::v3::core::ooo::execution_queue::{ExecutionRetireQueue, SpeculationOutcome};
+use crate::v3::core::ooo::node_cache::{NodeCache, NodeCacheSlot};
 use crate::v3::core::ooo::reorder_buffer::ReorderBuffer;
 use crate::v3::core::ooo::task_cache::TaskCache;
 use crate::v3::core::ooo::task_stack::TaskStack;
+use crate::v3::core::packed_bdd_node::PackedBddNode;
+use crate::v2::ops::BooleanOp;
+use alloc::vec;
 
 pub mod task_cache;
 pub mod node_cache;
@@ -13,24 +16,87 @@ pub mod task_stack;
 pub mod reorder_buffer;
 pub mod execution_queue;
 
-pub fn apply(left_bdd: &Bdd, right_bdd: &Bdd) -> Bdd {
-    let mut queue = ExecutionRetireQueue::<32>::new();
-    let mut rob = ReorderBuffer::new(left_bdd.get_height() + right_bdd.get_height());
+/// Statistics collected by `apply_with_stats`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct OooStats {
+    /// The number of iterations in which the frontend could not issue a task because
+    /// the ROB or the execution queue was full.
+    pub stalls: usize,
+    /// The number of tasks which speculated on a missing dependency.
+    pub speculations: usize,
+    /// The number of speculations which turned out to be wrong and were squashed.
+    pub mispredictions: usize,
+    /// The number of correct speculations where the slot found by the speculative probe
+    /// was used instead of a new node cache lookup.
+    pub speculative_hits: usize,
+}
+
+impl OooStats {
+    /// The fraction of speculations that were mispredicted (zero if there were none).
+    pub fn misprediction_rate(&self) -> f64 {
+        if self.speculations == 0 {
+            0.0
+        } else {
+            self.mispredictions as f64 / self.speculations as f64
+        }
+    }
+}
+
+/// **(internal)** The number of tasks which can be in flight in the execution queue.
+const QUEUE_CAPACITY: usize = 32;
+
+/// Apply the logical operator `OP` to the two `Bdds` using the out-of-order algorithm.
+///
+/// Instead of finishing one task at a time, the algorithm keeps a queue of tasks in flight
+/// (similar to the pipeline of a CPU), so the memory accesses of independent tasks overlap.
+/// The result is sorted in DFS preorder.
+pub fn apply<OP: BooleanOp>(left_bdd: &Bdd, right_bdd: &Bdd) -> Bdd {
+    apply_with_stats::<OP>(left_bdd, right_bdd, false).0
+}
+
+/// Same as `apply`, but also returns the `OooStats` of the run.
+///
+/// If `speculate` is set, a task that waits for one of its results in the execution queue
+/// predicts that the missing result is a new node, different from the sibling result.
+/// Since node ids grow monotonically, the prediction is the id of the next node created
+/// by the node cache. The task then speculatively probes the node cache for its predicted
+/// result node. Once both results are known, the prediction is validated: if it was correct,
+/// the task continues from the speculative probe (and skips the execute-stage lookup),
+/// otherwise the speculative state is squashed and the task executes normally.
+///
+/// The predicted id does not exist yet when the task speculates, so no cached node can link
+/// to it and the probe never finds the predicted node itself. It only finds the end of
+/// the list where the node would be inserted, which is where the retire stage continues.
+pub fn apply_with_stats<OP: BooleanOp>(
+    left_bdd: &Bdd,
+    right_bdd: &Bdd,
+    speculate: bool,
+) -> (Bdd, OooStats) {
+    let mut stats = OooStats::default();
+    let root_result = lookup::<OP>(left_bdd.get_root_id(), right_bdd.get_root_id());
+    if !root_result.is_undefined() {
+        return (constant(root_result), stats);
+    }
+    // The stored heights can be inexact, so they are not used to allocate the buffers.
+    let (left_height, right_height) = (height_bound(left_bdd), height_bound(right_bdd));
+    let mut queue = ExecutionRetireQueue::<QUEUE_CAPACITY>::new();
+    // Every level of the search can hold two results, and every task in flight can hold
+    // its own result and the results of its two sub-tasks. With this capacity, the ROB can
+    // only be full when some task in flight can make progress.
+    let rob_capacity = 2 * (left_height + right_height) + 3 * QUEUE_CAPACITY;
+    let mut rob = ReorderBuffer::new(rob_capacity);
     let mut task_cache = TaskCache::new(left_bdd.node_count(), right_bdd.node_count());
     let mut node_cache = NodeCache::new(left_bdd.node_count(), 2 * left_bdd.node_count());
-    let mut stack = TaskStack::new(left_bdd.get_height(), right_bdd.get_height());
-    let mut stall = 0;
-    unsafe {
+    let mut stack = TaskStack::new(left_height, right_height);
+    let root = unsafe {
         stack.push_new(0, (left_bdd.get_root_id(), right_bdd.get_root_id()));
 
         while !stack.is_empty() || !queue.is_empty() {
             if queue.can_retire() {
                 let task = queue.retire_task_reference();
                 if task.is_retired() { // The task was retired during the execute step.
-                    //println!("Skip retire. {:?}", task.operands());
                     queue.retire()
                 } else {
-                    //println!("Try retire. {:?}", task.operands());
                     match node_cache.ensure_at(&task.result_node(), task.get_node_slot()) {
                         Ok(id) => {
                             rob.set_slot_value(task.get_rob(), id);
@@ -46,17 +112,28 @@ pub fn apply(left_bdd: &Bdd, right_bdd: &Bdd) -> Bdd {
             if queue.can_execute() {
                 let task = queue.execute_task_reference();
                 if task.has_low_result() && task.has_high_result() {
-                    //println!("Execute. {:?}", task.operands());
                     let low_result = task.get_low_result();
                     let high_result = task.get_high_result();
+                    let speculative_slot = match task.validate_speculation() {
+                        SpeculationOutcome::Correct(slot) => slot,
+                        SpeculationOutcome::Mispredicted => {
+                            stats.mispredictions += 1;
+                            NodeCacheSlot::UNDEFINED
+                        }
+                        SpeculationOutcome::None => NodeCacheSlot::UNDEFINED,
+                    };
 
-                    if low_result == high_result {
+                    if !speculative_slot.is_undefined() {
+                        // The node did not exist, but the speculative probe already found
+                        // where the retire stage should continue the search.
+                        stats.speculative_hits += 1;
+                        task.set_node_slot(speculative_slot);
+                    } else if low_result == high_result {
                         // The node exists, we just need to mark it as a result of this task
                         // and it can be immediately retired (will be skipped in retire queue).
                         rob.set_slot_value(task.get_rob(), low_result);
                         task_cache.write_unchecked(task.operands(), low_result, task.get_task_slot());
                         task.mark_as_retired();
-                        //println!("Retire immediately as {:?}.", low_result);
                     } else {
                         // We actually need to query the node cache to check if this exists or not.
                         match node_cache.ensure(&task.result_node()) {
@@ -65,7 +142,6 @@ pub fn apply(left_bdd: &Bdd, right_bdd: &Bdd) -> Bdd {
                                 rob.set_slot_value(task.get_rob(), id);
                                 task_cache.write_unchecked(task.operands(), id, task.get_task_slot());
                                 task.mark_as_retired();
-                                //println!("Insertion success as {:?}", id);
                             }
                             Err(slot) => {
                                 // Node was not found here, try later.
@@ -76,7 +152,6 @@ pub fn apply(left_bdd: &Bdd, right_bdd: &Bdd) -> Bdd {
                     // Regardless of what happened, the task is moving into retire.
                     queue.move_to_retire();
                 } else {
-                    //println!("Resolve. {:?}", task.operands());
                     if !task.has_low_result() {
                         let slot = task.get_low_rob();
                         let result = rob.get_slot_value(slot);
@@ -93,36 +168,49 @@ pub fn apply(left_bdd: &Bdd, right_bdd: &Bdd) -> Bdd {
                             task.set_high_result(result);
                         }
                     }
+                    let is_waiting_for_one = task.has_low_result() != task.has_high_result();
+                    if speculate && is_waiting_for_one && !task.has_speculated() {
+                        let prediction = NodeId::from(node_cache.len() as u64);
+                        let node = if task.has_low_result() {
+                            let known = task.get_low_result();
+                            PackedBddNode::pack(task.get_decision_variable(), known, prediction)
+                        } else {
+                            let known = task.get_high_result();
+                            PackedBddNode::pack(task.get_decision_variable(), prediction, known)
+                        };
+                        // Predicting the sibling value would mean the task needs no node.
+                        if node.get_low_link() != node.get_high_link() {
+                            stats.speculations += 1;
+                            // The predicted id is not created yet, so the node is never found.
+                            let slot = node_cache.probe(&node).err();
+                            debug_assert!(slot.is_some());
+                            task.speculate(node, slot.unwrap_or(NodeCacheSlot::UNDEFINED));
+                        }
+                    }
                 }
             }
             if !stack.is_empty() {
                 let task = stack.get_top_mut();
                 if task.is_decoded() {
-                    //println!("Issue. {:?} {}", task.operands(), len);
                     // The task should have results declared and can be moved to the execution queue.
                     if !rob.is_full() && !queue.is_full() {
                         let slot = rob.allocate_slot();
                         queue.enqueue_for_execution(slot, task);
                         stack.pop_with_slot_id(slot);
                     } else {
-                        stall += 1;
-                        //println!("Frontend stall.");
-                        //panic!("Frontend stall: {} {}.", rob.is_full(), queue.is_full());
+                        stats.stalls += 1;
                     }
                 } else {
-                    //println!("Decode {:?}", task.operands());
                     // The task is newly created and must be decoded.
                     let (left, right) = task.operands();
-                    if left.is_one() || right.is_one() {
-                        stack.pop_with_node_id(NodeId::ONE);
-                    } else if left.is_zero() && right.is_zero() {
-                        stack.pop_with_node_id(NodeId::ZERO);
+                    let terminal = lookup::<OP>(left, right);
+                    if !terminal.is_undefined() {
+                        stack.pop_with_node_id(terminal);
                     } else {
                         let task_slot = task_cache.find_slot((left, right));
                         let cached_node = task_cache.read_unchecked((left, right), task_slot);
                         if !cached_node.is_undefined() {
                             stack.pop_with_node_id(cached_node);
-                            //println!("Found in cache.");
                         } else {
                             // Actually decode the task into two sub-tasks that will be pushed on
                             // the stack. Also, update task with computed data.
@@ -145,9 +233,6 @@ pub fn apply(left_bdd: &Bdd, right_bdd: &Bdd) -> Bdd {
                                 (right, right)
                             };
 
-                            //println!("Push {:?}", (left_high, right_high));
-                            //println!("Push {:?}", (left_low, right_low));
-
                             task.set_decoded();
                             task.set_task_slot(task_slot);
                             task.set_decision_variable(decision_variable);
@@ -159,11 +244,148 @@ pub fn apply(left_bdd: &Bdd, right_bdd: &Bdd) -> Bdd {
                 }
             }
         }
+        match stack.get_root_result() {
+            Ok(id) => id,
+            Err(slot) => rob.get_slot_value(slot),
+        }
+    };
+
+    debug!("OOO apply: {:?}.", stats);
+    if root.is_terminal() {
+        return (constant(root), stats);
+    }
+    // Every created node is reachable from the root, and the root is created last.
+    let nodes = node_cache.export_nodes();
+    debug_assert_eq!(root, NodeId::from(nodes.len() - 1));
+    let result = unsafe { Bdd::from_raw_nodes(nodes) };
+    (result.sort_preorder(), stats)
+}
+
+/// **(internal)** The terminal result of the task `(left, right)` under the operator `OP`,
+/// or `NodeId::UNDEFINED` if the task must be expanded.
+#[inline(always)]
+fn lookup<OP: BooleanOp>(left: NodeId, right: NodeId) -> NodeId {
+    let value = |id: NodeId| {
+        if id.is_terminal() {
+            Some(id.is_one())
+        } else {
+            None
+        }
+    };
+    match OP::partial_eval(value(left), value(right)) {
+        Some(false) => NodeId::ZERO,
+        Some(true) => NodeId::ONE,
+        None => NodeId::UNDEFINED,
     }
+}
+
+/// **(internal)** A constant `Bdd` given by a terminal node id.
+fn constant(terminal: NodeId) -> Bdd {
+    let nodes = if terminal.is_one() {
+        vec![PackedBddNode::ZERO, PackedBddNode::ONE]
+    } else {
+        vec![PackedBddNode::ZERO]
+    };
+    unsafe { Bdd::from_raw_nodes(nodes) }
+}
+
+/// **(internal)** An upper bound on the number of decision nodes on a path of the `bdd`:
+/// the variables on a path are increasing, so there are at most as many decision nodes as
+/// there are variables between the smallest and the largest variable.
+fn height_bound(bdd: &Bdd) -> usize {
+    let mut range: Option<(usize, usize)> = None;
+    for id in 2..bdd.node_count() {
+        let variable: usize = unsafe { bdd.get_node_unchecked(NodeId::from(id)) }
+            .get_variable()
+            .into();
+        range = Some(match range {
+            None => (variable, variable),
+            Some((min, max)) => (min.min(variable), max.max(variable)),
+        });
+    }
+    range.map(|(min, max)| max - min + 1).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{apply, apply_with_stats};
+    use crate::expressions::BooleanExpression;
+    use crate::v2::ops::{And, AndNot, BooleanOp, Iff, Imp, NotAnd, Or, Xor};
+    use crate::v2::BoolOp;
+    use crate::v3::core::bdd::Bdd;
+    use core::convert::TryFrom;
+
+    fn into_v2(bdd: &Bdd) -> crate::v2::Bdd {
+        let bdd = crate::v4::core::Bdd::try_from(bdd).unwrap();
+        crate::v2::Bdd::try_from(&bdd).unwrap()
+    }
+
+    fn from_v2(bdd: &crate::v2::Bdd) -> Bdd {
+        Bdd::from(&crate::v4::core::Bdd::try_from(bdd).unwrap())
+    }
+
+    #[test]
+    pub fn ooo_apply_test() {
+        let names = ["a", "b", "c", "d", "e"];
+        let build = |expression: &str| {
+            let expression = BooleanExpression::try_from(expression).unwrap();
+            crate::v2::Bdd::from_expression(&expression, &names).unwrap()
+        };
+        let left = build("(a & c) | (b ^ d) | !e");
+        let right = build("(a <=> e) & (c | !b)");
+        let constants = [crate::v2::Bdd::new_false(), crate::v2::Bdd::new_true()];
+        let mut operands = vec![left.clone(), right, left.not()];
+        operands.extend(constants.iter().cloned());
+        for l in &operands {
+            for r in &operands {
+                let check = |op: BoolOp, result: Bdd| {
+                    let expected = l.apply_op(op, r);
+                    assert!(expected.semantic_eq(&into_v2(&result)), "{:?}", op);
+                    // The result is sorted and does not contain unreachable nodes.
+                    let sorted = result.sort_preorder();
+                    let nodes = |bdd: &Bdd| crate::v4::core::Bdd::try_from(bdd).unwrap();
+                    assert_eq!(nodes(&sorted).as_node_slice(), nodes(&result).as_node_slice());
+                };
+                fn run<OP: BooleanOp>(l: &crate::v2::Bdd, r: &crate::v2::Bdd) -> Bdd {
+                    apply::<OP>(&from_v2(l), &from_v2(r))
+                }
+                check(BoolOp::And, run::<And>(l, r));
+                check(BoolOp::Or, run::<Or>(l, r));
+                check(BoolOp::Xor, run::<Xor>(l, r));
+                check(BoolOp::Imp, run::<Imp>(l, r));
+                check(BoolOp::Iff, run::<Iff>(l, r));
+                check(BoolOp::AndNot, run::<AndNot>(l, r));
+                check(BoolOp::NotAnd, run::<NotAnd>(l, r));
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "wasm"))] // Reads test data from the file system.
+    pub fn speculative_apply_test() {
+        let read = |side: &str| {
+            let path = format!("itgr/large-large-large.109.and_not.{}.bdd", side);
+            let data = crate::testing::read_corpus_file(&path).unwrap();
+            Bdd::try_from(data.as_str()).unwrap()
+        };
+        let (left, right) = (read("left"), read("right"));
+        let expected = into_v2(&left).or(&into_v2(&right));
+
+        let (result, stats) = apply_with_stats::<Or>(&left, &right, false);
+        assert_eq!(0, stats.speculations);
+        assert_eq!(2, into_v2(&result).iff(&expected).node_count());
+
+        let (result, stats) = apply_with_stats::<Or>(&left, &right, true);
+        assert_eq!(2, into_v2(&result).iff(&expected).node_count());
+        assert!(stats.speculations > 0);
+        assert!(stats.mispredictions + stats.speculative_hits <= stats.speculations);
+        assert!(stats.misprediction_rate() <= 1.0);
+        // Some predictions are correct, and some of them reuse the slot of their probe.
+        assert!(stats.mispredictions < stats.speculations);
+        assert!(stats.speculative_hits > 0);
 
-    println!("Stall: {}", stall);
-    // TODO: Add sorting.
-    unsafe {
-        Bdd::from_raw_nodes(node_cache.export_nodes())
+        let expected = into_v2(&left).and_not(&into_v2(&right));
+        let result = apply::<AndNot>(&left, &right);
+        assert!(expected.semantic_eq(&into_v2(&result)));
     }
-}
\ No newline at end of file
+}
@@ -1,8 +1,10 @@
-use std::num::NonZeroU64;
+use core::num::NonZeroU64;
 use crate::v3::core::packed_bdd_node::PackedBddNode;
-use std::ops::{BitXor, Rem};
-use std::cmp::max;
+use core::ops::{BitXor, Rem};
+use core::cmp::max;
 use crate::v3::core::node_id::NodeId;
+use alloc::vec::Vec;
+use alloc::vec;
 
 pub struct NodeCache {
     capacity: NonZeroU64,
@@ -56,18 +58,18 @@ impl NodeCache {
     const HASH_BLOCK: u64 = 1 << 14;
     const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
 
+    /// Create a cache with a hash table of `table_capacity` entries and space reserved for
+    /// `node_capacity` nodes. The table has a fixed size, but the nodes grow as needed.
     pub fn new(table_capacity: usize, node_capacity: usize) -> NodeCache {
-        debug_assert!(node_capacity > 2);
         debug_assert!(table_capacity > 0);
         NodeCache {
             capacity: NonZeroU64::new(table_capacity as u64).unwrap(),
             index_after_last: 2,    // Initially, there are two nodes already.
             table: vec![NodeCacheSlot::UNDEFINED; table_capacity],
             nodes: {
-                let mut result = Vec::with_capacity(node_capacity);
-                unsafe { result.set_len(node_capacity); }
-                result[0] = (PackedBddNode::ZERO, NodeCacheSlot::UNDEFINED);
-                result[1] = (PackedBddNode::ONE, NodeCacheSlot::UNDEFINED);
+                let mut result = Vec::with_capacity(max(node_capacity, 2));
+                result.push((PackedBddNode::ZERO, NodeCacheSlot::UNDEFINED));
+                result.push((PackedBddNode::ONE, NodeCacheSlot::UNDEFINED));
                 result
             }
         }
@@ -86,11 +88,7 @@ impl NodeCache {
             // This hash has not been seen before. Create a new node for it.
             let fresh_slot = NodeCacheSlot::from(self.index_after_last);
             *linked_list_start = fresh_slot;
-            self.index_after_last += 1;
-
-            let slot_value = unsafe { self.nodes.get_unchecked_mut(fresh_slot.into_usize()) };
-            *slot_value = (node.clone(), NodeCacheSlot::UNDEFINED);
-
+            self.push_node(node);
             Ok(fresh_slot.into())
         } else {
             // There already is a value for this hash, try later.
@@ -112,19 +110,37 @@ impl NodeCache {
             // The chain ends here and we still haven't found the node. Create it.
             let fresh_slot = NodeCacheSlot::from(self.index_after_last);
             slot_value.1 = fresh_slot;
-            self.index_after_last += 1;
-
-            let slot_value = unsafe { self.nodes.get_unchecked_mut(fresh_slot.into_usize()) };
-            *slot_value = (node.clone(), NodeCacheSlot::UNDEFINED);
-
+            self.push_node(node);
             Ok(fresh_slot.into())
         }
     }
 
-    pub fn check_capacity(&mut self)  {
-        if self.index_after_last == self.nodes.len() {
-            unimplemented!("Re-allocate nodes.")
+    /// Find the given node without modifying the cache. If the node is not found, returns
+    /// the last slot of the linked list where the node would be inserted (or an undefined
+    /// slot if the list is empty).
+    ///
+    /// Nodes are never removed and lists only grow at the end, hence the result stays usable
+    /// for `ensure_at` later: a found node stays valid and a returned slot is still a part
+    /// of the list (the node might have been added after it in the meantime).
+    pub fn probe(&self, node: &PackedBddNode) -> Result<NodeId, NodeCacheSlot> {
+        let mut slot = unsafe { *self.table.get_unchecked(self.hash_position(node)) };
+        while !slot.is_undefined() {
+            let slot_value = unsafe { self.nodes.get_unchecked(slot.into_usize()) };
+            if &slot_value.0 == node {
+                return Ok(slot.into());
+            }
+            if slot_value.1.is_undefined() {
+                return Err(slot);
+            }
+            slot = slot_value.1;
         }
+        Err(NodeCacheSlot::UNDEFINED)
+    }
+
+    /// **(internal)** Append a new node which is not a part of any linked list yet.
+    fn push_node(&mut self, node: &PackedBddNode) {
+        self.nodes.push((node.clone(), NodeCacheSlot::UNDEFINED));
+        self.index_after_last += 1;
     }
 
     fn hash_position(&self, key: &PackedBddNode) -> usize {
@@ -138,7 +154,7 @@ impl NodeCache {
     }
 
     pub fn export_nodes(self) -> Vec<PackedBddNode> {
-        self.nodes.into_iter().take(self.index_after_last).map(|(node, _)| { /*println!("{:?}", node); */node }).collect()
+        self.nodes.into_iter().map(|(node, _)| node).collect()
     }
 
 }
@@ -184,6 +200,44 @@ mod test {
         assert_eq!(NodeCacheSlot::from(3u64), result);
         let result = cache.ensure_at(&node_2, result).unwrap();
         assert_eq!(NodeId::from(3u64), result);
+
+        // Probing finds both nodes in the chain, but does not create new nodes.
+        let node_3 = PackedBddNode::pack(789u32.into(), 14u64.into(), 12u64.into());
+        assert_eq!(Ok(NodeId::from(2u64)), cache.probe(&node_1));
+        assert_eq!(Ok(NodeId::from(3u64)), cache.probe(&node_2));
+        assert_eq!(Err(NodeCacheSlot::from(3u64)), cache.probe(&node_3));
+        assert_eq!(4, cache.len());
+    }
+
+    #[test]
+    pub fn stale_probe_node_cache_test() {
+        let mut cache = NodeCache::new(2, 16);
+        // The terminals are always present and new nodes get increasing ids.
+        assert_eq!(2, cache.len());
+        let node_1 = PackedBddNode::pack(1u32.into(), 0u64.into(), 1u64.into());
+        let node_2 = PackedBddNode::pack(2u32.into(), 0u64.into(), 1u64.into());
+        let node_3 = PackedBddNode::pack(3u32.into(), 0u64.into(), 1u64.into());
+        assert_eq!(Err(NodeCacheSlot::UNDEFINED), cache.probe(&node_1));
+        assert_eq!(Ok(NodeId::from(2u64)), cache.ensure(&node_1));
+
+        // A probe returns the end of the chain...
+        let probe = cache.probe(&node_3).unwrap_err();
+        assert_eq!(NodeCacheSlot::from(2u64), probe);
+
+        // ...which can grow before the probe is used...
+        let slot = cache.ensure(&node_2).unwrap_err();
+        assert_eq!(Ok(NodeId::from(3u64)), cache.ensure_at(&node_2, slot));
+
+        // ...but the stale slot is still a part of the chain, so it is still valid.
+        let next = cache.ensure_at(&node_3, probe).unwrap_err();
+        assert_eq!(NodeCacheSlot::from(3u64), next);
+        assert_eq!(Ok(NodeId::from(4u64)), cache.ensure_at(&node_3, next));
+        assert_eq!(Ok(NodeId::from(4u64)), cache.probe(&node_3));
+        assert_eq!(5, cache.len());
+        let nodes = cache.export_nodes();
+        assert_eq!(PackedBddNode::ZERO, nodes[0]);
+        assert_eq!(PackedBddNode::ONE, nodes[1]);
+        assert_eq!(node_3, nodes[4]);
     }
 
 }
\ No newline at end of file
@@ -1,4 +1,6 @@
 use crate::v3::core::node_id::NodeId;
+use alloc::vec::Vec;
+use alloc::vec;
 
 /// `ReorderBuffer` (ROB) keeps track of outstanding dependencies of "in flight" tasks.
 ///
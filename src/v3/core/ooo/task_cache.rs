@@ -1,7 +1,9 @@
-use std::num::NonZeroU64;
+use core::num::NonZeroU64;
 use crate::v3::core::node_id::NodeId;
-use std::ops::{BitXor, Rem};
-use std::cmp::max;
+use core::ops::{BitXor, Rem};
+use core::cmp::max;
+use alloc::vec::Vec;
+use alloc::vec;
 
 pub struct TaskCache {
     collisions: u64,
@@ -1,8 +1,9 @@
-use std::ops::Not;
+use core::ops::Not;
 use crate::v3::core::node_id::NodeId;
 use crate::v3::core::ooo::reorder_buffer::RobSlot;
 use crate::v3::core::ooo::task_cache::TaskCacheSlot;
 use crate::v3::core::variable_id::VariableId;
+use alloc::vec::Vec;
 
 const NOT_DECODED: u32 = 1 << 31;
 const ROB_SLOT: u64 = 1 << 63;
@@ -21,6 +22,8 @@ pub struct StackedTask {
     task_cache_slot: TaskCacheSlot,
 }
 
+assert_eq_size!(StackedTask, [u64; 6]);
+
 impl StackedTask {
 
     pub fn is_decoded(&self) -> bool {
@@ -63,10 +66,14 @@ pub struct TaskStack {
 
 impl TaskStack {
 
+    /// Create a stack for operands with the given heights (upper bounds are fine).
     pub fn new(height_left: usize, height_right: usize) -> TaskStack {
-        let mut items = Vec::with_capacity(height_left + height_right);
+        // Every level of the search keeps a decoded task and its pending high sub-task
+        // on the stack, plus the top task.
+        let capacity = 2 * (height_left + height_right) + 1;
+        let mut items = Vec::with_capacity(capacity);
         unsafe {
-            items.set_len(height_left + height_right);
+            items.set_len(capacity);
         }
         TaskStack {
             index_after_last: 0,
@@ -98,6 +105,21 @@ impl TaskStack {
         self.index_after_last
     }
 
+    /// The result of the root (first) task once it was popped: either its `NodeId`,
+    /// or the ROB slot where the result will be stored.
+    ///
+    /// # Safety
+    ///
+    /// The root task must be already popped.
+    pub unsafe fn get_root_result(&self) -> Result<NodeId, RobSlot> {
+        let result = unsafe { self.items.get_unchecked(0) }.results.0;
+        if result & ROB_SLOT == 0 {
+            Ok(NodeId::from(result))
+        } else {
+            Err(RobSlot::from(result as u32))
+        }
+    }
+
     pub unsafe fn pop_with_slot_id(&mut self, result: RobSlot) {
         unsafe { self.pop_with_result(u64::from(u32::from(result)) | ROB_SLOT); }
     }
@@ -116,6 +138,39 @@ impl TaskStack {
         }
     }
 
-
-
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use crate::v3::core::node_id::NodeId;
+    use crate::v3::core::ooo::reorder_buffer::RobSlot;
+    use crate::v3::core::ooo::task_stack::{TaskStack, ROB_SLOT};
+
+    #[test]
+    pub fn task_stack_offset_test() {
+        unsafe {
+            let mut stack = TaskStack::new(2, 2);
+            assert!(stack.is_empty());
+            stack.push_new(0, (NodeId::from(2u64), NodeId::from(3u64)));
+            assert!(!stack.get_top_mut().is_decoded());
+            stack.get_top_mut().set_decoded();
+            assert!(stack.get_top_mut().is_decoded());
+
+            // The high task is one slot above the root, the low task is two slots above it.
+            stack.push_new(1, (NodeId::from(4u64), NodeId::from(5u64)));
+            stack.push_new(2, (NodeId::from(6u64), NodeId::from(7u64)));
+            assert_eq!(3, stack.len());
+
+            // Popping a task writes its result into the matching slot of the spawning task.
+            stack.pop_with_node_id(NodeId::from(10u64));
+            stack.pop_with_slot_id(RobSlot::from(3u32));
+            let root = stack.get_top_mut();
+            assert_eq!((NodeId::from(2u64), NodeId::from(3u64)), root.operands());
+            assert_eq!((10, 3 | ROB_SLOT), root.get_raw_results());
+
+            // The root task (offset zero) writes its result into itself.
+            stack.pop_with_node_id(NodeId::ONE);
+            assert!(stack.is_empty());
+            assert_eq!(Ok(NodeId::ONE), stack.get_root_result());
+        }
+    }
+}
@@ -11,6 +11,8 @@ pub struct PackedBddNode {
     high_link: u64,
 }
 
+assert_eq_size!(PackedBddNode, [u64; 3]);
+
 impl PackedBddNode {
     pub const ZERO: PackedBddNode = PackedBddNode {
         variable: u64::MAX,
@@ -0,0 +1,105 @@
+use super::super::core::{Node, NodeIndex};
+use crate::IntoIndex;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::hash::{Hash, Hasher};
+use core::sync::atomic::{AtomicU64, Ordering};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+/// The number of shards of a `ShardedMap`. Should be comfortably larger than the number
+/// of threads, such that two threads rarely compete for the same lock.
+const SHARDS: usize = 64;
+
+/// A hash map which can be shared between threads. The keys are split into a fixed number
+/// of shards based on their hash, and each shard is protected by its own lock.
+///
+/// Compared to `TaskCache` and `NodeCache`, this is not particularly clever (or fast), but
+/// it is simple and every operation only holds a lock for the duration of one map access.
+pub struct ShardedMap<K, V> {
+    shards: Vec<Mutex<HashMap<K, V>>>,
+}
+
+impl<K: Hash + Eq, V: Copy> ShardedMap<K, V> {
+    pub fn new() -> ShardedMap<K, V> {
+        ShardedMap {
+            shards: (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.shard(key).get(key).copied()
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        self.shard(&key).insert(key, value);
+    }
+
+    /// Return the value of `key`, or insert the value produced by `make` if there is none.
+    ///
+    /// The shard stays locked while `make` runs, so for every key, `make` runs at most once.
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&self, key: K, make: F) -> V {
+        *self.shard(&key).entry(key).or_insert_with(make)
+    }
+
+    /// Consume the map, returning all its entries (in no particular order).
+    pub fn into_entries(self) -> impl Iterator<Item = (K, V)> {
+        self.shards
+            .into_iter()
+            .flat_map(|shard| shard.into_inner().unwrap().into_iter())
+    }
+
+    fn shard(&self, key: &K) -> MutexGuard<'_, HashMap<K, V>> {
+        // `DefaultHasher::new` uses fixed keys, so the sharding is deterministic.
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let index = (hasher.finish() as usize) % self.shards.len();
+        // A poisoned lock means another worker panicked, so we panic as well.
+        self.shards[index].lock().unwrap()
+    }
+}
+
+/// A `TaskCache` which can be shared between threads. Unlike `TaskCache`, it is not leaky,
+/// so a task which was completed by one thread is never recomputed by another thread
+/// (unless both are computing it at the same time).
+pub type ConcurrentTaskCache = ShardedMap<(NodeIndex, NodeIndex), NodeIndex>;
+
+/// A `NodeCache` which can be shared between threads.
+///
+/// The indices are assigned from a shared atomic counter (starting after the two terminal
+/// nodes), so they are unique, but they do not follow any particular order. In particular,
+/// a node can have a larger index than its parent.
+pub struct ConcurrentNodeCache {
+    nodes: ShardedMap<Node, NodeIndex>,
+    index_after_last: AtomicU64,
+}
+
+impl ConcurrentNodeCache {
+    pub fn new() -> ConcurrentNodeCache {
+        ConcurrentNodeCache {
+            nodes: ShardedMap::new(),
+            index_after_last: AtomicU64::new(2),
+        }
+    }
+
+    /// Return the index of the given decision `node`, creating a new index if the node is not
+    /// in the cache yet.
+    pub fn ensure(&self, node: Node) -> NodeIndex {
+        self.nodes.get_or_insert_with(node, || {
+            NodeIndex::from(self.index_after_last.fetch_add(1, Ordering::Relaxed))
+        })
+    }
+
+    /// Consume the cache, returning a vector where every node is stored at its index
+    /// (the terminal nodes are at indices zero and one).
+    pub fn into_nodes(self) -> Vec<Node> {
+        let size = self.index_after_last.load(Ordering::Relaxed).into_index();
+        let mut nodes = vec![Node::ZERO; size];
+        nodes[1] = Node::ONE;
+        for (node, index) in self.nodes.into_entries() {
+            nodes[index.into_index()] = node;
+        }
+        nodes
+    }
+}
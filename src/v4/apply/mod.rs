@@ -1,23 +1,39 @@
-mod task_cache;
-mod unsafe_stack;
+pub(crate) mod task_cache;
+pub(crate) mod unsafe_stack;
 mod node_cache;
+#[cfg(feature = "std")]
+mod concurrent_cache;
+#[cfg(feature = "std")]
+mod parallel;
 
-use super::core::{Bdd, Variable, NodeIndex, Node};
+#[cfg(feature = "std")]
+pub use parallel::par_apply;
+
+use super::core::{Bdd, Variable, NodeIndex, Node, NodeStorage};
+use crate::v2::ops::BooleanOp;
+use crate::v2::TaskCacheConfig;
 use task_cache::{TaskCache, TaskCacheSlot};
 use node_cache::NodeCache;
 use unsafe_stack::UnsafeStack;
-use crate::IntoIndex;
-use std::cmp::{max, min};
+use crate::{FromIndex, IntoIndex};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::{max, min};
 
+/// One frame of the apply stack (also used by `v4::mtbdd::Mtbdd::apply`).
 #[derive(Copy, Clone, Eq, PartialEq)]
-struct ApplyTask {
+pub(crate) struct ApplyTask {
     offset: u8,
-    variable: Variable,
-    task: (NodeIndex, NodeIndex),
-    results: [NodeIndex; 2],
-    task_cache_slot: TaskCacheSlot,
+    pub(crate) variable: Variable,
+    pub(crate) task: (NodeIndex, NodeIndex),
+    pub(crate) results: [NodeIndex; 2],
+    pub(crate) task_cache_slot: TaskCacheSlot,
 }
 
+// The stack is sized for the height of the operands, so a task should stay reasonably small.
+assert_eq_size!(ApplyTask, [u64; 6]);
+
 impl ApplyTask {
 
     pub fn new(offset: u8, task: (NodeIndex, NodeIndex)) -> ApplyTask {
@@ -46,22 +62,225 @@ impl ApplyTask {
     }
 }
 
-pub fn apply(left_bdd: &Bdd, right_bdd: &Bdd) -> (usize, usize) {
+/// Statistics collected by `apply_with_stats`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct ApplyStats {
+    /// The number of nodes in the node cache at the end of the operation (including
+    /// the two terminal nodes).
+    pub nodes: usize,
+    /// The number of tasks which were expanded (i.e. not resolved by the lookup table
+    /// or the task cache). This is also the number of task cache misses.
+    pub tasks: usize,
+    /// The number of tasks which were resolved by the task cache.
+    pub task_cache_hits: usize,
+    /// The number of node cache lookups (one for every non-redundant result node).
+    pub node_lookups: usize,
+    /// The number of node cache lookups which found an existing node.
+    pub node_cache_hits: usize,
+    /// The number of extra node cache slots which had to be checked because of hash collisions.
+    pub node_cache_collisions: usize,
+    /// The largest number of tasks on the stack.
+    pub stack_high_water: usize,
+}
+
+impl ApplyStats {
+    /// The fraction of task cache lookups which were successful.
+    pub fn task_cache_hit_rate(&self) -> f64 {
+        let lookups = self.task_cache_hits + self.tasks;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.task_cache_hits as f64 / lookups as f64
+        }
+    }
+
+    /// The fraction of node cache lookups which found an existing node.
+    pub fn node_cache_hit_rate(&self) -> f64 {
+        if self.node_lookups == 0 {
+            0.0
+        } else {
+            self.node_cache_hits as f64 / self.node_lookups as f64
+        }
+    }
+}
+
+/// Apply the binary logical operator `OP` (e.g. `ops::Or`, see `ops::BooleanOp`) to the given
+/// `Bdds`. The result is sorted in DFS preorder.
+///
+/// The algorithm works best when the operands are also sorted in DFS preorder and the left
+/// operand is the larger one.
+pub fn apply<OP: BooleanOp>(left_bdd: &Bdd, right_bdd: &Bdd) -> Bdd {
+    apply_with_stats::<OP>(left_bdd, right_bdd).0
+}
+
+/// **(internal)** The terminal result of the task `(left, right)` under the operator `OP`,
+/// or `NodeIndex::UNDEFINED` if the task must be expanded.
+#[inline(always)]
+fn lookup<OP: BooleanOp>(left: NodeIndex, right: NodeIndex) -> NodeIndex {
+    let value = |index: NodeIndex| {
+        if index.is_zero() || index.is_one() {
+            Some(index.is_one())
+        } else {
+            None
+        }
+    };
+    match OP::partial_eval(value(left), value(right)) {
+        Some(false) => NodeIndex::ZERO,
+        Some(true) => NodeIndex::ONE,
+        None => NodeIndex::UNDEFINED,
+    }
+}
+
+/// Same as `apply`, but also returns the `ApplyStats` of the run.
+pub fn apply_with_stats<OP: BooleanOp>(left_bdd: &Bdd, right_bdd: &Bdd) -> (Bdd, ApplyStats) {
+    apply_with_config::<OP>(left_bdd, right_bdd, &TaskCacheConfig::default())
+}
+
+/// Same as `apply_with_stats`, but the task cache is configured using the given `config`.
+/// The capacity of the config is the largest size to which the task cache can grow.
+pub fn apply_with_config<OP: BooleanOp>(
+    left_bdd: &Bdd,
+    right_bdd: &Bdd,
+    config: &TaskCacheConfig,
+) -> (Bdd, ApplyStats) {
     let height_limit = left_bdd.get_height() + right_bdd.get_height();
-    let mut task_cache = TaskCache::new(left_bdd.get_size());
-    let mut node_cache = NodeCache::new(left_bdd.get_size() / 2);
-    let mut task_count = 0;
+    let capacity = max(left_bdd.get_size() / 2, 2);
+    let output = Vec::with_capacity(capacity.into_index());
+    let (left, right) = (left_bdd.as_node_vec(), right_bdd.as_node_vec());
+    let (nodes, root_result, stats) =
+        apply_kernel::<OP, _, _, _>(left, right, height_limit.into_index(), output, config);
+    let result = if root_result.is_one() {
+        Bdd::new_one()
+    } else if root_result.is_zero() {
+        Bdd::new_zero()
+    } else {
+        // Every created node is reachable from the root, and children are always created
+        // before their parents, so the root is the last node.
+        Bdd::from_nodes(nodes).sort_preorder()
+    };
+    (result, stats)
+}
+
+/// Same as `apply_with_config`, but the operands and the result can use any `NodeStorage`
+/// (e.g. a `Vec<Node>`, or a `core::MmapNodeStorage` for `Bdds` which do not fit into RAM).
+///
+/// The result nodes are pushed into the (empty) `output`: first the two terminal nodes, then
+/// every created node after its children. The `output` is returned together with the index
+/// of the result root, which is the last node unless the result is constant. Unlike `apply`,
+/// the result is not sorted.
+///
+/// Fails if an operand is not a valid `Bdd` (with terminal nodes `0` and `1` at indices `0`
+/// and `1`), or if the `output` is not empty. The operands are checked in one pass before
+/// the operation starts, which also yields a bound on their height.
+pub fn apply_with_storage<OP, L, R, S>(
+    left: &L,
+    right: &R,
+    output: S,
+    config: &TaskCacheConfig,
+) -> Result<(S, NodeIndex, ApplyStats), String>
+where
+    OP: BooleanOp,
+    L: NodeStorage + ?Sized,
+    R: NodeStorage + ?Sized,
+    S: NodeStorage,
+{
+    if !output.is_empty() {
+        return Err(String::from("The output storage must be empty."));
+    }
+    let height_limit = check_storage(left)? + check_storage(right)?;
+    let result = apply_kernel::<OP, L, R, S>(left, right, height_limit, output, config);
+    Ok(result)
+}
+
+/// **(internal)** Check that the `storage` contains a valid `Bdd` and return an upper bound
+/// on its height (the number of decision variables between its smallest and largest variable).
+///
+/// Only the properties that the apply algorithm depends on are checked: the terminal nodes,
+/// valid links, and increasing variables along every edge.
+fn check_storage<S: NodeStorage + ?Sized>(storage: &S) -> Result<usize, String> {
+    if storage.is_empty() {
+        return Err(String::from("A Bdd must contain at least one node."));
+    }
+    // The smallest and the largest decision variable.
+    let mut variables: Option<(u32, u32)> = None;
+    for index in 0..storage.len() {
+        let node = unsafe { storage.get_node_unchecked(NodeIndex::from_index(index)) };
+        if index < 2 {
+            let terminal = if index == 0 { Node::ZERO } else { Node::ONE };
+            if node != terminal {
+                return Err(format!("Node {} must be the terminal node {}.", index, index));
+            }
+            continue;
+        }
+        let (variable, low, high) = node.unpack();
+        if variable.is_undefined() {
+            return Err(format!("Node {} has an undefined variable.", index));
+        }
+        for link in [low, high] {
+            let is_valid = link.into_index() < storage.len()
+                && storage.get_node(link).get_variable() > variable;
+            if !is_valid {
+                return Err(format!("Node {} has an invalid link {:?}.", index, link));
+            }
+        }
+        let variable = u32::from(variable);
+        variables = Some(match variables {
+            None => (variable, variable),
+            Some((smallest, largest)) => (min(smallest, variable), max(largest, variable)),
+        });
+    }
+    Ok(match variables {
+        None => 0,
+        Some((smallest, largest)) => {
+            let decision_nodes = storage.len() - 2;
+            min((largest - smallest).into_index() + 1, decision_nodes)
+        }
+    })
+}
+
+/// **(internal)** The apply algorithm shared by `apply_with_config` and `apply_with_storage`.
+///
+/// The `height_limit` must be an upper bound on the sum of the heights of the operands.
+/// The nodes of the result are pushed into the empty `output`.
+fn apply_kernel<OP, L, R, S>(
+    left_bdd: &L,
+    right_bdd: &R,
+    height_limit: usize,
+    output: S,
+    config: &TaskCacheConfig,
+) -> (S, NodeIndex, ApplyStats)
+where
+    OP: BooleanOp,
+    L: NodeStorage + ?Sized,
+    R: NodeStorage + ?Sized,
+    S: NodeStorage,
+{
+    let left_size = u64::from_index(left_bdd.len());
+    let mut task_cache = TaskCache::new(left_size, config);
+    let mut node_cache = NodeCache::with_storage(max(left_size / 2, 2), output);
+    let mut stats = ApplyStats::default();
+
+    let root_task = (
+        NodeIndex::from_index(left_bdd.len() - 1),
+        NodeIndex::from_index(right_bdd.len() - 1),
+    );
+    let root_result = lookup::<OP>(root_task.0, root_task.1);
+    if !root_result.is_undefined() {
+        // The stack cannot handle a root task which is resolved immediately.
+        stats.nodes = 2;
+        return (node_cache.into_storage(), root_result, stats);
+    }
 
     // There are up to height_limit expanded tasks and every task has up to one extra non-expanded
-    // child.
-    let mut stack = UnsafeStack::new(2 * height_limit.into_index());
-    stack.push(ApplyTask::new(0, (left_bdd.get_root_index(), right_bdd.get_root_index())));
+    // child. On top of that, there is the root task.
+    let mut stack = UnsafeStack::new(2 * height_limit + 2);
+    stack.push(ApplyTask::new(0, root_task));
 
-    'main: loop {
+    let root_result = 'main: loop {
         // Aim to perform at least left_bdd.size / 8 iterations before checking again if stuff
         // needs to grow. Also, cap this at at least 1024 iterations (but assumption is that this
         // algorithm should not be used for BDDs that small).
-        let base_iterations = max(left_bdd.get_size() >> 3, 1024);
+        let base_iterations = max(left_size >> 3, 1024);
         // This will give us actual max number of iterations, but will try to be at least as
         // large as base_iterations.
         let node_iterations = node_cache.ensure_capacity(base_iterations);
@@ -74,24 +293,22 @@ pub fn apply(left_bdd: &Bdd, right_bdd: &Bdd) -> (usize, usize) {
             let top = stack.peek();
             let top_offset: usize = top.get_offset().into(); // Save for later...
 
-            let mut result = NodeIndex::UNDEFINED;
+            let mut result;
             // We could also try using top.variable, but this version seems to be faster
             // due to easier branch prediction.
             if top.is_not_decoded() {
                 top.mark_as_decoded();
 
                 let (left, right) = top.task;
-                if left.is_one() || right.is_one() {
-                    result = NodeIndex::ONE;
-                } else if left.is_zero() && right.is_zero() {
-                    result = NodeIndex::ZERO;
-                } else {
+                result = lookup::<OP>(left, right);
+                if result.is_undefined() {
                     let (cached, slot) = task_cache.read(top.task);
                     if !cached.is_undefined() {
+                        stats.task_cache_hits += 1;
                         result = cached;
                     } else {
                         top.task_cache_slot = slot;
-                        task_count += 1;
+                        stats.tasks += 1;
                         // Actually expand this task into sub-tasks.
 
                         let left_node = unsafe { left_bdd.get_node_unchecked(left) };
@@ -99,6 +316,9 @@ pub fn apply(left_bdd: &Bdd, right_bdd: &Bdd) -> (usize, usize) {
 
                         let (l_var, l_low, l_high) = left_node.unpack();
                         let (r_var, r_low, r_high) = right_node.unpack();
+                        // The low tasks are on top of the stack, so they are processed first.
+                        left_bdd.prefetch(l_low);
+                        right_bdd.prefetch(r_low);
 
                         // This explicit "switch" is slightly faster. Not sure exactly why, but
                         // it is probably easier to branch predict.
@@ -115,6 +335,7 @@ pub fn apply(left_bdd: &Bdd, right_bdd: &Bdd) -> (usize, usize) {
                             stack.push(ApplyTask::new(1, (left, r_high)));
                             stack.push(ApplyTask::new(2, (left, r_low)));
                         }
+                        stats.stack_high_water = max(stats.stack_high_water, stack.len());
                     }
                 }
             } else {
@@ -125,17 +346,27 @@ pub fn apply(left_bdd: &Bdd, right_bdd: &Bdd) -> (usize, usize) {
                 } else {
                     let node = Node::pack(top.variable, result_low, result_high);
 
+                    let size_before = node_cache.len();
                     let mut cached = node_cache.ensure(&node);
                     while let Err(slot) = cached {
+                        stats.node_cache_collisions += 1;
                         cached = node_cache.ensure_at(&node, slot);
                     }
                     result = cached.unwrap();
+                    stats.node_lookups += 1;
+                    if node_cache.len() == size_before {
+                        stats.node_cache_hits += 1;
+                    }
                 }
                 task_cache.write(top.task_cache_slot, top.task, result);
             }
 
             if !result.is_undefined() {
                 stack.pop();
+                if stack.is_empty() {
+                    // This was the root task.
+                    break 'main result;
+                }
                 // Offset one is the top task, offset two is the one beneath that.
                 let parent = stack.peek_at(top_offset);
                 // high = 1, low = 2, so they will be saved in reverse order.
@@ -144,12 +375,136 @@ pub fn apply(left_bdd: &Bdd, right_bdd: &Bdd) -> (usize, usize) {
                 };
                 *slot = result;
             }
+        }
+    };
+
+    stats.nodes = node_cache.len();
+    (node_cache.into_storage(), root_result, stats)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{apply, apply_with_config, apply_with_stats, apply_with_storage};
+    use crate::expressions::BooleanExpression;
+    use crate::v2::ops::{And, AndNot, BooleanOp, Iff, Imp, NotAnd, Or, Xor};
+    use crate::v2::{Bdd as Bdd2, BoolOp, ReplacementPolicy, TaskCacheConfig, TaskHash};
+    use crate::v4::core::{Bdd, Node, NodeIndex, Variable};
+    use core::convert::TryFrom;
 
-            if stack.is_empty() {
-                break 'main;
+    fn build(expression: &str, names: &[&str]) -> (Bdd2, Bdd) {
+        let expression = BooleanExpression::try_from(expression).unwrap();
+        let bdd = Bdd2::from_expression(&expression, names).unwrap();
+        let converted = Bdd::try_from(&bdd).unwrap().sort_preorder();
+        (bdd, converted)
+    }
+
+    fn check<OP: BooleanOp>(op: BoolOp, left: &(Bdd2, Bdd), right: &(Bdd2, Bdd)) {
+        let expected = left.0.apply_op(op, &right.0).reduce();
+        let result = apply::<OP>(&left.1, &right.1);
+        assert!(Bdd::check_consistency_errors(result.as_node_slice()).is_none());
+        assert_eq!(result.as_node_slice(), result.sort_preorder().as_node_slice());
+        assert!(Bdd2::try_from(&result).unwrap().semantic_eq(&expected));
+        assert_eq!(expected.node_count() as u64, result.get_size());
+    }
+
+    #[test]
+    pub fn apply_test() {
+        let names = ["a", "b", "c", "d", "e", "f"];
+        let left = build("(a ^ d) & (b ^ e) | (c & f)", &names);
+        let right = build("(a & b) | (c <=> e) | !f", &names);
+        for (l, r) in [(&left, &right), (&right, &left)] {
+            check::<And>(BoolOp::And, l, r);
+            check::<Or>(BoolOp::Or, l, r);
+            check::<Xor>(BoolOp::Xor, l, r);
+            check::<Imp>(BoolOp::Imp, l, r);
+            check::<Iff>(BoolOp::Iff, l, r);
+            check::<AndNot>(BoolOp::AndNot, l, r);
+            check::<NotAnd>(BoolOp::NotAnd, l, r);
+        }
+
+        // Constant results, including a root task which is resolved immediately.
+        let (_, not_left) = build("!((a ^ d) & (b ^ e) | (c & f))", &names);
+        assert!(apply::<Or>(&left.1, &not_left).is_one());
+        assert!(apply::<And>(&left.1, &not_left).is_zero());
+        let (result, stats) = apply_with_stats::<And>(&Bdd::new_zero(), &right.1);
+        assert!(result.is_zero());
+        assert_eq!(0, stats.tasks);
+        let (result, stats) = apply_with_stats::<Or>(&left.1, &right.1);
+        assert!(stats.tasks > 0);
+        assert!(stats.nodes as u64 >= result.get_size());
+        // Every created node (except terminals) comes from a missed node cache lookup.
+        assert_eq!(stats.nodes - 2, stats.node_lookups - stats.node_cache_hits);
+        assert!(stats.stack_high_water > 0);
+        let height = (left.1.get_height() + right.1.get_height()) as usize;
+        assert!(stats.stack_high_water <= 2 * height + 2);
+        assert!((0.0..=1.0).contains(&stats.task_cache_hit_rate()));
+        assert!((0.0..=1.0).contains(&stats.node_cache_hit_rate()));
+    }
+
+    #[test]
+    pub fn apply_with_config_test() {
+        let names = ["a", "b", "c", "d", "e", "f", "g", "h"];
+        let left = build("(a ^ e) & (b ^ f) & (c ^ g) & (d ^ h)", &names);
+        let right = build("(a & b) | (c & d) | (e & f) | (g & h)", &names);
+        let expected = apply::<Xor>(&left.1, &right.1);
+        for replacement in [ReplacementPolicy::Overwrite, ReplacementPolicy::TwoWay] {
+            for hash in [TaskHash::Rolling, TaskHash::Uniform] {
+                // The capped cache cannot grow, but the result is the same.
+                for capacity in [None, Some(2), Some(5)] {
+                    let config = TaskCacheConfig {
+                        capacity,
+                        replacement,
+                        hash,
+                        ..TaskCacheConfig::default()
+                    };
+                    let (result, _) = apply_with_config::<Xor>(&left.1, &right.1, &config);
+                    assert_eq!(expected.as_node_slice(), result.as_node_slice());
+                }
             }
         }
     }
 
-    (node_cache.len(), task_count)
-}
\ No newline at end of file
+    #[test]
+    pub fn apply_with_storage_test() {
+        let names = ["a", "b", "c", "d", "e", "f"];
+        let (_, left) = build("(a ^ d) & (b ^ e) | (c & f)", &names);
+        let (_, right) = build("(a & b) | (c <=> e) | !f", &names);
+        let config = TaskCacheConfig::default();
+        let left_nodes = left.as_node_slice().to_vec();
+        let right_nodes = right.as_node_slice().to_vec();
+
+        let expected = apply::<Iff>(&left, &right);
+        let (nodes, root, stats) =
+            apply_with_storage::<Iff, _, _, _>(&left_nodes, &right_nodes, Vec::new(), &config)
+                .unwrap();
+        assert_eq!(NodeIndex::from((nodes.len() - 1) as u64), root);
+        assert_eq!(stats.nodes, nodes.len());
+        let result = Bdd::from_nodes(nodes).sort_preorder();
+        assert_eq!(expected.as_node_slice(), result.as_node_slice());
+
+        // Constant results only contain the terminal nodes.
+        let and = |left: &Vec<Node>, right: &Vec<Node>, output: Vec<Node>| {
+            apply_with_storage::<And, _, _, _>(left, right, output, &config)
+        };
+        let (nodes, root, _) = and(&left_nodes, &vec![Node::ZERO], Vec::new()).unwrap();
+        assert!(root.is_zero());
+        assert_eq!(vec![Node::ZERO, Node::ONE], nodes);
+
+        // Invalid operands (or a non-empty output) are rejected.
+        assert!(and(&left_nodes, &right_nodes, vec![Node::ZERO]).is_err());
+        assert!(and(&Vec::new(), &right_nodes, Vec::new()).is_err());
+        let mut invalid = left_nodes.clone();
+        invalid[1] = Node::ZERO;
+        assert!(and(&invalid, &right_nodes, Vec::new()).is_err());
+        let mut invalid = left_nodes.clone();
+        let (variable, low, _) = invalid[2].unpack();
+        invalid[2] = Node::pack(variable, low, NodeIndex::from(100));
+        assert!(and(&invalid, &right_nodes, Vec::new()).is_err());
+        // The root variable is larger than the variables of its children.
+        let mut invalid = left_nodes;
+        let root = invalid.len() - 1;
+        let (_, low, high) = invalid[root].unpack();
+        invalid[root] = Node::pack(Variable::from(10), low, high);
+        assert!(and(&invalid, &right_nodes, Vec::new()).is_err());
+    }
+}
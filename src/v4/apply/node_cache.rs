@@ -1,6 +1,8 @@
-use std::cmp::max;
+use core::cmp::max;
 use crate::{FromIndex, IntoIndex};
-use super::super::core::{Node, NodeIndex};
+use super::super::core::{Node, NodeIndex, NodeStorage};
+use alloc::vec::Vec;
+use alloc::vec;
 
 /// Node cache serves as a temporary storage for BDD nodes which is responsible for ensuring that
 /// each node is given a unique index, and that there are no duplicate nodes.
@@ -27,9 +29,13 @@ use super::super::core::{Node, NodeIndex};
 /// To grow the cache, we simply double the size of both tables. Interestingly, since the "hash"
 /// remains the same, we don't need to do any rehashing. However, this also means that all
 /// collisions are deterministic and will appear in the updated table as well.
-pub struct NodeCache {
+///
+/// The nodes themselves are kept in a `NodeStorage` (by default a `Vec<Node>`), only the links
+/// of the linked lists are stored separately (in `next`).
+pub struct NodeCache<S: NodeStorage = Vec<Node>> {
     index_after_last: u64,
-    nodes: Vec<(Node, NodeCacheSlot)>,
+    nodes: S,
+    next: Vec<NodeCacheSlot>,
     table: Vec<NodeCacheSlot>,
 }
 
@@ -73,27 +79,24 @@ impl IntoIndex for NodeCacheSlot {
     }
 }
 
-impl NodeCache {
+impl<S: NodeStorage> NodeCache<S> {
 
-    /// Create a new node cache with the given initial capacity. To make the resulting node indices
-    /// compatible with our BDD conventions, the cache will be also pre-populated with two terminal
-    /// nodes at their assumed positions. As such, the initial capacity must be able to accommodate
-    /// at least these two nodes.
-    pub fn new(initial_capacity: u64) -> NodeCache {
+    /// Create a new node cache with the given initial capacity which stores the nodes
+    /// in the given (empty) `nodes` storage. To make the resulting node indices compatible with
+    /// our BDD conventions, the cache will be also pre-populated with two terminal nodes at their
+    /// assumed positions. As such, the initial capacity must be able to accommodate at least
+    /// these two nodes.
+    pub fn with_storage(initial_capacity: u64, mut nodes: S) -> NodeCache<S> {
         assert!(initial_capacity >= 2);
+        assert!(nodes.is_empty());
         let initial_capacity = initial_capacity.into_index();
+        nodes.push_node(Node::ZERO);
+        nodes.push_node(Node::ONE);
         NodeCache {
             index_after_last: 2,    // Initially, there are two nodes inserted.
             table: vec![NodeCacheSlot::UNDEFINED; initial_capacity],
-            nodes: {
-                // Create a block of uninitialized memory.
-                let mut result = Vec::with_capacity(initial_capacity);
-                unsafe { result.set_len(initial_capacity); }
-                // And fill the first two slots.
-                result[0] = (Node::ZERO, NodeCacheSlot::UNDEFINED);
-                result[1] = (Node::ONE, NodeCacheSlot::UNDEFINED);
-                result
-            }
+            next: vec![NodeCacheSlot::UNDEFINED; initial_capacity],
+            nodes,
         }
     }
 
@@ -101,61 +104,70 @@ impl NodeCache {
         self.index_after_last.into_index()
     }
 
+    /// Consume the cache, returning the storage with all created nodes (including
+    /// the terminals), such that every node is stored at its index.
+    pub fn into_storage(self) -> S {
+        self.nodes
+    }
+
     /// Try to add a node into the cache. If successful (or the node already exists), returns
     /// a `NodeIndex`. Otherwise, return a `NodeCacheSlot` that should be tried during
     /// the next attempt.
     ///
-    /// The function is unsafe because it assumes the cache has sufficient capacity to insert
-    /// a node.
+    /// If the cache is full, it grows before the node is created, so the caller does not
+    /// have to reserve capacity (but see `ensure_capacity`).
     pub fn ensure(&mut self, node: &Node) -> Result<NodeIndex, NodeCacheSlot> {
         let hash_slot = self.hash_position(&node);
-        let linked_list_start = unsafe {
-            self.table.get_unchecked_mut(hash_slot)
-        };
+        let linked_list_start = unsafe { *self.table.get_unchecked(hash_slot) };
         if linked_list_start.is_undefined() {
             // This hash has not been seen before. Create a new node for it.
-            let fresh_slot = NodeCacheSlot::from(self.index_after_last);
-            *linked_list_start = fresh_slot;
-            self.index_after_last += 1;
-
-            let slot_value = unsafe {
-                self.nodes.get_unchecked_mut(fresh_slot.into_index())
-            };
-            *slot_value = (node.clone(), NodeCacheSlot::UNDEFINED);
-
+            let fresh_slot = self.push_node(node);
+            // Growing does not change the hash positions, so the slot is still valid.
+            unsafe {
+                *self.table.get_unchecked_mut(hash_slot) = fresh_slot;
+            }
             Ok(unsafe { fresh_slot.into_node() })
         } else {
             // There already is a value for this hash, try later.
-            Err(*linked_list_start)
+            Err(linked_list_start)
         }
     }
 
     /// Try to add a node to the cache at the given slot. The same as `ensure`, but we are not
     /// starting a new linked list, only continuing an existing one.
-    ///
-    /// The function is unsafe because it assumes the cache has sufficient capacity to insert
-    /// a node.
     pub fn ensure_at(&mut self, node: &Node, slot: NodeCacheSlot) -> Result<NodeIndex, NodeCacheSlot> {
-        let slot_value = unsafe { self.nodes.get_unchecked_mut(slot.into_index()) };
-        if &slot_value.0 == node {
+        let slot_node = unsafe { self.nodes.get_node_unchecked(slot.into_node()) };
+        let next_slot = unsafe { self.next.get_unchecked(slot.into_index()) };
+        if slot_node == *node {
             // This is a duplicate insertion, the node is already here.
             Ok(unsafe { slot.into_node() })
-        } else if !slot_value.1.is_undefined() {
+        } else if !next_slot.is_undefined() {
             // The node is not here, but there is another link in the chain that we can try.
-            Err(slot_value.1)
+            Err(*next_slot)
         } else {
             // The chain ends here and we still haven't found the node. Create it.
-            let fresh_slot = NodeCacheSlot::from(self.index_after_last);
-            slot_value.1 = fresh_slot;
-            self.index_after_last += 1;
-
-            let slot_value = unsafe { self.nodes.get_unchecked_mut(fresh_slot.into_index()) };
-            *slot_value = (node.clone(), NodeCacheSlot::UNDEFINED);
-
+            let fresh_slot = self.push_node(node);
+            unsafe {
+                *self.next.get_unchecked_mut(slot.into_index()) = fresh_slot;
+            }
             Ok(unsafe { fresh_slot.into_node() })
         }
     }
 
+    /// **(internal)** Store a new node at the end of the cache (growing the cache if it is full)
+    /// and return its slot. The node is not linked into any list yet.
+    #[inline]
+    fn push_node(&mut self, node: &Node) -> NodeCacheSlot {
+        if self.free_slots() == 0 {
+            self.grow();
+        }
+        let fresh_slot = NodeCacheSlot::from(self.index_after_last);
+        self.index_after_last += 1;
+        self.nodes.push_node(node.clone());
+        // The link of a fresh slot is always undefined (see `grow`).
+        fresh_slot
+    }
+
     fn hash_position(&self, key: &Node) -> usize {
         let low_link = key.get_low_link().into_index();
         let high_link = key.get_high_link().into_index();
@@ -163,29 +175,70 @@ impl NodeCache {
     }
 
     /// Ensures that the cache can accommodate at least `minimal_capacity` additional nodes.
-    /// The returned number is the actual number of nodes that can be inserted without issues.
+    /// The returned number is the actual number of nodes that can be inserted without growing
+    /// the cache again.
     pub fn ensure_capacity(&mut self, minimal_capacity: u64) -> u64 {
-        let free_slots = u64::from_index(self.nodes.len()) - self.index_after_last;
-        if free_slots >= minimal_capacity {
-            return free_slots;
+        while self.free_slots() < minimal_capacity {
+            self.grow();
         }
+        self.free_slots()
+    }
+
+    /// **(internal)** The number of nodes that can be created before the cache has to grow.
+    #[inline]
+    fn free_slots(&self) -> u64 {
+        u64::from_index(self.next.len()) - self.index_after_last
+    }
+
+    /// **(internal)** Double the size of both tables.
+    ///
+    /// The hash of a node only depends on its links, and every link points to an existing node,
+    /// so the hash positions (and the linked lists) do not change and no rehashing is needed.
+    /// The storage grows on its own.
+    #[cold]
+    fn grow(&mut self) {
+        let capacity = 2 * self.table.len();
+        self.table.resize(capacity, NodeCacheSlot::UNDEFINED);
+        self.next.resize(capacity, NodeCacheSlot::UNDEFINED);
+    }
+
+}
 
-        let first_new_slot = self.table.len();
-        self.nodes.reserve_exact(self.nodes.len());
-        self.table.reserve_exact(self.table.len());
-        // Reserve memory without initializing it.
-        unsafe {
-            self.nodes.set_len(self.nodes.capacity());
-            self.table.set_len(self.table.capacity());
+#[cfg(test)]
+mod test {
+    use super::NodeCache;
+    use crate::v4::core::{Node, NodeIndex, Variable};
+
+    fn ensure(cache: &mut NodeCache, node: &Node) -> NodeIndex {
+        let mut result = cache.ensure(node);
+        while let Err(slot) = result {
+            result = cache.ensure_at(node, slot);
         }
-        // Nodes can be left uninitialized but the hash table needs to be erased.
-        for i in first_new_slot..self.table.len() {
-            unsafe {
-                *self.table.get_unchecked_mut(i) = NodeCacheSlot::UNDEFINED;
+        result.unwrap()
+    }
+
+    #[test]
+    pub fn node_cache_growth_test() {
+        // The initial capacity only fits the terminal nodes, so every new node grows the cache.
+        let mut cache = NodeCache::with_storage(2, Vec::new());
+        let mut expected = vec![Node::ZERO, Node::ONE];
+        for i in 0..500u64 {
+            // Many nodes share the same links, so the linked lists are long.
+            let low = NodeIndex::from(i / 3);
+            let high = NodeIndex::from(i / 7 + 1);
+            let node = Node::pack(Variable::from((i % 5) as u32), low, high);
+            if expected.contains(&node) {
+                continue;
             }
+            assert_eq!(NodeIndex::from(expected.len() as u64), ensure(&mut cache, &node));
+            expected.push(node);
         }
-
-        return u64::from_index(self.nodes.len()) - self.index_after_last;
+        // Duplicates are found after the cache has grown.
+        for (index, node) in expected.iter().enumerate().skip(2) {
+            assert_eq!(NodeIndex::from(index as u64), ensure(&mut cache, node));
+        }
+        assert_eq!(expected.len(), cache.len());
+        assert!(cache.ensure_capacity(1000) >= 1000);
+        assert_eq!(expected, cache.into_storage());
     }
-
-}
\ No newline at end of file
+}
@@ -0,0 +1,241 @@
+use super::super::core::{Bdd, Node, NodeIndex, Variable};
+use super::concurrent_cache::{ConcurrentNodeCache, ConcurrentTaskCache};
+use crate::v2::ops::BooleanOp;
+use crate::IntoIndex;
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+/// The number of independent tasks which are prepared for every thread before the parallel
+/// phase starts. More tasks give better load balancing, but also a larger sequential prefix.
+const TASKS_PER_THREAD: usize = 16;
+
+/// A product task: a pair of node indices in the left and right `Bdd`.
+type Task = (NodeIndex, NodeIndex);
+
+/// A parallel version of `apply::<OP>`, which applies the binary logical operator `OP` to
+/// the two `Bdds` using `threads` worker threads. Same as `apply`, the result is sorted in DFS
+/// preorder.
+///
+/// The coupled DFS product graph is split as follows: First, the graph is expanded
+/// breadth-first until there are enough independent tasks for every thread. These tasks are
+/// distributed between the per-thread queues. Each thread then solves the tasks from its own
+/// queue, and when the queue is empty, it steals tasks from the other queues. All threads share
+/// one `ConcurrentTaskCache` and one `ConcurrentNodeCache`, so sub-tasks shared by multiple
+/// tasks are usually computed only once. Finally, the remaining top part of the product
+/// graph is solved sequentially, at which point all the expensive tasks are already cached.
+///
+/// *Panics:* `threads` must be positive.
+pub fn par_apply<OP: BooleanOp>(left_bdd: &Bdd, right_bdd: &Bdd, threads: usize) -> Bdd {
+    assert!(threads > 0, "At least one thread is required.");
+    let root = (left_bdd.get_root_index(), right_bdd.get_root_index());
+    if let Some(result) = Worker::terminal_result::<OP>(root) {
+        return if result.is_one() {
+            Bdd::new_one()
+        } else {
+            Bdd::new_zero()
+        };
+    }
+    let worker = Worker {
+        left_bdd,
+        right_bdd,
+        task_cache: ConcurrentTaskCache::new(),
+        node_cache: ConcurrentNodeCache::new(),
+    };
+
+    let queues: Vec<Mutex<VecDeque<Task>>> = (0..threads).map(|_| Default::default()).collect();
+    for (index, task) in worker
+        .frontier::<OP>(root, threads * TASKS_PER_THREAD)
+        .into_iter()
+        .enumerate()
+    {
+        queues[index % threads].lock().unwrap().push_back(task);
+    }
+    std::thread::scope(|scope| {
+        for id in 0..threads {
+            let (worker, queues) = (&worker, &queues);
+            scope.spawn(move || {
+                while let Some(task) = steal(queues, id) {
+                    worker.solve::<OP>(task);
+                }
+            });
+        }
+    });
+    let result = worker.solve::<OP>(root);
+
+    if result.into_index() < 2 {
+        return if result.is_one() {
+            Bdd::new_one()
+        } else {
+            Bdd::new_zero()
+        };
+    }
+    // Move the root to the last position, as required by `Bdd`.
+    let mut nodes = worker.node_cache.into_nodes();
+    let last = NodeIndex::from((nodes.len() - 1) as u64);
+    nodes.swap(result.into_index(), last.into_index());
+    for node in nodes.iter_mut().skip(2) {
+        let (variable, low, high) = node.unpack();
+        let swap = |index: NodeIndex| match index {
+            index if index == result => last,
+            index if index == last => result,
+            index => index,
+        };
+        *node = Node::pack(variable, swap(low), swap(high));
+    }
+    Bdd::from_nodes(nodes).sort_preorder()
+}
+
+/// **(internal)** Take a task from the queue of the thread `id`, or steal one from
+/// the other queues if it is empty.
+fn steal(queues: &[Mutex<VecDeque<Task>>], id: usize) -> Option<Task> {
+    // A thread takes its own tasks from the back, and steals from the front, such that
+    // the owner and the thieves do not compete for the same tasks.
+    if let Some(task) = queues[id].lock().unwrap().pop_back() {
+        return Some(task);
+    }
+    (1..queues.len())
+        .map(|offset| (id + offset) % queues.len())
+        .find_map(|victim| queues[victim].lock().unwrap().pop_front())
+}
+
+/// **(internal)** The state shared by all threads of one `par_apply` operation.
+struct Worker<'a> {
+    left_bdd: &'a Bdd,
+    right_bdd: &'a Bdd,
+    task_cache: ConcurrentTaskCache,
+    node_cache: ConcurrentNodeCache,
+}
+
+impl Worker<'_> {
+    /// The result of a task which can be decided by `OP` without expanding it.
+    fn terminal_result<OP: BooleanOp>(task: Task) -> Option<NodeIndex> {
+        let result = super::lookup::<OP>(task.0, task.1);
+        if result.is_undefined() {
+            None
+        } else {
+            Some(result)
+        }
+    }
+
+    /// The result of a task which is either terminal or already solved.
+    fn known_result<OP: BooleanOp>(&self, task: Task) -> Option<NodeIndex> {
+        Worker::terminal_result::<OP>(task).or_else(|| self.task_cache.get(&task))
+    }
+
+    /// The decision variable of a task, together with its low and high sub-tasks.
+    fn expand(&self, task: Task) -> (Variable, Task, Task) {
+        let (left, right) = task;
+        let (l_var, l_low, l_high) = self.left_bdd.get_node(left).unpack();
+        let (r_var, r_low, r_high) = self.right_bdd.get_node(right).unpack();
+        // Terminals have an undefined variable, which is larger than any other variable.
+        if l_var == r_var {
+            (l_var, (l_low, r_low), (l_high, r_high))
+        } else if l_var < r_var {
+            (l_var, (l_low, right), (l_high, right))
+        } else {
+            (r_var, (left, r_low), (left, r_high))
+        }
+    }
+
+    /// Expand the product graph breadth-first, starting from `root`, until there are at least
+    /// `count` unsolved tasks (or no tasks at all).
+    fn frontier<OP: BooleanOp>(&self, root: Task, count: usize) -> Vec<Task> {
+        let mut seen = HashSet::new();
+        let mut frontier = VecDeque::from(vec![root]);
+        while frontier.len() < count {
+            let task = match frontier.pop_front() {
+                Some(task) => task,
+                None => break,
+            };
+            let (_, low, high) = self.expand(task);
+            for child in [low, high] {
+                if Worker::terminal_result::<OP>(child).is_none() && seen.insert(child) {
+                    frontier.push_back(child);
+                }
+            }
+        }
+        frontier.into()
+    }
+
+    /// Solve the given `task` using a sequential coupled DFS which reads and writes
+    /// the shared caches.
+    fn solve<OP: BooleanOp>(&self, task: Task) -> NodeIndex {
+        // Every task is first visited as unexpanded (`false`), and then again once its
+        // sub-tasks are solved (`true`).
+        let mut stack = vec![(task, false)];
+        while let Some((top, expanded)) = stack.pop() {
+            if self.known_result::<OP>(top).is_some() {
+                continue;
+            }
+            let (variable, low, high) = self.expand(top);
+            if !expanded {
+                stack.push((top, true));
+                stack.push((high, false));
+                stack.push((low, false));
+                continue;
+            }
+            // Both sub-tasks were solved before this task was visited again.
+            let low = self.known_result::<OP>(low).unwrap();
+            let high = self.known_result::<OP>(high).unwrap();
+            let result = if low == high {
+                low
+            } else {
+                self.node_cache.ensure(Node::pack(variable, low, high))
+            };
+            self.task_cache.insert(top, result);
+        }
+        self.known_result::<OP>(task).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::par_apply;
+    use crate::expressions::BooleanExpression;
+    use crate::v2::ops::{And, BooleanOp, Iff, Imp, Or, Xor};
+    use crate::v2::Bdd as Bdd2;
+    use crate::v4::apply::apply;
+    use crate::v4::core::Bdd;
+    use core::convert::TryFrom;
+
+    fn build(expression: &str, names: &[&str]) -> Bdd {
+        let expression = BooleanExpression::try_from(expression).unwrap();
+        let bdd = Bdd2::from_expression(&expression, names).unwrap();
+        Bdd::try_from(&bdd).unwrap()
+    }
+
+    /// The parallel result of `OP` is the same (node by node) as the sequential one.
+    fn check<OP: BooleanOp>(left: &Bdd, right: &Bdd) {
+        let expected = apply::<OP>(left, right);
+        for threads in [1, 2, 4, 7] {
+            let result = par_apply::<OP>(left, right, threads);
+            assert!(Bdd::check_consistency_errors(result.as_node_slice()).is_none());
+            assert_eq!(expected.as_node_slice(), result.as_node_slice());
+        }
+    }
+
+    #[test]
+    pub fn par_apply_test() {
+        let names = ["a", "b", "c", "d", "e", "f", "g", "h"];
+        let left = build("(a ^ e) & (b ^ f) & (c ^ g) & (d ^ h)", &names);
+        let right = build("(a & b) | (c & d) | (e & f) | (g & h)", &names);
+        for (l, r) in [(&left, &right), (&right, &left)] {
+            check::<And>(l, r);
+            check::<Or>(l, r);
+            check::<Xor>(l, r);
+            check::<Imp>(l, r);
+            check::<Iff>(l, r);
+        }
+
+        // Constant results.
+        let not_left = build("!((a ^ e) & (b ^ f) & (c ^ g) & (d ^ h))", &names);
+        assert!(par_apply::<Or>(&left, &not_left, 3).is_one());
+        assert!(par_apply::<And>(&left, &not_left, 3).is_zero());
+        assert!(par_apply::<Xor>(&Bdd::new_zero(), &Bdd::new_one(), 3).is_one());
+        let same = par_apply::<Or>(&Bdd::new_zero(), &left, 2);
+        assert_eq!(left.sort_preorder().as_node_slice(), same.as_node_slice());
+    }
+}
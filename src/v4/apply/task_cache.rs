@@ -1,6 +1,10 @@
 use super::super::core::NodeIndex;
+use crate::v2::{TaskCacheConfig, TaskHash};
 use crate::IntoIndex;
-use std::ops::Rem;
+use core::cmp::min;
+use core::ops::{BitXor, Rem};
+use alloc::vec::Vec;
+use alloc::vec;
 
 type KeyValuePair = ((NodeIndex, NodeIndex), NodeIndex);
 
@@ -27,6 +31,11 @@ type KeyValuePair = ((NodeIndex, NodeIndex), NodeIndex);
 /// it is added to the base value, it will add a certain amount of pseudo-random noise to it.
 /// This noise will significantly reduce collisions, but it cannot make the hash diverge too much
 /// from the expected base value and should therefore preserve its locality.
+///
+/// The largest capacity, the hash function and the replacement policy can be changed using
+/// a `TaskCacheConfig`. Once the capacity reaches the limit, the cache stops growing and
+/// entries are simply overwritten. With `ReplacementPolicy::TwoWay`, every slot is rounded
+/// down to an even bucket, and the odd slot holds the entry replaced most recently.
 pub struct TaskCache {
     /// The number of elements inserted into the cache so far. Used to determine whether
     /// we should grow the cache.
@@ -36,6 +45,10 @@ pub struct TaskCache {
     bit_extension: u64,
     /// The actual capacity of the table when discounting the hash block size.
     capacity: u64,
+    /// The capacity at which the cache stops growing.
+    max_capacity: u64,
+    two_way: bool,
+    rolling: bool,
     items: Vec<KeyValuePair>
 }
 
@@ -69,18 +82,27 @@ impl TaskCache {
     const HASH_BLOCK: u64 = 1 << 13;
     const UNDEFINED_ENTRY: KeyValuePair = ((NodeIndex::UNDEFINED, NodeIndex::UNDEFINED), NodeIndex::UNDEFINED);
 
-    pub fn new(initial_capacity: u64) -> TaskCache {
-        // By growing the cash capacity by the hash block size, we ensure that modulo is not needed
-        // on the computed hashed indices.
-        let actual_capacity = initial_capacity + Self::HASH_BLOCK;
+    pub fn new(initial_capacity: u64, config: &TaskCacheConfig) -> TaskCache {
+        let max_capacity = config.capacity.map(|it| it as u64).unwrap_or(u64::MAX);
+        let initial_capacity = min(initial_capacity, max_capacity);
         TaskCache {
             elements: 0,
             bit_extension: 0,
             capacity: initial_capacity,
-            items: vec![Self::UNDEFINED_ENTRY; actual_capacity.into_index()]
+            max_capacity,
+            two_way: config.is_two_way(),
+            rolling: config.hash == TaskHash::Rolling,
+            items: vec![Self::UNDEFINED_ENTRY; Self::table_size(initial_capacity)]
         }
     }
 
+    /// By growing the cache capacity by the hash block size, we ensure that modulo is usually
+    /// not needed on the computed hashed indices. The extra slot completes the last bucket
+    /// of a two-way cache.
+    fn table_size(capacity: u64) -> usize {
+        (capacity + Self::HASH_BLOCK + 1).into_index()
+    }
+
     #[inline]
     pub fn read(&self, task: (NodeIndex, NodeIndex)) -> (NodeIndex, TaskCacheSlot) {
         // Note that this has been tested as slightly faster than a version that returns
@@ -89,6 +111,13 @@ impl TaskCache {
         let slot_value = unsafe { self.items.get_unchecked(slot.into_index()) };
         if slot_value.0 == task {
             (slot_value.1, slot)
+        } else if self.two_way {
+            let second_value = unsafe { self.items.get_unchecked(slot.into_index() + 1) };
+            if second_value.0 == task {
+                (second_value.1, slot)
+            } else {
+                (NodeIndex::UNDEFINED, slot)
+            }
         } else {
             (NodeIndex::UNDEFINED, slot)
         }
@@ -96,21 +125,34 @@ impl TaskCache {
 
     #[inline]
     pub fn write(&mut self, slot: TaskCacheSlot, task: (NodeIndex, NodeIndex), result: NodeIndex) {
-        let slot_value = unsafe { self.items.get_unchecked_mut(slot.into_index()) };
+        let index = slot.into_index();
+        if self.two_way {
+            let replaced = unsafe { *self.items.get_unchecked(index) };
+            if replaced.0 != task {
+                // Keep the replaced entry in the second slot of the bucket.
+                unsafe {
+                    *self.items.get_unchecked_mut(index + 1) = replaced;
+                }
+            }
+        }
+        let slot_value = unsafe { self.items.get_unchecked_mut(index) };
         *slot_value = (task, result);
         self.elements += 1;
     }
 
     pub fn grow_if_necessary(&mut self) -> u64 {
-        if self.elements >= 2 * self.capacity {
-            println!("Grow task cache. Current: {}.", self.items.len());
+        if self.elements >= 2 * self.capacity && 2 * self.capacity > self.max_capacity {
+            // The cache cannot grow anymore, so it just keeps overwriting old entries.
+            self.elements = 0;
+        } else if self.elements >= 2 * self.capacity {
+            debug!("Grow task cache. Current: {}.", self.items.len());
             // Add one extra bit into the right index bit mask, and reset element count.
             self.bit_extension = (self.bit_extension << 1) | 1;
             self.elements = 0;
             // Create a new table and swap it with the current one.
             self.capacity = self.capacity * 2;
-            let mut items = vec![Self::UNDEFINED_ENTRY; (self.capacity + Self::HASH_BLOCK).into_index()];
-            std::mem::swap(&mut items, &mut self.items);
+            let mut items = vec![Self::UNDEFINED_ENTRY; Self::table_size(self.capacity)];
+            core::mem::swap(&mut items, &mut self.items);
             // Rehash all values in the table.
             for (key, value) in items {
                 if !value.is_undefined() {
@@ -118,7 +160,7 @@ impl TaskCache {
                     self.write(slot, key, value);
                 }
             }
-            println!("Task cache grown to {}.", self.items.len());
+            debug!("Task cache grown to {}.", self.items.len());
         }
         2 * self.capacity - self.elements
     }
@@ -126,20 +168,33 @@ impl TaskCache {
     fn hashed_index(&self, task: (NodeIndex, NodeIndex)) -> TaskCacheSlot {
         let (left, right) = (u64::from(task.0), u64::from(task.1));
         let right_hash = right.wrapping_mul(Self::SEED);
-        let block_offset = right_hash.rem(Self::HASH_BLOCK);
-        let shift_bits = 64 - self.bit_extension.leading_zeros();
-        let block_base: u64 = (left << shift_bits) | (right & self.bit_extension);
+        // The last slot is only used as the second slot of a two-way bucket.
+        let slots = (self.items.len() - 1) as u64;
+        let mut index = if self.rolling {
+            let block_offset = right_hash.rem(Self::HASH_BLOCK);
+            let shift_bits = 64 - self.bit_extension.leading_zeros();
+            let block_base: u64 = (left << shift_bits) | (right & self.bit_extension);
+            let index = block_base + block_offset;
+            // The index only overflows when the capacity is limited below the left size.
+            if index >= slots {
+                index.rem(slots)
+            } else {
+                index
+            }
+        } else {
+            left.wrapping_mul(Self::SEED).bitxor(right_hash).rem(slots)
+        };
+        if self.two_way {
+            index &= !1;
+        }
 
         /*
         This seems to help in some cases, but also increases instruction count significantly
         so it mostly isn't worth it. Maybe re-evaluate in the future though?
-        unsafe {
-            let pointer: *const KeyValuePair =
-                self.items.get_unchecked((block_base as usize) + 64);
-            std::arch::x86_64::_mm_prefetch::<1>(pointer as *const i8);
-        }*/
+        crate::arch::prefetch_far(self.items.as_ptr().wrapping_add((block_base as usize) + 64));
+        */
 
-        (block_base + block_offset).into()
+        index.into()
     }
 
 }
\ No newline at end of file
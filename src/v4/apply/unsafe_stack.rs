@@ -1,3 +1,5 @@
+use alloc::vec::Vec;
+
 pub struct UnsafeStack<T: Sized + Copy> {
     index_after_last: usize,
     items: Vec<T>
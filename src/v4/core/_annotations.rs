@@ -0,0 +1,186 @@
+use super::{Bdd, Node, NodeIndex};
+use crate::{FromIndex, IntoIndex};
+use core::ops::{Index, IndexMut};
+use alloc::vec::Vec;
+use alloc::vec;
+
+/// A side table which stores one value of type `T` for every node of a specific `Bdd`.
+///
+/// Many analyses (path counts, probabilities, costs, ...) need some per-node scratch data.
+/// Instead of allocating a raw `Vec` and indexing it with unchecked node indices, you can
+/// use `NodeAnnotations`, which is always exactly as large as the annotated `Bdd`.
+///
+/// The table borrows the `Bdd` for which it was created. As such, the graph cannot be modified
+/// while the annotations exist, and the annotations are invalidated (by the borrow checker)
+/// once the `Bdd` is mutated. Note that methods like `Bdd::sort_preorder` return a new `Bdd`,
+/// so the annotations of the original graph are not valid for the sorted copy.
+pub struct NodeAnnotations<'a, T> {
+    bdd: &'a Bdd,
+    values: Vec<T>,
+}
+
+impl<'a, T> NodeAnnotations<'a, T> {
+    /// Create annotations where every node of the `bdd` has the same `value`.
+    pub fn new(bdd: &'a Bdd, value: T) -> NodeAnnotations<'a, T>
+    where
+        T: Clone,
+    {
+        NodeAnnotations {
+            bdd,
+            values: vec![value; bdd.as_node_slice().len()],
+        }
+    }
+
+    /// Create annotations by computing the value of every node independently using `f`.
+    pub fn from_fn<F>(bdd: &'a Bdd, mut f: F) -> NodeAnnotations<'a, T>
+    where
+        F: FnMut(NodeIndex, &Node) -> T,
+    {
+        let values = bdd
+            .iter_indices()
+            .map(|index| f(index, bdd.get_node(index)))
+            .collect();
+        NodeAnnotations { bdd, values }
+    }
+
+    /// Create annotations by propagating values from the terminal nodes towards the root.
+    ///
+    /// The value of every terminal node is computed by `terminal`. The value of every
+    /// decision node is computed by `decision`, which also receives the already computed
+    /// values of the low and high successor (in this order).
+    ///
+    /// Every node is annotated, including the nodes that are not reachable from the root.
+    /// The nodes can be stored in any order; the method does not assume the graph is sorted.
+    pub fn annotate_bottom_up<F, G>(bdd: &'a Bdd, mut terminal: F, mut decision: G) -> NodeAnnotations<'a, T>
+    where
+        F: FnMut(NodeIndex, &Node) -> T,
+        G: FnMut(NodeIndex, &Node, &T, &T) -> T,
+    {
+        let nodes = bdd.as_node_slice();
+        let mut values: Vec<Option<T>> = Vec::with_capacity(nodes.len());
+        values.resize_with(nodes.len(), || None);
+
+        let mut stack: Vec<(NodeIndex, bool)> = Vec::with_capacity(2 * bdd.get_height().into_index() + 2);
+        for start in bdd.iter_indices() {
+            if values[start.into_index()].is_some() {
+                continue;
+            }
+            stack.push((start, false));
+            while let Some((task, expanded)) = stack.pop() {
+                if values[task.into_index()].is_some() {
+                    // The task was reachable through multiple paths and is already resolved.
+                    continue;
+                }
+                let node = &nodes[task.into_index()];
+                if node.is_terminal() {
+                    values[task.into_index()] = Some(terminal(task, node));
+                } else if expanded {
+                    // Both successors are resolved, because they were pushed after the task.
+                    let low = values[node.get_low_link().into_index()].as_ref().unwrap();
+                    let high = values[node.get_high_link().into_index()].as_ref().unwrap();
+                    let value = decision(task, node, low, high);
+                    values[task.into_index()] = Some(value);
+                } else {
+                    stack.push((task, true));
+                    stack.push((node.get_high_link(), false));
+                    stack.push((node.get_low_link(), false));
+                }
+            }
+        }
+
+        NodeAnnotations {
+            bdd,
+            values: values.into_iter().map(|it| it.unwrap()).collect(),
+        }
+    }
+
+    /// The `Bdd` to which these annotations belong.
+    pub fn get_bdd(&self) -> &'a Bdd {
+        self.bdd
+    }
+
+    /// Get a reference to the value of the node with the given `index`.
+    #[inline]
+    pub fn get_value(&self, index: NodeIndex) -> &T {
+        &self.values[index.into_index()]
+    }
+
+    /// Get a mutable reference to the value of the node with the given `index`.
+    #[inline]
+    pub fn get_value_mut(&mut self, index: NodeIndex) -> &mut T {
+        &mut self.values[index.into_index()]
+    }
+
+    /// Get a reference to the value of the root node.
+    #[inline]
+    pub fn get_root_value(&self) -> &T {
+        self.get_value(self.bdd.get_root_index())
+    }
+
+    /// Iterate over all node indices together with their values.
+    pub fn iter(&self) -> impl Iterator<Item = (NodeIndex, &T)> {
+        self.values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| (NodeIndex::from_index(i), value))
+    }
+
+    /// Consume the annotations, returning the underlying vector of values
+    /// (indexed in the same way as `Bdd::as_node_slice`).
+    pub fn into_values(self) -> Vec<T> {
+        self.values
+    }
+}
+
+impl<'a, T> Index<NodeIndex> for NodeAnnotations<'a, T> {
+    type Output = T;
+
+    fn index(&self, index: NodeIndex) -> &T {
+        self.get_value(index)
+    }
+}
+
+impl<'a, T> IndexMut<NodeIndex> for NodeAnnotations<'a, T> {
+    fn index_mut(&mut self, index: NodeIndex) -> &mut T {
+        self.get_value_mut(index)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::NodeAnnotations;
+    use crate::v4::core::{Bdd, Node, NodeIndex, Variable};
+
+    #[test]
+    pub fn annotate_bottom_up_test() {
+        // The root is stored before its successor to check that the order does not matter.
+        // Variable 1 is the root, variable 3 its low successor, and variable 2 is unreachable.
+        let nodes = vec![
+            Node::ZERO,
+            Node::ONE,
+            Node::pack(Variable::from(2), NodeIndex::ONE, NodeIndex::ZERO),
+            Node::pack(Variable::from(3), NodeIndex::ZERO, NodeIndex::ONE),
+            Node::pack(Variable::from(1), NodeIndex::from(3), NodeIndex::ONE),
+        ];
+        let bdd = Bdd::from_nodes(nodes);
+
+        // Count the paths leading to the `1` terminal.
+        let paths = NodeAnnotations::annotate_bottom_up(
+            &bdd,
+            |index, _| if index.is_one() { 1u64 } else { 0 },
+            |_, _, low, high| low + high,
+        );
+        assert_eq!(2, *paths.get_root_value());
+        assert_eq!(1, paths[NodeIndex::from(2)]);
+        assert_eq!(1, paths[NodeIndex::from(3)]);
+        assert_eq!(vec![0, 1, 1, 1, 2], paths.into_values());
+
+        let mut depth = NodeAnnotations::new(&bdd, 0usize);
+        depth[NodeIndex::from(4)] = 1;
+        assert_eq!(1, *depth.get_root_value());
+        assert_eq!(5, depth.iter().count());
+
+        let variables = NodeAnnotations::from_fn(&bdd, |_, node| node.get_variable());
+        assert_eq!(Variable::from(3), variables[NodeIndex::from(3)]);
+    }
+}
@@ -1,10 +1,15 @@
 use super::{Node, Variable, NodeIndex};
 use crate::{FromIndex, IntoIndex};
-use std::cmp::max;
-use std::collections::VecDeque;
-use std::iter::Map;
-use std::ops::Range;
-use std::convert::TryFrom;
+use core::cmp::max;
+use alloc::collections::VecDeque;
+use core::iter::Map;
+use core::ops::Range;
+use core::convert::TryFrom;
+use core::fmt::{Display, Formatter};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::vec;
 
 /// A `Bdd` describes a directed acyclic graph corresponding to a Boolean function.
 ///
@@ -87,6 +92,58 @@ impl Bdd {
         }
     }
 
+    /// Create a BDD representing the literal `variable = value`.
+    pub fn new_literal(variable: Variable, value: bool) -> Bdd {
+        Bdd::new_conjunctive_clause(&[(variable, value)])
+    }
+
+    /// Create a BDD representing the conjunction of the given literals.
+    ///
+    /// Duplicate literals are allowed. If the clause contains both values of some variable,
+    /// the result is the constant `0`, and an empty clause is the constant `1`.
+    pub fn new_conjunctive_clause(literals: &[(Variable, bool)]) -> Bdd {
+        let literals = match normalize_clause(literals) {
+            Some(literals) => literals,
+            None => return Bdd::new_zero(),
+        };
+        let mut nodes = Vec::with_capacity(literals.len() + 2);
+        nodes.push(Node::ZERO);
+        nodes.push(Node::ONE);
+        // The clause is built bottom-up, starting with the largest variable.
+        for (variable, value) in literals.into_iter().rev() {
+            let next = NodeIndex::from_index(nodes.len() - 1);
+            let (low, high) = if value { (NodeIndex::ZERO, next) } else { (next, NodeIndex::ZERO) };
+            nodes.push(Node::pack(variable, low, high));
+        }
+        let height = (nodes.len() - 2) as u32;
+        Bdd { height, nodes }
+    }
+
+    /// Create a BDD representing the disjunction of the given literals.
+    ///
+    /// Duplicate literals are allowed. If the clause contains both values of some variable,
+    /// the result is the constant `1`, and an empty clause is the constant `0`.
+    pub fn new_disjunctive_clause(literals: &[(Variable, bool)]) -> Bdd {
+        let literals = match normalize_clause(literals) {
+            Some(literals) => literals,
+            None => return Bdd::new_one(),
+        };
+        if literals.is_empty() {
+            return Bdd::new_zero();
+        }
+        let mut nodes = Vec::with_capacity(literals.len() + 2);
+        nodes.push(Node::ZERO);
+        nodes.push(Node::ONE);
+        let mut next = NodeIndex::ZERO;
+        for (variable, value) in literals.into_iter().rev() {
+            let (low, high) = if value { (next, NodeIndex::ONE) } else { (NodeIndex::ONE, next) };
+            next = NodeIndex::from_index(nodes.len());
+            nodes.push(Node::pack(variable, low, high));
+        }
+        let height = (nodes.len() - 2) as u32;
+        Bdd { height, nodes }
+    }
+
     /// Upper bound on the height of the BDD graph.
     #[inline]
     pub fn get_height(&self) -> u32 {
@@ -135,6 +192,31 @@ impl Bdd {
         (0..self.nodes.len()).map(|it| NodeIndex::from_index(it))
     }
 
+    /// A read-only view of the underlying node vector.
+    ///
+    /// The slice is indexed in the same way as `Bdd::get_node`, i.e. `slice[i]` is the node
+    /// with `NodeIndex::from_index(i)`. As such, it is safe to split the slice into disjoint
+    /// index ranges (e.g. using `chunks`) and process each range independently (possibly on
+    /// different threads), as long as the links are resolved against the whole slice.
+    ///
+    /// The slice satisfies the same invariants as the `Bdd` itself:
+    ///  - It is never empty and the terminal nodes form its prefix (`0` first, then `1`).
+    ///  - The last node is the root of the graph.
+    ///  - Every low/high link is a valid index into the slice, and the linked node has
+    ///    a strictly larger variable (unless the node is a terminal).
+    ///
+    /// However, you should not assume that the nodes are sorted in any particular order
+    /// (not even topologically), or that every node is reachable from the root.
+    #[inline]
+    pub fn as_node_slice(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// **(internal)** The node vector, which implements `NodeStorage`.
+    pub(crate) fn as_node_vec(&self) -> &Vec<Node> {
+        &self.nodes
+    }
+
     /// True if the BDD represents a constant (terminal) value.
     #[inline]
     pub fn is_constant(&self) -> bool {
@@ -360,6 +442,213 @@ impl Bdd {
         unsafe { self.shuffle_unchecked(&shuffle_map) }
     }
 
+    /// Create a copy of this `Bdd` without the nodes that are not reachable from the root.
+    ///
+    /// The remaining nodes keep their relative order, so a `Bdd` which is sorted in
+    /// preorder (or postorder) stays sorted, apart from the removed nodes.
+    pub fn prune_unreachable(&self) -> Bdd {
+        let terminals_count = self.nodes.iter().take_while(|it| it.is_terminal()).count();
+        let mut reachable = vec![false; self.nodes.len()];
+        for flag in &mut reachable[..terminals_count] {
+            *flag = true;
+        }
+        let mut search_stack: Vec<NodeIndex> = vec![self.get_root_index()];
+        while let Some(task) = search_stack.pop() {
+            if !reachable[task.into_index()] {
+                reachable[task.into_index()] = true;
+                let node = self.get_node(task);
+                search_stack.push(node.get_high_link());
+                search_stack.push(node.get_low_link());
+            }
+        }
+
+        // Reachable nodes get consecutive indices in their original order.
+        let mut new_index = vec![NodeIndex::UNDEFINED; self.nodes.len()];
+        let mut nodes = Vec::with_capacity(self.nodes.len());
+        for (index, node) in self.nodes.iter().enumerate() {
+            if reachable[index] {
+                new_index[index] = NodeIndex::from_index(nodes.len());
+                nodes.push(node.clone());
+            }
+        }
+        for node in nodes.iter_mut().skip(terminals_count) {
+            let (variable, low, high) = node.unpack();
+            let low = new_index[low.into_index()];
+            let high = new_index[high.into_index()];
+            *node = Node::pack(variable, low, high);
+        }
+        Bdd {
+            height: self.height,
+            nodes,
+        }
+    }
+
+    /// Create a copy of this `Bdd` without unreachable nodes, which is sorted based on
+    /// the DFS pre-order (see `Bdd::prune_unreachable` and `Bdd::sort_preorder`).
+    pub fn compact(&self) -> Bdd {
+        self.prune_unreachable().sort_preorder()
+    }
+
+}
+
+/// Logical operations which do not need a full `apply`.
+impl Bdd {
+
+    /// A logical negation of this `Bdd`.
+    ///
+    /// The terminal links of every node are swapped in a single linear pass, so the order
+    /// of the nodes and the height of the `Bdd` are preserved.
+    pub fn not(&self) -> Bdd {
+        if self.is_zero() {
+            return Bdd::new_one();
+        }
+        if self.is_one() {
+            return Bdd::new_zero();
+        }
+        let negate = |link: NodeIndex| {
+            if link.is_zero() {
+                NodeIndex::ONE
+            } else if link.is_one() {
+                NodeIndex::ZERO
+            } else {
+                link
+            }
+        };
+        let nodes = self.nodes.iter().map(|node| {
+            if node.is_terminal() {
+                node.clone()
+            } else {
+                let (variable, low, high) = node.unpack();
+                Node::pack(variable, negate(low), negate(high))
+            }
+        }).collect();
+        Bdd { height: self.height, nodes }
+    }
+
+}
+
+/// The magic bytes of the binary format of `Bdd::to_bytes`.
+const BYTES_MAGIC: &[u8; 4] = b"BDDR";
+/// The size of the binary header: magic bytes, height (`u32`) and node count (`u64`).
+const BYTES_HEADER: usize = 16;
+/// The size of one node in the binary format.
+const BYTES_NODE: usize = 24;
+
+/// Binary serialization.
+///
+/// The binary format starts with a 16 byte header (the magic bytes `BDDR`, the height as `u32`
+/// and the node count as `u64`), followed by every node as 24 bytes (the variable as `u32`,
+/// four padding bytes, and the low and high link as `u64`). All numbers are little endian.
+/// On little endian machines, the node section is therefore exactly the memory layout of
+/// the node vector, and it is deserialized by a single copy instead of being parsed.
+impl Bdd {
+
+    /// Serialize this `Bdd` into the binary format (the padding bytes are always zero).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut output = Vec::with_capacity(BYTES_HEADER + BYTES_NODE * self.nodes.len());
+        output.extend_from_slice(BYTES_MAGIC);
+        output.extend_from_slice(&self.height.to_le_bytes());
+        output.extend_from_slice(&u64::from_index(self.nodes.len()).to_le_bytes());
+        for node in &self.nodes {
+            let (variable, low, high) = node.unpack();
+            output.extend_from_slice(&u32::from(variable).to_le_bytes());
+            output.extend_from_slice(&[0; 4]);
+            output.extend_from_slice(&u64::from(low).to_le_bytes());
+            output.extend_from_slice(&u64::from(high).to_le_bytes());
+        }
+        output
+    }
+
+    /// Deserialize a `Bdd` from the binary format.
+    ///
+    /// The nodes are validated using `Bdd::check_consistency_errors` and the height is
+    /// recomputed, since neither can be trusted in an arbitrary buffer. The padding bytes
+    /// are ignored.
+    pub fn from_bytes(data: &[u8]) -> Result<Bdd, String> {
+        // The nodes are validated before the `Bdd` is used for anything else.
+        let mut bdd = unsafe { Bdd::from_bytes_unchecked(data)? };
+        if let Some(error) = Bdd::check_consistency_errors(&bdd.nodes) {
+            return Err(error);
+        }
+        bdd.recompute_height();
+        Ok(bdd)
+    }
+
+    /// Deserialize a `Bdd` from the binary format, trusting the nodes and the height stored
+    /// in the buffer. Only the header and the size of the buffer are checked.
+    ///
+    /// # Safety
+    ///
+    /// The buffer must have been created by `Bdd::to_bytes` (or it must otherwise describe
+    /// a valid `Bdd` in terms of `Bdd::check_consistency_errors` with a correct height bound).
+    pub unsafe fn from_bytes_unchecked(data: &[u8]) -> Result<Bdd, String> {
+        if data.len() < BYTES_HEADER || &data[0..4] != BYTES_MAGIC {
+            return Err(String::from("Missing binary BDD header."));
+        }
+        let height = u32::from_le_bytes([data[4], data[5], data[6], data[7]]);
+        let mut node_count = [0u8; 8];
+        node_count.copy_from_slice(&data[8..16]);
+        let node_count = u64::from_le_bytes(node_count);
+        let body = &data[BYTES_HEADER..];
+        if node_count == 0 || node_count.checked_mul(BYTES_NODE as u64) != Some(body.len() as u64) {
+            return Err(format!(
+                "Expected {} nodes, but found {} bytes of node data.", node_count, body.len()
+            ));
+        }
+
+        let node_count = body.len() / BYTES_NODE;
+        let mut nodes: Vec<Node> = Vec::with_capacity(node_count);
+        if cfg!(target_endian = "little") && core::mem::size_of::<Node>() == BYTES_NODE {
+            // Every bit pattern is a valid `Node` (padding included), so the bytes are just
+            // copied into the vector. The source does not need to be aligned.
+            unsafe {
+                let target = nodes.as_mut_ptr() as *mut u8;
+                core::ptr::copy_nonoverlapping(body.as_ptr(), target, body.len());
+                nodes.set_len(node_count);
+            }
+        } else {
+            for chunk in body.chunks_exact(BYTES_NODE) {
+                let mut low = [0u8; 8];
+                let mut high = [0u8; 8];
+                low.copy_from_slice(&chunk[8..16]);
+                high.copy_from_slice(&chunk[16..24]);
+                let variable = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+                nodes.push(Node::pack(
+                    Variable::from(variable),
+                    NodeIndex::from(u64::from_le_bytes(low)),
+                    NodeIndex::from(u64::from_le_bytes(high)),
+                ));
+            }
+        }
+        Ok(unsafe { Bdd::from_raw_parts(height, nodes) })
+    }
+
+}
+
+/// **(internal)** Sort the literals of a clause by variable and remove duplicates. Returns
+/// `None` if the clause contains both values of the same variable.
+fn normalize_clause(literals: &[(Variable, bool)]) -> Option<Vec<(Variable, bool)>> {
+    let mut literals = literals.to_vec();
+    literals.sort();
+    literals.dedup();
+    for pair in literals.windows(2) {
+        if pair[0].0 == pair[1].0 {
+            return None;
+        }
+    }
+    debug_assert!(literals.iter().all(|(variable, _)| !variable.is_undefined()));
+    Some(literals)
+}
+
+/// Serialization into the same simple string format which is accepted by `Bdd::try_from`.
+impl Display for Bdd {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        for node in &self.nodes {
+            let (variable, low, high) = node.unpack();
+            write!(f, "{},{},{}|", u32::from(variable), u64::from(low), u64::from(high))?;
+        }
+        Ok(())
+    }
 }
 
 /// Deserialization of a simple string format for sharing BDDs.
@@ -424,11 +713,162 @@ impl TryFrom<&str> for Bdd {
 #[cfg(test)]
 mod test {
     use super::Bdd;
-    use std::convert::TryFrom;
+    use crate::v4::core::{Node, NodeIndex, Variable};
+    use crate::IntoIndex;
+    use core::convert::TryFrom;
+
+    #[test]
+    pub fn node_slice_test() {
+        let nodes = vec![
+            Node::ZERO,
+            Node::ONE,
+            Node::pack(Variable::from(3), NodeIndex::ZERO, NodeIndex::ONE),
+            Node::pack(Variable::from(1), NodeIndex::from(2), NodeIndex::ONE),
+        ];
+        let bdd = Bdd::from_nodes(nodes.clone());
+        let slice = bdd.as_node_slice();
+        assert_eq!(nodes.as_slice(), slice);
+        assert_eq!(bdd.get_size(), slice.len() as u64);
+        for index in bdd.iter_indices() {
+            assert_eq!(bdd.get_node(index), &slice[index.into_index()]);
+        }
+    }
+
+    #[test]
+    pub fn string_round_trip_test() {
+        let bdd = Bdd::try_from("0,0,0|0,1,1|3,0,1|1,2,1|").unwrap();
+        let copy = Bdd::try_from(bdd.to_string().as_str()).unwrap();
+        assert_eq!(bdd.as_node_slice(), copy.as_node_slice());
+        assert_eq!(bdd.to_string(), copy.to_string());
+    }
+
+    #[test]
+    pub fn negation_test() {
+        let bdd = Bdd::try_from("0,0,0|0,1,1|3,0,1|1,2,1|").unwrap();
+        let not = bdd.not();
+        assert_eq!("4294967295,0,0|4294967295,1,1|3,1,0|1,2,0|", not.to_string());
+        assert_eq!(bdd.get_height(), not.get_height());
+        assert_eq!(bdd.as_node_slice(), not.not().as_node_slice());
+        assert!(Bdd::new_zero().not().is_one());
+        assert!(Bdd::new_one().not().is_zero());
+    }
+
+    #[test]
+    pub fn clause_constructors_test() {
+        let x = |id: u32| Variable::from(id);
+        // x3 & !x5, with a duplicate literal.
+        let clause = Bdd::new_conjunctive_clause(&[(x(5), false), (x(3), true), (x(5), false)]);
+        assert_eq!("4294967295,0,0|4294967295,1,1|5,1,0|3,0,2|", clause.to_string());
+        assert_eq!(2, clause.get_height());
+        assert!(Bdd::check_consistency_errors(clause.as_node_slice()).is_none());
+        let paths: Vec<_> = clause.paths().collect();
+        assert_eq!(vec![vec![(x(3), true), (x(5), false)]], paths);
+
+        // x3 | !x5 is the negation of !x3 & x5.
+        let clause = Bdd::new_disjunctive_clause(&[(x(3), true), (x(5), false)]);
+        assert_eq!("4294967295,0,0|4294967295,1,1|5,1,0|3,2,1|", clause.to_string());
+        assert_eq!(2, clause.get_height());
+        let negated = Bdd::new_conjunctive_clause(&[(x(3), false), (x(5), true)]).not();
+        assert_eq!(negated.as_node_slice(), clause.as_node_slice());
+
+        let literal = Bdd::new_literal(x(2), false);
+        assert_eq!("4294967295,0,0|4294967295,1,1|2,1,0|", literal.to_string());
+        assert_eq!(1, literal.get_height());
+
+        // Empty and contradictory clauses are constants.
+        assert!(Bdd::new_conjunctive_clause(&[]).is_one());
+        assert!(Bdd::new_conjunctive_clause(&[(x(1), true), (x(1), false)]).is_zero());
+        assert!(Bdd::new_disjunctive_clause(&[]).is_zero());
+        assert!(Bdd::new_disjunctive_clause(&[(x(1), true), (x(1), false)]).is_one());
+    }
+
+    #[test]
+    pub fn prune_unreachable_test() {
+        // The node 2 (variable 2) is unreachable and the graph is not sorted.
+        let bdd = Bdd::try_from("0,0,0|0,1,1|2,1,0|1,0,4|3,0,1|0,3,4|").unwrap();
+        let pruned = bdd.prune_unreachable();
+        assert_eq!("4294967295,0,0|4294967295,1,1|1,0,3|3,0,1|0,2,3|", pruned.to_string());
+        assert!(Bdd::check_consistency_errors(pruned.as_node_slice()).is_none());
+        assert_eq!(bdd.get_height(), pruned.get_height());
+        assert_eq!(pruned.as_node_slice(), pruned.prune_unreachable().as_node_slice());
+
+        let compact = bdd.compact();
+        assert_eq!("4294967295,0,0|4294967295,1,1|3,0,1|1,0,2|0,3,2|", compact.to_string());
+        assert_eq!(compact.as_node_slice(), compact.sort_preorder().as_node_slice());
+        assert!(Bdd::new_zero().prune_unreachable().is_zero());
+        assert!(Bdd::new_one().compact().is_one());
+    }
+
+    #[test]
+    pub fn bytes_round_trip_test() {
+        let bdd = Bdd::try_from("0,0,0|0,1,1|3,0,1|1,2,1|").unwrap();
+        let bytes = bdd.to_bytes();
+        assert_eq!(16 + 4 * 24, bytes.len());
+        let copy = Bdd::from_bytes(&bytes).unwrap();
+        assert_eq!(bdd.as_node_slice(), copy.as_node_slice());
+        assert_eq!(bdd.get_height(), copy.get_height());
+        let copy = unsafe { Bdd::from_bytes_unchecked(&bytes).unwrap() };
+        assert_eq!(bdd.as_node_slice(), copy.as_node_slice());
+
+        // The buffer does not have to be aligned.
+        let mut shifted = vec![0u8];
+        shifted.extend_from_slice(&bytes);
+        let copy = Bdd::from_bytes(&shifted[1..]).unwrap();
+        assert_eq!(bdd.as_node_slice(), copy.as_node_slice());
+
+        for constant in [Bdd::new_zero(), Bdd::new_one()] {
+            let copy = Bdd::from_bytes(&constant.to_bytes()).unwrap();
+            assert_eq!(constant.as_node_slice(), copy.as_node_slice());
+        }
+
+        // Invalid headers, sizes and nodes are rejected.
+        let mut no_magic = bytes.clone();
+        no_magic[0] = b'X';
+        let mut no_nodes = bytes[..16].to_vec();
+        no_nodes[8] = 0;
+        let mut bad_link = bytes.clone();
+        bad_link[16 + 3 * 24 + 8] = 7;
+        for data in [
+            &[][..],
+            &no_magic,
+            &no_nodes,
+            &bytes[..bytes.len() - 1],
+            &bytes[..bytes.len() - 24],
+            &bad_link,
+        ] {
+            assert!(Bdd::from_bytes(data).is_err());
+        }
+    }
+
+    #[test]
+    pub fn bytes_layout_test() {
+        // A node is stored as a `u32` variable, four padding bytes and two `u64` links.
+        let node = Node::pack(Variable::from(0x0102_0304), NodeIndex::from(5), NodeIndex::ONE);
+        let bdd = unsafe { Bdd::from_raw_parts(7, vec![Node::ZERO, Node::ONE, node.clone()]) };
+        let bytes = bdd.to_bytes();
+        let mut expected = b"BDDR".to_vec();
+        expected.extend_from_slice(&[7, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(&[0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0]);
+        expected.extend_from_slice(&[0; 16]);
+        expected.extend_from_slice(&[0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(&[4, 3, 2, 1, 0, 0, 0, 0, 5, 0, 0, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(expected, bytes);
+
+        // Extreme values survive the (unvalidated) round trip through the node layout.
+        let variable = Variable::from(u32::MAX - 1);
+        let node = Node::pack(variable, NodeIndex::UNDEFINED, NodeIndex::from(1 << 48));
+        let bdd = unsafe { Bdd::from_raw_parts(u32::MAX, vec![Node::ZERO, node]) };
+        let copy = unsafe { Bdd::from_bytes_unchecked(&bdd.to_bytes()).unwrap() };
+        assert_eq!(bdd.as_node_slice(), copy.as_node_slice());
+        assert_eq!(u32::MAX, copy.get_height());
+    }
 
     #[test]
+    #[cfg(not(feature = "wasm"))] // Reads test data from the file system.
     pub fn basic_sorting_test() {
-        let bdd = std::fs::read_to_string("bench_inputs/itgr/large-large-large.109.and_not.left.bdd").unwrap();
+        let bdd = crate::testing::read_corpus_file("itgr/large-large-large.109.and_not.left.bdd").unwrap();
         let bdd = Bdd::try_from(bdd.as_str()).unwrap();
 
         // Note that initially, the BDD is in post-order, but sorted from high to low. Our
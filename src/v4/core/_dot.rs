@@ -0,0 +1,64 @@
+use super::Bdd;
+use alloc::format;
+use alloc::string::String;
+use core::fmt::Write;
+
+impl Bdd {
+    /// Export this `Bdd` as a Graphviz `digraph` in the DOT format.
+    ///
+    /// Every node of the `Bdd` is exported, with high edges drawn as solid lines and low edges
+    /// as dashed lines. The terminals are boxes labelled `0` and `1`. Decision nodes are
+    /// labelled by the name of their variable from `variable_names` (indexed by
+    /// `u32::from(variable)`), or as `x_i` if there is no such name.
+    pub fn to_dot(&self, variable_names: Option<&[String]>) -> String {
+        let mut dot = String::from("digraph bdd {\n");
+        // Writing into a `String` cannot fail.
+        for (index, node) in self.as_node_slice().iter().enumerate() {
+            if index < 2 {
+                writeln!(dot, "  {} [shape=box, label=\"{}\"];", index, index).unwrap();
+                continue;
+            }
+            let (variable, low, high) = node.unpack();
+            let variable = u32::from(variable);
+            let name = variable_names
+                .and_then(|names| names.get(variable as usize))
+                .map(|name| name.replace('\\', "\\\\").replace('"', "\\\""))
+                .unwrap_or_else(|| format!("x_{}", variable));
+            writeln!(dot, "  {} [shape=circle, label=\"{}\"];", index, name).unwrap();
+            writeln!(dot, "  {} -> {} [style=dashed];", index, u64::from(low)).unwrap();
+            writeln!(dot, "  {} -> {} [style=solid];", index, u64::from(high)).unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v4::core::{Bdd, Variable};
+    use alloc::string::String;
+    use alloc::vec;
+
+    #[test]
+    pub fn to_dot_test() {
+        assert_eq!(
+            "digraph bdd {\n  0 [shape=box, label=\"0\"];\n}\n",
+            Bdd::new_zero().to_dot(None)
+        );
+
+        let bdd = Bdd::new_literal(Variable::from(1), false);
+        let dot = bdd.to_dot(None);
+        assert!(dot.starts_with("digraph bdd {\n"));
+        assert!(dot.contains("  1 [shape=box, label=\"1\"];\n"));
+        assert!(dot.contains("  2 [shape=circle, label=\"x_1\"];\n"));
+        assert!(dot.contains("  2 -> 1 [style=dashed];\n"));
+        assert!(dot.contains("  2 -> 0 [style=solid];\n"));
+
+        let names = vec![String::from("a"), String::from("\"b\"")];
+        let dot = bdd.to_dot(Some(&names));
+        assert!(dot.contains("  2 [shape=circle, label=\"\\\"b\\\"\"];\n"));
+        // Missing names fall back to the default.
+        let dot = bdd.to_dot(Some(&names[..1]));
+        assert!(dot.contains("  2 [shape=circle, label=\"x_1\"];\n"));
+    }
+}
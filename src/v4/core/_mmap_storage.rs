@@ -0,0 +1,208 @@
+use super::{Bdd, Node, NodeIndex, NodeStorage};
+use crate::{FromIndex, IntoIndex};
+use alloc::format;
+use alloc::string::String;
+use core::cmp::max;
+use core::mem::size_of;
+use memmap2::MmapMut;
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+
+/// A `NodeStorage` backed by a memory-mapped file (requires the `mmap` feature).
+///
+/// The file is a plain array of nodes in their in-memory layout (see `Node`), so it uses
+/// the byte order of the current platform and it is not portable between platforms. While
+/// nodes are added, the file grows in large steps; `MmapNodeStorage::flush` truncates it
+/// to the stored nodes, so that it can be opened again later.
+///
+/// The operating system only keeps the recently used pages in memory, so the storage can be
+/// much larger than the available RAM. The file must not be modified by anyone else while
+/// it is mapped.
+pub struct MmapNodeStorage {
+    file: File,
+    map: MmapMut,
+    len: usize,
+}
+
+/// **(internal)** The smallest number of nodes for which space is reserved in the file.
+const MIN_CAPACITY: usize = 1024;
+
+impl MmapNodeStorage {
+    /// Create an empty storage backed by a new file at the given `path` (an existing file
+    /// is overwritten).
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<MmapNodeStorage, String> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .map_err(|e| format!("{}", e))?;
+        let bytes = u64::from_index(MIN_CAPACITY * size_of::<Node>());
+        file.set_len(bytes).map_err(|e| format!("{}", e))?;
+        Ok(MmapNodeStorage {
+            map: map_file(&file)?,
+            file,
+            len: 0,
+        })
+    }
+
+    /// Open a file with nodes of a `Bdd` (e.g. written by `MmapNodeStorage::flush`) and check
+    /// that they are consistent (see `Bdd::check_consistency_errors`). The check reads
+    /// the whole file once.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<MmapNodeStorage, String> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map_err(|e| format!("{}", e))?;
+        let map = map_file(&file)?;
+        if map.len() % size_of::<Node>() != 0 || map.is_empty() {
+            return Err(format!("Invalid file size {}.", map.len()));
+        }
+        let storage = MmapNodeStorage {
+            len: map.len() / size_of::<Node>(),
+            map,
+            file,
+        };
+        match Bdd::check_consistency_errors(storage.as_node_slice()) {
+            Some(error) => Err(error),
+            None => Ok(storage),
+        }
+    }
+
+    /// All stored nodes.
+    pub fn as_node_slice(&self) -> &[Node] {
+        // The map is page-aligned (which is enough for `Node`), it has space for at least
+        // `len` nodes, and every bit pattern is a valid `Node`.
+        unsafe { core::slice::from_raw_parts(self.map.as_ptr() as *const Node, self.len) }
+    }
+
+    /// Write all nodes to the disk and truncate the file to the stored nodes.
+    pub fn flush(&mut self) -> Result<(), String> {
+        self.map.flush().map_err(|e| format!("{}", e))?;
+        self.resize(self.len)
+    }
+
+    /// Copy the stored nodes into a `Bdd`.
+    ///
+    /// *Panics:* the nodes must form a consistent `Bdd` (see `Bdd::from_nodes`).
+    pub fn to_bdd(&self) -> Bdd {
+        Bdd::from_nodes(self.as_node_slice().to_vec())
+    }
+
+    /// **(internal)** Change the size of the file to `capacity` nodes (at least one)
+    /// and map it again.
+    fn resize(&mut self, capacity: usize) -> Result<(), String> {
+        let bytes = u64::from_index(max(capacity, 1) * size_of::<Node>());
+        self.file.set_len(bytes).map_err(|e| format!("{}", e))?;
+        self.map = map_file(&self.file)?;
+        Ok(())
+    }
+}
+
+impl NodeStorage for MmapNodeStorage {
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    unsafe fn get_node_unchecked(&self, index: NodeIndex) -> Node {
+        unsafe { self.as_node_slice().get_unchecked(index.into_index()) }.clone()
+    }
+
+    /// *Panics:* if the file cannot be extended.
+    fn push_node(&mut self, node: Node) -> NodeIndex {
+        if (self.len + 1) * size_of::<Node>() > self.map.len() {
+            let capacity = max(2 * self.len, MIN_CAPACITY);
+            if let Err(error) = self.resize(capacity) {
+                panic!("Cannot extend the node file: {}", error);
+            }
+        }
+        // The map has space for at least `len + 1` nodes.
+        unsafe {
+            let nodes = self.map.as_mut_ptr() as *mut Node;
+            nodes.add(self.len).write(node);
+        }
+        self.len += 1;
+        NodeIndex::from_index(self.len - 1)
+    }
+
+    #[inline]
+    fn prefetch(&self, index: NodeIndex) {
+        // Prefetch operations ignore memory errors and are therefore "externally safe".
+        let nodes = self.map.as_ptr() as *const Node;
+        crate::arch::prefetch(nodes.wrapping_add(index.into_index()));
+    }
+}
+
+/// **(internal)** Map the whole `file` into memory.
+fn map_file(file: &File) -> Result<MmapMut, String> {
+    // The file is private to the storage, so it is not modified while it is mapped.
+    unsafe { MmapMut::map_mut(file) }.map_err(|e| format!("{}", e))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::expressions::BooleanExpression;
+    use crate::v2::ops::Xor;
+    use crate::v2::{Bdd as Bdd2, TaskCacheConfig};
+    use crate::v4::apply::{apply, apply_with_storage};
+    use crate::v4::core::{Bdd, MmapNodeStorage, Node, NodeIndex, NodeStorage, Variable};
+    use core::convert::TryFrom;
+
+    fn build(expression: &str, names: &[&str]) -> Bdd {
+        let expression = BooleanExpression::try_from(expression).unwrap();
+        let bdd = Bdd2::from_expression(&expression, names).unwrap();
+        Bdd::try_from(&bdd).unwrap()
+    }
+
+    #[test]
+    pub fn mmap_node_storage_test() {
+        let names = ["a", "b", "c", "d", "e", "f"];
+        let left = build("(a ^ d) & (b ^ e) | (c & f)", &names);
+        let right = build("(a & b) | (c <=> e) | !f", &names);
+        let left_path = std::env::temp_dir().join("mmap_node_storage_left.bin");
+        let result_path = std::env::temp_dir().join("mmap_node_storage_result.bin");
+
+        let mut operand = MmapNodeStorage::create(&left_path).unwrap();
+        for node in left.as_node_slice() {
+            operand.push_node(node.clone());
+        }
+        operand.flush().unwrap();
+        drop(operand);
+        let operand = MmapNodeStorage::open(&left_path).unwrap();
+        assert_eq!(left.as_node_slice(), operand.as_node_slice());
+
+        let output = MmapNodeStorage::create(&result_path).unwrap();
+        let config = TaskCacheConfig::default();
+        let right_nodes = right.as_node_slice().to_vec();
+        let (mut output, _, _) =
+            apply_with_storage::<Xor, _, _, _>(&operand, &right_nodes, output, &config).unwrap();
+        output.flush().unwrap();
+        let expected = apply::<Xor>(&left, &right);
+        assert_eq!(
+            expected.as_node_slice(),
+            output.to_bdd().sort_preorder().as_node_slice()
+        );
+        let reopened = MmapNodeStorage::open(&result_path).unwrap();
+        assert_eq!(output.as_node_slice(), reopened.as_node_slice());
+        drop((operand, output, reopened));
+
+        // The file grows as needed, but files with inconsistent nodes are rejected.
+        let mut storage = MmapNodeStorage::create(&result_path).unwrap();
+        let invalid = Node::pack(Variable::from(0), NodeIndex::ZERO, NodeIndex::from(5000));
+        for _ in 0..3000 {
+            storage.push_node(invalid.clone());
+        }
+        assert_eq!(3000, storage.len());
+        assert!(storage.as_node_slice().iter().all(|node| *node == invalid));
+        storage.flush().unwrap();
+        drop(storage);
+        assert!(MmapNodeStorage::open(&result_path).is_err());
+        std::fs::remove_file(&left_path).unwrap();
+        std::fs::remove_file(&result_path).unwrap();
+        assert!(MmapNodeStorage::open(&result_path).is_err());
+    }
+}
@@ -16,9 +16,17 @@ use super::NodeIndex;
 /// Also note that we assume a BDD is sorted in such a way that smaller variables are closer to the
 /// root. This means a `Variable::UNDEFINED` in the terminal node is always the correct termination
 /// of any growing sequence of variables that one may encounter on any path in the BDD.
+///
+/// The memory layout of a node is fixed (`u32` variable, 4 bytes of padding, and two `u64`
+/// links), such that `Bdd::from_bytes` can copy the node array directly from a buffer.
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[repr(C)]
 pub struct Node(Variable, NodeIndex, NodeIndex);
 
+// The layout is part of the binary format (see `Bdd::to_bytes`).
+assert_eq_size!(Node, [u64; 3]);
+const_assert_eq!(core::mem::align_of::<Node>(), 8);
+
 impl Node {
     pub const ZERO: Node = Node(Variable::UNDEFINED, NodeIndex::ZERO, NodeIndex::ZERO);
     pub const ONE: Node = Node(Variable::UNDEFINED, NodeIndex::ONE, NodeIndex::ONE);
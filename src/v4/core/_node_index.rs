@@ -8,6 +8,7 @@ use crate::{IntoIndex, FromIndex};
 /// metadata if you want to. Note that this is not checked anywhere, so always make sure you
 /// erase the metadata when interfacing with the BDD implementation (e.g. using a wrapper type).
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[repr(transparent)]
 pub struct NodeIndex(u64);
 
 impl NodeIndex {
@@ -0,0 +1,74 @@
+use super::{Node, NodeIndex};
+use crate::{FromIndex, IntoIndex};
+use alloc::vec::Vec;
+
+/// A growable storage of BDD nodes, addressed by `NodeIndex`.
+///
+/// The apply algorithm (see `apply::apply_with_storage`) only accesses the nodes of its
+/// operands and its result through this trait, so that different storage layouts (in memory,
+/// memory-mapped, compressed, ...) can be used without changing the algorithm. For this
+/// reason, nodes are returned by value: the storage does not have to keep `Node` objects
+/// in memory.
+///
+/// Same as in a `Bdd`, the first nodes of a storage are the terminal nodes, every node
+/// is stored after its successors, and the root is the last node.
+pub trait NodeStorage {
+    /// The number of stored nodes.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Read the node at the given `index`.
+    ///
+    /// # Safety
+    ///
+    /// The `index` must be smaller than `NodeStorage::len`.
+    unsafe fn get_node_unchecked(&self, index: NodeIndex) -> Node;
+
+    /// Read the node at the given `index`.
+    ///
+    /// *Panics:* `index` must be smaller than `NodeStorage::len`.
+    fn get_node(&self, index: NodeIndex) -> Node {
+        assert!(
+            index.into_index() < self.len(),
+            "Node index {:?} out of bounds.",
+            index
+        );
+        unsafe { self.get_node_unchecked(index) }
+    }
+
+    /// Append the `node` to the storage and return its index.
+    fn push_node(&mut self, node: Node) -> NodeIndex;
+
+    /// Hint that the node with the given `index` will be needed soon. Does nothing by default.
+    #[inline]
+    fn prefetch(&self, index: NodeIndex) {
+        let _ = index;
+    }
+}
+
+impl NodeStorage for Vec<Node> {
+    #[inline]
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    #[inline]
+    unsafe fn get_node_unchecked(&self, index: NodeIndex) -> Node {
+        unsafe { self.get_unchecked(index.into_index()) }.clone()
+    }
+
+    #[inline]
+    fn push_node(&mut self, node: Node) -> NodeIndex {
+        self.push(node);
+        NodeIndex::from_index(Vec::len(self) - 1)
+    }
+
+    #[inline]
+    fn prefetch(&self, index: NodeIndex) {
+        // Prefetch operations ignore memory errors and are therefore "externally safe".
+        crate::arch::prefetch(self.as_ptr().wrapping_add(index.into_index()));
+    }
+}
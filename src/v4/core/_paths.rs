@@ -0,0 +1,104 @@
+use super::{Bdd, NodeIndex, Variable};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// **(internal)** A variable together with its value on a path.
+type Literal = (Variable, bool);
+
+/// An iterator over all paths from the root of a `Bdd` to the `1` terminal (see `Bdd::paths`).
+///
+/// Every path is returned as a conjunctive clause: a list of `(variable, value)` literals
+/// sorted by variable. Variables which do not appear on the path are not restricted by the
+/// clause. The paths of a `Bdd` are disjoint, so the clauses form a DNF of the function where
+/// every satisfying valuation satisfies exactly one clause.
+///
+/// The paths are explored depth-first, with the low edge explored before the high edge.
+/// The iterator only needs memory proportional to the height of the `Bdd`.
+pub struct PathIterator<'a> {
+    bdd: &'a Bdd,
+    /// Nodes which remain to be explored, together with the length of the clause prefix
+    /// that leads to them and the literal of the edge that was used to reach them.
+    stack: Vec<(NodeIndex, usize, Option<Literal>)>,
+    clause: Vec<Literal>,
+}
+
+impl<'a> PathIterator<'a> {
+    /// Create a new iterator over the paths of the given `bdd`.
+    pub fn new(bdd: &'a Bdd) -> PathIterator<'a> {
+        PathIterator {
+            bdd,
+            stack: vec![(bdd.get_root_index(), 0, None)],
+            clause: Vec::new(),
+        }
+    }
+}
+
+impl<'a> Iterator for PathIterator<'a> {
+    type Item = Vec<Literal>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((index, depth, literal)) = self.stack.pop() {
+            self.clause.truncate(depth);
+            if let Some(literal) = literal {
+                self.clause.push(literal);
+            }
+            if index.is_one() {
+                return Some(self.clause.clone());
+            }
+            if index.is_zero() {
+                continue;
+            }
+            let (variable, low, high) = self.bdd.get_node(index).unpack();
+            let depth = self.clause.len();
+            self.stack.push((high, depth, Some((variable, true))));
+            self.stack.push((low, depth, Some((variable, false))));
+        }
+        None
+    }
+}
+
+impl Bdd {
+    /// Iterate over all paths from the root to the `1` terminal, each given as a conjunctive
+    /// clause (see `PathIterator`).
+    ///
+    /// The `0` constant has no paths, and the `1` constant has exactly one empty path.
+    pub fn paths(&self) -> PathIterator<'_> {
+        PathIterator::new(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v4::core::{Bdd, Variable};
+    use core::convert::TryFrom;
+
+    #[test]
+    pub fn path_iterator_test() {
+        // x0 | (!x1 & x2)
+        let bdd = Bdd::try_from("0,0,0|0,1,1|2,0,1|1,2,0|0,3,1|").unwrap();
+        let v = |id: u32| Variable::from(id);
+        let paths: Vec<Vec<(Variable, bool)>> = bdd.paths().collect();
+        assert_eq!(
+            vec![
+                vec![(v(0), false), (v(1), false), (v(2), true)],
+                vec![(v(0), true)],
+            ],
+            paths
+        );
+
+        // Every satisfying valuation satisfies exactly one clause.
+        for valuation in 0..8u32 {
+            let value = |var: Variable| valuation & (1 << u32::from(var)) != 0;
+            let expected = value(v(0)) || (!value(v(1)) && value(v(2)));
+            let satisfied = paths
+                .iter()
+                .filter(|clause| clause.iter().all(|(var, val)| value(*var) == *val))
+                .count();
+            assert_eq!(usize::from(expected), satisfied);
+        }
+
+        assert_eq!(0, Bdd::new_zero().paths().count());
+        let one: Vec<_> = Bdd::new_one().paths().collect();
+        assert_eq!(vec![Vec::<(Variable, bool)>::new()], one);
+    }
+}
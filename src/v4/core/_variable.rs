@@ -7,6 +7,7 @@ use crate::IntoIndex;
 /// However the BDD may not impose any restrictions on said variable. To find out if a variable
 /// is truly used in the BDD, one has to iterate through its nodes.
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[repr(transparent)]
 pub struct Variable(u32);
 
 impl Variable {
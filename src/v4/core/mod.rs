@@ -1,10 +1,20 @@
-
 mod _variable;
 mod _node_index;
 mod _node;
 mod _bdd;
+mod _annotations;
+mod _paths;
+mod _dot;
+mod _node_storage;
+#[cfg(feature = "mmap")]
+mod _mmap_storage;
 
 pub use _variable::Variable;
 pub use _node_index::NodeIndex;
 pub use _node::Node;
-pub use _bdd::Bdd;
\ No newline at end of file
+pub use _bdd::Bdd;
+pub use _annotations::NodeAnnotations;
+pub use _paths::PathIterator;
+pub use _node_storage::NodeStorage;
+#[cfg(feature = "mmap")]
+pub use _mmap_storage::MmapNodeStorage;
@@ -1,5 +1,16 @@
-
 /// Private declarations of the core data structures of the BDD implementation. Everything
 /// essential is re-exported in this module for public use.
 pub mod core;
-pub mod apply;
\ No newline at end of file
+
+/// The apply algorithm for binary logical operations on `core::Bdd` objects.
+pub mod apply;
+
+/// Multi-terminal BDDs with values of an arbitrary type, built on the `core` nodes.
+pub mod mtbdd;
+
+/// Conversion between `core::Bdd` objects and Sylvan (requires the `sylvan-interop` feature).
+#[cfg(feature = "sylvan-interop")]
+pub mod sylvan;
+
+pub use crate::v2::ops;
+pub use apply::{apply, apply_with_config, apply_with_stats, ApplyStats};
@@ -0,0 +1,426 @@
+use super::apply::task_cache::TaskCache;
+use super::apply::unsafe_stack::UnsafeStack;
+use super::apply::ApplyTask;
+use super::core::{Bdd, Node, NodeIndex, Variable};
+use crate::v2::TaskCacheConfig;
+use crate::{FromIndex, IntoIndex};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::max;
+
+/// A multi-terminal BDD (also called an algebraic decision diagram): a function from
+/// Boolean valuations into values of type `T`.
+///
+/// The decision nodes are the same as in a `core::Bdd`, but there can be any number of
+/// terminal nodes. Every terminal node has a value in `terminals` (the terminal node `i` has
+/// the value `terminals[i]`) and no two terminals have the same value. Same as in a `Bdd`,
+/// the terminal nodes are first and the root is the last node. Additionally, every node
+/// is stored after its successors.
+///
+/// The terminal values are only compared using `PartialEq`, so floating point values can
+/// be used directly (but `NaN` terminals are never merged). Finding an existing terminal
+/// takes time linear in the number of terminals, so the representation is intended for
+/// functions with a moderate number of distinct values.
+#[derive(Clone, Debug)]
+pub struct Mtbdd<T> {
+    terminals: Vec<T>,
+    nodes: Vec<Node>,
+}
+
+/// **(internal)** Marks the terminal results of `Mtbdd::apply` before they are
+/// renumbered by `ResultBuilder::export`.
+const TERMINAL_BIT: u64 = 1 << 63;
+
+/// **(internal)** An empty slot of the unique table of `ResultBuilder`.
+const EMPTY: u64 = u64::MAX;
+
+impl<T> Mtbdd<T> {
+    /// The number of nodes in this `Mtbdd`, including the terminal nodes.
+    pub fn get_size(&self) -> u64 {
+        u64::from_index(self.nodes.len())
+    }
+
+    pub fn get_root_index(&self) -> NodeIndex {
+        NodeIndex::from_index(self.nodes.len() - 1)
+    }
+
+    pub fn get_node(&self, index: NodeIndex) -> &Node {
+        &self.nodes[index.into_index()]
+    }
+
+    pub fn as_node_slice(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// The values of the terminal nodes, such that `terminals()[i]` is the value
+    /// of the terminal node `i`.
+    pub fn terminals(&self) -> &[T] {
+        &self.terminals
+    }
+
+    pub fn is_constant(&self) -> bool {
+        self.nodes.len() == 1
+    }
+
+    /// The value of the function in the given `valuation`, where `valuation[i]` is
+    /// the value of `Variable::from(i)`.
+    ///
+    /// *Panics:* `valuation` must contain every variable which appears in the `Mtbdd`.
+    pub fn eval(&self, valuation: &[bool]) -> &T {
+        let mut index = self.get_root_index();
+        loop {
+            let (variable, low, high) = self.get_node(index).unpack();
+            if variable.is_undefined() {
+                return &self.terminals[index.into_index()];
+            }
+            index = if valuation[variable.into_index()] {
+                high
+            } else {
+                low
+            };
+        }
+    }
+
+    /// **(internal)** The number of decision nodes on the longest path in the `Mtbdd`.
+    fn height(&self) -> usize {
+        // Every node is stored after its successors, so one pass is enough.
+        let mut heights = vec![0usize; self.nodes.len()];
+        for (index, node) in self.nodes.iter().enumerate() {
+            if !node.is_terminal() {
+                let (_, low, high) = node.unpack();
+                heights[index] = 1 + max(heights[low.into_index()], heights[high.into_index()]);
+            }
+        }
+        heights.last().copied().unwrap_or(0)
+    }
+}
+
+impl<T: Clone + PartialEq> Mtbdd<T> {
+    /// Create a constant function with the given `value`.
+    pub fn new_constant(value: T) -> Mtbdd<T> {
+        Mtbdd {
+            terminals: vec![value],
+            nodes: vec![Node::ZERO],
+        }
+    }
+
+    /// Create the function `variable ? high : low`.
+    pub fn new_variable(variable: Variable, low: T, high: T) -> Mtbdd<T> {
+        if low == high {
+            return Mtbdd::new_constant(low);
+        }
+        Mtbdd {
+            terminals: vec![low, high],
+            nodes: vec![
+                Node::ZERO,
+                Node::ONE,
+                Node::pack(variable, NodeIndex::ZERO, NodeIndex::ONE),
+            ],
+        }
+    }
+
+    /// Create a function which is `one` in the satisfying valuations of the `bdd`, and `zero`
+    /// in all other valuations.
+    pub fn from_bdd(bdd: &Bdd, zero: T, one: T) -> Mtbdd<T> {
+        if bdd.is_zero() || zero == one {
+            return Mtbdd::new_constant(zero);
+        }
+        if bdd.is_one() {
+            return Mtbdd::new_constant(one);
+        }
+        // A postorder stores every node after its successors.
+        let bdd = bdd.prune_unreachable().sort_postorder();
+        Mtbdd {
+            terminals: vec![zero, one],
+            nodes: bdd.as_node_slice().to_vec(),
+        }
+    }
+
+    /// Combine this function with the `other` function using a binary operation `op`
+    /// on terminal values, i.e. the result is the function `x -> op(self(x), other(x))`.
+    ///
+    /// This is the same coupled DFS as `v4::apply` (it uses the same explicit stack and
+    /// `TaskCache`), but since `op` is opaque, a task is only resolved once both operands
+    /// are terminals. The result is canonical: it only depends on the computed function.
+    pub fn apply<U, R, F>(&self, other: &Mtbdd<U>, mut op: F) -> Mtbdd<R>
+    where
+        R: Clone + PartialEq,
+        F: FnMut(&T, &U) -> R,
+    {
+        let height_limit = self.height() + other.height();
+        let mut task_cache = TaskCache::new(self.get_size(), &TaskCacheConfig::default());
+        let mut builder = ResultBuilder::new(self.nodes.len() + other.nodes.len());
+
+        // Same as in `v4::apply`, there are up to height_limit expanded tasks, every task has
+        // up to one extra non-expanded child, and there is the root task.
+        let mut stack = UnsafeStack::new(2 * height_limit + 2);
+        stack.push(ApplyTask::new(
+            0,
+            (self.get_root_index(), other.get_root_index()),
+        ));
+
+        let root_result = 'main: loop {
+            let iterations = max(task_cache.grow_if_necessary(), 1024);
+            for _ in 0..iterations {
+                let top = stack.peek();
+                let top_offset: usize = top.get_offset().into();
+
+                let mut result = NodeIndex::UNDEFINED;
+                if top.is_not_decoded() {
+                    top.mark_as_decoded();
+                    let (cached, slot) = task_cache.read(top.task);
+                    if !cached.is_undefined() {
+                        result = cached;
+                    } else {
+                        let (left, right) = top.task;
+                        let (l_var, l_low, l_high) = self.get_node(left).unpack();
+                        let (r_var, r_low, r_high) = other.get_node(right).unpack();
+                        if l_var.is_undefined() && r_var.is_undefined() {
+                            let value = op(
+                                &self.terminals[left.into_index()],
+                                &other.terminals[right.into_index()],
+                            );
+                            result = builder.terminal(value);
+                            task_cache.write(slot, top.task, result);
+                        } else {
+                            top.task_cache_slot = slot;
+                            // Terminals have an undefined variable, which is larger than
+                            // any other variable.
+                            if l_var == r_var {
+                                top.variable = l_var;
+                                stack.push(ApplyTask::new(1, (l_high, r_high)));
+                                stack.push(ApplyTask::new(2, (l_low, r_low)));
+                            } else if l_var < r_var {
+                                top.variable = l_var;
+                                stack.push(ApplyTask::new(1, (l_high, right)));
+                                stack.push(ApplyTask::new(2, (l_low, right)));
+                            } else {
+                                top.variable = r_var;
+                                stack.push(ApplyTask::new(1, (left, r_high)));
+                                stack.push(ApplyTask::new(2, (left, r_low)));
+                            }
+                        }
+                    }
+                } else {
+                    // Task is decoded, we have to create a new node for it.
+                    let (result_low, result_high) = (top.results[1], top.results[0]);
+                    result = builder.node(top.variable, result_low, result_high);
+                    task_cache.write(top.task_cache_slot, top.task, result);
+                }
+
+                if !result.is_undefined() {
+                    stack.pop();
+                    if stack.is_empty() {
+                        // This was the root task.
+                        break 'main result;
+                    }
+                    // high = 1, low = 2, so they will be saved in reverse order.
+                    let parent = stack.peek_at(top_offset);
+                    parent.results[top_offset - 1] = result;
+                }
+            }
+        };
+
+        builder.export(root_result)
+    }
+}
+
+/// **(internal)** The terminals and nodes created by `Mtbdd::apply`.
+///
+/// The terminals are not known in advance, so during the operation, a terminal result is
+/// the index of its value with `TERMINAL_BIT` set, and a decision node is its position
+/// in `nodes`. `ResultBuilder::export` then renumbers the reachable nodes.
+struct ResultBuilder<R> {
+    terminals: Vec<R>,
+    nodes: Vec<Node>,
+    /// Open addressing hash table of node positions (linear probing, at most half full).
+    unique: Vec<u64>,
+}
+
+impl<R: Clone + PartialEq> ResultBuilder<R> {
+    fn new(capacity: usize) -> ResultBuilder<R> {
+        ResultBuilder {
+            terminals: Vec::new(),
+            nodes: Vec::with_capacity(capacity),
+            unique: vec![EMPTY; (2 * capacity).next_power_of_two().max(16)],
+        }
+    }
+
+    /// The result index of the terminal with the given `value`.
+    fn terminal(&mut self, value: R) -> NodeIndex {
+        let id = match self.terminals.iter().position(|it| *it == value) {
+            Some(id) => id,
+            None => {
+                self.terminals.push(value);
+                self.terminals.len() - 1
+            }
+        };
+        NodeIndex::from(TERMINAL_BIT | u64::from_index(id))
+    }
+
+    /// The result index of the function `variable ? high : low`.
+    fn node(&mut self, variable: Variable, low: NodeIndex, high: NodeIndex) -> NodeIndex {
+        if low == high {
+            return low;
+        }
+        let node = Node::pack(variable, low, high);
+        let mask = self.unique.len() - 1;
+        let mut slot = hash(&node) & mask;
+        loop {
+            let position = self.unique[slot];
+            if position == EMPTY {
+                break;
+            }
+            if self.nodes[position.into_index()] == node {
+                return NodeIndex::from(position);
+            }
+            slot = (slot + 1) & mask;
+        }
+        let index = NodeIndex::from_index(self.nodes.len());
+        self.unique[slot] = u64::from(index);
+        self.nodes.push(node);
+        if 2 * self.nodes.len() > self.unique.len() {
+            self.grow();
+        }
+        index
+    }
+
+    /// **(internal)** Double the size of the unique table and re-insert all nodes.
+    #[cold]
+    fn grow(&mut self) {
+        self.unique = vec![EMPTY; 2 * self.unique.len()];
+        let mask = self.unique.len() - 1;
+        for (position, node) in self.nodes.iter().enumerate() {
+            let mut slot = hash(node) & mask;
+            while self.unique[slot] != EMPTY {
+                slot = (slot + 1) & mask;
+            }
+            self.unique[slot] = u64::from_index(position);
+        }
+    }
+
+    /// Build the `Mtbdd` of the result `root`. The nodes reachable from the root are sorted
+    /// in DFS postorder (low edge first) and the terminals are sorted by their first
+    /// appearance in this order, so the result does not depend on the order in which
+    /// the nodes were created.
+    fn export(self, root: NodeIndex) -> Mtbdd<R> {
+        let is_terminal = |index: NodeIndex| u64::from(index) & TERMINAL_BIT != 0;
+        let position = |index: NodeIndex| (u64::from(index) & !TERMINAL_BIT).into_index();
+
+        // The new index of every terminal and node, or `UNDEFINED` if it was not visited yet.
+        let mut terminal_ids = vec![NodeIndex::UNDEFINED; self.terminals.len()];
+        let mut node_ids = vec![NodeIndex::UNDEFINED; self.nodes.len()];
+        let mut terminals = Vec::new();
+        let mut order = Vec::new();
+        let mut stack = vec![(root, false)];
+        while let Some((index, expanded)) = stack.pop() {
+            if is_terminal(index) {
+                let id = &mut terminal_ids[position(index)];
+                if id.is_undefined() {
+                    *id = NodeIndex::from_index(terminals.len());
+                    terminals.push(self.terminals[position(index)].clone());
+                }
+            } else if expanded {
+                node_ids[position(index)] = NodeIndex::from_index(order.len());
+                order.push(position(index));
+            } else if node_ids[position(index)].is_undefined() {
+                let (_, low, high) = self.nodes[position(index)].unpack();
+                stack.push((index, true));
+                stack.push((high, false));
+                stack.push((low, false));
+            }
+        }
+
+        // The decision nodes are stored after all terminals.
+        let terminal_count = terminals.len();
+        let new_index = |index: NodeIndex| {
+            if is_terminal(index) {
+                terminal_ids[position(index)]
+            } else {
+                NodeIndex::from_index(terminal_count + node_ids[position(index)].into_index())
+            }
+        };
+        let mut nodes: Vec<Node> = (0..terminal_count)
+            .map(|id| {
+                let id = NodeIndex::from_index(id);
+                Node::pack(Variable::UNDEFINED, id, id)
+            })
+            .collect();
+        for position in order {
+            let (variable, low, high) = self.nodes[position].unpack();
+            nodes.push(Node::pack(variable, new_index(low), new_index(high)));
+        }
+        Mtbdd { terminals, nodes }
+    }
+}
+
+/// **(internal)** A multiplicative hash of all three components of a `node`.
+fn hash(node: &Node) -> usize {
+    let (variable, low, high) = node.unpack();
+    let key = u64::from(low) ^ u64::from(high).rotate_left(21) ^ u64::from(u32::from(variable));
+    (key.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32) as usize
+}
+
+#[cfg(test)]
+mod test {
+    use crate::expressions::BooleanExpression;
+    use crate::v2::ops::And;
+    use crate::v2::Bdd as Bdd2;
+    use crate::v4::core::{Bdd, Variable};
+    use crate::v4::mtbdd::Mtbdd;
+    use alloc::vec::Vec;
+    use core::convert::TryFrom;
+
+    fn build(expression: &str, names: &[&str]) -> Bdd {
+        let expression = BooleanExpression::try_from(expression).unwrap();
+        let bdd = Bdd2::from_expression(&expression, names).unwrap();
+        Bdd::try_from(&bdd).unwrap()
+    }
+
+    fn valuations(count: usize) -> impl Iterator<Item = Vec<bool>> {
+        (0..(1u32 << count)).map(move |mask| (0..count).map(|i| mask & (1 << i) != 0).collect())
+    }
+
+    #[test]
+    pub fn mtbdd_apply_test() {
+        let names = ["a", "b", "c", "d"];
+        let left = build("(a & b) | (c ^ d)", &names);
+        let right = build("a <=> d", &names);
+        let left_count = Mtbdd::from_bdd(&left, 0u32, 1);
+        let right_count = Mtbdd::from_bdd(&right, 0u32, 1);
+
+        let sum = left_count.apply(&right_count, |l, r| l + r);
+        let mirrored = right_count.apply(&left_count, |r, l| l + r);
+        assert_eq!(sum.as_node_slice(), mirrored.as_node_slice());
+        assert_eq!(sum.terminals(), mirrored.terminals());
+
+        // Weighted sum of variables as `f64` costs (every subset has a different cost).
+        let costs = (0..4).fold(Mtbdd::new_constant(0.0), |cost, i| {
+            let weight = Mtbdd::new_variable(Variable::from(i), 0.0, 0.5 * f64::from(1u32 << i));
+            cost.apply(&weight, |a, b| a + b)
+        });
+        let scaled = costs.apply(&sum, |cost, count| cost * f64::from(*count));
+        for valuation in valuations(4) {
+            let count = u32::from(left_count.eval(&valuation) == &1)
+                + u32::from(right_count.eval(&valuation) == &1);
+            assert_eq!(count, *sum.eval(&valuation));
+            let cost: f64 = (0..4)
+                .filter(|i| valuation[*i])
+                .map(|i| 0.5 * f64::from(1u32 << i))
+                .sum();
+            assert_eq!(cost, *costs.eval(&valuation));
+            assert_eq!(cost * f64::from(count), *scaled.eval(&valuation));
+        }
+        assert_eq!(16, costs.terminals().len());
+
+        // The Boolean case is the same as a `Bdd`.
+        let and = left_count.apply(&right_count, |l, r| l * r);
+        let expected = crate::v4::apply::<And>(&left, &right);
+        assert_eq!(expected.get_size(), and.get_size());
+        let difference = sum.apply(&sum, |l, r| i64::from(*l) - i64::from(*r));
+        assert!(difference.is_constant());
+        assert_eq!(&0, difference.eval(&[]));
+        assert!(Mtbdd::from_bdd(&left, 3, 3).is_constant());
+        assert!(Mtbdd::new_variable(Variable::from(0), 'x', 'x').is_constant());
+    }
+}
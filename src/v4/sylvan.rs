@@ -0,0 +1,148 @@
+//! Conversion between `core::Bdd` objects and Sylvan (requires the `sylvan-interop` feature,
+//! which links the Sylvan library).
+//!
+//! The variable `Variable::from(i)` of a `Bdd` is the Sylvan variable (level) `i`. Sylvan uses
+//! complement edges, so a Sylvan node which is reachable both through a regular and through
+//! a complemented edge is converted into two `Bdd` nodes (the function and its negation).
+//!
+//! All functions call into Sylvan, so they are `unsafe`: Sylvan must be fully initialized
+//! (Lace workers are running, and both `sylvan_init_package` and `sylvan_init_bdd` were called)
+//! and every `SylvanBdd` must be a valid node of the current node table.
+
+use super::core::{Bdd, Node, NodeIndex, Variable};
+use crate::{FromIndex, IntoIndex};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use std::collections::HashMap;
+
+/// A Sylvan `BDD`: an index into the Sylvan node table, where the highest bit marks
+/// a complemented edge.
+pub type SylvanBdd = u64;
+
+/// The Sylvan `BDD` of the constant `false`.
+pub const SYLVAN_FALSE: SylvanBdd = 0;
+
+/// The Sylvan `BDD` of the constant `true` (the complemented `false`).
+pub const SYLVAN_TRUE: SylvanBdd = 1 << 63;
+
+#[link(name = "sylvan")]
+extern "C" {
+    fn sylvan_makenode(level: u32, low: SylvanBdd, high: SylvanBdd) -> SylvanBdd;
+    fn sylvan_var(bdd: SylvanBdd) -> u32;
+    fn sylvan_low(bdd: SylvanBdd) -> SylvanBdd;
+    fn sylvan_high(bdd: SylvanBdd) -> SylvanBdd;
+    fn sylvan_ref(bdd: SylvanBdd) -> SylvanBdd;
+    fn sylvan_deref(bdd: SylvanBdd);
+}
+
+/// Build the Sylvan `BDD` of the given `bdd`.
+///
+/// The result is referenced (using `sylvan_ref`), so it is the responsibility of the caller
+/// to release it (using `sylvan_deref`) once it is no longer needed.
+///
+/// # Safety
+///
+/// Sylvan must be initialized (see module documentation).
+pub unsafe fn to_sylvan(bdd: &Bdd) -> SylvanBdd {
+    if bdd.is_constant() {
+        return if bdd.is_one() { SYLVAN_TRUE } else { SYLVAN_FALSE };
+    }
+
+    // Nodes which are not translated yet have no image.
+    let mut images: Vec<Option<SylvanBdd>> = vec![None; bdd.as_node_slice().len()];
+    images[0] = Some(SYLVAN_FALSE);
+    images[1] = Some(SYLVAN_TRUE);
+    let root = bdd.get_root_index();
+    let mut stack = vec![root];
+    while let Some(top) = stack.last().copied() {
+        let (variable, low, high) = bdd.get_node(top).unpack();
+        match (images[low.into_index()], images[high.into_index()]) {
+            (Some(low), Some(high)) => {
+                // The intermediate results must survive the garbage collection of Sylvan.
+                let image = unsafe { sylvan_ref(sylvan_makenode(u32::from(variable), low, high)) };
+                images[top.into_index()] = Some(image);
+                stack.pop();
+            }
+            (low_image, high_image) => {
+                if high_image.is_none() {
+                    stack.push(high);
+                }
+                if low_image.is_none() {
+                    stack.push(low);
+                }
+            }
+        }
+    }
+
+    let result = images[root.into_index()].unwrap();
+    unsafe {
+        sylvan_ref(result);
+        for image in images.into_iter().skip(2).flatten() {
+            sylvan_deref(image);
+        }
+    }
+    result
+}
+
+/// Build a `Bdd` of the given Sylvan `node`. The result is sorted in DFS preorder.
+///
+/// Fails if the variables do not grow along some path of the diagram (this cannot happen
+/// unless the Sylvan diagram is corrupted), or if a variable cannot be represented
+/// by a `Variable`.
+///
+/// # Safety
+///
+/// Sylvan must be initialized and `node` must be a valid node (see module documentation).
+pub unsafe fn from_sylvan(node: SylvanBdd) -> Result<Bdd, String> {
+    if node == SYLVAN_FALSE {
+        return Ok(Bdd::new_zero());
+    }
+    if node == SYLVAN_TRUE {
+        return Ok(Bdd::new_one());
+    }
+
+    let mut nodes = vec![Node::ZERO, Node::ONE];
+    let mut indices: HashMap<SylvanBdd, NodeIndex> = HashMap::new();
+    indices.insert(SYLVAN_FALSE, NodeIndex::ZERO);
+    indices.insert(SYLVAN_TRUE, NodeIndex::ONE);
+    let mut stack = vec![node];
+    while let Some(top) = stack.last().copied() {
+        if indices.contains_key(&top) {
+            stack.pop();
+            continue;
+        }
+        // Unlike the raw node table, these functions already apply the complement mark.
+        let (level, low, high) = unsafe { (sylvan_var(top), sylvan_low(top), sylvan_high(top)) };
+        match (indices.get(&low).copied(), indices.get(&high).copied()) {
+            (Some(low), Some(high)) => {
+                let variable = Variable::from(level);
+                if variable.is_undefined() {
+                    return Err(format!("Invalid Sylvan variable {}.", level));
+                }
+                for child in [low, high] {
+                    if nodes[child.into_index()].get_variable() <= variable {
+                        return Err(format!(
+                            "Sylvan variable {} is not ordered before its successors.",
+                            level
+                        ));
+                    }
+                }
+                // Children are always created first, so the root ends up as the last node.
+                indices.insert(top, NodeIndex::from_index(nodes.len()));
+                nodes.push(Node::pack(variable, low, high));
+                stack.pop();
+            }
+            (low_index, high_index) => {
+                if high_index.is_none() {
+                    stack.push(high);
+                }
+                if low_index.is_none() {
+                    stack.push(low);
+                }
+            }
+        }
+    }
+    Ok(Bdd::from_nodes(nodes).sort_preorder())
+}
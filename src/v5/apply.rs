@@ -0,0 +1,195 @@
+use super::core::{Bdd, NodeCache, NodeIndex, Variable};
+use crate::v2::ops::BooleanOp;
+use crate::IntoIndex;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A product task: a pair of (possibly complemented) edges in the left and right `Bdd`.
+type Task = (NodeIndex, NodeIndex);
+
+/// One step of the explicit DFS stack of `apply`.
+enum Step {
+    /// Resolve the task, either directly or by scheduling its sub-tasks.
+    Expand(Task),
+    /// Create the result node of the task from the results of its two sub-tasks.
+    Build(Variable, Task),
+}
+
+/// Apply a binary logical operator `OP` to two complement-edge `Bdds`.
+///
+/// This is the same coupled DFS as `v4::apply`, with two differences: The cofactors of
+/// a complemented edge are the complemented successors of its node, and the only terminal
+/// checks are the ones of `OP` for the two constant edges. The result nodes are created
+/// by a `NodeCache`, so the result is canonical and every node is stored after its successors.
+pub fn apply<OP: BooleanOp>(left_bdd: &Bdd, right_bdd: &Bdd) -> Bdd {
+    let capacity = (left_bdd.get_size() + right_bdd.get_size()).into_index();
+    let mut node_cache = NodeCache::new(capacity);
+    let mut task_cache = TaskCache::new(capacity);
+
+    let mut steps = vec![Step::Expand((
+        left_bdd.get_root_index(),
+        right_bdd.get_root_index(),
+    ))];
+    let mut results: Vec<NodeIndex> = Vec::new();
+    while let Some(step) = steps.pop() {
+        match step {
+            Step::Expand(task) => {
+                let known = terminal_result::<OP>(task).or_else(|| task_cache.get(task));
+                if let Some(result) = known {
+                    results.push(result);
+                    continue;
+                }
+                let (left, right) = task;
+                let l_var = left_bdd.get_node(left).get_variable();
+                let r_var = right_bdd.get_node(right).get_variable();
+                // Terminals have an undefined variable, which is larger than any other variable.
+                let variable = l_var.min(r_var);
+                let (l_low, l_high) = cofactors(left_bdd, left, variable);
+                let (r_low, r_high) = cofactors(right_bdd, right, variable);
+                // The low task is on top, so its result is computed first.
+                steps.push(Step::Build(variable, task));
+                steps.push(Step::Expand((l_high, r_high)));
+                steps.push(Step::Expand((l_low, r_low)));
+            }
+            Step::Build(variable, task) => {
+                let high = results.pop().unwrap();
+                let low = results.pop().unwrap();
+                let result = node_cache.make_node(variable, low, high);
+                task_cache.grow_if_necessary(node_cache.len());
+                task_cache.insert(task, result);
+                results.push(result);
+            }
+        }
+    }
+
+    debug_assert_eq!(results.len(), 1);
+    unsafe { Bdd::from_raw_parts(results[0], node_cache.into_nodes()) }
+}
+
+/// **(internal)** The result of a task which is decided by the constant operands.
+#[inline]
+fn terminal_result<OP: BooleanOp>(task: Task) -> Option<NodeIndex> {
+    let value = |edge: NodeIndex| {
+        if edge.is_terminal() {
+            Some(edge.is_one())
+        } else {
+            None
+        }
+    };
+    OP::partial_eval(value(task.0), value(task.1)).map(|result| {
+        if result {
+            NodeIndex::ONE
+        } else {
+            NodeIndex::ZERO
+        }
+    })
+}
+
+/// **(internal)** The low and high cofactor of the function of `edge` with respect
+/// to the given `variable`.
+#[inline]
+fn cofactors(bdd: &Bdd, edge: NodeIndex, variable: Variable) -> (NodeIndex, NodeIndex) {
+    let (node_variable, low, high) = bdd.get_node(edge).unpack();
+    if node_variable != variable {
+        (edge, edge)
+    } else {
+        let complement = edge.is_complemented();
+        (
+            low.complement_if(complement),
+            high.complement_if(complement),
+        )
+    }
+}
+
+/// **(internal)** A leaky, direct-mapped cache of task results.
+///
+/// The size of the table follows the number of result nodes. When it grows, the stored
+/// results are dropped, which is fine because a missing entry only leads to recomputation.
+struct TaskCache {
+    table: Vec<(Task, NodeIndex)>,
+}
+
+impl TaskCache {
+    fn new(capacity: usize) -> TaskCache {
+        let undefined = (NodeIndex::UNDEFINED, NodeIndex::UNDEFINED);
+        TaskCache {
+            table: vec![(undefined, NodeIndex::UNDEFINED); capacity.next_power_of_two().max(16)],
+        }
+    }
+
+    fn grow_if_necessary(&mut self, nodes: usize) {
+        if nodes > self.table.len() {
+            *self = TaskCache::new(2 * self.table.len());
+        }
+    }
+
+    fn get(&self, task: Task) -> Option<NodeIndex> {
+        let (key, result) = self.table[self.slot(task)];
+        if key == task {
+            Some(result)
+        } else {
+            None
+        }
+    }
+
+    fn insert(&mut self, task: Task, result: NodeIndex) {
+        let slot = self.slot(task);
+        self.table[slot] = (task, result);
+    }
+
+    fn slot(&self, task: Task) -> usize {
+        let key = u64::from(task.0) ^ u64::from(task.1).rotate_left(32);
+        let hash = (key.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32) as usize;
+        hash & (self.table.len() - 1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::expressions::BooleanExpression;
+    use crate::v2::ops::{And, AndNot, BooleanOp, Iff, Imp, Or, Xor};
+    use crate::v2::Bdd as Bdd2;
+    use crate::v4::core::Bdd as Bdd4;
+    use crate::v5::core::Bdd;
+    use core::convert::TryFrom;
+
+    fn build(expression: &str, names: &[&str]) -> Bdd4 {
+        let expression = BooleanExpression::try_from(expression).unwrap();
+        let bdd = Bdd2::from_expression(&expression, names).unwrap();
+        Bdd4::try_from(&bdd).unwrap()
+    }
+
+    fn check<OP: BooleanOp>(left: &Bdd4, right: &Bdd4) {
+        let expected = crate::v4::apply::<OP>(left, right);
+        let result = super::apply::<OP>(&Bdd::from(left), &Bdd::from(right));
+        let converted = Bdd4::from(&result);
+        assert_eq!(expected.as_node_slice(), converted.as_node_slice());
+        // The result is canonical, so converting it again gives the same nodes.
+        assert_eq!(result.as_node_slice(), Bdd::from(&expected).as_node_slice());
+    }
+
+    #[test]
+    pub fn complement_apply_test() {
+        let names = ["a", "b", "c", "d", "e", "f"];
+        let left = build("(a ^ d) | (b & !e) | (c <=> f)", &names);
+        let right = build("(a & b & c) ^ (d | e | f)", &names);
+        for (left, right) in [(&left, &right), (&right, &left), (&left, &left.not())] {
+            check::<And>(left, right);
+            check::<Or>(left, right);
+            check::<Xor>(left, right);
+            check::<Imp>(left, right);
+            check::<Iff>(left, right);
+            check::<AndNot>(left, right);
+        }
+
+        // A xor chain needs two nodes per variable, unless the edges are complemented.
+        let xor = build("a ^ b ^ c ^ d ^ e ^ f", &names);
+        assert_eq!(2 + 2 * 6 - 1, xor.get_size());
+        assert_eq!(1 + 6, Bdd::from(&xor).get_size());
+
+        let result = super::apply::<Xor>(&Bdd::from(&left), &!Bdd::from(&left));
+        assert!(result.is_one());
+        let result = super::apply::<And>(&Bdd::from(&left), &!Bdd::from(&left));
+        assert!(result.is_zero());
+    }
+}
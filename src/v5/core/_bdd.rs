@@ -0,0 +1,236 @@
+use super::{Node, NodeCache, NodeIndex, Variable};
+use crate::v4::core::{Bdd as Bdd4, Node as Node4, NodeIndex as NodeIndex4};
+use crate::{FromIndex, IntoIndex};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Not;
+
+/// A complement-edge `Bdd`: a directed acyclic graph of `Node` objects, together with
+/// the (possibly complemented) `root` edge.
+///
+/// The terminal node `1` is the first node, and every other node is stored after its
+/// successors. Smaller variables are closer to the root (same as `v4::core::Bdd`), and
+/// every node is canonical with respect to the rules of `NodeCache`.
+#[derive(Clone)]
+pub struct Bdd {
+    root: NodeIndex,
+    nodes: Vec<Node>,
+}
+
+impl Bdd {
+    /// An unsafe constructor for creating BDDs directly from node vectors.
+    ///
+    /// # Safety
+    ///
+    /// The `nodes` must be canonical and sorted as described in the documentation of `Bdd`,
+    /// and `root` must be an edge to one of the `nodes`.
+    pub unsafe fn from_raw_parts(root: NodeIndex, nodes: Vec<Node>) -> Bdd {
+        Bdd { root, nodes }
+    }
+
+    /// Create a BDD representing the constant `0`.
+    pub fn new_zero() -> Bdd {
+        Bdd {
+            root: NodeIndex::ZERO,
+            nodes: vec![Node::ONE],
+        }
+    }
+
+    /// Create a BDD representing the constant `1`.
+    pub fn new_one() -> Bdd {
+        Bdd {
+            root: NodeIndex::ONE,
+            nodes: vec![Node::ONE],
+        }
+    }
+
+    /// Create a BDD representing the literal `variable = value`.
+    pub fn new_literal(variable: Variable, value: bool) -> Bdd {
+        // The high edge cannot be complemented, so `!x` is the complemented edge of `x`.
+        let literal = Node::pack(variable, NodeIndex::ZERO, NodeIndex::ONE);
+        Bdd {
+            root: NodeIndex::from(1).complement_if(!value),
+            nodes: vec![Node::ONE, literal],
+        }
+    }
+
+    /// The number of nodes in this `Bdd`, including the terminal node.
+    pub fn get_size(&self) -> u64 {
+        u64::from_index(self.nodes.len())
+    }
+
+    /// The (possibly complemented) edge which represents the function of this `Bdd`.
+    pub fn get_root_index(&self) -> NodeIndex {
+        self.root
+    }
+
+    /// Get the node which is referenced by the given `index`. The complement tag is ignored.
+    pub fn get_node(&self, index: NodeIndex) -> &Node {
+        &self.nodes[index.into_index()]
+    }
+
+    pub fn as_node_slice(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn is_constant(&self) -> bool {
+        self.root.is_terminal()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.root.is_zero()
+    }
+
+    pub fn is_one(&self) -> bool {
+        self.root.is_one()
+    }
+}
+
+/// A logical negation of a `Bdd`.
+///
+/// This only complements the root edge, so it takes constant time (but it consumes
+/// the `Bdd`, so use `clone` to keep the original function).
+impl Not for Bdd {
+    type Output = Bdd;
+
+    fn not(mut self) -> Bdd {
+        self.root = self.root.complement();
+        self
+    }
+}
+
+/// Convert a `v4::core::Bdd` into a complement-edge `Bdd`. Only the nodes reachable from
+/// the root are converted.
+impl From<&Bdd4> for Bdd {
+    fn from(bdd: &Bdd4) -> Self {
+        if bdd.is_constant() {
+            return if bdd.is_one() {
+                Bdd::new_one()
+            } else {
+                Bdd::new_zero()
+            };
+        }
+        let mut cache = NodeCache::new(bdd.as_node_slice().len());
+        // Nodes which are not translated yet have an undefined image.
+        let mut images = vec![NodeIndex::UNDEFINED; bdd.as_node_slice().len()];
+        images[0] = NodeIndex::ZERO;
+        images[1] = NodeIndex::ONE;
+        let root = bdd.get_root_index();
+        let mut stack = vec![root];
+        while let Some(top) = stack.last().copied() {
+            let (variable, low, high) = bdd.get_node(top).unpack();
+            let low_image = images[low.into_index()];
+            let high_image = images[high.into_index()];
+            if low_image.is_undefined() || high_image.is_undefined() {
+                if high_image.is_undefined() {
+                    stack.push(high);
+                }
+                if low_image.is_undefined() {
+                    stack.push(low);
+                }
+                continue;
+            }
+            images[top.into_index()] = cache.make_node(variable, low_image, high_image);
+            stack.pop();
+        }
+        let root = images[root.into_index()];
+        Bdd {
+            root,
+            nodes: cache.into_nodes(),
+        }
+    }
+}
+
+/// Convert a complement-edge `Bdd` into a `v4::core::Bdd`, sorted in DFS preorder.
+///
+/// Every node which is reachable both through a regular and through a complemented edge
+/// is converted into two nodes (the function and its negation).
+impl From<&Bdd> for Bdd4 {
+    fn from(bdd: &Bdd) -> Self {
+        if bdd.is_constant() {
+            return if bdd.is_one() {
+                Bdd4::new_one()
+            } else {
+                Bdd4::new_zero()
+            };
+        }
+        // The images of the regular and of the complemented edge of every node.
+        let mut images = vec![[NodeIndex4::UNDEFINED; 2]; bdd.nodes.len()];
+        images[0] = [NodeIndex4::ONE, NodeIndex4::ZERO];
+        let image = |images: &[[NodeIndex4; 2]], edge: NodeIndex| {
+            images[edge.into_index()][edge.is_complemented() as usize]
+        };
+        let mut nodes = vec![Node4::ZERO, Node4::ONE];
+        let mut stack = vec![bdd.root];
+        while let Some(top) = stack.last().copied() {
+            if !image(&images, top).is_undefined() {
+                stack.pop();
+                continue;
+            }
+            let (variable, low, high) = bdd.get_node(top).unpack();
+            let complement = top.is_complemented();
+            let (low, high) = (
+                low.complement_if(complement),
+                high.complement_if(complement),
+            );
+            let (low_image, high_image) = (image(&images, low), image(&images, high));
+            if low_image.is_undefined() || high_image.is_undefined() {
+                if high_image.is_undefined() {
+                    stack.push(high);
+                }
+                if low_image.is_undefined() {
+                    stack.push(low);
+                }
+                continue;
+            }
+            // Children are always created first, so the root ends up as the last node.
+            images[top.into_index()][complement as usize] = NodeIndex4::from_index(nodes.len());
+            nodes.push(Node4::pack(variable, low_image, high_image));
+            stack.pop();
+        }
+        Bdd4::from_nodes(nodes).sort_preorder()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v4::core::Bdd as Bdd4;
+    use crate::v5::core::{Bdd, NodeIndex, Variable};
+
+    #[test]
+    pub fn literal_test() {
+        let x = Bdd::new_literal(Variable::from(3), true);
+        let not_x = Bdd::new_literal(Variable::from(3), false);
+        assert_eq!(2, x.get_size());
+        assert_eq!(x.as_node_slice(), not_x.as_node_slice());
+        assert_eq!(x.get_root_index(), not_x.get_root_index().complement());
+        assert_eq!(x.get_root_index(), (!!x.clone()).get_root_index());
+
+        let x4 = Bdd4::new_literal(Variable::from(3), true);
+        assert_eq!(x4.as_node_slice(), Bdd4::from(&x).as_node_slice());
+        assert_eq!(x4.not().as_node_slice(), Bdd4::from(&not_x).as_node_slice());
+        assert!(Bdd4::from(&Bdd::new_zero()).is_zero());
+        assert!(Bdd::from(&Bdd4::new_one()).is_one());
+        assert!((!Bdd::new_one()).get_root_index() == NodeIndex::ZERO);
+    }
+
+    #[test]
+    pub fn conversion_round_trip_test() {
+        let clause = [
+            (Variable::from(1), true),
+            (Variable::from(4), false),
+            (Variable::from(6), true),
+        ];
+        let bdd4 = Bdd4::new_disjunctive_clause(&clause);
+        let bdd = Bdd::from(&bdd4);
+        // `x | !y | z` and its negation share all nodes.
+        assert_eq!(4, bdd.get_size());
+        assert_eq!(
+            bdd4.sort_preorder().as_node_slice(),
+            Bdd4::from(&bdd).as_node_slice()
+        );
+        let negated = Bdd::from(&bdd4.not());
+        assert_eq!(bdd.as_node_slice(), negated.as_node_slice());
+        assert_eq!(bdd.get_root_index(), negated.get_root_index().complement());
+    }
+}
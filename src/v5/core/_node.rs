@@ -0,0 +1,48 @@
+use super::{NodeIndex, Variable};
+
+/// A decision node of a complement-edge `Bdd`: a variable with a low and a high edge.
+///
+/// The high edge of a decision node is never complemented (the node cache pushes
+/// the complement to the parent edge instead), so a decision node is packed exactly like
+/// a `v4::core::Node`, but the low edge can carry the complement tag. The only terminal node
+/// is `Node::ONE`, which has an undefined variable and self-loops.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[repr(C)]
+pub struct Node(Variable, NodeIndex, NodeIndex);
+
+assert_eq_size!(Node, [u64; 3]);
+
+impl Node {
+    pub const ONE: Node = Node(Variable::UNDEFINED, NodeIndex::ONE, NodeIndex::ONE);
+
+    #[inline]
+    pub fn pack(variable: Variable, low: NodeIndex, high: NodeIndex) -> Node {
+        debug_assert!(!high.is_complemented());
+        Node(variable, low, high)
+    }
+
+    #[inline]
+    pub fn unpack(&self) -> (Variable, NodeIndex, NodeIndex) {
+        (self.0, self.1, self.2)
+    }
+
+    #[inline]
+    pub fn is_terminal(&self) -> bool {
+        self.0.is_undefined()
+    }
+
+    #[inline]
+    pub fn get_variable(&self) -> Variable {
+        self.0
+    }
+
+    #[inline]
+    pub fn get_low_link(&self) -> NodeIndex {
+        self.1
+    }
+
+    #[inline]
+    pub fn get_high_link(&self) -> NodeIndex {
+        self.2
+    }
+}
@@ -0,0 +1,107 @@
+use super::{Node, NodeIndex, Variable};
+use crate::{FromIndex, IntoIndex};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// **(internal)** An empty slot of the hash table.
+const EMPTY: u64 = u64::MAX;
+
+/// A unique table which creates the canonical nodes of a complement-edge `Bdd`.
+///
+/// The canonicalization rule is that the high edge of a node is never complemented: a node
+/// `(variable, low, !high)` is stored as `(variable, !low, high)` and the edge pointing to it
+/// is complemented instead. Together with the usual reduction rule (a node with `low == high`
+/// is replaced by `low`), every function has exactly one edge.
+///
+/// The nodes are stored in creation order (the terminal node is always at position zero), so
+/// every node is stored after its successors. The table uses open addressing with linear
+/// probing and grows (with rehashing) once it is half full.
+pub struct NodeCache {
+    nodes: Vec<Node>,
+    /// Positions of the stored nodes in `nodes`, indexed by the hash of the node.
+    table: Vec<u64>,
+}
+
+impl NodeCache {
+    /// Create a new node cache which can store roughly `capacity` nodes without growing.
+    /// The cache always contains the terminal node.
+    pub fn new(capacity: usize) -> NodeCache {
+        let table_size = (2 * capacity).next_power_of_two().max(16);
+        let mut nodes = Vec::with_capacity(capacity);
+        nodes.push(Node::ONE);
+        NodeCache {
+            nodes,
+            table: vec![EMPTY; table_size],
+        }
+    }
+
+    /// The number of stored nodes, including the terminal node.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Return the edge of the function `variable ? high : low`, creating a new node
+    /// if necessary.
+    pub fn make_node(&mut self, variable: Variable, low: NodeIndex, high: NodeIndex) -> NodeIndex {
+        if low == high {
+            return low;
+        }
+        let complement = high.is_complemented();
+        let node = Node::pack(
+            variable,
+            low.complement_if(complement),
+            high.complement_if(complement),
+        );
+        self.ensure(node).complement_if(complement)
+    }
+
+    /// Consume the cache, returning all stored nodes such that every node is stored
+    /// at its position.
+    pub fn into_nodes(self) -> Vec<Node> {
+        self.nodes
+    }
+
+    /// **(internal)** Find the regular edge of the given canonical `node`, or create it.
+    fn ensure(&mut self, node: Node) -> NodeIndex {
+        let mask = self.table.len() - 1;
+        let mut slot = hash(&node) & mask;
+        loop {
+            let position = self.table[slot];
+            if position == EMPTY {
+                break;
+            }
+            if self.nodes[position.into_index()] == node {
+                return NodeIndex::from(position);
+            }
+            slot = (slot + 1) & mask;
+        }
+        let index = NodeIndex::from_index(self.nodes.len());
+        self.table[slot] = u64::from(index);
+        self.nodes.push(node);
+        if 2 * self.nodes.len() > self.table.len() {
+            self.grow();
+        }
+        index
+    }
+
+    /// **(internal)** Double the size of the hash table and re-insert all nodes.
+    #[cold]
+    fn grow(&mut self) {
+        self.table = vec![EMPTY; 2 * self.table.len()];
+        let mask = self.table.len() - 1;
+        for (position, node) in self.nodes.iter().enumerate().skip(1) {
+            let mut slot = hash(node) & mask;
+            while self.table[slot] != EMPTY {
+                slot = (slot + 1) & mask;
+            }
+            self.table[slot] = u64::from_index(position);
+        }
+    }
+}
+
+/// **(internal)** A multiplicative hash of all three components of a `node`.
+fn hash(node: &Node) -> usize {
+    let (variable, low, high) = node.unpack();
+    let key = u64::from(low) ^ u64::from(high).rotate_left(21) ^ u64::from(u32::from(variable));
+    (key.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32) as usize
+}
@@ -0,0 +1,89 @@
+use crate::{FromIndex, IntoIndex};
+
+/// **(internal)** The tag bit of a complemented edge.
+const COMPLEMENT_BIT: u64 = 1 << 63;
+
+/// A reference to a node within some complement-edge `Bdd`, i.e. an edge of the graph.
+///
+/// The lower 63 bits are the position of the node in the `Bdd`, the highest bit marks
+/// a complemented edge, which represents the negation of the function of the node.
+/// The terminal node `1` is at position zero, so `ONE` is its regular edge and `ZERO` is
+/// its complemented edge. The position does not include the tag bit, so `IntoIndex` can be
+/// used with complemented edges as well.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[repr(transparent)]
+pub struct NodeIndex(u64);
+
+impl NodeIndex {
+    pub const UNDEFINED: NodeIndex = NodeIndex(u64::MAX);
+    pub const ONE: NodeIndex = NodeIndex(0);
+    pub const ZERO: NodeIndex = NodeIndex(COMPLEMENT_BIT);
+
+    #[inline]
+    pub fn is_undefined(&self) -> bool {
+        *self == Self::UNDEFINED
+    }
+
+    #[inline]
+    pub fn is_zero(&self) -> bool {
+        *self == Self::ZERO
+    }
+
+    #[inline]
+    pub fn is_one(&self) -> bool {
+        *self == Self::ONE
+    }
+
+    /// True if this edge points to the terminal node (i.e. it is `ZERO` or `ONE`).
+    #[inline]
+    pub fn is_terminal(&self) -> bool {
+        self.regular() == Self::ONE
+    }
+
+    #[inline]
+    pub fn is_complemented(&self) -> bool {
+        self.0 & COMPLEMENT_BIT != 0
+    }
+
+    /// The edge to the same node, but with the opposite complement tag.
+    #[inline]
+    pub fn complement(self) -> NodeIndex {
+        NodeIndex(self.0 ^ COMPLEMENT_BIT)
+    }
+
+    /// The edge to the same node without the complement tag.
+    #[inline]
+    pub fn regular(self) -> NodeIndex {
+        NodeIndex(self.0 & !COMPLEMENT_BIT)
+    }
+
+    /// The edge to the same node, complemented if `complement` is set.
+    #[inline]
+    pub fn complement_if(self, complement: bool) -> NodeIndex {
+        NodeIndex(self.0 ^ ((complement as u64) << 63))
+    }
+}
+
+impl From<u64> for NodeIndex {
+    fn from(value: u64) -> Self {
+        NodeIndex(value)
+    }
+}
+
+impl From<NodeIndex> for u64 {
+    fn from(value: NodeIndex) -> Self {
+        value.0
+    }
+}
+
+impl IntoIndex for NodeIndex {
+    fn into_index(self) -> usize {
+        (self.0 & !COMPLEMENT_BIT).into_index()
+    }
+}
+
+impl FromIndex for NodeIndex {
+    fn from_index(index: usize) -> Self {
+        NodeIndex(u64::from_index(index))
+    }
+}
@@ -0,0 +1,10 @@
+mod _bdd;
+mod _node;
+mod _node_cache;
+mod _node_index;
+
+pub use crate::v4::core::Variable;
+pub use _bdd::Bdd;
+pub use _node::Node;
+pub(crate) use _node_cache::NodeCache;
+pub use _node_index::NodeIndex;
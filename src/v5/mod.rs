@@ -0,0 +1,18 @@
+//! Binary decision diagrams with complemented edges.
+//!
+//! Every edge (`core::NodeIndex`) carries a tag bit which negates the function of the node
+//! it points to. A function and its negation therefore share all their nodes, there is only
+//! one terminal node (`1`, with `0` being its complemented edge), and the negation of a `Bdd`
+//! is a constant-time operation (see the `Not` implementation of `core::Bdd`).
+//!
+//! To keep the representation canonical, the high edge of a decision node is never
+//! complemented (see `core::NodeCache`).
+
+/// The core data structures of the complement-edge `Bdd`.
+pub mod core;
+
+/// The apply algorithm for binary logical operations on complement-edge `Bdd` objects.
+pub mod apply;
+
+pub use crate::v2::ops;
+pub use apply::apply;
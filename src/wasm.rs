@@ -0,0 +1,110 @@
+use crate::v2;
+use crate::v4::core::{Bdd, Node, NodeIndex, Variable};
+use std::convert::TryFrom;
+use wasm_bindgen::prelude::*;
+
+/// A JS wrapper around the v4 `Bdd`.
+///
+/// The v4 `apply` algorithm does not produce a result `Bdd` yet, so the logical operations
+/// convert the operands to `v2::Bdd` and back. As a consequence, the operations only support
+/// variables that fit into the `v2` representation (i.e. less than `2^16 - 1`).
+#[wasm_bindgen]
+pub struct JsBdd(Bdd);
+
+#[wasm_bindgen]
+impl JsBdd {
+    /// Create a `Bdd` representing the constant `false`.
+    #[wasm_bindgen(js_name = newFalse)]
+    pub fn new_false() -> JsBdd {
+        JsBdd(Bdd::new_zero())
+    }
+
+    /// Create a `Bdd` representing the constant `true`.
+    #[wasm_bindgen(js_name = newTrue)]
+    pub fn new_true() -> JsBdd {
+        JsBdd(Bdd::new_one())
+    }
+
+    /// Create a `Bdd` representing the given positive literal.
+    #[wasm_bindgen(js_name = newVariable)]
+    pub fn new_variable(variable: u32) -> Result<JsBdd, JsValue> {
+        let nodes = vec![
+            Node::ZERO,
+            Node::ONE,
+            Node::pack(Variable::from(variable), NodeIndex::ZERO, NodeIndex::ONE),
+        ];
+        if let Some(error) = Bdd::check_consistency_errors(&nodes) {
+            Err(JsValue::from_str(&error))
+        } else {
+            Ok(JsBdd(Bdd::from_nodes(nodes)))
+        }
+    }
+
+    /// Read a `Bdd` from the string format used by `Bdd::try_from`.
+    #[wasm_bindgen(js_name = fromString)]
+    pub fn from_string(data: &str) -> Result<JsBdd, JsValue> {
+        Bdd::try_from(data).map(JsBdd).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Write the `Bdd` into the string format used by `Bdd::try_from`.
+    #[wasm_bindgen(js_name = toString)]
+    pub fn serialize(&self) -> String {
+        self.0.to_string()
+    }
+
+    /// The number of nodes in the `Bdd` graph.
+    #[wasm_bindgen(js_name = nodeCount)]
+    pub fn node_count(&self) -> usize {
+        self.0.as_node_slice().len()
+    }
+
+    #[wasm_bindgen(js_name = isFalse)]
+    pub fn is_false(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    #[wasm_bindgen(js_name = isTrue)]
+    pub fn is_true(&self) -> bool {
+        self.0.is_one()
+    }
+
+    pub fn and(&self, other: &JsBdd) -> Result<JsBdd, JsValue> {
+        self.apply(other, v2::Bdd::and)
+    }
+
+    pub fn or(&self, other: &JsBdd) -> Result<JsBdd, JsValue> {
+        self.apply(other, v2::Bdd::or)
+    }
+
+    pub fn imp(&self, other: &JsBdd) -> Result<JsBdd, JsValue> {
+        self.apply(other, v2::Bdd::imp)
+    }
+
+    pub fn iff(&self, other: &JsBdd) -> Result<JsBdd, JsValue> {
+        self.apply(other, v2::Bdd::iff)
+    }
+
+    pub fn xor(&self, other: &JsBdd) -> Result<JsBdd, JsValue> {
+        self.apply(other, v2::Bdd::xor)
+    }
+
+    #[wasm_bindgen(js_name = andNot)]
+    pub fn and_not(&self, other: &JsBdd) -> Result<JsBdd, JsValue> {
+        self.apply(other, v2::Bdd::and_not)
+    }
+}
+
+impl JsBdd {
+    /// **(internal)** Run the given `v2` operation on the converted operands.
+    fn apply<F>(&self, other: &JsBdd, operation: F) -> Result<JsBdd, JsValue>
+    where
+        F: Fn(&v2::Bdd, &v2::Bdd) -> v2::Bdd,
+    {
+        let left = v2::Bdd::try_from(&self.0).map_err(|e| JsValue::from_str(&e))?;
+        let right = v2::Bdd::try_from(&other.0).map_err(|e| JsValue::from_str(&e))?;
+        let result = operation(&left, &right);
+        Bdd::try_from(&result)
+            .map(JsBdd)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+}
@@ -0,0 +1,354 @@
+use crate::v4::core::{Node, NodeIndex, Variable};
+use crate::{FromIndex, IntoIndex};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// **(internal)** An empty slot of the unique table.
+const EMPTY: u64 = u64::MAX;
+
+/// **(internal)** The operations which share the `Manager::cache`.
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum Op {
+    Union = 1,
+    Intersection = 2,
+    Difference = 3,
+    Product = 4,
+    FromBdd = 5,
+    ToBdd = 6,
+}
+
+/// **(internal)** A shared node storage in which the ZDD operations are evaluated.
+///
+/// The operands are first imported into the manager, then the recursive operations create
+/// new nodes in the same storage, and the result is exported as a standalone node vector
+/// (see `Manager::export`). The manager stores plain (v4) nodes, so the reduction rule is up
+/// to the caller: `zdd_node` removes nodes whose high edge is `ZERO`, `bdd_node` removes
+/// nodes with equal edges.
+///
+/// The operations are recursive, but every level of the recursion (except for the unions
+/// nested in `product`) moves at least one operand to a larger variable, so the depth is
+/// bounded by a small multiple of the number of variables.
+pub struct Manager {
+    nodes: Vec<Node>,
+    /// Open addressing hash table of node positions (linear probing, at most half full).
+    unique: Vec<u64>,
+    /// A leaky, direct-mapped cache of operation results.
+    cache: Vec<(u8, NodeIndex, NodeIndex, NodeIndex)>,
+}
+
+impl Manager {
+    pub fn new(capacity: usize) -> Manager {
+        let size = (2 * capacity).next_power_of_two().max(16);
+        let undefined = NodeIndex::UNDEFINED;
+        Manager {
+            nodes: vec![Node::ZERO, Node::ONE],
+            unique: vec![EMPTY; size],
+            cache: vec![(0, undefined, undefined, undefined); size],
+        }
+    }
+
+    /// Copy the `nodes` (terminals first, every node after its successors) into the manager
+    /// and return the index of the last node.
+    pub fn import(&mut self, nodes: &[Node]) -> NodeIndex {
+        let mut images = vec![NodeIndex::ZERO, NodeIndex::ONE];
+        for node in nodes.iter().skip(2) {
+            let (variable, low, high) = node.unpack();
+            let node = Node::pack(
+                variable,
+                images[low.into_index()],
+                images[high.into_index()],
+            );
+            images.push(self.ensure(node));
+        }
+        images[nodes.len() - 1]
+    }
+
+    /// Return the nodes reachable from `root` (terminals first, root last), such that every
+    /// node is stored after its successors.
+    pub fn export(&self, root: NodeIndex) -> Vec<Node> {
+        if root.is_zero() {
+            return vec![Node::ZERO];
+        }
+        let mut images = vec![NodeIndex::UNDEFINED; self.nodes.len()];
+        images[0] = NodeIndex::ZERO;
+        images[1] = NodeIndex::ONE;
+        let mut nodes = vec![Node::ZERO, Node::ONE];
+        let mut stack = vec![root];
+        while let Some(top) = stack.last().copied() {
+            if !images[top.into_index()].is_undefined() {
+                stack.pop();
+                continue;
+            }
+            let (variable, low, high) = self.node(top);
+            let (low_image, high_image) = (images[low.into_index()], images[high.into_index()]);
+            if low_image.is_undefined() || high_image.is_undefined() {
+                if high_image.is_undefined() {
+                    stack.push(high);
+                }
+                if low_image.is_undefined() {
+                    stack.push(low);
+                }
+                continue;
+            }
+            images[top.into_index()] = NodeIndex::from_index(nodes.len());
+            nodes.push(Node::pack(variable, low_image, high_image));
+            stack.pop();
+        }
+        nodes
+    }
+
+    /// The variable, low and high edge of a node (terminals have an undefined variable).
+    #[inline]
+    pub fn node(&self, index: NodeIndex) -> (Variable, NodeIndex, NodeIndex) {
+        self.nodes[index.into_index()].unpack()
+    }
+
+    /// A ZDD node: a node whose high edge is `ZERO` is replaced by its low edge.
+    pub fn zdd_node(&mut self, variable: Variable, low: NodeIndex, high: NodeIndex) -> NodeIndex {
+        if high.is_zero() {
+            low
+        } else {
+            self.ensure(Node::pack(variable, low, high))
+        }
+    }
+
+    /// A BDD node: a node with equal edges is replaced by one of them.
+    pub fn bdd_node(&mut self, variable: Variable, low: NodeIndex, high: NodeIndex) -> NodeIndex {
+        if low == high {
+            low
+        } else {
+            self.ensure(Node::pack(variable, low, high))
+        }
+    }
+
+    /// The union of two families.
+    pub fn union(&mut self, left: NodeIndex, right: NodeIndex) -> NodeIndex {
+        if left.is_zero() || left == right {
+            return right;
+        }
+        if right.is_zero() {
+            return left;
+        }
+        let (left, right) = (left.min(right), left.max(right));
+        self.cached(Op::Union, left, right, |manager| {
+            let (l_var, l_low, l_high) = manager.node(left);
+            let (r_var, r_low, r_high) = manager.node(right);
+            if l_var < r_var {
+                let low = manager.union(l_low, right);
+                manager.zdd_node(l_var, low, l_high)
+            } else if r_var < l_var {
+                let low = manager.union(left, r_low);
+                manager.zdd_node(r_var, low, r_high)
+            } else {
+                let low = manager.union(l_low, r_low);
+                let high = manager.union(l_high, r_high);
+                manager.zdd_node(l_var, low, high)
+            }
+        })
+    }
+
+    /// The intersection of two families.
+    pub fn intersection(&mut self, left: NodeIndex, right: NodeIndex) -> NodeIndex {
+        if left.is_zero() || right.is_zero() {
+            return NodeIndex::ZERO;
+        }
+        if left == right {
+            return left;
+        }
+        let (left, right) = (left.min(right), left.max(right));
+        self.cached(Op::Intersection, left, right, |manager| {
+            let (l_var, l_low, l_high) = manager.node(left);
+            let (r_var, r_low, r_high) = manager.node(right);
+            if l_var < r_var {
+                manager.intersection(l_low, right)
+            } else if r_var < l_var {
+                manager.intersection(left, r_low)
+            } else {
+                let low = manager.intersection(l_low, r_low);
+                let high = manager.intersection(l_high, r_high);
+                manager.zdd_node(l_var, low, high)
+            }
+        })
+    }
+
+    /// The sets of the `left` family which are not in the `right` family.
+    pub fn difference(&mut self, left: NodeIndex, right: NodeIndex) -> NodeIndex {
+        if left.is_zero() || left == right {
+            return NodeIndex::ZERO;
+        }
+        if right.is_zero() {
+            return left;
+        }
+        self.cached(Op::Difference, left, right, |manager| {
+            let (l_var, l_low, l_high) = manager.node(left);
+            let (r_var, r_low, r_high) = manager.node(right);
+            if l_var < r_var {
+                let low = manager.difference(l_low, right);
+                manager.zdd_node(l_var, low, l_high)
+            } else if r_var < l_var {
+                manager.difference(left, r_low)
+            } else {
+                let low = manager.difference(l_low, r_low);
+                let high = manager.difference(l_high, r_high);
+                manager.zdd_node(l_var, low, high)
+            }
+        })
+    }
+
+    /// The product (join) of two families: all unions of one `left` set and one `right` set.
+    pub fn product(&mut self, left: NodeIndex, right: NodeIndex) -> NodeIndex {
+        if left.is_zero() || right.is_zero() {
+            return NodeIndex::ZERO;
+        }
+        if left.is_one() {
+            return right;
+        }
+        if right.is_one() {
+            return left;
+        }
+        let (left, right) = (left.min(right), left.max(right));
+        self.cached(Op::Product, left, right, |manager| {
+            let (l_var, l_low, l_high) = manager.node(left);
+            let (r_var, r_low, r_high) = manager.node(right);
+            if l_var < r_var {
+                let low = manager.product(l_low, right);
+                let high = manager.product(l_high, right);
+                manager.zdd_node(l_var, low, high)
+            } else if r_var < l_var {
+                let low = manager.product(left, r_low);
+                let high = manager.product(left, r_high);
+                manager.zdd_node(r_var, low, high)
+            } else {
+                // Sets which contain the variable come from at least one high branch.
+                let low = manager.product(l_low, r_low);
+                let low_high = manager.product(l_low, r_high);
+                let high_low = manager.product(l_high, r_low);
+                let high_high = manager.product(l_high, r_high);
+                let high = manager.union(low_high, high_low);
+                let high = manager.union(high, high_high);
+                manager.zdd_node(l_var, low, high)
+            }
+        })
+    }
+
+    /// Translate a BDD node (imported using `import`) into a ZDD over the variables
+    /// `level..variable_count`.
+    ///
+    /// The BDD must only use variables smaller than `variable_count`.
+    pub fn bdd_to_zdd(&mut self, bdd: NodeIndex, level: u32, variable_count: u32) -> NodeIndex {
+        if bdd.is_zero() || level == variable_count {
+            return bdd;
+        }
+        self.cached(
+            Op::FromBdd,
+            bdd,
+            NodeIndex::from(u64::from(level)),
+            |manager| {
+                let (variable, low, high) = manager.node(bdd);
+                let (low, high) = if variable == Variable::from(level) {
+                    (low, high)
+                } else {
+                    // The function does not depend on this variable.
+                    (bdd, bdd)
+                };
+                let low = manager.bdd_to_zdd(low, level + 1, variable_count);
+                let high = manager.bdd_to_zdd(high, level + 1, variable_count);
+                manager.zdd_node(Variable::from(level), low, high)
+            },
+        )
+    }
+
+    /// Translate a ZDD node into a BDD over the variables `level..variable_count`.
+    ///
+    /// The ZDD must only use variables smaller than `variable_count`.
+    pub fn zdd_to_bdd(&mut self, zdd: NodeIndex, level: u32, variable_count: u32) -> NodeIndex {
+        if zdd.is_zero() || level == variable_count {
+            return zdd;
+        }
+        self.cached(
+            Op::ToBdd,
+            zdd,
+            NodeIndex::from(u64::from(level)),
+            |manager| {
+                let (variable, low, high) = manager.node(zdd);
+                let (low, high) = if variable == Variable::from(level) {
+                    (low, high)
+                } else {
+                    // None of the sets contains this variable.
+                    (zdd, NodeIndex::ZERO)
+                };
+                let low = manager.zdd_to_bdd(low, level + 1, variable_count);
+                let high = manager.zdd_to_bdd(high, level + 1, variable_count);
+                manager.bdd_node(Variable::from(level), low, high)
+            },
+        )
+    }
+
+    /// **(internal)** Return the cached result of `op` for the given operands, or compute
+    /// it using `compute` and store it in the cache.
+    fn cached<F>(&mut self, op: Op, left: NodeIndex, right: NodeIndex, compute: F) -> NodeIndex
+    where
+        F: FnOnce(&mut Manager) -> NodeIndex,
+    {
+        let key = u64::from(left) ^ u64::from(right).rotate_left(32) ^ (op as u64);
+        let slot = (key.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32) as usize;
+        let slot = slot & (self.cache.len() - 1);
+        let (entry_op, entry_left, entry_right, result) = self.cache[slot];
+        if entry_op == op as u8 && entry_left == left && entry_right == right {
+            return result;
+        }
+        let result = compute(self);
+        // If the cache grew in the meantime, this slot is not the hash position of the key
+        // anymore, but the entry stores the whole key, so it is never used incorrectly.
+        self.cache[slot] = (op as u8, left, right, result);
+        result
+    }
+
+    /// **(internal)** Find the index of the given `node`, or create it.
+    fn ensure(&mut self, node: Node) -> NodeIndex {
+        let mask = self.unique.len() - 1;
+        let mut slot = hash(&node) & mask;
+        loop {
+            let position = self.unique[slot];
+            if position == EMPTY {
+                break;
+            }
+            if self.nodes[position.into_index()] == node {
+                return NodeIndex::from(position);
+            }
+            slot = (slot + 1) & mask;
+        }
+        let index = NodeIndex::from_index(self.nodes.len());
+        self.unique[slot] = u64::from(index);
+        self.nodes.push(node);
+        if 2 * self.nodes.len() > self.unique.len() {
+            self.grow();
+        }
+        index
+    }
+
+    /// **(internal)** Double the size of both tables. The unique table is rehashed,
+    /// the operation cache is simply cleared.
+    #[cold]
+    fn grow(&mut self) {
+        let size = 2 * self.unique.len();
+        self.unique = vec![EMPTY; size];
+        let undefined = NodeIndex::UNDEFINED;
+        self.cache = vec![(0, undefined, undefined, undefined); size];
+        let mask = size - 1;
+        for (position, node) in self.nodes.iter().enumerate().skip(2) {
+            let mut slot = hash(node) & mask;
+            while self.unique[slot] != EMPTY {
+                slot = (slot + 1) & mask;
+            }
+            self.unique[slot] = u64::from_index(position);
+        }
+    }
+}
+
+/// **(internal)** A multiplicative hash of all three components of a `node`.
+fn hash(node: &Node) -> usize {
+    let (variable, low, high) = node.unpack();
+    let key = u64::from(low) ^ u64::from(high).rotate_left(21) ^ u64::from(u32::from(variable));
+    (key.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32) as usize
+}
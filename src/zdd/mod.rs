@@ -0,0 +1,270 @@
+//! Zero-suppressed decision diagrams (ZDDs) for families of sets.
+//!
+//! A `Zdd` represents a family of sets of variables. It uses the same packed nodes as
+//! `v4::core::Bdd`, but with a different reduction rule: a node whose high edge leads to
+//! the `0` terminal (the empty family) is removed, so variables which do not appear in any
+//! set of the family do not need any nodes. The `1` terminal is the family `{∅}`. As a result,
+//! families of sparse sets (e.g. covers) are usually much smaller as ZDDs than as BDDs of their
+//! characteristic functions.
+
+mod _manager;
+
+use crate::v4::core::{Bdd, Node, NodeIndex, Variable};
+use crate::IntoIndex;
+use _manager::Manager;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A family of sets of variables, represented as a zero-suppressed decision diagram.
+///
+/// Same as in `v4::core::Bdd`, the terminal nodes are stored first and the root is the last
+/// node. Additionally, every node is stored after its successors, and every node is reachable
+/// from the root.
+#[derive(Clone)]
+pub struct Zdd {
+    nodes: Vec<Node>,
+}
+
+impl Zdd {
+    /// Create the empty family `∅`.
+    pub fn new_empty() -> Zdd {
+        Zdd {
+            nodes: vec![Node::ZERO],
+        }
+    }
+
+    /// Create the family `{∅}`, which only contains the empty set.
+    pub fn new_base() -> Zdd {
+        Zdd {
+            nodes: vec![Node::ZERO, Node::ONE],
+        }
+    }
+
+    /// Create a family which only contains the given set of variables (duplicates
+    /// are allowed).
+    pub fn new_set(variables: &[Variable]) -> Zdd {
+        let mut variables = variables.to_vec();
+        variables.sort_unstable();
+        variables.dedup();
+        let mut nodes = vec![Node::ZERO, Node::ONE];
+        // The largest variable is the deepest node, so the set is built bottom-up.
+        for variable in variables.into_iter().rev() {
+            let child = NodeIndex::from((nodes.len() - 1) as u64);
+            nodes.push(Node::pack(variable, NodeIndex::ZERO, child));
+        }
+        Zdd { nodes }
+    }
+
+    /// The number of nodes in this `Zdd`, including the terminal nodes.
+    pub fn get_size(&self) -> u64 {
+        self.nodes.len() as u64
+    }
+
+    pub fn get_root_index(&self) -> NodeIndex {
+        NodeIndex::from((self.nodes.len() - 1) as u64)
+    }
+
+    pub fn as_node_slice(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// True if this is the empty family `∅`.
+    pub fn is_empty_family(&self) -> bool {
+        self.get_root_index().is_zero()
+    }
+
+    /// True if the family contains the given set of variables (duplicates are allowed).
+    pub fn contains(&self, set: &[Variable]) -> bool {
+        let mut set = set.to_vec();
+        set.sort_unstable();
+        set.dedup();
+        let mut node = self.get_root_index();
+        for variable in set {
+            // Skip the variables which are not in the set.
+            loop {
+                let (node_variable, low, high) = self.nodes[node.into_index()].unpack();
+                if node_variable < variable {
+                    node = low;
+                } else if node_variable == variable {
+                    node = high;
+                    break;
+                } else {
+                    // No set of this sub-family contains the variable.
+                    return false;
+                }
+            }
+        }
+        // All remaining variables must be absent.
+        while !self.nodes[node.into_index()].is_terminal() {
+            node = self.nodes[node.into_index()].get_low_link();
+        }
+        node.is_one()
+    }
+
+    /// The union of the two families.
+    pub fn union(&self, other: &Zdd) -> Zdd {
+        self.binary_operation(other, Manager::union)
+    }
+
+    /// The sets which are in both families.
+    pub fn intersection(&self, other: &Zdd) -> Zdd {
+        self.binary_operation(other, Manager::intersection)
+    }
+
+    /// The sets of this family which are not in the `other` family.
+    pub fn difference(&self, other: &Zdd) -> Zdd {
+        self.binary_operation(other, Manager::difference)
+    }
+
+    /// The product (join) of the two families: the family of all sets `a ∪ b`, where `a` is
+    /// a set of this family and `b` is a set of the `other` family.
+    pub fn product(&self, other: &Zdd) -> Zdd {
+        self.binary_operation(other, Manager::product)
+    }
+
+    /// Convert a `Bdd` into the family of the sets of variables which are `true` in
+    /// the satisfying valuations of the `Bdd`, where the valuations range over the variables
+    /// `0..variable_count`.
+    ///
+    /// Fails if the `Bdd` uses a variable which is not smaller than `variable_count`.
+    pub fn from_bdd(bdd: &Bdd, variable_count: u32) -> Result<Zdd, String> {
+        check_variables(bdd.as_node_slice(), variable_count)?;
+        let mut manager = Manager::new(bdd.as_node_slice().len());
+        // `import` requires the successors first, which is true for a (pruned) postorder.
+        let root = manager.import(bdd.prune_unreachable().sort_postorder().as_node_slice());
+        let result = manager.bdd_to_zdd(root, 0, variable_count);
+        Ok(Zdd {
+            nodes: manager.export(result),
+        })
+    }
+
+    /// Convert this family into a `Bdd` of its characteristic function over the variables
+    /// `0..variable_count` (a valuation is satisfying if its `true` variables form a set
+    /// of the family). The result is sorted in DFS preorder.
+    ///
+    /// Fails if the family uses a variable which is not smaller than `variable_count`.
+    pub fn to_bdd(&self, variable_count: u32) -> Result<Bdd, String> {
+        check_variables(&self.nodes, variable_count)?;
+        let mut manager = Manager::new(self.nodes.len());
+        let root = manager.import(&self.nodes);
+        let result = manager.zdd_to_bdd(root, 0, variable_count);
+        Ok(Bdd::from_nodes(manager.export(result)).sort_preorder())
+    }
+
+    /// **(internal)** Evaluate a binary `operation` of the `Manager` on two families.
+    fn binary_operation<F>(&self, other: &Zdd, operation: F) -> Zdd
+    where
+        F: FnOnce(&mut Manager, NodeIndex, NodeIndex) -> NodeIndex,
+    {
+        let mut manager = Manager::new(self.nodes.len() + other.nodes.len());
+        let left = manager.import(&self.nodes);
+        let right = manager.import(&other.nodes);
+        let result = operation(&mut manager, left, right);
+        Zdd {
+            nodes: manager.export(result),
+        }
+    }
+}
+
+/// **(internal)** Check that all decision variables of the `nodes` are smaller than
+/// `variable_count`.
+fn check_variables(nodes: &[Node], variable_count: u32) -> Result<(), String> {
+    for node in nodes.iter().filter(|node| !node.is_terminal()) {
+        let variable = u32::from(node.get_variable());
+        if variable >= variable_count {
+            return Err(format!(
+                "Variable {} is not valid with {} variables.",
+                variable, variable_count
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::v4::core::{Bdd, Variable};
+    use crate::zdd::Zdd;
+    use alloc::vec::Vec;
+
+    fn set(variables: &[u32]) -> Vec<Variable> {
+        variables.iter().map(|it| Variable::from(*it)).collect()
+    }
+
+    fn family(sets: &[&[u32]]) -> Zdd {
+        sets.iter().fold(Zdd::new_empty(), |family, s| {
+            family.union(&Zdd::new_set(&set(s)))
+        })
+    }
+
+    /// All subsets of `0..4` which are in the family.
+    fn members(family: &Zdd) -> Vec<u32> {
+        (0..16u32)
+            .filter(|mask| {
+                let variables: Vec<u32> = (0..4).filter(|i| mask & (1 << i) != 0).collect();
+                family.contains(&set(&variables))
+            })
+            .collect()
+    }
+
+    #[test]
+    pub fn set_operations_test() {
+        assert!(Zdd::new_empty().is_empty_family());
+        assert!(Zdd::new_base().contains(&[]));
+        let a = family(&[&[0, 1], &[2], &[]]);
+        let b = family(&[&[2], &[1, 3]]);
+        assert_eq!(vec![0b0000, 0b0011, 0b0100], members(&a));
+        assert!(!a.contains(&set(&[0])) && !a.contains(&set(&[0, 1, 2])));
+
+        assert_eq!(vec![0b0000, 0b0011, 0b0100, 0b1010], members(&a.union(&b)));
+        assert_eq!(vec![0b0100], members(&a.intersection(&b)));
+        assert_eq!(vec![0b0000, 0b0011], members(&a.difference(&b)));
+        assert!(a.difference(&a).is_empty_family());
+        // {01, 2, ∅} x {2, 13} = {012, 0123, 2, 123, 13}
+        let product = a.product(&b);
+        assert_eq!(
+            vec![0b0100, 0b0111, 0b1010, 0b1011, 0b1110],
+            members(&product)
+        );
+        assert_eq!(members(&a), members(&a.product(&Zdd::new_base())));
+        assert!(a.product(&Zdd::new_empty()).is_empty_family());
+
+        // The canonical form does not depend on the order of operations.
+        let other = family(&[&[], &[2], &[1, 0]]);
+        assert_eq!(a.as_node_slice(), other.as_node_slice());
+    }
+
+    #[test]
+    pub fn bdd_conversion_test() {
+        // All singletons of ten variables: the ZDD is a chain, but the BDD needs
+        // to remember if a variable was already seen.
+        let singletons = (0..10).fold(Zdd::new_empty(), |family, i| {
+            family.union(&Zdd::new_set(&[Variable::from(i)]))
+        });
+        assert_eq!(12, singletons.get_size());
+        let bdd = singletons.to_bdd(10).unwrap();
+        assert_eq!(2 + 2 * 10 - 1, bdd.get_size());
+        let back = Zdd::from_bdd(&bdd, 10).unwrap();
+        assert_eq!(singletons.as_node_slice(), back.as_node_slice());
+
+        // The variables outside of the Bdd are free, and the sets may or may not contain them.
+        let literal = Bdd::new_literal(Variable::from(1), true);
+        let zdd = Zdd::from_bdd(&literal, 3).unwrap();
+        assert_eq!(vec![0b010, 0b011, 0b110, 0b111], members(&zdd));
+        let back = zdd.to_bdd(3).unwrap();
+        assert_eq!(
+            literal.sort_preorder().as_node_slice(),
+            back.as_node_slice()
+        );
+
+        assert!(Zdd::from_bdd(&literal, 1).is_err());
+        assert!(singletons.to_bdd(5).is_err());
+        assert!(Zdd::from_bdd(&Bdd::new_zero(), 3)
+            .unwrap()
+            .is_empty_family());
+        let everything = Zdd::from_bdd(&Bdd::new_one(), 2).unwrap();
+        assert_eq!(vec![0b00, 0b01, 0b10, 0b11], members(&everything));
+    }
+}